@@ -8,8 +8,11 @@
 //! rather than panic. Combined with crate-level lint denies for panic/unwrap,
 //! this keeps production code resilient to malformed or hostile input.
 
+use serde::ser::{Serialize, SerializeStruct, Serializer};
 use thiserror::Error;
 
+use crate::protocol::text::sys_frame;
+
 /// Stable client-facing error codes.
 ///
 /// These codes are used in outward-facing responses and logging to decouple
@@ -22,12 +25,16 @@ pub enum ClientCode {
     AuthFailed,
     /// Rate limited.
     RateLimited,
+    /// Per-user message quota used up for the current window.
+    QuotaExceeded,
     /// Payload too large.
     PayloadTooLarge,
     /// Not allowed by policy.
     NotAllowed,
     /// Resource exhausted
     ResourceExhausted,
+    /// Room at its capacity; the join was refused.
+    RoomFull,
     /// Unsupported protocol version.
     UnsupportedVersion,
     /// Internal server error.
@@ -41,20 +48,60 @@ impl ClientCode {
             ClientCode::BadRequest => "BAD_REQUEST",
             ClientCode::AuthFailed => "AUTH_FAILED",
             ClientCode::RateLimited => "RATE_LIMITED",
+            ClientCode::QuotaExceeded => "QUOTA_EXCEEDED",
             ClientCode::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
             ClientCode::NotAllowed => "NOT_ALLOWED",
             ClientCode::ResourceExhausted => "RESOURCE_EXHAUSTED",
+            ClientCode::RoomFull => "ROOM_FULL",
             ClientCode::UnsupportedVersion => "UNSUPPORTED_VERSION",
             ClientCode::Internal => "INTERNAL",
         }
     }
+
+    /// WebSocket close code used when a session ends with this code.
+    ///
+    /// Application codes live in 4000-4999; the rest use RFC 6455 codes.
+    pub fn close_code(self) -> u16 {
+        match self {
+            ClientCode::BadRequest => 4000,
+            ClientCode::AuthFailed => 4001,
+            ClientCode::NotAllowed => 4003,
+            ClientCode::RateLimited => 4008,
+            ClientCode::RoomFull => 4009,
+            ClientCode::QuotaExceeded => 4029,
+            ClientCode::PayloadTooLarge => 1009,
+            ClientCode::ResourceExhausted => 1013,
+            ClientCode::UnsupportedVersion => 1002,
+            ClientCode::Internal => 1011,
+        }
+    }
+
+    /// HTTP status for rejections made before the WebSocket upgrade.
+    pub fn http_status(self) -> u16 {
+        match self {
+            ClientCode::BadRequest | ClientCode::UnsupportedVersion => 400,
+            ClientCode::AuthFailed => 401,
+            ClientCode::NotAllowed => 403,
+            ClientCode::RoomFull => 409,
+            ClientCode::PayloadTooLarge => 413,
+            ClientCode::RateLimited | ClientCode::QuotaExceeded => 429,
+            ClientCode::Internal => 500,
+            ClientCode::ResourceExhausted => 503,
+        }
+    }
 }
 
 /// Convenient result alias for core operations.
 pub type Result<T> = std::result::Result<T, WsPrismError>;
 
 /// Unified error type used by core and gateway layers.
+///
+/// Non-exhaustive: new variants may be added in minor releases. Foreign
+/// errors convert with `?`: `serde_json::Error` into `BadRequest`,
+/// `std::io::Error` into `Internal`, and any boxed error into `Source`, which
+/// keeps it as the `source()`.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum WsPrismError {
     #[error("bad request: {0}")]
     BadRequest(String),
@@ -62,16 +109,38 @@ pub enum WsPrismError {
     AuthFailed,
     #[error("rate limited")]
     RateLimited,
+    /// Hard usage limit (e.g. messages per day) reached; retrying before the
+    /// window resets will not help, unlike `RateLimited`.
+    #[error("quota exceeded: {0}")]
+    QuotaExceeded(String),
     #[error("payload too large")]
     PayloadTooLarge,
     #[error("not allowed: {0}")]
     NotAllowed(String),
     #[error("resource exhausted: {0}")]
     ResourceExhausted(String),
+    /// The room has reached its capacity.
+    #[error("room full: {0}")]
+    RoomFull(String),
     #[error("unsupported protocol version")]
     UnsupportedVersion,
     #[error("internal: {0}")]
     Internal(String),
+    /// Internal failure wrapping the underlying error.
+    #[error("internal: {0}")]
+    Source(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+impl From<serde_json::Error> for WsPrismError {
+    fn from(e: serde_json::Error) -> Self {
+        WsPrismError::BadRequest(format!("invalid json: {e}"))
+    }
+}
+
+impl From<std::io::Error> for WsPrismError {
+    fn from(e: std::io::Error) -> Self {
+        WsPrismError::Internal(format!("io: {e}"))
+    }
 }
 
 impl WsPrismError {
@@ -81,11 +150,38 @@ impl WsPrismError {
             WsPrismError::BadRequest(_) => ClientCode::BadRequest,
             WsPrismError::AuthFailed => ClientCode::AuthFailed,
             WsPrismError::RateLimited => ClientCode::RateLimited,
+            WsPrismError::QuotaExceeded(_) => ClientCode::QuotaExceeded,
             WsPrismError::PayloadTooLarge => ClientCode::PayloadTooLarge,
             WsPrismError::NotAllowed(_) => ClientCode::NotAllowed,
             WsPrismError::ResourceExhausted(_) => ClientCode::ResourceExhausted,
+            WsPrismError::RoomFull(_) => ClientCode::RoomFull,
             WsPrismError::UnsupportedVersion => ClientCode::UnsupportedVersion,
-            WsPrismError::Internal(_) => ClientCode::Internal,
+            WsPrismError::Internal(_) | WsPrismError::Source(_) => ClientCode::Internal,
         }
     }
+
+    /// WebSocket close code for a session ended by this error.
+    pub fn close_code(&self) -> u16 {
+        self.client_code().close_code()
+    }
+
+    /// Full `sys.error` envelope for this error (no trace id).
+    pub fn to_sys_frame(&self) -> String {
+        sys_frame("error", self, None)
+    }
+
+    /// Full `sys.error` envelope carrying the session trace id.
+    pub fn to_sys_frame_traced(&self, trace_id: &str) -> String {
+        sys_frame("error", self, Some(trace_id))
+    }
+}
+
+/// Wire shape: `{"code": "BAD_REQUEST", "message": "..."}`.
+impl Serialize for WsPrismError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut st = serializer.serialize_struct("WsPrismError", 2)?;
+        st.serialize_field("code", self.client_code().as_str())?;
+        st.serialize_field("message", &self.to_string())?;
+        st.end()
+    }
 }
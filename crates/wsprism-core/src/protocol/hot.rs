@@ -30,11 +30,18 @@ pub struct HotFrame {
     pub payload: Bytes,
 }
 
-/// Decode a Hot Lane frame from bytes.
+/// Decode a Hot Lane frame from bytes, with no payload size limit.
 ///
 /// Defensive against malformed input; returns structured errors instead of
 /// panicking on short buffers or unsupported versions.
-pub fn decode_hot_frame(mut buf: Bytes) -> Result<HotFrame> {
+pub fn decode_hot_frame(buf: Bytes) -> Result<HotFrame> {
+    decode_hot_frame_with_limit(buf, usize::MAX)
+}
+
+/// Decode a Hot Lane frame, rejecting payloads (bytes after the header)
+/// longer than `max_payload_bytes` with `PayloadTooLarge` before they are
+/// split off.
+pub fn decode_hot_frame_with_limit(mut buf: Bytes, max_payload_bytes: usize) -> Result<HotFrame> {
     // Minimum header: v, svc_id, opcode, flags
     if buf.remaining() < 4 {
         return Err(WsPrismError::BadRequest("hot frame too short".into()));
@@ -61,6 +68,9 @@ pub fn decode_hot_frame(mut buf: Bytes) -> Result<HotFrame> {
     };
 
     // Remaining bytes are payload.
+    if buf.remaining() > max_payload_bytes {
+        return Err(WsPrismError::PayloadTooLarge);
+    }
     let payload = buf.copy_to_bytes(buf.remaining());
 
     Ok(HotFrame {
@@ -4,33 +4,98 @@
 //! services and plugins. Unknown fields are rejected to keep the contract
 //! strict and predictable.
 
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 
+use crate::error::{Result, WsPrismError};
+
+/// The only Ext lane protocol version.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// `flags` bit: the client expects an acknowledgement and resends the
+/// message without one, so handling it again is safe until a reply went out.
+pub const FLAG_WANT_ACK: u32 = 0x01;
+
+fn default_version() -> u8 {
+    ENVELOPE_VERSION
+}
+
 /// Ext Lane envelope (Text frame).
 ///
 /// This is the canonical JSON structure parsed on the server. Services may
 /// choose to further deserialize `data` depending on `svc`/`type`.
-#[derive(Debug, Deserialize)]
+///
+/// Only `svc` and `type` are mandatory; `{"svc":"chat","type":"send"}` is a
+/// valid envelope (`v` = 1, `flags` = 0, no `seq`/`room`/`data`).
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Envelope {
-    /// Protocol version.
+    /// Protocol version (default 1).
+    #[serde(default = "default_version")]
     pub v: u8,
     /// Service name (e.g., "chat").
     pub svc: String,
     /// Message type (field name is `type` in JSON).
     #[serde(rename = "type")]
     pub msg_type: String,
-    /// Feature flags bitmask.
+    /// Feature flags bitmask (default 0).
     #[serde(default)]
     pub flags: u32,
     /// Optional sequence number.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub seq: Option<u64>,
     /// Optional room id.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub room: Option<String>,
     /// Optional payload, stored as raw JSON (lazy parsing).
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub data: Option<Box<RawValue>>,
 }
+
+impl Envelope {
+    /// Reject versions other than `ENVELOPE_VERSION` (after defaults apply).
+    pub fn validate(&self) -> Result<()> {
+        if self.v != ENVELOPE_VERSION {
+            return Err(WsPrismError::UnsupportedVersion);
+        }
+        Ok(())
+    }
+
+    /// Deserialize `data` into `T`.
+    ///
+    /// Fails with `BadRequest` when `data` is absent or does not match `T`.
+    #[inline]
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<T> {
+        self.data_as_opt()?.ok_or_else(|| WsPrismError::BadRequest("missing data".into()))
+    }
+
+    /// Like [`data_as`](Self::data_as), but an absent `data` is `Ok(None)`.
+    #[inline]
+    pub fn data_as_opt<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        self.data
+            .as_deref()
+            .map(|raw| serde_json::from_str(raw.get()))
+            .transpose()
+            .map_err(|e| WsPrismError::BadRequest(format!("invalid data: {e}")))
+    }
+}
+
+/// Build a server-originated `sys.<msg_type>` envelope.
+///
+/// `trace_id` is appended as a top-level field when present. Serialization of
+/// `data` cannot fail for the types used here; on failure `data` is `null`.
+pub fn sys_frame<T: Serialize + ?Sized>(msg_type: &str, data: &T, trace_id: Option<&str>) -> String {
+    let data = serde_json::to_value(data).unwrap_or(serde_json::Value::Null);
+    let mut obj = serde_json::json!({
+        "v": 1,
+        "svc": "sys",
+        "type": msg_type,
+        "flags": 0,
+        "data": data,
+    });
+    if let (Some(t), Some(map)) = (trace_id, obj.as_object_mut()) {
+        map.insert("trace_id".into(), serde_json::Value::String(t.to_string()));
+    }
+    obj.to_string()
+}
@@ -0,0 +1,91 @@
+//! Client-facing error wire format.
+
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use wsprism_core::protocol::text::Envelope;
+use wsprism_core::WsPrismError;
+
+#[test]
+fn error_serializes_code_and_message() {
+    let e = WsPrismError::NotAllowed("svc/type".into());
+    let v = serde_json::to_value(&e).unwrap();
+    assert_eq!(v["code"], "NOT_ALLOWED");
+    assert_eq!(v["message"], "not allowed: svc/type");
+}
+
+#[test]
+fn sys_frame_roundtrips_through_envelope() {
+    let e = WsPrismError::BadRequest("oops".into());
+    let env: Envelope = serde_json::from_str(&e.to_sys_frame()).unwrap();
+    assert_eq!(env.svc, "sys");
+    assert_eq!(env.msg_type, "error");
+
+    let data: serde_json::Value = serde_json::from_str(env.data.unwrap().get()).unwrap();
+    assert_eq!(data["code"], "BAD_REQUEST");
+}
+
+#[test]
+fn errors_map_to_ws_close_codes() {
+    assert_eq!(WsPrismError::BadRequest("x".into()).close_code(), 4000);
+    assert_eq!(WsPrismError::AuthFailed.close_code(), 4001);
+    assert_eq!(WsPrismError::RateLimited.close_code(), 4008);
+    assert_eq!(wsprism_core::error::ClientCode::QuotaExceeded.close_code(), 4029);
+    assert_eq!(WsPrismError::Internal("x".into()).close_code(), 1011);
+}
+
+#[test]
+fn quota_exceeded_maps_to_wire_code_and_http_status() {
+    use wsprism_core::error::ClientCode;
+
+    let e = WsPrismError::QuotaExceeded("limits.max_messages_per_day".into());
+    assert_eq!(serde_json::to_value(&e).unwrap()["code"], "QUOTA_EXCEEDED");
+    assert_eq!(e.close_code(), 4029);
+    assert_eq!(ClientCode::QuotaExceeded.http_status(), 429);
+    assert_eq!(ClientCode::AuthFailed.http_status(), 401);
+    assert_eq!(ClientCode::ResourceExhausted.http_status(), 503);
+}
+
+#[test]
+fn room_full_has_its_own_code() {
+    use wsprism_core::error::ClientCode;
+
+    let e = WsPrismError::RoomFull("lobby".into());
+    assert_eq!(serde_json::to_value(&e).unwrap(), serde_json::json!({ "code": "ROOM_FULL", "message": "room full: lobby" }));
+    assert_eq!(e.close_code(), 4009);
+    assert_eq!(ClientCode::RoomFull.http_status(), 409);
+}
+
+#[test]
+fn json_errors_convert_to_bad_request() {
+    fn parse(s: &str) -> wsprism_core::Result<Envelope> {
+        Ok(serde_json::from_str(s)?)
+    }
+    let e = parse("{not json").unwrap_err();
+    assert!(matches!(&e, WsPrismError::BadRequest(m) if m.starts_with("invalid json: ")), "{e}");
+    assert_eq!(e.client_code().as_str(), "BAD_REQUEST");
+}
+
+#[test]
+fn io_errors_convert_to_internal() {
+    fn read() -> wsprism_core::Result<String> {
+        Ok(std::fs::read_to_string("/nonexistent/wsprism.yaml")?)
+    }
+    let e = read().unwrap_err();
+    assert!(matches!(&e, WsPrismError::Internal(m) if m.starts_with("io: ")), "{e}");
+    assert_eq!(e.close_code(), 1011);
+}
+
+#[test]
+fn boxed_errors_keep_their_source() {
+    use std::error::Error as _;
+
+    let inner: Box<dyn std::error::Error + Send + Sync> = "broker unreachable".into();
+    let e = WsPrismError::from(inner);
+    assert_eq!(e.to_string(), "internal: broker unreachable");
+    assert_eq!(e.source().unwrap().to_string(), "broker unreachable");
+    assert_eq!(e.client_code().as_str(), "INTERNAL");
+    // The wire message is the display text, like any other internal error.
+    assert_eq!(serde_json::to_value(&e).unwrap()["code"], "INTERNAL");
+}
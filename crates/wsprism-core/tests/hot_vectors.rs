@@ -8,7 +8,7 @@ use std::fs;
 
 use bytes::Bytes;
 
-use wsprism_core::protocol::hot::decode_hot_frame;
+use wsprism_core::protocol::hot::{decode_hot_frame, decode_hot_frame_with_limit};
 
 mod vector_loader;
 use vector_loader::TestVector;
@@ -26,12 +26,16 @@ fn hot_vectors() {
         "hot_seq_flag_missing_u32.json",
         "hot_too_short.json",
         "hot_payload_ok.json",
+        "hot_payload_too_large.json",
     ];
 
     for f in files {
         let v = load(f);
         let raw = v.frame.decode();
-        let res = decode_hot_frame(Bytes::from(raw));
+        let res = match v.max_payload_bytes {
+            Some(max) => decode_hot_frame_with_limit(Bytes::from(raw), max),
+            None => decode_hot_frame(Bytes::from(raw)),
+        };
 
         if let Some(err) = v.expect_error {
             let e = res.expect_err("expected error");
@@ -91,3 +95,13 @@ fn hot_vectors() {
         );
     }
 }
+
+#[test]
+fn payload_limit_is_inclusive() {
+    // Header with seq, then 4 payload bytes; the seq does not count.
+    let raw = Bytes::from_static(&[1, 1, 2, 1, 9, 0, 0, 0, b'a', b'b', b'c', b'd']);
+    let frame = decode_hot_frame_with_limit(raw.clone(), 4).unwrap();
+    assert_eq!((frame.seq, frame.payload.len()), (Some(9), 4));
+    let err = decode_hot_frame_with_limit(raw, 3).unwrap_err();
+    assert_eq!(err.client_code().as_str(), "PAYLOAD_TOO_LARGE");
+}
@@ -33,3 +33,48 @@ fn parse_envelope_full() {
     let raw = env.data.unwrap();
     assert!(raw.get().contains("\"text\""));
 }
+
+#[test]
+fn minimal_envelope_gets_defaults_and_round_trips() {
+    let s = r#"{"svc":"chat","type":"send","room":"r","data":{"msg":"hi"}}"#;
+    let env: Envelope = serde_json::from_str(s).unwrap();
+    assert_eq!((env.v, env.flags, env.seq), (1, 0, None));
+    env.validate().unwrap();
+
+    let out = serde_json::to_string(&env).unwrap();
+    assert_eq!(out, r#"{"v":1,"svc":"chat","type":"send","flags":0,"room":"r","data":{"msg":"hi"}}"#);
+    let back: Envelope = serde_json::from_str(&out).unwrap();
+    assert_eq!(back.svc, "chat");
+    assert_eq!(back.room.as_deref(), Some("r"));
+    assert_eq!(back.data.unwrap().get(), r#"{"msg":"hi"}"#);
+}
+
+#[test]
+fn unsupported_envelope_version_is_rejected() {
+    let env: Envelope = serde_json::from_str(r#"{"v":2,"svc":"chat","type":"send"}"#).unwrap();
+    assert_eq!(env.validate().unwrap_err().client_code().as_str(), "UNSUPPORTED_VERSION");
+
+    let missing_type = serde_json::from_str::<Envelope>(r#"{"svc":"chat"}"#);
+    assert!(missing_type.is_err());
+}
+
+#[test]
+fn data_as_parses_or_reports_bad_request() {
+    #[derive(Debug, serde::Deserialize)]
+    struct Send {
+        msg: String,
+    }
+    let env = |s: &str| serde_json::from_str::<Envelope>(s).unwrap();
+
+    let full = env(r#"{"svc":"chat","type":"send","data":{"msg":"hi"}}"#);
+    assert_eq!(full.data_as::<Send>().unwrap().msg, "hi");
+    assert_eq!(full.data_as_opt::<Send>().unwrap().unwrap().msg, "hi");
+
+    let empty = env(r#"{"svc":"chat","type":"send"}"#);
+    assert_eq!(empty.data_as::<Send>().unwrap_err().to_string(), "bad request: missing data");
+    assert!(empty.data_as_opt::<Send>().unwrap().is_none());
+
+    let wrong = env(r#"{"svc":"chat","type":"send","data":{"msg":7}}"#);
+    let err = wrong.data_as_opt::<Send>().unwrap_err().to_string();
+    assert!(err.starts_with("bad request: invalid data: "), "{err}");
+}
@@ -10,6 +10,9 @@ use serde::Deserialize;
 #[derive(Debug, Deserialize)]
 pub struct TestVector {
     pub description: String,
+    /// Hot Lane only: decode with `decode_hot_frame_with_limit`.
+    #[serde(default)]
+    pub max_payload_bytes: Option<usize>,
     pub frame: FrameData,
     #[serde(default)]
     pub expect: Option<serde_json::Value>,
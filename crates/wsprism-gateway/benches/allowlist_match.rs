@@ -0,0 +1,38 @@
+//! Ext allowlist lookup (`find_ext_rule`), run once per inbound message.
+//!
+//! The list holds 32 exact entries followed by the wildcard (and, with
+//! `regex-allowlist`, regex) entries, so the exact case pays for a full scan
+//! of the exact group only.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wsprism_gateway::policy::allowlist::{compile_ext_rules, find_ext_rule};
+
+fn rules() -> Vec<String> {
+    let mut raw: Vec<String> = (0..32).map(|i| format!("svc{i}:send")).collect();
+    raw.push("chat:*".into());
+    raw.push("*:ping".into());
+    #[cfg(feature = "regex-allowlist")]
+    raw.push("~game-[0-9]+:state".into());
+    raw
+}
+
+fn lookup(c: &mut Criterion) {
+    let rules = compile_ext_rules(&rules()).unwrap_or_default();
+    let mut g = c.benchmark_group("ext_allowlist");
+    let mut case = |name: &str, svc: &str, ty: &str| {
+        g.bench_function(name, |b| {
+            b.iter(|| find_ext_rule(black_box(&rules), black_box(svc), black_box(ty)).is_some())
+        });
+    };
+    case("exact_last", "svc31", "send");
+    case("svc_wildcard", "chat", "typing");
+    case("type_wildcard", "lobby", "ping");
+    #[cfg(feature = "regex-allowlist")]
+    case("regex", "game-42", "state");
+    case("miss", "nope", "send");
+    g.finish();
+}
+
+criterion_group!(benches, lookup);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+//! Reliable room publish to 10 000 recipients, bounded vs unbounded fan-out.
+//!
+//! `limit_128` runs at most 128 sends at a time (`max_fanout_parallelism`);
+//! `unbounded` builds one future per recipient up front (the previous
+//! behavior). Before timing, the peak heap growth of one publish is printed
+//! for each, measured with a counting allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::extract::ws::Message;
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCore};
+
+const RECIPIENTS: usize = 10_000;
+const ROOM: &str = "acme::lobby";
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(now, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: Counting = Counting;
+
+fn room() -> (Arc<RealtimeCore>, Vec<mpsc::Receiver<Message>>) {
+    let core = Arc::new(RealtimeCore::new());
+    let limits = TenantLimits::default();
+    let mut receivers = Vec::with_capacity(RECIPIENTS);
+    for i in 0..RECIPIENTS {
+        let (tx, rx) = mpsc::channel(4);
+        let id = ConnectionId::new();
+        let user = format!("acme::u{i}");
+        let conn = Connection::new(tx, SessionClaims::default());
+        if core.sessions.try_insert("acme".into(), user.clone(), id, conn, 0).is_ok() {
+            let _ = core.presence.try_join("acme", ROOM, &user, id, &limits);
+        }
+        receivers.push(rx);
+    }
+    (core, receivers)
+}
+
+fn out() -> Outgoing {
+    Outgoing {
+        qos: QoS::Reliable { timeout_ms: 100 },
+        payload: Payload::TextJson(json!({ "v": 1, "svc": "chat", "type": "msg", "data": "hi" })),
+        priority: Priority::Normal,
+    }
+}
+
+fn drain(receivers: &mut [mpsc::Receiver<Message>]) {
+    for rx in receivers {
+        while rx.try_recv().is_ok() {}
+    }
+}
+
+fn fanout(c: &mut Criterion) {
+    let Ok(rt) = tokio::runtime::Builder::new_current_thread().enable_time().build() else { return };
+    let (core, mut receivers) = room();
+    let cases = [("limit_128", 128), ("unbounded", usize::MAX)];
+
+    for (name, limit) in cases {
+        let base = CURRENT.load(Ordering::Relaxed);
+        PEAK.store(base, Ordering::Relaxed);
+        let _ = rt.block_on(core.publish_room_reliable_bounded(ROOM, out(), limit));
+        let peak_kib = PEAK.load(Ordering::Relaxed).saturating_sub(base) / 1024;
+        println!("reliable_fanout_10000/{name}: peak heap growth {peak_kib} KiB");
+        drain(&mut receivers);
+    }
+
+    let mut g = c.benchmark_group("reliable_fanout_10000");
+    for (name, limit) in cases {
+        g.bench_function(name, |b| {
+            b.iter(|| {
+                let _ = rt.block_on(core.publish_room_reliable_bounded(ROOM, out(), limit));
+                drain(&mut receivers);
+            })
+        });
+    }
+    g.finish();
+}
+
+criterion_group!(benches, fanout);
+criterion_main!(benches);
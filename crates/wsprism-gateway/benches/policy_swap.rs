@@ -0,0 +1,106 @@
+//! Tenant policy lookups from 1000 reader threads while one writer keeps
+//! replacing a policy.
+//!
+//! `arc_swap` is the current layout in `AppState`: an `ArcSwap` holding a
+//! map of per-tenant `ArcSwap` slots, of which the writer swaps one.
+//! `rwlock` models the previous design: one `RwLock<Arc<HashMap>>` swapped as
+//! a whole.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use arc_swap::ArcSwap;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wsprism_gateway::config::TenantPolicy;
+use wsprism_gateway::policy::TenantPolicyRuntime;
+
+const READERS: usize = 1000;
+const READS_PER_THREAD: usize = 100;
+const TENANTS: usize = 16;
+
+fn runtime(i: usize) -> Arc<TenantPolicyRuntime> {
+    Arc::new(TenantPolicyRuntime::new(format!("t{i}"), 4096, &TenantPolicy::default()).unwrap())
+}
+
+fn ids() -> Vec<String> {
+    (0..TENANTS).map(|i| format!("t{i}")).collect()
+}
+
+/// `READERS` threads doing `read` on every tenant in turn while `write`
+/// runs in a loop until they are done.
+fn contended<R, W>(ids: &[String], read: R, write: W)
+where
+    R: Fn(&str) -> bool + Send + Sync,
+    W: Fn() + Send + Sync,
+{
+    let done = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                write();
+            }
+        });
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                s.spawn(|| {
+                    for i in 0..READS_PER_THREAD {
+                        black_box(read(&ids[i % ids.len()]));
+                    }
+                })
+            })
+            .collect();
+        for r in readers {
+            let _ = r.join();
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
+
+fn readers(c: &mut Criterion) {
+    let ids = ids();
+    let mut g = c.benchmark_group("tenant_policy_1000_readers");
+    g.sample_size(10);
+    g.bench_function("arc_swap", |b| {
+        let map: HashMap<String, ArcSwap<TenantPolicyRuntime>> =
+            ids.iter().enumerate().map(|(i, id)| (id.clone(), ArcSwap::new(runtime(i)))).collect();
+        let slots = ArcSwap::from_pointee(map);
+        let next = runtime(0);
+        b.iter(|| {
+            contended(
+                &ids,
+                |id| slots.load().get(id).is_some_and(|s| !s.load().is_suspended()),
+                || {
+                    if let Some(s) = slots.load().get("t0") {
+                        s.store(Arc::clone(&next));
+                    }
+                },
+            )
+        })
+    });
+    g.bench_function("rwlock", |b| {
+        let map: HashMap<String, Arc<TenantPolicyRuntime>> =
+            ids.iter().enumerate().map(|(i, id)| (id.clone(), runtime(i))).collect();
+        let set = RwLock::new(Arc::new(map));
+        let next = runtime(0);
+        b.iter(|| {
+            contended(
+                &ids,
+                |id| {
+                    let set = Arc::clone(&set.read().unwrap());
+                    set.get(id).is_some_and(|p| !p.is_suspended())
+                },
+                || {
+                    let mut map = HashMap::clone(&set.read().unwrap());
+                    map.insert("t0".into(), Arc::clone(&next));
+                    *set.write().unwrap() = Arc::new(map);
+                },
+            )
+        })
+    });
+    g.finish();
+}
+
+criterion_group!(benches, readers);
+criterion_main!(benches);
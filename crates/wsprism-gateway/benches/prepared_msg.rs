@@ -0,0 +1,92 @@
+//! Heap allocations of a real room broadcast to 1000 sessions, counted with
+//! a counting global allocator.
+//!
+//! `publish_room_lossy` is the whole fanout: one serialization, the
+//! presence lookup and one queued `Message` per recipient. axum 0.7
+//! `Message::Text` owns a `String`, so `to_ws_message` still copies the body
+//! once per recipient; the `Arc<str>` body only saves the copies made when a
+//! prepared message itself is cloned (replay buffer, cluster relay).
+//! `to_ws_message` alone is reported for reference.
+//!
+//! Run with `cargo bench --bench prepared_msg`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::extract::ws::Message;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, PreparedMsg, Priority, QoS, RealtimeCore};
+
+const RECIPIENTS: usize = 1000;
+
+/// `System`, counting allocations and allocated bytes.
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        // SAFETY: forwarded unchanged to the system allocator.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` came from `alloc` above, i.e. from `System`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// `(allocations, bytes)` made while running `f`.
+fn count(f: impl FnOnce()) -> (usize, usize) {
+    let (a, b) = (ALLOCS.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed));
+    f();
+    (ALLOCS.load(Ordering::Relaxed) - a, BYTES.load(Ordering::Relaxed) - b)
+}
+
+fn main() {
+    let out = Outgoing {
+        qos: QoS::Lossy,
+        payload: Payload::TextJson(json!({
+            "v": 1, "svc": "chat", "type": "msg", "room": "lobby",
+            "data": { "from": "alice", "msg": "x".repeat(512) }
+        })),
+        priority: Priority::Normal,
+    };
+    let core = RealtimeCore::new();
+    let limits = TenantLimits::default();
+    let mut queues = Vec::with_capacity(RECIPIENTS);
+    for i in 0..RECIPIENTS {
+        let (tx, rx) = mpsc::channel(4);
+        let (id, user_key) = (ConnectionId::new(), format!("acme::u{i}"));
+        let conn = Connection::new(tx, SessionClaims::default());
+        core.sessions.try_insert("acme".into(), user_key.clone(), id, conn, 0).expect("session");
+        core.presence.try_join("acme", "acme::lobby", &user_key, id, &limits).expect("join");
+        queues.push(rx);
+    }
+    let drain = |queues: &mut Vec<mpsc::Receiver<Message>>| queues.iter_mut().for_each(|rx| while rx.try_recv().is_ok() {});
+
+    // Warm up lazily created per-room and per-channel state.
+    core.publish_room_lossy("acme::lobby", out.clone()).expect("publish");
+    drain(&mut queues);
+
+    let (allocs, bytes) = count(|| core.publish_room_lossy("acme::lobby", out.clone()).expect("publish"));
+    let delivered = queues.iter_mut().map(|rx| rx.try_recv().is_ok() as usize).sum::<usize>();
+    let prepared = PreparedMsg::prepare(&out).expect("prepare");
+    println!("broadcast of a {}-byte body to {delivered} sessions:", prepared.len());
+    println!("  {:<20} {allocs:>5} allocations {bytes:>8} bytes", "publish_room_lossy");
+    println!("  {:<20} {:>5.2} allocations {:>8} bytes", "  per recipient", allocs as f64 / delivered as f64, bytes / delivered);
+    let (allocs, bytes) = count(|| (0..RECIPIENTS).for_each(|_| drop(black_box(prepared.to_ws_message()))));
+    println!("  {:<20} {allocs:>5} allocations {bytes:>8} bytes", "to_ws_message x1000");
+}
@@ -0,0 +1,43 @@
+//! Presence lookups for a user in 50 rooms.
+//!
+//! `rooms_of` copies the user's room set; `user_room_count` and `is_user_in`
+//! read it in place.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::realtime::core::Presence;
+
+const ROOMS: usize = 50;
+const USER: &str = "acme::alice";
+
+fn presence() -> Presence {
+    let presence = Presence::new();
+    let limits = TenantLimits::default();
+    let conn = ConnectionId::new();
+    for i in 0..ROOMS {
+        let _ = presence.try_join("acme", &format!("acme::room-{i}"), USER, conn, &limits);
+    }
+    presence
+}
+
+fn lookups(c: &mut Criterion) {
+    let presence = presence();
+    let room = "acme::room-25";
+
+    let mut g = c.benchmark_group("presence_count_50_rooms");
+    g.bench_function("rooms_of_len", |b| b.iter(|| presence.rooms_of(black_box(USER)).len()));
+    g.bench_function("user_room_count", |b| b.iter(|| presence.user_room_count(black_box(USER))));
+    g.finish();
+
+    let mut g = c.benchmark_group("presence_contains_50_rooms");
+    g.bench_function("rooms_of_filter", |b| {
+        b.iter(|| presence.rooms_of(black_box(USER)).iter().any(|r| r == black_box(room)))
+    });
+    g.bench_function("is_user_in", |b| b.iter(|| presence.is_user_in(black_box(USER), black_box(room))));
+    g.finish();
+}
+
+criterion_group!(benches, lookups);
+criterion_main!(benches);
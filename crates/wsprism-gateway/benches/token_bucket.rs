@@ -0,0 +1,44 @@
+//! Token bucket admission under contention (8 threads sharing one bucket).
+//!
+//! `atomic` is the current lock-free `policy::rate::TokenBucket`. `mutex`
+//! models the previous design: the same bucket logic behind a `Mutex`.
+
+use std::sync::{Arc, Mutex};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wsprism_gateway::policy::rate::TokenBucket;
+
+const THREADS: usize = 8;
+const OPS_PER_THREAD: usize = 10_000;
+
+fn contended<F>(f: F)
+where
+    F: Fn() -> bool + Send + Sync,
+{
+    std::thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                for _ in 0..OPS_PER_THREAD {
+                    black_box(f());
+                }
+            });
+        }
+    });
+}
+
+fn contention(c: &mut Criterion) {
+    let mut g = c.benchmark_group("token_bucket_8_threads");
+    g.bench_function("atomic", |b| {
+        let bucket = Arc::new(TokenBucket::new(1_000_000, 1_000_000));
+        b.iter(|| contended(|| bucket.allow()))
+    });
+    g.bench_function("mutex", |b| {
+        let bucket = Arc::new(Mutex::new(TokenBucket::new(1_000_000, 1_000_000)));
+        b.iter(|| contended(|| bucket.lock().map(|g| g.allow()).unwrap_or(false)))
+    });
+    g.finish();
+}
+
+criterion_group!(benches, contention);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+//! Embeds build metadata for `wsprism_build_info`.
+//!
+//! - `WSPRISM_GIT_SHA`: short commit hash, or `unknown` outside a git checkout.
+//! - `WSPRISM_RUSTC_VERSION`: version of the compiler building the crate.
+
+use std::path::Path;
+use std::process::Command;
+
+fn output(cmd: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(cmd).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let s = String::from_utf8(out.stdout).ok()?.trim().to_string();
+    (!s.is_empty()).then_some(s)
+}
+
+fn main() {
+    let sha = output("git", &["rev-parse", "--short=12", "HEAD"]).unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=WSPRISM_GIT_SHA={sha}");
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let version = output(&rustc, &["--version"])
+        .and_then(|v| v.split_whitespace().nth(1).map(str::to_string))
+        .unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=WSPRISM_RUSTC_VERSION={version}");
+
+    // Re-run when HEAD moves; only watch files that exist, since a missing
+    // path would rebuild every time.
+    if let Some(git_dir) = output("git", &["rev-parse", "--git-dir"]) {
+        let head = Path::new(&git_dir).join("HEAD");
+        if head.exists() {
+            println!("cargo:rerun-if-changed={}", head.display());
+        }
+        if let Some(reference) = output("git", &["symbolic-ref", "-q", "HEAD"]) {
+            let r = Path::new(&git_dir).join(reference);
+            if r.exists() {
+                println!("cargo:rerun-if-changed={}", r.display());
+            }
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -4,21 +4,92 @@
 //! - Wire RealtimeCore + Dispatcher, and register built-in services.
 //! - Make startup errors explicit (Result instead of panic).
 
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
+use arc_swap::ArcSwap;
 use wsprism_core::error::{Result, WsPrismError};
 
-use crate::{config::GatewayConfig, policy};
-use crate::dispatch::Dispatcher;
-use crate::realtime::RealtimeCore;
-use crate::obs::metrics::GatewayMetrics;
-use crate::services::{ChatService, EchoBinaryService};
+use crate::{config::{GatewayConfig, TenantConfig}, policy};
+use crate::audit::{self, AuditSink};
+use crate::cluster::ClusterRelay;
+use crate::context::SessionClaims;
+use crate::egress::{Egress, EgressSink};
+use crate::dispatch::{Dispatcher, TextService};
+use crate::realtime::{MetricsDeadLetterHandler, PresenceNotifier, RealtimeCore};
+use crate::obs::metrics::{escape_label, GatewayMetrics};
+use crate::ops::auth::OpsAuth;
+use crate::plugin::PluginHost;
+use crate::policy::quota::{unix_now_ms, QuotaStore, QuotaTracker};
+use crate::policy::rate::{TokenBucket, ROOM_BUCKET_IDLE_TTL};
+use crate::services::{ChatService, EchoBinaryService, RoomService};
 // Sprint 5
 use crate::transport::handshake::HandshakeDefender;
+use crate::transport::poll::PollSessions;
+use crate::upstream::{HttpUpstreamService, UpstreamTextService};
+use crate::webhooks::{WebhookSender, Webhooks};
 
-/// If true, the gateway fails fast on allowlist/dispatcher mismatches at boot.
-const FAIL_FAST_ON_MISMATCH: bool = false;
+/// Ext services handled by the transport itself (never registered).
+pub(crate) const BUILTIN_TEXT_SVCS: [&str; 2] = ["room", "sys"];
+
+/// Minimum spacing between admin server-wide broadcasts.
+const ADMIN_BROADCAST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often idle per-room limiter buckets are swept.
+const ROOM_BUCKET_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often expired quota counters are swept.
+const QUOTA_SWEEP_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How often stale metric series are pruned.
+const METRICS_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often outbound queue depths and drops are sampled into metrics.
+const OUTBOUND_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `sys.goaway` reason sent to sessions of a tenant suspended on reload.
+pub const GOAWAY_TENANT_SUSPENDED: &str = "tenant_suspended";
+
+/// Config file re-read by `POST /admin/v1/reload` unless overridden.
+pub const DEFAULT_CONFIG_PATH: &str = "wsprism.yaml";
+
+/// Startup behavior switches (typically from CLI flags).
+#[derive(Debug, Clone, Default)]
+pub struct StartupOptions {
+    /// Log allowlist/dispatcher mismatches instead of failing startup.
+    pub lenient: bool,
+    /// Config file used for policy reloads (`DEFAULT_CONFIG_PATH` if unset).
+    pub config_path: Option<String>,
+}
+
+type PolicyMap = HashMap<String, Arc<policy::TenantPolicyRuntime>>;
+
+/// Compiled policy per tenant, one slot each. `update_tenant_policy` swaps a
+/// single slot, so it never blocks readers of another tenant; a read is a
+/// lock-free `load`. Reloads build a fresh set of slots and swap the whole
+/// set at once, so no reader sees some tenants reloaded and others not.
+type PolicySlots = HashMap<String, ArcSwap<policy::TenantPolicyRuntime>>;
+
+fn policy_slots(runtimes: PolicyMap) -> Arc<PolicySlots> {
+    Arc::new(runtimes.into_iter().map(|(id, rt)| (id, ArcSwap::new(rt))).collect())
+}
+
+/// Per-tenant outcome of a policy reload.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TenantReload {
+    pub tenant: String,
+    /// Reloaded fields that changed (e.g. `policy.ext_allowlist`).
+    pub changed: Vec<&'static str>,
+    /// Changed fields that were ignored until restart (e.g. `limits`).
+    pub restart_required: Vec<&'static str>,
+}
+
+/// Result of `AppState::reload_policies`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PolicyReload {
+    pub tenants: Vec<TenantReload>,
+}
 
 /// Shared, clonable gateway application state (config + policy + runtimes).
 #[derive(Clone)]
@@ -29,115 +100,549 @@ pub struct AppState {
     metrics: Arc<GatewayMetrics>,
     // Sprint 5
     handshake: Arc<HandshakeDefender>,
+    quotas: Arc<QuotaTracker>,
+    audit: Arc<dyn AuditSink>,
+    egress: Option<Arc<Egress>>,
+    webhooks: Option<Arc<Webhooks>>,
+    polls: Arc<PollSessions>,
 }
 
 struct AppStateInner {
     cfg: GatewayConfig,
-    tenant_policy: HashMap<String, Arc<policy::TenantPolicyRuntime>>,
+    policies: ArcSwap<PolicySlots>,
+    /// Tenant configs the current policies were built from. The write lock
+    /// also serializes reloads.
+    sources: RwLock<HashMap<String, TenantConfig>>,
+    opts: StartupOptions,
+    #[cfg(feature = "oidc-introspection")]
+    introspection: HashMap<String, Arc<crate::auth::IntrospectionVerifier>>,
+    plugins: HashMap<String, Arc<PluginHost>>,
+    cluster: Option<Arc<ClusterRelay>>,
+    last_admin_broadcast: Mutex<Option<Instant>>,
+    started_at: Instant,
+    ops_auth: OpsAuth,
+    /// `POST /v1/publish` limiter per tenant with an `api_key`.
+    publish_limiters: HashMap<String, TokenBucket>,
 }
 
 impl AppState {
     /// Build application state (config + compiled policies + runtimes).
     ///
     /// Returns `Result` so the binary can surface startup errors without panic.
+    /// Allowlist/dispatcher mismatches are fatal; see `new_with_options`.
+    /// Synchronous: external resources are not contacted; use
+    /// `from_config_async` to check them before serving.
     pub fn new(cfg: GatewayConfig) -> Result<Self> {
-        let metrics = Arc::new(GatewayMetrics::default());
+        Self::new_with_options(cfg, StartupOptions::default())
+    }
+
+    pub fn new_with_options(cfg: GatewayConfig, opts: StartupOptions) -> Result<Self> {
+        let metrics = Arc::new(GatewayMetrics::new(
+            cfg.gateway.max_label_values_per_metric,
+            &cfg.gateway.metrics,
+        ));
         // Sprint 5
-        let handshake = Arc::new(HandshakeDefender::new(cfg.gateway.handshake_limit.clone()));
+        let handshake = Arc::new(
+            HandshakeDefender::from_config(cfg.gateway.handshake_limit.clone()).with_metrics(metrics.clone()),
+        );
 
         // 1) Compile tenant policy runtimes
-        let mut tenant_policy = HashMap::new();
+        let audit = audit::from_config(&cfg.gateway.audit)?;
+        let tenant_policy = compile_policies(&cfg.tenants, &audit)?;
+
+        // 1b) Auth backends
+        #[cfg(feature = "oidc-introspection")]
+        let mut introspection = HashMap::new();
         for t in &cfg.tenants {
-            let runtime = policy::TenantPolicyRuntime::new(
-                t.id.clone(),
-                t.limits.max_frame_bytes,
-                &t.policy,
-            )
-            .map_err(|e| {
-                WsPrismError::BadRequest(format!(
-                    "tenant policy compile failed (tenant={}): {e}",
+            let Some(icfg) = &t.introspection else { continue };
+            #[cfg(feature = "oidc-introspection")]
+            {
+                let verifier = crate::auth::IntrospectionVerifier::new(icfg.clone())?;
+                introspection.insert(t.id.clone(), Arc::new(verifier));
+            }
+            #[cfg(not(feature = "oidc-introspection"))]
+            {
+                let _ = icfg;
+                return Err(WsPrismError::BadRequest(format!(
+                    "tenant {} configures introspection but the gateway was built without the oidc-introspection feature",
                     t.id
-                ))
-            })?;
+                )));
+            }
+        }
 
-            tenant_policy.insert(t.id.clone(), Arc::new(runtime));
+        // 1c) Plugin stages
+        #[cfg_attr(not(feature = "wasm-plugins"), allow(unused_mut))]
+        let mut plugins = HashMap::new();
+        for t in &cfg.tenants {
+            let Some(pcfg) = &t.plugin else { continue };
+            #[cfg(feature = "wasm-plugins")]
+            {
+                let stage = Arc::new(crate::plugin::WasmPlugin::load(pcfg)?);
+                plugins.insert(t.id.clone(), Arc::new(PluginHost::new(t.id.clone(), stage, pcfg.on_error)));
+            }
+            #[cfg(not(feature = "wasm-plugins"))]
+            {
+                let _ = pcfg;
+                return Err(WsPrismError::BadRequest(format!(
+                    "tenant {} configures a plugin but the gateway was built without the wasm-plugins feature",
+                    t.id
+                )));
+            }
         }
 
+        // 1d) Cluster bridge
+        let cluster = match &cfg.cluster {
+            Some(ccfg) => Some(Arc::new(ClusterRelay::from_config(ccfg, metrics.clone())?)),
+            None => None,
+        };
+
         // 2) Create core components
-        let realtime = Arc::new(RealtimeCore::new());
+        let mut realtime = RealtimeCore::new()
+            .with_metrics(metrics.clone())
+            .with_replay_capacity(cfg.gateway.room_replay_capacity)
+            .with_room_event_capacity(cfg.gateway.room_event_capacity)
+            .with_dead_letter_handler(Arc::new(MetricsDeadLetterHandler::new(metrics.clone())));
+        if let Some(relay) = &cluster {
+            realtime = realtime.with_room_relay(relay.clone());
+        }
+        let realtime = Arc::new(realtime);
+        let presence_tenants: Vec<String> =
+            cfg.tenants.iter().filter(|t| t.presence_events).map(|t| t.id.clone()).collect();
+        if !presence_tenants.is_empty() {
+            let notifier = PresenceNotifier::new(Arc::downgrade(&realtime), presence_tenants);
+            realtime.presence.set_notifier(Some(Arc::new(notifier)));
+        }
         let dispatcher = Dispatcher::new();
 
         // 3) Register built-in services (Sprint 3)
+        dispatcher.register_text(Arc::new(RoomService::new()));
         dispatcher.register_text(Arc::new(ChatService::new()));
         dispatcher.register_hot(Arc::new(EchoBinaryService::new(1)));
 
-        // allowlist <-> dispatcher sanity check
-        {
-            let text_svcs = dispatcher.registered_text_svcs();
-            let hot_svcs = dispatcher.registered_hot_svcs();
-
-            let exempt_text = ["room", "sys"]; // transport/internal
-
-            for t in &cfg.tenants {
-                // ext rules: "svc:type"
-                for rule in &t.policy.ext_allowlist {
-                    if let Some((svc, _ty)) = rule.split_once(':') {
-                        if exempt_text.contains(&svc) { continue; }
-                        if !text_svcs.contains(&svc) {
-                            tracing::warn!(tenant=%t.id, rule=%rule, "ext_allowlist refers to unregistered text service");
-                            if FAIL_FAST_ON_MISMATCH {
-                                return Err(WsPrismError::BadRequest(format!(
-                                    "tenant {} ext_allowlist references unregistered text service: {}",
-                                    t.id, svc
-                                )));
-                            }
-                        }
-                    }
-                }
-
-                // hot rules: "sid:opcode"
-                for rule in &t.policy.hot_allowlist {
-                    if let Some((sid_s, _op)) = rule.split_once(':') {
-                        if let Ok(sid) = sid_s.parse::<u8>() {
-                            if !hot_svcs.contains(&sid) {
-                                tracing::warn!(tenant=%t.id, rule=%rule, sid=%sid, "hot_allowlist refers to unregistered binary service");
-                                if FAIL_FAST_ON_MISMATCH {
-                                    return Err(WsPrismError::BadRequest(format!(
-                                        "tenant {} hot_allowlist references unregistered hot service id: {}",
-                                        t.id, sid
-                                    )));
-                                }
-                            }
-                        }
-                    }
-                }
+        // 3b) Tenant `upstreams` / `http_upstreams`
+        let grpc = UpstreamTextService::from_config(&cfg.tenants)?.into_iter().map(|s| s as Arc<dyn TextService>);
+        let http = HttpUpstreamService::from_config(&cfg.tenants)?.into_iter().map(|s| s as Arc<dyn TextService>);
+        for svc in grpc.chain(http) {
+            if dispatcher.has_text(svc.svc()) {
+                return Err(WsPrismError::BadRequest(format!(
+                    "upstream service {} conflicts with another registered service",
+                    svc.svc()
+                )));
             }
+            dispatcher.register_text(svc);
+        }
+
+        // 4) allowlist <-> dispatcher binding check
+        validate_service_bindings(&tenant_policy, &dispatcher, opts.lenient)?;
+
+        let egress = Egress::from_config(&cfg.gateway.egress, metrics.clone())?.map(Arc::new);
+        let webhooks = Webhooks::from_config(&cfg.tenants, metrics.clone())?.map(Arc::new);
+        if let Some(w) = &webhooks {
+            realtime.presence.set_observer(Some(w.clone()));
         }
+        let ops_auth = OpsAuth::from_config(&cfg.ops)?;
+
+        let publish = &cfg.gateway.publish;
+        let publish_limiters = cfg
+            .tenants
+            .iter()
+            .filter(|t| t.api_key.is_some())
+            .map(|t| (t.id.clone(), TokenBucket::new(publish.rate_limit_rps, publish.rate_limit_burst)))
+            .collect();
 
+        let sources = cfg.tenants.iter().map(|t| (t.id.clone(), t.clone())).collect();
         Ok(Self {
-            inner: Arc::new(AppStateInner { cfg, tenant_policy }),
+            inner: Arc::new(AppStateInner {
+                cfg,
+                policies: ArcSwap::new(policy_slots(tenant_policy)),
+                sources: RwLock::new(sources),
+                opts,
+                #[cfg(feature = "oidc-introspection")]
+                introspection,
+                plugins,
+                cluster,
+                last_admin_broadcast: Mutex::new(None),
+                started_at: Instant::now(),
+                ops_auth,
+                publish_limiters,
+            }),
             realtime,
             dispatcher: Arc::new(dispatcher),
             metrics,
             handshake,
+            quotas: Arc::new(QuotaTracker::new()),
+            audit,
+            egress,
+            webhooks,
+            polls: Arc::new(PollSessions::new()),
         })
     }
 
+    /// Build application state, then await async initialization of external
+    /// resources (see [`init_async`](Self::init_async)).
+    pub async fn from_config_async(cfg: GatewayConfig) -> Result<Self> {
+        Self::from_config_async_with_options(cfg, StartupOptions::default()).await
+    }
+
+    pub async fn from_config_async_with_options(cfg: GatewayConfig, opts: StartupOptions) -> Result<Self> {
+        let state = Self::new_with_options(cfg, opts)?;
+        state.init_async().await?;
+        Ok(state)
+    }
+
+    /// Async startup checks, failing on the first unavailable resource:
+    /// each tenant's introspection endpoint must answer with our client
+    /// credentials. Errors name the tenant; rejected credentials are a
+    /// `BadRequest`, anything else `Internal`.
+    pub async fn init_async(&self) -> Result<()> {
+        #[cfg(feature = "oidc-introspection")]
+        for t in &self.inner.cfg.tenants {
+            let Some(v) = self.inner.introspection.get(&t.id) else { continue };
+            v.probe().await.map_err(|e| match e {
+                WsPrismError::BadRequest(msg) => WsPrismError::BadRequest(format!("tenant {}: {msg}", t.id)),
+                e => WsPrismError::Internal(format!("tenant {}: introspection backend unavailable: {e}", t.id)),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Persist quota counters through `store`. Call before serving traffic;
+    /// counters recorded so far are discarded.
+    pub fn with_quota_store(mut self, store: Arc<dyn QuotaStore>) -> Self {
+        self.quotas = Arc::new(QuotaTracker::new().with_store(store));
+        self
+    }
+
+    /// Send audit events to `sink` instead of the configured one. Call
+    /// before serving traffic: tenant policies are recompiled to hold the
+    /// new sink.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = sink;
+        let tenants: Vec<TenantConfig> = self.sources().values().cloned().collect();
+        match compile_policies(&tenants, &self.audit) {
+            Ok(runtimes) => self.inner.policies.store(policy_slots(runtimes)),
+            // Compiled once already at startup, so this is not expected.
+            Err(e) => tracing::warn!(error = %e, "policies keep the configured audit sink"),
+        }
+        self
+    }
+
+    /// Archive `policy.egress` matches to `sink` instead of the configured
+    /// one. Call before `spawn_egress`.
+    pub fn with_egress_sink(mut self, sink: Arc<dyn EgressSink>) -> Self {
+        let egress = Egress::new(sink, &self.inner.cfg.gateway.egress, self.metrics.clone());
+        self.egress = Some(Arc::new(egress));
+        self
+    }
+
+    /// Deliver tenant `webhooks` batches through `sender` instead of HTTP.
+    /// Call before `spawn_webhooks`.
+    pub fn with_webhook_sender(mut self, sender: Arc<dyn WebhookSender>) -> Self {
+        let webhooks = Arc::new(Webhooks::new(&self.inner.cfg.tenants, sender, self.metrics.clone()));
+        self.realtime.presence.set_observer(Some(webhooks.clone()));
+        self.webhooks = Some(webhooks);
+        self
+    }
+
     pub fn cfg(&self) -> &GatewayConfig {
         &self.inner.cfg
     }
 
+    /// Token gate for `/metrics` and `/admin/*`.
+    pub fn ops_auth(&self) -> &OpsAuth {
+        &self.inner.ops_auth
+    }
+
+    /// Current compiled policy of a tenant.
+    ///
+    /// Sessions call this per message, so a reload takes effect on their next
+    /// frame.
     pub fn tenant_policy(&self, tenant_id: &str) -> Option<Arc<policy::TenantPolicyRuntime>> {
-        self.inner.tenant_policy.get(tenant_id).cloned()
+        self.inner.policies.load().get(tenant_id).map(|slot| slot.load_full())
+    }
+
+    /// Replace one tenant's compiled policy without touching the others.
+    /// Returns the previous policy. Sessions pick it up on their next frame.
+    ///
+    /// Waits for a reload in progress, so the update lands in the reloaded
+    /// set instead of being dropped with the old one.
+    pub fn update_tenant_policy(
+        &self,
+        tenant_id: &str,
+        policy: Arc<policy::TenantPolicyRuntime>,
+    ) -> Result<Arc<policy::TenantPolicyRuntime>> {
+        let _reload = self.sources();
+        let slots = self.inner.policies.load();
+        let slot = slots
+            .get(tenant_id)
+            .ok_or_else(|| WsPrismError::BadRequest(format!("unknown tenant: {tenant_id}")))?;
+        Ok(slot.swap(policy))
+    }
+
+    /// Plugin stage of a tenant, if one is configured.
+    pub fn tenant_plugin(&self, tenant_id: &str) -> Option<Arc<PluginHost>> {
+        self.inner.plugins.get(tenant_id).cloned()
+    }
+
+    /// Tenants whose current policy sets `allow_server_broadcast`.
+    pub fn server_broadcast_tenants(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .sources()
+            .values()
+            .filter(|t| t.policy.allow_server_broadcast)
+            .map(|t| t.id.clone())
+            .collect();
+        out.sort();
+        out
+    }
+
+    /// Config file re-read by the admin reload endpoint.
+    pub fn config_path(&self) -> &str {
+        self.inner.opts.config_path.as_deref().unwrap_or(DEFAULT_CONFIG_PATH)
+    }
+
+    /// Periodically drop per-room limiter buckets idle longer than
+    /// `ROOM_BUCKET_IDLE_TTL` and replay buffers of empty rooms. Must be
+    /// called from within a tokio runtime.
+    pub fn spawn_room_bucket_sweeper(&self) -> tokio::task::JoinHandle<()> {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(ROOM_BUCKET_SWEEP_INTERVAL);
+            loop {
+                tick.tick().await;
+                for slot in state.inner.policies.load().values() {
+                    if let Some(limiter) = slot.load().room_limiter() {
+                        limiter.sweep_idle(ROOM_BUCKET_IDLE_TTL);
+                    }
+                }
+                state.realtime.sweep_replay_buffers();
+            }
+        })
+    }
+
+    /// Record per-tenant outbound queue depth (max, p99) gauges and add
+    /// drops since the previous sample to `wsprism_outbound_drops_total`.
+    pub fn sample_outbound(&self) {
+        for s in self.realtime.sessions.sample_outbound() {
+            let tenant = [("tenant", s.tenant.as_str())];
+            self.metrics.outbound_queue_depth_max.set(&tenant, s.max_depth() as i64);
+            self.metrics.outbound_queue_depth_p99.set(&tenant, s.p99_depth() as i64);
+            if s.lossy_drops > 0 {
+                self.metrics.outbound_drops.add(&[("tenant", &s.tenant), ("qos", "lossy")], s.lossy_drops);
+            }
+            if s.reliable_drops > 0 {
+                self.metrics.outbound_drops.add(&[("tenant", &s.tenant), ("qos", "reliable")], s.reliable_drops);
+            }
+        }
+    }
+
+    /// Run `sample_outbound` every `OUTBOUND_SAMPLE_INTERVAL`. Must be
+    /// called from within a tokio runtime.
+    pub fn spawn_outbound_sampler(&self) -> tokio::task::JoinHandle<()> {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(OUTBOUND_SAMPLE_INTERVAL);
+            loop {
+                tick.tick().await;
+                state.sample_outbound();
+            }
+        })
     }
 
-    pub fn resolve_ticket(&self, ticket: &str) -> Result<String> {
+    /// Set `wsprism_room_members` for each tenant's
+    /// `observability.room_gauges.top_n` largest rooms and drop the gauges
+    /// of rooms no longer among them.
+    pub fn sample_room_sizes(&self) {
+        let n = self.cfg().observability.room_gauges.top_n;
+        let mut current = HashSet::new();
+        for t in &self.cfg().tenants {
+            for r in self.realtime.presence.top_rooms(Some(&t.id), n) {
+                let room = r.room.split_once("::").map_or(r.room.as_str(), |(_, room)| room);
+                self.metrics.room_members.set(&[("tenant", &t.id), ("room", room)], r.sessions as i64);
+                current.insert((t.id.clone(), room.to_string()));
+            }
+        }
+        self.metrics.room_members.retain(|labels| {
+            let label = |name: &str| labels.iter().find(|(k, _)| k == name).map_or("", |(_, v)| v.as_str());
+            current.contains(&(label("tenant").to_string(), label("room").to_string()))
+        });
+    }
+
+    /// Run `sample_room_sizes` every `observability.room_gauges.interval_secs`,
+    /// unless `top_n` is 0. Must be called from within a tokio runtime.
+    pub fn spawn_room_size_sampler(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let cfg = &self.cfg().observability.room_gauges;
+        if cfg.top_n == 0 {
+            return None;
+        }
+        let every = Duration::from_secs(cfg.interval_secs);
+        let state = self.clone();
+        Some(tokio::spawn(async move {
+            let mut tick = tokio::time::interval(every);
+            loop {
+                tick.tick().await;
+                state.sample_room_sizes();
+            }
+        }))
+    }
+
+    /// Connect the cluster bridge when `cluster` is configured, relaying
+    /// room publishes of every configured tenant. Must be called from within a
+    /// tokio runtime.
+    pub fn spawn_cluster_bridge(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let relay = self.inner.cluster.clone()?;
+        let tenants = self.cfg().tenants.iter().map(|t| t.id.clone()).collect();
+        relay.spawn(self.realtime(), tenants)
+    }
+
+    /// Start the egress writer when a sink is configured. Must be called
+    /// from within a tokio runtime.
+    pub fn spawn_egress(&self) -> Option<tokio::task::JoinHandle<()>> {
+        self.egress.clone()?.spawn()
+    }
+
+    /// Start the webhook workers, one per tenant with `webhooks`. Must be
+    /// called from within a tokio runtime.
+    pub fn spawn_webhooks(&self) -> Vec<tokio::task::JoinHandle<()>> {
+        self.webhooks.clone().map(Webhooks::spawn).unwrap_or_default()
+    }
+
+    /// Periodically drop metric series idle for
+    /// `gateway.metrics.series_ttl_secs`. Must be called from within a tokio
+    /// runtime.
+    pub fn spawn_metrics_pruner(&self) -> tokio::task::JoinHandle<()> {
+        let metrics = self.metrics();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(METRICS_PRUNE_INTERVAL);
+            loop {
+                tick.tick().await;
+                metrics.prune_stale();
+            }
+        })
+    }
+
+    /// Periodically drop quota counters whose window has elapsed. Must be
+    /// called from within a tokio runtime.
+    pub fn spawn_quota_sweeper(&self) -> tokio::task::JoinHandle<()> {
+        let quotas = self.quotas();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(QUOTA_SWEEP_INTERVAL);
+            loop {
+                tick.tick().await;
+                quotas.sweep_expired(unix_now_ms());
+            }
+        })
+    }
+
+    fn sources(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, TenantConfig>> {
+        // Writers replace the map whole, so a poisoned lock still holds a
+        // consistent one.
+        self.inner.sources.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Recompile tenant policies from `cfg` and swap them in.
+    ///
+    /// Only `policy.*`, `allow_guest`, `guest_scopes`, `service_policies`,
+    /// `suspended` and `read_only` are reloaded; other changes are reported
+    /// as `restart_required`. The tenant set must not change. On any error
+    /// the current policies stay in place. Once everything compiled, all
+    /// tenants' policies are swapped in together.
+    ///
+    /// Sessions of suspended tenants get `sys.goaway` and are closed once the
+    /// new policies are in place.
+    pub fn reload_policies(&self, cfg: &GatewayConfig) -> Result<PolicyReload> {
+        cfg.validate()?;
+        let mut sources = self.inner.sources.write().unwrap_or_else(|e| e.into_inner());
+        let old_ids: BTreeSet<&str> = sources.keys().map(String::as_str).collect();
+        let new_ids: BTreeSet<&str> = cfg.tenants.iter().map(|t| t.id.as_str()).collect();
+        if old_ids != new_ids {
+            let added: Vec<&str> = new_ids.difference(&old_ids).copied().collect();
+            let removed: Vec<&str> = old_ids.difference(&new_ids).copied().collect();
+            return Err(WsPrismError::BadRequest(format!(
+                "tenant set changed (added: {added:?}, removed: {removed:?}); restart required"
+            )));
+        }
+
+        let runtimes = compile_policies(&cfg.tenants, &self.audit)?;
+        validate_service_bindings(&runtimes, &self.dispatcher, self.inner.opts.lenient)?;
+
+        let mut tenants = Vec::new();
+        for t in &cfg.tenants {
+            let Some(old) = sources.get(&t.id) else { continue };
+            let restart_required = match self.inner.cfg.tenants.iter().find(|s| s.id == t.id) {
+                Some(startup) => t.restart_only_changes(startup),
+                None => Vec::new(),
+            };
+            tenants.push(TenantReload {
+                tenant: t.id.clone(),
+                changed: t.policy_changes(old),
+                restart_required,
+            });
+        }
+        tenants.sort_by(|a, b| a.tenant.cmp(&b.tenant));
+
+        self.inner.policies.store(policy_slots(runtimes));
+        *sources = cfg.tenants.iter().map(|t| (t.id.clone(), t.clone())).collect();
+        drop(sources);
+
+        for t in cfg.tenants.iter().filter(|t| t.suspended) {
+            let n = self.realtime.goaway_tenant(&t.id, GOAWAY_TENANT_SUSPENDED);
+            if n > 0 {
+                tracing::info!(tenant=%t.id, sessions=n, "tenant suspended; sessions closed");
+            }
+        }
+        Ok(PolicyReload { tenants })
+    }
+
+    /// Resolve a connect ticket into a user id plus the session's claims.
+    ///
+    /// Tenants with an introspection backend verify against their IdP; all
+    /// others use the built-in dev resolver.
+    pub async fn resolve_ticket(&self, tenant_id: &str, ticket: &str) -> Result<(String, SessionClaims)> {
+        #[cfg(feature = "oidc-introspection")]
+        if let Some(v) = self.inner.introspection.get(tenant_id) {
+            return v.verify(ticket).await;
+        }
+        #[cfg(not(feature = "oidc-introspection"))]
+        let _ = tenant_id;
+
         match ticket {
-            "dev" => Ok("user:dev".to_string()),
+            // No claims: services fall back to the user id for display.
+            "dev" => Ok(("user:dev".to_string(), SessionClaims::default())),
             _ => Err(WsPrismError::AuthFailed),
         }
     }
 
+    /// Claim the admin broadcast slot (1 per minute, gateway-wide).
+    ///
+    /// `Err(secs)` carries the retry hint when the slot is still taken.
+    pub fn try_admin_broadcast_slot(&self) -> std::result::Result<(), u64> {
+        // Poisoned mutex means logic bug; treat as "deny" instead of panic.
+        let Ok(mut last) = self.inner.last_admin_broadcast.lock() else {
+            return Err(ADMIN_BROADCAST_INTERVAL.as_secs());
+        };
+        let now = Instant::now();
+        if let Some(prev) = *last {
+            let since = now.duration_since(prev);
+            if since < ADMIN_BROADCAST_INTERVAL {
+                return Err((ADMIN_BROADCAST_INTERVAL - since).as_secs().max(1));
+            }
+        }
+        *last = Some(now);
+        Ok(())
+    }
+
+    /// Take a `POST /v1/publish` token for `tenant_id`.
+    ///
+    /// `Err(ms)` carries the retry hint; tenants without an `api_key` have no
+    /// limiter and are always denied.
+    pub fn try_publish_slot(&self, tenant_id: &str) -> std::result::Result<(), u64> {
+        match self.inner.publish_limiters.get(tenant_id) {
+            Some(bucket) => bucket.try_acquire(1),
+            None => Err(1000),
+        }
+    }
+
     pub fn realtime(&self) -> Arc<RealtimeCore> {
         Arc::clone(&self.realtime)
     }
@@ -154,26 +659,160 @@ impl AppState {
         Arc::clone(&self.handshake)
     }
 
+    /// Audit sink for security events (`gateway.audit`).
+    pub fn audit(&self) -> &dyn AuditSink {
+        self.audit.as_ref()
+    }
+
+    /// Webhook dispatcher, when a tenant configures `webhooks`.
+    pub fn webhooks(&self) -> Option<Arc<Webhooks>> {
+        self.webhooks.clone()
+    }
+
+    /// Egress pipeline, when `gateway.egress.sink` is set.
+    pub fn egress(&self) -> Option<&Egress> {
+        self.egress.as_deref()
+    }
+
+    /// Open long-poll sessions (`/v1/poll`).
+    pub fn polls(&self) -> Arc<PollSessions> {
+        Arc::clone(&self.polls)
+    }
+
+    /// Per-user quota counters (shared by all tenants, kept across reloads).
+    pub fn quotas(&self) -> Arc<QuotaTracker> {
+        Arc::clone(&self.quotas)
+    }
+
     pub fn is_draining(&self) -> bool {
         self.metrics.is_draining()
     }
 
     /// Enter draining mode (idempotent).
     pub fn enter_draining(&self) {
-        let _ = self.metrics.set_draining();
+        self.metrics.set_draining();
+    }
+
+    /// Time since this state was built.
+    pub fn uptime(&self) -> Duration {
+        self.inner.started_at.elapsed()
+    }
+
+    /// Update uptime, open sessions and tokio worker count (0 outside a
+    /// runtime) in the process gauges. Called before rendering `/metrics`.
+    pub fn refresh_process_metrics(&self) {
+        let workers = tokio::runtime::Handle::try_current()
+            .map(|h| h.metrics().num_workers())
+            .unwrap_or(0);
+        self.metrics.process.set(self.uptime(), self.realtime.sessions.len_sessions(), workers);
     }
 
-    /// Extra counters that are owned by other modules (egress drop/timeouts).
-    pub fn metrics_extra(&self) -> Vec<(&'static str, u64)> {
-        vec![
+    /// Extra series owned by other modules (egress drop/timeouts, audit),
+    /// plus values read at scrape time: live sessions per tenant and the
+    /// number of registered text and hot services.
+    pub fn metrics_extra(&self) -> Vec<(String, u64)> {
+        let mut extra = vec![
             (
-                "wsprism_egress_drop_total",
+                "wsprism_egress_drop_total".to_string(),
                 crate::realtime::core::egress_drop_count(),
             ),
             (
-                "wsprism_egress_send_fail_total",
+                "wsprism_egress_send_fail_total".to_string(),
                 crate::realtime::core::egress_send_fail_count(),
             ),
-        ]
+            ("wsprism_audit_dropped_total".to_string(), self.audit.dropped()),
+        ];
+        for (tenant, n) in self.realtime.sessions.tenant_session_counts() {
+            extra.push((format!("wsprism_tenant_sessions{{tenant=\"{}\"}}", escape_label(&tenant)), n));
+        }
+        extra.push((
+            "wsprism_text_services_registered".to_string(),
+            self.dispatcher.registered_text_svcs().len() as u64,
+        ));
+        extra.push((
+            "wsprism_hot_services_registered".to_string(),
+            self.dispatcher.registered_hot_svcs().len() as u64,
+        ));
+        extra
+    }
+}
+
+/// Compile a `TenantPolicyRuntime` per tenant.
+fn compile_policies(tenants: &[TenantConfig], audit: &Arc<dyn AuditSink>) -> Result<PolicyMap> {
+    let mut out = HashMap::new();
+    for t in tenants {
+        let runtime = policy::TenantPolicyRuntime::new(
+            t.id.clone(),
+            t.limits.max_frame_bytes,
+            &t.policy,
+        )
+        .and_then(|r| {
+            if t.allow_guest { r.with_guest_scopes(&t.guest_scopes) } else { Ok(r) }
+        })
+        .and_then(|r| r.with_service_policies(&t.service_policies))
+        .and_then(|r| r.with_daily_message_limit(t.limits.max_messages_per_day))
+        .map(|r| r.with_maintenance(t.suspended, t.read_only).with_audit(Arc::clone(audit)))
+        .map_err(|e| {
+            WsPrismError::BadRequest(format!(
+                "tenant policy compile failed (tenant={}): {e}",
+                t.id
+            ))
+        })?;
+
+        out.insert(t.id.clone(), Arc::new(runtime));
+    }
+    Ok(out)
+}
+
+/// Cross-check compiled tenant allowlists against registered services.
+///
+/// Fails on rules that reference unknown services and on duplicate service
+/// registrations. With `lenient`, problems are logged and startup continues.
+pub fn validate_service_bindings(
+    tenant_policy: &PolicyMap,
+    dispatcher: &Dispatcher,
+    lenient: bool,
+) -> Result<()> {
+    let text_svcs = dispatcher.registered_text_svcs();
+    let hot_svcs = dispatcher.registered_hot_svcs();
+    let mut problems = Vec::new();
+
+    for dup in dispatcher.duplicate_registrations() {
+        problems.push(format!("duplicate service registration: {dup}"));
+    }
+
+    let mut tenants: Vec<_> = tenant_policy.iter().collect();
+    tenants.sort_by(|a, b| a.0.cmp(b.0));
+    for (tenant, rt) in tenants {
+        for r in rt.ext_rules() {
+            let Some(svc) = r.concrete_svc() else { continue };
+            if !BUILTIN_TEXT_SVCS.contains(&svc) && !text_svcs.contains(&svc) {
+                problems.push(format!(
+                    "tenant {tenant} ext_allowlist references unregistered text service: {svc}"
+                ));
+            }
+        }
+        for r in rt.hot_rules() {
+            if !hot_svcs.contains(&r.svc_id) {
+                problems.push(format!(
+                    "tenant {tenant} hot_allowlist references unregistered hot service id: {}",
+                    r.svc_id
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    if lenient {
+        for p in &problems {
+            tracing::warn!(problem=%p, "service binding mismatch (lenient mode)");
+        }
+        return Ok(());
     }
+    Err(WsPrismError::BadRequest(format!(
+        "service binding check failed: {}",
+        problems.join("; ")
+    )))
 }
@@ -0,0 +1,217 @@
+//! Security audit log (`gateway.audit`).
+//!
+//! Auth failures, kicks, policy-triggered closes and shadowed (dry run)
+//! rejects are recorded as
+//! [`AuditEvent`]s, separately from debug logs. Recording never blocks the
+//! caller: the file sink queues events to a writer thread and counts events
+//! dropped when the queue is full (`wsprism_audit_dropped_total`).
+//!
+//! The file sink writes one JSON object per line, e.g.
+//! `{"event":"auth_failed","ts_unix_ms":..,"tenant":"acme","user":null,"ip":"10.0.0.7","reason":"auth failed"}`,
+//! and rotates to `<path>.1`, `<path>.2`, ... once a file reaches
+//! `max_file_bytes`.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+
+use serde::Serialize;
+use wsprism_core::error::{Result, WsPrismError};
+
+use crate::config::{AuditConfig, AuditSinkKind};
+use crate::policy::quota::unix_now_ms;
+
+/// Who and why; shared by every [`AuditEvent`] kind.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditRecord {
+    pub ts_unix_ms: u64,
+    pub tenant: String,
+    /// `None` when the event happens before the user is known.
+    pub user: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub reason: String,
+}
+
+impl AuditRecord {
+    pub fn new(tenant: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self { ts_unix_ms: unix_now_ms(), tenant: tenant.into(), user: None, ip: None, reason: reason.into() }
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn ip(mut self, ip: IpAddr) -> Self {
+        self.ip = Some(ip);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Upgrade rejected because the ticket did not verify.
+    AuthFailed(AuditRecord),
+    /// A ticket was presented again after it had been used.
+    TicketReplay(AuditRecord),
+    /// Session closed by policy (strike limit, close-on-violation).
+    PolicyClose(AuditRecord),
+    /// Session evicted by the gateway (e.g. `on_exceed: kick_oldest`).
+    Kicked(AuditRecord),
+    /// Upgrade rejected or session closed because the tenant is suspended.
+    TenantSuspended(AuditRecord),
+    /// Reject or close let through by `policy.mode: shadow` (`dry_run`).
+    /// The policy engine has no session identity, so `user` and `ip` are
+    /// unset; `reason` names the lane, decision and cause.
+    PolicyShadowed(AuditRecord),
+}
+
+impl AuditEvent {
+    pub fn record(&self) -> &AuditRecord {
+        match self {
+            AuditEvent::AuthFailed(r)
+            | AuditEvent::TicketReplay(r)
+            | AuditEvent::PolicyClose(r)
+            | AuditEvent::Kicked(r)
+            | AuditEvent::TenantSuspended(r)
+            | AuditEvent::PolicyShadowed(r) => r,
+        }
+    }
+}
+
+/// Destination of audit events. `record` is called from session tasks and
+/// must not block.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: AuditEvent);
+
+    /// Events lost because the sink could not keep up.
+    fn dropped(&self) -> u64 {
+        0
+    }
+}
+
+/// Discards every event (`sink: none`).
+pub struct NoopAuditSink;
+
+impl AuditSink for NoopAuditSink {
+    fn record(&self, _event: AuditEvent) {}
+}
+
+/// Appends events as JSON lines to a size-rotated file.
+pub struct FileAuditSink {
+    tx: SyncSender<AuditEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl FileAuditSink {
+    /// Open (or create) the log and start the writer thread. The file is
+    /// opened here so a bad path fails startup instead of losing events.
+    pub fn open(path: impl Into<PathBuf>, max_file_bytes: u64, max_files: usize, queue_capacity: usize) -> Result<Self> {
+        let file = RotatingFile::open(path.into(), max_file_bytes, max_files)?;
+        let (tx, rx) = mpsc::sync_channel(queue_capacity);
+        std::thread::Builder::new()
+            .name("wsprism-audit".into())
+            .spawn(move || write_loop(rx, file))
+            .map_err(|e| WsPrismError::Internal(format!("audit writer thread: {e}")))?;
+        Ok(Self { tx, dropped: Arc::new(AtomicU64::new(0)) })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, event: AuditEvent) {
+        match self.tx.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Build the sink selected by `gateway.audit`.
+pub fn from_config(cfg: &AuditConfig) -> Result<Arc<dyn AuditSink>> {
+    match cfg.sink {
+        AuditSinkKind::None => Ok(Arc::new(NoopAuditSink)),
+        AuditSinkKind::File => {
+            let path = cfg.path.as_deref().unwrap_or_default();
+            Ok(Arc::new(FileAuditSink::open(path, cfg.max_file_bytes, cfg.max_files, cfg.queue_capacity)?))
+        }
+    }
+}
+
+fn write_loop(rx: Receiver<AuditEvent>, mut file: RotatingFile) {
+    // Ends once every sender (the sink) is dropped.
+    while let Ok(event) = rx.recv() {
+        let mut line = match serde_json::to_vec(&event) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!(error = %e, "audit event not serializable");
+                continue;
+            }
+        };
+        line.push(b'\n');
+        if let Err(e) = file.write_line(&line) {
+            tracing::warn!(error = %e, path = %file.path.display(), "audit log write failed");
+        }
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> Result<Self> {
+        let file = append(&path)
+            .map_err(|e| WsPrismError::BadRequest(format!("gateway.audit.path {}: {e}", path.display())))?;
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self { path, file, len, max_bytes, max_files })
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.len > 0 && self.len + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.len += line.len() as u64;
+        Ok(())
+    }
+
+    /// `path` -> `path.1` -> `path.2` ...; the file past `max_files` is removed.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let _ = fs::remove_file(numbered(&self.path, self.max_files));
+        for n in (1..self.max_files).rev() {
+            let from = numbered(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, numbered(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, numbered(&self.path, 1))?;
+        self.file = append(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+fn append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(format!(".{n}"));
+    PathBuf::from(s)
+}
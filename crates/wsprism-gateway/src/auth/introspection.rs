@@ -0,0 +1,159 @@
+//! OAuth2 token introspection (RFC 7662) verifier.
+//!
+//! Opaque tokens are POSTed to the tenant's introspection endpoint with client
+//! credentials. Results are cached (positive and negative, separate TTLs) so a
+//! reconnect storm does not turn into an IdP storm.
+//!
+//! The token's `scope` becomes the `scopes` claim. The session may then only
+//! send Ext frames those scopes allow (`svc` or `svc:type`, on top of the
+//! tenant policy); other frames are rejected with `AUTH_FAILED`.
+//!
+//! Error mapping:
+//! - `active=false` or missing user claim => `AuthFailed`
+//! - 4xx from the endpoint (wrong url or client credentials) => `AuthFailed`,
+//!   not retried, logged as a config error
+//! - network/5xx failure (after one retry) => `Internal` (503 at upgrade)
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serde_json::Value;
+use wsprism_core::error::{Result, WsPrismError};
+
+use crate::config::schema::IntrospectionConfig;
+use crate::context::SessionClaims;
+
+#[derive(Clone)]
+struct CacheEntry {
+    expires_at: Instant,
+    /// `None` => token known inactive (negative cache).
+    identity: Option<(String, SessionClaims)>,
+}
+
+/// Per-tenant introspection client with a TTL cache.
+pub struct IntrospectionVerifier {
+    cfg: IntrospectionConfig,
+    http: reqwest::Client,
+    cache: DashMap<String, CacheEntry>,
+}
+
+impl IntrospectionVerifier {
+    pub fn new(cfg: IntrospectionConfig) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_millis(cfg.timeout_ms))
+            .build()
+            .map_err(|e| WsPrismError::Internal(format!("introspection client build failed: {e}")))?;
+        Ok(Self {
+            cfg,
+            http,
+            cache: DashMap::new(),
+        })
+    }
+
+    /// Verify an opaque token, returning `(user_id, claims)`.
+    pub async fn verify(&self, token: &str) -> Result<(String, SessionClaims)> {
+        if let Some(hit) = self.cache.get(token) {
+            if hit.expires_at > Instant::now() {
+                return hit.identity.clone().ok_or(WsPrismError::AuthFailed);
+            }
+        }
+
+        let body = match self.introspect(token).await {
+            Ok(b) => b,
+            // A rejected request fails the same way the second time.
+            Err(WsPrismError::AuthFailed) => return Err(WsPrismError::AuthFailed),
+            Err(first) => {
+                tracing::warn!(error=%first, "introspection failed; retrying once");
+                self.introspect(token).await?
+            }
+        };
+
+        let identity = self.identity_from(&body)?;
+        let ttl = if identity.is_some() { self.cfg.cache_ttl_ms } else { self.cfg.negative_cache_ttl_ms };
+        self.store(token, identity.clone(), ttl);
+        identity.ok_or(WsPrismError::AuthFailed)
+    }
+
+    /// Check that the endpoint is reachable and accepts our client
+    /// credentials, by introspecting a placeholder token (not cached).
+    /// A 4xx answer is a `BadRequest` (config error).
+    pub async fn probe(&self) -> Result<()> {
+        match self.introspect("wsprism-startup-probe").await {
+            Ok(_) => Ok(()),
+            Err(WsPrismError::AuthFailed) => Err(WsPrismError::BadRequest(
+                "introspection endpoint rejected the request; check url, client_id and client_secret".into(),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn introspect(&self, token: &str) -> Result<Value> {
+        let resp = self
+            .http
+            .post(&self.cfg.url)
+            .basic_auth(&self.cfg.client_id, Some(&self.cfg.client_secret))
+            .form(&[("token", token)])
+            .send()
+            .await
+            .map_err(|e| WsPrismError::Internal(format!("introspection request failed: {e}")))?;
+
+        if resp.status().is_client_error() {
+            tracing::error!(
+                url = %self.cfg.url,
+                status = %resp.status(),
+                "introspection endpoint rejected the request; check url, client_id and client_secret"
+            );
+            return Err(WsPrismError::AuthFailed);
+        }
+        if !resp.status().is_success() {
+            return Err(WsPrismError::Internal(format!(
+                "introspection endpoint returned {}",
+                resp.status()
+            )));
+        }
+        resp.json::<Value>()
+            .await
+            .map_err(|e| WsPrismError::Internal(format!("introspection response invalid: {e}")))
+    }
+
+    /// `Ok(None)` for inactive tokens; errors only on malformed claims.
+    fn identity_from(&self, body: &Value) -> Result<Option<(String, SessionClaims)>> {
+        if !body.get("active").and_then(Value::as_bool).unwrap_or(false) {
+            return Ok(None);
+        }
+        let Some(user) = body.get(&self.cfg.user_claim).and_then(Value::as_str) else {
+            return Ok(None);
+        };
+
+        let mut claims = HashMap::new();
+        if let Some(scope) = body.get("scope").and_then(Value::as_str) {
+            let scopes: Vec<Value> = scope.split_whitespace().map(Value::from).collect();
+            claims.insert("scopes".to_string(), Value::Array(scopes));
+        }
+        if let Some(name) = body.get("username").and_then(Value::as_str) {
+            claims.insert("display_name".to_string(), Value::from(name));
+        }
+        Ok(Some((user.to_string(), SessionClaims::new(claims)?)))
+    }
+
+    fn store(&self, token: &str, identity: Option<(String, SessionClaims)>, ttl_ms: u64) {
+        if ttl_ms == 0 {
+            return;
+        }
+        if self.cache.len() >= self.cfg.max_cache_entries {
+            let now = Instant::now();
+            self.cache.retain(|_, e| e.expires_at > now);
+            if self.cache.len() >= self.cfg.max_cache_entries {
+                return;
+            }
+        }
+        self.cache.insert(
+            token.to_string(),
+            CacheEntry {
+                expires_at: Instant::now() + Duration::from_millis(ttl_ms),
+                identity,
+            },
+        );
+    }
+}
@@ -0,0 +1,10 @@
+//! Authentication backends that turn a connect ticket into a user identity.
+//!
+//! The built-in resolver lives in `AppState::resolve_ticket`; optional
+//! backends are feature-gated so the default build carries no HTTP client.
+
+#[cfg(feature = "oidc-introspection")]
+pub mod introspection;
+
+#[cfg(feature = "oidc-introspection")]
+pub use introspection::IntrospectionVerifier;
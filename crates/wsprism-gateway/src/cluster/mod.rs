@@ -0,0 +1,514 @@
+//! Room fanout across gateway replicas (`cluster` config section).
+//!
+//! Every room publish is relayed through a [`ClusterTransport`] as one frame:
+//!
+//! ```text
+//! u8 version | u8 kind (0 text, 1 binary) | u64 sent_unix_ms
+//! u16 origin_len | origin | u16 exclude_len | exclude_user_key | payload
+//! ```
+//!
+//! (integers big-endian, `exclude_len` 0 = none). Each replica subscribes to
+//! the rooms of its tenants and delivers frames from other origins with the
+//! lossy local fanout.
+//!
+//! Room membership travels on the same channels, with the same header and
+//! kinds 2 (join), 3 (leave) and 4 (snapshot). A join or leave carries one
+//! user key, sent when a user's first session on this replica enters the
+//! room or their last one leaves. Every `presence_heartbeat_ms` each replica
+//! sends snapshots of its members per room (`u16 len | user_key` repeated,
+//! split to fit `max_message_bytes`). Receivers keep them in
+//! [`RemotePresence`](crate::realtime::RemotePresence) for
+//! `presence_ttl_ms`, so a replica that stops heartbeating drops out. Publishing never waits on the transport: frames go
+//! through a bounded queue, and a full queue drops the relay (the local
+//! fanout still happens). While the subscription is down the replica is
+//! local-only: nothing is relayed, `wsprism_cluster_connected` is 0, and it
+//! reconnects with exponential backoff.
+//!
+//! Transports, selected by `cluster.backend`:
+//! - `redis` (`cluster-redis` feature): [`RedisBridge`], channels
+//!   `wsprism:{tenant}:{room}`.
+//! - `nats` (`cluster-nats` feature): [`NatsBridge`], subjects
+//!   `wsprism.{tenant}.{room}`.
+//!
+//! Failures are counted in `wsprism_cluster_bridge_errors_total{kind}`:
+//! `connect`, `publish`, `queue_full`, `too_large`, `decode`.
+
+#[cfg(feature = "cluster-nats")]
+mod nats_bridge;
+#[cfg(feature = "cluster-redis")]
+mod redis_bridge;
+
+#[cfg(feature = "cluster-nats")]
+pub use nats_bridge::NatsBridge;
+#[cfg(feature = "cluster-redis")]
+pub use redis_bridge::RedisBridge;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
+use wsprism_core::error::{Result, WsPrismError};
+
+use crate::config::schema::ClusterConfig;
+use crate::obs::metrics::GatewayMetrics;
+use crate::realtime::{PreparedMsg, RealtimeCore, RoomRelay, UserMembershipObserver};
+
+const FRAME_VERSION: u8 = 1;
+const KIND_TEXT: u8 = 0;
+const KIND_BINARY: u8 = 1;
+const KIND_JOIN: u8 = 2;
+const KIND_LEAVE: u8 = 3;
+const KIND_SNAPSHOT: u8 = 4;
+/// Header bytes besides the origin and exclude ids.
+const HEADER_LEN: usize = 14;
+
+/// Relays waiting for the transport.
+const QUEUE_CAPACITY: usize = 4096;
+const BACKOFF_MIN: Duration = Duration::from_millis(100);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// `(room_key, frame)` pairs received from other replicas.
+pub type FrameStream = BoxStream<'static, (String, Bytes)>;
+
+/// Pub/sub connection between replicas. `room_key`s are tenant-qualified
+/// (`tenant::room`); mapping them to channels or subjects is up to the
+/// transport.
+#[async_trait]
+pub trait ClusterTransport: Send + Sync {
+    /// Backend name, used as the `backend` metric label.
+    fn backend(&self) -> &'static str;
+
+    /// Publish one encoded frame to every replica.
+    async fn publish(&self, room_key: &str, frame: Bytes) -> Result<()>;
+
+    /// Subscribe to every room of `tenants`. The stream ends when the
+    /// connection is lost; the caller subscribes again.
+    async fn subscribe(&self, tenants: &[String]) -> Result<FrameStream>;
+}
+
+/// A decoded bridge frame.
+#[derive(Debug, Clone)]
+pub struct RelayFrame {
+    /// `node_id` of the publishing replica.
+    pub origin: String,
+    pub sent_unix_ms: u64,
+    /// Tenant-qualified user whose sessions are skipped.
+    pub exclude_user_key: Option<String>,
+    pub msg: PreparedMsg,
+}
+
+/// Encode one room publish (see the module docs for the layout). Ids longer
+/// than `u16::MAX` bytes are truncated; node ids and user keys never are in
+/// practice.
+pub fn encode_frame(origin: &str, sent_unix_ms: u64, exclude_user_key: Option<&str>, msg: &PreparedMsg) -> Vec<u8> {
+    let (kind, payload): (u8, &[u8]) = match msg {
+        PreparedMsg::Text(s) => (KIND_TEXT, s.as_bytes()),
+        PreparedMsg::Binary(b) => (KIND_BINARY, b),
+    };
+    let exclude = exclude_user_key.unwrap_or("");
+    let mut out = encode_header(kind, origin, sent_unix_ms, exclude, payload.len());
+    out.extend_from_slice(payload);
+    out
+}
+
+fn encode_header(kind: u8, origin: &str, sent_unix_ms: u64, exclude: &str, payload_len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + origin.len() + exclude.len() + payload_len);
+    out.push(FRAME_VERSION);
+    out.push(kind);
+    out.extend_from_slice(&sent_unix_ms.to_be_bytes());
+    for field in [origin.as_bytes(), exclude.as_bytes()] {
+        push_field(&mut out, field);
+    }
+    out
+}
+
+/// `u16 len | bytes`, truncated to `u16::MAX` bytes.
+fn push_field(out: &mut Vec<u8>, field: &[u8]) {
+    let field = &field[..field.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&(field.len() as u16).to_be_bytes());
+    out.extend_from_slice(field);
+}
+
+/// A membership change or snapshot from another replica.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresenceDelta {
+    Joined(String),
+    Left(String),
+    /// Some of the origin's members of the room (a snapshot may take several
+    /// frames).
+    Snapshot(Vec<String>),
+}
+
+/// A decoded presence frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresenceFrame {
+    /// `node_id` of the sending replica.
+    pub origin: String,
+    pub sent_unix_ms: u64,
+    pub delta: PresenceDelta,
+}
+
+/// Encode one join or leave (see the module docs); snapshots go through
+/// [`encode_snapshots`].
+pub fn encode_presence(origin: &str, sent_unix_ms: u64, delta: &PresenceDelta) -> Vec<u8> {
+    match delta {
+        PresenceDelta::Joined(user_key) | PresenceDelta::Left(user_key) => {
+            let kind = if matches!(delta, PresenceDelta::Joined(_)) { KIND_JOIN } else { KIND_LEAVE };
+            let mut out = encode_header(kind, origin, sent_unix_ms, "", user_key.len());
+            out.extend_from_slice(user_key.as_bytes());
+            out
+        }
+        PresenceDelta::Snapshot(users) => {
+            encode_snapshots(origin, sent_unix_ms, users, usize::MAX).into_iter().next().unwrap_or_default()
+        }
+    }
+}
+
+/// Snapshot frames listing `users`, each at most `max_frame_bytes` long.
+/// Users that do not fit in a frame on their own are left out.
+pub fn encode_snapshots(origin: &str, sent_unix_ms: u64, users: &[String], max_frame_bytes: usize) -> Vec<Vec<u8>> {
+    let header = || encode_header(KIND_SNAPSHOT, origin, sent_unix_ms, "", 0);
+    let empty_len = header().len();
+    let mut frames = Vec::new();
+    let mut frame = header();
+    for user in users {
+        let entry = 2 + user.len().min(u16::MAX as usize);
+        if empty_len + entry > max_frame_bytes {
+            continue;
+        }
+        if frame.len() + entry > max_frame_bytes {
+            frames.push(std::mem::replace(&mut frame, header()));
+        }
+        push_field(&mut frame, user.as_bytes());
+    }
+    if frame.len() > empty_len || frames.is_empty() {
+        frames.push(frame);
+    }
+    frames
+}
+
+/// Decode a frame produced by [`encode_frame`].
+pub fn decode_frame(b: &[u8]) -> Result<RelayFrame> {
+    let Header { kind, sent_unix_ms, origin, exclude, payload: b } = decode_header(b)?;
+    let msg = match kind {
+        KIND_TEXT => PreparedMsg::Text(std::str::from_utf8(b).map_err(|_| bad("invalid utf-8 text"))?.into()),
+        KIND_BINARY => PreparedMsg::Binary(Bytes::copy_from_slice(b)),
+        _ => return Err(bad("unknown kind")),
+    };
+    Ok(RelayFrame {
+        origin,
+        sent_unix_ms,
+        exclude_user_key: (!exclude.is_empty()).then_some(exclude),
+        msg,
+    })
+}
+
+/// Decode a frame produced by [`encode_presence`] or [`encode_snapshots`].
+pub fn decode_presence(b: &[u8]) -> Result<PresenceFrame> {
+    let Header { kind, sent_unix_ms, origin, payload, .. } = decode_header(b)?;
+    let user = |b: &[u8]| std::str::from_utf8(b).map(str::to_string).map_err(|_| bad("invalid id"));
+    let delta = match kind {
+        KIND_JOIN => PresenceDelta::Joined(user(payload)?),
+        KIND_LEAVE => PresenceDelta::Left(user(payload)?),
+        KIND_SNAPSHOT => {
+            let mut users = Vec::new();
+            let mut b = payload;
+            while !b.is_empty() {
+                let (field, rest) = split_field(b)?;
+                users.push(user(field)?);
+                b = rest;
+            }
+            PresenceDelta::Snapshot(users)
+        }
+        _ => return Err(bad("unknown kind")),
+    };
+    Ok(PresenceFrame { origin, sent_unix_ms, delta })
+}
+
+fn is_presence_frame(b: &[u8]) -> bool {
+    matches!(b.get(1), Some(&(KIND_JOIN | KIND_LEAVE | KIND_SNAPSHOT)))
+}
+
+fn bad(what: &str) -> WsPrismError {
+    WsPrismError::BadRequest(format!("cluster frame: {what}"))
+}
+
+struct Header<'a> {
+    kind: u8,
+    sent_unix_ms: u64,
+    origin: String,
+    exclude: String,
+    payload: &'a [u8],
+}
+
+fn decode_header(b: &[u8]) -> Result<Header<'_>> {
+    let [version, kind, rest @ ..] = b else { return Err(bad("truncated header")) };
+    if *version != FRAME_VERSION {
+        return Err(bad("unsupported version"));
+    }
+    let (sent, mut b) = rest.split_first_chunk::<8>().ok_or_else(|| bad("truncated header"))?;
+    let mut fields = [String::new(), String::new()];
+    for field in &mut fields {
+        let (bytes, rest) = split_field(b)?;
+        *field = std::str::from_utf8(bytes).map_err(|_| bad("invalid id"))?.to_string();
+        b = rest;
+    }
+    let [origin, exclude] = fields;
+    Ok(Header { kind: *kind, sent_unix_ms: u64::from_be_bytes(*sent), origin, exclude, payload: b })
+}
+
+/// Split a `u16 len | bytes` field off the front of `b`.
+fn split_field(b: &[u8]) -> Result<(&[u8], &[u8])> {
+    let (len, rest) = b.split_first_chunk::<2>().ok_or_else(|| bad("truncated header"))?;
+    let len = u16::from_be_bytes(*len) as usize;
+    if rest.len() < len {
+        return Err(bad("truncated header"));
+    }
+    Ok(rest.split_at(len))
+}
+
+fn transport_for(cfg: &ClusterConfig) -> Result<Arc<dyn ClusterTransport>> {
+    match cfg.backend {
+        #[cfg(feature = "cluster-redis")]
+        crate::config::ClusterBackend::Redis => Ok(Arc::new(RedisBridge::new(cfg.redis_url.as_deref().unwrap_or_default())?)),
+        #[cfg(feature = "cluster-nats")]
+        crate::config::ClusterBackend::Nats => Ok(Arc::new(NatsBridge::new(cfg.nats_url.as_deref().unwrap_or_default()))),
+        #[allow(unreachable_patterns)]
+        backend => Err(WsPrismError::BadRequest(format!(
+            "cluster.backend is {0} but the gateway was built without the cluster-{0} feature",
+            backend.as_str()
+        ))),
+    }
+}
+
+fn unix_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Relays room publishes through a transport and delivers other replicas'
+/// publishes locally. Install it with `RealtimeCore::with_room_relay`, then
+/// [`spawn`](Self::spawn) it.
+pub struct ClusterRelay {
+    node_id: String,
+    max_message_bytes: usize,
+    presence_heartbeat: Duration,
+    presence_ttl: Duration,
+    transport: Arc<dyn ClusterTransport>,
+    metrics: Arc<GatewayMetrics>,
+    connected: AtomicBool,
+    tx: mpsc::Sender<(String, Bytes)>,
+    rx: Mutex<Option<mpsc::Receiver<(String, Bytes)>>>,
+}
+
+impl ClusterRelay {
+    pub fn new(cfg: &ClusterConfig, transport: Arc<dyn ClusterTransport>, metrics: Arc<GatewayMetrics>) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        metrics.cluster_connected.set(&[("backend", transport.backend())], 0);
+        Self {
+            node_id: cfg.node_id.clone(),
+            max_message_bytes: cfg.max_message_bytes,
+            presence_heartbeat: Duration::from_millis(cfg.presence_heartbeat_ms),
+            presence_ttl: Duration::from_millis(cfg.presence_ttl_ms),
+            transport,
+            metrics,
+            connected: AtomicBool::new(false),
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Relay over the transport selected by `cluster.backend`. Does not
+    /// connect yet; a bad URL or a backend missing from the build fails here.
+    pub fn from_config(cfg: &ClusterConfig, metrics: Arc<GatewayMetrics>) -> Result<Self> {
+        Ok(Self::new(cfg, transport_for(cfg)?, metrics))
+    }
+
+    pub fn node_id(&self) -> &str { &self.node_id }
+
+    /// Whether the subscription is up; relaying is skipped while it is not.
+    pub fn is_connected(&self) -> bool { self.connected.load(Ordering::Relaxed) }
+
+    /// Start the publisher, the subscriber and the presence heartbeat (for
+    /// `tenants`), delivering into `core`, and gossip `core`'s joins and
+    /// leaves. `None` if already started. Must be called from within a tokio
+    /// runtime.
+    pub fn spawn(self: Arc<Self>, core: Arc<RealtimeCore>, tenants: Vec<String>) -> Option<tokio::task::JoinHandle<()>> {
+        let rx = self.rx.lock().unwrap_or_else(|e| e.into_inner()).take()?;
+        core.presence.set_user_observer(Some(self.clone()));
+        Some(tokio::spawn(async move {
+            tokio::join!(
+                self.run_publisher(rx),
+                self.run_subscriber(&core, &tenants),
+                self.run_heartbeat(&core, &tenants)
+            );
+        }))
+    }
+
+    /// Handle one frame received for `room_key`. Frames from this node are
+    /// skipped; malformed or oversized ones are counted and dropped.
+    pub fn on_frame(&self, core: &RealtimeCore, room_key: &str, payload: &[u8]) {
+        let Some((tenant, _)) = room_key.split_once("::") else {
+            self.note_error("decode");
+            return;
+        };
+        if payload.len() > self.max_message_bytes {
+            self.note_error("too_large");
+            return;
+        }
+        if is_presence_frame(payload) {
+            self.on_presence(core, room_key, payload);
+            return;
+        }
+        let frame = match decode_frame(payload) {
+            Ok(f) => f,
+            Err(e) => {
+                self.note_error("decode");
+                tracing::debug!(%room_key, error=%e, "cluster frame dropped");
+                return;
+            }
+        };
+        if frame.origin == self.node_id {
+            return;
+        }
+        let lag = Duration::from_millis(unix_ms().saturating_sub(frame.sent_unix_ms));
+        self.metrics.cluster_bridge_lag.observe(&[("tenant", tenant)], lag);
+        core.deliver_relayed(room_key, &frame.msg, frame.exclude_user_key.as_deref());
+    }
+
+    fn on_presence(&self, core: &RealtimeCore, room_key: &str, payload: &[u8]) {
+        let frame = match decode_presence(payload) {
+            Ok(f) => f,
+            Err(e) => {
+                self.note_error("decode");
+                tracing::debug!(%room_key, error=%e, "cluster presence frame dropped");
+                return;
+            }
+        };
+        if frame.origin == self.node_id {
+            return;
+        }
+        let remote = core.presence.remote();
+        let expires_at = tokio::time::Instant::now() + self.presence_ttl;
+        match &frame.delta {
+            PresenceDelta::Joined(user_key) => remote.refresh(&frame.origin, room_key, [user_key.as_str()], expires_at),
+            PresenceDelta::Left(user_key) => remote.remove(&frame.origin, room_key, user_key),
+            PresenceDelta::Snapshot(users) => {
+                remote.refresh(&frame.origin, room_key, users.iter().map(String::as_str), expires_at)
+            }
+        }
+    }
+
+    /// Queue `frame` for `room_key` unless it is too large or the queue is
+    /// full.
+    fn enqueue(&self, room_key: &str, frame: Vec<u8>) {
+        if frame.len() > self.max_message_bytes {
+            self.note_error("too_large");
+            return;
+        }
+        if self.tx.try_send((room_key.to_string(), Bytes::from(frame))).is_err() {
+            self.note_error("queue_full");
+        }
+    }
+
+    /// Queue a join or leave while connected.
+    fn gossip(&self, room_key: &str, delta: PresenceDelta) {
+        if !self.is_connected() || !room_key.contains("::") {
+            return;
+        }
+        self.enqueue(room_key, encode_presence(&self.node_id, unix_ms(), &delta));
+    }
+
+    fn note_error(&self, kind: &str) {
+        self.metrics.cluster_bridge_errors.inc(&[("kind", kind)]);
+    }
+
+    fn set_connected(&self, up: bool) {
+        self.connected.store(up, Ordering::Relaxed);
+        self.metrics.cluster_connected.set(&[("backend", self.transport.backend())], i64::from(up));
+    }
+
+    /// Publish queued frames; those queued before a disconnect are dropped.
+    async fn run_publisher(&self, mut rx: mpsc::Receiver<(String, Bytes)>) {
+        while let Some((room_key, frame)) = rx.recv().await {
+            if !self.is_connected() {
+                continue;
+            }
+            if let Err(e) = self.transport.publish(&room_key, frame).await {
+                self.note_error("publish");
+                tracing::warn!(%room_key, backend=self.transport.backend(), error=%e, "cluster publish failed");
+            }
+        }
+    }
+
+    /// Every `presence_heartbeat_ms`: snapshot the local members of every
+    /// room (while connected) and sweep expired remote members.
+    async fn run_heartbeat(&self, core: &RealtimeCore, tenants: &[String]) {
+        let mut tick = tokio::time::interval(self.presence_heartbeat);
+        loop {
+            tick.tick().await;
+            core.presence.remote().sweep();
+            if !self.is_connected() {
+                continue;
+            }
+            let now = unix_ms();
+            for tenant in tenants {
+                core.presence.local_members(tenant, |room_key, users| {
+                    for frame in encode_snapshots(&self.node_id, now, &users, self.max_message_bytes) {
+                        self.enqueue(room_key, frame);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Subscribe and deliver until the connection drops, then reconnect.
+    async fn run_subscriber(&self, core: &RealtimeCore, tenants: &[String]) {
+        let backend = self.transport.backend();
+        let mut backoff = BACKOFF_MIN;
+        loop {
+            match self.transport.subscribe(tenants).await {
+                Ok(mut frames) => {
+                    backoff = BACKOFF_MIN;
+                    self.set_connected(true);
+                    tracing::info!(node_id=%self.node_id, backend, tenants=tenants.len(), "cluster connected");
+                    while let Some((room_key, frame)) = frames.next().await {
+                        self.on_frame(core, &room_key, &frame);
+                    }
+                    self.set_connected(false);
+                    self.note_error("connect");
+                    tracing::warn!(backend, "cluster connection lost; delivering locally only");
+                }
+                Err(e) => {
+                    self.note_error("connect");
+                    tracing::warn!(backend, error=%e, retry_ms=backoff.as_millis() as u64, "cluster connect failed");
+                }
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(BACKOFF_MAX);
+        }
+    }
+}
+
+impl RoomRelay for ClusterRelay {
+    fn relay(&self, room_key: &str, msg: &PreparedMsg, exclude_user_key: Option<&str>) {
+        if !self.is_connected() || !room_key.contains("::") {
+            return;
+        }
+        self.enqueue(room_key, encode_frame(&self.node_id, unix_ms(), exclude_user_key, msg));
+    }
+}
+
+impl UserMembershipObserver for ClusterRelay {
+    fn user_joined(&self, room_key: &str, user_key: &str) {
+        self.gossip(room_key, PresenceDelta::Joined(user_key.to_string()));
+    }
+
+    fn user_left(&self, room_key: &str, user_key: &str) {
+        self.gossip(room_key, PresenceDelta::Left(user_key.to_string()));
+    }
+}
@@ -0,0 +1,89 @@
+//! NATS transport: core pub/sub on `wsprism.{tenant}.{room}`, one
+//! `wsprism.{tenant}.>` subscription per tenant. No queue group, so every
+//! replica receives every message.
+
+use std::sync::{Arc, Mutex};
+
+use async_nats::{ConnectOptions, Event};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::{self, StreamExt};
+use tokio::sync::watch;
+use wsprism_core::error::{Result, WsPrismError};
+
+use super::{ClusterTransport, FrameStream};
+
+/// Prefix of every bridge subject.
+pub const SUBJECT_PREFIX: &str = "wsprism.";
+
+pub struct NatsBridge {
+    url: String,
+    /// Client of the current subscription, shared with the publisher.
+    client: Mutex<Option<async_nats::Client>>,
+}
+
+impl NatsBridge {
+    /// Does not connect yet.
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string(), client: Mutex::new(None) }
+    }
+
+    /// `tenant::room` => `wsprism.tenant.room`.
+    pub fn subject_for(room_key: &str) -> Option<String> {
+        let (tenant, room) = room_key.split_once("::")?;
+        Some(format!("{SUBJECT_PREFIX}{tenant}.{room}"))
+    }
+}
+
+#[async_trait]
+impl ClusterTransport for NatsBridge {
+    fn backend(&self) -> &'static str { "nats" }
+
+    async fn publish(&self, room_key: &str, frame: Bytes) -> Result<()> {
+        let subject = Self::subject_for(room_key)
+            .ok_or_else(|| WsPrismError::BadRequest(format!("not a room key: {room_key}")))?;
+        let client = self.client.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let client = client.ok_or_else(|| WsPrismError::Internal("nats: not connected".into()))?;
+        client.publish(subject, frame).await.map_err(|e| WsPrismError::Internal(format!("nats: {e}")))
+    }
+
+    async fn subscribe(&self, tenants: &[String]) -> Result<FrameStream> {
+        // The client reconnects on its own, but the bridge goes local-only
+        // on the first disconnect, so end the stream there and start over.
+        let (up_tx, mut up_rx) = watch::channel(true);
+        let up_tx = Arc::new(up_tx);
+        let client = ConnectOptions::new()
+            .event_callback(move |event| {
+                let up_tx = up_tx.clone();
+                async move {
+                    match event {
+                        Event::Connected => up_tx.send_replace(true),
+                        Event::Disconnected => up_tx.send_replace(false),
+                        _ => return,
+                    };
+                }
+            })
+            .connect(self.url.as_str())
+            .await
+            .map_err(|e| WsPrismError::Internal(format!("nats: {e}")))?;
+
+        let mut subs = Vec::with_capacity(tenants.len());
+        for tenant in tenants {
+            let sub = client
+                .subscribe(format!("{SUBJECT_PREFIX}{tenant}.>"))
+                .await
+                .map_err(|e| WsPrismError::Internal(format!("nats: {e}")))?;
+            let prefix = format!("{SUBJECT_PREFIX}{tenant}.");
+            let tenant = tenant.clone();
+            subs.push(sub.filter_map(move |msg| {
+                let room_key = msg.subject.strip_prefix(prefix.as_str()).map(|room| format!("{tenant}::{room}"));
+                std::future::ready(room_key.map(|k| (k, msg.payload)))
+            }));
+        }
+        *self.client.lock().unwrap_or_else(|e| e.into_inner()) = Some(client);
+        let lost = async move {
+            let _ = up_rx.wait_for(|up| !*up).await;
+        };
+        Ok(stream::select_all(subs).take_until(lost).boxed())
+    }
+}
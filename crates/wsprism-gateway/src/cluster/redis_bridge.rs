@@ -0,0 +1,99 @@
+//! Redis pub/sub transport: `PUBLISH` to `wsprism:{tenant}:{room}`, one
+//! `PSUBSCRIBE wsprism:{tenant}:*` per tenant.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{future, StreamExt};
+use redis::aio::MultiplexedConnection;
+use wsprism_core::error::{Result, WsPrismError};
+
+use super::{ClusterTransport, FrameStream};
+
+/// Prefix of every bridge channel.
+pub const CHANNEL_PREFIX: &str = "wsprism:";
+
+fn redis_err(e: redis::RedisError) -> WsPrismError {
+    WsPrismError::Internal(format!("redis: {e}"))
+}
+
+pub struct RedisBridge {
+    client: redis::Client,
+    /// Publishing connection, opened on first use and after a failure.
+    conn: tokio::sync::Mutex<Option<MultiplexedConnection>>,
+}
+
+impl RedisBridge {
+    /// Does not connect yet; a bad URL fails here.
+    pub fn new(url: &str) -> Result<Self> {
+        let client =
+            redis::Client::open(url).map_err(|e| WsPrismError::BadRequest(format!("cluster.redis_url: {e}")))?;
+        Ok(Self { client, conn: tokio::sync::Mutex::new(None) })
+    }
+
+    /// `tenant::room` => `wsprism:tenant:room`.
+    pub fn channel_for(room_key: &str) -> Option<String> {
+        let (tenant, room) = room_key.split_once("::")?;
+        Some(format!("{CHANNEL_PREFIX}{tenant}:{room}"))
+    }
+
+    /// Pattern matching every room channel of `tenant` (glob characters in
+    /// the tenant id are escaped).
+    pub fn pattern_for(tenant: &str) -> String {
+        let mut p = String::from(CHANNEL_PREFIX);
+        for c in tenant.chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                p.push('\\');
+            }
+            p.push(c);
+        }
+        p.push_str(":*");
+        p
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection> {
+        let mut conn = self.conn.lock().await;
+        if let Some(c) = conn.as_ref() {
+            return Ok(c.clone());
+        }
+        let c = self.client.get_multiplexed_async_connection().await.map_err(redis_err)?;
+        *conn = Some(c.clone());
+        Ok(c)
+    }
+}
+
+#[async_trait]
+impl ClusterTransport for RedisBridge {
+    fn backend(&self) -> &'static str { "redis" }
+
+    async fn publish(&self, room_key: &str, frame: Bytes) -> Result<()> {
+        let channel = Self::channel_for(room_key)
+            .ok_or_else(|| WsPrismError::BadRequest(format!("not a room key: {room_key}")))?;
+        let mut conn = self.connection().await?;
+        let sent: redis::RedisResult<i64> =
+            redis::cmd("PUBLISH").arg(channel).arg(&frame[..]).query_async(&mut conn).await;
+        if let Err(e) = sent {
+            *self.conn.lock().await = None;
+            return Err(redis_err(e));
+        }
+        Ok(())
+    }
+
+    async fn subscribe(&self, tenants: &[String]) -> Result<FrameStream> {
+        let mut pubsub = self.client.get_async_pubsub().await.map_err(redis_err)?;
+        let patterns: HashMap<String, String> = tenants.iter().map(|t| (Self::pattern_for(t), t.clone())).collect();
+        for p in patterns.keys() {
+            pubsub.psubscribe(p).await.map_err(redis_err)?;
+        }
+        let frames = pubsub.into_on_message().filter_map(move |msg| {
+            let pattern: Option<String> = msg.get_pattern().ok();
+            let room_key = pattern.and_then(|p| patterns.get(&p)).and_then(|tenant| {
+                let room = msg.get_channel_name().strip_prefix(CHANNEL_PREFIX)?.strip_prefix(tenant.as_str())?.strip_prefix(':')?;
+                Some(format!("{tenant}::{room}"))
+            });
+            future::ready(room_key.map(|k| (k, Bytes::copy_from_slice(msg.get_payload_bytes()))))
+        });
+        Ok(frames.boxed())
+    }
+}
@@ -0,0 +1,52 @@
+//! Field-level tenant config diffs (used by the admin policy reload report).
+
+use super::schema::TenantConfig;
+
+/// Push `"<prefix><field>"` for each listed field that differs.
+macro_rules! push_changed {
+    ($out:expr, $a:expr, $b:expr, $prefix:literal, [$($f:ident),* $(,)?]) => {
+        $( if $a.$f != $b.$f { $out.push(concat!($prefix, stringify!($f))); } )*
+    };
+}
+
+impl TenantConfig {
+    /// Reloadable fields (`policy.*`, guest access, service overrides) that differ from `other`.
+    pub fn policy_changes(&self, other: &TenantConfig) -> Vec<&'static str> {
+        let mut out = Vec::new();
+        push_changed!(out, self.policy, other.policy, "policy.", [
+            rate_limit_rps,
+            rate_limit_burst,
+            rate_limit_scope,
+            rate_limit_bytes_per_sec,
+            per_session_bytes_per_sec,
+            max_outbound_bytes_per_sec,
+            room_rate_limit_rps,
+            room_rate_limit_burst,
+            ext_allowlist,
+            hot_allowlist,
+            ext_denylist,
+            hot_denylist,
+            sessions,
+            hot_error_mode,
+            hot_requires_active_room,
+            allow_server_broadcast,
+            strike_limit,
+            strike_window_ms,
+            max_fanout_parallelism,
+            mode,
+            dry_run,
+            quotas,
+            replay_on_join,
+            egress,
+        ]);
+        push_changed!(out, self, other, "", [allow_guest, guest_scopes, service_policies, suspended, read_only]);
+        out
+    }
+
+    /// Fields that only take effect after a restart.
+    pub fn restart_only_changes(&self, other: &TenantConfig) -> Vec<&'static str> {
+        let mut out = Vec::new();
+        push_changed!(out, self, other, "", [limits, introspection, plugin, auto_join_rooms, presence_events, api_key, webhooks, upstreams, http_upstreams, long_poll]);
+        out
+    }
+}
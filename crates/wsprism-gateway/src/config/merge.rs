@@ -0,0 +1,189 @@
+//! Layered configuration (base file + environment overlay).
+//!
+//! Serde fills omitted overlay fields with their defaults, so "set in the
+//! overlay" is approximated as "differs from the default". An overlay therefore
+//! cannot reset a base value back to its default; drop it from the base instead.
+
+use wsprism_core::error::Result;
+
+use super::schema::{
+    GatewayConfig, GatewaySection, HandshakeConfig, SessionPolicy, TenantConfig, TenantLimits,
+    TenantPolicy,
+};
+
+/// Copy each listed field from `src` into `dst` when it differs from `def`.
+macro_rules! take_non_default {
+    ($dst:expr, $src:expr, $def:expr, [$($f:ident),* $(,)?]) => {
+        $( if $src.$f != $def.$f { $dst.$f = $src.$f.clone(); } )*
+    };
+}
+
+impl GatewayConfig {
+    /// Merge `overlay` on top of `base` and validate the result.
+    ///
+    /// - `gateway.*`: overlay fields override base fields.
+    /// - `observability.otlp`, `observability.room_gauges`: an overlay block
+    ///   replaces the base block.
+    /// - `ops`: an overlay that sets any field replaces the base section.
+    /// - `cluster`, `hot_tcp`: an overlay block replaces the base block.
+    /// - `tenants`: overlay tenants with a matching `id` override the base
+    ///   tenant field-by-field; unknown ids are appended.
+    pub fn merge(base: GatewayConfig, overlay: GatewayConfig) -> Result<GatewayConfig> {
+        let mut out = base;
+        out.version = overlay.version;
+        merge_gateway(&mut out.gateway, &overlay.gateway);
+        if overlay.observability.otlp.is_some() {
+            out.observability.otlp = overlay.observability.otlp;
+        }
+        if overlay.observability.room_gauges != Default::default() {
+            out.observability.room_gauges = overlay.observability.room_gauges;
+        }
+        if overlay.ops != Default::default() {
+            out.ops = overlay.ops;
+        }
+        if overlay.cluster.is_some() {
+            out.cluster = overlay.cluster;
+        }
+        if overlay.hot_tcp.is_some() {
+            out.hot_tcp = overlay.hot_tcp;
+        }
+
+        for t in overlay.tenants {
+            match out.tenants.iter_mut().find(|b| b.id == t.id) {
+                Some(b) => merge_tenant(b, &t),
+                None => out.tenants.push(t),
+            }
+        }
+
+        out.validate()?;
+        Ok(out)
+    }
+}
+
+fn merge_gateway(dst: &mut GatewaySection, src: &GatewaySection) {
+    let def = GatewaySection::default();
+    take_non_default!(dst, src, def, [
+        listen,
+        ping_interval_ms,
+        idle_timeout_ms,
+        writer_send_timeout_ms,
+        drain_grace_ms,
+        shutdown_timeout_ms,
+        admin_token,
+        max_label_values_per_metric,
+        cors,
+        metrics,
+        slow_handler_threshold_ms,
+        room_replay_capacity,
+        room_event_capacity,
+        max_session_events,
+        audit,
+        publish,
+        egress,
+    ]);
+
+    let def = HandshakeConfig::default();
+    take_non_default!(dst.handshake_limit, src.handshake_limit, def, [
+        enabled,
+        global_burst,
+        global_rps,
+        per_ip_burst,
+        per_ip_rps,
+        max_ip_entries,
+        limiter_kind,
+        window_ms,
+        state_path,
+        state_ttl_secs,
+    ]);
+}
+
+fn merge_tenant(dst: &mut TenantConfig, src: &TenantConfig) {
+    let def = TenantLimits::default();
+    take_non_default!(dst.limits, src.limits, def, [
+        max_frame_bytes,
+        max_sessions_total,
+        max_rooms_total,
+        max_users_per_room,
+        room_capacity,
+        max_rooms_per_user,
+        max_rooms_per_session,
+        max_messages_per_day,
+    ]);
+
+    let def = TenantPolicy::default();
+    take_non_default!(dst.policy, src.policy, def, [
+        rate_limit_rps,
+        rate_limit_burst,
+        rate_limit_scope,
+        rate_limit_bytes_per_sec,
+        per_session_bytes_per_sec,
+        max_outbound_bytes_per_sec,
+        room_rate_limit_rps,
+        room_rate_limit_burst,
+        ext_allowlist,
+        hot_allowlist,
+        ext_denylist,
+        hot_denylist,
+        hot_error_mode,
+        hot_requires_active_room,
+        allow_server_broadcast,
+        strike_limit,
+        strike_window_ms,
+        max_fanout_parallelism,
+        mode,
+        dry_run,
+        quotas,
+        replay_on_join,
+        egress,
+    ]);
+
+    let def = SessionPolicy::default();
+    take_non_default!(dst.policy.sessions, src.policy.sessions, def, [
+        mode,
+        max_sessions_per_user,
+        on_exceed,
+    ]);
+
+    if src.allow_guest {
+        dst.allow_guest = true;
+    }
+    if src.suspended {
+        dst.suspended = true;
+    }
+    if src.read_only {
+        dst.read_only = true;
+    }
+    if src.presence_events {
+        dst.presence_events = true;
+    }
+    if !src.guest_scopes.is_empty() {
+        dst.guest_scopes = src.guest_scopes.clone();
+    }
+    if !src.auto_join_rooms.is_empty() {
+        dst.auto_join_rooms = src.auto_join_rooms.clone();
+    }
+    if src.api_key.is_some() {
+        dst.api_key = src.api_key.clone();
+    }
+    if src.introspection.is_some() {
+        dst.introspection = src.introspection.clone();
+    }
+    if src.plugin.is_some() {
+        dst.plugin = src.plugin.clone();
+    }
+    if src.webhooks.is_some() {
+        dst.webhooks = src.webhooks.clone();
+    }
+    if src.long_poll.is_some() {
+        dst.long_poll = src.long_poll.clone();
+    }
+    for (svc, sp) in &src.service_policies {
+        dst.service_policies.insert(svc.clone(), sp.clone());
+    }
+    for (svc, u) in &src.upstreams {
+        dst.upstreams.insert(svc.clone(), u.clone());
+    }
+    for (svc, u) in &src.http_upstreams {
+        dst.http_upstreams.insert(svc.clone(), u.clone());
+    }
+}
@@ -3,17 +3,22 @@
 //! This module exposes the typed configuration (parsed from `wsprism.yaml`)
 //! and helpers to load/validate it before wiring the gateway runtime.
 
+pub mod diff;
+pub mod merge;
 pub mod schema;
 
 use std::fs;
 
 use wsprism_core::error::{Result, WsPrismError};
 
-pub use schema::{GatewayConfig, TenantConfig, TenantLimits, TenantPolicy};
+pub use schema::{
+    AuditConfig, AuditSinkKind, ClusterBackend, ClusterConfig, CorsConfig, EgressConfig, EgressSinkKind, GatewayConfig,
+    HotTcpConfig, HttpUpstreamConfig,    KafkaEgressConfig, LongPollConfig, ObservabilitySection, OpsSection, OtlpConfig, PublishConfig, RoomGaugesConfig, ServicePolicy, TenantConfig, TenantLimits, TenantPolicy,
+    UpstreamConfig, WebhookConfig, WebhookEventKind,
+};
 
 pub fn load_from_file(path: &str) -> Result<GatewayConfig> {
-    let s = fs::read_to_string(path)
-        .map_err(|e| WsPrismError::Internal(format!("read config failed: {e}")))?;
+    let s = fs::read_to_string(path)?;
     load_from_str(&s)
 }
 
@@ -23,3 +28,15 @@ pub fn load_from_str(s: &str) -> Result<GatewayConfig> {
     cfg.validate()?;
     Ok(cfg)
 }
+
+/// Load `base_path`, then apply `overlay_path` on top (see `GatewayConfig::merge`).
+///
+/// The overlay is parsed strictly but not validated on its own, so it may omit
+/// `tenants` or carry only a few fields.
+pub fn load_with_overlay(base_path: &str, overlay_path: &str) -> Result<GatewayConfig> {
+    let base = load_from_file(base_path)?;
+    let s = fs::read_to_string(overlay_path)?;
+    let overlay: GatewayConfig = serde_yaml::from_str(&s)
+        .map_err(|e| WsPrismError::BadRequest(format!("invalid overlay yaml: {e}")))?;
+    GatewayConfig::merge(base, overlay)
+}
@@ -2,13 +2,15 @@
 //!
 //! Unknown fields are rejected to avoid silently ignoring operator intent.
 
+use std::collections::HashMap;
+
 use serde::Deserialize;
 use wsprism_core::error::{Result, WsPrismError};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct GatewayConfig {
-    /// Schema version (must be 1).
+    /// Schema version (1 or 2). `service_policies` requires 2.
     pub version: u32,
 
     #[serde(default)]
@@ -16,11 +18,27 @@ pub struct GatewayConfig {
 
     #[serde(default)]
     pub tenants: Vec<TenantConfig>,
+
+    #[serde(default)]
+    pub observability: ObservabilitySection,
+
+    #[serde(default)]
+    pub ops: OpsSection,
+
+    /// Room fanout across replicas via Redis. Requires the `cluster-redis`
+    /// feature; unset = every replica publishes locally only.
+    #[serde(default)]
+    pub cluster: Option<ClusterConfig>,
+
+    /// Raw TCP listener for Hot Lane frames from trusted backends; unset =
+    /// disabled.
+    #[serde(default)]
+    pub hot_tcp: Option<HotTcpConfig>,
 }
 
 impl GatewayConfig {
     pub fn validate(&self) -> Result<()> {
-        if self.version != 1 {
+        if !(1..=2).contains(&self.version) {
             return Err(WsPrismError::UnsupportedVersion);
         }
         if self.tenants.is_empty() {
@@ -31,6 +49,7 @@ impl GatewayConfig {
         {
             use std::collections::HashSet;
             let mut seen = HashSet::new();
+            let mut api_keys = HashSet::new();
             for t in &self.tenants {
                 if t.id.trim().is_empty() {
                     return Err(WsPrismError::BadRequest("tenant.id must not be empty".into()));
@@ -38,16 +57,341 @@ impl GatewayConfig {
                 if !seen.insert(t.id.clone()) {
                     return Err(WsPrismError::BadRequest(format!("duplicate tenant id: {}", t.id)));
                 }
+                // The key alone selects the tenant for `/v1/users/{user}/send`.
+                if t.api_key.as_ref().is_some_and(|k| !api_keys.insert(k.as_str())) {
+                    return Err(WsPrismError::BadRequest(format!("tenant {}: api_key is used by another tenant", t.id)));
+                }
                 t.validate()?;
+                if self.version < 2 && !t.service_policies.is_empty() {
+                    return Err(WsPrismError::BadRequest(format!(
+                        "tenant {}: service_policies requires version: 2",
+                        t.id
+                    )));
+                }
             }
         }
 
         self.gateway.validate()?;
+        self.observability.validate()?;
+        self.ops.validate()?;
+        if let Some(c) = &self.cluster {
+            c.validate()?;
+            // Tenant ids become one subject token.
+            let bad = |c: char| matches!(c, '.' | '*' | '>') || c.is_whitespace();
+            if c.backend == ClusterBackend::Nats {
+                if let Some(t) = self.tenants.iter().find(|t| t.id.contains(bad)) {
+                    return Err(WsPrismError::BadRequest(format!(
+                        "tenant {}: ids used with cluster.backend nats must not contain '.', '*', '>' or whitespace",
+                        t.id
+                    )));
+                }
+            }
+        }
+        if let Some(h) = &self.hot_tcp {
+            h.validate()?;
+            if self.tenants.iter().all(|t| t.api_key.is_none()) {
+                return Err(WsPrismError::BadRequest("hot_tcp requires a tenant with an api_key".into()));
+            }
+        }
+        if self.gateway.egress.sink == EgressSinkKind::None {
+            if let Some(t) = self.tenants.iter().find(|t| !t.policy.egress.is_empty()) {
+                return Err(WsPrismError::BadRequest(format!(
+                    "tenant {}: policy.egress requires gateway.egress.sink",
+                    t.id
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Access control for `/metrics` and `/admin/*` (`ops.*`). `/healthz` and
+/// `/readyz` stay open.
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct OpsSection {
+    /// Bearer token required by `/metrics` and `/admin/*`. Unset = open.
+    #[serde(default)]
+    pub auth_token: Option<String>,
+
+    /// Read the token from this file (trimmed) instead; read at startup.
+    #[serde(default)]
+    pub auth_token_file: Option<String>,
+
+    /// Client networks (`10.0.0.0/8`, `fd00::/8`, or a single address) that
+    /// may skip the token, e.g. in-cluster scrapers.
+    #[serde(default)]
+    pub allow_cidrs: Vec<String>,
+}
+
+/// Shortest accepted `ops.auth_token`.
+pub const MIN_OPS_TOKEN_LEN: usize = 16;
+
+impl OpsSection {
+    pub fn validate(&self) -> Result<()> {
+        if self.auth_token.is_some() && self.auth_token_file.is_some() {
+            return Err(WsPrismError::BadRequest(
+                "ops: set either auth_token or auth_token_file, not both".into(),
+            ));
+        }
+        if let Some(t) = &self.auth_token {
+            check_ops_token(t)?;
+        }
+        if self.auth_token_file.as_deref().is_some_and(|p| p.trim().is_empty()) {
+            return Err(WsPrismError::BadRequest("ops.auth_token_file must not be empty".into()));
+        }
+        if !self.allow_cidrs.is_empty() && self.auth_token.is_none() && self.auth_token_file.is_none() {
+            return Err(WsPrismError::BadRequest(
+                "ops.allow_cidrs has no effect without auth_token or auth_token_file".into(),
+            ));
+        }
+        for c in &self.allow_cidrs {
+            parse_cidr(c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Length check shared by the inline token and the token file.
+pub fn check_ops_token(token: &str) -> Result<()> {
+    if token.len() < MIN_OPS_TOKEN_LEN || token.chars().any(char::is_whitespace) {
+        return Err(WsPrismError::BadRequest(format!(
+            "ops.auth_token must be at least {MIN_OPS_TOKEN_LEN} characters without whitespace"
+        )));
+    }
+    Ok(())
+}
+
+/// `a.b.c.d/n`, `x::/n`, or a bare address (a single-host network).
+pub fn parse_cidr(s: &str) -> Result<ipnet::IpNet> {
+    s.parse::<ipnet::IpNet>()
+        .or_else(|_| s.parse::<std::net::IpAddr>().map(ipnet::IpNet::from))
+        .map_err(|_| WsPrismError::BadRequest(format!("ops.allow_cidrs: invalid network {s:?}")))
+}
+
+/// Pub/sub bridge between gateway replicas (`cluster.*`).
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClusterConfig {
+    /// This replica's id, unique in the cluster; messages it published are
+    /// skipped when they come back.
+    pub node_id: String,
+
+    /// Transport; needs the matching `cluster-*` feature.
+    #[serde(default)]
+    pub backend: ClusterBackend,
+
+    /// `redis://` or `rediss://` URL shared by all replicas (`backend: redis`).
+    #[serde(default)]
+    pub redis_url: Option<String>,
+
+    /// `nats://` or `tls://` URL shared by all replicas (`backend: nats`).
+    #[serde(default)]
+    pub nats_url: Option<String>,
+
+    /// Largest relayed message (payload plus header); bigger room publishes
+    /// are delivered locally only and counted as `too_large` errors.
+    #[serde(default = "default_cluster_max_message_bytes")]
+    pub max_message_bytes: usize,
+
+    /// How often this replica re-announces its room members to the others.
+    #[serde(default = "default_presence_heartbeat_ms")]
+    pub presence_heartbeat_ms: u64,
+
+    /// How long another replica's members count without a heartbeat; must
+    /// exceed `presence_heartbeat_ms`.
+    #[serde(default = "default_presence_ttl_ms")]
+    pub presence_ttl_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterBackend {
+    #[default]
+    Redis,
+    Nats,
+}
+
+impl ClusterBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClusterBackend::Redis => "redis",
+            ClusterBackend::Nats => "nats",
+        }
+    }
+}
+
+fn default_cluster_max_message_bytes() -> usize { 64 * 1024 }
+fn default_presence_heartbeat_ms() -> u64 { 5_000 }
+fn default_presence_ttl_ms() -> u64 { 15_000 }
+
+impl ClusterConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.node_id.is_empty() || self.node_id.chars().any(char::is_whitespace) {
+            return Err(WsPrismError::BadRequest(
+                "cluster.node_id must be non-empty without whitespace".into(),
+            ));
+        }
+        let (url, other, schemes): (_, _, &[&str]) = match self.backend {
+            ClusterBackend::Redis => (&self.redis_url, &self.nats_url, &["redis://", "rediss://"]),
+            ClusterBackend::Nats => (&self.nats_url, &self.redis_url, &["nats://", "tls://"]),
+        };
+        let field = format!("cluster.{}_url", self.backend.as_str());
+        if !url.as_deref().is_some_and(|u| schemes.iter().any(|s| u.starts_with(s))) {
+            return Err(WsPrismError::BadRequest(format!(
+                "{field} must be a {} URL",
+                schemes.iter().map(|s| s.trim_end_matches("//")).collect::<Vec<_>>().join(" or ")
+            )));
+        }
+        if other.is_some() {
+            return Err(WsPrismError::BadRequest(format!(
+                "cluster: only {field} applies to backend {}",
+                self.backend.as_str()
+            )));
+        }
+        if self.max_message_bytes == 0 {
+            return Err(WsPrismError::BadRequest("cluster.max_message_bytes must be > 0".into()));
+        }
+        if !(100..=60_000).contains(&self.presence_heartbeat_ms) {
+            return Err(WsPrismError::BadRequest("cluster.presence_heartbeat_ms must be between 100 and 60000".into()));
+        }
+        if self.presence_ttl_ms <= self.presence_heartbeat_ms || self.presence_ttl_ms > 600_000 {
+            return Err(WsPrismError::BadRequest(
+                "cluster.presence_ttl_ms must exceed presence_heartbeat_ms and be at most 600000".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Length-prefixed Hot Lane frames over plain TCP (`hot_tcp.*`).
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HotTcpConfig {
+    /// Bind address, e.g. `10.0.0.5:7001`.
+    pub listen: String,
+
+    /// Time (ms) a new connection has to send its auth frame.
+    #[serde(default = "default_hot_tcp_auth_timeout_ms")]
+    pub auth_timeout_ms: u64,
+}
+
+fn default_hot_tcp_auth_timeout_ms() -> u64 { 5000 }
+
+impl HotTcpConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.listen.parse::<std::net::SocketAddr>().is_err() {
+            return Err(WsPrismError::BadRequest("hot_tcp.listen must be a valid SocketAddr".into()));
+        }
+        if !(100..=60000).contains(&self.auth_timeout_ms) {
+            return Err(WsPrismError::BadRequest(
+                "hot_tcp.auth_timeout_ms must be between 100 and 60000".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Trace export and sampled-gauge settings (`observability.*`).
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ObservabilitySection {
+    /// OTLP/HTTP trace exporter. Requires the `otel` feature; ignored with a
+    /// warning otherwise.
+    #[serde(default)]
+    pub otlp: Option<OtlpConfig>,
+
+    /// `wsprism_room_members` gauges for the largest rooms.
+    #[serde(default)]
+    pub room_gauges: RoomGaugesConfig,
+}
+
+impl ObservabilitySection {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(o) = &self.otlp {
+            o.validate()?;
+        }
+        self.room_gauges.validate()
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RoomGaugesConfig {
+    /// Rooms per tenant that get a gauge; 0 disables the sampler.
+    #[serde(default = "default_room_gauges_top_n")]
+    pub top_n: usize,
+
+    /// Seconds between samples.
+    #[serde(default = "default_room_gauges_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_room_gauges_top_n() -> usize { 10 }
+fn default_room_gauges_interval_secs() -> u64 { 15 }
+
+impl Default for RoomGaugesConfig {
+    fn default() -> Self {
+        Self { top_n: default_room_gauges_top_n(), interval_secs: default_room_gauges_interval_secs() }
+    }
+}
+
+impl RoomGaugesConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.top_n > 1000 {
+            return Err(WsPrismError::BadRequest("observability.room_gauges.top_n must be at most 1000".into()));
+        }
+        if self.interval_secs == 0 {
+            return Err(WsPrismError::BadRequest("observability.room_gauges.interval_secs must be > 0".into()));
+        }
         Ok(())
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OtlpConfig {
+    /// Collector base URL (`http(s)://host:4318`); spans are posted to
+    /// `<endpoint>/v1/traces`.
+    pub endpoint: String,
+
+    /// Extra request headers (e.g. an auth token for a hosted collector).
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// Fraction of new traces exported (0.0..=1.0). Spans with a remote
+    /// parent follow the parent's sampled flag.
+    #[serde(default = "default_otlp_sample_ratio")]
+    pub sample_ratio: f64,
+
+    /// `service.name` resource attribute.
+    #[serde(default = "default_otlp_service_name")]
+    pub service_name: String,
+}
+
+fn default_otlp_sample_ratio() -> f64 { 1.0 }
+fn default_otlp_service_name() -> String { "wsprism-gateway".into() }
+
+impl OtlpConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.endpoint.starts_with("http://") || self.endpoint.starts_with("https://")) {
+            return Err(WsPrismError::BadRequest(
+                "observability.otlp.endpoint must be an http:// or https:// URL".into(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.sample_ratio) {
+            return Err(WsPrismError::BadRequest(
+                "observability.otlp.sample_ratio must be between 0.0 and 1.0".into(),
+            ));
+        }
+        if self.service_name.trim().is_empty() {
+            return Err(WsPrismError::BadRequest("observability.otlp.service_name must not be empty".into()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct GatewaySection {
     /// Listener address (host:port).
@@ -76,118 +420,1034 @@ pub struct GatewaySection {
     #[serde(default = "default_drain_grace_ms")]
     pub drain_grace_ms: u64,
 
-    // Sprint 5: Handshake Defender Configuration
+    /// Longest wait (ms) from the shutdown signal for the server to stop;
+    /// the process then exits even if connections are still open.
+    #[serde(default = "default_shutdown_timeout_ms")]
+    pub shutdown_timeout_ms: u64,
+
+    // Sprint 5: Handshake Defender Configuration
+    #[serde(default)]
+    pub handshake_limit: HandshakeConfig,
+
+    /// Bearer token for `/admin/v1/*`. Unset = admin API disabled (404).
+    #[serde(default)]
+    pub admin_token: Option<String>,
+
+    /// Distinct values kept per label name of each metric; further values
+    /// are reported as `"_other"`.
+    #[serde(default = "default_max_label_values_per_metric")]
+    pub max_label_values_per_metric: usize,
+
+    /// CORS for the WebSocket upgrade endpoint (`/v1/ws`).
+    #[serde(default)]
+    pub cors: CorsConfig,
+
+    /// Histogram buckets and rendering for `/metrics`.
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    /// Log a warning for any single service dispatch taking at least this
+    /// long (ms). 0 = disabled.
+    #[serde(default)]
+    pub slow_handler_threshold_ms: u64,
+
+    /// Messages kept per room for `policy.replay_on_join` (1..=1000).
+    #[serde(default = "default_room_replay_capacity")]
+    pub room_replay_capacity: usize,
+
+    /// Events buffered per room membership subscription (1..=4096).
+    #[serde(default = "default_room_event_capacity")]
+    pub room_event_capacity: usize,
+
+    /// Lifecycle events kept per session for
+    /// `/admin/v1/sessions/{id}/events` (0..=100000; 0 = none).
+    #[serde(default = "default_max_session_events")]
+    pub max_session_events: usize,
+
+    /// Security audit log (auth failures, kicks, policy closes).
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Rate limit of `POST /v1/publish`, per tenant.
+    #[serde(default)]
+    pub publish: PublishConfig,
+
+    /// Archival of Ext messages matching each tenant's `policy.egress`.
+    #[serde(default)]
+    pub egress: EgressConfig,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PublishConfig {
+    /// Publish requests per second each tenant's backend may make.
+    #[serde(default = "default_publish_rps")]
+    pub rate_limit_rps: u32,
+
+    /// Burst capacity of that limiter.
+    #[serde(default = "default_publish_burst")]
+    pub rate_limit_burst: u32,
+}
+
+fn default_publish_rps() -> u32 { 100 }
+fn default_publish_burst() -> u32 { 200 }
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        Self { rate_limit_rps: default_publish_rps(), rate_limit_burst: default_publish_burst() }
+    }
+}
+
+/// Where audit events go. Off (`sink: none`) by default.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AuditConfig {
+    #[serde(default)]
+    pub sink: AuditSinkKind,
+
+    /// JSONL file for `sink: file`.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Rotate once the file reaches this size.
+    #[serde(default = "default_audit_max_file_bytes")]
+    pub max_file_bytes: u64,
+
+    /// Rotated files kept (`<path>.1` is the newest).
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+
+    /// Events buffered for the writer; further events are dropped and counted.
+    #[serde(default = "default_audit_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSinkKind {
+    #[default]
+    None,
+    File,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            sink: AuditSinkKind::None,
+            path: None,
+            max_file_bytes: default_audit_max_file_bytes(),
+            max_files: default_audit_max_files(),
+            queue_capacity: default_audit_queue_capacity(),
+        }
+    }
+}
+
+fn default_audit_max_file_bytes() -> u64 { 10 * 1024 * 1024 }
+fn default_audit_max_files() -> usize { 5 }
+fn default_audit_queue_capacity() -> usize { 1024 }
+
+impl AuditConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.sink == AuditSinkKind::File && self.path.as_deref().is_none_or(|p| p.trim().is_empty()) {
+            return Err(WsPrismError::BadRequest("gateway.audit.path is required for sink: file".into()));
+        }
+        if self.max_file_bytes < 1024 {
+            return Err(WsPrismError::BadRequest("gateway.audit.max_file_bytes must be >= 1024".into()));
+        }
+        if !(1..=100).contains(&self.max_files) {
+            return Err(WsPrismError::BadRequest("gateway.audit.max_files must be between 1 and 100".into()));
+        }
+        if self.queue_capacity == 0 {
+            return Err(WsPrismError::BadRequest("gateway.audit.queue_capacity must be > 0".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Where `policy.egress` matches are archived. Off (`sink: none`) by default.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EgressConfig {
+    /// Backend; needs the matching `egress-*` feature.
+    #[serde(default)]
+    pub sink: EgressSinkKind,
+
+    /// Settings for `sink: kafka`.
+    #[serde(default)]
+    pub kafka: Option<KafkaEgressConfig>,
+
+    /// Records buffered for the writer; further records are dropped and counted.
+    #[serde(default = "default_egress_queue_capacity")]
+    pub queue_capacity: usize,
+
+    /// Time allowed on shutdown to hand queued records to the sink and flush it.
+    #[serde(default = "default_egress_flush_timeout_ms")]
+    pub flush_timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EgressSinkKind {
+    #[default]
+    None,
+    Kafka,
+}
+
+impl EgressSinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EgressSinkKind::None => "none",
+            EgressSinkKind::Kafka => "kafka",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct KafkaEgressConfig {
+    /// Bootstrap brokers (`host:port`).
+    pub brokers: Vec<String>,
+
+    /// Topic receiving the records.
+    pub topic: String,
+
+    /// Extra librdkafka producer settings (e.g. `acks`, `compression.type`,
+    /// `security.protocol`).
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+impl Default for EgressConfig {
+    fn default() -> Self {
+        Self {
+            sink: EgressSinkKind::None,
+            kafka: None,
+            queue_capacity: default_egress_queue_capacity(),
+            flush_timeout_ms: default_egress_flush_timeout_ms(),
+        }
+    }
+}
+
+fn default_egress_queue_capacity() -> usize { 10_000 }
+fn default_egress_flush_timeout_ms() -> u64 { 5000 }
+
+impl EgressConfig {
+    pub fn validate(&self) -> Result<()> {
+        match (self.sink, &self.kafka) {
+            (EgressSinkKind::Kafka, None) => {
+                return Err(WsPrismError::BadRequest("gateway.egress.kafka is required for sink: kafka".into()));
+            }
+            (EgressSinkKind::None, Some(_)) => {
+                return Err(WsPrismError::BadRequest("gateway.egress.kafka is set but gateway.egress.sink is none".into()));
+            }
+            (_, Some(k)) => {
+                if k.brokers.is_empty() || k.brokers.iter().any(|b| b.trim().is_empty()) {
+                    return Err(WsPrismError::BadRequest("gateway.egress.kafka.brokers must list at least one broker".into()));
+                }
+                if k.topic.trim().is_empty() {
+                    return Err(WsPrismError::BadRequest("gateway.egress.kafka.topic must not be empty".into()));
+                }
+                if k.properties.contains_key("bootstrap.servers") {
+                    return Err(WsPrismError::BadRequest(
+                        "gateway.egress.kafka.properties must not set bootstrap.servers; use brokers".into(),
+                    ));
+                }
+            }
+            (EgressSinkKind::None, None) => {}
+        }
+        if self.queue_capacity == 0 {
+            return Err(WsPrismError::BadRequest("gateway.egress.queue_capacity must be > 0".into()));
+        }
+        if !(100..=60000).contains(&self.flush_timeout_ms) {
+            return Err(WsPrismError::BadRequest(
+                "gateway.egress.flush_timeout_ms must be between 100 and 60000".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Histogram settings. Unset bucket lists use the built-in defaults.
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Dispatch latency bucket bounds in microseconds (ascending). Rendered
+    /// in seconds.
+    #[serde(default)]
+    pub dispatch_buckets_micros: Option<Vec<u64>>,
+
+    /// Frame size bucket bounds in bytes (ascending).
+    #[serde(default)]
+    pub frame_buckets_bytes: Option<Vec<u64>>,
+
+    /// Render the dispatch histogram as `wsprism_dispatch_duration_micros`
+    /// with integer microsecond bounds, as before. Removed in the next release.
+    #[serde(default)]
+    pub legacy_histogram_names: bool,
+
+    /// Label sets kept per metric (default 10000). New sets past the cap are
+    /// recorded with every label set to `_overflow`.
+    #[serde(default)]
+    pub max_series_per_metric: Option<usize>,
+
+    /// Drop series not written for this long (default 3600). Gauges are only
+    /// dropped at 0. 0 = never.
+    #[serde(default)]
+    pub series_ttl_secs: Option<u64>,
+}
+
+/// Most bounds a histogram may have.
+const MAX_HISTOGRAM_BUCKETS: usize = 32;
+
+impl MetricsConfig {
+    pub fn validate(&self) -> Result<()> {
+        let lists = [
+            ("dispatch_buckets_micros", &self.dispatch_buckets_micros),
+            ("frame_buckets_bytes", &self.frame_buckets_bytes),
+        ];
+        for (field, bounds) in lists {
+            let Some(bounds) = bounds else { continue };
+            let ascending = bounds.windows(2).all(|w| w[0] < w[1]);
+            if bounds.is_empty() || bounds.len() > MAX_HISTOGRAM_BUCKETS || bounds[0] == 0 || !ascending {
+                return Err(WsPrismError::BadRequest(format!(
+                    "gateway.metrics.{field} must be 1 to {MAX_HISTOGRAM_BUCKETS} strictly ascending values > 0"
+                )));
+            }
+        }
+        if self.max_series_per_metric == Some(0) {
+            return Err(WsPrismError::BadRequest("gateway.metrics.max_series_per_metric must be > 0".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Cross-origin access to `/v1/ws`. No allowed origins = no CORS headers.
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Exact origins (e.g. `https://app.example.com`), or `"*"` for any.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+
+    /// Send `Access-Control-Allow-Credentials: true`. Not allowed with `"*"`.
+    #[serde(default)]
+    pub allow_credentials: bool,
+}
+
+impl CorsConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_origins.is_empty()
+    }
+
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.allow_credentials && self.allows_any_origin() {
+            return Err(WsPrismError::BadRequest(
+                "gateway.cors: allow_credentials cannot be combined with allowed_origins \"*\"".into(),
+            ));
+        }
+        for o in &self.allowed_origins {
+            if o.trim().is_empty() || !o.is_ascii() || o.chars().any(char::is_control) {
+                return Err(WsPrismError::BadRequest(format!(
+                    "gateway.cors.allowed_origins: invalid origin {o:?}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HandshakeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Global limiter: burst capacity.
+    #[serde(default = "default_hs_global_burst")]
+    pub global_burst: u32,
+    /// Global limiter: refill rate per sec.
+    #[serde(default = "default_hs_global_rps")]
+    pub global_rps: u32,
+
+    /// Per-IP limiter: burst capacity.
+    #[serde(default = "default_hs_ip_burst")]
+    pub per_ip_burst: u32,
+    /// Per-IP limiter: refill rate per sec.
+    #[serde(default = "default_hs_ip_rps")]
+    pub per_ip_rps: u32,
+
+    /// Clean up per-ip map opportunistically when it grows too big.
+    #[serde(default = "default_hs_max_entries")]
+    pub max_ip_entries: usize,
+
+    /// Limiter algorithm for both the global and per-IP checks.
+    #[serde(default)]
+    pub limiter_kind: HandshakeLimiterKind,
+
+    /// `sliding_window` only: window length. Each limiter admits
+    /// `rps * window_ms / 1000` handshakes in any window; bursts do not apply.
+    #[serde(default = "default_hs_window_ms")]
+    pub window_ms: u64,
+
+    /// File the leaky-bucket state is saved to on shutdown and restored
+    /// from on startup, so a restart does not hand every IP a full bucket.
+    #[serde(default)]
+    pub state_path: Option<String>,
+
+    /// A state file older than this is ignored at startup.
+    #[serde(default = "default_hs_state_ttl_secs")]
+    pub state_ttl_secs: u64,
+}
+
+/// Handshake limiter algorithm.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HandshakeLimiterKind {
+    /// Token bucket: `*_burst` up front, refilled at `*_rps`.
+    #[default]
+    LeakyBucket,
+    /// Count of handshakes within the trailing `window_ms`.
+    SlidingWindow,
+}
+
+impl Default for HandshakeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            global_burst: 200,
+            global_rps: 100,
+            per_ip_burst: 50,
+            per_ip_rps: 10,
+            max_ip_entries: 50_000,
+            limiter_kind: HandshakeLimiterKind::LeakyBucket,
+            window_ms: default_hs_window_ms(),
+            state_path: None,
+            state_ttl_secs: default_hs_state_ttl_secs(),
+        }
+    }
+}
+
+// Handshake defaults
+fn default_hs_global_burst() -> u32 { 200 }
+fn default_hs_global_rps() -> u32 { 100 }
+fn default_hs_ip_burst() -> u32 { 50 }
+fn default_hs_ip_rps() -> u32 { 10 }
+fn default_hs_max_entries() -> usize { 50_000 }
+fn default_hs_window_ms() -> u64 { 1000 }
+fn default_hs_state_ttl_secs() -> u64 { 300 }
+
+impl Default for GatewaySection {
+    fn default() -> Self {
+        Self {
+            listen: default_listen(),
+            ping_interval_ms: default_ping_interval_ms(),
+            idle_timeout_ms: default_idle_timeout_ms(),
+            writer_send_timeout_ms: default_writer_send_timeout_ms(),
+            drain_grace_ms: default_drain_grace_ms(),
+            shutdown_timeout_ms: default_shutdown_timeout_ms(),
+            handshake_limit: HandshakeConfig::default(),
+            admin_token: None,
+            max_label_values_per_metric: default_max_label_values_per_metric(),
+            cors: CorsConfig::default(),
+            metrics: MetricsConfig::default(),
+            slow_handler_threshold_ms: 0,
+            room_replay_capacity: default_room_replay_capacity(),
+            room_event_capacity: default_room_event_capacity(),
+            max_session_events: default_max_session_events(),
+            audit: AuditConfig::default(),
+            publish: PublishConfig::default(),
+            egress: EgressConfig::default(),
+        }
+    }
+}
+
+impl GatewaySection {
+    /// Range checks for gateway-level settings.
+    ///
+    /// Envelope strictness is not configurable here: clients sending minimal
+    /// Ext envelopes (only `svc` and `type`) are always accepted.
+    pub fn validate(&self) -> Result<()> {
+        if !(5000..=120000).contains(&self.ping_interval_ms) {
+            return Err(WsPrismError::BadRequest(
+                "gateway.ping_interval_ms must be between 5000 and 120000".into(),
+            ));
+        }
+        if !(10000..=600000).contains(&self.idle_timeout_ms) {
+            return Err(WsPrismError::BadRequest(
+                "gateway.idle_timeout_ms must be between 10000 and 600000".into(),
+            ));
+        }
+        if self.idle_timeout_ms <= self.ping_interval_ms {
+            return Err(WsPrismError::BadRequest(
+                "gateway.idle_timeout_ms must be greater than ping_interval_ms".into(),
+            ));
+        }
+        if !(50..=60000).contains(&self.writer_send_timeout_ms) {
+            return Err(WsPrismError::BadRequest(
+                "gateway.writer_send_timeout_ms must be between 50 and 60000".into(),
+            ));
+        }
+        if self.drain_grace_ms > 600000 {
+            return Err(WsPrismError::BadRequest(
+                "gateway.drain_grace_ms must be <= 600000".into(),
+            ));
+        }
+        if !(1000..=600000).contains(&self.shutdown_timeout_ms) {
+            return Err(WsPrismError::BadRequest(
+                "gateway.shutdown_timeout_ms must be between 1000 and 600000".into(),
+            ));
+        }
+        if self.max_label_values_per_metric == 0 {
+            return Err(WsPrismError::BadRequest(
+                "gateway.max_label_values_per_metric must be > 0".into(),
+            ));
+        }
+        if self.admin_token.as_deref().is_some_and(|t| t.trim().is_empty()) {
+            return Err(WsPrismError::BadRequest(
+                "gateway.admin_token must not be empty when set".into(),
+            ));
+        }
+        if self.handshake_limit.limiter_kind == HandshakeLimiterKind::SlidingWindow
+            && !(100..=60000).contains(&self.handshake_limit.window_ms)
+        {
+            return Err(WsPrismError::BadRequest(
+                "gateway.handshake_limit.window_ms must be between 100 and 60000".into(),
+            ));
+        }
+        if self.handshake_limit.state_ttl_secs == 0 {
+            return Err(WsPrismError::BadRequest(
+                "gateway.handshake_limit.state_ttl_secs must be > 0".into(),
+            ));
+        }
+        if self.slow_handler_threshold_ms > 600000 {
+            return Err(WsPrismError::BadRequest(
+                "gateway.slow_handler_threshold_ms must be <= 600000".into(),
+            ));
+        }
+        if !(1..=1000).contains(&self.room_replay_capacity) {
+            return Err(WsPrismError::BadRequest(
+                "gateway.room_replay_capacity must be between 1 and 1000".into(),
+            ));
+        }
+        if !(1..=4096).contains(&self.room_event_capacity) {
+            return Err(WsPrismError::BadRequest(
+                "gateway.room_event_capacity must be between 1 and 4096".into(),
+            ));
+        }
+        if self.max_session_events > 100_000 {
+            return Err(WsPrismError::BadRequest(
+                "gateway.max_session_events must be <= 100000".into(),
+            ));
+        }
+        self.metrics.validate()?;
+        self.audit.validate()?;
+        self.egress.validate()?;
+        if self.publish.rate_limit_rps == 0 || self.publish.rate_limit_burst == 0 {
+            return Err(WsPrismError::BadRequest(
+                "gateway.publish.rate_limit_rps and rate_limit_burst must be > 0".into(),
+            ));
+        }
+        self.cors.validate()
+    }
+}
+
+fn default_listen() -> String { "0.0.0.0:8080".into() }
+fn default_ping_interval_ms() -> u64 { 20000 }
+fn default_idle_timeout_ms() -> u64 { 60000 }
+fn default_writer_send_timeout_ms() -> u64 { 1500 }
+fn default_drain_grace_ms() -> u64 { 2000 }
+fn default_shutdown_timeout_ms() -> u64 { 30000 }
+fn default_max_label_values_per_metric() -> usize { crate::obs::metrics::DEFAULT_MAX_LABEL_VALUES }
+fn default_room_replay_capacity() -> usize { crate::realtime::core::DEFAULT_REPLAY_CAPACITY }
+fn default_room_event_capacity() -> usize { crate::realtime::core::DEFAULT_ROOM_EVENT_CAPACITY }
+fn default_max_session_events() -> usize { crate::realtime::core::DEFAULT_MAX_SESSION_EVENTS }
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    /// Tenant identifier (namespaces policy and runtime state).
+    pub id: String,
+
+    #[serde(default)]
+    pub limits: TenantLimits,
+
+    /// Sprint 2+: policy controls (strict by default).
+    #[serde(default)]
+    pub policy: TenantPolicy,
+
+    /// Allow connections without a ticket (anonymous spectators).
+    /// Guests get a generated `guest-<id>` user id.
+    #[serde(default)]
+    pub allow_guest: bool,
+
+    /// Ext lane entries ("svc:type" / "svc:*") a guest may use, applied on
+    /// top of `policy.ext_allowlist`. Empty = guests cannot send anything.
+    #[serde(default)]
+    pub guest_scopes: Vec<String>,
+
+    /// OAuth2 token introspection backend for this tenant.
+    /// Requires the `oidc-introspection` feature; otherwise startup fails.
+    #[serde(default)]
+    pub introspection: Option<IntrospectionConfig>,
+
+    /// WebAssembly policy plugin run after the built-in checks.
+    /// Requires the `wasm-plugins` feature; otherwise startup fails.
+    #[serde(default)]
+    pub plugin: Option<PluginConfig>,
+
+    /// Per-service overrides keyed by `svc` (schema version 2). Unset fields
+    /// fall back to the tenant-level `policy` / `limits`.
+    #[serde(default)]
+    pub service_policies: HashMap<String, ServicePolicy>,
+
+    /// Maintenance switch: new upgrades get 503 and, on reload, connected
+    /// sessions receive `sys.goaway` and are closed.
+    #[serde(default)]
+    pub suspended: bool,
+
+    /// Only `room:*` and `sys:*` are accepted; all other Ext and Hot traffic
+    /// is rejected with `NOT_ALLOWED`. Sessions stay connected.
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Rooms every session joins on connect, each confirmed with
+    /// `sys.joined`. The session's active room is left unset. Applied to new
+    /// sessions only; changes need a restart.
+    #[serde(default)]
+    pub auto_join_rooms: Vec<String>,
+
+    /// Send `sys.presence` to room members when a user joins or leaves a
+    /// room. Off by default: busy rooms generate a lot of churn. Changes
+    /// need a restart.
+    #[serde(default)]
+    pub presence_events: bool,
+
+    /// Bearer key for `POST /v1/publish`. Unset = backend publishing is
+    /// disabled for this tenant. Changes need a restart.
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    /// Session lifecycle notifications POSTed to the tenant's backend.
+    /// Requires the `webhooks` feature; otherwise startup fails. Changes
+    /// need a restart.
+    #[serde(default)]
+    pub webhooks: Option<WebhookConfig>,
+
+    /// Ext lane services forwarded to a gRPC backend, keyed by `svc`.
+    /// Requires the `upstream-grpc` feature; otherwise startup fails.
+    /// Changes need a restart.
+    #[serde(default)]
+    pub upstreams: HashMap<String, UpstreamConfig>,
+
+    /// Ext lane services POSTed to an HTTP backend, keyed by `svc`.
+    /// Requires the `upstream-http` feature; otherwise startup fails.
+    /// Changes need a restart.
     #[serde(default)]
-    pub handshake_limit: HandshakeConfig,
+    pub http_upstreams: HashMap<String, HttpUpstreamConfig>,
+
+    /// Long-polling fallback (`/v1/poll`) for clients that cannot keep a
+    /// WebSocket open. Unset = disabled for this tenant. Changes need a
+    /// restart.
+    #[serde(default)]
+    pub long_poll: Option<LongPollConfig>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// Shortest accepted `api_key`.
+const MIN_API_KEY_LEN: usize = 16;
+
+/// Longest room name accepted in `auto_join_rooms`.
+const MAX_AUTO_JOIN_ROOM_LEN: usize = 64;
+
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
-pub struct HandshakeConfig {
+pub struct ServicePolicy {
+    /// Inbound rate for this service, applied with the tenant's
+    /// `rate_limit_scope` in place of the tenant-level limiter.
     #[serde(default)]
-    pub enabled: bool,
+    pub rate_limit_rps: Option<u32>,
 
-    /// Global limiter: burst capacity.
-    #[serde(default = "default_hs_global_burst")]
-    pub global_burst: u32,
-    /// Global limiter: refill rate per sec.
-    #[serde(default = "default_hs_global_rps")]
-    pub global_rps: u32,
-
-    /// Per-IP limiter: burst capacity.
-    #[serde(default = "default_hs_ip_burst")]
-    pub per_ip_burst: u32,
-    /// Per-IP limiter: refill rate per sec.
-    #[serde(default = "default_hs_ip_rps")]
-    pub per_ip_rps: u32,
+    /// Burst capacity for this service's bucket.
+    #[serde(default)]
+    pub rate_limit_burst: Option<u32>,
 
-    /// Clean up per-ip map opportunistically when it grows too big.
-    #[serde(default = "default_hs_max_entries")]
-    pub max_ip_entries: usize,
+    /// Frame size cap for this service (bytes).
+    #[serde(default)]
+    pub max_frame_bytes: Option<usize>,
 }
 
-impl Default for HandshakeConfig {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            global_burst: 200,
-            global_rps: 100,
-            per_ip_burst: 50,
-            per_ip_rps: 10,
-            max_ip_entries: 50_000,
+impl ServicePolicy {
+    pub fn validate(&self, svc: &str) -> Result<()> {
+        if self.rate_limit_rps == Some(0) || self.rate_limit_burst == Some(0) {
+            return Err(WsPrismError::BadRequest(format!(
+                "service_policies.{svc}: rate_limit_rps and rate_limit_burst must be > 0"
+            )));
+        }
+        if self.max_frame_bytes == Some(0) {
+            return Err(WsPrismError::BadRequest(format!(
+                "service_policies.{svc}: max_frame_bytes must be > 0"
+            )));
         }
+        Ok(())
     }
 }
 
-// Handshake defaults
-fn default_hs_global_burst() -> u32 { 200 }
-fn default_hs_global_rps() -> u32 { 100 }
-fn default_hs_ip_burst() -> u32 { 50 }
-fn default_hs_ip_rps() -> u32 { 10 }
-fn default_hs_max_entries() -> usize { 50_000 }
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Path to the `.wasm` module.
+    pub path: String,
 
-impl Default for GatewaySection {
-    fn default() -> Self {
-        Self {
-            listen: default_listen(),
-            ping_interval_ms: default_ping_interval_ms(),
-            idle_timeout_ms: default_idle_timeout_ms(),
-            writer_send_timeout_ms: default_writer_send_timeout_ms(),
-            drain_grace_ms: default_drain_grace_ms(),
-            handshake_limit: HandshakeConfig::default(),
+    /// Fuel (roughly: wasm instructions) granted per call.
+    #[serde(default = "default_plugin_fuel")]
+    pub fuel: u64,
+
+    /// Wall-clock budget per call (ms).
+    #[serde(default = "default_plugin_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// What to do when the plugin traps, times out or misbehaves.
+    #[serde(default = "default_plugin_on_error")]
+    pub on_error: PluginErrorMode,
+}
+
+/// Outcome applied when a plugin call fails.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginErrorMode {
+    /// Fail closed: drop the frame.
+    Drop,
+    /// Fail open: let the frame through.
+    Pass,
+}
+
+fn default_plugin_fuel() -> u64 { 1_000_000 }
+fn default_plugin_timeout_ms() -> u64 { 20 }
+fn default_plugin_on_error() -> PluginErrorMode { PluginErrorMode::Drop }
+
+impl PluginConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.path.trim().is_empty() {
+            return Err(WsPrismError::BadRequest("plugin.path must not be empty".into()));
+        }
+        if self.fuel == 0 {
+            return Err(WsPrismError::BadRequest("plugin.fuel must be > 0".into()));
+        }
+        if !(1..=1000).contains(&self.timeout_ms) {
+            return Err(WsPrismError::BadRequest(
+                "plugin.timeout_ms must be between 1 and 1000".into(),
+            ));
         }
+        Ok(())
     }
 }
 
-impl GatewaySection {
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct IntrospectionConfig {
+    /// RFC 7662 introspection endpoint.
+    pub url: String,
+    pub client_id: String,
+    pub client_secret: String,
+
+    /// Response field used as the user id.
+    #[serde(default = "default_introspection_user_claim")]
+    pub user_claim: String,
+
+    /// TTL for cached `active=true` results (ms).
+    #[serde(default = "default_introspection_cache_ttl_ms")]
+    pub cache_ttl_ms: u64,
+
+    /// TTL for cached `active=false` results (ms).
+    #[serde(default = "default_introspection_negative_ttl_ms")]
+    pub negative_cache_ttl_ms: u64,
+
+    /// Per-request timeout (ms).
+    #[serde(default = "default_introspection_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Cache size bound; expired entries are swept when exceeded.
+    #[serde(default = "default_introspection_max_cache_entries")]
+    pub max_cache_entries: usize,
+}
+
+fn default_introspection_user_claim() -> String { "sub".into() }
+fn default_introspection_cache_ttl_ms() -> u64 { 30_000 }
+fn default_introspection_negative_ttl_ms() -> u64 { 5_000 }
+fn default_introspection_timeout_ms() -> u64 { 2_000 }
+fn default_introspection_max_cache_entries() -> usize { 10_000 }
+
+impl IntrospectionConfig {
     pub fn validate(&self) -> Result<()> {
-        if !(5000..=120000).contains(&self.ping_interval_ms) {
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
             return Err(WsPrismError::BadRequest(
-                "gateway.ping_interval_ms must be between 5000 and 120000".into(),
+                "introspection.url must be an http(s) URL".into(),
             ));
         }
-        if !(10000..=600000).contains(&self.idle_timeout_ms) {
+        if self.client_id.is_empty() || self.user_claim.is_empty() {
             return Err(WsPrismError::BadRequest(
-                "gateway.idle_timeout_ms must be between 10000 and 600000".into(),
+                "introspection.client_id and user_claim must not be empty".into(),
             ));
         }
-        if self.idle_timeout_ms <= self.ping_interval_ms {
+        if !(100..=30000).contains(&self.timeout_ms) {
             return Err(WsPrismError::BadRequest(
-                "gateway.idle_timeout_ms must be greater than ping_interval_ms".into(),
+                "introspection.timeout_ms must be between 100 and 30000".into(),
             ));
         }
-        if !(50..=60000).contains(&self.writer_send_timeout_ms) {
+        Ok(())
+    }
+}
+
+/// Tenant `webhooks` section.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Endpoint receiving batches (http or https).
+    pub url: String,
+
+    /// HMAC-SHA256 key for the `X-Wsprism-Signature` header (>= 16 chars).
+    pub secret: String,
+
+    /// Events sent; all of them by default.
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<WebhookEventKind>,
+
+    /// Most events per POST.
+    #[serde(default = "default_webhook_batch_max")]
+    pub batch_max: usize,
+
+    /// How long the first event of a batch waits for more (ms).
+    #[serde(default = "default_webhook_batch_interval_ms")]
+    pub batch_interval_ms: u64,
+
+    /// POST attempts per batch before it is dropped.
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+
+    /// Wait before the first retry (ms); doubles per attempt, up to 30 s.
+    #[serde(default = "default_webhook_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+
+    /// Per-request timeout (ms).
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Events buffered while batches are sent or retried; further events
+    /// are dropped and counted.
+    #[serde(default = "default_webhook_backlog")]
+    pub backlog: usize,
+}
+
+#[derive(Debug, Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    Connect,
+    Disconnect,
+    RoomJoin,
+    RoomLeave,
+}
+
+fn default_webhook_events() -> Vec<WebhookEventKind> {
+    vec![
+        WebhookEventKind::Connect,
+        WebhookEventKind::Disconnect,
+        WebhookEventKind::RoomJoin,
+        WebhookEventKind::RoomLeave,
+    ]
+}
+fn default_webhook_batch_max() -> usize { 100 }
+fn default_webhook_batch_interval_ms() -> u64 { 1000 }
+fn default_webhook_max_attempts() -> u32 { 5 }
+fn default_webhook_retry_backoff_ms() -> u64 { 500 }
+fn default_webhook_timeout_ms() -> u64 { 5000 }
+fn default_webhook_backlog() -> usize { 10_000 }
+
+/// Shortest accepted `webhooks.secret`.
+const MIN_WEBHOOK_SECRET_LEN: usize = 16;
+
+impl WebhookConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(WsPrismError::BadRequest("webhooks.url must be an http(s) URL".into()));
+        }
+        if self.secret.len() < MIN_WEBHOOK_SECRET_LEN {
+            return Err(WsPrismError::BadRequest(format!(
+                "webhooks.secret must be at least {MIN_WEBHOOK_SECRET_LEN} characters"
+            )));
+        }
+        if self.events.is_empty() {
+            return Err(WsPrismError::BadRequest("webhooks.events must not be empty".into()));
+        }
+        if !(1..=1000).contains(&self.batch_max) {
+            return Err(WsPrismError::BadRequest("webhooks.batch_max must be between 1 and 1000".into()));
+        }
+        if !(10..=60000).contains(&self.batch_interval_ms) {
             return Err(WsPrismError::BadRequest(
-                "gateway.writer_send_timeout_ms must be between 50 and 60000".into(),
+                "webhooks.batch_interval_ms must be between 10 and 60000".into(),
             ));
         }
-        if self.drain_grace_ms > 600000 {
+        if !(1..=20).contains(&self.max_attempts) {
+            return Err(WsPrismError::BadRequest("webhooks.max_attempts must be between 1 and 20".into()));
+        }
+        if !(10..=60000).contains(&self.retry_backoff_ms) {
             return Err(WsPrismError::BadRequest(
-                "gateway.drain_grace_ms must be <= 600000".into(),
+                "webhooks.retry_backoff_ms must be between 10 and 60000".into(),
             ));
         }
+        if !(100..=30000).contains(&self.timeout_ms) {
+            return Err(WsPrismError::BadRequest("webhooks.timeout_ms must be between 100 and 30000".into()));
+        }
+        if self.backlog == 0 {
+            return Err(WsPrismError::BadRequest("webhooks.backlog must be > 0".into()));
+        }
         Ok(())
     }
 }
 
-fn default_listen() -> String { "0.0.0.0:8080".into() }
-fn default_ping_interval_ms() -> u64 { 20000 }
-fn default_idle_timeout_ms() -> u64 { 60000 }
-fn default_writer_send_timeout_ms() -> u64 { 1500 }
-fn default_drain_grace_ms() -> u64 { 2000 }
+/// One tenant `upstreams` entry.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamConfig {
+    /// gRPC endpoint serving `wsprism.upstream.v1.Upstream` (plaintext
+    /// HTTP/2, `http://host:port`).
+    pub endpoint: String,
+
+    /// Deadline for one call, including the streamed replies (ms). Defaults
+    /// to the reliable reply budget (`REPLY_TIMEOUT_MS`).
+    #[serde(default = "default_upstream_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Consecutive failed calls that open the circuit.
+    #[serde(default = "default_upstream_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// How long an open circuit fails calls without contacting the
+    /// endpoint (ms).
+    #[serde(default = "default_upstream_cooldown_ms")]
+    pub cooldown_ms: u64,
+}
+
+fn default_upstream_timeout_ms() -> u64 { crate::realtime::types::REPLY_TIMEOUT_MS }
+fn default_upstream_failure_threshold() -> u32 { 5 }
+fn default_upstream_cooldown_ms() -> u64 { 10_000 }
+
+impl UpstreamConfig {
+    pub fn validate(&self, svc: &str) -> Result<()> {
+        let rest = self.endpoint.strip_prefix("http://").unwrap_or_default();
+        if rest.is_empty() || rest.contains(char::is_whitespace) {
+            return Err(WsPrismError::BadRequest(format!(
+                "upstreams.{svc}.endpoint must be an http://host:port URL"
+            )));
+        }
+        if !(10..=60000).contains(&self.timeout_ms) {
+            return Err(WsPrismError::BadRequest(format!(
+                "upstreams.{svc}.timeout_ms must be between 10 and 60000"
+            )));
+        }
+        if !(1..=100).contains(&self.failure_threshold) {
+            return Err(WsPrismError::BadRequest(format!(
+                "upstreams.{svc}.failure_threshold must be between 1 and 100"
+            )));
+        }
+        if !(100..=600_000).contains(&self.cooldown_ms) {
+            return Err(WsPrismError::BadRequest(format!(
+                "upstreams.{svc}.cooldown_ms must be between 100 and 600000"
+            )));
+        }
+        Ok(())
+    }
+}
 
-#[derive(Debug, Deserialize, Clone)]
+/// One tenant `http_upstreams` entry.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
-pub struct TenantConfig {
-    /// Tenant identifier (namespaces policy and runtime state).
-    pub id: String,
+pub struct HttpUpstreamConfig {
+    /// Endpoint receiving the envelopes (http or https).
+    pub url: String,
 
-    #[serde(default)]
-    pub limits: TenantLimits,
+    /// Per-request timeout (ms).
+    #[serde(default = "default_upstream_timeout_ms")]
+    pub timeout_ms: u64,
 
-    /// Sprint 2+: policy controls (strict by default).
-    #[serde(default)]
-    pub policy: TenantPolicy,
+    /// Largest response body accepted (bytes).
+    #[serde(default = "default_http_upstream_max_response_bytes")]
+    pub max_response_bytes: usize,
+
+    /// Requests per message when the backend answers 5xx. Only messages
+    /// flagged `FLAG_WANT_ACK` are retried.
+    #[serde(default = "default_http_upstream_max_attempts")]
+    pub max_attempts: u32,
+}
+
+fn default_http_upstream_max_response_bytes() -> usize { 64 * 1024 }
+fn default_http_upstream_max_attempts() -> u32 { 3 }
+
+impl HttpUpstreamConfig {
+    pub fn validate(&self, svc: &str) -> Result<()> {
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(WsPrismError::BadRequest(format!(
+                "http_upstreams.{svc}.url must be an http(s) URL"
+            )));
+        }
+        if !(10..=60000).contains(&self.timeout_ms) {
+            return Err(WsPrismError::BadRequest(format!(
+                "http_upstreams.{svc}.timeout_ms must be between 10 and 60000"
+            )));
+        }
+        if !(1..=16 * 1024 * 1024).contains(&self.max_response_bytes) {
+            return Err(WsPrismError::BadRequest(format!(
+                "http_upstreams.{svc}.max_response_bytes must be between 1 and 16777216"
+            )));
+        }
+        if !(1..=10).contains(&self.max_attempts) {
+            return Err(WsPrismError::BadRequest(format!(
+                "http_upstreams.{svc}.max_attempts must be between 1 and 10"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Tenant `long_poll` section.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LongPollConfig {
+    /// Longest a `GET /v1/poll` waits for messages (ms).
+    #[serde(default = "default_long_poll_wait_ms")]
+    pub wait_ms: u64,
+
+    /// A session with no request for this long is closed (ms).
+    #[serde(default = "default_long_poll_session_ttl_ms")]
+    pub session_ttl_ms: u64,
+
+    /// Messages held until the client acknowledges them. While full, new
+    /// messages back up in the session queue like a slow WebSocket's.
+    #[serde(default = "default_long_poll_max_buffered")]
+    pub max_buffered: usize,
+}
+
+fn default_long_poll_wait_ms() -> u64 { 25_000 }
+fn default_long_poll_session_ttl_ms() -> u64 { 60_000 }
+fn default_long_poll_max_buffered() -> usize { 1024 }
+
+impl LongPollConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(1000..=60000).contains(&self.wait_ms) {
+            return Err(WsPrismError::BadRequest("long_poll.wait_ms must be between 1000 and 60000".into()));
+        }
+        if self.session_ttl_ms <= self.wait_ms || self.session_ttl_ms > 600_000 {
+            return Err(WsPrismError::BadRequest(
+                "long_poll.session_ttl_ms must be greater than wait_ms and at most 600000".into(),
+            ));
+        }
+        if !(1..=65536).contains(&self.max_buffered) {
+            return Err(WsPrismError::BadRequest("long_poll.max_buffered must be between 1 and 65536".into()));
+        }
+        Ok(())
+    }
 }
 
 impl TenantConfig {
@@ -195,12 +1455,81 @@ impl TenantConfig {
         if self.limits.max_frame_bytes == 0 {
             return Err(WsPrismError::BadRequest("limits.max_frame_bytes must be > 0".into()));
         }
+        if self.api_key.as_ref().is_some_and(|k| k.len() < MIN_API_KEY_LEN) {
+            return Err(WsPrismError::BadRequest(format!(
+                "tenant {}: api_key must be at least {MIN_API_KEY_LEN} characters",
+                self.id
+            )));
+        }
+        if !self.allow_guest && !self.guest_scopes.is_empty() {
+            return Err(WsPrismError::BadRequest(
+                "guest_scopes requires allow_guest=true".into(),
+            ));
+        }
+        if let Some(i) = &self.introspection {
+            i.validate()?;
+        }
+        if let Some(p) = &self.plugin {
+            p.validate()?;
+        }
+        if let Some(w) = &self.webhooks {
+            w.validate()?;
+        }
+        if let Some(lp) = &self.long_poll {
+            lp.validate()?;
+        }
+        for (i, room) in self.auto_join_rooms.iter().enumerate() {
+            if room.is_empty() || room.len() > MAX_AUTO_JOIN_ROOM_LEN || room.chars().any(char::is_whitespace) {
+                return Err(WsPrismError::BadRequest(format!(
+                    "auto_join_rooms: {room:?} must be 1 to {MAX_AUTO_JOIN_ROOM_LEN} chars without whitespace"
+                )));
+            }
+            if self.auto_join_rooms[..i].contains(room) {
+                return Err(WsPrismError::BadRequest(format!("auto_join_rooms: {room:?} is listed twice")));
+            }
+        }
+        let max_rooms = self.limits.max_rooms_per_session;
+        if max_rooms > 0 && self.auto_join_rooms.len() as u64 > max_rooms {
+            return Err(WsPrismError::BadRequest(format!(
+                "auto_join_rooms lists {} rooms but limits.max_rooms_per_session is {max_rooms}",
+                self.auto_join_rooms.len()
+            )));
+        }
+        for (svc, sp) in &self.service_policies {
+            if svc.trim().is_empty() {
+                return Err(WsPrismError::BadRequest(
+                    "service_policies keys must not be empty".into(),
+                ));
+            }
+            sp.validate(svc)?;
+        }
+        for (svc, u) in &self.upstreams {
+            if svc.trim().is_empty() || ["room", "sys"].contains(&svc.as_str()) {
+                return Err(WsPrismError::BadRequest(format!(
+                    "upstreams: {svc:?} is not a valid service name"
+                )));
+            }
+            u.validate(svc)?;
+        }
+        for (svc, u) in &self.http_upstreams {
+            if svc.trim().is_empty() || ["room", "sys"].contains(&svc.as_str()) {
+                return Err(WsPrismError::BadRequest(format!(
+                    "http_upstreams: {svc:?} is not a valid service name"
+                )));
+            }
+            if self.upstreams.contains_key(svc) {
+                return Err(WsPrismError::BadRequest(format!(
+                    "{svc} is listed in both upstreams and http_upstreams"
+                )));
+            }
+            u.validate(svc)?;
+        }
         self.policy.validate()?;
         Ok(())
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct TenantLimits {
 /// Maximum allowed frame size for this tenant (bytes).
@@ -214,14 +1543,25 @@ pub max_frame_bytes: usize,
 pub max_sessions_total: u64,
 /// Max active rooms for the tenant. 0 = unlimited.
 /// A room counts as "active" if it has at least one session.
-#[serde(default)]
+#[serde(default, alias = "max_rooms_per_tenant")]
 pub max_rooms_total: u64,
 /// Max users allowed in a single room. 0 = unlimited.
 #[serde(default)]
 pub max_users_per_room: u64,
+/// Max users in a single room on this replica, enforced exactly; a
+/// `room:join` may lower it for a room it creates. 0 = unlimited.
+#[serde(default)]
+pub room_capacity: u64,
 /// Max unique rooms a single user can join. 0 = unlimited.
 #[serde(default)]
 pub max_rooms_per_user: u64,
+/// Max rooms a single connection can be in. 0 = unlimited.
+#[serde(default)]
+pub max_rooms_per_session: u64,
+/// Max Ext frames a user may send per 24h window, across all of their
+/// sessions. Rejected frames get `QUOTA_EXCEEDED`. 0 = unlimited.
+#[serde(default)]
+pub max_messages_per_day: u64,
 }
 
 impl Default for TenantLimits {
@@ -231,14 +1571,17 @@ impl Default for TenantLimits {
             max_sessions_total: 0,
             max_rooms_total: 0,
             max_users_per_room: 0,
+            room_capacity: 0,
             max_rooms_per_user: 0,
+            max_rooms_per_session: 0,
+            max_messages_per_day: 0,
         }
     }
 }
 
 fn default_max_frame_bytes() -> usize { 4096 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum RateLimitScope {
     Tenant,
@@ -248,7 +1591,7 @@ pub enum RateLimitScope {
 
 fn default_rate_limit_scope() -> RateLimitScope { RateLimitScope::Connection }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum HotErrorMode {
     SysError,
@@ -257,21 +1600,32 @@ pub enum HotErrorMode {
 
 fn default_hot_error_mode() -> HotErrorMode { HotErrorMode::SysError }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+/// Whether policy decisions are applied or only recorded.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyMode {
+    #[default]
+    Enforce,
+    /// Dry run: would-be decisions are counted with `mode="shadow"` but the
+    /// frame passes. Frame length and guest scope are still enforced.
+    Shadow,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum SessionMode {
     Single,
     Multi,
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum OnExceed {
     Deny,
     KickOldest,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct SessionPolicy {
     #[serde(default = "default_session_mode")]
@@ -300,7 +1654,7 @@ impl Default for SessionPolicy {
 
 /// Tenant policy knobs.
 /// Defaults are STRICT (deny-by-default).
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct TenantPolicy {
     /// Tenant-level or connection-level inbound rate limit in requests per second.
@@ -315,14 +1669,49 @@ pub struct TenantPolicy {
     #[serde(default = "default_rate_limit_scope")]
     pub rate_limit_scope: RateLimitScope,
 
+    /// Tenant-wide inbound bandwidth cap in bytes per second (0 = off).
+    /// Burst is one second's worth, or `max_frame_bytes` if larger.
+    #[serde(default)]
+    pub rate_limit_bytes_per_sec: u32,
+
+    /// Per-session inbound bandwidth cap in bytes per second (0 = off).
+    #[serde(default)]
+    pub per_session_bytes_per_sec: u32,
+
+    /// Per-session outbound bandwidth cap in bytes per second (0 = off).
+    /// Lossy messages over the cap are dropped; reliable ones wait within
+    /// their timeout.
+    #[serde(default)]
+    pub max_outbound_bytes_per_sec: u64,
+
+    /// Per-room inbound rate across all senders (Ext `room`, Hot active room),
+    /// messages/sec (0 = off). Room publishes that did not come through
+    /// that gate (other rooms, spawned tasks, upstreams, `/v1/publish`) are
+    /// charged too and refused with `RATE_LIMITED`.
+    #[serde(default)]
+    pub room_rate_limit_rps: u32,
+
+    /// Per-room burst capacity (0 = same as `room_rate_limit_rps`).
+    #[serde(default)]
+    pub room_rate_limit_burst: u32,
+
     /// Ext lane allowlist entries, like "svc:type"
     #[serde(default = "default_ext_allowlist")]
     pub ext_allowlist: Vec<String>,
 
-    /// Hot lane allowlist entries, like "sid:opcode"
+    /// Hot lane allowlist entries, like "sid:opcode", "sid:*" or "sid:lo-hi"
     #[serde(default)]
     pub hot_allowlist: Vec<String>,
 
+    /// Ext lane deny entries, checked before the allowlist.
+    /// Same syntax; "svc:type@N" allows at most N/s and denies the rest.
+    #[serde(default)]
+    pub ext_denylist: Vec<String>,
+
+    /// Hot lane deny entries ("sid:opcode", optional "@N").
+    #[serde(default)]
+    pub hot_denylist: Vec<String>,
+
     /// Session policy (1:1 / 1:N)
     #[serde(default)]
     pub sessions: SessionPolicy,
@@ -334,9 +1723,81 @@ pub struct TenantPolicy {
     /// If true, hot lane requires active_room.
     #[serde(default = "default_hot_requires_active_room")]
     pub hot_requires_active_room: bool,
+
+    /// Include this tenant's sessions in admin server-wide broadcasts.
+    #[serde(default)]
+    pub allow_server_broadcast: bool,
+
+    /// Policy rejects a session may collect within `strike_window_ms` before
+    /// it is closed (0 = off).
+    #[serde(default)]
+    pub strike_limit: u32,
+
+    /// Sliding window for `strike_limit` (ms).
+    #[serde(default = "default_strike_window_ms")]
+    pub strike_window_ms: u64,
+
+    /// Max recipients a reliable room publish sends to concurrently; larger
+    /// rooms are delivered in chunks of this size.
+    #[serde(default = "default_max_fanout_parallelism")]
+    pub max_fanout_parallelism: usize,
+
+    /// `enforce` or `shadow` (dry run of the lane checks). Unset means
+    /// `enforce`, or `shadow` with `dry_run`; see [`Self::effective_mode`].
+    #[serde(default)]
+    pub mode: Option<PolicyMode>,
+
+    /// Shorthand for `mode: shadow`. Conflicts with `mode: enforce`.
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Per-user Ext lane message quotas, keyed by `ext_allowlist`-style
+    /// pattern (e.g. `"chat:send"`, `"chat:*"`). Every matching entry is
+    /// charged.
+    #[serde(default)]
+    pub quotas: HashMap<String, QuotaSpec>,
+
+    /// Recent room messages sent to a session right after it joins a room
+    /// (0 = off). Capped by `gateway.room_replay_capacity`.
+    #[serde(default)]
+    pub replay_on_join: usize,
+
+    /// Ext lane `svc:type` patterns (allowlist syntax, no `@` clauses)
+    /// whose messages are archived to `gateway.egress` once dispatched.
+    #[serde(default)]
+    pub egress: Vec<String>,
+}
+
+/// One `policy.quotas` entry: at most `limit` messages per user per `window`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct QuotaSpec {
+    pub limit: u64,
+    pub window: QuotaWindow,
+}
+
+/// Quota window length. A window starts at the user's first counted
+/// message and the count resets once it has elapsed.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaWindow {
+    #[serde(rename = "1h")]
+    Hour,
+    #[serde(rename = "24h")]
+    Day,
+}
+
+impl QuotaWindow {
+    pub fn as_millis(self) -> u64 {
+        match self {
+            QuotaWindow::Hour => 3_600_000,
+            QuotaWindow::Day => 86_400_000,
+        }
+    }
 }
 
 fn default_hot_requires_active_room() -> bool { true }
+fn default_strike_window_ms() -> u64 { 60_000 }
+fn default_max_fanout_parallelism() -> usize { 256 }
 
 impl Default for TenantPolicy {
     fn default() -> Self {
@@ -344,22 +1805,82 @@ impl Default for TenantPolicy {
             rate_limit_rps: default_rate_limit_rps(),
             rate_limit_burst: default_rate_limit_burst(),
             rate_limit_scope: default_rate_limit_scope(),
+            rate_limit_bytes_per_sec: 0,
+            per_session_bytes_per_sec: 0,
+            max_outbound_bytes_per_sec: 0,
+            room_rate_limit_rps: 0,
+            room_rate_limit_burst: 0,
             ext_allowlist: default_ext_allowlist(),
             hot_allowlist: Vec::new(),
+            ext_denylist: Vec::new(),
+            hot_denylist: Vec::new(),
             sessions: SessionPolicy::default(),
             hot_error_mode: default_hot_error_mode(),
             hot_requires_active_room: default_hot_requires_active_room(),
+            allow_server_broadcast: false,
+            strike_limit: 0,
+            strike_window_ms: default_strike_window_ms(),
+            max_fanout_parallelism: default_max_fanout_parallelism(),
+            mode: None,
+            dry_run: false,
+            quotas: HashMap::new(),
+            replay_on_join: 0,
+            egress: Vec::new(),
         }
     }
 }
 
 impl TenantPolicy {
+    /// `mode`, with `dry_run` selecting `shadow` when it is unset.
+    pub fn effective_mode(&self) -> PolicyMode {
+        match (self.mode, self.dry_run) {
+            (Some(mode), _) => mode,
+            (None, true) => PolicyMode::Shadow,
+            (None, false) => PolicyMode::Enforce,
+        }
+    }
+
     pub fn validate(&self) -> Result<()> {
+        if self.dry_run && self.mode == Some(PolicyMode::Enforce) {
+            return Err(WsPrismError::BadRequest(
+                "policy.dry_run: true conflicts with policy.mode: enforce".into(),
+            ));
+        }
         if self.rate_limit_rps == 0 || self.rate_limit_burst == 0 {
             return Err(WsPrismError::BadRequest(
                 "policy.rate_limit_rps and rate_limit_burst must be > 0".into(),
             ));
         }
+        if self.room_rate_limit_burst > 0 && self.room_rate_limit_rps == 0 {
+            return Err(WsPrismError::BadRequest(
+                "policy.room_rate_limit_burst requires room_rate_limit_rps > 0".into(),
+            ));
+        }
+        if self.max_fanout_parallelism == 0 {
+            return Err(WsPrismError::BadRequest(
+                "policy.max_fanout_parallelism must be > 0".into(),
+            ));
+        }
+        if let Some(pattern) = self.quotas.iter().find(|(_, q)| q.limit == 0).map(|(p, _)| p) {
+            return Err(WsPrismError::BadRequest(format!(
+                "policy.quotas.{pattern}: limit must be > 0"
+            )));
+        }
+        if self.max_outbound_bytes_per_sec > u64::from(u32::MAX) {
+            return Err(WsPrismError::BadRequest(
+                "policy.max_outbound_bytes_per_sec must be <= 4294967295".into(),
+            ));
+        }
+        if self.replay_on_join > 1000 {
+            return Err(WsPrismError::BadRequest(
+                "policy.replay_on_join must be <= 1000".into(),
+            ));
+        }
+        if self.strike_limit > 0 && self.strike_window_ms == 0 {
+            return Err(WsPrismError::BadRequest(
+                "policy.strike_window_ms must be > 0 when strike_limit is set".into(),
+            ));
+        }
         // sessions policy sanity
         match self.sessions.mode {
             SessionMode::Single => {
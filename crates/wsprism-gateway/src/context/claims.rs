@@ -0,0 +1,52 @@
+//! Per-session user claims (display name, roles, ...).
+//!
+//! Claims are resolved once at connect by the auth layer and stay immutable for
+//! the lifetime of the session. They are shared behind an `Arc` so handing them
+//! to services through `RealtimeCtx` is a cheap clone.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde_json::Value;
+use wsprism_core::error::{Result, WsPrismError};
+
+/// Upper bound on the serialized size of a claim set (bytes).
+pub const MAX_CLAIMS_BYTES: usize = 4096;
+
+/// Immutable, size-capped claim map attached to a session.
+#[derive(Debug, Clone, Default)]
+pub struct SessionClaims {
+    inner: Arc<HashMap<String, Value>>,
+}
+
+impl SessionClaims {
+    /// Build claims, rejecting sets larger than `MAX_CLAIMS_BYTES` when serialized.
+    pub fn new(map: HashMap<String, Value>) -> Result<Self> {
+        let size = serde_json::to_vec(&map)
+            .map_err(|e| WsPrismError::Internal(format!("claims encode failed: {e}")))?
+            .len();
+        if size > MAX_CLAIMS_BYTES {
+            return Err(WsPrismError::BadRequest(format!(
+                "claims too large: {size} bytes (max {MAX_CLAIMS_BYTES})"
+            )));
+        }
+        Ok(Self { inner: Arc::new(map) })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.inner.get(key)
+    }
+
+    /// `display_name` claim, if present and a string.
+    pub fn display_name(&self) -> Option<&str> {
+        self.get("display_name").and_then(Value::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.inner.iter()
+    }
+}
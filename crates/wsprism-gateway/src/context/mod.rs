@@ -3,4 +3,9 @@
 //! Sprint 2: we introduce TenantContext so that policy/presence can be tenant-aware
 //! without coupling to transport specifics.
 
+pub mod claims;
+pub mod connection;
 pub mod tenant;
+
+pub use claims::SessionClaims;
+pub use connection::ConnectionId;
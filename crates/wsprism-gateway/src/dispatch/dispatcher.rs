@@ -1,19 +1,26 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
+use tracing::{Instrument, Span};
 
 use wsprism_core::error::{Result, WsPrismError};
 use wsprism_core::protocol::hot::HotFrame;
 use wsprism_core::protocol::text::Envelope;
 
-use crate::realtime::RealtimeCtx;
+use crate::realtime::{Outgoing, RealtimeCtx};
 
-/// Text services (Ext Lane). Can be extended by WASM later.
+use super::middleware::{chain, BinaryServiceChain, TextServiceMiddleware};
+
+/// Text services (Ext Lane). Per-tenant WASM filtering happens earlier, in
+/// the plugin stage (`crate::plugin`).
+///
+/// Returning `Some(out)` replies to the calling user (see
+/// `Outgoing::reply`); `None` means the service sent whatever it needed itself.
 #[async_trait]
 pub trait TextService: Send + Sync {
     fn svc(&self) -> &'static str;
-    async fn handle(&self, ctx: RealtimeCtx, env: Envelope) -> Result<()>;
+    async fn handle(&self, ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>>;
 }
 
 /// Binary services (Hot Lane). **Native only** (no WASM/script).
@@ -28,6 +35,8 @@ pub trait BinaryService: Send + Sync {
 pub struct Dispatcher {
     text: DashMap<&'static str, Arc<dyn TextService>>,
     hot: DashMap<u8, Arc<dyn BinaryService>>,
+    /// Keys registered more than once (last registration wins).
+    duplicates: DashSet<String>,
 }
 
 impl Dispatcher {
@@ -35,15 +44,54 @@ impl Dispatcher {
         Self {
             text: DashMap::new(),
             hot: DashMap::new(),
+            duplicates: DashSet::new(),
         }
     }
 
     pub fn register_text(&self, svc: Arc<dyn TextService>) {
-        self.text.insert(svc.svc(), svc);
+        let name = svc.svc();
+        if self.text.insert(name, svc).is_some() {
+            self.duplicates.insert(format!("text:{name}"));
+        }
+    }
+
+    /// Register a text service wrapped in `middleware` (first entry runs first).
+    pub fn register_text_with_middleware(
+        &self,
+        svc: Arc<dyn TextService>,
+        middleware: Vec<Arc<dyn TextServiceMiddleware>>,
+    ) {
+        self.register_text(chain(svc, middleware));
     }
 
     pub fn register_hot(&self, svc: Arc<dyn BinaryService>) {
-        self.hot.insert(svc.svc_id(), svc);
+        let sid = svc.svc_id();
+        if self.hot.insert(sid, svc).is_some() {
+            self.duplicates.insert(format!("hot:{sid}"));
+        }
+    }
+
+    /// Register `chain` as the handler for `svc_id` (its stages' own
+    /// `svc_id`s are not consulted). Single handlers keep using
+    /// [`register_hot`](Self::register_hot).
+    pub fn register_hot_chain(&self, svc_id: u8, mut chain: BinaryServiceChain) {
+        chain.svc_id = svc_id;
+        self.register_hot(Arc::new(chain));
+    }
+
+    /// Services registered more than once, as `text:<svc>` / `hot:<svc_id>`.
+    pub fn duplicate_registrations(&self) -> Vec<String> {
+        let mut v: Vec<String> = self.duplicates.iter().map(|d| d.key().clone()).collect();
+        v.sort();
+        v
+    }
+
+    pub fn has_text(&self, svc: &str) -> bool {
+        self.text.contains_key(svc)
+    }
+
+    pub fn has_hot(&self, svc_id: u8) -> bool {
+        self.hot.contains_key(&svc_id)
     }
 
     pub fn registered_text_svcs(&self) -> Vec<&'static str> {
@@ -54,6 +102,11 @@ impl Dispatcher {
         self.hot.iter().map(|e| *e.key()).collect()
     }
 
+    /// Route to the text service; a returned `Outgoing` goes to the caller.
+    ///
+    /// The handler runs in a `dispatch` span (tenant, svc, msg_type, user,
+    /// correlation_id) for trace exporters. With the `otel` feature, a valid
+    /// `data.traceparent` makes the span a child of that remote trace.
     pub async fn dispatch_text(&self, ctx: RealtimeCtx, env: Envelope) -> Result<()> {
         let svc = env.svc.as_str();
         let handler = self
@@ -62,9 +115,26 @@ impl Dispatcher {
             .ok_or_else(|| WsPrismError::BadRequest(format!("unknown svc: {svc}")))?
             .value()
             .clone();
-        handler.handle(ctx, env).await
+        let traceparent = remote_traceparent(&env);
+        let span = tracing::info_span!(
+            "dispatch",
+            tenant = ctx.tenant(),
+            svc = svc,
+            msg_type = env.msg_type.as_str(),
+            user = ctx.user(),
+            correlation_id = &*ctx.trace_id,
+            traceparent = traceparent.as_deref(),
+            otel.status_code = tracing::field::Empty,
+        );
+        let res = handler.handle(ctx.clone(), env).instrument(span.clone()).await;
+        match record_status(&span, res)? {
+            Some(out) => ctx.send_to_self(out),
+            None => Ok(()),
+        }
     }
 
+    /// Route to the binary service, in a `dispatch` span (tenant, svc_id,
+    /// opcode, user, correlation_id).
     pub async fn dispatch_hot(&self, ctx: RealtimeCtx, frame: HotFrame) -> Result<()> {
         let sid = frame.svc_id;
         let handler = self
@@ -73,6 +143,34 @@ impl Dispatcher {
             .ok_or_else(|| WsPrismError::BadRequest(format!("unknown hot svc_id: {sid}")))?
             .value()
             .clone();
-        handler.handle_binary(ctx, frame).await
+        let span = tracing::info_span!(
+            "dispatch",
+            tenant = ctx.tenant(),
+            svc_id = sid,
+            opcode = frame.opcode,
+            user = ctx.user(),
+            correlation_id = &*ctx.trace_id,
+            otel.status_code = tracing::field::Empty,
+        );
+        let res = handler.handle_binary(ctx, frame).instrument(span.clone()).await;
+        record_status(&span, res)
+    }
+}
+
+#[cfg(feature = "otel")]
+fn remote_traceparent(env: &Envelope) -> Option<String> {
+    crate::obs::otlp::envelope_traceparent(env.data.as_deref())
+}
+
+#[cfg(not(feature = "otel"))]
+fn remote_traceparent(_env: &Envelope) -> Option<String> {
+    None
+}
+
+/// Mark `span` failed (`otel.status_code = "ERROR"`) when `res` is an error.
+fn record_status<T>(span: &Span, res: Result<T>) -> Result<T> {
+    if res.is_err() {
+        span.record("otel.status_code", "ERROR");
     }
+    res
 }
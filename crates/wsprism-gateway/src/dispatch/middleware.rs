@@ -0,0 +1,171 @@
+//! Composable interceptors for Ext lane services, and handler chains for
+//! Hot Lane services.
+//!
+//! A middleware sees the context and envelope before the wrapped service and
+//! decides whether (and how) to call `next`. Chains are built once at
+//! registration time, so dispatch cost is one virtual call per layer.
+//!
+//! On the Hot Lane, a [`BinaryServiceChain`] runs several handlers for one
+//! `svc_id` in order, stopping at the first error.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::hot::HotFrame;
+use wsprism_core::protocol::text::Envelope;
+
+use crate::realtime::{Outgoing, RealtimeCtx};
+
+use super::{BinaryService, TextService};
+
+/// Interceptor wrapping a `TextService`.
+#[async_trait]
+pub trait TextServiceMiddleware: Send + Sync {
+    async fn call(&self, ctx: RealtimeCtx, env: Envelope, next: &dyn TextService) -> Result<Option<Outgoing>>;
+}
+
+/// One chain link: `mw` runs with `next` as its continuation.
+struct Chained {
+    mw: Arc<dyn TextServiceMiddleware>,
+    next: Arc<dyn TextService>,
+}
+
+#[async_trait]
+impl TextService for Chained {
+    fn svc(&self) -> &'static str {
+        self.next.svc()
+    }
+
+    async fn handle(&self, ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
+        self.mw.call(ctx, env, self.next.as_ref()).await
+    }
+}
+
+/// Wrap `svc` so that `middleware[0]` runs first and the service runs last.
+pub fn chain(
+    svc: Arc<dyn TextService>,
+    middleware: Vec<Arc<dyn TextServiceMiddleware>>,
+) -> Arc<dyn TextService> {
+    middleware
+        .into_iter()
+        .rev()
+        .fold(svc, |next, mw| Arc::new(Chained { mw, next }))
+}
+
+/// Logs svc/type/user around the inner call, with elapsed time.
+#[derive(Default)]
+pub struct LoggingMiddleware;
+
+#[async_trait]
+impl TextServiceMiddleware for LoggingMiddleware {
+    async fn call(&self, ctx: RealtimeCtx, env: Envelope, next: &dyn TextService) -> Result<Option<Outgoing>> {
+        let svc = env.svc.clone();
+        let msg_type = env.msg_type.clone();
+        let user = ctx.user().to_string();
+        tracing::info!(%svc, %msg_type, %user, "ext dispatch start");
+
+        let start = Instant::now();
+        let res = next.handle(ctx, env).await;
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+        match &res {
+            Ok(_) => tracing::info!(%svc, %msg_type, %user, elapsed_ms, "ext dispatch done"),
+            Err(e) => tracing::info!(%svc, %msg_type, %user, elapsed_ms, error=%e, "ext dispatch failed"),
+        }
+        res
+    }
+}
+
+/// Rejects guest sessions with `AuthFailed`.
+#[derive(Default)]
+pub struct AuthRequiredMiddleware;
+
+#[async_trait]
+impl TextServiceMiddleware for AuthRequiredMiddleware {
+    async fn call(&self, ctx: RealtimeCtx, env: Envelope, next: &dyn TextService) -> Result<Option<Outgoing>> {
+        if ctx.is_guest() {
+            return Err(WsPrismError::AuthFailed);
+        }
+        next.handle(ctx, env).await
+    }
+}
+
+/// Hot Lane handlers run in order for one `svc_id`. Each gets a clone of
+/// the context and frame (the payload is shared); the first `Err` stops the
+/// chain and is returned. Checks such as auth are stages that return
+/// `Ok(())` to let the frame through.
+///
+/// Register with [`Dispatcher::register_hot_chain`](super::Dispatcher::register_hot_chain),
+/// which sets the chain's `svc_id`.
+#[derive(Clone, Default)]
+pub struct BinaryServiceChain {
+    pub(super) svc_id: u8,
+    stages: Vec<Arc<dyn BinaryService>>,
+}
+
+impl BinaryServiceChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a stage; it runs after the ones already pushed.
+    pub fn push(mut self, stage: Arc<dyn BinaryService>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+}
+
+#[async_trait]
+impl BinaryService for BinaryServiceChain {
+    fn svc_id(&self) -> u8 {
+        self.svc_id
+    }
+
+    async fn handle_binary(&self, ctx: RealtimeCtx, frame: HotFrame) -> Result<()> {
+        for stage in &self.stages {
+            stage.handle_binary(ctx.clone(), frame.clone()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Logs svc_id/opcode/payload_len around the wrapped Hot Lane service, with
+/// elapsed time. Wrap a whole [`BinaryServiceChain`] to time all its stages.
+pub struct LoggingBinaryMiddleware {
+    inner: Arc<dyn BinaryService>,
+}
+
+impl LoggingBinaryMiddleware {
+    pub fn new(inner: Arc<dyn BinaryService>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl BinaryService for LoggingBinaryMiddleware {
+    fn svc_id(&self) -> u8 {
+        self.inner.svc_id()
+    }
+
+    async fn handle_binary(&self, ctx: RealtimeCtx, frame: HotFrame) -> Result<()> {
+        let (svc_id, opcode, payload_len) = (frame.svc_id, frame.opcode, frame.payload.len());
+        let start = Instant::now();
+        let res = self.inner.handle_binary(ctx, frame).await;
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        match &res {
+            Ok(()) => tracing::info!(svc_id, opcode, payload_len, elapsed_us, "hot dispatch done"),
+            Err(e) => tracing::info!(svc_id, opcode, payload_len, elapsed_us, error=%e, "hot dispatch failed"),
+        }
+        res
+    }
+}
@@ -4,5 +4,9 @@
 //! depend on this module directly.
 
 pub mod dispatcher;
+pub mod middleware;
 
 pub use dispatcher::{BinaryService, Dispatcher, TextService};
+pub use middleware::{
+    AuthRequiredMiddleware, BinaryServiceChain, LoggingBinaryMiddleware, LoggingMiddleware, TextServiceMiddleware,
+};
@@ -0,0 +1,76 @@
+//! Kafka [`EgressSink`] (`egress-kafka` feature).
+//!
+//! Records are produced to `gateway.egress.kafka.topic` keyed by
+//! [`EgressRecord::key`], so a room's records land on one partition in
+//! order. `write` only enqueues into librdkafka; broker acknowledgements are
+//! awaited in the background and failures counted as `delivery` drops.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use wsprism_core::error::{Result, WsPrismError};
+
+use super::{EgressRecord, EgressSink};
+use crate::config::schema::EgressConfig;
+use crate::obs::metrics::GatewayMetrics;
+
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl KafkaSink {
+    /// Create the producer. Brokers are contacted lazily, so an unreachable
+    /// cluster does not fail startup; records queue in librdkafka meanwhile.
+    pub fn new(cfg: &EgressConfig, metrics: Arc<GatewayMetrics>) -> Result<Self> {
+        let kcfg = cfg
+            .kafka
+            .as_ref()
+            .ok_or_else(|| WsPrismError::BadRequest("gateway.egress.kafka is required for sink: kafka".into()))?;
+        let mut client = ClientConfig::new();
+        for (k, v) in &kcfg.properties {
+            client.set(k, v);
+        }
+        client.set("bootstrap.servers", kcfg.brokers.join(","));
+        let producer = client
+            .create()
+            .map_err(|e| WsPrismError::BadRequest(format!("gateway.egress.kafka: {e}")))?;
+        Ok(Self { producer, topic: kcfg.topic.clone(), metrics })
+    }
+}
+
+#[async_trait]
+impl EgressSink for KafkaSink {
+    async fn write(&self, record: &EgressRecord) -> Result<()> {
+        let payload = serde_json::to_vec(record).map_err(|e| WsPrismError::Internal(format!("egress record: {e}")))?;
+        let delivery = self
+            .producer
+            .send_result(FutureRecord::to(&self.topic).key(record.key()).payload(&payload))
+            .map_err(|(e, _)| WsPrismError::Internal(format!("kafka enqueue: {e}")))?;
+
+        let tenant = record.tenant.clone();
+        let metrics = self.metrics.clone();
+        tokio::spawn(async move {
+            let err = match delivery.await {
+                Ok(Ok(_)) => return,
+                Ok(Err((e, _))) => e.to_string(),
+                Err(_) => "producer dropped".to_string(),
+            };
+            tracing::warn!(%tenant, error = %err, "kafka egress delivery failed");
+            metrics.egress_dropped.inc(&[("tenant", tenant.as_str()), ("reason", "delivery")]);
+        });
+        Ok(())
+    }
+
+    async fn flush(&self, timeout: Duration) -> Result<()> {
+        let producer = self.producer.clone();
+        tokio::task::spawn_blocking(move || producer.flush(timeout))
+            .await
+            .map_err(|e| WsPrismError::Internal(format!("kafka flush: {e}")))?
+            .map_err(|e| WsPrismError::Internal(format!("kafka flush: {e}")))
+    }
+}
@@ -0,0 +1,203 @@
+//! Archival of selected Ext lane messages (`gateway.egress`).
+//!
+//! Each tenant's `policy.egress` lists `svc:type` patterns (the allowlist
+//! syntax, without `@` clauses). Every inbound Ext message that matches and
+//! was dispatched successfully becomes one JSON [`EgressRecord`]:
+//!
+//! ```json
+//! {"ts":1730000000000,"tenant":"acme","user":"u1","room":"lobby","svc":"chat","type":"send","data":{"text":"hi"}}
+//! ```
+//!
+//! Sessions never wait on the sink: records go through a bounded queue to
+//! one writer task, and a full queue drops the record. Drops are counted in
+//! `wsprism_egress_dropped_total{tenant,reason}` with `reason` `queue_full`,
+//! `delivery` (the sink failed to write it) or `shutdown` (offered after
+//! shutdown began). On shutdown the queue is drained and the sink flushed,
+//! bounded by `gateway.egress.flush_timeout_ms`.
+//!
+//! Sinks, selected by `gateway.egress.sink`:
+//! - `kafka` (`egress-kafka` feature): [`KafkaSink`], keyed by room.
+
+#[cfg(feature = "egress-kafka")]
+mod kafka;
+
+#[cfg(feature = "egress-kafka")]
+pub use kafka::KafkaSink;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use tokio::sync::{mpsc, oneshot};
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::text::Envelope;
+
+use crate::config::schema::{EgressConfig, EgressSinkKind};
+use crate::obs::metrics::GatewayMetrics;
+use crate::policy::quota::unix_now_ms;
+
+/// One archived message.
+#[derive(Debug, Clone, Serialize)]
+pub struct EgressRecord {
+    /// Receive time, unix ms.
+    pub ts: u64,
+    pub tenant: String,
+    /// User id, without the tenant prefix.
+    pub user: String,
+    /// The envelope's room, else the session's active room.
+    pub room: Option<String>,
+    pub svc: String,
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    /// The envelope's `data`, verbatim; `null` when absent.
+    pub data: Option<Box<RawValue>>,
+}
+
+impl EgressRecord {
+    pub fn new(tenant: impl Into<String>, user: impl Into<String>, env: &Envelope) -> Self {
+        Self {
+            ts: unix_now_ms(),
+            tenant: tenant.into(),
+            user: user.into(),
+            room: env.room.clone(),
+            svc: env.svc.clone(),
+            msg_type: env.msg_type.clone(),
+            data: env.data.clone(),
+        }
+    }
+
+    /// Partition key: the room, or the tenant for room-less messages, so
+    /// each room's records stay in order.
+    pub fn key(&self) -> &str {
+        self.room.as_deref().unwrap_or(&self.tenant)
+    }
+}
+
+/// Destination of egress records. Called from the single writer task, in
+/// the order records were accepted.
+#[async_trait]
+pub trait EgressSink: Send + Sync {
+    /// Hand one record to the backend. Errors count the record as a
+    /// `delivery` drop.
+    async fn write(&self, record: &EgressRecord) -> Result<()>;
+
+    /// Wait until records handed over so far are durable, for at most
+    /// `timeout`.
+    async fn flush(&self, timeout: Duration) -> Result<()>;
+}
+
+enum Command {
+    Record(EgressRecord),
+    Flush(Duration, oneshot::Sender<Result<()>>),
+}
+
+/// Bounded queue in front of an [`EgressSink`].
+pub struct Egress {
+    tx: mpsc::Sender<Command>,
+    rx: Mutex<Option<mpsc::Receiver<Command>>>,
+    sink: Arc<dyn EgressSink>,
+    closed: AtomicBool,
+    flush_timeout: Duration,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl Egress {
+    pub fn new(sink: Arc<dyn EgressSink>, cfg: &EgressConfig, metrics: Arc<GatewayMetrics>) -> Self {
+        let (tx, rx) = mpsc::channel(cfg.queue_capacity);
+        Self {
+            tx,
+            rx: Mutex::new(Some(rx)),
+            sink,
+            closed: AtomicBool::new(false),
+            flush_timeout: Duration::from_millis(cfg.flush_timeout_ms),
+            metrics,
+        }
+    }
+
+    /// Build the sink selected by `gateway.egress`; `None` for `sink: none`.
+    pub fn from_config(cfg: &EgressConfig, metrics: Arc<GatewayMetrics>) -> Result<Option<Self>> {
+        if cfg.sink == EgressSinkKind::None {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(sink_for(cfg, &metrics)?, cfg, metrics)))
+    }
+
+    /// Queue `record` without waiting. Returns false (and counts the drop)
+    /// when the queue is full or [`shutdown`](Self::shutdown) was called.
+    pub fn offer(&self, record: EgressRecord) -> bool {
+        if self.closed.load(Ordering::Acquire) {
+            self.dropped(&record.tenant, "shutdown");
+            return false;
+        }
+        match self.tx.try_send(Command::Record(record)) {
+            Ok(()) => true,
+            Err(e) => {
+                if let Command::Record(r) = e.into_inner() {
+                    self.dropped(&r.tenant, "queue_full");
+                }
+                false
+            }
+        }
+    }
+
+    fn dropped(&self, tenant: &str, reason: &str) {
+        self.metrics.egress_dropped.inc(&[("tenant", tenant), ("reason", reason)]);
+    }
+
+    /// Start the writer task. `None` if it was already started. Must be
+    /// called from within a tokio runtime.
+    pub fn spawn(self: Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let mut rx = self.rx.lock().ok()?.take()?;
+        Some(tokio::spawn(async move {
+            while let Some(cmd) = rx.recv().await {
+                match cmd {
+                    Command::Record(record) => {
+                        if let Err(e) = self.sink.write(&record).await {
+                            tracing::warn!(tenant = %record.tenant, error = %e, "egress write failed");
+                            self.dropped(&record.tenant, "delivery");
+                        }
+                    }
+                    Command::Flush(timeout, done) => {
+                        let _ = done.send(self.sink.flush(timeout).await);
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Stop accepting records, wait for the queued ones to reach the sink,
+    /// then flush it; all within `gateway.egress.flush_timeout_ms`. Returns
+    /// false if that did not finish in time (or the writer is not running).
+    pub async fn shutdown(&self) -> bool {
+        self.closed.store(true, Ordering::Release);
+        let (done, flushed) = oneshot::channel();
+        let run = async {
+            self.tx.send(Command::Flush(self.flush_timeout, done)).await.ok()?;
+            flushed.await.ok()
+        };
+        match tokio::time::timeout(self.flush_timeout, run).await {
+            Ok(Some(Ok(()))) => true,
+            Ok(Some(Err(e))) => {
+                tracing::warn!(error = %e, "egress flush failed");
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "egress-kafka"), allow(unused_variables))]
+fn sink_for(cfg: &EgressConfig, metrics: &Arc<GatewayMetrics>) -> Result<Arc<dyn EgressSink>> {
+    match cfg.sink {
+        #[cfg(feature = "egress-kafka")]
+        EgressSinkKind::Kafka => Ok(Arc::new(KafkaSink::new(cfg, metrics.clone())?)),
+        #[allow(unreachable_patterns)]
+        kind => Err(WsPrismError::BadRequest(format!(
+            "gateway.egress.sink is {0} but the gateway was built without the egress-{0} feature",
+            kind.as_str()
+        ))),
+    }
+}
@@ -2,10 +2,18 @@
 //!
 //! This crate assembles the production gateway stack:
 //! - Transport: Axum-based WebSocket upgrade with handshake defense, tenant caps,
-//!   slow-consumer protection, and trace-id propagation.
+//!   slow-consumer protection, and trace-id propagation; optional raw TCP
+//!   Hot Lane listener for trusted backends.
 //! - Policy: Allowlist, rate limiting, session/room governance, and hot-lane behavior.
+//! - Plugins: optional per-tenant frame filters (WASM behind `wasm-plugins`).
 //! - Dispatch: Routes ext/hot messages to registered services.
 //! - Realtime core: Session/room registries, lossy/reliable egress.
+//! - Cluster: optional room fanout across replicas over Redis
+//!   (`cluster-redis`) or NATS (`cluster-nats`).
+//! - Webhooks: batched session lifecycle notifications to tenant backends
+//!   (HTTP delivery behind `webhooks`).
+//! - Upstreams: Ext lane services forwarded to tenant backends (gRPC behind
+//!   `upstream-grpc`, HTTP behind `upstream-http`).
 //! - Observability: Labeled counters/gauges/histograms, sys.* envelopes with trace_id,
 //!   and /metrics exposure via ops endpoints.
 //! - Ops: /healthz, /readyz, /metrics, graceful drain.
@@ -17,8 +25,13 @@
 //! This crate is consumed by the binary (`main.rs`) and by integration tests.
 
 pub mod app_state;
+pub mod audit;
+pub mod auth;
+pub mod cluster;
 pub mod config;
 pub mod context;
+pub mod egress;
+pub mod plugin;
 pub mod policy;
 pub mod router;
 pub mod transport;
@@ -27,3 +40,5 @@ pub mod realtime;
 pub mod services;
 pub mod ops;
 pub mod obs;
+pub mod upstream;
+pub mod webhooks;
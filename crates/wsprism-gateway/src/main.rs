@@ -4,53 +4,111 @@
 //! starts the WebSocket server.
 
 use std::net::SocketAddr;
-use tracing_subscriber::{fmt, EnvFilter};
+use std::sync::Arc;
 use tokio::time::Instant;
 
-use wsprism_gateway::{app_state, config, router};
+use wsprism_gateway::transport::handshake::HandshakeDefender;
+use wsprism_gateway::{app_state, config, obs, ops, router, transport};
 
 #[tokio::main]
 async fn main() {
-    fmt().with_env_filter(EnvFilter::from_default_env()).init();
-
     // Config (strict parsing + validate already in Sprint 0)
-    let cfg = config::load_from_file("wsprism.yaml").expect("config load failed");
+    let cfg = config::load_from_file(app_state::DEFAULT_CONFIG_PATH).expect("config load failed");
+    let telemetry = obs::telemetry::init(&cfg.observability).expect("tracing setup failed");
     let listen: SocketAddr = cfg
         .gateway
         .listen
         .parse()
         .expect("gateway.listen must be a valid SocketAddr");
 
-    let state = app_state::AppState::new(cfg).expect("failed to build app state");
+    // `--lenient`: log allowlist/service mismatches instead of refusing to start.
+    let opts = app_state::StartupOptions {
+        lenient: std::env::args().any(|a| a == "--lenient"),
+        config_path: Some(app_state::DEFAULT_CONFIG_PATH.to_string()),
+    };
+    let state = match app_state::AppState::from_config_async_with_options(cfg, opts).await {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::error!(error = %e, "gateway startup failed");
+            std::process::exit(1);
+        }
+    };
     let app = router::build_router(state.clone());
+    state.spawn_room_bucket_sweeper();
+    state.spawn_quota_sweeper();
+    state.spawn_outbound_sampler();
+    state.spawn_metrics_pruner();
+    state.spawn_room_size_sampler();
+    state.spawn_cluster_bridge();
+    state.spawn_egress();
+    state.spawn_webhooks();
 
     tracing::info!(%listen, "wsprism-gateway starting");
     let listener = tokio::net::TcpListener::bind(listen).await.expect("failed to bind");
+    if let Err(e) = transport::hot_tcp::spawn(&state).await {
+        tracing::error!(error = %e, "hot_tcp listener failed to start");
+        std::process::exit(1);
+    }
 
     let drain_grace_ms = state.cfg().gateway.drain_grace_ms;
+    let shutdown_timeout = std::time::Duration::from_millis(state.cfg().gateway.shutdown_timeout_ms);
+    let signalled = Arc::new(tokio::sync::Notify::new());
+    let on_signal = signalled.clone();
+    let handshake = state.handshake();
+    let egress_state = state.clone();
     
     // Sprint 5: Enable ConnectInfo for HandshakeDefender
-    axum::serve(
+    let serve = axum::serve(
         listener, 
         app.into_make_service_with_connect_info::<SocketAddr>()
     )
     .with_graceful_shutdown(async move {
         shutdown_signal().await;
+        on_signal.notify_one();
 
         tracing::info!("shutdown signal received; entering draining mode");
         state.enter_draining();
+        let deadline = Instant::now() + std::time::Duration::from_millis(drain_grace_ms);
+        // Give queued messages part of the grace period before the Close frames.
+        let flush = std::time::Duration::from_millis(drain_grace_ms / 2);
+        if !state.realtime().flush_all(flush).await {
+            tracing::warn!(?flush, "outbound queues not flushed before close");
+        }
         state.realtime().best_effort_shutdown_all("draining");
 
-        let deadline = Instant::now() + std::time::Duration::from_millis(drain_grace_ms);
         while Instant::now() < deadline {
             if state.realtime().sessions.len_sessions() == 0 {
                 break;
             }
             tokio::time::sleep(std::time::Duration::from_millis(50)).await;
         }
-    })
-    .await
-    .expect("server failed");
+    });
+    match ops::shutdown::bounded(serve, signalled.notified(), shutdown_timeout).await {
+        Some(served) => served.expect("server failed"),
+        None => {
+            tracing::warn!(?shutdown_timeout, "shutdown timeout reached, forcing exit");
+            persist_on_exit(&handshake, &egress_state, Some(ops::shutdown::FORCED_EXIT_STEP_TIMEOUT)).await;
+            std::process::exit(0);
+        }
+    }
+
+    persist_on_exit(&handshake, &egress_state, None).await;
+    telemetry.shutdown().await;
+}
+
+/// Save handshake limiter state and flush the egress archive, each step
+/// bounded by `limit` when set (forced exit).
+async fn persist_on_exit(handshake: &HandshakeDefender, state: &app_state::AppState, limit: Option<std::time::Duration>) {
+    if ops::shutdown::exit_step(handshake.save_state(), limit).await.is_none() {
+        tracing::warn!(?limit, "handshake limiter state not saved: forced exit");
+    }
+    if let Some(egress) = state.egress() {
+        match ops::shutdown::exit_step(egress.shutdown(), limit).await {
+            Some(true) => {}
+            Some(false) => tracing::warn!("egress records not flushed before exit"),
+            None => tracing::warn!(?limit, "egress records dropped: forced exit"),
+        }
+    }
 }
 
 async fn shutdown_signal() {
@@ -3,24 +3,247 @@
 //! No external dependencies are used; this module provides counter/gauge/histogram
 //! types with dynamic labels backed by `DashMap`. Labels are flattened into
 //! sorted key vectors to keep deterministic ordering. Histogram buckets are
-//! fixed in microseconds to avoid floating point math.
+//! integer bounds (microseconds or bytes) to avoid floating point math;
+//! duration histograms render their bounds and sums in seconds.
+//!
+//! Each metric accepts at most `max_label_values_per_metric` distinct values
+//! per label name; later values are folded into `"_other"` so client-chosen
+//! strings cannot grow the registry without bound. On top of that, a metric
+//! holds at most `gateway.metrics.max_series_per_metric` label sets; new
+//! sets past that are recorded under one series whose labels are all
+//! `"_overflow"`. Series not written for `gateway.metrics.series_ttl_secs`
+//! are pruned (gauges only while at 0) and re-created on the next write.
+//!
+//! Services can add their own metrics at runtime with
+//! [`GatewayMetrics::register_counter`] (and `_gauge` / `_histogram`); they
+//! render after the built-in ones under the exact name given.
 
 use dashmap::DashMap;
+use std::collections::HashSet;
 use std::fmt::Write;
 use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
-use std::time::Duration;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use wsprism_core::error::{Result as WsResult, WsPrismError};
+
+use crate::config::schema::MetricsConfig;
 
 /// Helper to escape label values.
-fn escape_label(v: &str) -> String {
+pub(crate) fn escape_label(v: &str) -> String {
     v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
 }
 
-#[derive(Default)]
+/// Default distinct values per label name and metric.
+pub const DEFAULT_MAX_LABEL_VALUES: usize = 1000;
+
+/// Label value that absorbs values past the cap.
+pub const OVERFLOW_LABEL_VALUE: &str = "_other";
+
+/// Default label sets per metric.
+pub const DEFAULT_MAX_SERIES: usize = 10_000;
+
+/// Label value of the series absorbing label sets past the per-metric cap.
+pub const OVERFLOW_SERIES_VALUE: &str = "_overflow";
+
+/// Default idle time after which a series is pruned.
+pub const DEFAULT_SERIES_TTL: Duration = Duration::from_secs(3600);
+
+type LabelKey = Vec<(String, String)>;
+
+/// Per-metric label cardinality guard.
+struct LabelCap {
+    max: usize,
+    seen: DashMap<String, HashSet<String>>,
+}
+
+impl LabelCap {
+    fn new(max: usize) -> Self {
+        Self { max, seen: DashMap::new() }
+    }
+
+    /// `v`, or `_other` once `k` already has `max` other values.
+    fn admit<'a>(&self, k: &str, v: &'a str) -> &'a str {
+        if self.seen.get(k).is_some_and(|vals| vals.contains(v)) {
+            return v;
+        }
+        let mut vals = self.seen.entry(k.to_string()).or_default();
+        if vals.contains(v) || vals.len() < self.max {
+            vals.insert(v.to_string());
+            v
+        } else {
+            OVERFLOW_LABEL_VALUE
+        }
+    }
+
+    /// Sorted registry key for `labels`, with overflow folding.
+    fn key(&self, labels: &[(&str, &str)]) -> LabelKey {
+        let mut key: LabelKey = labels.iter()
+            .map(|(k, v)| (k.to_string(), self.admit(k, v).to_string()))
+            .collect();
+        key.sort();
+        key
+    }
+}
+
+/// Exact (unfolded) key, for lookups.
+fn raw_key(labels: &[(&str, &str)]) -> LabelKey {
+    let mut key: LabelKey = labels.iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    key.sort();
+    key
+}
+
+fn label_str(key: &LabelKey) -> String {
+    key.iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+        .collect::<Vec<_>>().join(",")
+}
+
+/// Milliseconds since the first call, for series timestamps.
+fn now_ms() -> u64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
+
+/// A series value and when it was last written.
+struct Stamped<V> {
+    value: V,
+    touched_ms: AtomicU64,
+}
+
+/// Label sets of one metric, with the label and series caps and the
+/// prune/overflow tallies.
+struct SeriesMap<V> {
+    map: DashMap<LabelKey, Stamped<V>>,
+    cap: LabelCap,
+    max_series: usize,
+    pruned: AtomicU64,
+    overflowed: AtomicU64,
+}
+
+impl<V> SeriesMap<V> {
+    fn new(max_label_values: usize) -> Self {
+        Self {
+            map: DashMap::new(),
+            cap: LabelCap::new(max_label_values),
+            max_series: DEFAULT_MAX_SERIES,
+            pruned: AtomicU64::new(0),
+            overflowed: AtomicU64::new(0),
+        }
+    }
+
+    /// Apply `f` to the series for `labels`, creating it with `init`. The
+    /// series cap is checked without a lock, so concurrent first writes may
+    /// overshoot it slightly.
+    fn update(&self, labels: &[(&str, &str)], init: impl FnOnce() -> V, f: impl FnOnce(&V)) {
+        let key = self.cap.key(labels);
+        let now = now_ms();
+        // The shard lock is held while `f` runs, so `prune` cannot drop the
+        // series between the write and its timestamp.
+        if let Some(s) = self.map.get(&key) {
+            s.touched_ms.store(now, Ordering::Relaxed);
+            f(&s.value);
+            return;
+        }
+        let key = if self.map.len() >= self.max_series {
+            self.overflowed.fetch_add(1, Ordering::Relaxed);
+            key.into_iter().map(|(k, _)| (k, OVERFLOW_SERIES_VALUE.to_string())).collect()
+        } else {
+            key
+        };
+        let s = self.map.entry(key).or_insert_with(|| Stamped { value: init(), touched_ms: AtomicU64::new(now) });
+        s.touched_ms.store(now, Ordering::Relaxed);
+        f(&s.value);
+    }
+
+    /// Value for an exact label set.
+    fn get<T>(&self, labels: &[(&str, &str)], f: impl FnOnce(&V) -> T) -> Option<T> {
+        self.map.get(&raw_key(labels)).map(|s| f(&s.value))
+    }
+
+    /// Drop every series for which `keep` is false, and forget label values
+    /// no remaining series uses so they stop counting against the cap.
+    fn retain_labels(&self, keep: impl Fn(&LabelKey) -> bool) {
+        self.map.retain(|k, _| keep(k));
+        let mut used: HashSet<(String, String)> = HashSet::new();
+        for r in self.map.iter() {
+            used.extend(r.key().iter().cloned());
+        }
+        self.cap.seen.retain(|name, vals| {
+            vals.retain(|v| used.contains(&(name.clone(), v.clone())));
+            !vals.is_empty()
+        });
+    }
+
+    /// Entries in label order, so output is stable.
+    fn sorted<T>(&self, f: impl Fn(&V) -> T) -> Vec<(LabelKey, T)> {
+        let mut v: Vec<(LabelKey, T)> = self.map.iter().map(|r| (r.key().clone(), f(&r.value().value))).collect();
+        v.sort_by(|a, b| a.0.cmp(&b.0));
+        v
+    }
+
+    /// Drop series not written for `older_than`, unless `keep` holds for
+    /// their value. Returns how many were dropped.
+    fn prune(&self, older_than: Duration, keep: impl Fn(&V) -> bool) -> usize {
+        let now = now_ms();
+        let ttl = older_than.as_millis() as u64;
+        let before = self.map.len();
+        self.map.retain(|_, s| {
+            now.saturating_sub(s.touched_ms.load(Ordering::Relaxed)) < ttl || keep(&s.value)
+        });
+        let dropped = before.saturating_sub(self.map.len());
+        self.pruned.fetch_add(dropped as u64, Ordering::Relaxed);
+        dropped
+    }
+}
+
+/// Series bookkeeping shared by the label-keyed metric types.
+pub trait SeriesStats {
+    /// Label sets currently held.
+    fn series_count(&self) -> usize;
+    /// Series removed by pruning so far.
+    fn pruned_series(&self) -> u64;
+    /// Writes of new label sets folded into the `_overflow` series so far.
+    fn overflowed_series(&self) -> u64;
+    /// Drop stale series; see the type's inherent `prune`.
+    fn prune_stale(&self, older_than: Duration) -> usize;
+}
+
+macro_rules! impl_series_stats {
+    ($($ty:ty),*) => {$(
+        impl SeriesStats for $ty {
+            fn series_count(&self) -> usize { self.series.map.len() }
+            fn pruned_series(&self) -> u64 { self.series.pruned.load(Ordering::Relaxed) }
+            fn overflowed_series(&self) -> u64 { self.series.overflowed.load(Ordering::Relaxed) }
+            fn prune_stale(&self, older_than: Duration) -> usize { self.prune(older_than) }
+        }
+    )*};
+}
+
+impl_series_stats!(CounterVec, GaugeVec, HistogramVec, SummaryVec);
+
 pub struct CounterVec {
-    map: DashMap<Vec<(String, String)>, AtomicU64>,
+    series: SeriesMap<AtomicU64>,
+}
+
+impl Default for CounterVec {
+    fn default() -> Self {
+        Self::with_max_label_values(DEFAULT_MAX_LABEL_VALUES)
+    }
 }
 
 impl CounterVec {
+    pub fn with_max_label_values(max: usize) -> Self {
+        Self { series: SeriesMap::new(max) }
+    }
+
+    /// Same labels cap, holding at most `max` label sets.
+    pub fn with_max_series(mut self, max: usize) -> Self {
+        self.series.max_series = max;
+        self
+    }
+
     /// Increment by 1.
     pub fn inc(&self, labels: &[(&str, &str)]) {
         self.add(labels, 1);
@@ -28,35 +251,52 @@ impl CounterVec {
 
     /// Increment by an arbitrary value.
     pub fn add(&self, labels: &[(&str, &str)], v: u64) {
-        let mut key: Vec<(String, String)> = labels.iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect();
-        key.sort();
-        
-        let counter = self.map.entry(key).or_insert_with(|| AtomicU64::new(0));
-        counter.fetch_add(v, Ordering::Relaxed);
+        self.series.update(labels, || AtomicU64::new(0), |c| {
+            c.fetch_add(v, Ordering::Relaxed);
+        });
+    }
+
+    /// Current value for an exact label set (0 if never incremented).
+    pub fn get(&self, labels: &[(&str, &str)]) -> u64 {
+        self.series.get(labels, |c| c.load(Ordering::Relaxed)).unwrap_or(0)
+    }
+
+    /// Drop series not incremented for `older_than`. A later increment
+    /// starts the series again from 0, which scrapers treat as a reset.
+    pub fn prune(&self, older_than: Duration) -> usize {
+        self.series.prune(older_than, |_| false)
     }
 
     /// Render in Prometheus text exposition format.
     fn render(&self, name: &str, out: &mut String) {
         let _ = writeln!(out, "# TYPE {} counter", name);
-        for r in self.map.iter() {
-            let key = r.key();
-            let val = r.value().load(Ordering::Relaxed);
-            let label_str = key.iter()
-                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
-                .collect::<Vec<_>>().join(",");
-            let _ = writeln!(out, "{}{{{}}} {}", name, label_str, val);
+        for (key, val) in self.series.sorted(|c| c.load(Ordering::Relaxed)) {
+            let _ = writeln!(out, "{}{{{}}} {}", name, label_str(&key), val);
         }
     }
 }
 
-#[derive(Default)]
 pub struct GaugeVec {
-    map: DashMap<Vec<(String, String)>, AtomicI64>,
+    series: SeriesMap<AtomicI64>,
+}
+
+impl Default for GaugeVec {
+    fn default() -> Self {
+        Self::with_max_label_values(DEFAULT_MAX_LABEL_VALUES)
+    }
 }
 
 impl GaugeVec {
+    pub fn with_max_label_values(max: usize) -> Self {
+        Self { series: SeriesMap::new(max) }
+    }
+
+    /// Same labels cap, holding at most `max` label sets.
+    pub fn with_max_series(mut self, max: usize) -> Self {
+        self.series.max_series = max;
+        self
+    }
+
     /// Increment by 1.
     pub fn inc(&self, labels: &[(&str, &str)]) { self.add(labels, 1); }
     /// Decrement by 1.
@@ -64,144 +304,844 @@ impl GaugeVec {
 
     /// Add an arbitrary signed delta.
     pub fn add(&self, labels: &[(&str, &str)], v: i64) {
-        let mut key: Vec<(String, String)> = labels.iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect();
-        key.sort();
-        let gauge = self.map.entry(key).or_insert_with(|| AtomicI64::new(0));
-        gauge.fetch_add(v, Ordering::Relaxed);
+        self.series.update(labels, || AtomicI64::new(0), |g| {
+            g.fetch_add(v, Ordering::Relaxed);
+        });
+    }
+
+    /// Overwrite the value (for sampled gauges).
+    pub fn set(&self, labels: &[(&str, &str)], v: i64) {
+        self.series.update(labels, || AtomicI64::new(0), |g| g.store(v, Ordering::Relaxed));
+    }
+
+    /// Current value for an exact label set (0 if never set).
+    pub fn get(&self, labels: &[(&str, &str)]) -> i64 {
+        self.series.get(labels, |g| g.load(Ordering::Relaxed)).unwrap_or(0)
+    }
+
+    /// Drop series at 0 that were not written for `older_than`. Non-zero
+    /// gauges are kept however old, since their value is still current.
+    pub fn prune(&self, older_than: Duration) -> usize {
+        self.series.prune(older_than, |g| g.load(Ordering::Relaxed) != 0)
+    }
+
+    /// Keep only the series whose labels (sorted by name) satisfy `keep`,
+    /// for gauges that track a changing set such as the largest rooms.
+    pub fn retain(&self, keep: impl Fn(&[(String, String)]) -> bool) {
+        self.series.retain_labels(|k| keep(k));
     }
 
     /// Render in Prometheus text exposition format.
     fn render(&self, name: &str, out: &mut String) {
         let _ = writeln!(out, "# TYPE {} gauge", name);
-        for r in self.map.iter() {
-            let key = r.key();
-            let val = r.value().load(Ordering::Relaxed);
-            let label_str = key.iter()
-                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
-                .collect::<Vec<_>>().join(",");
-            let _ = writeln!(out, "{}{{{}}} {}", name, label_str, val);
+        for (key, val) in self.series.sorted(|g| g.load(Ordering::Relaxed)) {
+            let _ = writeln!(out, "{}{{{}}} {}", name, label_str(&key), val);
         }
     }
 }
 
-// Fixed Buckets in Microseconds (µs)
-// 100us, 500us, 1ms, 5ms, 10ms, 50ms, 100ms, 500ms, 1s
-const BUCKETS_MICROS: [u64; 9] = [100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000];
+/// Default duration buckets in microseconds: 100us .. 10s.
+pub const DEFAULT_BUCKETS_MICROS: [u64; 12] = [
+    100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000, 2_500_000, 5_000_000, 10_000_000,
+];
+
+/// Default size buckets in bytes: 64B .. 256KiB.
+pub const DEFAULT_BUCKETS_BYTES: [u64; 8] = [64, 256, 512, 1_024, 4_096, 16_384, 65_536, 262_144];
+
+/// Microseconds as a decimal seconds string (`100` -> `0.0001`, `1000000` -> `1`).
+fn micros_as_seconds(v: u64) -> String {
+    let (secs, frac) = (v / 1_000_000, v % 1_000_000);
+    if frac == 0 {
+        return secs.to_string();
+    }
+    let frac = format!("{frac:06}");
+    format!("{secs}.{}", frac.trim_end_matches('0'))
+}
 
 struct AtomicHistogram {
     count: AtomicU64,
     sum: AtomicU64,
-    buckets: [AtomicU64; 9],
+    buckets: Box<[AtomicU64]>,
 }
 
-impl Default for AtomicHistogram {
-    fn default() -> Self {
+impl AtomicHistogram {
+    fn new(n: usize) -> Self {
         Self {
             count: AtomicU64::new(0),
             sum: AtomicU64::new(0),
-            buckets: [
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)
-            ],
+            buckets: (0..n).map(|_| AtomicU64::new(0)).collect(),
         }
     }
 }
 
-#[derive(Default)]
 pub struct HistogramVec {
-    map: DashMap<Vec<(String, String)>, AtomicHistogram>,
+    bounds: Box<[u64]>,
+    /// Values are microseconds, rendered as seconds.
+    micros: bool,
+    /// Unit announced with `# UNIT` when the rendered name ends in it.
+    unit: Option<&'static str>,
+    series: SeriesMap<AtomicHistogram>,
+}
+
+/// Duration histogram (default microsecond buckets).
+impl Default for HistogramVec {
+    fn default() -> Self {
+        Self::micros(&DEFAULT_BUCKETS_MICROS)
+    }
 }
 
 impl HistogramVec {
+    /// Histogram with custom (ascending) bucket upper bounds, rendered as
+    /// plain integers.
+    pub fn with_buckets(bounds: &[u64]) -> Self {
+        Self { bounds: bounds.into(), micros: false, unit: None, series: SeriesMap::new(DEFAULT_MAX_LABEL_VALUES) }
+    }
+
+    /// Duration histogram with custom bucket bounds in microseconds; bounds
+    /// and sums render in seconds.
+    pub fn micros(bounds: &[u64]) -> Self {
+        Self { micros: true, unit: Some("seconds"), ..Self::with_buckets(bounds) }
+    }
+
+    /// Same buckets, with a different label value cap.
+    pub fn with_max_label_values(mut self, max: usize) -> Self {
+        self.series.cap = LabelCap::new(max);
+        self
+    }
+
+    /// Same buckets, holding at most `max` label sets.
+    pub fn with_max_series(mut self, max: usize) -> Self {
+        self.series.max_series = max;
+        self
+    }
+
+    /// Size histogram (default byte buckets, 64 B to 256 KiB).
+    pub fn bytes() -> Self {
+        Self::bytes_with_buckets(&DEFAULT_BUCKETS_BYTES)
+    }
+
+    /// Size histogram with custom bucket bounds in bytes.
+    pub fn bytes_with_buckets(bounds: &[u64]) -> Self {
+        Self { unit: Some("bytes"), ..Self::with_buckets(bounds) }
+    }
+
     /// Observe a duration and increment cumulative buckets (microsecond scale).
     pub fn observe(&self, labels: &[(&str, &str)], duration: Duration) {
-        let mut key: Vec<(String, String)> = labels.iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect();
-        key.sort();
-
-        let hist = self.map.entry(key).or_insert_with(AtomicHistogram::default);
-        let micros = duration.as_micros() as u64;
+        self.observe_value(labels, duration.as_micros() as u64);
+    }
 
-        hist.count.fetch_add(1, Ordering::Relaxed);
-        hist.sum.fetch_add(micros, Ordering::Relaxed); // Record sum in micros
+    /// Observe a raw value in the histogram's unit.
+    pub fn observe_value(&self, labels: &[(&str, &str)], value: u64) {
+        self.series.update(labels, || AtomicHistogram::new(self.bounds.len()), |hist| {
+            hist.count.fetch_add(1, Ordering::Relaxed);
+            hist.sum.fetch_add(value, Ordering::Relaxed);
 
-        // Cumulative Buckets: Increment ALL buckets larger than value
-        for (i, &b) in BUCKETS_MICROS.iter().enumerate() {
-            if micros <= b {
-                hist.buckets[i].fetch_add(1, Ordering::Relaxed);
+            // Cumulative Buckets: Increment ALL buckets larger than value
+            for (i, &b) in self.bounds.iter().enumerate() {
+                if value <= b {
+                    hist.buckets[i].fetch_add(1, Ordering::Relaxed);
+                }
             }
-        }
+        });
+    }
+
+    /// Cumulative count of the `le` bucket for an exact label set
+    /// (`None` if `le` is not a bucket bound). `le` is in the histogram's
+    /// unit (microseconds for durations).
+    pub fn bucket_count(&self, labels: &[(&str, &str)], le: u64) -> Option<u64> {
+        let i = self.bounds.iter().position(|&b| b == le)?;
+        Some(self.series.get(labels, |h| h.buckets[i].load(Ordering::Relaxed)).unwrap_or(0))
+    }
+
+    /// Drop series with no observation for `older_than`.
+    pub fn prune(&self, older_than: Duration) -> usize {
+        self.series.prune(older_than, |_| false)
     }
 
-    /// Render in Prometheus text exposition format (unit: microseconds).
+    /// Render in Prometheus text exposition format (seconds for durations).
     fn render(&self, name: &str, out: &mut String) {
+        self.render_with(name, out, self.micros);
+    }
+
+    /// Render with integer `le` bounds and sums in the raw unit (the format
+    /// before durations were rendered in seconds).
+    fn render_raw(&self, name: &str, out: &mut String) {
+        self.render_with(name, out, false);
+    }
+
+    fn render_with(&self, name: &str, out: &mut String, seconds: bool) {
+        let fmt = |v: u64| if seconds { micros_as_seconds(v) } else { v.to_string() };
         let _ = writeln!(out, "# TYPE {} histogram", name);
-        for r in self.map.iter() {
-            let key = r.key();
-            let hist = r.value();
-            
-            let label_str = key.iter()
-                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
-                .collect::<Vec<_>>().join(",");
+        // OpenMetrics requires the family name to end in its unit.
+        if let Some(unit) = self.unit.filter(|u| name.strip_suffix(u).is_some_and(|n| n.ends_with('_'))) {
+            let _ = writeln!(out, "# UNIT {} {}", name, unit);
+        }
+        let mut keys: Vec<LabelKey> = self.series.map.iter().map(|r| r.key().clone()).collect();
+        keys.sort();
+        for key in keys {
+            let Some(entry) = self.series.map.get(&key) else { continue };
+            let hist = &entry.value;
+            let label_str = label_str(&key);
             let prefix = if label_str.is_empty() { String::new() } else { format!("{},", label_str) };
 
-            for (i, &le) in BUCKETS_MICROS.iter().enumerate() {
+            for (i, &le) in self.bounds.iter().enumerate() {
                 let count = hist.buckets[i].load(Ordering::Relaxed);
-                // Convert bucket le from micros to seconds string for standard Prometheus display? 
-                // Or just keep int? Standard is seconds (float).
-                // For simplicity in this int-based impl, we output micros but should ideally label unit.
-                // Let's output integer micros for 'le'.
-                let _ = writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, prefix, le, count);
+                let _ = writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, prefix, fmt(le), count);
             }
             let count = hist.count.load(Ordering::Relaxed);
             let _ = writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, prefix, count);
             
             let sum = hist.sum.load(Ordering::Relaxed);
-            let _ = writeln!(out, "{}_sum{{{}}} {}", name, label_str, sum);
+            let _ = writeln!(out, "{}_sum{{{}}} {}", name, label_str, fmt(sum));
             let _ = writeln!(out, "{}_count{{{}}} {}", name, label_str, count);
         }
     }
 }
 
-#[derive(Default)]
+/// Observations kept per label set for summary quantiles.
+pub const SUMMARY_WINDOW: usize = 1024;
+
+/// Quantiles reported by [`SummaryVec`].
+const SUMMARY_QUANTILES: [(&str, f64); 4] = [("0.5", 0.5), ("0.9", 0.9), ("0.99", 0.99), ("0.999", 0.999)];
+
+/// Ring buffer of the most recent observations (microseconds).
+struct SummaryRing {
+    slots: Box<[AtomicU64]>,
+    /// Observations ever made; the next slot is `next % SUMMARY_WINDOW`.
+    next: AtomicU64,
+    sum: AtomicU64,
+}
+
+impl SummaryRing {
+    fn new() -> Self {
+        Self {
+            slots: (0..SUMMARY_WINDOW).map(|_| AtomicU64::new(0)).collect(),
+            next: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+        }
+    }
+
+    /// Sorted copy of the retained observations.
+    fn sorted_window(&self) -> Vec<u64> {
+        let n = (self.next.load(Ordering::Relaxed) as usize).min(SUMMARY_WINDOW);
+        let mut v: Vec<u64> = self.slots[..n].iter().map(|s| s.load(Ordering::Relaxed)).collect();
+        v.sort_unstable();
+        v
+    }
+}
+
+/// Nearest-rank quantile of a sorted, non-empty slice.
+fn quantile(sorted: &[u64], q: f64) -> u64 {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Duration summary: p50/p90/p99/p999 over the last [`SUMMARY_WINDOW`]
+/// observations per label set, rendered in seconds. `_sum` and `_count`
+/// cover all observations. Best effort under concurrency: a render racing an
+/// observe may see that slot's previous value.
+pub struct SummaryVec {
+    series: SeriesMap<SummaryRing>,
+}
+
+impl Default for SummaryVec {
+    fn default() -> Self {
+        Self::with_max_label_values(DEFAULT_MAX_LABEL_VALUES)
+    }
+}
+
+impl SummaryVec {
+    pub fn with_max_label_values(max: usize) -> Self {
+        Self { series: SeriesMap::new(max) }
+    }
+
+    /// Same labels cap, holding at most `max` label sets.
+    pub fn with_max_series(mut self, max: usize) -> Self {
+        self.series.max_series = max;
+        self
+    }
+
+    /// Record a duration (microsecond resolution).
+    pub fn observe(&self, labels: &[(&str, &str)], duration: Duration) {
+        let v = duration.as_micros() as u64;
+        self.series.update(labels, SummaryRing::new, |ring| {
+            let i = ring.next.fetch_add(1, Ordering::Relaxed) as usize % SUMMARY_WINDOW;
+            ring.slots[i].store(v, Ordering::Relaxed);
+            ring.sum.fetch_add(v, Ordering::Relaxed);
+        });
+    }
+
+    /// Quantile `q` (0..=1) over the retained window for an exact label set,
+    /// in microseconds. `None` before the first observation.
+    pub fn quantile(&self, labels: &[(&str, &str)], q: f64) -> Option<u64> {
+        let window = self.series.get(labels, SummaryRing::sorted_window)?;
+        (!window.is_empty()).then(|| quantile(&window, q))
+    }
+
+    /// Drop series with no observation for `older_than`.
+    pub fn prune(&self, older_than: Duration) -> usize {
+        self.series.prune(older_than, |_| false)
+    }
+
+    /// Render in Prometheus text exposition format.
+    fn render(&self, name: &str, out: &mut String) {
+        let _ = writeln!(out, "# TYPE {} summary", name);
+        let mut keys: Vec<LabelKey> = self.series.map.iter().map(|r| r.key().clone()).collect();
+        keys.sort();
+        for key in keys {
+            let Some(entry) = self.series.map.get(&key) else { continue };
+            let ring = &entry.value;
+            let window = ring.sorted_window();
+            if window.is_empty() {
+                continue;
+            }
+            let label_str = label_str(&key);
+            let prefix = if label_str.is_empty() { String::new() } else { format!("{},", label_str) };
+            for (q_str, q) in SUMMARY_QUANTILES {
+                let v = micros_as_seconds(quantile(&window, q));
+                let _ = writeln!(out, "{}{{{}quantile=\"{}\"}} {}", name, prefix, q_str, v);
+            }
+            let sum = micros_as_seconds(ring.sum.load(Ordering::Relaxed));
+            let _ = writeln!(out, "{}_sum{{{}}} {}", name, label_str, sum);
+            let _ = writeln!(out, "{}_count{{{}}} {}", name, label_str, ring.next.load(Ordering::Relaxed));
+        }
+    }
+}
+
+/// Direction of an observed WebSocket frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDir {
+    Inbound,
+    Outbound,
+}
+
+/// Which handshake limiter rejected a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeScope {
+    Global,
+    PerIp,
+}
+
+impl HandshakeScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Global => "global",
+            Self::PerIp => "per_ip",
+        }
+    }
+}
+
+/// Handshake defender counters. Plain atomics rather than label maps, since
+/// `check` runs before every upgrade.
+#[derive(Debug, Default)]
+pub struct HandshakeMetrics {
+    checks: AtomicU64,
+    limited_global: AtomicU64,
+    limited_per_ip: AtomicU64,
+    ip_entries: AtomicU64,
+}
+
+impl HandshakeMetrics {
+    pub fn note_check(&self) {
+        self.checks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn note_limited(&self, scope: HandshakeScope) {
+        self.limited(scope).fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Current size of the per-IP limiter map.
+    pub fn set_ip_entries(&self, n: usize) {
+        self.ip_entries.store(n as u64, Ordering::Relaxed);
+    }
+
+    pub fn checks(&self) -> u64 {
+        self.checks.load(Ordering::Relaxed)
+    }
+
+    pub fn limited_count(&self, scope: HandshakeScope) -> u64 {
+        self.limited(scope).load(Ordering::Relaxed)
+    }
+
+    pub fn ip_entries(&self) -> u64 {
+        self.ip_entries.load(Ordering::Relaxed)
+    }
+
+    fn limited(&self, scope: HandshakeScope) -> &AtomicU64 {
+        match scope {
+            HandshakeScope::Global => &self.limited_global,
+            HandshakeScope::PerIp => &self.limited_per_ip,
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# TYPE wsprism_handshake_checks_total counter");
+        let _ = writeln!(out, "wsprism_handshake_checks_total {}", self.checks());
+        let _ = writeln!(out, "# TYPE wsprism_handshake_limited_total counter");
+        for scope in [HandshakeScope::Global, HandshakeScope::PerIp] {
+            let _ = writeln!(out, "wsprism_handshake_limited_total{{scope=\"{}\"}} {}", scope.as_str(), self.limited_count(scope));
+        }
+        let _ = writeln!(out, "# TYPE wsprism_handshake_ip_entries gauge");
+        let _ = writeln!(out, "wsprism_handshake_ip_entries {}", self.ip_entries());
+    }
+}
+
+/// Crate version, commit and compiler, fixed at build time (`build.rs`).
+pub const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const BUILD_GIT_SHA: &str = match option_env!("WSPRISM_GIT_SHA") {
+    Some(sha) => sha,
+    None => "unknown",
+};
+pub const BUILD_RUSTC: &str = match option_env!("WSPRISM_RUSTC_VERSION") {
+    Some(v) => v,
+    None => "unknown",
+};
+
+/// Process gauges refreshed by the owner of the gateway state before each
+/// scrape (see `AppState::refresh_process_metrics`).
+#[derive(Debug, Default)]
+pub struct ProcessMetrics {
+    uptime_secs: AtomicU64,
+    sessions: AtomicU64,
+    worker_threads: AtomicU64,
+}
+
+impl ProcessMetrics {
+    pub fn set(&self, uptime: Duration, sessions: usize, worker_threads: usize) {
+        self.uptime_secs.store(uptime.as_secs(), Ordering::Relaxed);
+        self.sessions.store(sessions as u64, Ordering::Relaxed);
+        self.worker_threads.store(worker_threads as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# TYPE wsprism_build_info gauge");
+        let _ = writeln!(
+            out,
+            "wsprism_build_info{{git_sha=\"{}\",rustc=\"{}\",version=\"{}\"}} 1",
+            escape_label(BUILD_GIT_SHA),
+            escape_label(BUILD_RUSTC),
+            escape_label(BUILD_VERSION),
+        );
+        let _ = writeln!(out, "# TYPE wsprism_uptime_seconds gauge");
+        let _ = writeln!(out, "wsprism_uptime_seconds {}", self.uptime_secs.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# TYPE wsprism_sessions_open gauge");
+        let _ = writeln!(out, "wsprism_sessions_open {}", self.sessions.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# TYPE wsprism_tokio_worker_threads gauge");
+        let _ = writeln!(out, "wsprism_tokio_worker_threads {}", self.worker_threads.load(Ordering::Relaxed));
+    }
+}
+
+/// Name prefix of the built-in metrics, not available to custom ones.
+pub const RESERVED_METRIC_PREFIX: &str = "wsprism_";
+
+/// A metric registered by a service at runtime.
+#[derive(Clone)]
+enum CustomMetric {
+    Counter(Arc<CounterVec>),
+    Gauge(Arc<GaugeVec>),
+    Histogram(Arc<HistogramVec>),
+}
+
+impl CustomMetric {
+    fn stats(&self) -> &dyn SeriesStats {
+        match self {
+            Self::Counter(m) => m.as_ref(),
+            Self::Gauge(m) => m.as_ref(),
+            Self::Histogram(m) => m.as_ref(),
+        }
+    }
+
+    fn render(&self, name: &str, out: &mut String) {
+        match self {
+            Self::Counter(m) => m.render(name, out),
+            Self::Gauge(m) => m.render(name, out),
+            Self::Histogram(m) => m.render(name, out),
+        }
+    }
+}
+
+/// `^[a-z][a-z0-9_]+$`, outside the built-in `wsprism_` namespace.
+fn validate_custom_name(name: &str) -> WsResult<()> {
+    let mut chars = name.chars();
+    let well_formed = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && name.len() >= 2
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !well_formed {
+        return Err(WsPrismError::BadRequest(format!("metric name {name:?} must match ^[a-z][a-z0-9_]+$")));
+    }
+    if name.starts_with(RESERVED_METRIC_PREFIX) {
+        return Err(WsPrismError::BadRequest(format!(
+            "metric name {name:?} uses the reserved {RESERVED_METRIC_PREFIX:?} prefix"
+        )));
+    }
+    Ok(())
+}
+
 pub struct GatewayMetrics {
     pub ws_upgrades: CounterVec,
     pub ws_active_sessions: GaugeVec,
     pub policy_decisions: CounterVec,
+    /// Shadow-mode rejects/closes that were let through.
+    pub policy_dryrun_violations: CounterVec,
     pub handshake_rejections: CounterVec,
+    pub handshake: HandshakeMetrics,
     pub dispatch_duration: HistogramVec, // In Microseconds
+    pub dispatch_latency_summary: SummaryVec,
+    pub inbound_frame_bytes: HistogramVec, // In Bytes
+    pub outbound_frame_bytes: HistogramVec, // In Bytes
+    pub bytes_in: CounterVec,
+    pub bytes_out: CounterVec,
+    pub bytes_sent: CounterVec,
+    pub bandwidth_limited: CounterVec,
     pub decode_errors: CounterVec,
     pub service_errors: CounterVec,
     pub writer_timeouts: CounterVec,
     pub unknown_service_errors: CounterVec,
+    pub dead_letters: CounterVec,
+    pub room_rate_limited: CounterVec,
+    pub plugin_errors: CounterVec,
+    pub process: ProcessMetrics,
+    pub outbound_queue_depth_max: GaugeVec,
+    pub outbound_queue_depth_p99: GaugeVec,
+    pub outbound_drops: CounterVec,
+    /// Connections of the largest rooms per tenant (`observability.room_gauges`).
+    pub room_members: GaugeVec,
+    /// Publish-to-delivery delay of room messages relayed from other replicas.
+    pub cluster_bridge_lag: HistogramVec, // In Microseconds
+    /// Cluster bridge failures by `kind`.
+    pub cluster_bridge_errors: CounterVec,
+    /// 1 while the cluster subscription is up, per `backend`.
+    pub cluster_connected: GaugeVec,
+    /// Egress records lost, by `tenant` and `reason`.
+    pub egress_dropped: CounterVec,
+    /// Webhook events lost, by `tenant` and `reason`.
+    pub webhook_dropped: CounterVec,
+    /// Time spent on forwarded Ext lane messages (`upstreams`,
+    /// `http_upstreams`), by `tenant` and `svc`.
+    pub upstream_duration: HistogramVec, // In Microseconds
+    /// Service-defined metrics by rendered name.
+    custom: DashMap<&'static str, CustomMetric>,
+    /// Caps applied to custom metrics, as for the built-in ones.
+    max_label_values: usize,
+    max_series: usize,
+    /// Render `dispatch_duration` under its old `_micros` name and format.
+    legacy_histograms: bool,
+    /// Idle time before `prune_stale` drops a series; `None` = never.
+    series_ttl: Option<Duration>,
     draining: std::sync::atomic::AtomicBool,
 }
 
+impl Default for GatewayMetrics {
+    fn default() -> Self {
+        Self::with_max_label_values_per_metric(DEFAULT_MAX_LABEL_VALUES)
+    }
+}
+
 impl GatewayMetrics {
+    /// Registry whose metrics each keep at most `max` values per label name.
+    pub fn with_max_label_values_per_metric(max: usize) -> Self {
+        Self::new(max, &MetricsConfig::default())
+    }
+
+    /// Registry with histogram settings from `gateway.metrics`.
+    pub fn new(max: usize, cfg: &MetricsConfig) -> Self {
+        let series = cfg.max_series_per_metric.unwrap_or(DEFAULT_MAX_SERIES);
+        let counter = || CounterVec::with_max_label_values(max).with_max_series(series);
+        let gauge = || GaugeVec::with_max_label_values(max).with_max_series(series);
+        let histogram = |bounds: &[u64], micros: bool| {
+            let h = if micros { HistogramVec::micros(bounds) } else { HistogramVec::bytes_with_buckets(bounds) };
+            h.with_max_label_values(max).with_max_series(series)
+        };
+        let dispatch = cfg.dispatch_buckets_micros.as_deref().unwrap_or(&DEFAULT_BUCKETS_MICROS);
+        let frame = cfg.frame_buckets_bytes.as_deref().unwrap_or(&DEFAULT_BUCKETS_BYTES);
+        let ttl_secs = cfg.series_ttl_secs.unwrap_or(DEFAULT_SERIES_TTL.as_secs());
+        Self {
+            ws_upgrades: counter(),
+            ws_active_sessions: gauge(),
+            policy_decisions: counter(),
+            policy_dryrun_violations: counter(),
+            handshake_rejections: counter(),
+            handshake: HandshakeMetrics::default(),
+            dispatch_duration: histogram(dispatch, true),
+            dispatch_latency_summary: SummaryVec::with_max_label_values(max).with_max_series(series),
+            inbound_frame_bytes: histogram(frame, false),
+            outbound_frame_bytes: histogram(frame, false),
+            bytes_in: counter(),
+            bytes_out: counter(),
+            bytes_sent: counter(),
+            bandwidth_limited: counter(),
+            decode_errors: counter(),
+            service_errors: counter(),
+            writer_timeouts: counter(),
+            unknown_service_errors: counter(),
+            dead_letters: counter(),
+            room_rate_limited: counter(),
+            plugin_errors: counter(),
+            process: ProcessMetrics::default(),
+            outbound_queue_depth_max: gauge(),
+            outbound_queue_depth_p99: gauge(),
+            room_members: gauge(),
+            outbound_drops: counter(),
+            cluster_bridge_lag: histogram(&DEFAULT_BUCKETS_MICROS, true),
+            cluster_bridge_errors: counter(),
+            cluster_connected: gauge(),
+            egress_dropped: counter(),
+            webhook_dropped: counter(),
+            upstream_duration: histogram(dispatch, true),
+            custom: DashMap::new(),
+            max_label_values: max,
+            max_series: series,
+            legacy_histograms: cfg.legacy_histogram_names,
+            series_ttl: (ttl_secs > 0).then(|| Duration::from_secs(ttl_secs)),
+            draining: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    /// Record a dispatch duration in the histogram and the summary.
+    pub fn observe_dispatch(&self, labels: &[(&str, &str)], duration: Duration) {
+        self.dispatch_duration.observe(labels, duration);
+        self.dispatch_latency_summary.observe(labels, duration);
+    }
+
+    /// Record a data frame's size in `wsprism_{inbound,outbound}_frame_bytes`
+    /// (`lane` is `ext` for text, `hot` for binary).
+    pub fn observe_frame_size(&self, dir: FrameDir, tenant: &str, lane: &str, bytes: usize) {
+        let hist = match dir {
+            FrameDir::Inbound => &self.inbound_frame_bytes,
+            FrameDir::Outbound => &self.outbound_frame_bytes,
+        };
+        hist.observe_value(&[("tenant", tenant), ("lane", lane)], bytes as u64);
+    }
+
+    /// Add a data frame's size to `wsprism_bytes_{in,out}_total`. Outbound
+    /// frames are counted once written to the socket, so drops are excluded.
+    pub fn count_frame_bytes(&self, dir: FrameDir, tenant: &str, lane: &str, bytes: usize) {
+        let counter = match dir {
+            FrameDir::Inbound => &self.bytes_in,
+            FrameDir::Outbound => &self.bytes_out,
+        };
+        counter.add(&[("tenant", tenant), ("lane", lane)], bytes as u64);
+    }
+
+    /// Counter named `name`, registered on first use. Registering the same
+    /// name again returns the same counter; a name already used by another
+    /// kind of metric, or not matching `^[a-z][a-z0-9_]+$`, is rejected.
+    /// Names starting with `wsprism_` are reserved for built-in metrics.
+    pub fn register_counter(&self, name: &'static str) -> WsResult<Arc<CounterVec>> {
+        let init = || {
+            let m = CounterVec::with_max_label_values(self.max_label_values).with_max_series(self.max_series);
+            CustomMetric::Counter(Arc::new(m))
+        };
+        match self.register(name, init)? {
+            CustomMetric::Counter(m) => Ok(m),
+            _ => Err(kind_conflict(name)),
+        }
+    }
+
+    /// Gauge named `name`; see [`register_counter`](Self::register_counter).
+    pub fn register_gauge(&self, name: &'static str) -> WsResult<Arc<GaugeVec>> {
+        let init = || {
+            let m = GaugeVec::with_max_label_values(self.max_label_values).with_max_series(self.max_series);
+            CustomMetric::Gauge(Arc::new(m))
+        };
+        match self.register(name, init)? {
+            CustomMetric::Gauge(m) => Ok(m),
+            _ => Err(kind_conflict(name)),
+        }
+    }
+
+    /// Duration histogram (default buckets, rendered in seconds) named
+    /// `name`; see [`register_counter`](Self::register_counter).
+    pub fn register_histogram(&self, name: &'static str) -> WsResult<Arc<HistogramVec>> {
+        let init = || {
+            let m = HistogramVec::default().with_max_label_values(self.max_label_values).with_max_series(self.max_series);
+            CustomMetric::Histogram(Arc::new(m))
+        };
+        match self.register(name, init)? {
+            CustomMetric::Histogram(m) => Ok(m),
+            _ => Err(kind_conflict(name)),
+        }
+    }
+
+    fn register(&self, name: &'static str, init: impl FnOnce() -> CustomMetric) -> WsResult<CustomMetric> {
+        validate_custom_name(name)?;
+        Ok(self.custom.entry(name).or_insert_with(init).clone())
+    }
+
+    /// Custom metrics sorted by name, so output is stable.
+    fn custom_metrics(&self) -> Vec<(&'static str, CustomMetric)> {
+        let mut v: Vec<_> = self.custom.iter().map(|r| (*r.key(), r.value().clone())).collect();
+        v.sort_by_key(|(name, _)| *name);
+        v
+    }
+
+    /// Label-keyed metrics by rendered name.
+    fn series_metrics(&self) -> [(&'static str, &dyn SeriesStats); 30] {
+        let dispatch = if self.legacy_histograms {
+            "wsprism_dispatch_duration_micros"
+        } else {
+            "wsprism_dispatch_duration_seconds"
+        };
+        [
+            ("wsprism_ws_upgrades_total", &self.ws_upgrades),
+            ("wsprism_ws_sessions_active", &self.ws_active_sessions),
+            ("wsprism_policy_decisions_total", &self.policy_decisions),
+            ("wsprism_policy_dryrun_violations_total", &self.policy_dryrun_violations),
+            ("wsprism_handshake_rejections_total", &self.handshake_rejections),
+            (dispatch, &self.dispatch_duration),
+            ("wsprism_dispatch_latency_seconds", &self.dispatch_latency_summary),
+            ("wsprism_inbound_frame_size_bytes", &self.inbound_frame_bytes),
+            ("wsprism_outbound_frame_size_bytes", &self.outbound_frame_bytes),
+            ("wsprism_bytes_in_total", &self.bytes_in),
+            ("wsprism_bytes_out_total", &self.bytes_out),
+            ("wsprism_bytes_sent_total", &self.bytes_sent),
+            ("wsprism_bandwidth_limited_total", &self.bandwidth_limited),
+            ("wsprism_decode_errors_total", &self.decode_errors),
+            ("wsprism_service_errors_total", &self.service_errors),
+            ("wsprism_writer_timeouts_total", &self.writer_timeouts),
+            ("wsprism_unknown_service_total", &self.unknown_service_errors),
+            ("wsprism_dead_letters_total", &self.dead_letters),
+            ("wsprism_room_rate_limited_total", &self.room_rate_limited),
+            ("wsprism_plugin_errors_total", &self.plugin_errors),
+            ("wsprism_outbound_queue_depth_max", &self.outbound_queue_depth_max),
+            ("wsprism_outbound_queue_depth_p99", &self.outbound_queue_depth_p99),
+            ("wsprism_outbound_drops_total", &self.outbound_drops),
+            ("wsprism_room_members", &self.room_members),
+            ("wsprism_cluster_bridge_lag_seconds", &self.cluster_bridge_lag),
+            ("wsprism_cluster_bridge_errors_total", &self.cluster_bridge_errors),
+            ("wsprism_cluster_connected", &self.cluster_connected),
+            ("wsprism_egress_dropped_total", &self.egress_dropped),
+            ("wsprism_webhook_dropped_total", &self.webhook_dropped),
+            ("wsprism_upstream_duration_seconds", &self.upstream_duration),
+        ]
+    }
+
+    /// Drop series idle for `gateway.metrics.series_ttl_secs` from every
+    /// metric. Returns how many were dropped (0 when pruning is disabled).
+    pub fn prune_stale(&self) -> usize {
+        let Some(ttl) = self.series_ttl else { return 0 };
+        let custom: usize = self.custom_metrics().iter().map(|(_, m)| m.stats().prune_stale(ttl)).sum();
+        custom + self.series_metrics().iter().map(|(_, m)| m.prune_stale(ttl)).sum::<usize>()
+    }
+
+    /// Pruned and overflowed series per metric, for metrics with either.
+    fn render_series_stats(&self, custom: &[(&'static str, CustomMetric)], out: &mut String) {
+        let custom = custom.iter().map(|(name, m)| (*name, m.stats()));
+        let stats: Vec<(&str, &dyn SeriesStats)> = self.series_metrics().into_iter().chain(custom).collect();
+        let _ = writeln!(out, "# TYPE wsprism_metric_series_pruned_total counter");
+        for (name, m) in stats.iter().filter(|(_, m)| m.pruned_series() > 0) {
+            let _ = writeln!(out, "wsprism_metric_series_pruned_total{{metric=\"{}\"}} {}", name, m.pruned_series());
+        }
+        let _ = writeln!(out, "# TYPE wsprism_metric_series_overflowed_total counter");
+        for (name, m) in stats.iter().filter(|(_, m)| m.overflowed_series() > 0) {
+            let _ = writeln!(out, "wsprism_metric_series_overflowed_total{{metric=\"{}\"}} {}", name, m.overflowed_series());
+        }
+    }
+
     /// Mark draining state.
     pub fn set_draining(&self) { self.draining.store(true, Ordering::Relaxed); }
     /// Return whether draining is active.
     pub fn is_draining(&self) -> bool { self.draining.load(Ordering::Relaxed) }
 
     /// Render all registered metrics plus any extra lines provided by callers.
-    pub fn render(&self, extra: &[(&str, u64)]) -> String {
+    pub fn render(&self, extra: &[(String, u64)]) -> String {
         let mut out = String::new();
-        self.ws_upgrades.render("wsprism_ws_upgrades_total", &mut out);
-        self.ws_active_sessions.render("wsprism_ws_sessions_active", &mut out);
-        self.policy_decisions.render("wsprism_policy_decisions_total", &mut out);
-        self.handshake_rejections.render("wsprism_handshake_rejections_total", &mut out);
-        self.dispatch_duration.render("wsprism_dispatch_duration_micros", &mut out); // Explicit unit
-        self.decode_errors.render("wsprism_decode_errors_total", &mut out);
-        self.service_errors.render("wsprism_service_errors_total", &mut out);
-        self.writer_timeouts.render("wsprism_writer_timeouts_total", &mut out);
-        self.unknown_service_errors.render("wsprism_unknown_service_total", &mut out);
+        self.render_into(extra, &mut out);
+        out
+    }
+
+    /// Same as [`render`](Self::render), appending to `out` so a scrape
+    /// buffer can be reused.
+    pub fn render_into(&self, extra: &[(String, u64)], out: &mut String) {
+        self.process.render(out);
+        self.ws_upgrades.render("wsprism_ws_upgrades_total", out);
+        self.ws_active_sessions.render("wsprism_ws_sessions_active", out);
+        self.policy_decisions.render("wsprism_policy_decisions_total", out);
+        self.policy_dryrun_violations.render("wsprism_policy_dryrun_violations_total", out);
+        self.handshake_rejections.render("wsprism_handshake_rejections_total", out);
+        self.handshake.render(out);
+        if self.legacy_histograms {
+            self.dispatch_duration.render_raw("wsprism_dispatch_duration_micros", out);
+        } else {
+            self.dispatch_duration.render("wsprism_dispatch_duration_seconds", out);
+        }
+        self.dispatch_latency_summary.render("wsprism_dispatch_latency_seconds", out);
+        self.inbound_frame_bytes.render("wsprism_inbound_frame_size_bytes", out);
+        self.outbound_frame_bytes.render("wsprism_outbound_frame_size_bytes", out);
+        self.bytes_in.render("wsprism_bytes_in_total", out);
+        self.bytes_out.render("wsprism_bytes_out_total", out);
+        self.bytes_sent.render("wsprism_bytes_sent_total", out);
+        self.bandwidth_limited.render("wsprism_bandwidth_limited_total", out);
+        self.decode_errors.render("wsprism_decode_errors_total", out);
+        self.service_errors.render("wsprism_service_errors_total", out);
+        self.writer_timeouts.render("wsprism_writer_timeouts_total", out);
+        self.unknown_service_errors.render("wsprism_unknown_service_total", out);
+        self.dead_letters.render("wsprism_dead_letters_total", out);
+        self.room_rate_limited.render("wsprism_room_rate_limited_total", out);
+        self.plugin_errors.render("wsprism_plugin_errors_total", out);
+        self.outbound_queue_depth_max.render("wsprism_outbound_queue_depth_max", out);
+        self.outbound_queue_depth_p99.render("wsprism_outbound_queue_depth_p99", out);
+        self.outbound_drops.render("wsprism_outbound_drops_total", out);
+        self.room_members.render("wsprism_room_members", out);
+        self.cluster_bridge_lag.render("wsprism_cluster_bridge_lag_seconds", out);
+        self.cluster_bridge_errors.render("wsprism_cluster_bridge_errors_total", out);
+        self.cluster_connected.render("wsprism_cluster_connected", out);
+        self.egress_dropped.render("wsprism_egress_dropped_total", out);
+        self.webhook_dropped.render("wsprism_webhook_dropped_total", out);
+        self.upstream_duration.render("wsprism_upstream_duration_seconds", out);
+        let custom = self.custom_metrics();
+        for (name, m) in &custom {
+            m.render(name, out);
+        }
+        self.render_series_stats(&custom, out);
         
         let _ = writeln!(out, "# TYPE wsprism_draining gauge\nwsprism_draining {}", if self.is_draining() { 1 } else { 0 });
         for (k, v) in extra { let _ = writeln!(out, "{} {}", k, v); }
-        out
+    }
+}
+
+fn kind_conflict(name: &str) -> WsPrismError {
+    WsPrismError::BadRequest(format!("metric {name:?} is already registered as another kind"))
+}
+
+/// Rewrite Prometheus text exposition (as produced by
+/// [`GatewayMetrics::render`]) as OpenMetrics 1.0 into `out`.
+///
+/// Counter families are declared without their `_total` suffix (samples
+/// keep it), samples without a `# TYPE` get an `unknown` family, and the
+/// body ends with `# EOF`.
+pub fn to_openmetrics(text: &str, out: &mut String) {
+    let mut family = "";
+    for line in text.lines() {
+        if let Some(decl) = line.strip_prefix("# TYPE ") {
+            let (name, declared) = decl.split_once(' ').unwrap_or((decl, "unknown"));
+            let kind;
+            (family, kind) = match (declared, name.strip_suffix("_total")) {
+                ("counter", Some(base)) => (base, "counter"),
+                // OpenMetrics counters must be sampled as `<family>_total`.
+                ("counter", None) => (name, "unknown"),
+                (k, _) => (name, k),
+            };
+            let _ = writeln!(out, "# TYPE {} {}", family, kind);
+            continue;
+        }
+        if !line.starts_with('#') && !line.is_empty() {
+            let name = line.split(['{', ' ']).next().unwrap_or(line);
+            if !in_family(name, family) {
+                family = name;
+                let _ = writeln!(out, "# TYPE {} unknown", name);
+            }
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("# EOF\n");
+}
+
+/// Whether sample `name` belongs to metric family `family`.
+fn in_family(name: &str, family: &str) -> bool {
+    match name.strip_prefix(family) {
+        Some(rest) => ["", "_total", "_bucket", "_sum", "_count", "_created"].contains(&rest),
+        None => false,
     }
 }
@@ -2,6 +2,10 @@
 //!
 //! Sprint 4 goal: expose minimal Prometheus-compatible metrics without adding
 //! external crates. Metrics are stored as atomics and rendered by the `/metrics`
-//! handler.
+//! handler. Tracing setup lives in `telemetry`; the optional OTLP span
+//! exporter (`otel` feature) in `otlp`.
 
 pub mod metrics;
+#[cfg(feature = "otel")]
+pub mod otlp;
+pub mod telemetry;
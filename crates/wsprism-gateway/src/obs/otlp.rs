@@ -0,0 +1,320 @@
+//! OTLP/HTTP trace export (`otel` feature).
+//!
+//! [`OtlpLayer`] is a `tracing-subscriber` layer that gives every span a W3C
+//! trace/span id, samples new traces by `sample_ratio`, and hands closed,
+//! sampled spans to [`OtlpExporter`]. The exporter posts them in batches to
+//! `<endpoint>/v1/traces` using the OTLP JSON encoding, so no protobuf or
+//! OpenTelemetry SDK is needed.
+//!
+//! A span with a `traceparent` field holding a valid W3C header joins that
+//! remote trace instead of its local parent. `otel.status_code = "ERROR"`
+//! marks a span failed.
+
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use wsprism_core::error::{Result, WsPrismError};
+
+use crate::config::schema::OtlpConfig;
+
+/// Spans buffered for export; further spans are dropped until the exporter
+/// catches up.
+const QUEUE_CAPACITY: usize = 4096;
+/// Spans per request.
+const MAX_BATCH: usize = 512;
+/// Longest a span waits before its batch is sent.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Parsed W3C `traceparent` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceParent {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub sampled: bool,
+}
+
+impl TraceParent {
+    /// Parse `00-<32 hex>-<16 hex>-<2 hex>`. All-zero ids and version `ff`
+    /// are invalid; later versions are read by their version-00 prefix.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.split('-');
+        let (version, trace, span, flags) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let version = decode_hex::<1>(version)?;
+        if version[0] == 0xff || (version[0] == 0 && parts.next().is_some()) {
+            return None;
+        }
+        let trace_id = decode_hex::<16>(trace)?;
+        let span_id = decode_hex::<8>(span)?;
+        let flags = decode_hex::<1>(flags)?;
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+        Some(Self { trace_id, span_id, sampled: flags[0] & 1 == 1 })
+    }
+}
+
+/// Lowercase hex of exactly `N` bytes.
+fn decode_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != N * 2 || !s.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)) {
+        return None;
+    }
+    let mut out = [0u8; N];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(out)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// `data.traceparent` of an Ext envelope, when present and valid.
+pub fn envelope_traceparent(data: Option<&serde_json::value::RawValue>) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Carrier {
+        traceparent: Option<String>,
+    }
+    let carrier: Carrier = serde_json::from_str(data?.get()).ok()?;
+    carrier.traceparent.filter(|tp| TraceParent::parse(tp).is_some())
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut out = [0u8; N];
+    out.copy_from_slice(&uuid::Uuid::new_v4().as_bytes()[..N]);
+    out
+}
+
+fn unix_nanos() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
+}
+
+/// Ids and recorded fields of an open span (stored in its extensions).
+struct SpanData {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    sampled: bool,
+    start_ns: u64,
+    attributes: Vec<Value>,
+    error: bool,
+}
+
+/// Collects span fields as OTLP attributes.
+struct Fields<'a> {
+    attributes: &'a mut Vec<Value>,
+    error: &'a mut bool,
+    traceparent: Option<TraceParent>,
+}
+
+impl Fields<'_> {
+    fn push(&mut self, field: &Field, value: Value) {
+        self.attributes.push(json!({ "key": field.name(), "value": value }));
+    }
+}
+
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "traceparent" => self.traceparent = TraceParent::parse(value),
+            "otel.status_code" => *self.error = value == "ERROR",
+            _ => self.push(field, json!({ "stringValue": value })),
+        }
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, json!({ "intValue": value.to_string() }));
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, json!({ "intValue": value.to_string() }));
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, json!({ "boolValue": value }));
+    }
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, json!({ "doubleValue": value }));
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+enum Export {
+    Span(Value),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Layer assigning trace context to spans and queueing sampled ones.
+pub struct OtlpLayer {
+    tx: mpsc::Sender<Export>,
+    /// New traces are sampled when their id's low 64 bits fall below this.
+    sample_threshold: u64,
+}
+
+impl OtlpLayer {
+    fn sample_new_trace(&self, trace_id: &[u8; 16]) -> bool {
+        let mut low = [0u8; 8];
+        low.copy_from_slice(&trace_id[8..]);
+        self.sample_threshold == u64::MAX || u64::from_be_bytes(low) < self.sample_threshold
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut attributes = Vec::new();
+        let mut error = false;
+        let mut fields = Fields { attributes: &mut attributes, error: &mut error, traceparent: None };
+        attrs.record(&mut fields);
+        let remote = fields.traceparent;
+
+        let local = span.parent().and_then(|p| {
+            p.extensions().get::<SpanData>().map(|d| (d.trace_id, d.span_id, d.sampled))
+        });
+        let (trace_id, parent_span_id, sampled) = match (remote, local) {
+            (Some(tp), _) => (tp.trace_id, Some(tp.span_id), tp.sampled),
+            (None, Some((trace_id, span_id, sampled))) => (trace_id, Some(span_id), sampled),
+            (None, None) => {
+                let trace_id = random_bytes::<16>();
+                (trace_id, None, self.sample_new_trace(&trace_id))
+            }
+        };
+        span.extensions_mut().insert(SpanData {
+            trace_id,
+            span_id: random_bytes::<8>(),
+            parent_span_id,
+            sampled,
+            start_ns: unix_nanos(),
+            attributes,
+            error,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut ext = span.extensions_mut();
+        let Some(data) = ext.get_mut::<SpanData>() else { return };
+        let mut fields = Fields { attributes: &mut data.attributes, error: &mut data.error, traceparent: None };
+        values.record(&mut fields);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(data) = span.extensions_mut().remove::<SpanData>() else { return };
+        if !data.sampled {
+            return;
+        }
+        let mut otlp = json!({
+            "traceId": hex(&data.trace_id),
+            "spanId": hex(&data.span_id),
+            "name": span.name(),
+            "kind": 1,
+            "startTimeUnixNano": data.start_ns.to_string(),
+            "endTimeUnixNano": unix_nanos().to_string(),
+            "attributes": data.attributes,
+            "status": { "code": if data.error { 2 } else { 0 } },
+        });
+        if let Some(parent) = data.parent_span_id {
+            otlp["parentSpanId"] = json!(hex(&parent));
+        }
+        // Full queue: drop rather than block the traced code.
+        let _ = self.tx.try_send(Export::Span(otlp));
+    }
+}
+
+/// Handle to the background task posting spans to the collector.
+pub struct OtlpExporter {
+    tx: mpsc::Sender<Export>,
+}
+
+impl OtlpExporter {
+    /// Start the export task and return it with the layer feeding it. Must
+    /// be called from within a tokio runtime.
+    pub fn start(cfg: &OtlpConfig) -> Result<(OtlpLayer, OtlpExporter)> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (k, v) in &cfg.headers {
+            let name = reqwest::header::HeaderName::from_bytes(k.as_bytes())
+                .map_err(|e| WsPrismError::BadRequest(format!("observability.otlp.headers: {k}: {e}")))?;
+            let value = reqwest::header::HeaderValue::from_str(v)
+                .map_err(|e| WsPrismError::BadRequest(format!("observability.otlp.headers: {k}: {e}")))?;
+            headers.insert(name, value);
+        }
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .default_headers(headers)
+            .build()
+            .map_err(|e| WsPrismError::Internal(format!("otlp client build failed: {e}")))?;
+        let url = format!("{}/v1/traces", cfg.endpoint.trim_end_matches('/'));
+        let resource = json!({
+            "attributes": [{ "key": "service.name", "value": { "stringValue": cfg.service_name } }],
+        });
+
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run_export(rx, http, url, resource));
+        let sample_threshold = if cfg.sample_ratio >= 1.0 { u64::MAX } else { (cfg.sample_ratio * u64::MAX as f64) as u64 };
+        Ok((OtlpLayer { tx: tx.clone(), sample_threshold }, OtlpExporter { tx }))
+    }
+
+    /// Send every span queued so far and wait for the request to finish.
+    pub async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.tx.send(Export::Flush(done)).await.is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
+
+async fn run_export(mut rx: mpsc::Receiver<Export>, http: reqwest::Client, url: String, resource: Value) {
+    let mut batch = Vec::new();
+    let mut tick = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        let flushed = tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(Export::Span(span)) => {
+                    batch.push(span);
+                    if batch.len() < MAX_BATCH {
+                        continue;
+                    }
+                    None
+                }
+                Some(Export::Flush(done)) => Some(done),
+                None => break,
+            },
+            _ = tick.tick() => None,
+        };
+        if !batch.is_empty() {
+            post(&http, &url, &resource, std::mem::take(&mut batch)).await;
+        }
+        if let Some(done) = flushed {
+            let _ = done.send(());
+        }
+    }
+    if !batch.is_empty() {
+        post(&http, &url, &resource, batch).await;
+    }
+}
+
+async fn post(http: &reqwest::Client, url: &str, resource: &Value, spans: Vec<Value>) {
+    let body = json!({
+        "resourceSpans": [{
+            "resource": resource,
+            "scopeSpans": [{ "scope": { "name": "wsprism-gateway" }, "spans": spans }],
+        }],
+    });
+    match http.post(url).json(&body).send().await {
+        Ok(resp) if resp.status().is_success() => {}
+        Ok(resp) => tracing::warn!(status = %resp.status(), "otlp export rejected"),
+        Err(e) => tracing::warn!(error = %e, "otlp export failed"),
+    }
+}
@@ -0,0 +1,61 @@
+//! Global tracing subscriber setup for the gateway binary.
+//!
+//! Log output is filtered by `RUST_LOG`. With the `otel` feature and an
+//! `observability.otlp` block, INFO-and-above spans are also exported over
+//! OTLP, independently of the log filter.
+
+#[cfg(feature = "otel")]
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+use wsprism_core::error::Result;
+
+use crate::config::ObservabilitySection;
+
+/// Keeps the span exporter running; call [`Telemetry::shutdown`] on exit so
+/// queued spans are sent.
+#[derive(Default)]
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    exporter: Option<super::otlp::OtlpExporter>,
+}
+
+impl Telemetry {
+    /// Flush spans still queued for export.
+    pub async fn shutdown(self) {
+        #[cfg(feature = "otel")]
+        if let Some(exporter) = self.exporter {
+            exporter.flush().await;
+        }
+    }
+}
+
+/// Install the global subscriber. Must be called once, from within a tokio
+/// runtime. Fails only when the OTLP exporter cannot be built (e.g. an
+/// invalid header), before anything is installed.
+pub fn init(cfg: &ObservabilitySection) -> Result<Telemetry> {
+    let logs = fmt::layer().with_filter(EnvFilter::from_default_env());
+
+    #[cfg(feature = "otel")]
+    {
+        let (otlp, exporter) = match &cfg.otlp {
+            Some(o) => {
+                let (layer, exporter) = super::otlp::OtlpExporter::start(o)?;
+                (Some(layer.with_filter(LevelFilter::INFO)), Some(exporter))
+            }
+            None => (None, None),
+        };
+        tracing_subscriber::registry().with(logs).with(otlp).init();
+        Ok(Telemetry { exporter })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        tracing_subscriber::registry().with(logs).init();
+        if cfg.otlp.is_some() {
+            tracing::warn!("observability.otlp is set but the gateway was built without the otel feature; spans are not exported");
+        }
+        Ok(Telemetry::default())
+    }
+}
@@ -0,0 +1,256 @@
+//! Admin HTTP API (`/admin/v1/*`).
+//!
+//! Disabled (404) unless `gateway.admin_token` is set; requests must carry
+//! `Authorization: Bearer <admin_token>`.
+//!
+//! - `POST /admin/v1/broadcast` : send `{svc, type, data}` to every session of
+//!   tenants with `policy.allow_server_broadcast`. Limited to 1 req/min.
+//! - `GET /admin/v1/sessions?tenant=` : list live connections (of one
+//!   tenant, if given) as
+//!   `{connection_id, user_id, tenant, connected_at_unix_ms, queue_depth}`.
+//! - `GET /admin/v1/sessions/:id/events` : lifecycle event log of one
+//!   connection (live or among the last 64 to close) as
+//!   `{connection_id, live, events: [{event, at_ms, at_unix_ms, ..}]}`.
+//! - `POST /admin/v1/reload` : re-read the config file and hot-swap tenant
+//!   policies; reports per-tenant changes. 400 keeps the old policies.
+//! - `GET /admin/v1/stats?tenant=` : snapshot of what the node is doing now:
+//!   `sessions`, `sessions_by_tenant`, `rooms`, `top_rooms` (20 largest by
+//!   session count: `{tenant, room, sessions, users}`), `queue_depth`
+//!   (`{p50, p99, max}` over session outbound queues), `draining`,
+//!   `uptime_secs` and `quotas`, the per-user quota counters (`tenant, user,
+//!   pattern, count, window_start_unix_ms, reset_at_unix_ms`). `tenant`
+//!   limits everything but `draining` and `uptime_secs` to one tenant.
+//! - `GET /admin/v1/handshake/top?n=10` : IPs with the most handshake
+//!   rejections in the last minute, as `{window_secs, top: [{ip, rejections}]}`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::app_state::AppState;
+use crate::config;
+use crate::context::ConnectionId;
+use crate::ops::auth::{bearer, token_matches};
+use crate::realtime::{Outgoing, Payload, Priority, QoS};
+use crate::transport::handshake::THROTTLE_WINDOW;
+
+/// Rooms listed in `/admin/v1/stats`.
+const STATS_TOP_ROOMS: usize = 20;
+
+/// Default and maximum `n` for `/admin/v1/handshake/top`.
+const HANDSHAKE_TOP_DEFAULT: usize = 10;
+const HANDSHAKE_TOP_MAX: usize = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BroadcastRequest {
+    pub svc: String,
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    #[serde(default)]
+    pub data: Value,
+}
+
+/// `?tenant=` filter of the sessions and stats endpoints.
+#[derive(Debug, Deserialize)]
+pub struct TenantQuery {
+    pub tenant: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TopQuery {
+    pub n: Option<usize>,
+}
+
+fn error(status: StatusCode, code: &str) -> Response {
+    (status, Json(json!({ "error": code }))).into_response()
+}
+
+/// `None` when authorized, otherwise the rejection response.
+fn authorize(state: &AppState, headers: &HeaderMap) -> Option<Response> {
+    let Some(expected) = state.cfg().gateway.admin_token.as_deref() else {
+        return Some(error(StatusCode::NOT_FOUND, "admin_disabled"));
+    };
+    match bearer(headers) {
+        Some(t) if token_matches(expected, t) => None,
+        _ => Some(error(StatusCode::UNAUTHORIZED, "unauthorized")),
+    }
+}
+
+pub async fn broadcast(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<BroadcastRequest>,
+) -> Response {
+    if let Some(rejection) = authorize(&state, &headers) {
+        return rejection;
+    }
+
+    let allowed = state.server_broadcast_tenants();
+    if allowed.is_empty() {
+        return error(StatusCode::FORBIDDEN, "server_broadcast_disabled");
+    }
+
+    if let Err(retry_after) = state.try_admin_broadcast_slot() {
+        let mut resp = error(StatusCode::TOO_MANY_REQUESTS, "rate_limited");
+        if let Ok(v) = retry_after.to_string().parse() {
+            resp.headers_mut().insert(header::RETRY_AFTER, v);
+        }
+        return resp;
+    }
+
+    let out = Outgoing {
+        qos: QoS::Lossy,
+        payload: Payload::TextJson(json!({
+            "v": 1,
+            "svc": req.svc,
+            "type": req.msg_type,
+            "flags": 0,
+            "data": req.data,
+        })),
+        priority: Priority::Normal,
+    };
+    match state
+        .realtime()
+        .broadcast_tenants(out, |tenant| allowed.iter().any(|t| t == tenant))
+        .await
+    {
+        Ok(sent) => {
+            tracing::info!(sent, tenants=?allowed, "admin broadcast");
+            (StatusCode::OK, Json(json!({ "sent": sent }))).into_response()
+        }
+        Err(e) => error(StatusCode::BAD_REQUEST, e.client_code().as_str()),
+    }
+}
+
+pub async fn sessions(State(state): State<AppState>, headers: HeaderMap, Query(q): Query<TenantQuery>) -> Response {
+    if let Some(rejection) = authorize(&state, &headers) {
+        return rejection;
+    }
+    let registry = &state.realtime().sessions;
+    let sessions = match q.tenant.as_deref() {
+        Some(t) => registry.collect_tenant_sessions(t),
+        None => registry.collect_sessions(),
+    };
+    (StatusCode::OK, Json(json!({ "sessions": sessions }))).into_response()
+}
+
+pub async fn session_events(State(state): State<AppState>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    if let Some(rejection) = authorize(&state, &headers) {
+        return rejection;
+    }
+    let Ok(id) = id.parse::<ConnectionId>() else {
+        return error(StatusCode::BAD_REQUEST, "invalid_connection_id");
+    };
+    let registry = &state.realtime().sessions;
+    let Some(log) = registry.event_log(id) else {
+        return error(StatusCode::NOT_FOUND, "session_not_found");
+    };
+    let body = json!({
+        "connection_id": id,
+        "live": registry.get_session(id).is_some(),
+        "events": log.to_json(),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+pub async fn reload(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(rejection) = authorize(&state, &headers) {
+        return rejection;
+    }
+    let result = config::load_from_file(state.config_path())
+        .and_then(|cfg| state.reload_policies(&cfg));
+    match result {
+        Ok(report) => {
+            tracing::info!(path = state.config_path(), "tenant policies reloaded");
+            (StatusCode::OK, Json(json!(report))).into_response()
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "policy reload failed; keeping current policies");
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "reload_failed", "detail": e.to_string() })),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn stats(State(state): State<AppState>, headers: HeaderMap, Query(q): Query<TenantQuery>) -> Response {
+    if let Some(rejection) = authorize(&state, &headers) {
+        return rejection;
+    }
+    let tenant = q.tenant.as_deref();
+    let core = state.realtime();
+    let mut by_tenant = core.sessions.tenant_session_counts();
+    by_tenant.retain(|(t, _)| tenant.is_none_or(|f| f == t));
+    let sessions = match tenant {
+        Some(t) => core.sessions.count_tenant_sessions(t) as usize,
+        None => core.sessions.len_sessions(),
+    };
+    let rooms = match tenant {
+        Some(t) => core.presence.tenant_room_count(t) as usize,
+        None => core.presence.room_count(),
+    };
+    let top_rooms: Vec<Value> = core
+        .presence
+        .top_rooms(tenant, STATS_TOP_ROOMS)
+        .into_iter()
+        .map(|r| {
+            let (t, room) = r.room.split_once("::").unwrap_or(("", &r.room));
+            json!({ "tenant": t, "room": room, "sessions": r.sessions, "users": r.users })
+        })
+        .collect();
+    let depths = core.sessions.queue_depths(tenant);
+    let mut quotas = state.quotas().snapshot();
+    quotas.retain(|u| tenant.is_none_or(|t| t == u.tenant));
+
+    let body = json!({
+        "sessions": sessions,
+        "sessions_by_tenant": by_tenant.into_iter().collect::<std::collections::BTreeMap<_, _>>(),
+        "rooms": rooms,
+        "top_rooms": top_rooms,
+        "queue_depth": {
+            "p50": percentile(&depths, 50),
+            "p99": percentile(&depths, 99),
+            "max": depths.last().copied().unwrap_or(0),
+        },
+        "draining": state.is_draining(),
+        "uptime_secs": state.uptime().as_secs(),
+        "quotas": quotas,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Nearest-rank percentile of ascending `sorted` (0 when empty).
+fn percentile(sorted: &[usize], p: usize) -> usize {
+    let rank = (sorted.len() * p).div_ceil(100);
+    sorted.get(rank.saturating_sub(1)).copied().unwrap_or(0)
+}
+
+pub async fn handshake_top(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<TopQuery>,
+) -> Response {
+    if let Some(rejection) = authorize(&state, &headers) {
+        return rejection;
+    }
+    let n = q.n.unwrap_or(HANDSHAKE_TOP_DEFAULT).clamp(1, HANDSHAKE_TOP_MAX);
+    let top: Vec<Value> = state
+        .handshake()
+        .top_throttled(n)
+        .into_iter()
+        .map(|(ip, rejections)| json!({ "ip": ip.to_string(), "rejections": rejections }))
+        .collect();
+    (
+        StatusCode::OK,
+        Json(json!({ "window_secs": THROTTLE_WINDOW.as_secs(), "top": top })),
+    )
+        .into_response()
+}
@@ -0,0 +1,95 @@
+//! Bearer token gate for `/metrics` and `/admin/*` (`ops.*`).
+//!
+//! Requests pass with `Authorization: Bearer <ops token>` or
+//! `<gateway.admin_token>`, or from a client address inside
+//! `ops.allow_cidrs`. Admin handlers still check `admin_token` themselves.
+
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{connect_info::ConnectInfo, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use ipnet::IpNet;
+use serde_json::json;
+use wsprism_core::error::{Result, WsPrismError};
+
+use crate::app_state::AppState;
+use crate::config::schema::{check_ops_token, parse_cidr};
+use crate::config::OpsSection;
+
+/// Resolved `ops` settings.
+#[derive(Debug, Clone, Default)]
+pub struct OpsAuth {
+    token: Option<String>,
+    allow: Vec<IpNet>,
+}
+
+impl OpsAuth {
+    /// Resolve the token (reading `auth_token_file` if set) and networks.
+    pub fn from_config(cfg: &OpsSection) -> Result<Self> {
+        let token = match (&cfg.auth_token, &cfg.auth_token_file) {
+            (Some(t), _) => Some(t.clone()),
+            (None, Some(path)) => {
+                let t = std::fs::read_to_string(path)
+                    .map_err(|e| WsPrismError::BadRequest(format!("ops.auth_token_file {path}: {e}")))?;
+                let t = t.trim().to_string();
+                check_ops_token(&t)?;
+                Some(t)
+            }
+            (None, None) => None,
+        };
+        let allow = cfg.allow_cidrs.iter().map(|c| parse_cidr(c)).collect::<Result<_>>()?;
+        Ok(Self { token, allow })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some()
+    }
+
+    fn allows(&self, headers: &HeaderMap, peer: Option<SocketAddr>, admin_token: Option<&str>) -> bool {
+        let Some(expected) = self.token.as_deref() else { return true };
+        if peer.is_some_and(|p| self.allow.iter().any(|net| net.contains(&p.ip()))) {
+            return true;
+        }
+        match bearer(headers) {
+            Some(t) => token_matches(expected, t) || admin_token.is_some_and(|a| token_matches(a, t)),
+            None => false,
+        }
+    }
+}
+
+/// Middleware for the ops routes; 401 unless [`OpsAuth`] admits the request.
+pub async fn require_ops_auth(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|c| c.0);
+    let admin_token = state.cfg().gateway.admin_token.as_deref();
+    if state.ops_auth().allows(req.headers(), peer, admin_token) {
+        return next.run(req).await;
+    }
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(json!({ "error": "unauthorized" })),
+    )
+        .into_response()
+}
+
+pub(crate) fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Compare without short-circuiting on the first differing byte.
+pub(crate) fn token_matches(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
@@ -2,11 +2,59 @@
 //!
 //! - `/healthz` : liveness
 //! - `/readyz`  : readiness (503 when draining)
-//! - `/metrics` : Prometheus text format
+//! - `/v1/capabilities` : registered services and compiled-in features
+//! - `/metrics` : Prometheus text format, or OpenMetrics when the `Accept`
+//!   header asks for it; gzip-compressed with `Accept-Encoding: gzip`
+//! - `/admin/v1/*` : admin API (see [`admin`])
+//!
+//! `/metrics` and `/admin/*` can be put behind a bearer token (see [`auth`]).
+
+pub mod admin;
+pub mod auth;
+pub mod shutdown;
+
+use std::io::Write;
+use std::sync::Mutex;
+
+use axum::{
+    body::Bytes,
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+};
+use flate2::{write::GzEncoder, Compression};
+
+use crate::app_state::{AppState, BUILTIN_TEXT_SVCS};
+use crate::obs::metrics::to_openmetrics;
 
-use axum::{http::StatusCode, response::{IntoResponse, Response}};
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
 
-use crate::app_state::AppState;
+/// Render buffers kept between scrapes, so a body of a few hundred KB is
+/// not regrown from empty every time.
+static SCRAPE_BUFFERS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+const MAX_POOLED_BUFFERS: usize = 4;
+
+fn take_buffer() -> String {
+    SCRAPE_BUFFERS.lock().unwrap_or_else(|e| e.into_inner()).pop().unwrap_or_default()
+}
+
+fn return_buffer(mut buf: String) {
+    buf.clear();
+    let mut pool = SCRAPE_BUFFERS.lock().unwrap_or_else(|e| e.into_inner());
+    if pool.len() < MAX_POOLED_BUFFERS {
+        pool.push(buf);
+    }
+}
+
+/// Whether the comma-separated `name` header lists `token` (ignoring case)
+/// without `q=0`.
+fn accepts(headers: &HeaderMap, name: HeaderName, token: &str) -> bool {
+    headers.get_all(name).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(',')).any(|item| {
+        let mut parts = item.split(';').map(str::trim);
+        parts.next().is_some_and(|t| t.eq_ignore_ascii_case(token))
+            && !parts.any(|p| p.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0))
+    })
+}
 
 pub async fn healthz() -> impl IntoResponse {
     (StatusCode::OK, "ok")
@@ -20,14 +68,77 @@ pub async fn readyz(axum::extract::State(state): axum::extract::State<AppState>)
     }
 }
 
-pub async fn metrics(axum::extract::State(state): axum::extract::State<AppState>) -> Response {
+/// Cargo features this gateway was built with, as listed in
+/// `/v1/capabilities`.
+const FEATURES: [(&str, bool); 11] = [
+    ("governor-ratelimit", cfg!(feature = "governor-ratelimit")),
+    ("oidc-introspection", cfg!(feature = "oidc-introspection")),
+    ("wasm-plugins", cfg!(feature = "wasm-plugins")),
+    ("regex-allowlist", cfg!(feature = "regex-allowlist")),
+    ("otel", cfg!(feature = "otel")),
+    ("cluster-redis", cfg!(feature = "cluster-redis")),
+    ("cluster-nats", cfg!(feature = "cluster-nats")),
+    ("egress-kafka", cfg!(feature = "egress-kafka")),
+    ("webhooks", cfg!(feature = "webhooks")),
+    ("upstream-grpc", cfg!(feature = "upstream-grpc")),
+    ("upstream-http", cfg!(feature = "upstream-http")),
+];
+
+/// Services a client can address and the features compiled in. Open like
+/// `/healthz`, for dashboards and client feature detection.
+pub async fn capabilities(axum::extract::State(state): axum::extract::State<AppState>) -> impl IntoResponse {
+    let dispatcher = state.dispatcher();
+    let mut text: Vec<&str> = BUILTIN_TEXT_SVCS.into_iter().chain(dispatcher.registered_text_svcs()).collect();
+    text.sort_unstable();
+    text.dedup();
+    let mut hot = dispatcher.registered_hot_svcs();
+    hot.sort_unstable();
+    let features: Vec<&str> = FEATURES.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect();
+    axum::Json(serde_json::json!({
+        "text_services": text,
+        "hot_services": hot,
+        "version": "1",
+        "features": features,
+    }))
+}
+
+pub async fn metrics(axum::extract::State(state): axum::extract::State<AppState>, headers: HeaderMap) -> Response {
+    state.refresh_process_metrics();
     let extra = state.metrics_extra();
-    let body = state.metrics().render(&extra);
+    let mut text = take_buffer();
+    state.metrics().render_into(&extra, &mut text);
 
-    (
+    let (text, content_type) = if accepts(&headers, header::ACCEPT, "application/openmetrics-text") {
+        let mut om = take_buffer();
+        to_openmetrics(&text, &mut om);
+        return_buffer(text);
+        (om, OPENMETRICS_CONTENT_TYPE)
+    } else {
+        (text, PROMETHEUS_CONTENT_TYPE)
+    };
+
+    let gzipped = accepts(&headers, header::ACCEPT_ENCODING, "gzip").then(|| {
+        let mut gz = GzEncoder::new(Vec::with_capacity(text.len() / 4), Compression::fast());
+        gz.write_all(text.as_bytes()).and_then(|()| gz.finish())
+    });
+    let (body, encoding) = match gzipped {
+        Some(Ok(compressed)) => (Bytes::from(compressed), Some("gzip")),
+        Some(Err(e)) => {
+            tracing::warn!(error = %e, "metrics gzip failed; sending identity");
+            (Bytes::copy_from_slice(text.as_bytes()), None)
+        }
+        None => (Bytes::copy_from_slice(text.as_bytes()), None),
+    };
+    return_buffer(text);
+
+    let mut resp = (
         StatusCode::OK,
-        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        [(header::CONTENT_TYPE, content_type), (header::VARY, "Accept, Accept-Encoding")],
         body,
     )
-        .into_response()
+        .into_response();
+    if let Some(enc) = encoding {
+        resp.headers_mut().insert(header::CONTENT_ENCODING, header::HeaderValue::from_static(enc));
+    }
+    resp
 }
@@ -0,0 +1,38 @@
+//! Upper bound on graceful shutdown (`gateway.shutdown_timeout_ms`).
+//!
+//! Graceful shutdown waits for every connection to finish, so one client
+//! that never closes would keep the process alive. [`bounded`] races the
+//! server against a timer started by the shutdown signal.
+//!
+//! The exit steps after it (saving handshake limiter state, flushing the
+//! egress archive) still run on a forced exit, each cut short by
+//! [`FORCED_EXIT_STEP_TIMEOUT`] through [`exit_step`].
+
+use std::future::{Future, IntoFuture};
+use std::time::Duration;
+
+/// Bound on each exit step once the shutdown timeout has already passed.
+pub const FORCED_EXIT_STEP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Drive `serve` to completion, unless it is still running `timeout` after
+/// `started` resolves. Returns `None` on timeout; the caller then exits
+/// without waiting for the remaining connections.
+pub async fn bounded<T>(serve: impl IntoFuture<Output = T>, started: impl Future<Output = ()>, timeout: Duration) -> Option<T> {
+    let deadline = async {
+        started.await;
+        tokio::time::sleep(timeout).await;
+    };
+    tokio::select! {
+        out = serve.into_future() => Some(out),
+        () = deadline => None,
+    }
+}
+
+/// Run one exit step, abandoning it after `limit` when set. `None` means it
+/// was abandoned.
+pub async fn exit_step<T>(step: impl Future<Output = T>, limit: Option<Duration>) -> Option<T> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, step).await.ok(),
+        None => Some(step.await),
+    }
+}
@@ -0,0 +1,137 @@
+//! Plugin stage between the built-in policy checks and service dispatch.
+//!
+//! A `PluginStage` sees every frame that passed the tenant policy and returns
+//! a `PluginVerdict`. `PluginHost` turns verdicts into `PolicyDecision`s,
+//! records them, and applies the tenant's `on_error` mode when the plugin
+//! fails (error, trap, timeout or panic) so the session task never dies.
+//!
+//! The wasmtime backend is behind the `wasm-plugins` feature.
+
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use wsprism_core::error::{ClientCode, Result, WsPrismError};
+use wsprism_core::protocol::hot::HotFrame;
+use wsprism_core::protocol::text::Envelope;
+
+use crate::config::schema::PluginErrorMode;
+use crate::obs::metrics::GatewayMetrics;
+use crate::policy::{DecisionReason, Lane, PolicyDecision};
+
+#[cfg(feature = "wasm-plugins")]
+pub use wasm::WasmPlugin;
+
+/// Who sent the frame.
+#[derive(Debug, Clone, Copy)]
+pub struct PluginCtx<'a> {
+    pub tenant: &'a str,
+    pub user: &'a str,
+    pub guest: bool,
+}
+
+/// Plugin outcome for one frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginVerdict {
+    Pass,
+    Drop,
+    Reject,
+    Close,
+}
+
+impl PluginVerdict {
+    /// Decode the i32 returned by plugin ABIs (0 pass, 1 drop, 2 reject, 3 close).
+    pub fn from_code(code: i32) -> Option<Self> {
+        match code {
+            0 => Some(PluginVerdict::Pass),
+            1 => Some(PluginVerdict::Drop),
+            2 => Some(PluginVerdict::Reject),
+            3 => Some(PluginVerdict::Close),
+            _ => None,
+        }
+    }
+
+    pub fn into_decision(self) -> PolicyDecision {
+        let reason = DecisionReason::Plugin;
+        match self {
+            PluginVerdict::Pass => PolicyDecision::Pass,
+            PluginVerdict::Drop => PolicyDecision::Drop { reason },
+            PluginVerdict::Reject => PolicyDecision::Reject {
+                code: ClientCode::NotAllowed,
+                msg: "rejected by plugin",
+                reason,
+                retry_after_ms: None,
+            },
+            PluginVerdict::Close => PolicyDecision::Close {
+                code: ClientCode::NotAllowed,
+                msg: "closed by plugin",
+                reason,
+                retry_after_ms: None,
+            },
+        }
+    }
+}
+
+/// Tenant-specific frame filter.
+///
+/// Calls run inline on the session task, so implementations must be fast and
+/// bounded.
+pub trait PluginStage: Send + Sync {
+    fn on_ext(&self, ctx: &PluginCtx<'_>, env: &Envelope) -> Result<PluginVerdict>;
+    fn on_hot(&self, ctx: &PluginCtx<'_>, frame: &HotFrame) -> Result<PluginVerdict>;
+}
+
+/// A tenant's plugin plus its failure mode.
+pub struct PluginHost {
+    tenant_id: String,
+    stage: Arc<dyn PluginStage>,
+    on_error: PluginErrorMode,
+}
+
+impl PluginHost {
+    pub fn new(tenant_id: String, stage: Arc<dyn PluginStage>, on_error: PluginErrorMode) -> Self {
+        Self { tenant_id, stage, on_error }
+    }
+
+    /// Run the Ext lane hook. Non-pass outcomes are recorded in `policy_decisions`.
+    pub fn check_ext(&self, metrics: &GatewayMetrics, ctx: &PluginCtx<'_>, env: &Envelope) -> PolicyDecision {
+        self.run(metrics, Lane::Ext, || self.stage.on_ext(ctx, env))
+    }
+
+    /// Run the Hot lane hook. Non-pass outcomes are recorded in `policy_decisions`.
+    pub fn check_hot(&self, metrics: &GatewayMetrics, ctx: &PluginCtx<'_>, frame: &HotFrame) -> PolicyDecision {
+        self.run(metrics, Lane::Hot, || self.stage.on_hot(ctx, frame))
+    }
+
+    fn run(
+        &self,
+        metrics: &GatewayMetrics,
+        lane: Lane,
+        call: impl FnOnce() -> Result<PluginVerdict>,
+    ) -> PolicyDecision {
+        let outcome = catch_unwind(AssertUnwindSafe(call))
+            .unwrap_or_else(|_| Err(WsPrismError::Internal("plugin panicked".into())));
+        let decision = match outcome {
+            Ok(v) => v.into_decision(),
+            Err(e) => {
+                metrics.plugin_errors.inc(&[("tenant", &self.tenant_id), ("lane", lane.as_str())]);
+                tracing::debug!(tenant=%self.tenant_id, lane=lane.as_str(), error=%e, "plugin call failed");
+                match self.on_error {
+                    PluginErrorMode::Drop => PolicyDecision::Drop { reason: DecisionReason::Plugin },
+                    PluginErrorMode::Pass => PolicyDecision::Pass,
+                }
+            }
+        };
+        if !matches!(decision, PolicyDecision::Pass) {
+            metrics.policy_decisions.inc(&[
+                ("tenant", &self.tenant_id),
+                ("lane", lane.as_str()),
+                ("decision", decision.kind()),
+                ("reason", decision.reason().as_str()),
+            ]);
+        }
+        decision
+    }
+}
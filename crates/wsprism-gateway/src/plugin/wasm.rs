@@ -0,0 +1,154 @@
+//! wasmtime-backed `PluginStage`.
+//!
+//! Module ABI (no imports allowed):
+//! - `memory`: exported linear memory.
+//! - `alloc(len: i32) -> i32`: returns a pointer with room for `len` bytes.
+//! - `check_ext(ptr: i32, len: i32) -> i32`: input is the envelope as JSON
+//!   (`v`, `svc`, `type`, `flags`, `seq`, `room`, `data`).
+//! - `check_hot(ptr: i32, len: i32) -> i32`: input is `[svc_id, opcode, flags]`
+//!   followed by the payload.
+//!
+//! Return values: 0 pass, 1 drop, 2 reject, 3 close; anything else is an
+//! error. A missing `check_*` export passes that lane. Every call gets a fresh
+//! instance (no state survives between frames), `fuel` units of fuel and a
+//! `timeout_ms` epoch deadline.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde_json::json;
+use wasmtime::{Config, Engine, ExternType, InstancePre, Linker, Module, Store, TypedFunc};
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::hot::HotFrame;
+use wsprism_core::protocol::text::Envelope;
+
+use super::{PluginCtx, PluginStage, PluginVerdict};
+use crate::config::schema::PluginConfig;
+
+/// Epoch tick; `timeout_ms` is expressed in ticks.
+const EPOCH_TICK: Duration = Duration::from_millis(1);
+
+/// Shared engine (fuel + epoch interruption) with its epoch ticker thread.
+fn engine() -> Result<&'static Engine> {
+    static ENGINE: OnceLock<std::result::Result<Engine, String>> = OnceLock::new();
+    ENGINE
+        .get_or_init(|| {
+            let mut cfg = Config::new();
+            cfg.consume_fuel(true).epoch_interruption(true);
+            let engine = Engine::new(&cfg).map_err(|e| e.to_string())?;
+            let ticker = engine.clone();
+            std::thread::Builder::new()
+                .name("wsprism-wasm-epoch".into())
+                .spawn(move || loop {
+                    std::thread::sleep(EPOCH_TICK);
+                    ticker.increment_epoch();
+                })
+                .map_err(|e| e.to_string())?;
+            Ok(engine)
+        })
+        .as_ref()
+        .map_err(|e| WsPrismError::Internal(format!("wasm engine init failed: {e}")))
+}
+
+fn call_err(e: impl std::fmt::Display) -> WsPrismError {
+    WsPrismError::Internal(format!("plugin call failed: {e}"))
+}
+
+/// A compiled tenant plugin module.
+pub struct WasmPlugin {
+    pre: InstancePre<()>,
+    fuel: u64,
+    deadline_ticks: u64,
+    has_ext: bool,
+    has_hot: bool,
+}
+
+impl WasmPlugin {
+    /// Read, compile and link the module at `cfg.path`.
+    pub fn load(cfg: &PluginConfig) -> Result<Self> {
+        let bytes = std::fs::read(&cfg.path)
+            .map_err(|e| WsPrismError::Internal(format!("read plugin {} failed: {e}", cfg.path)))?;
+        Self::from_bytes(&bytes, cfg)
+    }
+
+    /// Compile and link an in-memory module (`cfg.path` is ignored).
+    pub fn from_bytes(bytes: &[u8], cfg: &PluginConfig) -> Result<Self> {
+        let engine = engine()?;
+        let bad = |msg: String| WsPrismError::BadRequest(format!("plugin {}: {msg}", cfg.path));
+
+        let module = Module::new(engine, bytes).map_err(|e| bad(format!("invalid module: {e}")))?;
+        if !matches!(module.get_export("memory"), Some(ExternType::Memory(_))) {
+            return Err(bad("missing `memory` export".into()));
+        }
+        if !matches!(module.get_export("alloc"), Some(ExternType::Func(_))) {
+            return Err(bad("missing `alloc` export".into()));
+        }
+        let has_ext = matches!(module.get_export("check_ext"), Some(ExternType::Func(_)));
+        let has_hot = matches!(module.get_export("check_hot"), Some(ExternType::Func(_)));
+        if !has_ext && !has_hot {
+            return Err(bad("exports neither `check_ext` nor `check_hot`".into()));
+        }
+
+        let pre = Linker::new(engine)
+            .instantiate_pre(&module)
+            .map_err(|e| bad(format!("link failed (imports are not supported): {e}")))?;
+
+        Ok(Self {
+            pre,
+            fuel: cfg.fuel,
+            deadline_ticks: cfg.timeout_ms.max(1),
+            has_ext,
+            has_hot,
+        })
+    }
+
+    fn call(&self, export: &str, input: &[u8]) -> Result<PluginVerdict> {
+        let mut store = Store::new(self.pre.module().engine(), ());
+        store.set_fuel(self.fuel).map_err(call_err)?;
+        store.set_epoch_deadline(self.deadline_ticks);
+
+        let instance = self.pre.instantiate(&mut store).map_err(call_err)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| call_err("missing memory"))?;
+        let alloc: TypedFunc<i32, i32> = instance.get_typed_func(&mut store, "alloc").map_err(call_err)?;
+        let check: TypedFunc<(i32, i32), i32> = instance.get_typed_func(&mut store, export).map_err(call_err)?;
+
+        let len = i32::try_from(input.len()).map_err(call_err)?;
+        let ptr = alloc.call(&mut store, len).map_err(call_err)?;
+        let offset = usize::try_from(ptr).map_err(call_err)?;
+        memory.write(&mut store, offset, input).map_err(call_err)?;
+
+        let code = check.call(&mut store, (ptr, len)).map_err(call_err)?;
+        PluginVerdict::from_code(code).ok_or_else(|| call_err(format!("unknown verdict {code}")))
+    }
+}
+
+impl PluginStage for WasmPlugin {
+    fn on_ext(&self, _ctx: &PluginCtx<'_>, env: &Envelope) -> Result<PluginVerdict> {
+        if !self.has_ext {
+            return Ok(PluginVerdict::Pass);
+        }
+        let input = json!({
+            "v": env.v,
+            "svc": env.svc,
+            "type": env.msg_type,
+            "flags": env.flags,
+            "seq": env.seq,
+            "room": env.room,
+            "data": env.data,
+        })
+        .to_string();
+        self.call("check_ext", input.as_bytes())
+    }
+
+    fn on_hot(&self, _ctx: &PluginCtx<'_>, frame: &HotFrame) -> Result<PluginVerdict> {
+        if !self.has_hot {
+            return Ok(PluginVerdict::Pass);
+        }
+        let mut input = Vec::with_capacity(3 + frame.payload.len());
+        input.extend_from_slice(&[frame.svc_id, frame.opcode, frame.flags]);
+        input.extend_from_slice(&frame.payload);
+        self.call("check_hot", &input)
+    }
+}
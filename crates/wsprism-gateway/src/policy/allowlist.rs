@@ -1,78 +1,384 @@
 //! Allowlist compilation and matching utilities.
 //!
-//! Supports simple wildcard matching for Ext lane (`svc:*`) and Hot lane
-//! (`svc_id:*`) entries.
+//! Supports simple wildcard matching for Ext lane (`svc:*`, `*:type`) and Hot
+//! lane (`svc_id:*`) entries, plus inclusive opcode ranges (`svc_id:lo-hi`).
+//! With the `regex-allowlist` feature, an Ext entry starting with `~` takes a
+//! regex for the svc (`~game-.*:state`), matched against the whole svc name,
+//! and an entry wrapped in `/…/` is a regex for the whole `svc:type`
+//! (`/chat:send_(text|image)/`). Prefer `~` when only the svc varies: its
+//! type is compared as a plain string. `/…/` is for patterns over the type.
+//!
+//! Ext allowlists are kept in precedence order: exact entries, then
+//! wildcards, then regexes, each group in config order.
+//!
+//! Entries take optional clauses, in any order:
+//! - `@<=N` : frames matching this entry may be at most N bytes.
+//! - `@N`   : at most N frames/s (tenant-wide) match this entry; the rest drop.
+//!
+//! e.g. `chat:send@<=2048@20`. The first matching entry decides.
+
+use std::fmt;
+use std::sync::Arc;
 
 use wsprism_core::error::{Result, WsPrismError};
 
+use super::rate::TokenBucket;
+
+/// Optional `@...` clauses of a list entry.
+#[derive(Debug, Default)]
+pub(crate) struct Clauses {
+    /// `@<=N`
+    pub max_bytes: Option<usize>,
+    /// `@N`
+    pub rps: Option<u32>,
+}
+
+/// Split `rule@clause@clause` into the bare rule and its clauses.
+pub(crate) fn split_clauses<'a>(s: &'a str, field: &str) -> Result<(&'a str, Clauses)> {
+    let mut parts = s.split('@');
+    let rule = parts.next().unwrap_or_default();
+    let mut out = Clauses::default();
+    let bad = |what: &str| WsPrismError::BadRequest(format!("invalid {field} entry: {s} ({what})"));
+    for clause in parts {
+        if let Some(n) = clause.strip_prefix("<=") {
+            let n: usize = n.parse().ok().filter(|n| *n > 0).ok_or_else(|| bad("expected @<=N, N > 0"))?;
+            if out.max_bytes.replace(n).is_some() {
+                return Err(bad("duplicate size clause"));
+            }
+        } else {
+            let n: u32 = clause.parse().ok().filter(|n| *n > 0).ok_or_else(|| bad("expected @N, N > 0"))?;
+            if out.rps.replace(n).is_some() {
+                return Err(bad("duplicate rate clause"));
+            }
+        }
+    }
+    Ok((rule, out))
+}
+
+/// Ext rule precedence group (lower matches first).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExtMatchKind {
+    /// `svc:type`
+    Exact,
+    /// `svc:*`, `*:type`, `*:*`
+    Wildcard,
+    /// `~regex:type`, `/regex/`
+    Regex,
+}
+
+/// What a regex rule's pattern is matched against.
+#[cfg(feature = "regex-allowlist")]
+#[derive(Debug, Clone)]
+enum RegexTarget {
+    /// `~regex:type`: the svc name.
+    Svc(regex::Regex),
+    /// `/regex/`: the whole `svc:type`.
+    Entry(regex::Regex),
+}
+
 /// Compiled allowlist rule for Ext Lane.
 #[derive(Debug, Clone)]
 pub struct ExtRule {
-    pub svc: String,
+    pub svc: String, // "*" => any svc; the pattern for regex rules
     pub msg_type: Option<String>, // None => wildcard
+    /// Position of the entry in the configured list.
+    pub index: usize,
+    any_svc: bool,
+    #[cfg(feature = "regex-allowlist")]
+    regex: Option<RegexTarget>,
+    /// `@<=N` size clause.
+    pub max_bytes: Option<usize>,
+    /// `@N` rate clause (shared bucket, so clones share the budget).
+    pub rate: Option<Arc<TokenBucket>>,
+}
+
+/// Opcode matcher of a Hot Lane rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotMatchKind {
+    Exact(u8),
+    Wildcard,
+    /// Inclusive `lo..=hi`.
+    Range(u8, u8),
 }
 
 /// Compiled allowlist rule for Hot Lane.
 #[derive(Debug, Clone)]
 pub struct HotRule {
     pub svc_id: u8,
-    pub opcode: Option<u8>, // None => wildcard
+    pub opcode: HotMatchKind,
+    /// `@<=N` size clause.
+    pub max_bytes: Option<usize>,
+    /// `@N` rate clause.
+    pub rate: Option<Arc<TokenBucket>>,
+}
+
+fn rate_bucket(rps: Option<u32>) -> Option<Arc<TokenBucket>> {
+    rps.map(|n| Arc::new(TokenBucket::new(n, n)))
 }
 
+/// Compile an Ext allowlist, sorted into precedence order (see module docs).
 pub fn compile_ext_rules(raw: &[String]) -> Result<Vec<ExtRule>> {
-    let mut out = Vec::with_capacity(raw.len());
-    for s in raw {
-        // format: "svc:type" or "svc:*"
-        let (svc, ty) = s.split_once(':').ok_or_else(|| {
-            WsPrismError::BadRequest(format!("invalid ext_allowlist entry: {s} (expected svc:type)"))
-        })?;
-        let ty = if ty == "*" { None } else { Some(ty.to_string()) };
-        out.push(ExtRule { svc: svc.to_string(), msg_type: ty });
-    }
-    Ok(out)
+    let mut rules = raw
+        .iter()
+        .enumerate()
+        .map(|(index, s)| {
+            let (rule, clauses) = split_clauses(s, "ext_allowlist")?;
+            let mut rule = parse_ext_rule(rule, "ext_allowlist")?;
+            rule.index = index;
+            rule.max_bytes = clauses.max_bytes;
+            rule.rate = rate_bucket(clauses.rps);
+            Ok(rule)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    rules.sort_by_key(ExtRule::kind);
+    Ok(rules)
+}
+
+/// `policy.egress`: plain `svc:type` patterns, any of which selects a
+/// message.
+pub fn compile_egress_rules(raw: &[String]) -> Result<Vec<ExtRule>> {
+    raw.iter()
+        .map(|s| {
+            if s.contains('@') {
+                return Err(WsPrismError::BadRequest(format!(
+                    "invalid egress entry: {s} (@ clauses are not supported here)"
+                )));
+            }
+            parse_ext_rule(s, "egress")
+        })
+        .collect()
+}
+
+/// Token scopes (`claims["scopes"]`): `svc:type` patterns like `egress`,
+/// where a bare `svc` allows every type of the service.
+pub fn compile_scope_rules(raw: &[String]) -> Result<Vec<ExtRule>> {
+    let entries: Vec<String> =
+        raw.iter().map(|s| if s.contains(':') { s.clone() } else { format!("{s}:*") }).collect();
+    compile_egress_rules(&entries)
 }
 
 pub fn compile_hot_rules(raw: &[String]) -> Result<Vec<HotRule>> {
-    let mut out = Vec::with_capacity(raw.len());
-    for s in raw {
-        // format: "svc_id:opcode" where opcode may be "*"
-        let (svc_id_s, op_s) = s.split_once(':').ok_or_else(|| {
-            WsPrismError::BadRequest(format!("invalid hot_allowlist entry: {s} (expected svc_id:opcode)"))
-        })?;
-
-        let svc_id: u8 = svc_id_s.parse().map_err(|_| {
-            WsPrismError::BadRequest(format!("invalid hot_allowlist svc_id: {svc_id_s}"))
-        })?;
-
-        let opcode = if op_s == "*" {
-            None
-        } else {
-            Some(op_s.parse().map_err(|_| {
-                WsPrismError::BadRequest(format!("invalid hot_allowlist opcode: {op_s}"))
-            })?)
-        };
+    raw.iter()
+        .map(|s| {
+            let (rule, clauses) = split_clauses(s, "hot_allowlist")?;
+            let mut rule = parse_hot_rule(rule, "hot_allowlist")?;
+            rule.max_bytes = clauses.max_bytes;
+            rule.rate = rate_bucket(clauses.rps);
+            Ok(rule)
+        })
+        .collect()
+}
+
+/// Parse one "svc:type" / "svc:*" / "*:type" / "~regex:type" / "/regex/"
+/// entry; `field` names the config list in errors.
+pub(crate) fn parse_ext_rule(s: &str, field: &str) -> Result<ExtRule> {
+    if let Some(pattern) = s.strip_prefix('/').and_then(|r| r.strip_suffix('/')) {
+        return parse_entry_regex(pattern, s, field);
+    }
+    let (pattern, regex) = match s.strip_prefix('~') {
+        Some(rest) => (rest, true),
+        None => (s, false),
+    };
+    #[cfg(not(feature = "regex-allowlist"))]
+    if regex {
+        return Err(WsPrismError::BadRequest(format!(
+            "invalid {field} entry: {s} (regex entries require the regex-allowlist feature)"
+        )));
+    }
+    // The regex may itself contain ':', so split at the last one.
+    let split = if regex { pattern.rsplit_once(':') } else { pattern.split_once(':') };
+    let (svc, ty) = split.ok_or_else(|| {
+        WsPrismError::BadRequest(format!("invalid {field} entry: {s} (expected svc:type)"))
+    })?;
+    let ty = if ty == "*" { None } else { Some(ty.to_string()) };
+    Ok(ExtRule {
+        svc: svc.to_string(),
+        msg_type: ty,
+        index: 0,
+        any_svc: !regex && svc == "*",
+        #[cfg(feature = "regex-allowlist")]
+        regex: regex.then(|| compile_regex(svc, s, field).map(RegexTarget::Svc)).transpose()?,
+        max_bytes: None,
+        rate: None,
+    })
+}
 
-        out.push(HotRule { svc_id, opcode });
+/// A `/regex/` entry, matched against the whole `svc:type`.
+fn parse_entry_regex(pattern: &str, s: &str, field: &str) -> Result<ExtRule> {
+    #[cfg(not(feature = "regex-allowlist"))]
+    {
+        let _ = pattern;
+        Err(WsPrismError::BadRequest(format!(
+            "invalid {field} entry: {s} (regex entries require the regex-allowlist feature)"
+        )))
     }
-    Ok(out)
+    #[cfg(feature = "regex-allowlist")]
+    Ok(ExtRule {
+        svc: pattern.to_string(),
+        msg_type: None,
+        index: 0,
+        any_svc: false,
+        regex: Some(RegexTarget::Entry(compile_regex(pattern, s, field)?)),
+        max_bytes: None,
+        rate: None,
+    })
+}
+
+#[cfg(feature = "regex-allowlist")]
+fn compile_regex(pattern: &str, entry: &str, field: &str) -> Result<regex::Regex> {
+    regex::Regex::new(&format!("^(?:{pattern})$")).map_err(|e| {
+        WsPrismError::BadRequest(format!("invalid {field} entry: {entry} (regex: {e})"))
+    })
+}
+
+/// Parse one "svc_id:opcode" entry where opcode may be "*" or "lo-hi".
+pub(crate) fn parse_hot_rule(s: &str, field: &str) -> Result<HotRule> {
+    let (svc_id_s, op_s) = s.split_once(':').ok_or_else(|| {
+        WsPrismError::BadRequest(format!("invalid {field} entry: {s} (expected svc_id:opcode)"))
+    })?;
+
+    let svc_id: u8 = svc_id_s.parse().map_err(|_| {
+        WsPrismError::BadRequest(format!("invalid {field} svc_id: {svc_id_s}"))
+    })?;
+
+    let parse_op = |o: &str| -> Result<u8> {
+        o.parse().map_err(|_| {
+            WsPrismError::BadRequest(format!("invalid {field} opcode: {op_s}"))
+        })
+    };
+    let opcode = if op_s == "*" {
+        HotMatchKind::Wildcard
+    } else if let Some((lo, hi)) = op_s.split_once('-') {
+        let (lo, hi) = (parse_op(lo)?, parse_op(hi)?);
+        if lo > hi {
+            return Err(WsPrismError::BadRequest(format!(
+                "invalid {field} opcode range: {op_s} (lo > hi)"
+            )));
+        }
+        HotMatchKind::Range(lo, hi)
+    } else {
+        HotMatchKind::Exact(parse_op(op_s)?)
+    };
+
+    Ok(HotRule { svc_id, opcode, max_bytes: None, rate: None })
 }
 
 pub fn is_ext_allowed(rules: &[ExtRule], svc: &str, msg_type: &str) -> bool {
-    rules.iter().any(|r| {
-        if r.svc != svc { return false; }
-        match &r.msg_type {
+    rules.iter().any(|r| r.matches(svc, msg_type))
+}
+
+pub fn is_hot_allowed(rules: &[HotRule], svc_id: u8, opcode: u8) -> bool {
+    rules.iter().any(|r| r.matches(svc_id, opcode))
+}
+
+/// First rule matching `svc`/`msg_type` in precedence order (the one whose
+/// clauses apply).
+pub fn find_ext_rule<'a>(rules: &'a [ExtRule], svc: &str, msg_type: &str) -> Option<&'a ExtRule> {
+    rules.iter().find(|r| r.matches(svc, msg_type))
+}
+
+/// First rule matching `svc_id`/`opcode` (the one whose clauses apply).
+pub fn find_hot_rule(rules: &[HotRule], svc_id: u8, opcode: u8) -> Option<&HotRule> {
+    rules.iter().find(|r| r.matches(svc_id, opcode))
+}
+
+impl ExtRule {
+    pub fn matches(&self, svc: &str, msg_type: &str) -> bool {
+        #[cfg(feature = "regex-allowlist")]
+        if let Some(RegexTarget::Entry(re)) = &self.regex {
+            return entry_regex_match(re, svc, msg_type);
+        }
+        if !self.matches_svc(svc) { return false; }
+        match &self.msg_type {
             None => true,
             Some(t) => t == msg_type,
         }
+    }
+
+    #[inline]
+    fn matches_svc(&self, svc: &str) -> bool {
+        #[cfg(feature = "regex-allowlist")]
+        if let Some(RegexTarget::Svc(re)) = &self.regex {
+            return re.is_match(svc);
+        }
+        self.svc == svc || self.any_svc
+    }
+
+    pub fn kind(&self) -> ExtMatchKind {
+        #[cfg(feature = "regex-allowlist")]
+        if self.regex.is_some() {
+            return ExtMatchKind::Regex;
+        }
+        if self.any_svc || self.msg_type.is_none() {
+            ExtMatchKind::Wildcard
+        } else {
+            ExtMatchKind::Exact
+        }
+    }
+
+    /// Whether this is a `/regex/` rule over the whole `svc:type`.
+    fn is_entry_regex(&self) -> bool {
+        #[cfg(feature = "regex-allowlist")]
+        if let Some(RegexTarget::Entry(_)) = self.regex {
+            return true;
+        }
+        false
+    }
+
+    /// The svc this rule names, unless it is `*` or a regex.
+    pub fn concrete_svc(&self) -> Option<&str> {
+        match self.kind() {
+            ExtMatchKind::Regex => None,
+            _ if self.any_svc => None,
+            _ => Some(&self.svc),
+        }
+    }
+}
+
+/// Match `re` against `svc:type`, joined in a per-thread buffer so the
+/// per-message check does not allocate.
+#[cfg(feature = "regex-allowlist")]
+fn entry_regex_match(re: &regex::Regex, svc: &str, msg_type: &str) -> bool {
+    thread_local! {
+        static SVC_TYPE: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) };
+    }
+    SVC_TYPE.with_borrow_mut(|buf| {
+        buf.clear();
+        buf.push_str(svc);
+        buf.push(':');
+        buf.push_str(msg_type);
+        re.is_match(buf)
     })
 }
 
-pub fn is_hot_allowed(rules: &[HotRule], svc_id: u8, opcode: u8) -> bool {
-    rules.iter().any(|r| {
-        if r.svc_id != svc_id { return false; }
-        match r.opcode {
-            None => true,
-            Some(op) => op == opcode,
+/// Renders the entry back in config syntax, e.g. `chat:send@<=2048@20`.
+impl fmt::Display for ExtRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_entry_regex() {
+            write!(f, "/{}/", self.svc)?;
+        } else {
+            if self.kind() == ExtMatchKind::Regex {
+                f.write_str("~")?;
+            }
+            write!(f, "{}:{}", self.svc, self.msg_type.as_deref().unwrap_or("*"))?;
         }
-    })
+        if let Some(n) = self.max_bytes {
+            write!(f, "@<={n}")?;
+        }
+        if let Some(b) = &self.rate {
+            write!(f, "@{}", b.rate())?;
+        }
+        Ok(())
+    }
+}
+
+impl HotRule {
+    pub fn matches(&self, svc_id: u8, opcode: u8) -> bool {
+        if self.svc_id != svc_id { return false; }
+        match self.opcode {
+            HotMatchKind::Wildcard => true,
+            HotMatchKind::Exact(op) => op == opcode,
+            HotMatchKind::Range(lo, hi) => (lo..=hi).contains(&opcode),
+        }
+    }
 }
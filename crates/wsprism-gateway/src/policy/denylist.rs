@@ -0,0 +1,94 @@
+//! Denylist compilation and matching.
+//!
+//! Entries use the allowlist syntax (`svc:type`, `svc:*`, `svc_id:opcode`)
+//! with an optional `@N` suffix:
+//! - `chat:send`    : always deny.
+//! - `chat:send@5`  : allow at most 5/s tenant-wide, deny the rest.
+//!
+//! The first matching entry decides.
+
+use wsprism_core::error::{Result, WsPrismError};
+
+use super::allowlist::{parse_ext_rule, parse_hot_rule, split_clauses, ExtRule, HotRule};
+use super::rate::TokenBucket;
+
+/// Compiled deny rule for Ext Lane.
+#[derive(Debug)]
+pub struct ExtDenyRule {
+    pub rule: ExtRule,
+    /// `None` => hard deny; `Some` => soft throttle at `@N`/s.
+    limit: Option<TokenBucket>,
+}
+
+/// Compiled deny rule for Hot Lane.
+#[derive(Debug)]
+pub struct HotDenyRule {
+    pub rule: HotRule,
+    limit: Option<TokenBucket>,
+}
+
+/// Split `entry@N` into the rule and its per-second budget.
+/// Size clauses (`@<=N`) are allowlist-only.
+fn split_rate<'a>(s: &'a str, field: &str) -> Result<(&'a str, Option<TokenBucket>)> {
+    let (rule, clauses) = split_clauses(s, field)?;
+    if clauses.max_bytes.is_some() {
+        return Err(WsPrismError::BadRequest(format!(
+            "invalid {field} entry: {s} (size clauses are allowlist-only)"
+        )));
+    }
+    Ok((rule, clauses.rps.map(|rps| TokenBucket::new(rps, rps))))
+}
+
+pub fn compile_ext_deny_rules(raw: &[String]) -> Result<Vec<ExtDenyRule>> {
+    raw.iter()
+        .map(|s| {
+            let (rule, limit) = split_rate(s, "ext_denylist")?;
+            Ok(ExtDenyRule { rule: parse_ext_rule(rule, "ext_denylist")?, limit })
+        })
+        .collect()
+}
+
+pub fn compile_hot_deny_rules(raw: &[String]) -> Result<Vec<HotDenyRule>> {
+    raw.iter()
+        .map(|s| {
+            let (rule, limit) = split_rate(s, "hot_denylist")?;
+            Ok(HotDenyRule { rule: parse_hot_rule(rule, "hot_denylist")?, limit })
+        })
+        .collect()
+}
+
+impl ExtDenyRule {
+    /// Whether this is an `@N` throttle rather than a hard deny.
+    pub fn is_soft(&self) -> bool {
+        self.limit.is_some()
+    }
+}
+
+/// Index and rule of the first entry matching `svc`/`msg_type`, without
+/// consuming any throttle budget.
+pub fn find_ext_deny_rule<'a>(
+    rules: &'a [ExtDenyRule],
+    svc: &str,
+    msg_type: &str,
+) -> Option<(usize, &'a ExtDenyRule)> {
+    rules.iter().enumerate().find(|(_, r)| r.rule.matches(svc, msg_type))
+}
+
+/// Soft rules consume a token only when they are the matching rule.
+fn denied_by(limit: &Option<TokenBucket>) -> bool {
+    limit.as_ref().is_none_or(|b| !b.allow())
+}
+
+pub fn is_ext_denied(rules: &[ExtDenyRule], svc: &str, msg_type: &str) -> bool {
+    rules
+        .iter()
+        .find(|r| r.rule.matches(svc, msg_type))
+        .is_some_and(|r| denied_by(&r.limit))
+}
+
+pub fn is_hot_denied(rules: &[HotDenyRule], svc_id: u8, opcode: u8) -> bool {
+    rules
+        .iter()
+        .find(|r| r.rule.matches(svc_id, opcode))
+        .is_some_and(|r| denied_by(&r.limit))
+}
@@ -3,25 +3,150 @@
 //! Parses allowlists, enforces size/rate limits, and exposes connection-level
 //! limiters as needed by the transport layer.
 
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 use wsprism_core::error::ClientCode;
 
-pub use crate::config::schema::{HotErrorMode, OnExceed, SessionMode};
-use crate::config::schema::{RateLimitScope, SessionPolicy, TenantPolicy};
+pub use crate::config::schema::{HotErrorMode, OnExceed, PolicyMode, SessionMode};
+use crate::audit::{AuditEvent, AuditRecord, AuditSink};
+use crate::config::schema::{RateLimitScope, ServicePolicy, SessionPolicy, TenantPolicy};
+use crate::obs::metrics::GatewayMetrics;
+use crate::transport::bandwidth::BandwidthMeter;
 
+use super::rate::{RoomRateLimiter, TokenBucket};
+use super::strikes::StrikeCounter;
+use super::quota::{compile_quota_rules, unix_now_ms, QuotaRule, QuotaTracker};
 use super::allowlist::{
-    compile_ext_rules, compile_hot_rules, is_ext_allowed, is_hot_allowed, ExtRule, HotRule,
+    compile_egress_rules, compile_ext_rules, compile_hot_rules, find_ext_rule, find_hot_rule, is_ext_allowed, ExtRule,
+    HotRule,
 };
+use super::denylist::{
+    compile_ext_deny_rules, compile_hot_deny_rules, find_ext_deny_rule, is_ext_denied, is_hot_denied, ExtDenyRule,
+    HotDenyRule,
+};
+
+/// Why a policy decision was made. Closed set, so it is safe as a metrics label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionReason {
+    /// Passed every check.
+    None,
+    /// Frame exceeds `max_frame_bytes`.
+    Len,
+    /// Message-count or byte-rate limit.
+    Rate,
+    /// Not covered by the allowlist (or allowlist empty).
+    Allowlist,
+    /// Matched a denylist rule.
+    Denylist,
+    /// Outside the session's scope (guest restrictions).
+    Scope,
+    /// Verdict (or failure) of the tenant's plugin stage.
+    Plugin,
+    /// Too many rejects within the strike window.
+    StrikeLimit,
+    /// Exceeds the matching allowlist entry's `@<=N` size clause.
+    RuleSize,
+    /// Tenant is `read_only` (maintenance).
+    ReadOnly,
+    /// User's `policy.quotas` entry used up for the current window.
+    Quota,
+}
+
+impl DecisionReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DecisionReason::None => "none",
+            DecisionReason::Len => "len",
+            DecisionReason::Rate => "rate",
+            DecisionReason::Allowlist => "allowlist",
+            DecisionReason::Denylist => "denylist",
+            DecisionReason::Scope => "scope",
+            DecisionReason::Plugin => "plugin",
+            DecisionReason::StrikeLimit => "strike_limit",
+            DecisionReason::RuleSize => "rule_size",
+            DecisionReason::ReadOnly => "read_only",
+            DecisionReason::Quota => "quota",
+        }
+    }
+}
+
+/// Inbound lane, as used in metrics labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    Ext,
+    Hot,
+}
+
+impl Lane {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lane::Ext => "ext",
+            Lane::Hot => "hot",
+        }
+    }
+}
 
 /// Decision from policy evaluation.
+///
+/// `msg` is the human-readable text sent to the client; `reason` is the
+/// machine-readable cause used for metrics. `retry_after_ms` is the backoff
+/// hint for rate-limit decisions.
 #[derive(Debug, Clone)]
 pub enum PolicyDecision {
     Pass,
-    Drop,
-    Reject { code: ClientCode, msg: &'static str },
-    Close { code: ClientCode, msg: &'static str },
+    Drop { reason: DecisionReason },
+    Reject { code: ClientCode, msg: &'static str, reason: DecisionReason, retry_after_ms: Option<u64> },
+    Close { code: ClientCode, msg: &'static str, reason: DecisionReason, retry_after_ms: Option<u64> },
+}
+
+impl PolicyDecision {
+    /// `decision` label value.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PolicyDecision::Pass => "pass",
+            PolicyDecision::Drop { .. } => "drop",
+            PolicyDecision::Reject { .. } => "reject",
+            PolicyDecision::Close { .. } => "close",
+        }
+    }
+
+    pub fn reason(&self) -> DecisionReason {
+        match self {
+            PolicyDecision::Pass => DecisionReason::None,
+            PolicyDecision::Drop { reason }
+            | PolicyDecision::Reject { reason, .. }
+            | PolicyDecision::Close { reason, .. } => *reason,
+        }
+    }
+
+    /// Rate-limited Ext lane reject carrying its retry hint.
+    pub fn rate_limited(msg: &'static str, retry_after_ms: u64) -> Self {
+        PolicyDecision::Reject {
+            code: ClientCode::RateLimited,
+            msg,
+            reason: DecisionReason::Rate,
+            retry_after_ms: Some(retry_after_ms),
+        }
+    }
+
+    pub fn retry_after_ms(&self) -> Option<u64> {
+        match self {
+            PolicyDecision::Reject { retry_after_ms, .. } | PolicyDecision::Close { retry_after_ms, .. } => *retry_after_ms,
+            _ => None,
+        }
+    }
+}
+
+/// Why `explain_text` reached its decision, for audit logs and the admin API.
+#[derive(Debug, Clone)]
+pub struct PolicyExplanation {
+    pub decision: PolicyDecision,
+    /// e.g. `frame size 5000 > max 4096`.
+    pub reason: String,
+    /// 0-based index of the deciding list entry, if any.
+    pub rule_index: Option<usize>,
 }
 
 /// Tenant-scoped policy runtime.
@@ -32,19 +157,85 @@ pub struct TenantPolicyRuntime {
     max_frame_bytes: usize,
     ext_rules: Vec<ExtRule>,
     hot_rules: Vec<HotRule>,
+    ext_deny: Vec<ExtDenyRule>,
+    hot_deny: Vec<HotDenyRule>,
 
     // Rate limit configuration
     rate_limit_scope: RateLimitScope,
     conn_rps: u32,
     conn_burst: u32,
-    tenant_limiter: Option<RateLimiter>,
+    tenant_limiter: Option<TenantLimiter>,
+
+    // Bandwidth limits (bytes/sec, 0 = off); burst = max(rate, max_frame_bytes)
+    tenant_byte_bucket: Option<TokenBucket>,
+    session_bytes_per_sec: u32,
+    outbound_bytes_per_sec: u64,
+
+    // Violation escalation (0 = off)
+    strike_limit: u32,
+    strike_window_ms: u64,
+
+    // Per-room publish limit (shared by all rooms of the tenant)
+    room_limiter: Option<Arc<RoomRateLimiter>>,
+
+    // Reliable room publish parallelism
+    max_fanout_parallelism: usize,
+
+    // Room history sent on join
+    replay_on_join: usize,
+
+    // Ext messages archived to the egress sink
+    egress_rules: Vec<ExtRule>,
 
     // Session policy
     sessions: SessionPolicy,
 
+    // Enforce or shadow (dry run) the lane checks
+    mode: PolicyMode,
+    // Receives shadowed rejects and closes
+    audit: Option<Arc<dyn AuditSink>>,
+
     // Hot lane behavior
     hot_error_mode: HotErrorMode,
     hot_requires_active_room: bool,
+
+    // Guest access (None => guests not allowed)
+    guest_rules: Option<Vec<ExtRule>>,
+
+    // Per-service overrides (schema v2), keyed by svc
+    services: HashMap<String, ServicePolicyRuntime>,
+
+    // Maintenance switches
+    suspended: bool,
+    read_only: bool,
+
+    // Per-user message quotas (counters live in the gateway's QuotaTracker)
+    quotas: Vec<QuotaRule>,
+}
+
+/// Ext services a `read_only` tenant still accepts.
+const READ_ONLY_SVCS: [&str; 2] = ["room", "sys"];
+
+const READ_ONLY: PolicyDecision = PolicyDecision::Reject {
+    code: ClientCode::NotAllowed,
+    msg: "tenant is read-only",
+    reason: DecisionReason::ReadOnly,
+    retry_after_ms: None,
+};
+
+/// `Reject { code, reason: Scope }` unless `rules` allow `svc:msg_type`.
+fn scope_filter(rules: &[ExtRule], svc: &str, msg_type: &str, code: ClientCode, msg: &'static str) -> PolicyDecision {
+    if is_ext_allowed(rules, svc, msg_type) {
+        return PolicyDecision::Pass;
+    }
+    PolicyDecision::Reject { code, msg, reason: DecisionReason::Scope, retry_after_ms: None }
+}
+
+/// Compiled `service_policies` entry; unset fields inherit the tenant values.
+struct ServicePolicyRuntime {
+    max_frame_bytes: usize,
+    rate: Option<(u32, u32)>,
+    limiter: Option<TenantLimiter>,
 }
 
 impl TenantPolicyRuntime {
@@ -55,32 +246,173 @@ impl TenantPolicyRuntime {
     ) -> wsprism_core::Result<Self> {
         let ext_rules = compile_ext_rules(&policy.ext_allowlist)?;
         let hot_rules = compile_hot_rules(&policy.hot_allowlist)?;
+        let ext_deny = compile_ext_deny_rules(&policy.ext_denylist)?;
+        let hot_deny = compile_hot_deny_rules(&policy.hot_denylist)?;
+        let quotas = compile_quota_rules(&policy.quotas)?;
+        let egress_rules = compile_egress_rules(&policy.egress)?;
 
         let tenant_limiter = match policy.rate_limit_scope {
             RateLimitScope::Tenant | RateLimitScope::Both => {
-                Some(RateLimiter::new(policy.rate_limit_rps, policy.rate_limit_burst))
+                Some(TenantLimiter::new(policy.rate_limit_rps, policy.rate_limit_burst))
             }
             RateLimitScope::Connection => None,
         };
 
+        let byte_burst = |rate: u32| rate.max(u32::try_from(max_frame_bytes).unwrap_or(u32::MAX));
+        let tenant_byte_bucket = (policy.rate_limit_bytes_per_sec > 0).then(|| {
+            TokenBucket::new(
+                policy.rate_limit_bytes_per_sec,
+                byte_burst(policy.rate_limit_bytes_per_sec),
+            )
+        });
+
+        let room_limiter = (policy.room_rate_limit_rps > 0).then(|| {
+            let burst = match policy.room_rate_limit_burst {
+                0 => policy.room_rate_limit_rps,
+                b => b,
+            };
+            Arc::new(RoomRateLimiter::new(policy.room_rate_limit_rps, burst))
+        });
+
         Ok(Self {
             tenant_id,
             max_frame_bytes,
             ext_rules,
             hot_rules,
+            ext_deny,
+            hot_deny,
             rate_limit_scope: policy.rate_limit_scope,
             conn_rps: policy.rate_limit_rps,
             conn_burst: policy.rate_limit_burst,
             tenant_limiter,
+            tenant_byte_bucket,
+            session_bytes_per_sec: policy.per_session_bytes_per_sec,
+            outbound_bytes_per_sec: policy.max_outbound_bytes_per_sec,
+            strike_limit: policy.strike_limit,
+            strike_window_ms: policy.strike_window_ms,
+            room_limiter,
+            max_fanout_parallelism: policy.max_fanout_parallelism,
+            replay_on_join: policy.replay_on_join,
+            egress_rules,
             sessions: policy.sessions.clone(),
+            mode: policy.effective_mode(),
+            audit: None,
             hot_error_mode: policy.hot_error_mode,
             hot_requires_active_room: policy.hot_requires_active_room,
+            guest_rules: None,
+            services: HashMap::new(),
+            suspended: false,
+            read_only: false,
+            quotas,
         })
     }
 
+    /// Compile per-service overrides (`service_policies`).
+    pub fn with_service_policies(
+        mut self,
+        policies: &HashMap<String, ServicePolicy>,
+    ) -> wsprism_core::Result<Self> {
+        for (svc, sp) in policies {
+            let rate = (sp.rate_limit_rps.is_some() || sp.rate_limit_burst.is_some()).then(|| {
+                (
+                    sp.rate_limit_rps.unwrap_or(self.conn_rps),
+                    sp.rate_limit_burst.unwrap_or(self.conn_burst),
+                )
+            });
+            let limiter = match (rate, self.rate_limit_scope) {
+                (Some((rps, burst)), RateLimitScope::Tenant | RateLimitScope::Both) => {
+                    Some(TenantLimiter::new(rps, burst))
+                }
+                _ => None,
+            };
+            self.services.insert(
+                svc.clone(),
+                ServicePolicyRuntime {
+                    max_frame_bytes: sp.max_frame_bytes.unwrap_or(self.max_frame_bytes),
+                    rate,
+                    limiter,
+                },
+            );
+        }
+        Ok(self)
+    }
+
+    /// Add `limits.max_messages_per_day` (0 = none) to the quotas charged
+    /// by `evaluate_quota`.
+    pub fn with_daily_message_limit(mut self, limit: u64) -> wsprism_core::Result<Self> {
+        if limit > 0 {
+            self.quotas.push(QuotaRule::daily_messages(limit)?);
+        }
+        Ok(self)
+    }
+
+    /// Apply the tenant's maintenance switches (`suspended`, `read_only`).
+    pub fn with_maintenance(mut self, suspended: bool, read_only: bool) -> Self {
+        self.suspended = suspended;
+        self.read_only = read_only;
+        self
+    }
+
+    /// Record shadowed rejects and closes in `sink`.
+    pub fn with_audit(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Some(sink);
+        self
+    }
+
+    /// Enable guest access with the given Ext lane scopes.
+    pub fn with_guest_scopes(mut self, scopes: &[String]) -> wsprism_core::Result<Self> {
+        self.guest_rules = Some(compile_ext_rules(scopes)?);
+        Ok(self)
+    }
+
+    pub fn allow_guest(&self) -> bool {
+        self.guest_rules.is_some()
+    }
+
+    /// Compiled Ext lane allowlist.
+    pub fn ext_rules(&self) -> &[ExtRule] {
+        &self.ext_rules
+    }
+    /// Compiled Hot lane allowlist.
+    pub fn hot_rules(&self) -> &[HotRule] {
+        &self.hot_rules
+    }
+
+    /// Per-room publish limiter, if `room_rate_limit_rps` is set.
+    pub fn room_limiter(&self) -> Option<Arc<RoomRateLimiter>> {
+        self.room_limiter.clone()
+    }
+
+    /// Max concurrent recipients of one reliable room publish.
+    pub fn max_fanout_parallelism(&self) -> usize {
+        self.max_fanout_parallelism
+    }
+
+    /// Recent room messages replayed to a session after it joins.
+    pub fn replay_on_join(&self) -> usize {
+        self.replay_on_join
+    }
+
+    /// Whether `svc`/`msg_type` messages are archived (`policy.egress`).
+    pub fn archives(&self, svc: &str, msg_type: &str) -> bool {
+        is_ext_allowed(&self.egress_rules, svc, msg_type)
+    }
+
     pub fn session_policy(&self) -> &SessionPolicy {
         &self.sessions
     }
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+    pub fn mode(&self) -> PolicyMode {
+        self.mode
+    }
+    pub fn max_frame_bytes(&self) -> usize {
+        self.max_frame_bytes
+    }
     pub fn hot_error_mode(&self) -> HotErrorMode {
         self.hot_error_mode
     }
@@ -88,164 +420,498 @@ impl TenantPolicyRuntime {
         self.hot_requires_active_room
     }
 
-    /// Create per-connection limiter if enabled (Connection/Both).
+    /// Create per-connection limiter if enabled (Connection/Both), with a
+    /// separate bucket for each service that overrides the rate.
     pub fn new_connection_limiter(&self) -> Option<ConnRateLimiter> {
         match self.rate_limit_scope {
             RateLimitScope::Connection | RateLimitScope::Both => {
-                Some(ConnRateLimiter::new(self.conn_rps, self.conn_burst))
+                let mut lim = ConnRateLimiter::new(self.conn_rps, self.conn_burst);
+                for (svc, sp) in &self.services {
+                    if let Some((rps, burst)) = sp.rate {
+                        lim.services.insert(svc.clone(), TokenBucket::new(rps, burst));
+                    }
+                }
+                Some(lim)
             }
             RateLimitScope::Tenant => None,
         }
     }
 
+    /// Create the per-session byte bucket if `per_session_bytes_per_sec` is set.
+    pub fn new_session_byte_bucket(&self) -> Option<TokenBucket> {
+        let rate = self.session_bytes_per_sec;
+        (rate > 0).then(|| {
+            let burst = rate.max(u32::try_from(self.max_frame_bytes).unwrap_or(u32::MAX));
+            TokenBucket::new(rate, burst)
+        })
+    }
+
+    /// Create the per-session outbound meter if `max_outbound_bytes_per_sec`
+    /// is set. Burst is one second's worth, or `max_frame_bytes` if larger.
+    pub fn new_outbound_meter(&self) -> Option<Arc<BandwidthMeter>> {
+        let rate = self.outbound_bytes_per_sec;
+        (rate > 0).then(|| Arc::new(BandwidthMeter::new(self.tenant_id.as_str(), rate, self.max_frame_bytes as u64)))
+    }
+
+    /// Create the per-session strike counter if `strike_limit` is set.
+    pub fn new_strike_counter(&self) -> Option<StrikeCounter> {
+        (self.strike_limit > 0).then(|| {
+            StrikeCounter::new(self.strike_limit, Duration::from_millis(self.strike_window_ms))
+        })
+    }
+
+    /// Bandwidth precheck, run on the raw frame before any parsing.
+    ///
+    /// Debits `bytes_len` from the session bucket, then the tenant bucket.
+    /// `Err(secs)` is the retry hint for the bucket that denied.
+    pub fn admit_bytes(&self, bytes_len: usize, session: Option<&TokenBucket>) -> Result<(), u64> {
+        let cost = u32::try_from(bytes_len).unwrap_or(u32::MAX);
+        for bucket in [session, self.tenant_byte_bucket.as_ref()].into_iter().flatten() {
+            if !bucket.try_take(cost) {
+                return Err(bucket.retry_after_secs(cost));
+            }
+        }
+        Ok(())
+    }
+
+    /// Count `decision` in `wsprism_policy_decisions_total{tenant,lane,decision,reason}`.
+    pub fn record(&self, metrics: &GatewayMetrics, lane: Lane, decision: &PolicyDecision) {
+        metrics.policy_decisions.inc(&[
+            ("tenant", &self.tenant_id),
+            ("lane", lane.as_str()),
+            ("decision", decision.kind()),
+            ("reason", decision.reason().as_str()),
+        ]);
+    }
+
+    /// Apply `mode` to a lane decision and record it.
+    ///
+    /// In shadow mode a non-pass decision is counted with `mode="shadow"` and
+    /// turned into `Pass`, except for length, guest or token scope and read-only
+    /// violations. Would-be rejects and closes are also logged, counted in
+    /// `wsprism_policy_dryrun_violations_total{tenant,lane,decision}` and
+    /// sent to the audit sink. Checks made outside the runtime (e.g. the
+    /// per-connection rate) go through here too.
+    pub fn finish(&self, metrics: &GatewayMetrics, lane: Lane, decision: PolicyDecision) -> PolicyDecision {
+        let shadowed = self.mode == PolicyMode::Shadow
+            && !matches!(
+                decision.reason(),
+                DecisionReason::None | DecisionReason::Len | DecisionReason::Scope | DecisionReason::ReadOnly
+            );
+        if !shadowed {
+            self.record(metrics, lane, &decision);
+            return decision;
+        }
+        metrics.policy_decisions.inc(&[
+            ("tenant", &self.tenant_id),
+            ("lane", lane.as_str()),
+            ("decision", decision.kind()),
+            ("reason", decision.reason().as_str()),
+            ("mode", "shadow"),
+        ]);
+        if matches!(decision, PolicyDecision::Reject { .. } | PolicyDecision::Close { .. }) {
+            tracing::warn!(tenant = %self.tenant_id, lane = lane.as_str(), "policy dry_run violation: {decision:?}");
+            metrics.policy_dryrun_violations.inc(&[
+                ("tenant", &self.tenant_id),
+                ("lane", lane.as_str()),
+                ("decision", decision.kind()),
+            ]);
+            if let Some(audit) = &self.audit {
+                let reason = format!("{} {} ({})", lane.as_str(), decision.kind(), decision.reason().as_str());
+                audit.record(AuditEvent::PolicyShadowed(AuditRecord::new(self.tenant_id.as_str(), reason)));
+            }
+        }
+        PolicyDecision::Pass
+    }
+
+    /// Full Ext lane evaluation for one frame (`check_text`, then guest
+    /// scope), recorded once in `metrics`. Honors shadow `mode`.
+    pub fn evaluate_text(
+        &self,
+        metrics: &GatewayMetrics,
+        bytes_len: usize,
+        svc: &str,
+        msg_type: &str,
+        guest: bool,
+    ) -> PolicyDecision {
+        let mut d = self.check_text(bytes_len, svc, msg_type);
+        // Guest scope is never shadowed, so check it behind shadowed rejects too.
+        let scope_applies = match d {
+            PolicyDecision::Pass => true,
+            _ => self.mode == PolicyMode::Shadow && d.reason() != DecisionReason::Len,
+        };
+        if guest && scope_applies {
+            if let scoped @ PolicyDecision::Reject { .. } = self.check_guest_text(svc, msg_type) {
+                d = scoped;
+            }
+        }
+        self.finish(metrics, Lane::Ext, d)
+    }
+
+    /// Charge `user`'s quotas for an Ext frame that passed every other check.
+    ///
+    /// Recorded in `metrics` only when it rejects (the frame's pass is
+    /// already counted). Honors shadow `mode`.
+    pub async fn evaluate_quota(
+        &self,
+        metrics: &GatewayMetrics,
+        tracker: &QuotaTracker,
+        user: &str,
+        svc: &str,
+        msg_type: &str,
+    ) -> PolicyDecision {
+        let rules: Vec<&QuotaRule> = self.quotas.iter().filter(|q| q.matches(svc, msg_type)).collect();
+        if rules.is_empty() {
+            return PolicyDecision::Pass;
+        }
+        let now = unix_now_ms();
+        match tracker.charge(&self.tenant_id, user, &rules, now).await {
+            Ok(()) => PolicyDecision::Pass,
+            Err(reset_at) => {
+                let d = PolicyDecision::Reject {
+                    code: ClientCode::QuotaExceeded,
+                    msg: "quota exceeded",
+                    reason: DecisionReason::Quota,
+                    retry_after_ms: Some(reset_at.saturating_sub(now)),
+                };
+                self.finish(metrics, Lane::Ext, d)
+            }
+        }
+    }
+
+    /// Full Hot lane evaluation for one frame, recorded once in `metrics`.
+    /// Honors shadow `mode`. Guests are Ext-lane only (spectators), so their frames are dropped.
+    pub fn evaluate_hot(
+        &self,
+        metrics: &GatewayMetrics,
+        bytes_len: usize,
+        svc_id: u8,
+        opcode: u8,
+        guest: bool,
+    ) -> PolicyDecision {
+        let d = if guest {
+            PolicyDecision::Drop { reason: DecisionReason::Scope }
+        } else {
+            self.check_hot(bytes_len, svc_id, opcode)
+        };
+        self.finish(metrics, Lane::Hot, d)
+    }
+
     /// Cheap global checks for any inbound payload.
     pub fn check_len(&self, bytes_len: usize) -> PolicyDecision {
-        if bytes_len > self.max_frame_bytes {
+        Self::check_len_against(bytes_len, self.max_frame_bytes)
+    }
+
+    fn check_len_against(bytes_len: usize, max_frame_bytes: usize) -> PolicyDecision {
+        if bytes_len > max_frame_bytes {
             return PolicyDecision::Close {
                 code: ClientCode::BadRequest,
                 msg: "frame too large",
+                reason: DecisionReason::Len,
+                retry_after_ms: None,
             };
         }
         PolicyDecision::Pass
     }
 
-    /// Ext Lane policy: svc/type allowlist + (optional) tenant-level rate limit.
+    /// Ext Lane policy: read-only switch, svc/type denylist, then allowlist + (optional) tenant-level rate limit.
+    /// Size and rate limits come from `svc`'s override when it has one.
     pub fn check_text(&self, bytes_len: usize, svc: &str, msg_type: &str) -> PolicyDecision {
-        match self.check_len(bytes_len) {
+        let service = self.services.get(svc);
+        let max_frame_bytes = service.map_or(self.max_frame_bytes, |s| s.max_frame_bytes);
+        match Self::check_len_against(bytes_len, max_frame_bytes) {
             PolicyDecision::Pass => {}
             other => return other,
         }
 
-        if let Some(lim) = &self.tenant_limiter {
-            if !lim.allow() {
-                return PolicyDecision::Drop;
+        if self.read_only && !READ_ONLY_SVCS.contains(&svc) {
+            return READ_ONLY;
+        }
+
+        let limiter = match service {
+            Some(s) if s.rate.is_some() => s.limiter.as_ref(),
+            _ => self.tenant_limiter.as_ref(),
+        };
+        if let Some(lim) = limiter {
+            if let Err(wait_ms) = lim.acquire() {
+                return PolicyDecision::rate_limited("rate limited", wait_ms);
             }
         }
 
+        if is_ext_denied(&self.ext_deny, svc, msg_type) {
+            return PolicyDecision::Reject {
+                code: ClientCode::NotAllowed,
+                msg: "svc/type denied",
+                reason: DecisionReason::Denylist,
+                retry_after_ms: None,
+            };
+        }
+
         if self.ext_rules.is_empty() {
             return PolicyDecision::Reject {
                 code: ClientCode::BadRequest,
                 msg: "ext_allowlist empty (strict deny)",
+                reason: DecisionReason::Allowlist,
+                retry_after_ms: None,
             };
         }
 
-        if !is_ext_allowed(&self.ext_rules, svc, msg_type) {
+        let Some(rule) = find_ext_rule(&self.ext_rules, svc, msg_type) else {
             return PolicyDecision::Reject {
                 code: ClientCode::BadRequest,
                 msg: "svc/type not allowed",
+                reason: DecisionReason::Allowlist,
+                retry_after_ms: None,
+            };
+        };
+
+        if rule.max_bytes.is_some_and(|max| bytes_len > max) {
+            return PolicyDecision::Reject {
+                code: ClientCode::PayloadTooLarge,
+                msg: "payload too large for svc/type",
+                reason: DecisionReason::RuleSize,
+                retry_after_ms: None,
             };
         }
+        if let Some(Err(wait_ms)) = rule.rate.as_ref().map(|b| b.try_acquire(1)) {
+            return PolicyDecision::rate_limited("svc/type rate limited", wait_ms);
+        }
 
         PolicyDecision::Pass
     }
 
-    /// Hot Lane policy: svc_id/opcode allowlist + (optional) tenant-level rate limit.
+    /// `check_text` with its rationale spelled out.
+    ///
+    /// Side-effect free: rate limits and `@N` throttles are not evaluated
+    /// (no tokens are spent), so a `Pass` here may still drop on the wire.
+    /// Not meant for the per-frame path.
+    pub fn explain_text(&self, bytes_len: usize, svc: &str, msg_type: &str) -> PolicyExplanation {
+        let explained = |decision, reason: String, rule_index| PolicyExplanation {
+            decision,
+            reason,
+            rule_index,
+        };
+
+        let max_frame_bytes = self.services.get(svc).map_or(self.max_frame_bytes, |s| s.max_frame_bytes);
+        let len = Self::check_len_against(bytes_len, max_frame_bytes);
+        if !matches!(len, PolicyDecision::Pass) {
+            return explained(len, format!("frame size {bytes_len} > max {max_frame_bytes}"), None);
+        }
+
+        if self.read_only && !READ_ONLY_SVCS.contains(&svc) {
+            return explained(READ_ONLY, "tenant is read-only; only room:* and sys:* accepted".into(), None);
+        }
+
+        let target = format!("svc={svc} type={msg_type}");
+        let mut throttled = None;
+        if let Some((i, deny)) = find_ext_deny_rule(&self.ext_deny, svc, msg_type) {
+            if !deny.is_soft() {
+                let d = PolicyDecision::Reject {
+                    code: ClientCode::NotAllowed,
+                    msg: "svc/type denied",
+                    reason: DecisionReason::Denylist,
+                    retry_after_ms: None,
+                };
+                return explained(d, format!("{target} matched deny rule {i} ({})", deny.rule), Some(i));
+            }
+            throttled = Some(i);
+        }
+
+        if self.ext_rules.is_empty() {
+            let d = PolicyDecision::Reject {
+                code: ClientCode::BadRequest,
+                msg: "ext_allowlist empty (strict deny)",
+                reason: DecisionReason::Allowlist,
+                retry_after_ms: None,
+            };
+            return explained(d, "ext_allowlist empty; strict deny".into(), None);
+        }
+
+        let Some(rule) = find_ext_rule(&self.ext_rules, svc, msg_type) else {
+            let d = PolicyDecision::Reject {
+                code: ClientCode::BadRequest,
+                msg: "svc/type not allowed",
+                reason: DecisionReason::Allowlist,
+                retry_after_ms: None,
+            };
+            return explained(d, "no matching rule; strict deny".into(), None);
+        };
+        let i = rule.index;
+
+        if let Some(max) = rule.max_bytes.filter(|max| bytes_len > *max) {
+            let d = PolicyDecision::Reject {
+                code: ClientCode::PayloadTooLarge,
+                msg: "payload too large for svc/type",
+                reason: DecisionReason::RuleSize,
+                retry_after_ms: None,
+            };
+            let reason = format!("frame size {bytes_len} > max {max} of rule {i} ({rule})");
+            return explained(d, reason, Some(i));
+        }
+
+        let mut reason = format!("{target} matched rule {i} ({rule})");
+        if let Some(t) = throttled {
+            reason.push_str(&format!("; throttled by deny rule {t}"));
+        }
+        explained(PolicyDecision::Pass, reason, Some(i))
+    }
+
+    /// Guest scope filter, evaluated after `check_text` passed.
+    pub fn check_guest_text(&self, svc: &str, msg_type: &str) -> PolicyDecision {
+        let rules = self.guest_rules.as_deref().unwrap_or_default();
+        scope_filter(rules, svc, msg_type, ClientCode::NotAllowed, "not allowed for guest")
+    }
+
+    /// Token scope filter (`compile_scope_rules`), evaluated after
+    /// `evaluate_text` passed.
+    ///
+    /// Recorded in `metrics` only when it rejects. Never shadowed, like the
+    /// guest scope.
+    pub fn evaluate_token_scopes(
+        &self,
+        metrics: &GatewayMetrics,
+        scopes: &[ExtRule],
+        svc: &str,
+        msg_type: &str,
+    ) -> PolicyDecision {
+        match scope_filter(scopes, svc, msg_type, ClientCode::AuthFailed, "not allowed by token scope") {
+            PolicyDecision::Pass => PolicyDecision::Pass,
+            d => self.finish(metrics, Lane::Ext, d),
+        }
+    }
+
+    /// Hot Lane policy: read-only switch, svc_id/opcode denylist, then allowlist + (optional) tenant-level rate limit.
     pub fn check_hot(&self, bytes_len: usize, svc_id: u8, opcode: u8) -> PolicyDecision {
         match self.check_len(bytes_len) {
             PolicyDecision::Pass => {}
             other => return other,
         }
 
+        if self.read_only {
+            return READ_ONLY;
+        }
+
         if let Some(lim) = &self.tenant_limiter {
-            if !lim.allow() {
-                return PolicyDecision::Drop;
+            if lim.acquire().is_err() {
+                return PolicyDecision::Drop { reason: DecisionReason::Rate };
             }
         }
 
+        if is_hot_denied(&self.hot_deny, svc_id, opcode) {
+            return PolicyDecision::Drop { reason: DecisionReason::Denylist };
+        }
+
         if self.hot_rules.is_empty() {
-            return PolicyDecision::Drop; // strict deny
+            return PolicyDecision::Drop { reason: DecisionReason::Allowlist }; // strict deny
         }
 
-        if !is_hot_allowed(&self.hot_rules, svc_id, opcode) {
-            return PolicyDecision::Drop;
+        let Some(rule) = find_hot_rule(&self.hot_rules, svc_id, opcode) else {
+            return PolicyDecision::Drop { reason: DecisionReason::Allowlist };
+        };
+
+        if rule.max_bytes.is_some_and(|max| bytes_len > max) {
+            return PolicyDecision::Drop { reason: DecisionReason::RuleSize };
+        }
+        if rule.rate.as_ref().is_some_and(|b| !b.allow()) {
+            return PolicyDecision::Drop { reason: DecisionReason::Rate };
         }
 
         PolicyDecision::Pass
     }
 }
 
-/// Per-connection token bucket (no mutex).
+/// Per-connection token bucket (plus per-service overrides).
 #[derive(Debug)]
 pub struct ConnRateLimiter {
     bucket: TokenBucket,
+    services: HashMap<String, TokenBucket>,
 }
 
 impl ConnRateLimiter {
     pub fn new(rps: u32, burst: u32) -> Self {
         Self {
             bucket: TokenBucket::new(rps, burst),
+            services: HashMap::new(),
         }
     }
 
     pub fn allow(&mut self) -> bool {
         self.bucket.allow()
     }
+
+    /// Take from `svc`'s own bucket if it has one, else the shared bucket.
+    pub fn allow_svc(&mut self, svc: &str) -> bool {
+        self.acquire_svc(svc).is_ok()
+    }
+
+    /// `allow_svc`, with the wait in milliseconds until the next token when denied.
+    pub fn acquire_svc(&mut self, svc: &str) -> Result<(), u64> {
+        self.services.get(svc).unwrap_or(&self.bucket).try_acquire(1)
+    }
+}
+
+/// Shared (tenant-level) limiter contract.
+///
+/// Implemented by the built-in token bucket and, with the `governor-ratelimit`
+/// feature, by a `governor` direct limiter.
+trait InternalRateLimiter: Send + Sync {
+    /// Take one permit; `Err` is the wait in milliseconds until the next.
+    fn acquire(&self) -> Result<(), u64>;
 }
 
-/// Minimal token-bucket limiter (tenant-level, shared).
+#[cfg(not(feature = "governor-ratelimit"))]
+type TenantLimiter = RateLimiter;
+#[cfg(feature = "governor-ratelimit")]
+type TenantLimiter = GovernorLimiter;
+
+/// Token-bucket limiter (tenant-level, shared, lock-free).
+#[cfg(not(feature = "governor-ratelimit"))]
 struct RateLimiter {
-    inner: Arc<Mutex<TokenBucket>>,
+    bucket: TokenBucket,
 }
 
+#[cfg(not(feature = "governor-ratelimit"))]
 impl RateLimiter {
     fn new(rps: u32, burst: u32) -> Self {
         Self {
-            inner: Arc::new(Mutex::new(TokenBucket::new(rps, burst))),
+            bucket: TokenBucket::new(rps, burst),
         }
     }
+}
 
-    fn allow(&self) -> bool {
-        // Poisoned mutex means logic bug; treat as "deny" instead of panic.
-        // (enterprise: never bring down gateway)
-        if let Ok(mut g) = self.inner.lock() {
-            g.allow()
-        } else {
-            false
-        }
+#[cfg(not(feature = "governor-ratelimit"))]
+impl InternalRateLimiter for RateLimiter {
+    fn acquire(&self) -> Result<(), u64> {
+        self.bucket.try_acquire(1)
     }
 }
 
-#[derive(Debug)]
-struct TokenBucket {
-    rps: u32,
-    capacity: u32,
-    tokens: u32,
-    last: Instant,
+/// `governor`-backed tenant limiter (GCRA, not keyed).
+#[cfg(feature = "governor-ratelimit")]
+struct GovernorLimiter {
+    inner: governor::DefaultDirectRateLimiter,
 }
 
-impl TokenBucket {
+#[cfg(feature = "governor-ratelimit")]
+impl GovernorLimiter {
     fn new(rps: u32, burst: u32) -> Self {
-        let rps = rps.max(1);
-        let capacity = burst.max(1);
+        use std::num::NonZeroU32;
+        let rps = NonZeroU32::new(rps.max(1)).unwrap_or(NonZeroU32::MIN);
+        let burst = NonZeroU32::new(burst.max(1)).unwrap_or(NonZeroU32::MIN);
+        let quota = governor::Quota::per_second(rps).allow_burst(burst);
         Self {
-            rps,
-            capacity,
-            tokens: capacity,
-            last: Instant::now(),
-        }
-    }
-
-    fn allow(&mut self) -> bool {
-        self.refill();
-
-        if self.tokens == 0 {
-            return false;
+            inner: governor::RateLimiter::direct(quota),
         }
-        self.tokens -= 1;
-        true
     }
+}
 
-    fn refill(&mut self) {
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last);
-        if elapsed < Duration::from_millis(50) {
-            return;
-        }
-
-        let add = (elapsed.as_millis() as u64 * self.rps as u64 / 1000) as u32;
-        if add > 0 {
-            self.tokens = (self.tokens + add).min(self.capacity);
-            self.last = now;
-        }
+#[cfg(feature = "governor-ratelimit")]
+impl InternalRateLimiter for GovernorLimiter {
+    fn acquire(&self) -> Result<(), u64> {
+        use governor::clock::Clock;
+        self.inner.check().map_err(|not_until| {
+            let wait = not_until.wait_time_from(self.inner.clock().now());
+            (wait.as_millis() as u64).max(1)
+        })
     }
 }
@@ -1,9 +1,13 @@
-//! Policy layer (allowlists, limits, rate limiting).
+//! Policy layer (allow/deny lists, limits, rate limiting).
 //!
 //! Compiles tenant policy configuration into fast lookup structures for
 //! transport and dispatcher layers to consume at runtime.
 
 pub mod allowlist;
+pub mod denylist;
 pub mod engine;
+pub mod quota;
+pub mod rate;
+pub mod strikes;
 
-pub use engine::{PolicyDecision, TenantPolicyRuntime};
+pub use engine::{DecisionReason, Lane, PolicyDecision, PolicyExplanation, TenantPolicyRuntime};
@@ -0,0 +1,267 @@
+//! Per-user message quotas (`policy.quotas`).
+//!
+//! A quota allows at most `limit` matching Ext frames per user per window
+//! (`1h` or `24h`). A window starts at the first counted frame and resets
+//! lazily on the first frame after it has elapsed.
+//!
+//! Counters live in the gateway-wide `QuotaTracker`, keyed by tenant, user
+//! and pattern, so they survive policy reloads. Counters whose window has
+//! elapsed are dropped by `sweep_expired`. An optional `QuotaStore` receives
+//! every change and is consulted for users not yet in memory.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use serde::Serialize;
+use wsprism_core::error::{Result, WsPrismError};
+
+use super::allowlist::{parse_ext_rule, split_clauses, ExtRule};
+use crate::config::schema::{QuotaSpec, QuotaWindow};
+
+/// Above this many counters, expired ones are evicted before inserting.
+const MAX_QUOTA_COUNTERS: usize = 262_144;
+
+/// Compiled `policy.quotas` entry.
+#[derive(Debug, Clone)]
+pub struct QuotaRule {
+    /// Pattern as configured; part of the counter key.
+    pub pattern: String,
+    rule: ExtRule,
+    pub limit: u64,
+    pub window_ms: u64,
+}
+
+/// Counter pattern of `limits.max_messages_per_day`.
+pub const DAILY_MESSAGES_PATTERN: &str = "limits.max_messages_per_day";
+
+impl QuotaRule {
+    /// Rule counting every Ext frame against `limit` per 24h.
+    pub fn daily_messages(limit: u64) -> Result<Self> {
+        Ok(Self {
+            pattern: DAILY_MESSAGES_PATTERN.to_string(),
+            rule: parse_ext_rule("*:*", "limits")?,
+            limit,
+            window_ms: QuotaWindow::Day.as_millis(),
+        })
+    }
+
+    pub fn matches(&self, svc: &str, msg_type: &str) -> bool {
+        self.rule.matches(svc, msg_type)
+    }
+}
+
+/// Compile `policy.quotas`, ordered by pattern. Patterns use the
+/// `ext_allowlist` syntax without `@` clauses.
+pub fn compile_quota_rules(quotas: &HashMap<String, QuotaSpec>) -> Result<Vec<QuotaRule>> {
+    let mut out = Vec::with_capacity(quotas.len());
+    for (pattern, spec) in quotas {
+        let (bare, clauses) = split_clauses(pattern, "quotas")?;
+        if clauses.max_bytes.is_some() || clauses.rps.is_some() {
+            return Err(WsPrismError::BadRequest(format!(
+                "invalid quotas entry: {pattern} (@ clauses are not supported)"
+            )));
+        }
+        out.push(QuotaRule {
+            pattern: pattern.clone(),
+            rule: parse_ext_rule(bare, "quotas")?,
+            limit: spec.limit,
+            window_ms: spec.window.as_millis(),
+        });
+    }
+    out.sort_by(|a, b| a.pattern.cmp(&b.pattern));
+    Ok(out)
+}
+
+/// Milliseconds since the Unix epoch (quota windows use wall-clock time so
+/// they can be persisted and reported).
+pub fn unix_now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Counter identity. `user` is the user id as authenticated (not tenant-qualified).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuotaKey {
+    pub tenant: String,
+    pub user: String,
+    pub pattern: String,
+}
+
+/// Usage within the current window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaCounter {
+    pub window_start_ms: u64,
+    pub window_ms: u64,
+    pub count: u64,
+}
+
+impl QuotaCounter {
+    fn new(now_ms: u64, window_ms: u64) -> Self {
+        Self { window_start_ms: now_ms, window_ms, count: 0 }
+    }
+
+    /// When the count resets (Unix ms).
+    pub fn reset_at_ms(&self) -> u64 {
+        self.window_start_ms.saturating_add(self.window_ms)
+    }
+
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        now_ms >= self.reset_at_ms()
+    }
+}
+
+/// Persistence hook for quota counters (e.g. Redis, to share quotas across
+/// gateway nodes).
+///
+/// `load` is called for keys the tracker does not hold in memory; `save`
+/// after every change. Failures should be handled (logged) by the store.
+#[async_trait]
+pub trait QuotaStore: Send + Sync {
+    async fn load(&self, key: &QuotaKey) -> Option<QuotaCounter>;
+    async fn save(&self, key: &QuotaKey, counter: &QuotaCounter);
+}
+
+/// One counter as exported by the admin stats endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaUsage {
+    pub tenant: String,
+    pub user: String,
+    pub pattern: String,
+    pub count: u64,
+    pub window_start_unix_ms: u64,
+    pub reset_at_unix_ms: u64,
+}
+
+/// Gateway-wide quota counters.
+///
+/// Best effort under concurrency: two sessions of one user racing on the
+/// last unit may both be admitted when a frame matches several quotas.
+#[derive(Default)]
+pub struct QuotaTracker {
+    counters: DashMap<QuotaKey, QuotaCounter>,
+    store: Option<Arc<dyn QuotaStore>>,
+}
+
+impl QuotaTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Persist counters through `store`.
+    pub fn with_store(mut self, store: Arc<dyn QuotaStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Count one frame against every rule in `rules` at `now_ms`.
+    ///
+    /// All or nothing: if any rule is used up, nothing is counted and the
+    /// latest reset time among the exhausted rules is returned.
+    pub async fn charge(
+        &self,
+        tenant: &str,
+        user: &str,
+        rules: &[&QuotaRule],
+        now_ms: u64,
+    ) -> std::result::Result<(), u64> {
+        let mut charged = Vec::with_capacity(rules.len());
+        let mut exhausted: Option<u64> = None;
+        for rule in rules {
+            let key = QuotaKey {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+                pattern: rule.pattern.clone(),
+            };
+            match self.charge_one(&key, rule, now_ms).await {
+                Ok(()) => charged.push(key),
+                Err(reset_at) => exhausted = Some(exhausted.map_or(reset_at, |r| r.max(reset_at))),
+            }
+        }
+        let Some(reset_at) = exhausted else { return Ok(()) };
+        for key in &charged {
+            self.refund(key).await;
+        }
+        Err(reset_at)
+    }
+
+    async fn charge_one(&self, key: &QuotaKey, rule: &QuotaRule, now_ms: u64) -> std::result::Result<(), u64> {
+        let loaded = match &self.store {
+            Some(store) if !self.counters.contains_key(key) => store.load(key).await,
+            _ => None,
+        };
+        if self.counters.len() >= MAX_QUOTA_COUNTERS && !self.counters.contains_key(key) {
+            self.sweep_expired(now_ms);
+        }
+        let snapshot = {
+            let mut c = self
+                .counters
+                .entry(key.clone())
+                .or_insert_with(|| loaded.unwrap_or_else(|| QuotaCounter::new(now_ms, rule.window_ms)));
+            c.window_ms = rule.window_ms;
+            if c.is_expired(now_ms) {
+                *c = QuotaCounter::new(now_ms, rule.window_ms);
+            }
+            if c.count >= rule.limit {
+                return Err(c.reset_at_ms());
+            }
+            c.count += 1;
+            *c
+        };
+        if let Some(store) = &self.store {
+            store.save(key, &snapshot).await;
+        }
+        Ok(())
+    }
+
+    async fn refund(&self, key: &QuotaKey) {
+        let snapshot = self.counters.get_mut(key).map(|mut c| {
+            c.count = c.count.saturating_sub(1);
+            *c
+        });
+        if let (Some(store), Some(c)) = (&self.store, snapshot) {
+            store.save(key, &c).await;
+        }
+    }
+
+    /// Current counter for `key`, if tracked in memory.
+    pub fn counter(&self, key: &QuotaKey) -> Option<QuotaCounter> {
+        self.counters.get(key).map(|c| *c)
+    }
+
+    /// Drop counters whose window has elapsed at `now_ms`. Returns how many
+    /// were removed.
+    pub fn sweep_expired(&self, now_ms: u64) -> usize {
+        let before = self.counters.len();
+        self.counters.retain(|_, c| !c.is_expired(now_ms));
+        before.saturating_sub(self.counters.len())
+    }
+
+    /// All tracked counters, sorted by tenant, user and pattern.
+    pub fn snapshot(&self) -> Vec<QuotaUsage> {
+        let mut out: Vec<QuotaUsage> = self
+            .counters
+            .iter()
+            .map(|e| QuotaUsage {
+                tenant: e.key().tenant.clone(),
+                user: e.key().user.clone(),
+                pattern: e.key().pattern.clone(),
+                count: e.count,
+                window_start_unix_ms: e.window_start_ms,
+                reset_at_unix_ms: e.reset_at_ms(),
+            })
+            .collect();
+        out.sort_by(|a, b| (&a.tenant, &a.user, &a.pattern).cmp(&(&b.tenant, &b.user, &b.pattern)));
+        out
+    }
+
+    /// Number of counters currently tracked.
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+}
@@ -0,0 +1,233 @@
+//! Lock-free token bucket shared by all policy limiters, plus the keyed
+//! per-room limiter built on it.
+//!
+//! State is packed into a single `AtomicU64`:
+//! - high 32 bits: available tokens
+//! - low 32 bits : last refill time, in milliseconds since the bucket's epoch
+//!
+//! `try_acquire` runs a CAS loop over that word, so concurrent callers never block
+//! and tokens can never go negative (saturation => deny). The millisecond clock
+//! wraps after ~49 days; elapsed time is computed with wrapping arithmetic so
+//! only a bucket idle for longer than that under-refills once.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use wsprism_core::error::{Result, WsPrismError};
+
+#[inline]
+fn pack(tokens: u32, ms: u32) -> u64 {
+    ((tokens as u64) << 32) | ms as u64
+}
+
+#[inline]
+fn unpack(word: u64) -> (u32, u32) {
+    ((word >> 32) as u32, word as u32)
+}
+
+/// Token bucket refilled at `rate` tokens per second up to `capacity`.
+///
+/// Denomination is up to the caller (messages, bytes, ...).
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: u32,
+    capacity: u32,
+    epoch: Instant,
+    state: AtomicU64,
+}
+
+impl TokenBucket {
+    /// Full bucket. `rate` and `capacity` are clamped to at least 1.
+    pub fn new(rate: u32, capacity: u32) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            rate: rate.max(1),
+            capacity,
+            epoch: Instant::now(),
+            state: AtomicU64::new(pack(capacity, 0)),
+        }
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    fn now_ms(&self) -> u32 {
+        self.epoch.elapsed().as_millis() as u32
+    }
+
+    /// Tokens and refill timestamp after refilling up to `now`.
+    ///
+    /// Only whole tokens are credited; the timestamp advances by exactly the
+    /// time those tokens represent, so fractional progress carries over.
+    fn refilled(&self, tokens: u32, last: u32, now: u32) -> (u32, u32) {
+        let elapsed = now.wrapping_sub(last) as u64;
+        let add = elapsed.saturating_mul(self.rate as u64) / 1000;
+        if add == 0 {
+            return (tokens, last);
+        }
+        let tokens = (tokens as u64).saturating_add(add).min(self.capacity as u64) as u32;
+        if tokens == self.capacity {
+            return (tokens, now);
+        }
+        let used_ms = (add * 1000 / self.rate as u64) as u32;
+        (tokens, last.wrapping_add(used_ms))
+    }
+
+    /// Whether the bucket would be at capacity if refilled now.
+    ///
+    /// A full bucket is indistinguishable from a fresh one, so it can be
+    /// evicted from keyed maps without changing behavior.
+    pub fn is_full(&self) -> bool {
+        let (tokens, last) = unpack(self.state.load(Ordering::Acquire));
+        self.refilled(tokens, last, self.now_ms()).0 == self.capacity
+    }
+
+    /// Take one token.
+    pub fn allow(&self) -> bool {
+        self.try_take(1)
+    }
+
+    /// Take `cost` tokens atomically, or none at all.
+    ///
+    /// A cost above `capacity` can never succeed.
+    pub fn try_take(&self, cost: u32) -> bool {
+        self.try_acquire(cost).is_ok()
+    }
+
+    /// `try_take`, with the estimated wait in milliseconds (min 1) until
+    /// `cost` tokens are available when denied.
+    pub fn try_acquire(&self, cost: u32) -> std::result::Result<(), u64> {
+        let mut cur = self.state.load(Ordering::Acquire);
+        loop {
+            // Read the clock *after* the state so `now >= last` always holds.
+            let now = self.now_ms();
+            let (tokens, last) = unpack(cur);
+            let (tokens, last) = self.refilled(tokens, last, now);
+            let next = if tokens >= cost {
+                pack(tokens - cost, last)
+            } else {
+                // Persist the refill only; the request is denied.
+                pack(tokens, last)
+            };
+            match self.state.compare_exchange_weak(cur, next, Ordering::AcqRel, Ordering::Relaxed) {
+                Ok(_) if tokens >= cost => return Ok(()),
+                Ok(_) => return Err(self.wait_ms(cost - tokens, now.wrapping_sub(last))),
+                Err(actual) => cur = actual,
+            }
+        }
+    }
+
+    /// Milliseconds until `missing` more tokens accrue, given `progress_ms`
+    /// already elapsed towards the next one.
+    fn wait_ms(&self, missing: u32, progress_ms: u32) -> u64 {
+        let full = (missing as u64 * 1000).div_ceil(self.rate as u64);
+        full.saturating_sub(progress_ms as u64).max(1)
+    }
+
+    /// Seconds until `cost` tokens are available (min 1). 0 if available now.
+    pub fn retry_after_secs(&self, cost: u32) -> u64 {
+        let (tokens, last) = unpack(self.state.load(Ordering::Acquire));
+        let (tokens, _) = self.refilled(tokens, last, self.now_ms());
+        if tokens >= cost {
+            return 0;
+        }
+        let missing = (cost - tokens) as u64;
+        missing.div_ceil(self.rate as u64).max(1)
+    }
+}
+
+/// Above this many tracked rooms, full (idle) buckets are evicted.
+const MAX_ROOM_BUCKETS: usize = 65_536;
+
+/// Room buckets unused for this long are dropped by `sweep_idle`.
+pub const ROOM_BUCKET_IDLE_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Debug)]
+struct RoomBucket {
+    bucket: TokenBucket,
+    /// Last `check`, in milliseconds since the limiter's epoch.
+    last_used_ms: AtomicU64,
+}
+
+/// Per-room inbound limiter (one bucket per tenant-qualified room key).
+///
+/// Applies to all senders into a room combined, independent of the
+/// per-tenant and per-session inbound limits. Buckets are created lazily and
+/// removed by `sweep_idle` once a room goes quiet.
+#[derive(Debug)]
+pub struct RoomRateLimiter {
+    rps: u32,
+    burst: u32,
+    epoch: Instant,
+    buckets: DashMap<String, RoomBucket>,
+}
+
+impl RoomRateLimiter {
+    pub fn new(rps: u32, burst: u32) -> Self {
+        Self {
+            rps,
+            burst,
+            epoch: Instant::now(),
+            buckets: DashMap::new(),
+        }
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+
+    /// Take one token for `room_key`; `RateLimited` when exhausted.
+    pub fn check(&self, room_key: &str) -> Result<()> {
+        let now = self.now_ms();
+        let take = |b: &RoomBucket| {
+            b.last_used_ms.store(now, Ordering::Relaxed);
+            b.bucket.allow()
+        };
+        let allowed = match self.buckets.get(room_key) {
+            Some(b) => take(&b),
+            None => {
+                if self.buckets.len() >= MAX_ROOM_BUCKETS {
+                    self.buckets.retain(|_, b| !b.bucket.is_full());
+                }
+                let entry = self.buckets.entry(room_key.to_string()).or_insert_with(|| RoomBucket {
+                    bucket: TokenBucket::new(self.rps, self.burst),
+                    last_used_ms: AtomicU64::new(now),
+                });
+                take(&entry)
+            }
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(WsPrismError::RateLimited)
+        }
+    }
+
+    /// Seconds until `room_key` admits another message (0 if untracked).
+    pub fn retry_after_secs(&self, room_key: &str) -> u64 {
+        self.buckets.get(room_key).map_or(0, |b| b.bucket.retry_after_secs(1))
+    }
+
+    /// Drop buckets not used within `idle`. Returns how many were removed.
+    pub fn sweep_idle(&self, idle: Duration) -> usize {
+        let cutoff = self.now_ms().saturating_sub(idle.as_millis() as u64);
+        let before = self.buckets.len();
+        self.buckets.retain(|_, b| b.last_used_ms.load(Ordering::Relaxed) >= cutoff);
+        before.saturating_sub(self.buckets.len())
+    }
+
+    /// Number of rooms currently tracked.
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+}
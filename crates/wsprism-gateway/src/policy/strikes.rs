@@ -0,0 +1,49 @@
+//! Per-session violation strikes.
+//!
+//! Each policy reject is a strike. Strikes older than the window age out;
+//! once more than `limit` are live at the same time, the session is closed.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Sliding-window strike counter owned by one session (no locking).
+#[derive(Debug)]
+pub struct StrikeCounter {
+    limit: u32,
+    window: Duration,
+    hits: VecDeque<Instant>,
+}
+
+impl StrikeCounter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self { limit, window, hits: VecDeque::new() }
+    }
+
+    /// Record a strike at `now`. Returns `true` when the live strike count
+    /// exceeds the limit (time to escalate).
+    pub fn strike(&mut self, now: Instant) -> bool {
+        self.expire(now);
+        // Keep at most limit + 1 entries: enough to decide, bounded memory.
+        if self.hits.len() > self.limit as usize {
+            self.hits.pop_front();
+        }
+        self.hits.push_back(now);
+        self.hits.len() > self.limit as usize
+    }
+
+    /// Strikes still inside the window at `now`.
+    pub fn live(&mut self, now: Instant) -> usize {
+        self.expire(now);
+        self.hits.len()
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some(&t) = self.hits.front() {
+            if now.saturating_duration_since(t) >= self.window {
+                self.hits.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
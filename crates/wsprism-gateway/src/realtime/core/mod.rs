@@ -1,12 +1,28 @@
 //! Realtime core components for Gateway runtime.
 //!
-//! Session registry, presence tracking, and the egress runtime/context shared
-//! across services.
+//! Session registry, presence tracking, per-room replay buffers, and the
+//! egress runtime/context shared across services.
 
 mod presence;
+mod presence_notify;
 mod realtime;
+mod remote_presence;
+mod replay;
+mod session_events;
+mod session_handle;
 mod session_registry;
 
-pub use presence::Presence;
-pub use realtime::{egress_drop_count, egress_send_fail_count, RealtimeCore, RealtimeCtx};
-pub use session_registry::{Connection, SessionRegistry};
+pub use presence::{
+    MembershipObserver, Presence, PresenceEvent, RoomEvent, RoomSize, UserMembershipObserver,
+    DEFAULT_ROOM_EVENT_CAPACITY,
+};
+pub use presence_notify::{PresenceNotifier, DEFAULT_PRESENCE_COALESCE_WINDOW};
+pub use realtime::{
+    egress_drop_count, egress_send_fail_count, PublishReport, RealtimeCore, RealtimeCtx, SessionDelivery,
+    DEFAULT_FANOUT_LIMIT,
+};
+pub use remote_presence::RemotePresence;
+pub use replay::{MessageRingBuffer, DEFAULT_REPLAY_CAPACITY};
+pub use session_events::{LoggedEvent, SessionEvent, SessionEventLog, DEFAULT_MAX_SESSION_EVENTS};
+pub use session_handle::SessionHandle;
+pub use session_registry::{Connection, ConnectionSnapshot, OutboundDrops, OutboundSample, SessionRegistry};
@@ -1,18 +1,74 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap};
+use std::sync::{Arc, RwLock};
+
+use dashmap::mapref::entry::Entry;
 use dashmap::{DashMap, DashSet};
-use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast;
 use wsprism_core::error::{Result, WsPrismError};
 use crate::config::schema::TenantLimits;
+use crate::context::ConnectionId;
+use super::remote_presence::RemotePresence;
+
+/// Membership change reported by `Presence` operations (keys are
+/// tenant-qualified).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresenceEvent {
+    UserMoved { user: String, from: String, to: String },
+}
 
-/// Room presence: `room_key -> sessions`, `session_key -> rooms`.
+/// Room membership change, sent to [`Presence::subscribe_room_events`]
+/// receivers. Carries the tenant-qualified user key; a user counts as joined
+/// from their first session in the room until their last one leaves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoomEvent {
+    Joined(String),
+    Left(String),
+}
+
+/// Told about every session entering or leaving a room, e.g. to notify a
+/// backend. Keys are tenant-qualified. Called inline from `Presence`
+/// operations, so implementations must not block.
+pub trait MembershipObserver: Send + Sync {
+    fn joined(&self, tenant_id: &str, room_key: &str, user_key: &str, conn_id: ConnectionId);
+    fn left(&self, tenant_id: &str, room_key: &str, user_key: &str, conn_id: ConnectionId);
+}
+
+/// Told when a user enters a room on this replica (first session) or
+/// leaves it (last session), e.g. to gossip membership to other replicas.
+/// Keys are tenant-qualified. Called inline from `Presence` operations, so
+/// implementations must not block.
+pub trait UserMembershipObserver: Send + Sync {
+    fn user_joined(&self, room_key: &str, user_key: &str);
+    fn user_left(&self, room_key: &str, user_key: &str);
+}
+
+/// Default buffered events per room subscription
+/// (`gateway.room_event_capacity`).
+pub const DEFAULT_ROOM_EVENT_CAPACITY: usize = 64;
+
+/// Occupancy of one room, as reported by [`Presence::top_rooms`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RoomSize {
+    /// Tenant-qualified room key.
+    pub room: String,
+    pub sessions: usize,
+    pub users: usize,
+}
+
+/// Room presence: `room_key -> connections`, `ConnectionId -> rooms`.
+///
+/// Routing is per connection; governance (room/user limits) stays per user.
 ///
 /// Sprint 5: Added user-level indexing and tenant counters for governance.
 /// Lock-free best-effort design: under heavy contention, limits can be
-/// temporarily exceeded by a small margin to preserve throughput.
-#[derive(Default)]
+/// temporarily exceeded by a small margin to preserve throughput. The
+/// exception is room capacity (`room_capacity`), which is checked while
+/// holding the room's metadata entry.
 pub struct Presence {
     // Routing indices
-    room_to_sessions: DashMap<String, DashSet<String>>,
-    session_to_rooms: DashMap<String, DashSet<String>>,
+    room_to_sessions: DashMap<String, DashSet<ConnectionId>>,
+    session_to_rooms: DashMap<ConnectionId, DashSet<String>>,
 
     // Governance indices
     room_to_users: DashMap<String, DashSet<String>>,
@@ -21,8 +77,54 @@ pub struct Presence {
     // Multi-session ref-counting: user::room -> session_count
     user_room_refs: DashMap<String, usize>,
 
-    // O(1) Counters
-    tenant_room_counts: DashMap<String, AtomicU64>,
+    // Active rooms per tenant (len() is the tenant room count)
+    tenant_rooms: DashMap<String, DashSet<String>>,
+
+    // Membership event channels, only for rooms someone subscribed to
+    room_events: DashMap<String, broadcast::Sender<RoomEvent>>,
+    event_capacity: usize,
+
+    // Per-session membership observer
+    observer: RwLock<Option<Arc<dyn MembershipObserver>>>,
+
+    // Per-user membership observer (cluster gossip)
+    user_observer: RwLock<Option<Arc<dyn UserMembershipObserver>>>,
+
+    // Per-user observer announcing joins and leaves to room members
+    notifier: RwLock<Option<Arc<dyn UserMembershipObserver>>>,
+
+    // Per-room metadata, from the first join until the last user leaves
+    room_meta: DashMap<String, RoomMeta>,
+
+    // Members connected to other replicas
+    remote: RemotePresence,
+}
+
+/// Settings fixed by a room's first joiner.
+///
+/// Joins hold the room's entry while checking capacity and adding the
+/// member, so concurrent joins of one room are serialized.
+#[derive(Debug, Default)]
+struct RoomMeta {
+    /// `room:join` capacity override (`None` = the tenant's `room_capacity`).
+    capacity: Option<u64>,
+}
+
+impl RoomMeta {
+    /// The override, capped by the tenant limit (0 = unlimited).
+    fn capacity(&self, limits: &TenantLimits) -> u64 {
+        match (self.capacity, limits.room_capacity) {
+            (Some(c), 0) => c,
+            (Some(c), t) => c.min(t),
+            (None, t) => t,
+        }
+    }
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Presence {
@@ -33,7 +135,73 @@ impl Presence {
             room_to_users: DashMap::new(),
             user_to_rooms: DashMap::new(),
             user_room_refs: DashMap::new(),
-            tenant_room_counts: DashMap::new(),
+            tenant_rooms: DashMap::new(),
+            room_events: DashMap::new(),
+            event_capacity: DEFAULT_ROOM_EVENT_CAPACITY,
+            observer: RwLock::new(None),
+            user_observer: RwLock::new(None),
+            notifier: RwLock::new(None),
+            room_meta: DashMap::new(),
+            remote: RemotePresence::new(),
+        }
+    }
+
+    /// Events buffered per room subscription before slow receivers lag
+    /// (0 is treated as 1).
+    pub fn with_room_event_capacity(mut self, n: usize) -> Self {
+        self.event_capacity = n.max(1);
+        self
+    }
+
+    /// Receive `Joined`/`Left` events for `room_key`. The channel closes
+    /// when the room's last user leaves; subscribe again after the next
+    /// join. A receiver more than `gateway.room_event_capacity` events
+    /// behind gets `RecvError::Lagged`.
+    pub fn subscribe_room_events(&self, room_key: &str) -> broadcast::Receiver<RoomEvent> {
+        self.room_events
+            .entry(room_key.to_string())
+            .or_insert_with(|| broadcast::channel(self.event_capacity).0)
+            .subscribe()
+    }
+
+    /// Install (or with `None`, remove) the membership observer. Unlike room
+    /// events, it sees every session, not just a user's first and last.
+    pub fn set_observer(&self, observer: Option<Arc<dyn MembershipObserver>>) {
+        *self.observer.write().unwrap_or_else(|e| e.into_inner()) = observer;
+    }
+
+    fn observer(&self) -> Option<Arc<dyn MembershipObserver>> {
+        self.observer.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Install (or with `None`, remove) the per-user membership observer.
+    pub fn set_user_observer(&self, observer: Option<Arc<dyn UserMembershipObserver>>) {
+        *self.user_observer.write().unwrap_or_else(|e| e.into_inner()) = observer;
+    }
+
+    fn user_observer(&self) -> Option<Arc<dyn UserMembershipObserver>> {
+        self.user_observer.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Install (or with `None`, remove) the observer that tells room members
+    /// about joins and leaves (`PresenceNotifier`). Separate from the
+    /// per-user observer, which the cluster bridge owns.
+    pub fn set_notifier(&self, notifier: Option<Arc<dyn UserMembershipObserver>>) {
+        *self.notifier.write().unwrap_or_else(|e| e.into_inner()) = notifier;
+    }
+
+    fn notifier(&self) -> Option<Arc<dyn UserMembershipObserver>> {
+        self.notifier.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Members on other replicas, fed by the cluster bridge.
+    pub fn remote(&self) -> &RemotePresence {
+        &self.remote
+    }
+
+    fn send_room_event(&self, room_key: &str, event: RoomEvent) {
+        if let Some(tx) = self.room_events.get(room_key) {
+            let _ = tx.send(event);
         }
     }
 
@@ -50,50 +218,186 @@ impl Presence {
         tenant_id: &str,
         room_key: &str,
         user_key: &str,
-        session_key: &str,
+        conn_id: ConnectionId,
         limits: &TenantLimits
     ) -> Result<()> {
-        
-        // --- 1. Check Room Capacity (Max Users per Room) ---
-        if limits.max_users_per_room > 0 {
-            if let Some(users) = self.room_to_users.get(room_key) {
-                // If user is not already in room, check limit
-                if users.len() as u64 >= limits.max_users_per_room && !users.contains(user_key) {
-                     return Err(WsPrismError::ResourceExhausted("room user limit reached".into()));
-                }
-            }
+        self.try_join_with_capacity(tenant_id, room_key, user_key, conn_id, limits, None)
+    }
+
+    /// [`try_join`](Self::try_join) that sets the room's capacity when this
+    /// join creates the room. The capacity of an existing room is kept. A
+    /// join over capacity fails with `RoomFull` and changes nothing.
+    pub fn try_join_with_capacity(
+        &self,
+        tenant_id: &str,
+        room_key: &str,
+        user_key: &str,
+        conn_id: ConnectionId,
+        limits: &TenantLimits,
+        capacity: Option<u64>,
+    ) -> Result<()> {
+        // Rejoining a room this connection is already in is a no-op.
+        let Some(session_rooms) = self.rooms_before_join(conn_id, room_key) else { return Ok(()) };
+
+        // --- 0. Check Session's Room Limit (Max Rooms per Session) ---
+        if limits.max_rooms_per_session > 0 && session_rooms >= limits.max_rooms_per_session {
+            return Err(WsPrismError::NotAllowed(format!(
+                "session room limit reached (max_rooms_per_session={})",
+                limits.max_rooms_per_session
+            )));
         }
 
-        // --- 2. Check User's Room Limit (Max Rooms per User) ---
-        if limits.max_rooms_per_user > 0 {
-            if let Some(rooms) = self.user_to_rooms.get(user_key) {
-                if rooms.len() as u64 >= limits.max_rooms_per_user && !rooms.contains(room_key) {
-                    return Err(WsPrismError::ResourceExhausted("user room limit reached".into()));
-                }
+        // --- 1. Check User's Room Limit (Max Rooms per User) ---
+        if limits.max_rooms_per_user > 0
+            && self.user_room_count(user_key) as u64 >= limits.max_rooms_per_user
+            && !self.is_user_in(user_key, room_key)
+        {
+            return Err(WsPrismError::ResourceExhausted("user room limit reached".into()));
+        }
+
+        // --- 2. Room capacity and tenant room limit ---
+        let meta = self.lock_room_meta(room_key, capacity);
+        if let Err(e) = self.check_capacity(&meta, room_key, user_key, limits)
+            .and_then(|()| self.check_room_admission(tenant_id, room_key, user_key, limits))
+        {
+            drop(meta);
+            self.drop_room_meta_if_empty(room_key);
+            return Err(e);
+        }
+
+        // --- 3. Perform Join ---
+        self.add_member(tenant_id, room_key, user_key, conn_id);
+        // Never held while locking `session_to_rooms`: `move_user` locks
+        // them the other way round.
+        drop(meta);
+        self.session_to_rooms.entry(conn_id).or_default().insert(room_key.to_string());
+        Ok(())
+    }
+
+    /// The room's metadata entry, created with `capacity` if the room has
+    /// none yet. Holding it serializes joins of the room.
+    fn lock_room_meta(&self, room_key: &str, capacity: Option<u64>) -> dashmap::mapref::one::RefMut<'_, String, RoomMeta> {
+        match self.room_meta.entry(room_key.to_string()) {
+            Entry::Occupied(o) => o.into_ref(),
+            Entry::Vacant(v) => v.insert(RoomMeta { capacity }),
+        }
+    }
+
+    /// Remove the room's metadata once it has no users on this replica.
+    fn drop_room_meta_if_empty(&self, room_key: &str) {
+        self.room_meta.remove_if(room_key, |_, _| !self.room_to_users.contains_key(room_key));
+    }
+
+    /// `RoomFull` if a user not yet in the room would exceed its capacity
+    /// (users on this replica).
+    fn check_capacity(&self, meta: &RoomMeta, room_key: &str, user_key: &str, limits: &TenantLimits) -> Result<()> {
+        let capacity = meta.capacity(limits);
+        if capacity == 0 {
+            return Ok(());
+        }
+        let users = self.room_to_users.get(room_key);
+        let already_in = users.as_ref().is_some_and(|u| u.contains(user_key));
+        if !already_in && users.as_ref().map_or(0, |u| u.len()) as u64 >= capacity {
+            return Err(WsPrismError::RoomFull(format!("room is full (capacity={capacity})")));
+        }
+        Ok(())
+    }
+
+    /// Capacity set by the room's first joiner, if any.
+    pub fn room_capacity_override(&self, room_key: &str) -> Option<u64> {
+        self.room_meta.get(room_key).and_then(|m| m.capacity)
+    }
+
+    /// Move a connection from `from_key` to `to_key` in one step.
+    ///
+    /// The connection's room set stays locked for the whole move, so
+    /// concurrent moves of the same connection serialize. The connection joins
+    /// `to_key` before leaving `from_key`, so a broadcast to either room
+    /// always finds it in at least one of them. Room capacity and the tenant
+    /// room limit apply to `to_key`. The per-session and per-user room counts
+    /// do not grow.
+    pub fn move_user(
+        &self,
+        tenant_id: &str,
+        user_key: &str,
+        conn_id: ConnectionId,
+        from_key: &str,
+        to_key: &str,
+        limits: &TenantLimits,
+    ) -> Result<PresenceEvent> {
+        let rooms = self.session_to_rooms.entry(conn_id).or_default();
+        if !rooms.contains(from_key) {
+            return Err(WsPrismError::BadRequest(format!("not in room: {from_key}")));
+        }
+        if rooms.contains(to_key) {
+            return Err(WsPrismError::BadRequest(format!("already in room: {to_key}")));
+        }
+        let meta = self.lock_room_meta(to_key, None);
+        if let Err(e) = self.check_capacity(&meta, to_key, user_key, limits)
+            .and_then(|()| self.check_room_admission(tenant_id, to_key, user_key, limits))
+        {
+            drop(meta);
+            self.drop_room_meta_if_empty(to_key);
+            return Err(e);
+        }
+
+        self.add_member(tenant_id, to_key, user_key, conn_id);
+        // Released before leaving `from_key`, whose entry may share the shard.
+        drop(meta);
+        rooms.insert(to_key.to_string());
+        rooms.remove(from_key);
+        self.remove_member(tenant_id, from_key, user_key, conn_id);
+
+        Ok(PresenceEvent::UserMoved {
+            user: user_key.to_string(),
+            from: from_key.to_string(),
+            to: to_key.to_string(),
+        })
+    }
+
+    /// Room capacity and tenant room limit checks for entering `room_key`.
+    fn check_room_admission(
+        &self,
+        tenant_id: &str,
+        room_key: &str,
+        user_key: &str,
+        limits: &TenantLimits,
+    ) -> Result<()> {
+        // Room capacity (max users per room, counted across replicas)
+        if limits.max_users_per_room > 0 {
+            let remote = self.remote.users_in(room_key);
+            let local = self.room_to_users.get(room_key);
+            let already_in = remote.contains(user_key) || local.as_ref().is_some_and(|u| u.contains(user_key));
+            if !already_in && count_members(local.as_deref(), &remote) as u64 >= limits.max_users_per_room {
+                return Err(WsPrismError::ResourceExhausted("room user limit reached".into()));
             }
         }
 
-        // --- 3. Check Tenant Total Rooms ---
-        // We only increment if the room is NEW (currently has no sessions).
-        // Note: This is a slight approximation. A room exists if it has sessions.
+        // Tenant total rooms.
+        // Only a NEW room (currently no sessions) counts against the limit.
         let is_new_room = !self.room_to_sessions.contains_key(room_key);
-        if is_new_room && limits.max_rooms_total > 0 {
-            let counter = self.tenant_room_counts.entry(tenant_id.to_string()).or_insert_with(|| AtomicU64::new(0));
-            if counter.load(Ordering::Relaxed) >= limits.max_rooms_total {
-                return Err(WsPrismError::ResourceExhausted("tenant room limit reached".into()));
-            }
-            // Increment
-            counter.fetch_add(1, Ordering::Relaxed);
-        } else if is_new_room {
-             self.tenant_room_counts.entry(tenant_id.to_string()).or_default().fetch_add(1, Ordering::Relaxed);
+        if is_new_room && limits.max_rooms_total > 0 && self.tenant_room_count(tenant_id) >= limits.max_rooms_total {
+            return Err(WsPrismError::NotAllowed(format!(
+                "tenant room limit reached (max_rooms_total={})",
+                limits.max_rooms_total
+            )));
         }
+        Ok(())
+    }
+
+    /// Add `conn_id` to the room's routing, governance and tenant indices
+    /// (everything except `session_to_rooms`). Order: Routing -> Governance.
+    fn add_member(&self, tenant_id: &str, room_key: &str, user_key: &str, conn_id: ConnectionId) {
+        self.tenant_rooms.entry(tenant_id.to_string()).or_default().insert(room_key.to_string());
 
-        // --- 4. Perform Join (Order: Routing -> Governance) ---
-        
         // A. Routing
-        self.room_to_sessions.entry(room_key.to_string()).or_default().insert(session_key.to_string());
-        self.session_to_rooms.entry(session_key.to_string()).or_default().insert(room_key.to_string());
-        
+        let added = self.room_to_sessions.entry(room_key.to_string()).or_default().insert(conn_id);
+        if added {
+            if let Some(o) = self.observer() {
+                o.joined(tenant_id, room_key, user_key, conn_id);
+            }
+        }
+
         // B. Governance (Ref counting for multi-session support)
         let ref_key = format!("{}::{}", user_key, room_key);
         let mut refs = self.user_room_refs.entry(ref_key).or_insert(0);
@@ -101,26 +405,42 @@ impl Presence {
         
         // If this is the first session for this user in this room, add to user indices
         if *refs == 1 {
+            drop(refs);
             self.room_to_users.entry(room_key.to_string()).or_default().insert(user_key.to_string());
             self.user_to_rooms.entry(user_key.to_string()).or_default().insert(room_key.to_string());
+            self.send_room_event(room_key, RoomEvent::Joined(user_key.to_string()));
+            if let Some(o) = self.user_observer() {
+                o.user_joined(room_key, user_key);
+            }
+            if let Some(n) = self.notifier() {
+                n.user_joined(room_key, user_key);
+            }
         }
+    }
 
-        Ok(())
+    pub fn leave(&self, tenant_id: &str, room_key: &str, user_key: &str, conn_id: ConnectionId) {
+        if let Some(set) = self.session_to_rooms.get(&conn_id) {
+            set.remove(room_key);
+            if set.is_empty() { drop(set); self.session_to_rooms.remove(&conn_id); }
+        }
+        self.remove_member(tenant_id, room_key, user_key, conn_id);
     }
 
-    pub fn leave(&self, tenant_id: &str, room_key: &str, user_key: &str, session_key: &str) {
+    /// Inverse of `add_member`.
+    fn remove_member(&self, tenant_id: &str, room_key: &str, user_key: &str, conn_id: ConnectionId) {
         // 1. Remove from routing
         let mut room_empty = false;
+        let mut removed = false;
         if let Some(set) = self.room_to_sessions.get(room_key) {
-            set.remove(session_key);
+            removed = set.remove(&conn_id).is_some();
             room_empty = set.is_empty();
         }
         // Cleanup empty routing set outside lock
         if room_empty { self.room_to_sessions.remove(room_key); }
-
-        if let Some(set) = self.session_to_rooms.get(session_key) {
-            set.remove(room_key);
-            if set.is_empty() { drop(set); self.session_to_rooms.remove(session_key); }
+        if removed {
+            if let Some(o) = self.observer() {
+                o.left(tenant_id, room_key, user_key, conn_id);
+            }
         }
 
         // 2. Remove from governance (Ref counting)
@@ -143,34 +463,196 @@ impl Presence {
             }
 
             // Remove from room_to_users
+            let mut last_user = false;
             if let Some(set) = self.room_to_users.get(room_key) {
                 set.remove(user_key);
-                if set.is_empty() { drop(set); self.room_to_users.remove(room_key); }
+                if set.is_empty() { drop(set); self.room_to_users.remove(room_key); last_user = true; }
+            }
+
+            // Tell subscribers; the last user leaving closes their channel
+            self.send_room_event(room_key, RoomEvent::Left(user_key.to_string()));
+            if last_user {
+                self.room_events.remove(room_key);
+                self.drop_room_meta_if_empty(room_key);
+            }
+            if let Some(o) = self.user_observer() {
+                o.user_left(room_key, user_key);
+            }
+            if let Some(n) = self.notifier() {
+                n.user_left(room_key, user_key);
             }
         }
         
-        // 3. Decrement Tenant Room Count if room empty
+        // 3. Drop the room from the tenant index once empty
         if room_empty {
-             if let Some(counter) = self.tenant_room_counts.get(tenant_id) {
-                 counter.fetch_sub(1, Ordering::Relaxed);
-             }
+            if let Some(set) = self.tenant_rooms.get(tenant_id) {
+                set.remove(room_key);
+                if set.is_empty() { drop(set); self.tenant_rooms.remove(tenant_id); }
+            }
+        }
+    }
+
+    /// Rooms the connection is in, or `None` if it is already in `room_key`.
+    fn rooms_before_join(&self, conn_id: ConnectionId, room_key: &str) -> Option<u64> {
+        match self.session_to_rooms.get(&conn_id) {
+            Some(rooms) if rooms.contains(room_key) => None,
+            Some(rooms) => Some(rooms.len() as u64),
+            None => Some(0),
         }
     }
 
-    pub fn sessions_in(&self, room_key: &str) -> Vec<String> {
+    /// Number of active rooms (with at least one session) in the tenant.
+    pub fn tenant_room_count(&self, tenant_id: &str) -> u64 {
+        self.tenant_rooms.get(tenant_id).map_or(0, |set| set.len() as u64)
+    }
+
+    /// Number of active rooms across all tenants.
+    pub fn room_count(&self) -> usize {
+        self.room_to_sessions.len()
+    }
+
+    /// Call `f` with the key and session count of every room, optionally
+    /// limited to one tenant. Keys are borrowed, so nothing is cloned; `f`
+    /// runs under presence shard read locks and must not join or leave.
+    pub fn room_sizes(&self, tenant_id: Option<&str>, mut f: impl FnMut(&str, usize)) {
+        match tenant_id {
+            Some(t) => {
+                if let Some(rooms) = self.tenant_rooms.get(t) {
+                    for r in rooms.iter() {
+                        f(r.key(), self.room_to_sessions.get(r.key()).map_or(0, |s| s.len()));
+                    }
+                }
+            }
+            None => {
+                for r in self.room_to_sessions.iter() {
+                    f(r.key(), r.value().len());
+                }
+            }
+        }
+    }
+
+    /// The `n` rooms with the most sessions (ties by key), largest first,
+    /// optionally limited to one tenant. Keeps at most `n` rooms while
+    /// walking the map.
+    pub fn top_rooms(&self, tenant_id: Option<&str>, n: usize) -> Vec<RoomSize> {
+        let mut heap: BinaryHeap<Reverse<(usize, Reverse<String>)>> = BinaryHeap::with_capacity(n + 1);
+        self.room_sizes(tenant_id, |room, sessions| {
+            if n == 0 {
+                return;
+            }
+            if heap.len() == n {
+                let Some(Reverse((min, Reverse(min_key)))) = heap.peek() else { return };
+                if (sessions, Reverse(room)) <= (*min, Reverse(min_key.as_str())) {
+                    return;
+                }
+                heap.pop();
+            }
+            heap.push(Reverse((sessions, Reverse(room.to_string()))));
+        });
+        let mut top: Vec<(usize, String)> = heap.into_iter().map(|Reverse((c, Reverse(k)))| (c, k)).collect();
+        top.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        top.into_iter()
+            .map(|(sessions, room)| {
+                let users = self.room_to_users.get(&room).map_or(0, |u| u.len());
+                RoomSize { room, sessions, users }
+            })
+            .collect()
+    }
+
+    /// Number of rooms the connection is currently in.
+    pub fn session_room_count(&self, conn_id: ConnectionId) -> u64 {
+        self.session_to_rooms.get(&conn_id).map_or(0, |set| set.len() as u64)
+    }
+
+    /// Room keys the connection is currently in.
+    pub fn rooms_of_session(&self, conn_id: ConnectionId) -> Vec<String> {
+        self.session_to_rooms.get(&conn_id)
+            .map(|set| set.iter().map(|r| r.key().to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn sessions_in(&self, room_key: &str) -> Vec<ConnectionId> {
         self.room_to_sessions.get(room_key)
+            .map(|set| set.iter().map(|c| *c.key()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Users with a session in the room on this replica, sorted.
+    pub fn users_in(&self, room_key: &str) -> Vec<String> {
+        let mut users: Vec<String> = self.room_to_users.get(room_key)
             .map(|set| set.iter().map(|u| u.key().to_string()).collect())
+            .unwrap_or_default();
+        users.sort_unstable();
+        users
+    }
+
+    /// [`users_in`](Self::users_in) plus the room's members on other
+    /// replicas; a user connected to several counts once.
+    pub fn users_in_global(&self, room_key: &str) -> Vec<String> {
+        let mut users = self.remote.users_in(room_key);
+        if let Some(set) = self.room_to_users.get(room_key) {
+            users.extend(set.iter().map(|u| u.key().to_string()));
+        }
+        users.into_iter().collect()
+    }
+
+    /// Number of [`users_in_global`](Self::users_in_global).
+    pub fn global_count(&self, room_key: &str) -> usize {
+        let remote = self.remote.users_in(room_key);
+        count_members(self.room_to_users.get(room_key).as_deref(), &remote)
+    }
+
+    /// Call `f` with every room of `tenant_id` and its users on this
+    /// replica. The users are copied, so `f` may join or leave.
+    pub fn local_members(&self, tenant_id: &str, mut f: impl FnMut(&str, Vec<String>)) {
+        let rooms: Vec<String> = self.tenant_rooms.get(tenant_id)
+            .map(|set| set.iter().map(|r| r.key().to_string()).collect())
+            .unwrap_or_default();
+        for room in rooms {
+            let users = self.users_in(&room);
+            if !users.is_empty() {
+                f(&room, users);
+            }
+        }
+    }
+
+    /// Room keys the user currently belongs to (across all of their sessions).
+    pub fn rooms_of(&self, user_key: &str) -> Vec<String> {
+        self.user_to_rooms.get(user_key)
+            .map(|set| set.iter().map(|r| r.key().to_string()).collect())
             .unwrap_or_default()
     }
 
+    /// Number of rooms the user is in, without copying the room set.
+    pub fn user_room_count(&self, user_key: &str) -> usize {
+        self.user_to_rooms.get(user_key).map_or(0, |set| set.len())
+    }
+
+    /// Whether the user has at least one session in the room.
+    pub fn is_user_in(&self, user_key: &str, room_key: &str) -> bool {
+        self.user_to_rooms.get(user_key)
+            .map(|set| set.contains(room_key))
+            .unwrap_or(false)
+    }
+
     // Called by RAII Drop
-    pub fn cleanup_session(&self, tenant_id: &str, user_key: &str, session_key: &str) {
-        if let Some(rooms) = self.session_to_rooms.remove(session_key).map(|(_, v)| v) {
+    pub fn cleanup_session(&self, tenant_id: &str, user_key: &str, conn_id: ConnectionId) {
+        if let Some(rooms) = self.session_to_rooms.remove(&conn_id).map(|(_, v)| v) {
             for r in rooms.iter() {
                 let room_key = r.key();
                 // Use the full leave logic to ensure ref-counts and limits are updated correctly
-                self.leave(tenant_id, room_key, user_key, session_key);
+                self.leave(tenant_id, room_key, user_key, conn_id);
             }
         }
     }
 }
+
+/// Local users not in `remote`, plus `remote`.
+fn count_members(local: Option<&DashSet<String>>, remote: &BTreeSet<String>) -> usize {
+    let local_only = match local {
+        Some(users) if remote.is_empty() => users.len(),
+        Some(users) => users.iter().filter(|u| !remote.contains(u.key())).count(),
+        None => 0,
+    };
+    local_only + remote.len()
+}
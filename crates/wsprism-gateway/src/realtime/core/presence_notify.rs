@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use serde_json::json;
+
+use super::presence::UserMembershipObserver;
+use super::realtime::RealtimeCore;
+use crate::realtime::types::{Outgoing, Payload, Priority, QoS};
+
+/// Default time a leave waits for the same user to rejoin before it is
+/// announced.
+pub const DEFAULT_PRESENCE_COALESCE_WINDOW: Duration = Duration::from_millis(500);
+
+/// Sends `sys.presence` to a room's members when a user joins it (first
+/// session) or leaves it (last session), for tenants with
+/// `presence_events`.
+///
+/// Leaves are held for the coalesce window; a rejoin within it cancels the
+/// leave and is not announced either, so a flapping user produces at most
+/// one join and one leave. The subject of an event is not sent it. Events
+/// are Lossy room messages, relayed to other replicas like any other.
+pub struct PresenceNotifier {
+    core: Weak<RealtimeCore>,
+    tenants: HashSet<String>,
+    window: Duration,
+    /// Leaves not announced yet, by `user_key::room_key`, with the
+    /// generation that may announce them.
+    pending_leaves: Arc<DashMap<String, u64>>,
+    next_gen: AtomicU64,
+}
+
+impl PresenceNotifier {
+    /// Notifier for `tenants`, publishing through `core`.
+    pub fn new(core: Weak<RealtimeCore>, tenants: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            core,
+            tenants: tenants.into_iter().collect(),
+            window: DEFAULT_PRESENCE_COALESCE_WINDOW,
+            pending_leaves: Arc::new(DashMap::new()),
+            next_gen: AtomicU64::new(0),
+        }
+    }
+
+    /// Coalesce window (zero announces leaves immediately).
+    pub fn with_coalesce_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    fn enabled(&self, room_key: &str) -> bool {
+        room_key.split_once("::").is_some_and(|(tenant, _)| self.tenants.contains(tenant))
+    }
+}
+
+/// Publish one `sys.presence` event to `room_key`, skipping `user_key`.
+fn announce(core: &RealtimeCore, room_key: &str, user_key: &str, event: &str) {
+    let room = room_key.split_once("::").map_or(room_key, |(_, r)| r);
+    let user = user_key.split_once("::").map_or(user_key, |(_, u)| u);
+    let frame = json!({
+        "v": 1,
+        "svc": "sys",
+        "type": "presence",
+        "room": room,
+        "data": { "event": event, "user": user, "member_count": core.presence.global_count(room_key) },
+    });
+    let out = Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(frame), priority: Priority::Normal };
+    if let Err(e) = core.publish_room_lossy_excluding(room_key, out, Some(user_key)) {
+        tracing::debug!(room_key, error = %e, "presence event not published");
+    }
+}
+
+impl UserMembershipObserver for PresenceNotifier {
+    fn user_joined(&self, room_key: &str, user_key: &str) {
+        if !self.enabled(room_key) {
+            return;
+        }
+        // Back before the leave was announced: members never saw it go.
+        if self.pending_leaves.remove(&format!("{user_key}::{room_key}")).is_some() {
+            return;
+        }
+        if let Some(core) = self.core.upgrade() {
+            announce(&core, room_key, user_key, "join");
+        }
+    }
+
+    fn user_left(&self, room_key: &str, user_key: &str) {
+        if !self.enabled(room_key) {
+            return;
+        }
+        let Some(core) = self.core.upgrade() else { return };
+        // Outside a runtime there is nothing to wait on.
+        let runtime = match tokio::runtime::Handle::try_current() {
+            Ok(h) if !self.window.is_zero() => h,
+            _ => return announce(&core, room_key, user_key, "leave"),
+        };
+        let key = format!("{user_key}::{room_key}");
+        let generation = self.next_gen.fetch_add(1, Ordering::Relaxed);
+        self.pending_leaves.insert(key.clone(), generation);
+        let (pending, core, window) = (Arc::clone(&self.pending_leaves), Arc::downgrade(&core), self.window);
+        let (room_key, user_key) = (room_key.to_string(), user_key.to_string());
+        runtime.spawn(async move {
+            tokio::time::sleep(window).await;
+            if pending.remove_if(&key, |_, g| *g == generation).is_some() {
+                if let Some(core) = core.upgrade() {
+                    announce(&core, &room_key, &user_key, "leave");
+                }
+            }
+        });
+    }
+}
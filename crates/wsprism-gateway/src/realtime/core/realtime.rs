@@ -7,15 +7,26 @@
 
 use std::borrow::Cow;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use axum::extract::ws::{CloseFrame, Message};
-use futures_util::stream::FuturesUnordered;
+use futures_util::stream::{self, FuturesUnordered};
 use futures_util::StreamExt;
-use tokio::time::{timeout, Duration};
+use tokio::time::{timeout_at, Duration, Instant};
 use wsprism_core::error::{Result, WsPrismError};
-use crate::realtime::core::{Presence, SessionRegistry};
-use crate::realtime::types::{Outgoing, PreparedMsg, QoS};
+use wsprism_core::protocol::text::sys_frame;
+use dashmap::DashMap;
+use crate::realtime::core::{
+    Connection, MessageRingBuffer, Presence, PresenceEvent, RoomEvent, SessionEvent, SessionHandle,
+    SessionRegistry, DEFAULT_REPLAY_CAPACITY,
+};
+use crate::realtime::dead_letter::DeadLetterHandler;
+use crate::realtime::relay::RoomRelay;
+use crate::realtime::types::{Outgoing, Payload, PreparedMsg, Priority, QoS};
 use crate::config::schema::TenantLimits;
+use crate::context::{ConnectionId, SessionClaims};
+use crate::obs::metrics::GatewayMetrics;
+use crate::policy::rate::RoomRateLimiter;
+use crate::transport::bandwidth::BandwidthMeter;
 
 static DROP_COUNT: AtomicU64 = AtomicU64::new(0);
 static SEND_FAIL_COUNT: AtomicU64 = AtomicU64::new(0);
@@ -24,9 +35,49 @@ pub fn egress_drop_count() -> u64 { DROP_COUNT.load(Ordering::Relaxed) }
 pub fn egress_send_fail_count() -> u64 { SEND_FAIL_COUNT.load(Ordering::Relaxed) }
 fn sample_every_1024(n: u64) -> bool { (n & 1023) == 1 }
 
+/// Outcome of a counted room publish.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct PublishReport {
+    /// Sessions the message was sent to.
+    pub targets: usize,
+    /// Of those, sends dropped (lossy, queue full) or failed (reliable).
+    pub dropped: usize,
+}
+
+/// Outcome of a counted send to one of a user's sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct SessionDelivery {
+    pub connection_id: ConnectionId,
+    /// Queued for the session; `false` if dropped (lossy) or failed (reliable).
+    pub delivered: bool,
+}
+
+/// Why a reliable send did not reach the session's queue.
+enum SendFailure {
+    Timeout,
+    Closed,
+}
+
 pub struct RealtimeCore {
     pub sessions: Arc<SessionRegistry>,
     pub presence: Arc<Presence>,
+    dead_letter: Option<Arc<dyn DeadLetterHandler>>,
+    relay: Option<Arc<dyn RoomRelay>>,
+    metrics: Option<Arc<GatewayMetrics>>,
+    fanout_limit: usize,
+    /// Recent messages per room key; only rooms someone joined with replay
+    /// enabled have a buffer.
+    replay: DashMap<String, Mutex<MessageRingBuffer>>,
+    replay_capacity: usize,
+}
+
+/// Default max concurrent recipients of one reliable room publish.
+pub const DEFAULT_FANOUT_LIMIT: usize = 256;
+
+impl Default for RealtimeCore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RealtimeCore {
@@ -35,13 +86,216 @@ impl RealtimeCore {
         Self {
             sessions: Arc::new(SessionRegistry::new()),
             presence: Arc::new(Presence::new()),
+            dead_letter: None,
+            relay: None,
+            metrics: None,
+            fanout_limit: DEFAULT_FANOUT_LIMIT,
+            replay: DashMap::new(),
+            replay_capacity: DEFAULT_REPLAY_CAPACITY,
+        }
+    }
+
+    /// Messages kept per room for replay on join (0 is treated as 1).
+    pub fn with_replay_capacity(mut self, n: usize) -> Self {
+        self.replay_capacity = n;
+        self
+    }
+
+    /// Events buffered per room membership subscription (see
+    /// [`Presence::subscribe_room_events`]). Replaces the presence index, so
+    /// call it before anyone joins.
+    pub fn with_room_event_capacity(mut self, n: usize) -> Self {
+        self.presence = Arc::new(Presence::new().with_room_event_capacity(n));
+        self
+    }
+
+    /// Max concurrent recipients for `publish_room_reliable` (0 is treated
+    /// as 1). Tenant contexts override it with `max_fanout_parallelism`.
+    pub fn with_fanout_limit(mut self, n: usize) -> Self {
+        self.fanout_limit = n;
+        self
+    }
+
+    /// Install a handler for reliable deliveries that time out or fail.
+    pub fn with_dead_letter_handler(mut self, handler: Arc<dyn DeadLetterHandler>) -> Self {
+        self.dead_letter = Some(handler);
+        self
+    }
+
+    /// Install a relay that copies room publishes to other replicas.
+    pub fn with_room_relay(mut self, relay: Arc<dyn RoomRelay>) -> Self {
+        self.relay = Some(relay);
+        self
+    }
+
+    /// Install gateway metrics (room rate limiting is counted here).
+    pub fn with_metrics(mut self, metrics: Arc<GatewayMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    fn record_replay(&self, room_key: &str, prepared: &PreparedMsg) {
+        if let Some(buf) = self.replay.get(room_key) {
+            buf.lock().unwrap_or_else(|e| e.into_inner()).push(prepared.clone());
+        }
+    }
+
+    fn relay(&self, room_key: &str, prepared: &PreparedMsg, exclude_user_key: Option<&str>) {
+        if let Some(relay) = &self.relay {
+            relay.relay(room_key, prepared, exclude_user_key);
+        }
+    }
+
+    /// Send up to `n` of the room's most recent messages to one session and
+    /// start buffering the room if it was not yet. Returns the number queued.
+    pub fn replay_room(&self, room_key: &str, connection_id: ConnectionId, n: usize) -> usize {
+        if n == 0 {
+            return 0;
+        }
+        let recent = self
+            .replay
+            .entry(room_key.to_string())
+            .or_insert_with(|| Mutex::new(MessageRingBuffer::new(self.replay_capacity)))
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .last(n);
+        self.send_to_session_batch(connection_id, &recent)
+    }
+
+    /// Drop replay buffers of rooms nobody is in any more.
+    pub fn sweep_replay_buffers(&self) {
+        self.replay.retain(|room_key, _| !self.presence.sessions_in(room_key).is_empty());
+    }
+
+    /// Queue `prepared` without waiting, within the session's bandwidth cap.
+    /// `false` if the cap or a full queue dropped it. High priority goes to
+    /// the session's high-priority queue, if it has one, uncapped.
+    fn try_send_metered(&self, conn: &Connection, prepared: &PreparedMsg, priority: Priority) -> bool {
+        if priority == Priority::High && conn.high_tx.is_some() {
+            return self.send_high(conn, prepared);
+        }
+        if let Some(meter) = &conn.meter {
+            if meter.try_consume(prepared.len()).is_err() {
+                self.note_bandwidth_limited(meter);
+                return false;
+            }
+        }
+        let ok = conn.tx.try_send(prepared.to_ws_message()).is_ok();
+        if ok {
+            self.note_bytes_sent(conn, prepared.len());
+        }
+        ok
+    }
+
+    /// Queue `prepared`, waiting for bandwidth and queue space for at most
+    /// `timeout_ms` in total (0 waits indefinitely).
+    async fn send_metered(
+        &self,
+        conn: &Connection,
+        prepared: &PreparedMsg,
+        timeout_ms: u64,
+        priority: Priority,
+    ) -> std::result::Result<(), SendFailure> {
+        if priority == Priority::High && conn.high_tx.is_some() {
+            return if self.send_high(conn, prepared) { Ok(()) } else { Err(SendFailure::Closed) };
+        }
+        let deadline = (timeout_ms > 0).then(|| Instant::now() + Duration::from_millis(timeout_ms));
+        if let Some(meter) = &conn.meter {
+            if meter.try_consume(prepared.len()).is_err() {
+                self.note_bandwidth_limited(meter);
+                if !meter.consume_until(prepared.len(), deadline).await {
+                    return Err(SendFailure::Timeout);
+                }
+            }
+        }
+        let msg = prepared.to_ws_message();
+        let sent = match deadline {
+            Some(d) => match timeout_at(d, conn.tx.send(msg)).await {
+                Ok(r) => r.map_err(|_| SendFailure::Closed),
+                Err(_) => Err(SendFailure::Timeout),
+            },
+            None => conn.tx.send(msg).await.map_err(|_| SendFailure::Closed),
+        };
+        if sent.is_ok() {
+            self.note_bytes_sent(conn, prepared.len());
+        }
+        sent
+    }
+
+    fn send_high(&self, conn: &Connection, prepared: &PreparedMsg) -> bool {
+        let ok = conn.send_high(prepared.to_ws_message());
+        if ok {
+            self.note_bytes_sent(conn, prepared.len());
+        }
+        ok
+    }
+
+    fn note_bytes_sent(&self, conn: &Connection, bytes: usize) {
+        if let (Some(m), Some(meter)) = (&self.metrics, &conn.meter) {
+            m.bytes_sent.add(&[("tenant", meter.tenant())], bytes as u64);
+        }
+    }
+
+    fn note_bandwidth_limited(&self, meter: &BandwidthMeter) {
+        if let Some(m) = &self.metrics {
+            m.bandwidth_limited.inc(&[("tenant", meter.tenant())]);
+        }
+    }
+
+    fn note_room_rate_limited(&self, room_key: &str) {
+        if let Some(m) = &self.metrics {
+            let (tenant, room) = room_key.split_once("::").unwrap_or(("", room_key));
+            m.room_rate_limited.inc(&[("tenant", tenant), ("room", room)]);
+        }
+    }
+
+    /// Charge one message to `room_key` against `limiter`, counting refusals
+    /// in the room rate metric. `Err(secs)` is the retry hint.
+    pub fn check_room_rate(&self, limiter: &RoomRateLimiter, room_key: &str) -> std::result::Result<(), u64> {
+        limiter.check(room_key).map_err(|_| {
+            self.note_room_rate_limited(room_key);
+            limiter.retry_after_secs(room_key)
+        })
+    }
+
+    /// Wait for every session's outbound queue to empty, for at most
+    /// `timeout`. Run before [`best_effort_shutdown_all`](Self::best_effort_shutdown_all)
+    /// so queued messages are not crowded out by the Close frames. `true` if
+    /// all queues flushed.
+    pub async fn flush_all(&self, timeout: Duration) -> bool {
+        let conns: Vec<Connection> = self.sessions.all_sessions().into_iter().map(|(_, c)| c).collect();
+        wait_flushed(&conns, timeout).await
+    }
+
+    /// Disconnect every session of `user_key` once its outbound queue is
+    /// empty: waits up to `timeout`, then unregisters the sessions, leaves
+    /// their rooms and queues a Close frame (1000, `drained`). Sessions are
+    /// disconnected either way; `Err` if the user was not connected or a
+    /// queue did not flush in time.
+    pub async fn drain_user(&self, user_key: &str, timeout: Duration) -> Result<()> {
+        let sessions = self.sessions.get_user_sessions_by_id(user_key);
+        if sessions.is_empty() {
+            return Err(WsPrismError::BadRequest("user not connected".into()));
+        }
+        let conns: Vec<Connection> = sessions.iter().map(|(_, c)| c.clone()).collect();
+        let flushed = wait_flushed(&conns, timeout).await;
+        let tenant_id = user_key.split_once("::").map_or("", |(t, _)| t);
+        for (connection_id, conn) in sessions {
+            self.sessions.unregister(connection_id);
+            self.presence.cleanup_session(tenant_id, user_key, connection_id);
+            let _ = conn.tx.try_send(Message::Close(Some(CloseFrame { code: 1000, reason: Cow::from("drained") })));
+        }
+        if flushed {
+            Ok(())
+        } else {
+            Err(WsPrismError::ResourceExhausted(format!("outbound queue not flushed within {timeout:?}")))
         }
     }
 
     /// Send Close frames to all sessions during draining (best-effort).
     pub fn best_effort_shutdown_all(&self, reason: &str) {
         let sessions = self.sessions.all_sessions();
-        for (session_key, conn) in sessions {
+        for (connection_id, conn) in sessions {
             let frame = CloseFrame {
                 code: 1001,
                 reason: Cow::from(reason.to_string()),
@@ -49,12 +303,91 @@ impl RealtimeCore {
             if conn.tx.try_send(Message::Close(Some(frame))).is_err() {
                 let n = DROP_COUNT.fetch_add(1, Ordering::Relaxed);
                 if sample_every_1024(n) {
-                    tracing::warn!(%session_key, drops=%n, "egress drop while draining");
+                    tracing::warn!(%connection_id, drops=%n, "egress drop while draining");
                 }
             }
         }
     }
 
+    /// Send `sys.goaway { reason }` and a Close frame to every session of
+    /// `tenant_id` (best-effort). Returns the number of sessions reached.
+    pub fn goaway_tenant(&self, tenant_id: &str, reason: &str) -> usize {
+        let goaway = sys_frame("goaway", &serde_json::json!({ "reason": reason }), None);
+        let mut reached = 0;
+        // Only `try_send`s under the registry's read locks.
+        self.sessions.iter_for_tenant(tenant_id, |connection_id, conn| {
+            let frame = CloseFrame { code: 1001, reason: Cow::from(reason.to_string()) };
+            let sent = conn.send_high(Message::Text(goaway.clone()));
+            if !conn.send_high(Message::Close(Some(frame))) {
+                let n = DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+                if sample_every_1024(n) {
+                    tracing::warn!(%connection_id, drops=%n, "egress drop on goaway");
+                }
+            }
+            reached += usize::from(sent);
+        });
+        reached
+    }
+
+    /// Send to every connected session. Returns the number of sessions reached.
+    pub async fn broadcast_all(&self, out: Outgoing) -> Result<usize> {
+        self.broadcast_tenants(out, |_| true).await
+    }
+
+    /// Send to every session of the tenants accepted by `tenant_filter`.
+    ///
+    /// Serializes once. Lossy uses `try_send` (full queues are skipped);
+    /// Reliable awaits all sends concurrently, each bounded by its timeout.
+    /// Returns the number of sessions the message was queued for.
+    pub async fn broadcast_tenants(
+        &self,
+        out: Outgoing,
+        tenant_filter: impl Fn(&str) -> bool,
+    ) -> Result<usize> {
+        let prepared = PreparedMsg::prepare(&out)?;
+        let sessions = self.sessions.sessions_where(tenant_filter);
+
+        let timeout_ms = match out.qos {
+            QoS::Lossy => {
+                let mut reached = 0;
+                for (connection_id, conn) in sessions {
+                    if self.try_send_metered(&conn, &prepared, out.priority) {
+                        reached += 1;
+                    } else {
+                        conn.drops.note_lossy();
+                        let n = DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+                        if sample_every_1024(n) { tracing::warn!(%connection_id, drops=%n, "broadcast drop"); }
+                    }
+                }
+                return Ok(reached);
+            }
+            QoS::Reliable { timeout_ms } => timeout_ms,
+        };
+
+        let mut futs = FuturesUnordered::new();
+        let prepared = &prepared;
+        let priority = out.priority;
+        for (_, conn) in sessions {
+            futs.push(async move {
+                let ok = self.send_metered(&conn, prepared, timeout_ms, priority).await.is_ok();
+                if !ok {
+                    conn.drops.note_reliable();
+                }
+                ok
+            });
+        }
+        let mut reached = 0;
+        while let Some(ok) = futs.next().await {
+            if ok {
+                reached += 1;
+            } else {
+                let n = SEND_FAIL_COUNT.fetch_add(1, Ordering::Relaxed);
+                if sample_every_1024(n) { tracing::warn!(fails=%n, "reliable broadcast failed"); }
+            }
+        }
+        Ok(reached)
+    }
+
     pub fn send_to_user(&self, user_key: &str, out: Outgoing) -> Result<()> {
         let conns = self.sessions.get_user_sessions(user_key);
         if conns.is_empty() {
@@ -62,7 +395,8 @@ impl RealtimeCore {
         }
         let prepared = PreparedMsg::prepare(&out)?;
         for c in conns {
-            if c.tx.try_send(prepared.to_ws_message()).is_err() {
+            if !self.try_send_metered(&c, &prepared, out.priority) {
+                c.drops.note_lossy();
                 let n = DROP_COUNT.fetch_add(1, Ordering::Relaxed);
                 if sample_every_1024(n) { tracing::warn!(user_key=%user_key, drops=%n, "egress drop"); }
             }
@@ -70,58 +404,223 @@ impl RealtimeCore {
         Ok(())
     }
 
+    /// [`send_to_user`](Self::send_to_user) honoring `out.qos`, with each
+    /// session's outcome. Reliable sends run concurrently; failures go to the
+    /// dead-letter handler with an empty room. Empty if the user is offline.
+    pub async fn deliver_to_user(&self, user_key: &str, out: Outgoing) -> Result<Vec<SessionDelivery>> {
+        let conns = self.sessions.get_user_sessions_by_id(user_key);
+        if conns.is_empty() {
+            return Ok(vec![]);
+        }
+        let prepared = PreparedMsg::prepare(&out)?;
+        let QoS::Reliable { timeout_ms } = out.qos else {
+            let deliveries = conns.into_iter().map(|(connection_id, conn)| {
+                let delivered = self.try_send_metered(&conn, &prepared, out.priority);
+                if !delivered {
+                    conn.drops.note_lossy();
+                    let n = DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+                    if sample_every_1024(n) { tracing::warn!(user_key=%user_key, drops=%n, "egress drop"); }
+                }
+                SessionDelivery { connection_id, delivered }
+            });
+            return Ok(deliveries.collect());
+        };
+        let prepared = &prepared;
+        let priority = out.priority;
+        let futs: FuturesUnordered<_> = conns
+            .into_iter()
+            .map(|(connection_id, conn)| async move {
+                let delivered = self.deliver_reliable("", prepared, connection_id, conn, timeout_ms, priority).await;
+                SessionDelivery { connection_id, delivered }
+            })
+            .collect();
+        Ok(futs.collect().await)
+    }
+
     /// Send to a single session. Queue-full drops are sampled and logged.
-    pub fn send_to_session(&self, session_key: &str, out: Outgoing) -> Result<()> {
-        let conn = self.sessions.get_session(session_key)
+    pub fn send_to_session(&self, connection_id: ConnectionId, out: Outgoing) -> Result<()> {
+        let conn = self.sessions.get_session(connection_id)
             .ok_or_else(|| WsPrismError::BadRequest("session not connected".into()))?;
         let prepared = PreparedMsg::prepare(&out)?;
-        if conn.tx.try_send(prepared.to_ws_message()).is_err() {
+        if !self.try_send_metered(&conn, &prepared, out.priority) {
+            conn.drops.note_lossy();
             let n = DROP_COUNT.fetch_add(1, Ordering::Relaxed);
-            if sample_every_1024(n) { tracing::warn!(%session_key, "send_to_session dropped"); }
+            if sample_every_1024(n) { tracing::warn!(%connection_id, "send_to_session dropped"); }
         }
         Ok(())
     }
 
+    /// Queue already-prepared messages for one session, in order (lossy).
+    /// Returns the number queued.
+    pub fn send_to_session_batch(&self, connection_id: ConnectionId, msgs: &[PreparedMsg]) -> usize {
+        let Some(conn) = self.sessions.get_session(connection_id) else { return 0 };
+        let mut queued = 0;
+        for msg in msgs {
+            if self.try_send_metered(&conn, msg, Priority::Normal) {
+                queued += 1;
+            } else {
+                conn.drops.note_lossy();
+                let n = DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+                if sample_every_1024(n) { tracing::warn!(%connection_id, drops=%n, "batch drop"); }
+            }
+        }
+        queued
+    }
+
     pub fn publish_room_lossy(&self, room_key: &str, out: Outgoing) -> Result<()> {
+        self.publish_room_lossy_excluding(room_key, out, None).map(drop)
+    }
+
+    /// [`publish_room_lossy`](Self::publish_room_lossy), skipping the
+    /// sessions of `exclude_user_key`, and counting targets and drops.
+    pub fn publish_room_lossy_excluding(
+        &self,
+        room_key: &str,
+        out: Outgoing,
+        exclude_user_key: Option<&str>,
+    ) -> Result<PublishReport> {
         let prepared = PreparedMsg::prepare(&out)?;
-        let sessions = self.presence.sessions_in(room_key);
-        for sid in sessions {
-            if let Some(conn) = self.sessions.get_session(&sid) {
-                if conn.tx.try_send(prepared.to_ws_message()).is_err() {
+        self.relay(room_key, &prepared, exclude_user_key);
+        Ok(self.deliver_local(room_key, &prepared, exclude_user_key, out.priority))
+    }
+
+    /// Lossy local fanout of a message another replica published; unlike
+    /// [`publish_room_lossy_excluding`](Self::publish_room_lossy_excluding)
+    /// it is not handed to the relay again.
+    pub fn deliver_relayed(&self, room_key: &str, prepared: &PreparedMsg, exclude_user_key: Option<&str>) -> PublishReport {
+        self.deliver_local(room_key, prepared, exclude_user_key, Priority::Normal)
+    }
+
+    fn deliver_local(
+        &self,
+        room_key: &str,
+        prepared: &PreparedMsg,
+        exclude_user_key: Option<&str>,
+        priority: Priority,
+    ) -> PublishReport {
+        self.record_replay(room_key, prepared);
+        let mut report = PublishReport::default();
+        for id in self.room_targets(room_key, exclude_user_key) {
+            if let Some(conn) = self.sessions.get_session(id) {
+                report.targets += 1;
+                if !self.try_send_metered(&conn, prepared, priority) {
+                    report.dropped += 1;
+                    conn.drops.note_lossy();
                     let n = DROP_COUNT.fetch_add(1, Ordering::Relaxed);
                     if sample_every_1024(n) { tracing::warn!(room_key=%room_key, drops=%n, "lossy drop"); }
                 }
             }
         }
-        Ok(())
+        report
     }
 
+    /// Reliable room publish with the core's `fanout_limit`.
     pub async fn publish_room_reliable(&self, room_key: &str, out: Outgoing) -> Result<()> {
+        self.publish_room_reliable_bounded(room_key, out, self.fanout_limit).await
+    }
+
+    /// Reliable room publish to at most `fanout_limit` recipients at a time.
+    ///
+    /// Recipients are processed in chunks: each chunk's sends run
+    /// concurrently, and the next chunk starts once all of them finished
+    /// (delivered, failed or timed out). This bounds in-flight futures for
+    /// very large rooms at the cost of latency for later chunks.
+    pub async fn publish_room_reliable_bounded(&self, room_key: &str, out: Outgoing, fanout_limit: usize) -> Result<()> {
+        self.publish_room_reliable_excluding(room_key, out, fanout_limit, None).await.map(drop)
+    }
+
+    /// [`publish_room_reliable_bounded`](Self::publish_room_reliable_bounded),
+    /// skipping the sessions of `exclude_user_key`, and counting targets and
+    /// failed sends.
+    pub async fn publish_room_reliable_excluding(
+        &self,
+        room_key: &str,
+        out: Outgoing,
+        fanout_limit: usize,
+        exclude_user_key: Option<&str>,
+    ) -> Result<PublishReport> {
         let prepared = PreparedMsg::prepare(&out)?;
-        let sessions = self.presence.sessions_in(room_key);
-        let (timeout_ms, do_timeout) = match out.qos {
-            QoS::Reliable { timeout_ms } => (timeout_ms, timeout_ms > 0),
-            _ => (0, false),
+        self.record_replay(room_key, &prepared);
+        self.relay(room_key, &prepared, exclude_user_key);
+        let sessions = self.room_targets(room_key, exclude_user_key);
+        let timeout_ms = match out.qos {
+            QoS::Reliable { timeout_ms } => timeout_ms,
+            _ => 0,
         };
-        let mut futs = FuturesUnordered::new();
-        for sid in sessions {
-            if let Some(conn) = self.sessions.get_session(&sid) {
-                let msg = prepared.to_ws_message();
-                futs.push(async move {
-                    if do_timeout {
-                        if timeout(Duration::from_millis(timeout_ms), conn.tx.send(msg)).await.is_err() {
-                            let n = SEND_FAIL_COUNT.fetch_add(1, Ordering::Relaxed);
-                            if sample_every_1024(n) { tracing::warn!(fails=%n, "reliable send timeout"); }
-                        }
-                    } else if conn.tx.send(msg).await.is_err() {
-                        let n = SEND_FAIL_COUNT.fetch_add(1, Ordering::Relaxed);
-                        if sample_every_1024(n) { tracing::warn!(fails=%n, "reliable send failed"); }
-                    }
-                });
+        let mut report = PublishReport::default();
+        // `chunks` preallocates a chunk, so never ask for more than the room holds.
+        let chunk = fanout_limit.clamp(1, sessions.len().max(1));
+        let mut chunks = stream::iter(sessions).chunks(chunk);
+        while let Some(chunk) = chunks.next().await {
+            let mut futs: FuturesUnordered<_> = chunk
+                .into_iter()
+                .filter_map(|id| self.sessions.get_session(id).map(|conn| (id, conn)))
+                .map(|(id, conn)| self.deliver_reliable(room_key, &prepared, id, conn, timeout_ms, out.priority))
+                .collect();
+            while let Some(delivered) = futs.next().await {
+                report.targets += 1;
+                report.dropped += usize::from(!delivered);
             }
         }
-        while futs.next().await.is_some() {}
-        Ok(())
+        Ok(report)
+    }
+
+    /// Sessions in `room_key`, minus those of `exclude_user_key`.
+    fn room_targets(&self, room_key: &str, exclude_user_key: Option<&str>) -> Vec<ConnectionId> {
+        let mut sessions = self.presence.sessions_in(room_key);
+        if let Some(user_key) = exclude_user_key {
+            sessions.retain(|id| self.sessions.user_of(*id).as_deref() != Some(user_key));
+        }
+        sessions
+    }
+
+    /// One reliable send (`timeout_ms == 0` waits indefinitely); failures go
+    /// to the dead-letter handler.
+    async fn deliver_reliable(
+        &self,
+        room_key: &str,
+        prepared: &PreparedMsg,
+        id: ConnectionId,
+        conn: Connection,
+        timeout_ms: u64,
+        priority: Priority,
+    ) -> bool {
+        let delivered = match self.send_metered(&conn, prepared, timeout_ms, priority).await {
+            Ok(()) => true,
+            Err(SendFailure::Timeout) => {
+                let n = SEND_FAIL_COUNT.fetch_add(1, Ordering::Relaxed);
+                if sample_every_1024(n) { tracing::warn!(fails=%n, "reliable send timeout"); }
+                false
+            }
+            Err(SendFailure::Closed) => {
+                let n = SEND_FAIL_COUNT.fetch_add(1, Ordering::Relaxed);
+                if sample_every_1024(n) { tracing::warn!(fails=%n, "reliable send failed"); }
+                false
+            }
+        };
+        if !delivered {
+            conn.drops.note_reliable();
+            if let Some(h) = &self.dead_letter {
+                let user = self.sessions.user_of(id).unwrap_or_default();
+                h.on_drop(&user, room_key, prepared).await;
+            }
+        }
+        delivered
+    }
+}
+
+/// Poll (every millisecond) until each queue in `conns` is empty or closed,
+/// for at most `timeout`. `true` if they all emptied.
+async fn wait_flushed(conns: &[Connection], timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if conns.iter().all(|c| c.tx.is_closed() || c.tx.capacity() == c.tx.max_capacity()) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
     }
 }
 
@@ -130,10 +629,17 @@ pub struct RealtimeCtx {
     tenant: Arc<str>,
     user: Arc<str>,
     user_key: Arc<str>,
-    session_id: Arc<str>,
-    session_key: Arc<str>,
+    connection_id: ConnectionId,
     pub trace_id: Arc<str>,
     active_room: Option<Arc<str>>,
+    guest: bool,
+    claims: SessionClaims,
+    room_limiter: Option<Arc<RoomRateLimiter>>,
+    /// Room the inbound message already paid for at the pre-dispatch gate.
+    gated_room: Option<Arc<str>>,
+    fanout_limit: Option<usize>,
+    replay_on_join: usize,
+    session: Option<SessionHandle>,
     core: Arc<RealtimeCore>,
 }
 
@@ -142,56 +648,238 @@ impl RealtimeCtx {
     pub fn new(
         tenant: impl Into<Arc<str>>,
         user: impl Into<Arc<str>>,
-        session_id: impl Into<Arc<str>>,
+        connection_id: ConnectionId,
         trace_id: impl Into<Arc<str>>,
         active_room: Option<String>,
         core: Arc<RealtimeCore>,
     ) -> Self {
         let tenant = tenant.into();
         let user = user.into();
-        let session_id = session_id.into();
         let user_key: Arc<str> = Arc::from(format!("{}::{}", tenant, user));
-        let session_key: Arc<str> = Arc::from(format!("{}::{}::{}", tenant, user, session_id));
 
         Self {
             tenant,
             user,
             user_key,
-            session_id,
-            session_key,
+            connection_id,
             trace_id: trace_id.into(),
             active_room: active_room.map(Arc::from),
+            guest: false,
+            claims: SessionClaims::default(),
+            room_limiter: None,
+            gated_room: None,
+            fanout_limit: None,
+            replay_on_join: 0,
+            session: None,
             core,
         }
     }
 
+    /// Attach the session's claims (resolved at connect).
+    pub fn with_claims(mut self, claims: SessionClaims) -> Self {
+        self.claims = claims;
+        self
+    }
+
+    /// Mark the context as belonging to an anonymous guest session.
+    pub fn with_guest(mut self, guest: bool) -> Self {
+        self.guest = guest;
+        self
+    }
+
+    /// Tenant's per-room limiter, used by `check_room_rate` and the room
+    /// publishes.
+    pub fn with_room_limiter(mut self, limiter: Option<Arc<RoomRateLimiter>>) -> Self {
+        self.room_limiter = limiter;
+        self
+    }
+
+    /// Tenant's `max_fanout_parallelism` for `publish_room_reliable`
+    /// (the core's limit when unset).
+    pub fn with_fanout_limit(mut self, n: usize) -> Self {
+        self.fanout_limit = Some(n);
+        self
+    }
+
+    /// Tenant's `replay_on_join`: recent room messages `replay_room` sends.
+    pub fn with_replay_on_join(mut self, n: usize) -> Self {
+        self.replay_on_join = n;
+        self
+    }
+
+    /// Attach the calling session's [`SessionHandle`]; the active room is
+    /// taken from it.
+    pub fn with_session(mut self, session: SessionHandle) -> Self {
+        self.active_room = session.active_room().map(|r| Arc::from(r.as_str()));
+        self.session = Some(session);
+        self
+    }
+
+    /// Clone for moving into a `tokio::spawn`ed task.
+    ///
+    /// The clone's active room is cleared: by the time the task runs the
+    /// session may have switched rooms, so the task must name any room it
+    /// publishes to. Identity, claims and limits are kept; the task's
+    /// publishes are metered, since they are not the gated message.
+    pub fn clone_for_spawn(&self) -> Self {
+        Self { active_room: None, gated_room: None, ..self.clone() }
+    }
+
     pub fn tenant(&self) -> &str { &self.tenant }
     pub fn user(&self) -> &str { &self.user }
     pub fn user_key(&self) -> &str { &self.user_key }
-    pub fn session_id(&self) -> &str { &self.session_id }
-    pub fn session_key(&self) -> &str { &self.session_key }
+    pub fn connection_id(&self) -> ConnectionId { self.connection_id }
     pub fn active_room(&self) -> Option<&str> { self.active_room.as_deref() }
+    pub fn is_guest(&self) -> bool { self.guest }
+    pub fn claims(&self) -> SessionClaims { self.claims.clone() }
+    /// The client session's handle (`None` for backend and test contexts).
+    pub fn session(&self) -> Option<&SessionHandle> { self.session.as_ref() }
+
+    /// Gateway metrics registry, for services registering their own metrics
+    /// (`None` when the core runs without one, as in unit tests).
+    pub fn metrics(&self) -> Option<&Arc<GatewayMetrics>> { self.core.metrics.as_ref() }
 
     fn room_key(&self, room: &str) -> String { format!("{}::{}", self.tenant(), room) }
 
+    /// Inbound gate for a message targeting `room`, run by the gateway before
+    /// dispatch. `Err(secs)` is the retry hint.
+    ///
+    /// The context remembers `room` either way, so the handler's publishes
+    /// to it are not charged a second time for the same message (a refused
+    /// message is only dispatched when shadow mode lets it through).
+    pub fn check_room_rate(&mut self, room: &str) -> std::result::Result<(), u64> {
+        let Some(limiter) = &self.room_limiter else { return Ok(()) };
+        self.gated_room = Some(Arc::from(room));
+        self.core.check_room_rate(limiter, &self.room_key(room))
+    }
+
+    /// Publish-side check: publishes to a room other than the gated one
+    /// (a second room, spawned tasks, backend contexts) pay for themselves.
+    fn check_publish_rate(&self, room: &str, rk: &str) -> Result<()> {
+        let Some(limiter) = &self.room_limiter else { return Ok(()) };
+        if self.gated_room.as_deref() == Some(room) {
+            return Ok(());
+        }
+        self.core.check_room_rate(limiter, rk).map_err(|_| WsPrismError::RateLimited)
+    }
+
+    /// Record a lifecycle event in this session's event log, if it has one.
+    fn log_event(&self, event: impl FnOnce() -> SessionEvent) {
+        if let Some(log) = self.core.sessions.event_log(self.connection_id) {
+            log.record(event());
+        }
+    }
+
     pub fn join_room_with_limits(&self, room: &str, limits: &TenantLimits) -> Result<()> {
+        self.join_room_with_capacity(room, limits, None)
+    }
+
+    /// Join `room`, setting its capacity if this join creates it (see
+    /// [`Presence::try_join_with_capacity`]).
+    pub fn join_room_with_capacity(&self, room: &str, limits: &TenantLimits, capacity: Option<u64>) -> Result<()> {
         let rk = self.room_key(room);
-        self.core.presence.try_join(self.tenant(), &rk, self.user_key(), self.session_key(), limits)
+        self.core.presence.try_join_with_capacity(self.tenant(), &rk, self.user_key(), self.connection_id, limits, capacity)?;
+        self.log_event(|| SessionEvent::JoinedRoom(room.into()));
+        Ok(())
+    }
+
+    /// Receive `Joined`/`Left` events for a room of this tenant (see
+    /// [`Presence::subscribe_room_events`]).
+    pub fn subscribe_room_events(&self, room: &str) -> tokio::sync::broadcast::Receiver<RoomEvent> {
+        self.core.presence.subscribe_room_events(&self.room_key(room))
+    }
+
+    /// Move this connection from `from` to `to` atomically (see
+    /// [`Presence::move_user`]).
+    pub fn move_room_with_limits(&self, from: &str, to: &str, limits: &TenantLimits) -> Result<PresenceEvent> {
+        let (fk, tk) = (self.room_key(from), self.room_key(to));
+        let moved = self.core.presence.move_user(self.tenant(), self.user_key(), self.connection_id, &fk, &tk, limits)?;
+        self.log_event(|| SessionEvent::LeftRoom(from.into()));
+        self.log_event(|| SessionEvent::JoinedRoom(to.into()));
+        Ok(moved)
+    }
+
+    /// Send this session the last `replay_on_join` messages published to
+    /// `room` (no-op when 0). The gateway calls it after a successful join.
+    pub fn replay_room(&self, room: &str) -> usize {
+        if self.replay_on_join == 0 {
+            return 0;
+        }
+        let rk = self.room_key(room);
+        self.core.replay_room(&rk, self.connection_id, self.replay_on_join)
+    }
+
+    /// Rooms this connection is in, without the tenant prefix, sorted.
+    pub fn session_rooms(&self) -> Vec<String> {
+        let prefix = self.room_key("");
+        let mut rooms: Vec<String> = self.core.presence.rooms_of_session(self.connection_id)
+            .into_iter()
+            .filter_map(|rk| rk.strip_prefix(&prefix).map(str::to_string))
+            .collect();
+        rooms.sort_unstable();
+        rooms
+    }
+
+    /// Members of `room` across replicas (see [`Presence::global_count`]).
+    pub fn room_member_count(&self, room: &str) -> usize {
+        self.core.presence.global_count(&self.room_key(room))
     }
 
     pub fn leave_room(&self, room: &str) {
         let rk = self.room_key(room);
-        self.core.presence.leave(self.tenant(), &rk, self.user_key(), self.session_key());
+        self.core.presence.leave(self.tenant(), &rk, self.user_key(), self.connection_id);
+        self.log_event(|| SessionEvent::LeftRoom(room.into()));
+    }
+
+    /// Rooms (tenant-local names) the calling user is currently in.
+    ///
+    /// Membership is tracked per user, so rooms joined from another session of
+    /// the same user are included.
+    pub fn rooms(&self) -> Vec<String> {
+        let prefix = format!("{}::", self.tenant());
+        self.core.presence.rooms_of(self.user_key())
+            .into_iter()
+            .filter_map(|rk| rk.strip_prefix(&prefix).map(str::to_string))
+            .collect()
+    }
+
+    /// Whether the calling user is currently in `room`.
+    pub fn is_in_room(&self, room: &str) -> bool {
+        let rk = self.room_key(room);
+        self.core.presence.is_user_in(self.user_key(), &rk)
     }
 
     pub fn send_to_user(&self, out: Outgoing) -> Result<()> { self.core.send_to_user(self.user_key(), out) }
-    pub fn send_to_session(&self, out: Outgoing) -> Result<()> { self.core.send_to_session(self.session_key(), out) }
+
+    /// Send `out` to every session of the calling user.
+    pub fn send_to_self(&self, out: Outgoing) -> Result<()> {
+        self.core.send_to_user(self.user_key(), out)
+    }
+
+    /// [`send_to_self`](Self::send_to_self) with `QoS::Lossy`: dropped for
+    /// sessions whose queue is full.
+    pub fn send_to_self_lossy(&self, payload: impl Into<Payload>) -> Result<()> {
+        self.send_to_self(Outgoing { qos: QoS::Lossy, payload: payload.into(), priority: Priority::Normal })
+    }
+
+    /// Send `out` to every session of `user` in this tenant.
+    pub fn send_to_tenant_user(&self, user: &str, out: Outgoing) -> Result<()> {
+        self.core.send_to_user(&format!("{}::{}", self.tenant(), user), out)
+    }
+
+    pub fn send_to_session(&self, out: Outgoing) -> Result<()> { self.core.send_to_session(self.connection_id, out) }
+    /// Publish to `room`; `RateLimited` when the room is over its rate and
+    /// the message did not already pass the inbound gate for it.
     pub fn publish_room_lossy(&self, room: &str, out: Outgoing) -> Result<()> {
         let rk = self.room_key(room);
+        self.check_publish_rate(room, &rk)?;
         self.core.publish_room_lossy(&rk, out)
     }
+    /// Reliable counterpart of [`publish_room_lossy`](Self::publish_room_lossy).
     pub async fn publish_room_reliable(&self, room: &str, out: Outgoing) -> Result<()> {
         let rk = self.room_key(room);
-        self.core.publish_room_reliable(&rk, out).await
+        self.check_publish_rate(room, &rk)?;
+        let limit = self.fanout_limit.unwrap_or(self.core.fanout_limit);
+        self.core.publish_room_reliable_bounded(&rk, out, limit).await
     }
 }
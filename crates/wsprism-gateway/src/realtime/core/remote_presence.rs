@@ -0,0 +1,84 @@
+use std::collections::{BTreeSet, HashMap};
+
+use dashmap::DashMap;
+use tokio::time::Instant;
+
+/// Room members connected to other replicas, as gossiped by the cluster
+/// bridge: `room_key -> node_id -> user_key -> expiry`.
+///
+/// Entries are kept per node, so a user with sessions on two replicas stays
+/// listed until both have reported them gone. Every entry expires unless it
+/// is refreshed (by a join or a heartbeat snapshot) before its deadline, which
+/// drops the members of a replica that went away without saying so. Expired
+/// entries are ignored by reads and removed by [`sweep`](Self::sweep).
+#[derive(Default)]
+pub struct RemotePresence {
+    rooms: DashMap<String, HashMap<String, HashMap<String, Instant>>>,
+}
+
+impl RemotePresence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `node_id` has `user_keys` in `room_key`, until `expires_at` unless
+    /// refreshed.
+    pub fn refresh<'a>(
+        &self,
+        node_id: &str,
+        room_key: &str,
+        user_keys: impl IntoIterator<Item = &'a str>,
+        expires_at: Instant,
+    ) {
+        let mut room = self.rooms.entry(room_key.to_string()).or_default();
+        let users = room.entry(node_id.to_string()).or_default();
+        for user_key in user_keys {
+            users.insert(user_key.to_string(), expires_at);
+        }
+    }
+
+    /// `node_id` no longer has `user_key` in `room_key`.
+    pub fn remove(&self, node_id: &str, room_key: &str, user_key: &str) {
+        let Some(mut room) = self.rooms.get_mut(room_key) else { return };
+        if let Some(users) = room.get_mut(node_id) {
+            users.remove(user_key);
+            if users.is_empty() {
+                room.remove(node_id);
+            }
+        }
+        let empty = room.is_empty();
+        drop(room);
+        if empty {
+            self.rooms.remove_if(room_key, |_, nodes| nodes.is_empty());
+        }
+    }
+
+    /// Live remote members of `room_key`, each once however many replicas
+    /// report them.
+    pub fn users_in(&self, room_key: &str) -> BTreeSet<String> {
+        let now = Instant::now();
+        let Some(room) = self.rooms.get(room_key) else { return BTreeSet::new() };
+        room.values()
+            .flat_map(|users| users.iter())
+            .filter(|(_, expires_at)| **expires_at > now)
+            .map(|(user_key, _)| user_key.clone())
+            .collect()
+    }
+
+    /// Drop expired entries and rooms left empty.
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        self.rooms.retain(|_, nodes| {
+            nodes.retain(|_, users| {
+                users.retain(|_, expires_at| *expires_at > now);
+                !users.is_empty()
+            });
+            !nodes.is_empty()
+        });
+    }
+
+    /// Rooms with at least one remote entry, expired or not.
+    pub fn room_count(&self) -> usize {
+        self.rooms.len()
+    }
+}
@@ -0,0 +1,49 @@
+//! Per-room replay buffer.
+//!
+//! Keeps the last few messages published to a room, already serialized, so
+//! a session joining the room can be sent recent history without
+//! re-encoding it.
+
+use std::collections::VecDeque;
+
+use crate::realtime::types::PreparedMsg;
+
+/// Default messages kept per room (`gateway.room_replay_capacity`).
+pub const DEFAULT_REPLAY_CAPACITY: usize = 50;
+
+/// Fixed-capacity FIFO of prepared messages; pushing past capacity evicts
+/// the oldest.
+#[derive(Debug)]
+pub struct MessageRingBuffer {
+    capacity: usize,
+    buf: VecDeque<PreparedMsg>,
+}
+
+impl MessageRingBuffer {
+    /// `capacity` of 0 is treated as 1.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self { capacity, buf: VecDeque::with_capacity(capacity) }
+    }
+
+    pub fn push(&mut self, msg: PreparedMsg) {
+        if self.buf.len() == self.capacity {
+            self.buf.pop_front();
+        }
+        self.buf.push_back(msg);
+    }
+
+    /// Up to `n` most recent messages, oldest first.
+    pub fn last(&self, n: usize) -> Vec<PreparedMsg> {
+        let skip = self.buf.len().saturating_sub(n);
+        self.buf.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use wsprism_core::error::ClientCode;
+
+use crate::policy::engine::Lane;
+
+/// Default `gateway.max_session_events`.
+pub const DEFAULT_MAX_SESSION_EVENTS: usize = 1000;
+
+/// One step in a session's lifecycle.
+///
+/// Names are `Arc<str>` so recording clones a pointer, never a `String`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    Connected,
+    Authenticated(Arc<str>),
+    JoinedRoom(Arc<str>),
+    LeftRoom(Arc<str>),
+    /// Inbound data frame: lane and raw length.
+    FrameReceived(Lane, usize),
+    /// Outbound data frame written to the client: length.
+    FrameSent(usize),
+    /// Error sent to the client.
+    Error(ClientCode, Arc<str>),
+    /// Close reason, as in the `disconnect` webhook.
+    Disconnected(Arc<str>),
+}
+
+impl SessionEvent {
+    fn to_json(&self) -> Value {
+        match self {
+            Self::Connected => json!({ "event": "connected" }),
+            Self::Authenticated(user) => json!({ "event": "authenticated", "user": &**user }),
+            Self::JoinedRoom(room) => json!({ "event": "joined_room", "room": &**room }),
+            Self::LeftRoom(room) => json!({ "event": "left_room", "room": &**room }),
+            Self::FrameReceived(lane, bytes) => json!({ "event": "frame_received", "lane": lane.as_str(), "bytes": bytes }),
+            Self::FrameSent(bytes) => json!({ "event": "frame_sent", "bytes": bytes }),
+            Self::Error(code, msg) => json!({ "event": "error", "code": code.as_str(), "message": &**msg }),
+            Self::Disconnected(reason) => json!({ "event": "disconnected", "reason": &**reason }),
+        }
+    }
+}
+
+/// A [`SessionEvent`] and when it was recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedEvent {
+    pub at: Instant,
+    pub event: SessionEvent,
+}
+
+/// The last `max_events` lifecycle events of one session (oldest first).
+///
+/// Shared by the transport, which records frames and errors, and the
+/// realtime core, which records room changes; read by
+/// `GET /admin/v1/sessions/{id}/events`.
+pub struct SessionEventLog {
+    max_events: usize,
+    started: Instant,
+    started_unix_ms: u64,
+    events: Mutex<VecDeque<LoggedEvent>>,
+}
+
+impl SessionEventLog {
+    pub fn new(max_events: usize) -> Self {
+        let started_unix_ms = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+        Self { max_events, started: Instant::now(), started_unix_ms, events: Mutex::new(VecDeque::new()) }
+    }
+
+    fn events(&self) -> std::sync::MutexGuard<'_, VecDeque<LoggedEvent>> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Append `event`, evicting the oldest once `max_events` are kept.
+    pub fn record(&self, event: SessionEvent) {
+        if self.max_events == 0 {
+            return;
+        }
+        let mut events = self.events();
+        if events.len() == self.max_events {
+            events.pop_front();
+        }
+        events.push_back(LoggedEvent { at: Instant::now(), event });
+    }
+
+    pub fn snapshot(&self) -> Vec<LoggedEvent> {
+        self.events().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.events().clear();
+    }
+
+    /// Events as JSON, each with `at_ms` (since the log was created) and
+    /// `at_unix_ms`.
+    pub fn to_json(&self) -> Vec<Value> {
+        self.events()
+            .iter()
+            .map(|e| {
+                let at_ms = e.at.saturating_duration_since(self.started).as_millis() as u64;
+                let mut v = e.event.to_json();
+                v["at_ms"] = at_ms.into();
+                v["at_unix_ms"] = self.started_unix_ms.saturating_add(at_ms).into();
+                v
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use crate::config::schema::TenantLimits;
+
+/// Per-session state shared between the transport and Ext lane services.
+///
+/// The active room is set by the `room` service and read by the transport
+/// for every Hot Lane frame, so it lives behind an `ArcSwapOption` rather
+/// than in the transport loop. Clones share the same state.
+#[derive(Clone)]
+pub struct SessionHandle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    active_room: ArcSwapOption<String>,
+    limits: TenantLimits,
+}
+
+impl SessionHandle {
+    /// `limits` are the tenant limits applied to this session's room joins.
+    pub fn new(limits: TenantLimits) -> Self {
+        Self { inner: Arc::new(Inner { active_room: ArcSwapOption::empty(), limits }) }
+    }
+
+    pub fn active_room(&self) -> Option<Arc<String>> {
+        self.inner.active_room.load_full()
+    }
+
+    pub fn set_active_room(&self, room: Option<&str>) {
+        self.inner.active_room.store(room.map(|r| Arc::new(r.to_string())));
+    }
+
+    /// Clear the active room, returning the previous one.
+    pub fn take_active_room(&self) -> Option<Arc<String>> {
+        self.inner.active_room.swap(None)
+    }
+
+    pub fn limits(&self) -> &TenantLimits {
+        &self.inner.limits
+    }
+}
@@ -2,33 +2,159 @@ use axum::extract::ws::Message;
 use dashmap::{DashMap, DashSet};
 use tokio::sync::mpsc;
 
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use wsprism_core::error::{Result, WsPrismError};
 
-/// One session's outbound queue sender.
+use crate::context::{ConnectionId, SessionClaims};
+use crate::realtime::core::SessionEventLog;
+use crate::transport::bandwidth::BandwidthMeter;
+
+/// One session's outbound queue sender plus its resolved claims.
 #[derive(Clone)]
 pub struct Connection {
     pub tx: mpsc::Sender<Message>,
+    pub claims: SessionClaims,
+    /// Messages that never reached `tx`, shared by all clones.
+    pub drops: Arc<OutboundDrops>,
+    /// Outbound bandwidth cap, if the tenant sets one.
+    pub meter: Option<Arc<BandwidthMeter>>,
+    /// Queue the writer drains before `tx` (`Priority::High`). Without one,
+    /// high-priority messages go through `tx` like any other.
+    pub high_tx: Option<mpsc::UnboundedSender<Message>>,
+    /// Lifecycle events, if `gateway.max_session_events` is above 0.
+    pub events: Option<Arc<SessionEventLog>>,
+}
+
+impl Connection {
+    pub fn new(tx: mpsc::Sender<Message>, claims: SessionClaims) -> Self {
+        Self { tx, claims, drops: Arc::new(OutboundDrops::default()), meter: None, high_tx: None, events: None }
+    }
+
+    /// Keep this session's lifecycle events in `log`.
+    pub fn with_event_log(mut self, log: Option<Arc<SessionEventLog>>) -> Self {
+        self.events = log;
+        self
+    }
+
+    /// Cap this session's outbound bytes (see `policy.max_outbound_bytes_per_sec`).
+    pub fn with_meter(mut self, meter: Option<Arc<BandwidthMeter>>) -> Self {
+        self.meter = meter;
+        self
+    }
+
+    /// Give this session a high-priority queue (see [`Priority`](crate::realtime::Priority)).
+    pub fn with_high_priority(mut self, high_tx: mpsc::UnboundedSender<Message>) -> Self {
+        self.high_tx = Some(high_tx);
+        self
+    }
+
+    /// Queue `msg` ahead of everything in `tx`, or `try_send` it on `tx`
+    /// without a high-priority queue. `false` if it was not queued.
+    pub fn send_high(&self, msg: Message) -> bool {
+        match &self.high_tx {
+            Some(high) => high.send(msg).is_ok(),
+            None => self.tx.try_send(msg).is_ok(),
+        }
+    }
+}
+
+/// Per-connection egress drop counters, drained by the outbound sampler.
+#[derive(Debug, Default)]
+pub struct OutboundDrops {
+    lossy: AtomicU64,
+    reliable: AtomicU64,
+}
+
+impl OutboundDrops {
+    /// A lossy send found the queue full (or closed).
+    pub fn note_lossy(&self) {
+        self.lossy.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A reliable send timed out or the queue was closed.
+    pub fn note_reliable(&self) {
+        self.reliable.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Reset both counters, returning `(lossy, reliable)` since the last take.
+    fn take(&self) -> (u64, u64) {
+        (self.lossy.swap(0, Ordering::Relaxed), self.reliable.swap(0, Ordering::Relaxed))
+    }
+
+    fn add(&self, (lossy, reliable): (u64, u64)) {
+        self.lossy.fetch_add(lossy, Ordering::Relaxed);
+        self.reliable.fetch_add(reliable, Ordering::Relaxed);
+    }
+}
+
+/// Outbound queue state of one tenant, taken by `sample_outbound`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutboundSample {
+    pub tenant: String,
+    /// Queue depth of every session, ascending.
+    pub depths: Vec<usize>,
+    /// Lossy drops since the previous sample.
+    pub lossy_drops: u64,
+    /// Reliable send failures since the previous sample.
+    pub reliable_drops: u64,
+}
+
+impl OutboundSample {
+    pub fn max_depth(&self) -> usize {
+        self.depths.last().copied().unwrap_or(0)
+    }
+
+    /// Nearest-rank p99 of the queue depths (0 without sessions).
+    pub fn p99_depth(&self) -> usize {
+        if self.depths.is_empty() {
+            return 0;
+        }
+        let rank = (self.depths.len() * 99).div_ceil(100);
+        self.depths[rank.saturating_sub(1)]
+    }
+}
+
+/// Point-in-time view of one registered connection (admin API, metrics).
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConnectionSnapshot {
+    pub connection_id: ConnectionId,
+    pub user_id: String,
+    pub tenant: String,
+    pub connected_at_unix_ms: u64,
+    /// Messages waiting in the outbound queue.
+    pub queue_depth: usize,
 }
 
+/// Event logs kept after their session is unregistered.
+const CLOSED_EVENT_LOGS: usize = 64;
+
 #[derive(Clone)]
 struct SessionEntry {
     conn: Connection,
     created_seq: u64,
     // Sprint 5: store tenant here to facilitate cleanup without looking up other maps
     tenant_id: String,
+    user_key: String,
+    connected_at: Instant,
 }
 
 /// Session registry:
-/// - `session_key -> Connection`
-/// - `user_key -> {session_key...}`
+/// - `ConnectionId -> Connection`
+/// - `user_key -> {ConnectionId...}`
 /// - `tenant_id -> count` (Atomic)
 #[derive(Default)]
 pub struct SessionRegistry {
-    sessions: DashMap<String, SessionEntry>,
-    user_index: DashMap<String, DashSet<String>>,
+    sessions: DashMap<ConnectionId, SessionEntry>,
+    user_index: DashMap<String, DashSet<ConnectionId>>,
     // Sprint 5: O(1) Tenant Counter
     tenant_counts: DashMap<String, AtomicU64>,
+    /// Drops of unregistered sessions not yet taken by `sample_outbound`.
+    retired_drops: DashMap<String, OutboundDrops>,
+    /// Event logs of the last `CLOSED_EVENT_LOGS` unregistered sessions.
+    closed_events: Mutex<VecDeque<(ConnectionId, Arc<SessionEventLog>)>>,
     seq: AtomicU64,
 }
 
@@ -38,6 +164,8 @@ impl SessionRegistry {
             sessions: DashMap::new(),
             user_index: DashMap::new(),
             tenant_counts: DashMap::new(),
+            retired_drops: DashMap::new(),
+            closed_events: Mutex::new(VecDeque::new()),
             seq: AtomicU64::new(1),
         }
     }
@@ -53,7 +181,7 @@ impl SessionRegistry {
         &self,
         tenant_id: String,
         user_key: String,
-        session_key: String,
+        id: ConnectionId,
         conn: Connection,
         max_total: u64
     ) -> Result<()> {
@@ -71,52 +199,88 @@ impl SessionRegistry {
         counter.fetch_add(1, Ordering::Relaxed);
 
         // Check again (race condition mitigation) - Optional but safer
-        if max_total > 0 {
-            if counter.load(Ordering::Relaxed) > max_total {
-                counter.fetch_sub(1, Ordering::Relaxed);
-                return Err(WsPrismError::ResourceExhausted("tenant session limit reached (race)".into()));
-            }
+        if max_total > 0 && counter.load(Ordering::Relaxed) > max_total {
+            counter.fetch_sub(1, Ordering::Relaxed);
+            return Err(WsPrismError::ResourceExhausted("tenant session limit reached (race)".into()));
         }
 
         self.user_index
-            .entry(user_key)
-            .or_insert_with(DashSet::new)
-            .insert(session_key.clone());
+            .entry(user_key.clone())
+            .or_default()
+            .insert(id);
 
         let created_seq = self.seq.fetch_add(1, Ordering::Relaxed);
-        self.sessions.insert(session_key, SessionEntry { conn, created_seq, tenant_id });
+        self.sessions.insert(id, SessionEntry {
+            conn,
+            created_seq,
+            tenant_id,
+            user_key,
+            connected_at: Instant::now(),
+        });
 
         Ok(())
     }
 
-    pub fn remove_session(&self, user_key: &str, session_key: &str) -> Option<Connection> {
-        if let Some(set) = self.user_index.get(user_key) {
-            set.remove(session_key);
+    /// Remove a connection and its user index entry. Idempotent.
+    pub fn unregister(&self, id: ConnectionId) -> Option<Connection> {
+        let (_, entry) = self.sessions.remove(&id)?;
+
+        if let Some(set) = self.user_index.get(&entry.user_key) {
+            set.remove(&id);
             if set.is_empty() {
                 drop(set);
-                self.user_index.remove(user_key);
+                self.user_index.remove_if(&entry.user_key, |_, s| s.is_empty());
             }
         }
 
-        if let Some((_, entry)) = self.sessions.remove(session_key) {
-            // Sprint 5: Decrement tenant counter
-            if let Some(counter) = self.tenant_counts.get(&entry.tenant_id) {
-                counter.fetch_sub(1, Ordering::Relaxed);
+        // Sprint 5: Decrement tenant counter
+        if let Some(counter) = self.tenant_counts.get(&entry.tenant_id) {
+            counter.fetch_sub(1, Ordering::Relaxed);
+        }
+        let pending = entry.conn.drops.take();
+        if pending != (0, 0) {
+            self.retired_drops.entry(entry.tenant_id.clone()).or_default().add(pending);
+        }
+        if let Some(log) = &entry.conn.events {
+            let mut closed = self.closed_events.lock().unwrap_or_else(|e| e.into_inner());
+            if closed.len() == CLOSED_EVENT_LOGS {
+                closed.pop_front();
             }
-            Some(entry.conn)
-        } else {
-            None
+            closed.push_back((id, Arc::clone(log)));
         }
+        Some(entry.conn)
+    }
+
+    /// Event log of a live connection, or of one of the last 64 to close.
+    pub fn event_log(&self, id: ConnectionId) -> Option<Arc<SessionEventLog>> {
+        if let Some(entry) = self.sessions.get(&id) {
+            return entry.conn.events.clone();
+        }
+        let closed = self.closed_events.lock().unwrap_or_else(|e| e.into_inner());
+        closed.iter().rev().find(|(c, _)| *c == id).map(|(_, log)| Arc::clone(log))
+    }
+
+    pub fn get_session(&self, id: ConnectionId) -> Option<Connection> {
+        self.sessions.get(&id).map(|r| r.value().conn.clone())
     }
 
-    pub fn get_session(&self, session_key: &str) -> Option<Connection> {
-        self.sessions.get(session_key).map(|r| r.value().conn.clone())
+    /// Owning user key (`tenant::user`) of a connection.
+    pub fn user_of(&self, id: ConnectionId) -> Option<String> {
+        self.sessions.get(&id).map(|r| r.value().user_key.clone())
     }
 
     pub fn get_user_sessions(&self, user_key: &str) -> Vec<Connection> {
         let Some(set) = self.user_index.get(user_key) else { return vec![]; };
         set.iter()
-            .filter_map(|sid| self.get_session(sid.key()))
+            .filter_map(|id| self.get_session(*id.key()))
+            .collect()
+    }
+
+    /// [`get_user_sessions`](Self::get_user_sessions) with each session's id.
+    pub fn get_user_sessions_by_id(&self, user_key: &str) -> Vec<(ConnectionId, Connection)> {
+        let Some(set) = self.user_index.get(user_key) else { return vec![]; };
+        set.iter()
+            .filter_map(|id| self.get_session(*id.key()).map(|c| (*id.key(), c)))
             .collect()
     }
 
@@ -131,39 +295,167 @@ impl SessionRegistry {
 
     /// Snapshot of all active sessions.
     ///
-    /// Returns a vector of (session_key, Connection). Intended for best-effort
+    /// Returns a vector of (ConnectionId, Connection). Intended for best-effort
     /// shutdown/draining logic.
-    pub fn all_sessions(&self) -> Vec<(String, Connection)> {
+    pub fn all_sessions(&self) -> Vec<(ConnectionId, Connection)> {
+        self.sessions
+            .iter()
+            .map(|r| (*r.key(), r.value().conn.clone()))
+            .collect()
+    }
+
+    /// Snapshot of sessions whose tenant matches `tenant_filter`.
+    pub fn sessions_where(&self, tenant_filter: impl Fn(&str) -> bool) -> Vec<(ConnectionId, Connection)> {
+        self.sessions
+            .iter()
+            .filter(|r| tenant_filter(&r.value().tenant_id))
+            .map(|r| (*r.key(), r.value().conn.clone()))
+            .collect()
+    }
+
+    /// Call `f` for every registered connection.
+    ///
+    /// Runs while holding map shard read locks: `f` must not call back into
+    /// the registry's write paths.
+    pub fn iter_sessions<F: Fn(&Connection)>(&self, f: F) {
+        for r in self.sessions.iter() {
+            f(&r.value().conn);
+        }
+    }
+
+    /// Call `f` for every connection of `tenant_id`, without collecting them
+    /// first. Same locking caveat as [`iter_sessions`](Self::iter_sessions).
+    pub fn iter_for_tenant<F: FnMut(ConnectionId, &Connection)>(&self, tenant_id: &str, mut f: F) {
+        for r in self.sessions.iter() {
+            if r.value().tenant_id == tenant_id {
+                f(*r.key(), &r.value().conn);
+            }
+        }
+    }
+
+    /// Ids of the connections of `tenant_id`, e.g. to kick them one by one.
+    pub fn connection_ids_for_tenant(&self, tenant_id: &str) -> Vec<ConnectionId> {
+        let mut ids = Vec::new();
+        self.iter_for_tenant(tenant_id, |id, _| ids.push(id));
+        ids
+    }
+
+    /// Snapshot of every registered connection.
+    pub fn collect_sessions(&self) -> Vec<ConnectionSnapshot> {
+        self.snapshots(|_| true)
+    }
+
+    /// Snapshot of the connections of `tenant_id`.
+    pub fn collect_tenant_sessions(&self, tenant_id: &str) -> Vec<ConnectionSnapshot> {
+        self.snapshots(|t| t == tenant_id)
+    }
+
+    fn snapshots(&self, tenant_filter: impl Fn(&str) -> bool) -> Vec<ConnectionSnapshot> {
+        let now_unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
         self.sessions
             .iter()
-            .map(|r| (r.key().clone(), r.value().conn.clone()))
+            .filter(|r| tenant_filter(&r.value().tenant_id))
+            .map(|r| {
+                let e = r.value();
+                let age_ms = e.connected_at.elapsed().as_millis() as u64;
+                let user_id = e.user_key.get(e.tenant_id.len() + 2..).unwrap_or(&e.user_key);
+                ConnectionSnapshot {
+                    connection_id: *r.key(),
+                    user_id: user_id.to_string(),
+                    tenant: e.tenant_id.clone(),
+                    connected_at_unix_ms: now_unix_ms.saturating_sub(age_ms),
+                    queue_depth: e.conn.tx.max_capacity().saturating_sub(e.conn.tx.capacity()),
+                }
+            })
             .collect()
     }
 
+    /// Per-tenant queue depths and drops since the previous call, sorted by
+    /// tenant. Every tenant that ever had a session is reported, so depths
+    /// fall back to zero once it has none.
+    ///
+    /// Walks the map synchronously; sessions unregistered mid-walk are
+    /// either seen or have their drops retired for the next call.
+    pub fn sample_outbound(&self) -> Vec<OutboundSample> {
+        let mut by_tenant: HashMap<String, OutboundSample> = self.tenant_counts
+            .iter()
+            .map(|r| (r.key().clone(), OutboundSample { tenant: r.key().clone(), ..Default::default() }))
+            .collect();
+        for r in self.sessions.iter() {
+            let e = r.value();
+            let sample = by_tenant.entry(e.tenant_id.clone())
+                .or_insert_with(|| OutboundSample { tenant: e.tenant_id.clone(), ..Default::default() });
+            sample.depths.push(e.conn.tx.max_capacity().saturating_sub(e.conn.tx.capacity()));
+            let (lossy, reliable) = e.conn.drops.take();
+            sample.lossy_drops += lossy;
+            sample.reliable_drops += reliable;
+        }
+        for r in self.retired_drops.iter() {
+            let sample = by_tenant.entry(r.key().clone())
+                .or_insert_with(|| OutboundSample { tenant: r.key().clone(), ..Default::default() });
+            let (lossy, reliable) = r.value().take();
+            sample.lossy_drops += lossy;
+            sample.reliable_drops += reliable;
+        }
+        let mut samples: Vec<OutboundSample> = by_tenant.into_values().collect();
+        for s in &mut samples {
+            s.depths.sort_unstable();
+        }
+        samples.sort_by(|a, b| a.tenant.cmp(&b.tenant));
+        samples
+    }
+
+    /// Live session count per tenant, sorted by tenant; tenants without
+    /// sessions are left out.
+    pub fn tenant_session_counts(&self) -> Vec<(String, u64)> {
+        let mut counts: Vec<(String, u64)> = self.tenant_counts
+            .iter()
+            .map(|r| (r.key().clone(), r.value().load(Ordering::Relaxed)))
+            .filter(|(_, n)| *n > 0)
+            .collect();
+        counts.sort();
+        counts
+    }
+
+    /// Outbound queue depth of every session (of one tenant, if given),
+    /// ascending. Unlike `sample_outbound`, drop counters are left alone.
+    pub fn queue_depths(&self, tenant_id: Option<&str>) -> Vec<usize> {
+        let mut depths: Vec<usize> = self.sessions
+            .iter()
+            .filter(|r| tenant_id.is_none_or(|t| r.value().tenant_id == t))
+            .map(|r| r.value().conn.tx.max_capacity().saturating_sub(r.value().conn.tx.capacity()))
+            .collect();
+        depths.sort_unstable();
+        depths
+    }
+
     pub fn len_sessions(&self) -> usize {
         self.sessions.len()
     }
 
     /// Evict the oldest session for this user.
-    /// Returns (victim_session_key, victim_connection).
-    pub fn evict_oldest(&self, user_key: &str) -> Option<(String, Connection)> {
+    /// Returns (victim_connection_id, victim_connection).
+    pub fn evict_oldest(&self, user_key: &str) -> Option<(ConnectionId, Connection)> {
         let set = self.user_index.get(user_key)?;
-        let keys: Vec<String> = set.iter().map(|s| s.key().to_string()).collect();
+        let ids: Vec<ConnectionId> = set.iter().map(|s| *s.key()).collect();
         drop(set);
 
-        let mut victim_key: Option<String> = None;
+        let mut victim: Option<ConnectionId> = None;
         let mut victim_seq: u64 = u64::MAX;
-        for k in &keys {
-            if let Some(e) = self.sessions.get(k) {
+        for id in ids {
+            if let Some(e) = self.sessions.get(&id) {
                 if e.value().created_seq < victim_seq {
                     victim_seq = e.value().created_seq;
-                    victim_key = Some(k.clone());
+                    victim = Some(id);
                 }
             }
         }
 
-        let victim_key = victim_key?;
-        let conn = self.remove_session(user_key, &victim_key)?;
-        Some((victim_key, conn))
+        let victim = victim?;
+        let conn = self.unregister(victim)?;
+        Some((victim, conn))
     }
 }
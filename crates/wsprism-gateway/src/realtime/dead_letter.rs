@@ -0,0 +1,54 @@
+//! Dead-letter hooks for reliable deliveries that could not be completed.
+//!
+//! `publish_room_reliable` calls the configured handler once per recipient
+//! whose send timed out or whose queue was closed. Implementations could
+//! persist messages for replay; the built-ins only log or count.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::obs::metrics::GatewayMetrics;
+use crate::realtime::types::PreparedMsg;
+
+/// Receives reliable messages that were not delivered.
+///
+/// `user` and `room` are tenant-qualified keys (`tenant::user`, `tenant::room`).
+#[async_trait]
+pub trait DeadLetterHandler: Send + Sync {
+    async fn on_drop(&self, user: &str, room: &str, msg: &PreparedMsg);
+}
+
+/// Logs each dead letter at WARN.
+#[derive(Default)]
+pub struct LogDeadLetterHandler;
+
+#[async_trait]
+impl DeadLetterHandler for LogDeadLetterHandler {
+    async fn on_drop(&self, user: &str, room: &str, msg: &PreparedMsg) {
+        let (kind, len) = match msg {
+            PreparedMsg::Text(s) => ("text", s.len()),
+            PreparedMsg::Binary(b) => ("binary", b.len()),
+        };
+        tracing::warn!(%user, %room, kind, len, "reliable delivery dead-lettered");
+    }
+}
+
+/// Counts dead letters as `wsprism_dead_letters_total{tenant,room}`.
+pub struct MetricsDeadLetterHandler {
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl MetricsDeadLetterHandler {
+    pub fn new(metrics: Arc<GatewayMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+#[async_trait]
+impl DeadLetterHandler for MetricsDeadLetterHandler {
+    async fn on_drop(&self, _user: &str, room: &str, _msg: &PreparedMsg) {
+        let (tenant, room) = room.split_once("::").unwrap_or(("", room));
+        self.metrics.dead_letters.inc(&[("tenant", tenant), ("room", room)]);
+    }
+}
@@ -4,7 +4,14 @@
 //! and per-message context passed to services.
 
 pub mod core;
+pub mod dead_letter;
+pub mod relay;
 pub mod types;
 
-pub use core::{Presence, RealtimeCore, RealtimeCtx, SessionRegistry};
-pub use types::{Outgoing, Payload, PreparedMsg, QoS};
+pub use core::{
+    MembershipObserver, Presence, PresenceEvent, PresenceNotifier, RealtimeCore, RealtimeCtx, RemotePresence, RoomEvent, SessionEvent,
+    SessionEventLog, SessionHandle, SessionRegistry, UserMembershipObserver,
+};
+pub use dead_letter::{DeadLetterHandler, LogDeadLetterHandler, MetricsDeadLetterHandler};
+pub use relay::RoomRelay;
+pub use types::{Outgoing, Payload, PreparedMsg, Priority, QoS};
@@ -0,0 +1,17 @@
+//! Hook for copying room publishes to other gateway replicas.
+//!
+//! `RealtimeCore` hands every room publish (lossy or reliable) to the
+//! installed relay once it has been prepared. Messages arriving from other
+//! replicas go through [`RealtimeCore::deliver_relayed`], which never relays
+//! them again. Without a relay, publishing stays local.
+
+use crate::realtime::types::PreparedMsg;
+
+/// Forwards room publishes to other replicas.
+///
+/// `room_key` and `exclude_user_key` are tenant-qualified (`tenant::room`,
+/// `tenant::user`). Called on the publishing task, so implementations must
+/// not block: queue the message and return.
+pub trait RoomRelay: Send + Sync {
+    fn relay(&self, room_key: &str, msg: &PreparedMsg, exclude_user_key: Option<&str>);
+}
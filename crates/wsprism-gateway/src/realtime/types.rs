@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use axum::extract::ws::Message;
 use bytes::Bytes;
 use serde_json::Value;
@@ -8,18 +10,25 @@ use wsprism_core::error::{Result, WsPrismError};
 ///
 /// Chooses between latency-first (drop on backpressure) and reliability-first
 /// (await with optional timeout) behavior.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum QoS {
     /// Latency-critical: do not await; if the user's queue is full, drop.
+    #[default]
     Lossy,
     /// Reliability-critical: attempt delivery and optionally time out.
     Reliable { timeout_ms: u64 },
 }
 
-impl Default for QoS {
-    fn default() -> Self {
-        QoS::Lossy
-    }
+/// Which of a session's outbound queues a message takes.
+///
+/// `High` messages skip ahead of everything queued `Normal` and are never
+/// dropped for a full queue or the bandwidth cap; keep them for control
+/// traffic (errors, kicks, goaway).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
 }
 
 /// Outgoing payload variants.
@@ -33,17 +42,47 @@ pub enum Payload {
     Binary(Bytes),
 }
 
+impl From<Value> for Payload {
+    fn from(v: Value) -> Self {
+        Payload::TextJson(v)
+    }
+}
+
+impl From<Bytes> for Payload {
+    fn from(b: Bytes) -> Self {
+        Payload::Binary(b)
+    }
+}
+
 /// Application-level outgoing message.
 #[derive(Debug, Clone)]
 pub struct Outgoing {
     pub qos: QoS,
     pub payload: Payload,
+    pub priority: Priority,
+}
+
+/// Delivery timeout used for request-reply responses.
+pub const REPLY_TIMEOUT_MS: u64 = 1500;
+
+impl Outgoing {
+    /// Reply envelope for request-reply services (`QoS::Reliable`, 1500 ms).
+    pub fn reply(v: Value) -> Self {
+        Self {
+            qos: QoS::Reliable { timeout_ms: REPLY_TIMEOUT_MS },
+            payload: Payload::TextJson(v),
+            priority: Priority::Normal,
+        }
+    }
 }
 
 /// Prepared message cached for broadcasting (serialize once, send N times).
+///
+/// Both variants are cheap to clone (`Arc<str>` / `Bytes`), so a prepared
+/// message can be shared across fan-out tasks without copying the body.
 #[derive(Debug, Clone)]
 pub enum PreparedMsg {
-    Text(String),
+    Text(Arc<str>),
     Binary(Bytes),
 }
 
@@ -53,24 +92,41 @@ impl PreparedMsg {
             Payload::TextJson(v) => {
                 let s = serde_json::to_string(v)
                     .map_err(|e| WsPrismError::BadRequest(format!("json encode failed: {e}")))?;
-                Ok(PreparedMsg::Text(s))
+                Ok(PreparedMsg::Text(Arc::from(s)))
             }
             Payload::Utf8Bytes(b) => {
                 // validate UTF-8 once; send as Text
                 let s = std::str::from_utf8(b)
-                    .map_err(|e| WsPrismError::BadRequest(format!("utf8 invalid: {e}")))?
-                    .to_owned();
-                Ok(PreparedMsg::Text(s))
+                    .map_err(|e| WsPrismError::BadRequest(format!("utf8 invalid: {e}")))?;
+                Ok(PreparedMsg::Text(Arc::from(s)))
             }
             Payload::Binary(b) => Ok(PreparedMsg::Binary(b.clone())),
         }
     }
 
+    /// Body size in bytes.
+    pub fn len(&self) -> usize {
+        match self {
+            PreparedMsg::Text(s) => s.len(),
+            PreparedMsg::Binary(b) => b.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Wrap already-shared binary bytes without copying.
+    pub fn from_shared_bytes(b: Bytes) -> Self {
+        PreparedMsg::Binary(b)
+    }
+
     /// Convert to axum::ws::Message for transport.
-    /// NOTE: axum::Message::Binary requires `Vec<u8>`, so Binary path clones into Vec.
+    /// NOTE: axum 0.7 `Message` owns `String`/`Vec<u8>`, so each recipient
+    /// still costs one copy here; the shared body is never cloned before that.
     pub fn to_ws_message(&self) -> Message {
         match self {
-            PreparedMsg::Text(s) => Message::Text(s.clone()),
+            PreparedMsg::Text(s) => Message::Text(s.to_string()),
             PreparedMsg::Binary(b) => Message::Binary(b.to_vec()),
         }
     }
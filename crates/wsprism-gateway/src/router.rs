@@ -2,19 +2,74 @@
 //!
 //! Exposes:
 //! - `/v1/ws`    : WebSocket upgrade
+//! - `/v1/publish` : room publish for backend services (tenant `api_key`)
+//! - `/v1/users/:user/send` : send to one user's sessions (tenant `api_key`)
+//! - `/v1/poll`, `/v1/poll/send` : long-polling fallback (tenant `long_poll`)
 //! - `/healthz`  : liveness
 //! - `/readyz`   : readiness
+//! - `/v1/capabilities` : registered services and compiled-in features
 //! - `/metrics`  : Prometheus metrics
+//! - `/admin/v1/broadcast` : server-wide announcement (admin token)
+//! - `/admin/v1/sessions`  : live connection listing (admin token)
+//! - `/admin/v1/sessions/:id/events` : one connection's lifecycle events (admin token)
+//! - `/admin/v1/reload`    : hot-reload tenant policies (admin token)
+//! - `/admin/v1/stats`     : session count and quota counters (admin token)
+//! - `/admin/v1/handshake/top` : most throttled handshake IPs (admin token)
+//!
+//! `gateway.cors` applies to `/v1/ws` only. With `ops.auth_token`, `/metrics`
+//! and `/admin/*` require a bearer token (see [`ops::auth`]).
 
-use axum::{routing::get, Router};
+use axum::http::{HeaderValue, Method};
+use axum::{middleware, routing::{get, post}, Router};
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
 
+use crate::config::CorsConfig;
 use crate::{app_state::AppState, ops, transport};
 
 pub fn build_router(state: AppState) -> Router {
+    let mut ws = get(transport::ws::ws_upgrade);
+    if let Some(cors) = cors_layer(&state.cfg().gateway.cors) {
+        ws = ws.layer(cors);
+    }
+    let mut protected = Router::new()
+        .route("/metrics", get(ops::metrics))
+        .route("/admin/v1/broadcast", post(ops::admin::broadcast))
+        .route("/admin/v1/sessions", get(ops::admin::sessions))
+        .route("/admin/v1/sessions/:id/events", get(ops::admin::session_events))
+        .route("/admin/v1/reload", post(ops::admin::reload))
+        .route("/admin/v1/stats", get(ops::admin::stats))
+        .route("/admin/v1/handshake/top", get(ops::admin::handshake_top));
+    if state.ops_auth().is_enabled() {
+        protected = protected.route_layer(middleware::from_fn_with_state(state.clone(), ops::auth::require_ops_auth));
+    }
     Router::new()
-        .route("/v1/ws", get(transport::ws::ws_upgrade))
+        .route("/v1/ws", ws)
+        .route("/v1/publish", post(transport::publish::publish))
+        .route("/v1/users/:user/send", post(transport::publish::send_to_user))
+        .route("/v1/poll", get(transport::poll::poll))
+        .route("/v1/poll/send", post(transport::poll::send))
         .route("/healthz", get(ops::healthz))
         .route("/readyz", get(ops::readyz))
-        .route("/metrics", get(ops::metrics))
+        .route("/v1/capabilities", get(ops::capabilities))
+        .merge(protected)
         .with_state(state)
 }
+
+/// Layer for a validated `CorsConfig`; `None` when no origins are allowed.
+fn cors_layer(cfg: &CorsConfig) -> Option<CorsLayer> {
+    if !cfg.is_enabled() {
+        return None;
+    }
+    let origin = if cfg.allows_any_origin() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(cfg.allowed_origins.iter().filter_map(|o| HeaderValue::from_str(o).ok()))
+    };
+    Some(
+        CorsLayer::new()
+            .allow_origin(origin)
+            .allow_methods([Method::GET, Method::OPTIONS])
+            .allow_headers(AllowHeaders::mirror_request())
+            .allow_credentials(cfg.allow_credentials),
+    )
+}
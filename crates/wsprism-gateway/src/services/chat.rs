@@ -6,7 +6,7 @@ use wsprism_core::error::{Result, WsPrismError};
 use wsprism_core::protocol::text::Envelope;
 
 use crate::dispatch::TextService;
-use crate::realtime::{Outgoing, Payload, QoS, RealtimeCtx};
+use crate::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCtx};
 
 #[derive(Default)]
 /// Built-in text service for chat messaging on the Ext lane.
@@ -29,7 +29,7 @@ impl TextService for ChatService {
         "chat"
     }
 
-    async fn handle(&self, ctx: RealtimeCtx, env: Envelope) -> Result<()> {
+    async fn handle(&self, ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
         match env.msg_type.as_str() {
             "send" => {
                 let room = env
@@ -37,13 +37,10 @@ impl TextService for ChatService {
                     .clone()
                     .ok_or_else(|| WsPrismError::BadRequest("chat.send requires room".into()))?;
 
-                let raw = env
-                    .data
-                    .as_ref()
-                    .ok_or_else(|| WsPrismError::BadRequest("chat.send requires data".into()))?;
+                let req: SendReq = env.data_as()?;
 
-                let req: SendReq = serde_json::from_str(raw.get())
-                    .map_err(|e| WsPrismError::BadRequest(format!("chat.send invalid data: {e}")))?;
+                let claims = ctx.claims();
+                let from = claims.display_name().unwrap_or(ctx.user());
 
                 let out = Outgoing {
                     qos: QoS::Reliable { timeout_ms: 1500 },
@@ -52,12 +49,15 @@ impl TextService for ChatService {
                         "svc": "chat",
                         "type": "msg",
                         "room": room,
-                        "data": { "from": ctx.user(), "msg": req.msg }
+                        "data": { "from": from, "msg": req.msg }
                     })),
+                    priority: Priority::Normal,
                 };
 
                 // ✅ room은 이미 있으니 그대로 사용
-                ctx.publish_room_reliable(&room, out).await
+                // Fan-out is the response; nothing extra for the sender.
+                ctx.publish_room_reliable(&room, out).await?;
+                Ok(None)
             }
             _ => Err(WsPrismError::BadRequest("unknown chat type".into())),
         }
@@ -4,7 +4,7 @@ use wsprism_core::error::Result;
 use wsprism_core::protocol::hot::HotFrame;
 
 use crate::dispatch::BinaryService;
-use crate::realtime::{Outgoing, Payload, QoS, RealtimeCtx};
+use crate::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCtx};
 
 /// Echo binary frames back to the active room or the current session.
 ///
@@ -29,6 +29,7 @@ impl BinaryService for EchoBinaryService {
         let out = Outgoing {
             qos: QoS::Lossy,
             payload: Payload::Binary(frame.payload.clone()),
+            priority: Priority::Normal,
         };
 
         if let Some(room) = ctx.active_room() {
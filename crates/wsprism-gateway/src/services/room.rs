@@ -0,0 +1,101 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::text::Envelope;
+
+use crate::dispatch::TextService;
+use crate::realtime::{Outgoing, RealtimeCtx, SessionHandle};
+
+#[derive(Default)]
+/// Built-in text service for room membership (`room:join`, `room:move`,
+/// `room:leave`, `room:list`, `room:info`).
+///
+/// Join, move and leave update presence and the session's active room,
+/// which the Hot Lane routes to. Replies go to the calling session only.
+pub struct RoomService;
+
+impl RoomService {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Optional `room:join` data.
+#[derive(Debug, Deserialize)]
+struct JoinReq {
+    /// Capacity of the room, if this join creates it.
+    capacity: Option<u64>,
+}
+
+/// Reply to the calling session; `send_to_self` would reach the user's
+/// other sessions too.
+fn reply(ctx: &RealtimeCtx, mut frame: Value) -> Result<Option<Outgoing>> {
+    frame["trace_id"] = Value::from(&*ctx.trace_id);
+    ctx.send_to_session(Outgoing::reply(frame))?;
+    Ok(None)
+}
+
+#[async_trait]
+impl TextService for RoomService {
+    fn svc(&self) -> &'static str {
+        "room"
+    }
+
+    async fn handle(&self, ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
+        let session: SessionHandle = ctx
+            .session()
+            .cloned()
+            .ok_or_else(|| WsPrismError::BadRequest("room commands require a client session".into()))?;
+        match env.msg_type.as_str() {
+            "join" => {
+                // Join data used to be opaque; only an object is read.
+                let capacity = match env.data.as_deref() {
+                    Some(raw) if raw.get().starts_with('{') => env.data_as::<JoinReq>()?.capacity,
+                    _ => None,
+                };
+                if capacity == Some(0) {
+                    return Err(WsPrismError::BadRequest("room:join capacity must be > 0".into()));
+                }
+                let room = env.room.unwrap_or_else(|| "default".to_string());
+                ctx.join_room_with_capacity(&room, session.limits(), capacity)?;
+                session.set_active_room(Some(&room));
+                reply(&ctx, json!({ "v": 1, "svc": "sys", "type": "joined", "room": room }))?;
+                // After `sys.joined`, on the same queue.
+                ctx.replay_room(&room);
+                Ok(None)
+            }
+            "move" => {
+                let from = session
+                    .active_room()
+                    .ok_or_else(|| WsPrismError::BadRequest("no active room to move from".into()))?;
+                let to = env
+                    .room
+                    .ok_or_else(|| WsPrismError::BadRequest("room:move requires a target room".into()))?;
+                ctx.move_room_with_limits(&from, &to, session.limits())?;
+                session.set_active_room(Some(&to));
+                reply(&ctx, json!({ "v": 1, "svc": "sys", "type": "moved", "from": *from, "to": to }))
+            }
+            "leave" => {
+                if let Some(room) = session.take_active_room() {
+                    ctx.leave_room(&room);
+                }
+                reply(&ctx, json!({ "v": 1, "svc": "sys", "type": "left" }))
+            }
+            "list" => {
+                let rooms = ctx.session_rooms();
+                reply(&ctx, json!({ "v": 1, "svc": "sys", "type": "rooms", "data": { "rooms": rooms } }))
+            }
+            "info" => {
+                let room = env
+                    .room
+                    .or_else(|| ctx.active_room().map(str::to_string))
+                    .ok_or_else(|| WsPrismError::BadRequest("room:info requires a room".into()))?;
+                let members = ctx.room_member_count(&room);
+                reply(&ctx, json!({ "v": 1, "svc": "sys", "type": "room_info", "room": room, "data": { "members": members } }))
+            }
+            _ => Err(WsPrismError::BadRequest("unknown room type".into())),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Per-session outbound bandwidth cap (`policy.max_outbound_bytes_per_sec`).
+//!
+//! A byte-denominated [`TokenBucket`] checked by the realtime core before a
+//! message is queued for the session. Lossy sends are dropped when the bucket
+//! is empty; reliable sends wait for tokens within their timeout.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::policy::rate::TokenBucket;
+
+/// Outbound byte budget of one session.
+#[derive(Debug)]
+pub struct BandwidthMeter {
+    tenant: Arc<str>,
+    bucket: TokenBucket,
+}
+
+impl BandwidthMeter {
+    /// `bytes_per_sec` refill with one second's worth (or `burst_bytes`, if
+    /// larger) as capacity. Both saturate at `u32::MAX`.
+    pub fn new(tenant: impl Into<Arc<str>>, bytes_per_sec: u64, burst_bytes: u64) -> Self {
+        let rate = u32::try_from(bytes_per_sec).unwrap_or(u32::MAX);
+        let burst = u32::try_from(burst_bytes.max(bytes_per_sec)).unwrap_or(u32::MAX);
+        Self { tenant: tenant.into(), bucket: TokenBucket::new(rate, burst) }
+    }
+
+    pub fn tenant(&self) -> &str {
+        &self.tenant
+    }
+
+    pub fn bytes_per_sec(&self) -> u32 {
+        self.bucket.rate()
+    }
+
+    /// Frames above capacity only need a full bucket, so they are delayed
+    /// rather than refused forever.
+    fn cost(&self, bytes: usize) -> u32 {
+        u32::try_from(bytes).unwrap_or(u32::MAX).min(self.bucket.capacity())
+    }
+
+    /// Debit `bytes` now. Err carries the wait in milliseconds.
+    pub fn try_consume(&self, bytes: usize) -> Result<(), u64> {
+        self.bucket.try_acquire(self.cost(bytes))
+    }
+
+    /// Debit `bytes`, sleeping until enough tokens accrue. Gives up (false)
+    /// when the wait would pass `deadline`; `None` waits indefinitely.
+    pub async fn consume_until(&self, bytes: usize, deadline: Option<Instant>) -> bool {
+        loop {
+            let wait_ms = match self.try_consume(bytes) {
+                Ok(()) => return true,
+                Err(ms) => ms,
+            };
+            let wake = Instant::now() + Duration::from_millis(wait_ms);
+            if deadline.is_some_and(|d| wake > d) {
+                return false;
+            }
+            tokio::time::sleep_until(wake).await;
+        }
+    }
+}
@@ -1,12 +1,13 @@
 //! Decode-once codec for the transport layer.
 //!
-//! - Text frames => Envelope (lazy `RawValue` for data)
+//! - Text frames => Envelope (lazy `RawValue` for data; minimal
+//!   `{"svc","type"}` envelopes are accepted, `v` must be 1)
 //! - Binary frames => HotFrame (panic-free bytes::Buf parsing)
 //! - Ping/Pong/Close are surfaced for lifecycle management
 
 use axum::extract::ws::Message;
 use wsprism_core::{
-    error::{Result, WsPrismError},
+    error::Result,
     protocol::{hot, text},
 };
 
@@ -24,17 +25,19 @@ pub enum Inbound {
     Close,
 }
 
-pub fn decode(msg: Message) -> Result<Inbound> {
+/// Decode one inbound message. Hot frames whose payload exceeds
+/// `max_frame_bytes` fail with `PayloadTooLarge` before it is split off.
+pub fn decode(msg: Message, max_frame_bytes: usize) -> Result<Inbound> {
     match msg {
         Message::Text(s) => {
-            let bytes_len = s.as_bytes().len();
-            let env: text::Envelope = serde_json::from_str(&s)
-                .map_err(|e| WsPrismError::BadRequest(format!("invalid envelope json: {e}")))?;
+            let bytes_len = s.len();
+            let env: text::Envelope = serde_json::from_str(&s)?;
+            env.validate()?;
             Ok(Inbound::Text { env, bytes_len })
         }
         Message::Binary(b) => {
             let bytes_len = b.len();
-            let frame = hot::decode_hot_frame(bytes::Bytes::from(b))?;
+            let frame = hot::decode_hot_frame_with_limit(bytes::Bytes::from(b), max_frame_bytes)?;
             Ok(Inbound::Hot { frame, bytes_len })
         }
         Message::Ping(v) => Ok(Inbound::Ping(v)),
@@ -2,20 +2,43 @@
 //!
 //! Purpose:
 //! - Stop abuse *before* WebSocket upgrade.
-//! - Per-IP + global leaky-bucket limiter.
+//! - Per-IP + global limiter: leaky bucket (default) or sliding window
+//!   (`handshake_limit.limiter_kind`).
 //! - Returns HTTP 429 with Retry-After header hint.
+//! - Counts checks and rejections (by scope) into `GatewayMetrics`, and keeps
+//!   per-IP rejection counts for the admin "top throttled" listing.
 //! - Note: cleanup is probabilistic and inline; under extreme IP churn it can
 //!   briefly block the caller. A background cleaner is preferable for very high churn.
+//! - Leaky-bucket state can be saved on shutdown and restored on startup
+//!   (`handshake_limit.state_path`), so a restart is not a free burst.
 
+use std::collections::{HashMap, VecDeque};
+use std::io;
 use std::net::IpAddr;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
-use crate::config::schema::HandshakeConfig;
+use wsprism_core::error::{Result as WsResult, WsPrismError};
+use crate::config::schema::{HandshakeConfig, HandshakeLimiterKind};
+use crate::obs::metrics::{GatewayMetrics, HandshakeScope};
+
+/// Window over which per-IP rejections are counted for `top_throttled`.
+pub const THROTTLE_WINDOW: Duration = Duration::from_secs(60);
+
+fn unix_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
 
 /// Simple leaky bucket (capacity/refill, best-effort).
-#[derive(Debug)]
+///
+/// Serializes as [`LeakyBucketState`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "LeakyBucketState", from = "LeakyBucketState")]
 pub struct LeakyBucket {
     capacity: u32,
     tokens: f64,
@@ -34,6 +57,20 @@ impl LeakyBucket {
         }
     }
 
+    /// Take over the tokens and last refill time of `state`, keeping this
+    /// bucket's capacity and rate.
+    pub fn with_state(mut self, state: &LeakyBucketState) -> Self {
+        self.tokens = state.tokens.clamp(0.0, self.capacity as f64);
+        self.last = instant_at(state.last_unix_ms);
+        self
+    }
+
+    /// Tokens available right now.
+    pub fn tokens(&mut self) -> f64 {
+        self.refill();
+        self.tokens
+    }
+
     fn refill(&mut self) {
         let now = Instant::now();
         let elapsed = now.duration_since(self.last).as_secs_f64();
@@ -41,12 +78,12 @@ impl LeakyBucket {
         self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity as f64);
     }
 
-    /// Consume `cost` tokens. Returns Ok if allowed, Err with retry_after seconds (ceil).
-    pub fn try_take(&mut self, cost: u32) -> Result<(), u64> {
+    /// Check whether `cost` tokens are available without consuming them.
+    /// Refills first. Err carries retry_after seconds (ceil).
+    pub fn peek(&mut self, cost: u32) -> Result<(), u64> {
         self.refill();
         let c = cost.max(1) as f64;
         if self.tokens >= c {
-            self.tokens -= c;
             Ok(())
         } else {
             let missing = c - self.tokens;
@@ -54,6 +91,183 @@ impl LeakyBucket {
             Err(wait.max(1.0) as u64) // Retry-After min 1
         }
     }
+
+    /// Consume `cost` tokens. Returns Ok if allowed, Err with retry_after seconds (ceil).
+    pub fn try_take(&mut self, cost: u32) -> Result<(), u64> {
+        self.peek(cost)?;
+        self.tokens -= cost.max(1) as f64;
+        Ok(())
+    }
+}
+
+/// Persisted form of a [`LeakyBucket`]. The last refill is wall-clock time,
+/// so the time the gateway was down refills the bucket on restore.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LeakyBucketState {
+    pub capacity: u32,
+    pub tokens: f64,
+    pub refill_per_sec: f64,
+    pub last_unix_ms: u64,
+}
+
+impl From<LeakyBucket> for LeakyBucketState {
+    fn from(b: LeakyBucket) -> Self {
+        let ago = b.last.elapsed().as_millis() as u64;
+        Self {
+            capacity: b.capacity,
+            tokens: b.tokens,
+            refill_per_sec: b.refill_per_sec,
+            last_unix_ms: unix_ms().saturating_sub(ago),
+        }
+    }
+}
+
+impl From<LeakyBucketState> for LeakyBucket {
+    fn from(s: LeakyBucketState) -> Self {
+        let capacity = s.capacity.max(1);
+        Self {
+            capacity,
+            tokens: s.tokens.clamp(0.0, capacity as f64),
+            refill_per_sec: s.refill_per_sec.max(1.0),
+            last: instant_at(s.last_unix_ms),
+        }
+    }
+}
+
+/// The `Instant` corresponding to `unix_ms` (now, if it is in the future or
+/// before the monotonic clock's origin).
+fn instant_at(unix_ms_then: u64) -> Instant {
+    let ago = Duration::from_millis(unix_ms().saturating_sub(unix_ms_then));
+    Instant::now().checked_sub(ago).unwrap_or_else(Instant::now)
+}
+
+/// Sliding window limiter: at most `limit` takes within any `window`.
+///
+/// Unlike [`LeakyBucket`], which starts full, it never admits more than
+/// `limit` per window, including right after startup.
+#[derive(Debug)]
+pub struct SlidingWindowHandshakeGuard {
+    limit: u32,
+    window: Duration,
+    hits: VecDeque<Instant>,
+}
+
+impl SlidingWindowHandshakeGuard {
+    pub fn new(limit: u32, window_ms: u64) -> Self {
+        Self {
+            limit: limit.max(1),
+            window: Duration::from_millis(window_ms.max(1)),
+            hits: VecDeque::new(),
+        }
+    }
+
+    fn evict(&mut self, now: Instant) {
+        while self.hits.front().is_some_and(|t| now.duration_since(*t) >= self.window) {
+            self.hits.pop_front();
+        }
+    }
+
+    /// Check whether `cost` takes fit in the window without recording them.
+    /// Err carries retry_after seconds (ceil): until the oldest take leaves
+    /// the window.
+    pub fn peek(&mut self, cost: u32) -> Result<(), u64> {
+        let now = Instant::now();
+        self.evict(now);
+        if self.hits.len() + cost.max(1) as usize <= self.limit as usize {
+            return Ok(());
+        }
+        let wait = match self.hits.front() {
+            Some(oldest) => (*oldest + self.window).saturating_duration_since(now),
+            None => self.window,
+        };
+        Err(wait.as_millis().div_ceil(1000).max(1) as u64)
+    }
+
+    /// Record `cost` takes. Returns Ok if allowed, Err with retry_after seconds (ceil).
+    pub fn try_take(&mut self, cost: u32) -> Result<(), u64> {
+        self.peek(cost)?;
+        let now = Instant::now();
+        self.hits.extend(std::iter::repeat_n(now, cost.max(1) as usize));
+        Ok(())
+    }
+}
+
+/// Limiter selected by `handshake_limit.limiter_kind`.
+#[derive(Debug)]
+pub enum HandshakeLimiter {
+    LeakyBucket(LeakyBucket),
+    SlidingWindow(SlidingWindowHandshakeGuard),
+}
+
+impl HandshakeLimiter {
+    /// `burst`/`rps` for a leaky bucket; `rps * window_ms / 1000` per window
+    /// for a sliding window.
+    pub fn new(kind: HandshakeLimiterKind, burst: u32, rps: u32, window_ms: u64) -> Self {
+        match kind {
+            HandshakeLimiterKind::LeakyBucket => Self::LeakyBucket(LeakyBucket::new(burst, rps)),
+            HandshakeLimiterKind::SlidingWindow => {
+                let limit = (u64::from(rps) * window_ms / 1000).min(u64::from(u32::MAX)) as u32;
+                Self::SlidingWindow(SlidingWindowHandshakeGuard::new(limit, window_ms))
+            }
+        }
+    }
+
+    pub fn peek(&mut self, cost: u32) -> Result<(), u64> {
+        match self {
+            Self::LeakyBucket(b) => b.peek(cost),
+            Self::SlidingWindow(w) => w.peek(cost),
+        }
+    }
+
+    pub fn try_take(&mut self, cost: u32) -> Result<(), u64> {
+        match self {
+            Self::LeakyBucket(b) => b.try_take(cost),
+            Self::SlidingWindow(w) => w.try_take(cost),
+        }
+    }
+}
+
+/// One IP's limiter plus its rejections in the current `THROTTLE_WINDOW`.
+#[derive(Debug)]
+struct IpEntry {
+    limiter: Mutex<HandshakeLimiter>,
+    rejected: AtomicU64,
+    window_start_ms: AtomicU64,
+}
+
+impl IpEntry {
+    fn new(limiter: HandshakeLimiter) -> Self {
+        Self { limiter: Mutex::new(limiter), rejected: AtomicU64::new(0), window_start_ms: AtomicU64::new(0) }
+    }
+
+    /// Count a rejection, starting a new window if the last one expired.
+    fn note_rejected(&self, now_ms: u64) {
+        let start = self.window_start_ms.load(Ordering::Relaxed);
+        if now_ms.saturating_sub(start) >= THROTTLE_WINDOW.as_millis() as u64 {
+            self.window_start_ms.store(now_ms, Ordering::Relaxed);
+            self.rejected.store(1, Ordering::Relaxed);
+        } else {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Rejections in the window still open at `now_ms` (0 if it expired).
+    fn rejected_at(&self, now_ms: u64) -> u64 {
+        let start = self.window_start_ms.load(Ordering::Relaxed);
+        if now_ms.saturating_sub(start) >= THROTTLE_WINDOW.as_millis() as u64 {
+            0
+        } else {
+            self.rejected.load(Ordering::Relaxed)
+        }
+    }
+}
+
+/// Persisted [`HandshakeDefender`] state: the leaky buckets that are not
+/// full. Sliding-window limiters are not saved.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandshakeDefenderState {
+    pub global: Option<LeakyBucketState>,
+    pub per_ip: HashMap<IpAddr, LeakyBucketState>,
 }
 
 /// A lightweight in-memory handshake rate limiter.
@@ -62,22 +276,148 @@ impl LeakyBucket {
 /// when `per_ip` grows large. That cleanup can briefly lock shards. For strict
 /// latency guarantees, move cleanup to a background task instead of running
 /// inline with request handling.
-#[derive(Debug)]
 pub struct HandshakeDefender {
     cfg: HandshakeConfig,
-    global: Mutex<LeakyBucket>,
-    per_ip: DashMap<IpAddr, Mutex<LeakyBucket>>,
+    global: Mutex<HandshakeLimiter>,
+    per_ip: DashMap<IpAddr, Arc<IpEntry>>,
+    metrics: Option<Arc<GatewayMetrics>>,
 }
 
 impl HandshakeDefender {
     pub fn new(cfg: HandshakeConfig) -> Self {
         Self {
-            global: Mutex::new(LeakyBucket::new(cfg.global_burst, cfg.global_rps)),
+            global: Mutex::new(HandshakeLimiter::new(
+                cfg.limiter_kind,
+                cfg.global_burst,
+                cfg.global_rps,
+                cfg.window_ms,
+            )),
             per_ip: DashMap::new(),
+            metrics: None,
             cfg,
         }
     }
 
+    /// Restore from `cfg.state_path` when that file exists and is younger
+    /// than `cfg.state_ttl_secs`; otherwise start with full buckets.
+    pub fn from_config(cfg: HandshakeConfig) -> Self {
+        let Some(path) = cfg.state_path.clone() else { return Self::new(cfg) };
+        let path = Path::new(&path);
+        let age = std::fs::metadata(path).and_then(|m| m.modified()).map(|t| t.elapsed().unwrap_or_default());
+        match age {
+            Ok(age) if age.as_secs() <= cfg.state_ttl_secs => match Self::load_from_path(cfg.clone(), path) {
+                Ok(d) => {
+                    tracing::info!(path = %path.display(), ips = d.per_ip.len(), "handshake limiter state restored");
+                    d
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "handshake limiter state not restored");
+                    Self::new(cfg)
+                }
+            },
+            Ok(_) => {
+                tracing::info!(path = %path.display(), "handshake limiter state is stale; ignored");
+                Self::new(cfg)
+            }
+            Err(_) => Self::new(cfg),
+        }
+    }
+
+    /// Build a defender from `cfg` and the state saved at `path`. Saved
+    /// tokens are capped at the configured bursts; with
+    /// `limiter_kind: sliding_window` the state is ignored.
+    pub fn load_from_path(cfg: HandshakeConfig, path: &Path) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let state: HandshakeDefenderState =
+            serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::with_state(cfg, &state))
+    }
+
+    fn with_state(cfg: HandshakeConfig, state: &HandshakeDefenderState) -> Self {
+        let mut d = Self::new(cfg);
+        if d.cfg.limiter_kind != HandshakeLimiterKind::LeakyBucket {
+            return d;
+        }
+        let bucket = |burst, rps, s: &LeakyBucketState| {
+            HandshakeLimiter::LeakyBucket(LeakyBucket::new(burst, rps).with_state(s))
+        };
+        if let Some(g) = &state.global {
+            d.global = Mutex::new(bucket(d.cfg.global_burst, d.cfg.global_rps, g));
+        }
+        for (ip, s) in &state.per_ip {
+            d.per_ip.insert(*ip, Arc::new(IpEntry::new(bucket(d.cfg.per_ip_burst, d.cfg.per_ip_rps, s))));
+        }
+        d
+    }
+
+    /// Current leaky-bucket state, omitting full buckets (a fresh bucket is
+    /// the same).
+    pub async fn state(&self) -> HandshakeDefenderState {
+        let snapshot = |limiter: &mut HandshakeLimiter| match limiter {
+            HandshakeLimiter::LeakyBucket(b) => (b.tokens() < b.capacity as f64).then(|| LeakyBucketState::from(b.clone())),
+            HandshakeLimiter::SlidingWindow(_) => None,
+        };
+        let global = snapshot(&mut *self.global.lock().await);
+        // Collected first so no map shard stays locked across `.await`.
+        let entries: Vec<(IpAddr, Arc<IpEntry>)> = self.per_ip.iter().map(|r| (*r.key(), r.value().clone())).collect();
+        let mut per_ip = HashMap::new();
+        for (ip, entry) in entries {
+            if let Some(s) = snapshot(&mut *entry.limiter.lock().await) {
+                per_ip.insert(ip, s);
+            }
+        }
+        HandshakeDefenderState { global, per_ip }
+    }
+
+    /// Write [`state`](Self::state) to `path` as JSON (via a temporary file
+    /// and rename, so a crash mid-write leaves the old file).
+    pub async fn save_to_path(&self, path: &Path) -> WsResult<()> {
+        let state = self.state().await;
+        let io_err = |e: io::Error| WsPrismError::Internal(format!("handshake state {}: {e}", path.display()));
+        let json = serde_json::to_vec(&state).map_err(|e| WsPrismError::Internal(format!("handshake state: {e}")))?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, json).map_err(io_err)?;
+        std::fs::rename(&tmp, path).map_err(io_err)
+    }
+
+    /// Save to `handshake_limit.state_path`, if set, logging failures.
+    pub async fn save_state(&self) {
+        let Some(path) = &self.cfg.state_path else { return };
+        match self.save_to_path(Path::new(path)).await {
+            Ok(()) => tracing::info!(path = %path, "handshake limiter state saved"),
+            Err(e) => tracing::warn!(error = %e, "handshake limiter state not saved"),
+        }
+    }
+
+    /// Count checks, rejections and the per-IP map size into `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<GatewayMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    fn note_limited(&self, scope: HandshakeScope, entry: &IpEntry) {
+        entry.note_rejected(unix_ms());
+        if let Some(m) = &self.metrics {
+            m.handshake.note_limited(scope);
+        }
+    }
+
+    /// Up to `n` IPs with the most rejections in the current
+    /// `THROTTLE_WINDOW`, most throttled first.
+    pub fn top_throttled(&self, n: usize) -> Vec<(IpAddr, u64)> {
+        let now_ms = unix_ms();
+        let mut top: Vec<(IpAddr, u64)> = self
+            .per_ip
+            .iter()
+            .map(|r| (*r.key(), r.value().rejected_at(now_ms)))
+            .filter(|(_, rejected)| *rejected > 0)
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        top.truncate(n);
+        top
+    }
+
     pub fn enabled(&self) -> bool {
         self.cfg.enabled
     }
@@ -88,28 +428,52 @@ impl HandshakeDefender {
         if !self.cfg.enabled {
             return Ok(());
         }
-
-        // 1) Global
-        {
-            let mut g = self.global.lock().await;
-            if let Err(ra) = g.try_take(1) {
-                return Err(ra);
-            }
+        if let Some(m) = &self.metrics {
+            m.handshake.note_check();
         }
 
-        // 2) Per-IP
-        let entry = self.per_ip.entry(ip).or_insert_with(|| {
-            Mutex::new(LeakyBucket::new(self.cfg.per_ip_burst, self.cfg.per_ip_rps))
-        });
+        // Clone the bucket out so no map shard stays locked across `.await`.
+        let ip_entry = self
+            .per_ip
+            .entry(ip)
+            .or_insert_with(|| {
+                let c = &self.cfg;
+                Arc::new(IpEntry::new(HandshakeLimiter::new(c.limiter_kind, c.per_ip_burst, c.per_ip_rps, c.window_ms)))
+            })
+            .value()
+            .clone();
+
+        // Two-phase: peek global + per-IP, then take from both under the same
+        // locks (always global first). A per-IP reject no longer costs the
+        // global bucket a token.
         {
-            let mut b = entry.value().lock().await;
-            if let Err(ra) = b.try_take(1) {
-                return Err(ra);
+            let mut g = self.global.lock().await;
+            let mut b = ip_entry.limiter.lock().await;
+            match (g.peek(1), b.peek(1)) {
+                (Ok(()), Ok(())) => {}
+                (Err(a), Err(c)) => {
+                    self.note_limited(HandshakeScope::Global, &ip_entry);
+                    return Err(a.max(c));
+                }
+                (Err(a), Ok(())) => {
+                    self.note_limited(HandshakeScope::Global, &ip_entry);
+                    return Err(a);
+                }
+                (Ok(()), Err(a)) => {
+                    self.note_limited(HandshakeScope::PerIp, &ip_entry);
+                    return Err(a);
+                }
             }
+            g.try_take(1)?;
+            b.try_take(1)?;
         }
 
         // Best-effort size control (Lazy Cleanup)
-        if self.per_ip.len() > self.cfg.max_ip_entries {
+        let ip_entries = self.per_ip.len();
+        if let Some(m) = &self.metrics {
+            m.handshake.set_ip_entries(ip_entries);
+        }
+        if ip_entries > self.cfg.max_ip_entries {
             // "Pseudo-random" eviction without external crate dependency.
             // Use nanoseconds from system time as a seed.
             let nanos = SystemTime::now()
@@ -127,9 +491,13 @@ impl HandshakeDefender {
                 // Or just:
                 self.per_ip.retain(|_, _| {
                     let n = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
-                    n % 10 != 0 // Drop ~10%
+                    !n.is_multiple_of(10) // Drop ~10%
                 });
-                tracing::warn!(len = self.per_ip.len(), "handshake defender ip map trimmed");
+                let len = self.per_ip.len();
+                if let Some(m) = &self.metrics {
+                    m.handshake.set_ip_entries(len);
+                }
+                tracing::warn!(len, "handshake defender ip map trimmed");
             }
         }
 
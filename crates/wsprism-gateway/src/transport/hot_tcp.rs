@@ -0,0 +1,356 @@
+//! Raw TCP listener for the Hot Lane (`hot_tcp`).
+//!
+//! For trusted backends (e.g. a game server in the same VPC) that do not
+//! need WebSocket framing. Every frame in either direction is a `u32`
+//! little-endian length followed by that many bytes:
+//!
+//! 1. The client's first frame authenticates the connection:
+//!    `{"api_key": "<tenant api_key>", "room": "<optional active room>"}`.
+//!    The server answers `{"tenant": "...", "connection_id": "..."}`, or
+//!    closes the connection without a reply.
+//! 2. Then each frame is one Hot Lane frame, handled like a WS binary
+//!    message: same policy checks and Hot services, as the user
+//!    [`BACKEND_USER`]. Binary messages routed to the connection are sent
+//!    back as frames; text messages (`sys.*`, Ext lane JSON) are not.
+//! 3. An empty frame is a keepalive. The server sends one every
+//!    `gateway.ping_interval_ms` and closes connections silent for
+//!    `gateway.idle_timeout_ms`.
+//!
+//! A frame longer than the tenant's `max_frame_bytes` closes the connection.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::Message;
+use bytes::{Buf, Bytes, BytesMut};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Instant};
+use tracing::Instrument;
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::hot::decode_hot_frame_with_limit;
+
+use crate::app_state::AppState;
+use crate::config::TenantConfig;
+use crate::context::{ConnectionId, SessionClaims};
+use crate::obs::metrics::{FrameDir, GatewayMetrics};
+use crate::ops::auth::token_matches;
+use crate::plugin::PluginCtx;
+use crate::policy::engine::{DecisionReason, Lane, PolicyDecision};
+use crate::realtime::core::Connection;
+use crate::realtime::{RealtimeCore, RealtimeCtx};
+use crate::transport::ws::{gen_trace, UNKNOWN_TENANT_LABEL};
+
+/// User id of every `hot_tcp` connection.
+pub const BACKEND_USER: &str = "backend";
+
+/// `ws_active_sessions` kind of `hot_tcp` connections.
+const KIND: &str = "backend";
+
+/// Largest accepted auth frame.
+const MAX_AUTH_FRAME_BYTES: usize = 4096;
+
+/// Length prefix size.
+const HEADER_BYTES: usize = 4;
+
+/// Byte or room rate exceeded (Hot lane drops silently).
+const RATE_DROP: PolicyDecision = PolicyDecision::Drop { reason: DecisionReason::Rate };
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuthFrame {
+    api_key: String,
+    #[serde(default)]
+    room: Option<String>,
+}
+
+/// Accept `hot_tcp` connections until the gateway starts draining. Must be
+/// called from within a tokio runtime.
+pub async fn serve(app: AppState, listener: TcpListener) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!(error = %e, "hot_tcp accept failed");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        if app.is_draining() {
+            break;
+        }
+        let _ = stream.set_nodelay(true);
+        let span = tracing::info_span!("hot_tcp", %peer, tenant = tracing::field::Empty, connection_id = tracing::field::Empty);
+        let app = app.clone();
+        tokio::spawn(
+            async move {
+                if let Err(e) = run_connection(app, stream).await {
+                    tracing::debug!(error = %e, "hot_tcp connection ended");
+                }
+            }
+            .instrument(span),
+        );
+    }
+}
+
+/// Bind `hot_tcp.listen` and [`serve`] it in the background; `None` when
+/// `hot_tcp` is not configured.
+pub async fn spawn(app: &AppState) -> Result<Option<(SocketAddr, tokio::task::JoinHandle<()>)>> {
+    let Some(cfg) = &app.cfg().hot_tcp else { return Ok(None) };
+    let listener = TcpListener::bind(&cfg.listen).await?;
+    let addr = listener.local_addr()?;
+    tracing::info!(%addr, "hot_tcp listening");
+    Ok(Some((addr, tokio::spawn(serve(app.clone(), listener)))))
+}
+
+/// Take one complete frame off the front of `buf`. `Err(len)` when the
+/// next frame is longer than `max`.
+fn take_frame(buf: &mut BytesMut, max: usize) -> std::result::Result<Option<Bytes>, usize> {
+    if buf.len() < HEADER_BYTES {
+        return Ok(None);
+    }
+    let len = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+    if len > max {
+        return Err(len);
+    }
+    if buf.len() < HEADER_BYTES + len {
+        return Ok(None);
+    }
+    buf.advance(HEADER_BYTES);
+    Ok(Some(buf.split_to(len).freeze()))
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(w: &mut W, payload: &[u8]) -> std::io::Result<()> {
+    let len = u32::try_from(payload.len()).map_err(|_| std::io::Error::other("frame too large"))?;
+    w.write_all(&len.to_le_bytes()).await?;
+    w.write_all(payload).await?;
+    w.flush().await
+}
+
+/// Read the auth frame and resolve its tenant.
+async fn authenticate(app: &AppState, stream: &mut TcpStream, buf: &mut BytesMut) -> Result<(TenantConfig, Option<String>)> {
+    let frame = loop {
+        match take_frame(buf, MAX_AUTH_FRAME_BYTES) {
+            Ok(Some(frame)) => break frame,
+            Ok(None) => {}
+            Err(_) => return Err(WsPrismError::BadRequest("auth frame too large".into())),
+        }
+        if stream.read_buf(buf).await? == 0 {
+            return Err(WsPrismError::BadRequest("closed before auth".into()));
+        }
+    };
+    let auth: AuthFrame = serde_json::from_slice(&frame)?;
+    let tenant = app
+        .cfg()
+        .tenants
+        .iter()
+        .find(|t| t.api_key.as_deref().is_some_and(|k| token_matches(k, &auth.api_key)))
+        .ok_or(WsPrismError::AuthFailed)?;
+    if auth.room.as_deref().is_some_and(|r| r.is_empty() || r.contains("::")) {
+        return Err(WsPrismError::BadRequest("invalid room".into()));
+    }
+    Ok((tenant.clone(), auth.room))
+}
+
+/// Tears down the registry entry on exit.
+struct ConnectionCleanup {
+    core: Arc<RealtimeCore>,
+    tenant_id: String,
+    user_key: String,
+    connection_id: ConnectionId,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl Drop for ConnectionCleanup {
+    fn drop(&mut self) {
+        let _ = self.core.sessions.unregister(self.connection_id);
+        self.core.presence.cleanup_session(&self.tenant_id, &self.user_key, self.connection_id);
+        self.metrics.ws_active_sessions.dec(&[("tenant", &self.tenant_id), ("kind", KIND)]);
+    }
+}
+
+async fn run_connection(app: AppState, mut stream: TcpStream) -> Result<()> {
+    let gw = &app.cfg().gateway;
+    let metrics = app.metrics();
+    let mut buf = BytesMut::with_capacity(8 * 1024);
+    let auth_timeout = Duration::from_millis(app.cfg().hot_tcp.as_ref().map_or(0, |h| h.auth_timeout_ms));
+    let (t_cfg, active_room) = match timeout(auth_timeout, authenticate(&app, &mut stream, &mut buf)).await {
+        Ok(Ok(auth)) => auth,
+        Ok(Err(e)) => {
+            metrics.handshake_rejections.inc(&[("tenant", UNKNOWN_TENANT_LABEL), ("reason", "hot_tcp_auth")]);
+            return Err(e);
+        }
+        Err(_) => {
+            metrics.handshake_rejections.inc(&[("tenant", UNKNOWN_TENANT_LABEL), ("reason", "hot_tcp_auth_timeout")]);
+            return Err(WsPrismError::BadRequest("auth timed out".into()));
+        }
+    };
+    let tenant = t_cfg.id.as_str();
+    let mut policy = app.tenant_policy(tenant).ok_or(WsPrismError::AuthFailed)?;
+    if policy.is_suspended() {
+        metrics.handshake_rejections.inc(&[("tenant", tenant), ("reason", "tenant_suspended")]);
+        return Err(WsPrismError::NotAllowed("tenant suspended".into()));
+    }
+
+    let connection_id = ConnectionId::new();
+    let trace_id: Arc<str> = Arc::from(gen_trace());
+    let span = tracing::Span::current();
+    span.record("tenant", tenant);
+    span.record("connection_id", tracing::field::display(connection_id));
+    let core = app.realtime();
+    let dispatcher = app.dispatcher();
+    let user_key = format!("{tenant}::{BACKEND_USER}");
+    let (out_tx, mut out_rx) = mpsc::channel::<Message>(1024);
+    let conn = Connection::new(out_tx, SessionClaims::default()).with_meter(policy.new_outbound_meter());
+    if let Err(e) = core.sessions.try_insert(tenant.to_string(), user_key.clone(), connection_id, conn, t_cfg.limits.max_sessions_total) {
+        metrics.handshake_rejections.inc(&[("tenant", tenant), ("reason", "tenant_capacity")]);
+        return Err(e);
+    }
+    metrics.ws_active_sessions.inc(&[("tenant", tenant), ("kind", KIND)]);
+    let _cleanup = ConnectionCleanup {
+        core: core.clone(),
+        tenant_id: tenant.to_string(),
+        user_key,
+        connection_id,
+        metrics: metrics.clone(),
+    };
+
+    let writer_timeout = Duration::from_millis(gw.writer_send_timeout_ms);
+    let ack = json!({ "tenant": tenant, "connection_id": connection_id.to_string() }).to_string();
+    timeout(writer_timeout, write_frame(&mut stream, ack.as_bytes()))
+        .await
+        .map_err(|_| WsPrismError::Internal("auth reply timed out".into()))??;
+
+    let (mut rd, mut wr) = stream.into_split();
+    let ping_interval = Duration::from_millis(gw.ping_interval_ms);
+    let mut ping_tick = tokio::time::interval_at(Instant::now() + ping_interval, ping_interval);
+    let mut idle_tick = tokio::time::interval(Duration::from_millis(1000));
+    let idle_timeout = Duration::from_millis(gw.idle_timeout_ms);
+    let plugin = app.tenant_plugin(tenant);
+    let plugin_ctx = PluginCtx { tenant, user: BACKEND_USER, guest: false };
+    let mut byte_bucket = policy.new_session_byte_bucket();
+    let mut last_activity = Instant::now();
+    let (mut bytes_in, mut bytes_out) = (0u64, 0u64);
+
+    let reason: &str = 'session: loop {
+        // Frames already buffered (e.g. sent right behind the auth frame).
+        loop {
+            let frame = match take_frame(&mut buf, policy.max_frame_bytes()) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(len) => {
+                    metrics.observe_frame_size(FrameDir::Inbound, tenant, Lane::Hot.as_str(), len);
+                    metrics.decode_errors.inc(&[("tenant", tenant), ("reason", "hot")]);
+                    break 'session "frame too large";
+                }
+            };
+            last_activity = Instant::now();
+            if let Some(current) = app.tenant_policy(tenant) {
+                if !Arc::ptr_eq(&current, &policy) {
+                    byte_bucket = current.new_session_byte_bucket();
+                    policy = current;
+                }
+            }
+            if policy.is_suspended() {
+                break 'session "tenant suspended";
+            }
+            if frame.is_empty() {
+                continue; // keepalive
+            }
+            let raw_len = frame.len();
+            metrics.observe_frame_size(FrameDir::Inbound, tenant, Lane::Hot.as_str(), raw_len);
+            metrics.count_frame_bytes(FrameDir::Inbound, tenant, Lane::Hot.as_str(), raw_len);
+            bytes_in += raw_len as u64;
+            if policy.admit_bytes(raw_len, byte_bucket.as_ref()).is_err()
+                && !matches!(policy.finish(&metrics, Lane::Hot, RATE_DROP), PolicyDecision::Pass)
+            {
+                continue;
+            }
+            let frame = match decode_hot_frame_with_limit(frame, policy.max_frame_bytes()) {
+                Ok(frame) => frame,
+                Err(_) => {
+                    metrics.decode_errors.inc(&[("tenant", tenant), ("reason", "hot")]);
+                    break 'session "invalid hot frame";
+                }
+            };
+            let decision = match policy.evaluate_hot(&metrics, raw_len, frame.svc_id, frame.opcode, false) {
+                PolicyDecision::Pass => match &plugin {
+                    Some(p) => p.check_hot(&metrics, &plugin_ctx, &frame),
+                    None => PolicyDecision::Pass,
+                },
+                d => d,
+            };
+            match decision {
+                PolicyDecision::Pass => {}
+                PolicyDecision::Drop { .. } | PolicyDecision::Reject { .. } => continue,
+                PolicyDecision::Close { msg, .. } => break 'session msg,
+            }
+            if policy.hot_requires_active_room() && active_room.is_none() {
+                continue;
+            }
+            let mut ctx = RealtimeCtx::new(tenant, BACKEND_USER, connection_id, trace_id.clone(), active_room.clone(), core.clone())
+                .with_room_limiter(policy.room_limiter())
+                .with_fanout_limit(policy.max_fanout_parallelism());
+            if let Some(room) = active_room.as_deref() {
+                if ctx.check_room_rate(room).is_err() && !matches!(policy.finish(&metrics, Lane::Hot, RATE_DROP), PolicyDecision::Pass) {
+                    continue;
+                }
+            }
+            let svc_id = frame.svc_id;
+            let known = dispatcher.has_hot(svc_id);
+            if let Err(e) = dispatcher.dispatch_hot(ctx, frame).await {
+                let svc = svc_id.to_string();
+                if known {
+                    metrics.service_errors.inc(&[("tenant", tenant), ("lane", "hot"), ("svc", &svc), ("code", e.client_code().as_str())]);
+                } else {
+                    metrics.unknown_service_errors.inc(&[("tenant", tenant), ("lane", "hot"), ("svc", &svc)]);
+                }
+            }
+        }
+
+        tokio::select! {
+            maybe_out = out_rx.recv() => {
+                let payload = match maybe_out {
+                    Some(Message::Binary(b)) => b,
+                    Some(Message::Close(_)) | None => break "server closed",
+                    // No text lane on this transport.
+                    Some(_) => continue,
+                };
+                metrics.observe_frame_size(FrameDir::Outbound, tenant, Lane::Hot.as_str(), payload.len());
+                match timeout(writer_timeout, write_frame(&mut wr, &payload)).await {
+                    Ok(Ok(())) => {
+                        metrics.count_frame_bytes(FrameDir::Outbound, tenant, Lane::Hot.as_str(), payload.len());
+                        bytes_out += payload.len() as u64;
+                    }
+                    Ok(Err(_)) => break "write failed",
+                    Err(_) => {
+                        metrics.writer_timeouts.inc(&[("tenant", tenant)]);
+                        break "writer timeout";
+                    }
+                }
+            }
+            read = rd.read_buf(&mut buf) => match read {
+                Ok(0) => break "client_closed",
+                Ok(_) => {}
+                Err(_) => break "read failed",
+            },
+            _ = ping_tick.tick() => {
+                if !matches!(timeout(writer_timeout, write_frame(&mut wr, &[])).await, Ok(Ok(()))) {
+                    break "write failed";
+                }
+            }
+            _ = idle_tick.tick() => {
+                if last_activity.elapsed() >= idle_timeout {
+                    break "idle timeout";
+                }
+            }
+        }
+    };
+    let _ = wr.shutdown().await;
+    tracing::info!(bytes_in, bytes_out, reason, "hot_tcp session summary");
+    Ok(())
+}
@@ -1,8 +1,12 @@
-//! Transport layer (WebSocket).
+//! Transport layer (WebSocket, HTTP publish and long polling, raw TCP Hot Lane).
 //!
 //! Exposes the WS upgrade handler and codec that decodes messages once before
-//! they reach policy/dispatcher layers.
+//! they reach policy/dispatcher layers, and the backend publish endpoint.
 
+pub mod bandwidth;
 pub mod codec;
+pub mod hot_tcp;
 pub mod ws;
-pub mod handshake;
\ No newline at end of file
+pub mod handshake;
+pub mod poll;
+pub mod publish;
\ No newline at end of file
@@ -0,0 +1,464 @@
+//! Long-polling fallback for clients behind proxies that cut idle
+//! WebSockets (tenant `long_poll`).
+//!
+//! `GET /v1/poll?tenant=&ticket=` opens a pseudo-session: a connection
+//! registered like a WebSocket session (same limits, presence, webhooks and
+//! `sys.authed` greeting) whose outbound messages collect in a buffer instead
+//! of being written to a socket. Each poll answers
+//!
+//! ```json
+//! { "cursor": "<session>.<seq>", "messages": [ { "svc": "sys", "type": "authed", .. } ] }
+//! ```
+//!
+//! with every buffered message, waiting up to `long_poll.wait_ms` for the
+//! first one. Passing `cursor` to the next poll acknowledges the messages it
+//! covers; unacknowledged ones are returned again, so a poll whose response
+//! was lost can simply be repeated.
+//!
+//! `POST /v1/poll/send?tenant=&ticket=&cursor=` takes one Ext lane envelope
+//! as its body and runs it through the same policy checks, room commands and
+//! dispatch as a WebSocket text frame; replies and `sys.error`s arrive on the
+//! next poll. Requests for a session must authenticate as its user (guest
+//! sessions are identified by the cursor alone).
+//!
+//! A session ends after `long_poll.session_ttl_ms` without a request, or
+//! wherever a WebSocket session would be closed (kick, policy close, tenant
+//! suspension). The next poll returns what is left with `"closed": reason`;
+//! later requests get `410 Gone`.
+
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use axum::{
+    extract::{connect_info::ConnectInfo, ws::CloseFrame, ws::Message, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use dashmap::DashMap;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::Instant;
+use wsprism_core::error::WsPrismError;
+
+use crate::app_state::AppState;
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::config::schema::LongPollConfig;
+use crate::context::ConnectionId;
+use crate::obs::metrics::FrameDir;
+use crate::policy::engine::Lane;
+use crate::realtime::{SessionEvent, SessionHandle};
+use crate::transport::codec::{decode, Inbound};
+use crate::transport::publish::Rejected;
+use crate::transport::ws::{
+    admit_frame, admit_user_session, gen_trace, greet, handle_text, new_event_log, open_session, resolve_identity, Admit,
+    SessionCleanup, SessionIo, SessionState, UNKNOWN_TENANT_LABEL,
+};
+
+/// Query of both poll endpoints.
+#[derive(Debug, Deserialize)]
+pub struct PollQuery {
+    pub tenant: String,
+    /// Auth ticket. May be omitted only for tenants with `allow_guest`.
+    #[serde(default)]
+    pub ticket: Option<String>,
+    /// `cursor` of the previous poll. Absent on the poll that opens a session.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Optional client-provided session id, echoed in `sys.authed`.
+    #[serde(default)]
+    pub sid: Option<String>,
+}
+
+/// Open long-poll sessions by id.
+#[derive(Default)]
+pub struct PollSessions {
+    sessions: DashMap<String, Arc<PollSession>>,
+}
+
+impl PollSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sessions not yet collected, including ended ones whose last poll is
+    /// still pending.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
+
+struct PollSession {
+    id: String,
+    io: SessionIo,
+    ttl: Duration,
+    max_buffered: usize,
+    /// Policy state for sends; the lock also runs them one at a time, in
+    /// order, like a WebSocket's frames.
+    state: tokio::sync::Mutex<SessionState>,
+    buf: Mutex<PollBuffer>,
+    /// New messages, or the session ended.
+    ready: Notify,
+    /// Acknowledged messages freed buffer space.
+    space: Notify,
+}
+
+struct PollBuffer {
+    /// Sequence number of the last buffered message.
+    last_seq: u64,
+    /// Highest sequence number acknowledged by a cursor.
+    acked: u64,
+    messages: VecDeque<(u64, String)>,
+    /// Why the session ended.
+    closed: Option<String>,
+    last_seen: Instant,
+    /// Polls waiting; the session does not expire under them.
+    polling: usize,
+}
+
+/// Decrements `polling` when a poll finishes or is dropped.
+struct Polling<'a>(&'a PollSession);
+
+impl Drop for Polling<'_> {
+    fn drop(&mut self) {
+        let mut buf = self.0.buf();
+        buf.polling -= 1;
+        buf.last_seen = Instant::now();
+    }
+}
+
+impl PollSession {
+    fn buf(&self) -> MutexGuard<'_, PollBuffer> {
+        self.buf.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn touch(&self) {
+        self.buf().last_seen = Instant::now();
+    }
+
+    fn expired(&self) -> bool {
+        let buf = self.buf();
+        buf.polling == 0 && buf.last_seen.elapsed() >= self.ttl
+    }
+
+    fn has_space(&self) -> bool {
+        self.buf().messages.len() < self.max_buffered
+    }
+
+    fn cursor(&self, seq: u64) -> String {
+        format!("{}.{}", self.id, seq)
+    }
+
+    /// Drop messages up to `seq`.
+    fn ack(&self, seq: u64) {
+        let mut buf = self.buf();
+        let seq = seq.min(buf.last_seq);
+        buf.acked = buf.acked.max(seq);
+        let before = buf.messages.len();
+        while buf.messages.front().is_some_and(|(s, _)| *s <= seq) {
+            buf.messages.pop_front();
+        }
+        if buf.messages.len() < before {
+            self.space.notify_one();
+        }
+    }
+
+    /// Buffer one outbound frame. Returns the close reason for a Close.
+    fn push(&self, m: Message) -> Option<String> {
+        match m {
+            Message::Text(text) => {
+                let metrics = self.io.app.metrics();
+                metrics.observe_frame_size(FrameDir::Outbound, &self.io.tenant, Lane::Ext.as_str(), text.len());
+                metrics.count_frame_bytes(FrameDir::Outbound, &self.io.tenant, Lane::Ext.as_str(), text.len());
+                self.io.log_event(|| SessionEvent::FrameSent(text.len()));
+                let mut buf = self.buf();
+                buf.last_seq += 1;
+                let seq = buf.last_seq;
+                buf.messages.push_back((seq, text));
+                drop(buf);
+                self.ready.notify_waiters();
+                None
+            }
+            Message::Close(frame) => Some(frame.map_or_else(|| "closed".to_string(), |f| f.reason.into_owned())),
+            // Hot Lane frames and pings have no long-poll form.
+            _ => None,
+        }
+    }
+
+    /// End the session once the frames queued before this are buffered.
+    fn close(&self, code: u16, reason: String) {
+        let _ = self.io.high_tx.send(Message::Close(Some(CloseFrame { code, reason: reason.into() })));
+    }
+
+    /// Wait up to `wait` for a message, then answer with the buffer.
+    async fn collect(&self, wait: Duration) -> Value {
+        self.buf().polling += 1;
+        let _polling = Polling(self);
+        let deadline = Instant::now() + wait;
+        loop {
+            let ready = self.ready.notified();
+            tokio::pin!(ready);
+            ready.as_mut().enable();
+            {
+                let buf = self.buf();
+                if !buf.messages.is_empty() || buf.closed.is_some() {
+                    break;
+                }
+            }
+            if tokio::time::timeout_at(deadline, ready).await.is_err() {
+                break;
+            }
+        }
+        let buf = self.buf();
+        let seq = buf.messages.back().map_or(buf.acked, |(s, _)| *s);
+        let messages: Vec<Value> = buf
+            .messages
+            .iter()
+            .map(|(_, text)| serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.clone())))
+            .collect();
+        let mut body = json!({ "cursor": self.cursor(seq), "messages": messages });
+        if let Some(reason) = &buf.closed {
+            body["closed"] = reason.as_str().into();
+        }
+        body
+    }
+}
+
+/// Move the session's outbound queues into its buffer until it ends, then
+/// unregister it. The entry stays for its final poll, or until it expires.
+async fn run_session(
+    polls: Arc<PollSessions>,
+    session: Arc<PollSession>,
+    mut out_rx: mpsc::Receiver<Message>,
+    mut high_rx: mpsc::UnboundedReceiver<Message>,
+    mut cleanup: SessionCleanup,
+) {
+    let mut tick = tokio::time::interval((session.ttl / 4).min(Duration::from_secs(1)));
+    let reason = loop {
+        let space = session.has_space();
+        tokio::select! {
+            biased;
+            Some(m) = high_rx.recv() => {
+                if let Some(reason) = session.push(m) {
+                    break reason;
+                }
+            }
+            Some(m) = out_rx.recv(), if space => {
+                if let Some(reason) = session.push(m) {
+                    break reason;
+                }
+            }
+            _ = session.space.notified() => {}
+            _ = tick.tick() => {
+                if session.expired() {
+                    break "expired".to_string();
+                }
+            }
+        }
+    };
+    // Keep what was queued before the decision (e.g. the sys.error).
+    while let Ok(m) = high_rx.try_recv().or_else(|_| out_rx.try_recv()) {
+        if !matches!(m, Message::Close(_)) && session.has_space() {
+            session.push(m);
+        }
+    }
+    tracing::debug!(tenant = %session.io.tenant, connection_id = %session.io.connection_id, reason = %reason, "long-poll session ended");
+    cleanup.reason = reason.clone();
+    drop(cleanup);
+    session.buf().closed = Some(reason);
+    session.ready.notify_waiters();
+    while !session.expired() {
+        tick.tick().await;
+    }
+    polls.sessions.remove(&session.id);
+}
+
+fn reject(status: StatusCode, code: &'static str) -> Rejected {
+    Rejected::new(status, code)
+}
+
+/// The tenant's `long_poll` section, if the tenant exists and enables it.
+fn long_poll_config<'a>(app: &'a AppState, tenant: &str) -> Result<&'a LongPollConfig, Rejected> {
+    let Some(t_cfg) = app.cfg().tenants.iter().find(|t| t.id == tenant) else {
+        app.metrics().handshake_rejections.inc(&[("tenant", UNKNOWN_TENANT_LABEL), ("reason", "unknown_tenant")]);
+        return Err(reject(StatusCode::BAD_REQUEST, "unknown_tenant"));
+    };
+    t_cfg.long_poll.as_ref().ok_or_else(|| reject(StatusCode::NOT_FOUND, "long_poll_disabled"))
+}
+
+/// Map an identity error as the WebSocket handshake does.
+fn auth_rejected(app: &AppState, tenant: &str, ip: IpAddr, e: WsPrismError) -> Rejected {
+    let (status, reason) = match e {
+        WsPrismError::Internal(_) => (StatusCode::SERVICE_UNAVAILABLE, "auth_unavailable"),
+        WsPrismError::QuotaExceeded(_) => (StatusCode::TOO_MANY_REQUESTS, "quota_exceeded"),
+        _ => (StatusCode::UNAUTHORIZED, "auth_failed"),
+    };
+    if reason == "auth_failed" {
+        app.audit().record(AuditEvent::AuthFailed(AuditRecord::new(tenant, e.to_string()).ip(ip)));
+    }
+    app.metrics().handshake_rejections.inc(&[("tenant", tenant), ("reason", reason)]);
+    reject(status, reason)
+}
+
+/// Register a new session, with the same admission checks as a WebSocket
+/// upgrade.
+async fn open(app: &AppState, q: &PollQuery, lp: &LongPollConfig, ip: IpAddr) -> Result<Arc<PollSession>, Rejected> {
+    let metrics = app.metrics();
+    if let Err(wait_secs) = app.handshake().check(ip).await {
+        metrics.handshake_rejections.inc(&[("tenant", &q.tenant), ("reason", "rate_limit")]);
+        return Err(reject(StatusCode::TOO_MANY_REQUESTS, "rate_limited").retry_after(wait_secs));
+    }
+    if app.is_draining() {
+        return Err(reject(StatusCode::SERVICE_UNAVAILABLE, "draining"));
+    }
+    let policy = app.tenant_policy(&q.tenant).ok_or_else(|| reject(StatusCode::BAD_REQUEST, "unknown_tenant"))?;
+    if policy.is_suspended() {
+        metrics.handshake_rejections.inc(&[("tenant", &q.tenant), ("reason", "tenant_suspended")]);
+        app.audit().record(AuditEvent::TenantSuspended(AuditRecord::new(&q.tenant, "tenant suspended").ip(ip)));
+        return Err(reject(StatusCode::SERVICE_UNAVAILABLE, "tenant_suspended"));
+    }
+    let identity = resolve_identity(app, &q.tenant, q.ticket.as_deref())
+        .await
+        .map_err(|e| auth_rejected(app, &q.tenant, ip, e))?;
+    let t_cfg = app.cfg().tenants.iter().find(|t| t.id == q.tenant).ok_or_else(|| reject(StatusCode::BAD_REQUEST, "unknown_tenant"))?;
+
+    let (out_tx, out_rx) = mpsc::channel(1024);
+    let (high_tx, high_rx) = mpsc::unbounded_channel();
+    let connection_id = ConnectionId::new();
+    let io = SessionIo {
+        app: app.clone(), tenant: q.tenant.clone(), user_id: identity.user_id, connection_id, trace_id: gen_trace(),
+        guest: identity.guest, claims: identity.claims, scopes: identity.scopes, ip, session: SessionHandle::new(t_cfg.limits.clone()), events: new_event_log(app), plugin: app.tenant_plugin(&q.tenant),
+        out_tx, high_tx,
+    };
+    if !admit_user_session(&io, &policy) {
+        return Err(reject(StatusCode::TOO_MANY_REQUESTS, "too_many_sessions"));
+    }
+    let cleanup = open_session(&io, &policy, t_cfg.limits.max_sessions_total).map_err(|_| {
+        metrics.handshake_rejections.inc(&[("tenant", &q.tenant), ("reason", "tenant_capacity")]);
+        reject(StatusCode::SERVICE_UNAVAILABLE, "tenant_capacity")
+    })?;
+    let sid = q.sid.clone().unwrap_or_else(|| connection_id.to_string());
+    greet(&io, &sid, &policy, &t_cfg.auto_join_rooms)
+        .await
+        .map_err(|_| reject(StatusCode::INTERNAL_SERVER_ERROR, "internal"))?;
+
+    let session = Arc::new(PollSession {
+        id: connection_id.to_string(),
+        io,
+        ttl: Duration::from_millis(lp.session_ttl_ms),
+        max_buffered: lp.max_buffered,
+        state: tokio::sync::Mutex::new(SessionState::new(policy)),
+        buf: Mutex::new(PollBuffer {
+            last_seq: 0,
+            acked: 0,
+            messages: VecDeque::new(),
+            closed: None,
+            last_seen: Instant::now(),
+            polling: 0,
+        }),
+        ready: Notify::new(),
+        space: Notify::new(),
+    });
+    let polls = app.polls();
+    polls.sessions.insert(session.id.clone(), session.clone());
+    tokio::spawn(run_session(polls, session.clone(), out_rx, high_rx, cleanup));
+    Ok(session)
+}
+
+/// The session a cursor names and its sequence number, once the request is
+/// authenticated as the session's user.
+async fn resume(app: &AppState, q: &PollQuery, cursor: &str, ip: IpAddr) -> Result<(Arc<PollSession>, u64), Rejected> {
+    let (id, seq) = cursor
+        .split_once('.')
+        .and_then(|(id, seq)| Some((id, seq.parse::<u64>().ok()?)))
+        .ok_or_else(|| reject(StatusCode::BAD_REQUEST, "invalid_cursor"))?;
+    let session = app
+        .polls()
+        .sessions
+        .get(id)
+        .map(|s| s.value().clone())
+        .filter(|s| s.io.tenant == q.tenant)
+        .ok_or_else(|| reject(StatusCode::GONE, "session_expired"))?;
+    if !session.io.guest {
+        let identity = resolve_identity(app, &q.tenant, q.ticket.as_deref())
+            .await
+            .map_err(|e| auth_rejected(app, &q.tenant, ip, e))?;
+        if identity.user_id != session.io.user_id {
+            return Err(reject(StatusCode::FORBIDDEN, "not_allowed"));
+        }
+    }
+    Ok((session, seq))
+}
+
+fn peer_ip(peer: Option<ConnectInfo<SocketAddr>>) -> IpAddr {
+    peer.map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |ConnectInfo(addr)| addr.ip())
+}
+
+/// `GET /v1/poll`: open a session (no `cursor`) or acknowledge up to
+/// `cursor` and wait for messages.
+pub async fn poll(
+    State(app): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    Query(q): Query<PollQuery>,
+) -> Result<Response, Rejected> {
+    let lp = long_poll_config(&app, &q.tenant)?;
+    let ip = peer_ip(peer);
+    let session = match q.cursor.as_deref() {
+        None => open(&app, &q, lp, ip).await?,
+        Some(cursor) => {
+            let (session, seq) = resume(&app, &q, cursor, ip).await?;
+            session.ack(seq);
+            session
+        }
+    };
+    session.touch();
+    let body = session.collect(Duration::from_millis(lp.wait_ms)).await;
+    if body.get("closed").is_some() {
+        app.polls().sessions.remove(&session.id);
+    }
+    Ok(Json(body).into_response())
+}
+
+/// `POST /v1/poll/send`: handle one Ext lane envelope as the session's
+/// inbound text frame. `202` once handled; errors arrive on the next poll.
+pub async fn send(
+    State(app): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    Query(q): Query<PollQuery>,
+    body: String,
+) -> Result<Response, Rejected> {
+    long_poll_config(&app, &q.tenant)?;
+    let cursor = q.cursor.as_deref().ok_or_else(|| reject(StatusCode::BAD_REQUEST, "invalid_cursor"))?;
+    let (session, _) = resume(&app, &q, cursor, peer_ip(peer)).await?;
+    if session.buf().closed.is_some() {
+        return Err(reject(StatusCode::GONE, "session_expired"));
+    }
+    session.touch();
+    let io = &session.io;
+    let mut sess = session.state.lock().await;
+    let close = match admit_frame(io, &mut sess, Some((Lane::Ext, body.len()))) {
+        Admit::Pass => match decode(Message::Text(body), sess.max_frame_bytes()) {
+            Ok(Inbound::Text { env, bytes_len }) => handle_text(io, &mut sess, env, bytes_len).await,
+            Ok(_) => None,
+            Err(e) => {
+                app.metrics().decode_errors.inc(&[("tenant", &io.tenant), ("reason", "json")]);
+                let _ = io.high_tx.send(Message::Text(e.to_sys_frame_traced(&io.trace_id)));
+                Some((e.close_code(), e.to_string()))
+            }
+        },
+        Admit::Skip => None,
+        Admit::Close(code, reason) => Some((code, reason)),
+    };
+    if let Some((code, reason)) = close {
+        session.close(code, reason);
+    }
+    session.touch();
+    Ok(StatusCode::ACCEPTED.into_response())
+}
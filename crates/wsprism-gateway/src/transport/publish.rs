@@ -0,0 +1,234 @@
+//! HTTP publish API for backend services.
+//!
+//! Application servers push events to clients without holding a WebSocket.
+//! `POST /v1/publish` targets a room:
+//!
+//! ```json
+//! { "tenant": "acme", "room": "match:1", "qos": "reliable",
+//!   "payload": { "json": { "type": "result" } }, "exclude_user": "u1" }
+//! ```
+//!
+//! and `POST /v1/users/{user}/send` every session of one user, with a body of
+//! just `{ "qos": .., "payload": .. }`; the tenant is the one owning the key.
+//!
+//! Authenticated with `Authorization: Bearer <tenant api_key>`. The payload
+//! is either `json` (sent as a text frame) or `binary_b64` (sent as a binary
+//! frame), at most the tenant's `limits.max_frame_bytes`. Each tenant's
+//! requests are rate limited by `gateway.publish`. Room publishes reply with
+//! `{ "targets": n, "dropped": n }`; user sends add per-session outcomes.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{connect_info::ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use bytes::Bytes;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::app_state::AppState;
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::config::schema::TenantConfig;
+use crate::ops::auth::{bearer, token_matches};
+use crate::policy::TenantPolicyRuntime;
+use crate::realtime::{Outgoing, Payload, Priority, QoS};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PublishRequest {
+    pub tenant: String,
+    pub room: String,
+    #[serde(default)]
+    pub qos: PublishQoS,
+    pub payload: PublishPayload,
+    /// User id whose sessions are skipped (e.g. the one who caused the event).
+    #[serde(default)]
+    pub exclude_user: Option<String>,
+}
+
+/// Body of `POST /v1/users/{user}/send`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserSendRequest {
+    #[serde(default)]
+    pub qos: PublishQoS,
+    pub payload: PublishPayload,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishQoS {
+    /// Skip sessions whose queue is full.
+    #[default]
+    Lossy,
+    /// Wait up to `gateway.writer_send_timeout_ms` per session.
+    Reliable,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum PublishPayload {
+    Json(Value),
+    BinaryB64(String),
+}
+
+/// A request refused before anything was sent: `{ "error": code }`.
+#[derive(Debug)]
+pub struct Rejected {
+    status: StatusCode,
+    code: &'static str,
+    retry_after_secs: Option<u64>,
+}
+
+impl Rejected {
+    pub(crate) fn new(status: StatusCode, code: &'static str) -> Self {
+        Self { status, code, retry_after_secs: None }
+    }
+
+    pub(crate) fn retry_after(self, secs: u64) -> Self {
+        Self { retry_after_secs: Some(secs), ..self }
+    }
+}
+
+impl IntoResponse for Rejected {
+    fn into_response(self) -> Response {
+        let mut resp = (self.status, Json(json!({ "error": self.code }))).into_response();
+        if let Some(v) = self.retry_after_secs.and_then(|s| s.to_string().parse().ok()) {
+            resp.headers_mut().insert(header::RETRY_AFTER, v);
+        }
+        resp
+    }
+}
+
+/// Audit a bad key presented for a known tenant.
+fn audit_rejected_key(state: &AppState, tenant: &str, peer: Option<ConnectInfo<SocketAddr>>) {
+    let mut record = AuditRecord::new(tenant, "publish api key rejected");
+    if let Some(ConnectInfo(addr)) = peer {
+        record = record.ip(addr.ip());
+    }
+    state.audit().record(AuditEvent::AuthFailed(record));
+}
+
+/// Suspension and rate limit checks shared by both endpoints.
+fn admit(state: &AppState, tenant: &TenantConfig) -> Result<Arc<TenantPolicyRuntime>, Rejected> {
+    let policy = state
+        .tenant_policy(&tenant.id)
+        .ok_or(Rejected::new(StatusCode::UNAUTHORIZED, "unauthorized"))?;
+    if policy.is_suspended() {
+        return Err(Rejected::new(StatusCode::SERVICE_UNAVAILABLE, "tenant_suspended"));
+    }
+    if let Err(retry_after_ms) = state.try_publish_slot(&tenant.id) {
+        return Err(Rejected::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited").retry_after(retry_after_ms.div_ceil(1000).max(1)));
+    }
+    Ok(policy)
+}
+
+/// Decode the payload and apply the tenant's frame size limit.
+fn outgoing(state: &AppState, tenant: &TenantConfig, qos: PublishQoS, payload: PublishPayload) -> Result<Outgoing, Rejected> {
+    let payload = match payload {
+        PublishPayload::Json(v) => Payload::TextJson(v),
+        PublishPayload::BinaryB64(s) => match STANDARD.decode(s) {
+            Ok(b) => Payload::Binary(Bytes::from(b)),
+            Err(_) => return Err(Rejected::new(StatusCode::BAD_REQUEST, "invalid_base64")),
+        },
+    };
+    let len = match &payload {
+        Payload::TextJson(v) => serde_json::to_vec(v).map_or(0, |b| b.len()),
+        Payload::Binary(b) => b.len(),
+        _ => 0,
+    };
+    if len > tenant.limits.max_frame_bytes {
+        return Err(Rejected::new(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large"));
+    }
+    let qos = match qos {
+        PublishQoS::Lossy => QoS::Lossy,
+        PublishQoS::Reliable => QoS::Reliable { timeout_ms: state.cfg().gateway.writer_send_timeout_ms },
+    };
+    Ok(Outgoing { qos, payload, priority: Priority::Normal })
+}
+
+pub async fn publish(
+    State(state): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(req): Json<PublishRequest>,
+) -> Result<Response, Rejected> {
+    let tenant = state.cfg().tenants.iter().find(|t| t.id == req.tenant);
+    let authorized = match (tenant.and_then(|t| t.api_key.as_deref()), bearer(&headers)) {
+        (Some(expected), Some(presented)) => token_matches(expected, presented),
+        _ => false,
+    };
+    let Some(tenant) = tenant.filter(|_| authorized) else {
+        if tenant.is_some() {
+            audit_rejected_key(&state, &req.tenant, peer);
+        }
+        return Err(Rejected::new(StatusCode::UNAUTHORIZED, "unauthorized"));
+    };
+
+    let policy = admit(&state, tenant)?;
+    if req.room.is_empty() || req.room.contains("::") {
+        return Err(Rejected::new(StatusCode::BAD_REQUEST, "invalid_room"));
+    }
+    let out = outgoing(&state, tenant, req.qos, req.payload)?;
+
+    let room_key = format!("{}::{}", tenant.id, req.room);
+    let exclude = req.exclude_user.map(|u| format!("{}::{}", tenant.id, u));
+    let core = state.realtime();
+    if let Some(limiter) = policy.room_limiter() {
+        core.check_room_rate(&limiter, &room_key)
+            .map_err(|secs| Rejected::new(StatusCode::TOO_MANY_REQUESTS, "room_rate_limited").retry_after(secs.max(1)))?;
+    }
+    let report = match out.qos {
+        QoS::Lossy => core.publish_room_lossy_excluding(&room_key, out, exclude.as_deref()),
+        QoS::Reliable { .. } => {
+            let fanout = policy.max_fanout_parallelism();
+            core.publish_room_reliable_excluding(&room_key, out, fanout, exclude.as_deref()).await
+        }
+    };
+    match report {
+        Ok(report) => Ok((StatusCode::OK, Json(report)).into_response()),
+        Err(e) => Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.client_code().as_str() }))).into_response()),
+    }
+}
+
+/// `POST /v1/users/{user}/send`: 404 `user_offline` (with `queued: false`)
+/// when the user has no session, otherwise 200 with each session's outcome.
+pub async fn send_to_user(
+    State(state): State<AppState>,
+    Path(user): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<UserSendRequest>,
+) -> Result<Response, Rejected> {
+    // The key selects the tenant; keys are unique across tenants.
+    let presented = bearer(&headers);
+    let tenant = state.cfg().tenants.iter().find(|t| match (t.api_key.as_deref(), presented) {
+        (Some(expected), Some(presented)) => token_matches(expected, presented),
+        _ => false,
+    });
+    let tenant = tenant.ok_or(Rejected::new(StatusCode::UNAUTHORIZED, "unauthorized"))?;
+
+    admit(&state, tenant)?;
+    if user.is_empty() || user.contains("::") {
+        return Err(Rejected::new(StatusCode::BAD_REQUEST, "invalid_user"));
+    }
+    let out = outgoing(&state, tenant, req.qos, req.payload)?;
+
+    let user_key = format!("{}::{}", tenant.id, user);
+    let resp = match state.realtime().deliver_to_user(&user_key, out).await {
+        Ok(sessions) if sessions.is_empty() => {
+            (StatusCode::NOT_FOUND, Json(json!({ "error": "user_offline", "queued": false }))).into_response()
+        }
+        Ok(sessions) => {
+            let dropped = sessions.iter().filter(|s| !s.delivered).count();
+            let body = json!({ "targets": sessions.len(), "dropped": dropped, "sessions": sessions });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e.client_code().as_str() }))).into_response(),
+    };
+    Ok(resp)
+}
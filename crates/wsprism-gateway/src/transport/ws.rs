@@ -10,86 +10,241 @@ use axum::{
     http::{HeaderMap, StatusCode, header::RETRY_AFTER},
     response::{IntoResponse},
 };
+use futures_util::stream::SplitSink;
 use futures_util::{SinkExt, StreamExt};
 use serde::Deserialize;
 use serde_json::json;
 use tokio::sync::mpsc;
 use tokio::time::{timeout, Duration, Instant};
-use std::net::SocketAddr;
+use tracing::Instrument;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
-use wsprism_core::error::{Result, WsPrismError};
-use crate::app_state::AppState;
-use crate::policy::engine::{ConnRateLimiter, HotErrorMode, OnExceed, PolicyDecision};
+use wsprism_core::error::{ClientCode, Result, WsPrismError};
+use wsprism_core::protocol::text::{sys_frame, Envelope};
+use crate::app_state::{AppState, GOAWAY_TENANT_SUSPENDED};
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::context::{ConnectionId, SessionClaims};
+use crate::egress::EgressRecord;
+use crate::policy::allowlist::{compile_scope_rules, ExtRule};
+use crate::policy::engine::{ConnRateLimiter, DecisionReason, HotErrorMode, Lane, OnExceed, PolicyDecision, TenantPolicyRuntime};
+use crate::plugin::{PluginCtx, PluginHost};
+use crate::policy::quota::unix_now_ms;
+use crate::policy::rate::TokenBucket;
+use crate::policy::strikes::StrikeCounter;
 use crate::realtime::core::Connection;
 use crate::realtime::RealtimeCore;
-use crate::realtime::RealtimeCtx;
+use crate::realtime::types::REPLY_TIMEOUT_MS;
+use crate::realtime::{Outgoing, PreparedMsg};
+use crate::realtime::{RealtimeCtx, SessionEvent, SessionEventLog, SessionHandle};
 use crate::transport::codec::{decode, Inbound};
 use crate::transport::handshake::retry_after_header_secs;
-use crate::obs::metrics::GatewayMetrics;
+use crate::webhooks::{WebhookEvent, Webhooks};
+use crate::obs::metrics::{FrameDir, GatewayMetrics};
 
 static NEXT_SID: AtomicU64 = AtomicU64::new(1);
 static NEXT_TRACE: AtomicU64 = AtomicU64::new(1);
 
 fn gen_sid() -> String { format!("{:x}", NEXT_SID.fetch_add(1, Ordering::Relaxed)) }
-fn gen_trace() -> String {
+pub(crate) fn gen_trace() -> String {
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
     let seq = NEXT_TRACE.fetch_add(1, Ordering::Relaxed);
     format!("{:x}-{:x}", now, seq)
 }
+fn gen_guest_id() -> String {
+    format!("guest-{}", uuid::Uuid::new_v4())
+}
 
 /// WebSocket upgrade query parameters.
 #[derive(Debug, Deserialize)]
 pub struct WsQuery {
     pub tenant: String,
-    pub ticket: String,
+    /// Auth ticket. May be omitted only for tenants with `allow_guest`.
+    #[serde(default)]
+    pub ticket: Option<String>,
     /// Optional client-provided session id (tab/browser id). Generated if absent.
     #[serde(default)]
     pub sid: Option<String>,
 }
 
-/// Per-connection mutable state used inside the WS loop.
-struct SessionState {
-    active_room: Option<String>,
+/// Per-connection mutable state used inside the WS loop (and by a long-poll
+/// session's sends).
+pub(crate) struct SessionState {
     last_activity: Instant,
+    policy: Arc<TenantPolicyRuntime>,
     conn_limiter: Option<ConnRateLimiter>,
+    byte_bucket: Option<TokenBucket>,
+    strikes: Option<StrikeCounter>,
+    bytes_in: u64,
+    bytes_out: u64,
+}
+
+const STRIKE_MSG: &str = "too many policy violations";
+
+/// Recorded when a session collects too many rejects (`strike_limit`).
+const STRIKE_OUT: PolicyDecision = PolicyDecision::Close {
+    code: ClientCode::NotAllowed,
+    msg: STRIKE_MSG,
+    reason: DecisionReason::StrikeLimit,
+    retry_after_ms: None,
+};
+
+impl SessionState {
+    pub(crate) fn new(policy: Arc<TenantPolicyRuntime>) -> Self {
+        Self {
+            last_activity: Instant::now(),
+            conn_limiter: policy.new_connection_limiter(),
+            byte_bucket: policy.new_session_byte_bucket(),
+            strikes: policy.new_strike_counter(),
+            policy,
+            bytes_in: 0,
+            bytes_out: 0,
+        }
+    }
+
+    /// Count a data frame in this session's totals and the tenant byte counters.
+    pub(crate) fn count_bytes(&mut self, metrics: &GatewayMetrics, dir: FrameDir, tenant: &str, lane: Lane, len: usize) {
+        match dir {
+            FrameDir::Inbound => self.bytes_in += len as u64,
+            FrameDir::Outbound => self.bytes_out += len as u64,
+        }
+        metrics.count_frame_bytes(dir, tenant, lane.as_str(), len);
+    }
+
+    /// The tenant's `max_frame_bytes`, as of the last policy refresh.
+    pub(crate) fn max_frame_bytes(&self) -> usize {
+        self.policy.max_frame_bytes()
+    }
+
+    /// Count a policy reject. `true` once the strike limit is exceeded.
+    fn strike(&mut self) -> bool {
+        self.strikes.as_mut().is_some_and(|s| s.strike(std::time::Instant::now()))
+    }
+
+    /// Pick up a reloaded tenant policy. Per-connection limiters restart
+    /// from the new settings.
+    fn refresh_policy(&mut self, app: &AppState, tenant: &str) -> Arc<TenantPolicyRuntime> {
+        if let Some(current) = app.tenant_policy(tenant) {
+            if !Arc::ptr_eq(&current, &self.policy) {
+                self.conn_limiter = current.new_connection_limiter();
+                self.byte_bucket = current.new_session_byte_bucket();
+                self.strikes = current.new_strike_counter();
+                self.policy = current;
+            }
+        }
+        Arc::clone(&self.policy)
+    }
 }
 
-fn sys_authed_json(tenant: &str, user: &str, sid: &str, trace_id: &str) -> String {
-    json!({ "v": 1, "svc": "sys", "type": "authed", "data": { "tenant": tenant, "user": user, "sid": sid }, "trace_id": trace_id }).to_string()
+/// `sys.authed` as a reply `Outgoing` (same convention as service replies).
+fn sys_authed_reply(tenant: &str, user: &str, sid: &str, connection_id: ConnectionId, trace_id: &str) -> Outgoing {
+    Outgoing::reply(json!({ "v": 1, "svc": "sys", "type": "authed", "data": { "tenant": tenant, "user": user, "sid": sid, "connection_id": connection_id.to_string() }, "trace_id": trace_id }))
 }
+/// Error frame for codes that do not originate from a `WsPrismError`.
+/// Same wire shape as `WsPrismError::to_sys_frame_traced`.
 fn sys_error_json(code: &str, msg: &str, trace_id: &str) -> String {
-    json!({ "v": 1, "svc": "sys", "type": "error", "data": { "code": code, "msg": msg }, "trace_id": trace_id }).to_string()
+    sys_frame("error", &json!({ "code": code, "message": msg }), Some(trace_id))
+}
+/// `sys.error` for a policy reject/close, with the backoff hint if any.
+/// Quota rejects also carry the absolute `reset_at_unix_ms`.
+fn sys_reject_json(code: ClientCode, msg: &str, retry_after_ms: Option<u64>, trace_id: &str) -> String {
+    let mut data = json!({ "code": code.as_str(), "message": msg });
+    if let Some(ms) = retry_after_ms {
+        data["retry_after_ms"] = ms.into();
+        if code == ClientCode::QuotaExceeded {
+            data["reset_at_unix_ms"] = unix_now_ms().saturating_add(ms).into();
+        }
+    }
+    sys_frame("error", &data, Some(trace_id))
+}
+/// Close frame reason; carries the backoff hint as `retry_after_ms=N`.
+fn close_reason(msg: &str, retry_after_ms: Option<u64>) -> String {
+    match retry_after_ms {
+        Some(ms) => format!("{msg}; retry_after_ms={ms}"),
+        None => msg.to_string(),
+    }
+}
+fn sys_strike_out_json(trace_id: &str) -> String {
+    sys_error_json(ClientCode::NotAllowed.as_str(), STRIKE_MSG, trace_id)
+}
+fn strike_out_close() -> (u16, String) {
+    (ClientCode::NotAllowed.close_code(), STRIKE_MSG.to_string())
+}
+fn sys_rate_limited_json(msg: &str, retry_after_secs: u64, trace_id: &str) -> String {
+    let data = json!({ "code": "RATE_LIMITED", "message": msg, "retry_after_secs": retry_after_secs });
+    sys_frame("error", &data, Some(trace_id))
 }
 fn sys_kicked_json(reason: &str, trace_id: &str) -> String {
     json!({ "v": 1, "svc": "sys", "type": "kicked", "data": { "reason": reason }, "trace_id": trace_id }).to_string()
 }
+fn sys_goaway_json(reason: &str, trace_id: &str) -> String {
+    sys_frame("goaway", &json!({ "reason": reason }), Some(trace_id))
+}
 fn sys_joined_json(room: &str, trace_id: &str) -> String {
     json!({ "v": 1, "svc": "sys", "type": "joined", "room": room, "trace_id": trace_id }).to_string()
 }
-fn sys_left_json(trace_id: &str) -> String {
-    json!({ "v": 1, "svc": "sys", "type": "left", "trace_id": trace_id }).to_string()
+
+/// `tenant` label for handshakes naming a tenant that is not configured.
+pub(crate) const UNKNOWN_TENANT_LABEL: &str = "_unknown";
+
+/// Close code for sessions ended by the server without an error (idle).
+pub(crate) const CLOSE_NORMAL: u16 = 1000;
+/// Close code for sessions ended by drain or tenant suspension.
+pub(crate) const CLOSE_GOING_AWAY: u16 = 1001;
+/// Max Close frame reason length (control frame payload minus the code).
+const CLOSE_REASON_MAX: usize = 123;
+
+/// Send a Close frame so the client sees why the session ended.
+///
+/// `reason` is truncated to fit a control frame.
+async fn close_session(ws_tx: &mut SplitSink<WebSocket, Message>, code: u16, reason: &str) {
+    let mut end = reason.len().min(CLOSE_REASON_MAX);
+    while !reason.is_char_boundary(end) {
+        end -= 1;
+    }
+    let frame = CloseFrame { code, reason: reason[..end].to_string().into() };
+    let _ = ws_tx.send(Message::Close(Some(frame))).await;
+}
+
+/// Lane and payload size of a data frame (`None` for control frames).
+fn data_frame(m: &Message) -> Option<(Lane, usize)> {
+    match m {
+        Message::Text(s) => Some((Lane::Ext, s.len())),
+        Message::Binary(b) => Some((Lane::Hot, b.len())),
+        _ => None,
+    }
 }
 
-/// RAII guard that tears down session and presence entries on exit.
-struct SessionCleanup {
-    core: Arc<RealtimeCore>, tenant_id: String, user_key: String, session_key: String, metrics: Arc<GatewayMetrics>,
+/// RAII guard that tears down session and presence entries on exit, then
+/// reports the `disconnect` webhook with `reason` (`error` unless set).
+pub(crate) struct SessionCleanup {
+    core: Arc<RealtimeCore>, tenant_id: String, user_key: String, connection_id: ConnectionId, kind: &'static str, metrics: Arc<GatewayMetrics>,
+    webhooks: Option<Arc<Webhooks>>, user_id: String, connected_at: Instant, events: Option<Arc<SessionEventLog>>, pub(crate) reason: String,
 }
 impl Drop for SessionCleanup {
     fn drop(&mut self) {
-        let _ = self.core.sessions.remove_session(&self.user_key, &self.session_key);
-        self.core.presence.cleanup_session(&self.tenant_id, &self.user_key, &self.session_key);
-        self.metrics.ws_active_sessions.dec(&[("tenant", &self.tenant_id)]);
-        tracing::debug!(s=%self.session_key, "session raii cleanup done");
+        let _ = self.core.sessions.unregister(self.connection_id);
+        self.core.presence.cleanup_session(&self.tenant_id, &self.user_key, self.connection_id);
+        self.metrics.ws_active_sessions.dec(&[("tenant", &self.tenant_id), ("kind", self.kind)]);
+        if let Some(w) = &self.webhooks {
+            w.emit(WebhookEvent::disconnect(&self.tenant_id, &self.user_id, self.connection_id, &self.reason, self.connected_at.elapsed()));
+        }
+        if let Some(log) = &self.events {
+            log.record(SessionEvent::Disconnected(self.reason.as_str().into()));
+        }
+        tracing::debug!(connection_id=%self.connection_id, "session raii cleanup done");
     }
 }
 
 pub async fn ws_upgrade(
     State(app): State<AppState>, ConnectInfo(addr): ConnectInfo<SocketAddr>, ws: WebSocketUpgrade, Query(q): Query<WsQuery>,
 ) -> impl IntoResponse {
+    // `tenant` is client input until matched against the config.
+    let known_tenant = app.cfg().tenants.iter().any(|t| t.id == q.tenant);
+    let tenant_label = if known_tenant { q.tenant.as_str() } else { UNKNOWN_TENANT_LABEL };
     if let Err(wait_secs) = app.handshake().check(addr.ip()).await {
-        app.metrics().handshake_rejections.inc(&[("tenant", &q.tenant), ("reason", "rate_limit")]);
+        app.metrics().handshake_rejections.inc(&[("tenant", tenant_label), ("reason", "rate_limit")]);
         let (val, _) = retry_after_header_secs(wait_secs);
         let mut headers = HeaderMap::new();
         headers.insert(RETRY_AFTER, val.parse().unwrap());
@@ -97,6 +252,12 @@ pub async fn ws_upgrade(
     }
     if app.is_draining() { return (StatusCode::SERVICE_UNAVAILABLE, "draining").into_response(); }
     if let Some(t_cfg) = app.cfg().tenants.iter().find(|t| t.id == q.tenant) {
+        if app.tenant_policy(&q.tenant).is_some_and(|p| p.is_suspended()) {
+            app.metrics().handshake_rejections.inc(&[("tenant", &q.tenant), ("reason", "tenant_suspended")]);
+            app.audit().record(AuditEvent::TenantSuspended(AuditRecord::new(&q.tenant, "tenant suspended").ip(addr.ip())));
+            let body = json!({ "code": "TENANT_SUSPENDED", "tenant": q.tenant });
+            return (StatusCode::SERVICE_UNAVAILABLE, axum::Json(body)).into_response();
+        }
         let limit = t_cfg.limits.max_sessions_total;
         if limit > 0 {
             let current = app.realtime().sessions.count_tenant_sessions(&q.tenant);
@@ -107,196 +268,576 @@ pub async fn ws_upgrade(
                 return (StatusCode::SERVICE_UNAVAILABLE, headers, "Tenant Capacity Exceeded").into_response();
             }
         }
-    } else { return (StatusCode::BAD_REQUEST, "Unknown Tenant").into_response(); }
+    } else {
+        app.metrics().handshake_rejections.inc(&[("tenant", tenant_label), ("reason", "unknown_tenant")]);
+        return (StatusCode::BAD_REQUEST, "Unknown Tenant").into_response();
+    }
+
+    let identity = match resolve_identity(&app, &q.tenant, q.ticket.as_deref()).await {
+        Ok(id) => id,
+        Err(e) => {
+            let (status, reason) = match e {
+                WsPrismError::Internal(_) => (StatusCode::SERVICE_UNAVAILABLE, "auth_unavailable"),
+                WsPrismError::QuotaExceeded(_) => (StatusCode::TOO_MANY_REQUESTS, "quota_exceeded"),
+                _ => (StatusCode::UNAUTHORIZED, "auth_failed"),
+            };
+            tracing::warn!(tenant=%q.tenant, error=%e, "handshake auth rejected");
+            if reason == "auth_failed" {
+                app.audit().record(AuditEvent::AuthFailed(AuditRecord::new(&q.tenant, e.to_string()).ip(addr.ip())));
+            }
+            app.metrics().handshake_rejections.inc(&[("tenant", &q.tenant), ("reason", reason)]);
+            return (status, e.client_code().as_str()).into_response();
+        }
+    };
 
     app.metrics().ws_upgrades.inc(&[("tenant", &q.tenant), ("status", "ok")]);
     ws.on_upgrade(move |socket| async move {
-        if let Err(e) = run_session(app, q, socket).await { tracing::error!("session error: {}", e); }
+        let span = tracing::info_span!(
+            "ws",
+            tenant = %q.tenant,
+            user = %identity.user_id,
+            trace_id = tracing::field::Empty,
+            sid = tracing::field::Empty,
+            connection_id = tracing::field::Empty,
+        );
+        if let Err(e) = run_session(app, q, identity, addr.ip(), socket).instrument(span).await { tracing::error!("session error: {}", e); }
     })
 }
 
-async fn run_session(app: AppState, q: WsQuery, socket: WebSocket) -> Result<()> {
-    let policy = app.tenant_policy(&q.tenant).ok_or(WsPrismError::BadRequest("unknown tenant".into()))?;
-    let user_id = app.resolve_ticket(&q.ticket)?;
+/// Authenticated (or guest) identity resolved before the upgrade.
+pub(crate) struct Identity {
+    pub(crate) user_id: String,
+    pub(crate) claims: SessionClaims,
+    pub(crate) guest: bool,
+    /// Compiled `scopes` claim, if the ticket carried one.
+    pub(crate) scopes: Option<Vec<ExtRule>>,
+}
+
+pub(crate) async fn resolve_identity(app: &AppState, tenant: &str, ticket: Option<&str>) -> Result<Identity> {
+    match ticket {
+        Some(ticket) => {
+            let (user_id, claims) = app.resolve_ticket(tenant, ticket).await?;
+            let scopes = token_scopes(&claims)?;
+            Ok(Identity { user_id, claims, guest: false, scopes })
+        }
+        None if app.tenant_policy(tenant).is_some_and(|p| p.allow_guest()) => {
+            Ok(Identity { user_id: gen_guest_id(), claims: SessionClaims::default(), guest: true, scopes: None })
+        }
+        None => Err(WsPrismError::AuthFailed),
+    }
+}
+
+/// Ext lane rules from the `scopes` claim (token introspection). Scopes that
+/// do not compile fail authentication rather than widening access.
+fn token_scopes(claims: &SessionClaims) -> Result<Option<Vec<ExtRule>>> {
+    let Some(scopes) = claims.get("scopes").and_then(serde_json::Value::as_array) else {
+        return Ok(None);
+    };
+    let scopes: Vec<String> = scopes.iter().filter_map(serde_json::Value::as_str).map(str::to_owned).collect();
+    compile_scope_rules(&scopes).map(Some).map_err(|_| WsPrismError::AuthFailed)
+}
+
+/// A session's identity and outbound queues, shared by its frame handlers.
+pub(crate) struct SessionIo {
+    pub(crate) app: AppState,
+    pub(crate) tenant: String,
+    pub(crate) user_id: String,
+    pub(crate) connection_id: ConnectionId,
+    pub(crate) trace_id: String,
+    pub(crate) guest: bool,
+    pub(crate) claims: SessionClaims,
+    /// Token scopes, checked after the tenant policy (`Identity::scopes`).
+    pub(crate) scopes: Option<Vec<ExtRule>>,
+    pub(crate) ip: IpAddr,
+    /// Active room and join limits, shared with the `room` service.
+    pub(crate) session: SessionHandle,
+    /// Lifecycle events (`gateway.max_session_events`), also kept by the
+    /// registered `Connection`.
+    pub(crate) events: Option<Arc<SessionEventLog>>,
+    pub(crate) plugin: Option<Arc<PluginHost>>,
+    pub(crate) out_tx: mpsc::Sender<Message>,
+    /// Errors, kicks and goaways: drained before `out_tx`, never dropped.
+    pub(crate) high_tx: mpsc::UnboundedSender<Message>,
+}
+
+impl SessionIo {
+    pub(crate) fn user_key(&self) -> String {
+        format!("{}::{}", self.tenant, self.user_id)
+    }
+
+    /// `ws_active_sessions` kind label.
+    pub(crate) fn kind(&self) -> &'static str {
+        if self.guest { "guest" } else { "user" }
+    }
+
+    /// Record a lifecycle event, if the session keeps a log.
+    pub(crate) fn log_event(&self, event: impl FnOnce() -> SessionEvent) {
+        if let Some(log) = &self.events {
+            log.record(event());
+        }
+    }
+
+    fn audit(&self, reason: &str) -> AuditRecord {
+        AuditRecord::new(self.tenant.as_str(), reason).user(self.user_id.as_str()).ip(self.ip)
+    }
+
+    fn ctx(&self) -> RealtimeCtx {
+        RealtimeCtx::new(self.tenant.clone(), self.user_id.clone(), self.connection_id, self.trace_id.clone(), None, self.app.realtime())
+            .with_guest(self.guest)
+            .with_claims(self.claims.clone())
+            .with_session(self.session.clone())
+    }
+
+    fn room_error(&self, e: &WsPrismError) {
+        self.log_event(|| SessionEvent::Error(e.client_code(), e.to_string().into()));
+        self.app.metrics().service_errors.inc(&[("tenant", &self.tenant), ("lane", "ext"), ("svc", "room"), ("code", e.client_code().as_str())]);
+        let _ = self.high_tx.send(Message::Text(e.to_sys_frame_traced(&self.trace_id)));
+    }
+}
+
+/// A fresh event log, unless `gateway.max_session_events` is 0.
+pub(crate) fn new_event_log(app: &AppState) -> Option<Arc<SessionEventLog>> {
+    let max = app.cfg().gateway.max_session_events;
+    (max > 0).then(|| Arc::new(SessionEventLog::new(max)))
+}
+
+/// Apply `max_sessions_per_user` before registering a session: `false` to
+/// refuse it (`on_exceed: deny`); with `kick_oldest` the user's oldest
+/// session is evicted instead.
+pub(crate) fn admit_user_session(io: &SessionIo, policy: &TenantPolicyRuntime) -> bool {
+    let core = io.app.realtime();
+    let user_key = io.user_key();
+    let sp = policy.session_policy();
+    if core.sessions.count_user_sessions(&user_key) < sp.max_sessions_per_user as usize {
+        return true;
+    }
+    io.app.metrics().policy_decisions.inc(&[("tenant", &io.tenant), ("lane", "session"), ("decision", "reject"), ("reason", "max_user_sessions")]);
+    if matches!(sp.on_exceed, OnExceed::Deny) {
+        return false;
+    }
+    if let Some((victim, victim_conn)) = core.sessions.evict_oldest(&user_key) {
+        victim_conn.send_high(Message::Text(sys_kicked_json("max_sessions_exceeded", &io.trace_id)));
+        victim_conn.send_high(Message::Close(Some(CloseFrame { code: 1008, reason: "kicked".into() })));
+        // The victim's address is not tracked; `user` is the same.
+        io.app.audit().record(AuditEvent::Kicked(AuditRecord::new(io.tenant.as_str(), "max_sessions_exceeded").user(io.user_id.as_str())));
+        core.presence.cleanup_session(&io.tenant, &user_key, victim);
+        io.app.metrics().ws_active_sessions.dec(&[("tenant", &io.tenant), ("kind", io.kind())]);
+    }
+    true
+}
+
+/// Register the session and report `connect`. Dropping the returned guard
+/// undoes both.
+pub(crate) fn open_session(io: &SessionIo, policy: &TenantPolicyRuntime, max_sessions_total: u64) -> Result<SessionCleanup> {
+    let core = io.app.realtime();
+    let metrics = io.app.metrics();
+    let conn = Connection::new(io.out_tx.clone(), io.claims.clone())
+        .with_meter(policy.new_outbound_meter())
+        .with_high_priority(io.high_tx.clone())
+        .with_event_log(io.events.clone());
+    core.sessions.try_insert(io.tenant.clone(), io.user_key(), io.connection_id, conn, max_sessions_total)?;
+    io.log_event(|| SessionEvent::Connected);
+    io.log_event(|| SessionEvent::Authenticated(io.user_id.as_str().into()));
+    metrics.ws_active_sessions.inc(&[("tenant", &io.tenant), ("kind", io.kind())]);
+    let webhooks = io.app.webhooks();
+    if let Some(w) = &webhooks {
+        w.emit(WebhookEvent::connect(&io.tenant, &io.user_id, io.connection_id));
+    }
+    Ok(SessionCleanup {
+        core, tenant_id: io.tenant.clone(), user_key: io.user_key(), connection_id: io.connection_id, kind: io.kind(), metrics,
+        webhooks, user_id: io.user_id.clone(), connected_at: Instant::now(), events: io.events.clone(), reason: "error".to_string(),
+    })
+}
+
+/// Queue `sys.authed`, then join the tenant's `auto_join_rooms`.
+pub(crate) async fn greet(io: &SessionIo, sid: &str, policy: &TenantPolicyRuntime, auto_join_rooms: &[String]) -> Result<()> {
+    // Reply to this session only: `send_to_user` would also reach the user's other sessions.
+    let authed = PreparedMsg::prepare(&sys_authed_reply(&io.tenant, &io.user_id, sid, io.connection_id, &io.trace_id))?;
+    timeout(Duration::from_millis(REPLY_TIMEOUT_MS), io.out_tx.send(authed.to_ws_message())).await
+        .map_err(|_| WsPrismError::Internal("authed reply timed out".into()))?
+        .map_err(|_| WsPrismError::Internal("closed".into()))?;
+
+    if !auto_join_rooms.is_empty() {
+        let ctx = io.ctx().with_replay_on_join(policy.replay_on_join());
+        for room in auto_join_rooms {
+            match ctx.join_room_with_limits(room, io.session.limits()) {
+                Ok(_) => {
+                    let _ = io.out_tx.send(Message::Text(sys_joined_json(room, &io.trace_id))).await;
+                    ctx.replay_room(room);
+                }
+                Err(e) => io.room_error(&e),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What to do with an inbound frame after the pre-decode checks.
+pub(crate) enum Admit {
+    Pass,
+    /// Drop the frame and keep the session.
+    Skip,
+    /// End the session with this close code and reason.
+    Close(u16, String),
+}
+
+/// Checks made before decoding any inbound frame: picks up a reloaded
+/// policy, ends sessions of a suspended tenant and applies the byte rate to
+/// data frames (`frame` is their lane and raw length).
+pub(crate) fn admit_frame(io: &SessionIo, sess: &mut SessionState, frame: Option<(Lane, usize)>) -> Admit {
+    let metrics = io.app.metrics();
+    let policy = sess.refresh_policy(&io.app, &io.tenant);
+    // Normally closed by the reload itself; catches sessions that raced it.
+    if policy.is_suspended() {
+        let _ = io.high_tx.send(Message::Text(sys_goaway_json(GOAWAY_TENANT_SUSPENDED, &io.trace_id)));
+        io.app.audit().record(AuditEvent::TenantSuspended(io.audit("tenant suspended")));
+        return Admit::Close(CLOSE_GOING_AWAY, GOAWAY_TENANT_SUSPENDED.to_string());
+    }
+    // Bandwidth precheck on the raw frame (before decode).
+    if let Some((lane, raw_len)) = frame {
+        io.log_event(|| SessionEvent::FrameReceived(lane, raw_len));
+        metrics.observe_frame_size(FrameDir::Inbound, &io.tenant, lane.as_str(), raw_len);
+        sess.count_bytes(&metrics, FrameDir::Inbound, &io.tenant, lane, raw_len);
+        if let Err(retry_after) = policy.admit_bytes(raw_len, sess.byte_bucket.as_ref()) {
+            let msg = "byte rate exceeded";
+            let d = match lane {
+                Lane::Ext => PolicyDecision::rate_limited(msg, retry_after.saturating_mul(1000)),
+                _ => PolicyDecision::Drop { reason: DecisionReason::Rate },
+            };
+            // Shadow mode lets the frame through (and does not strike).
+            match policy.finish(&metrics, lane, d) {
+                PolicyDecision::Pass => {}
+                PolicyDecision::Reject { .. } => {
+                    let _ = io.high_tx.send(Message::Text(sys_rate_limited_json(msg, retry_after, &io.trace_id)));
+                    if sess.strike() {
+                        policy.record(&metrics, lane, &STRIKE_OUT);
+                        let _ = io.high_tx.send(Message::Text(sys_strike_out_json(&io.trace_id)));
+                        io.app.audit().record(AuditEvent::PolicyClose(io.audit(STRIKE_MSG)));
+                        let (code, reason) = strike_out_close();
+                        return Admit::Close(code, reason);
+                    }
+                    return Admit::Skip;
+                }
+                _ => return Admit::Skip,
+            }
+        }
+    }
+    Admit::Pass
+}
+
+/// Policy, quota and dispatch for one Ext lane envelope.
+/// Returns the close code and reason when the session must end.
+pub(crate) async fn handle_text(io: &SessionIo, sess: &mut SessionState, env: Envelope, bytes_len: usize) -> Option<(u16, String)> {
+    let app = &io.app;
+    let tenant = io.tenant.as_str();
+    let metrics = app.metrics();
+    let policy = Arc::clone(&sess.policy);
+    let plugin_ctx = PluginCtx { tenant, user: &io.user_id, guest: io.guest };
+    // Recorded in policy_decisions (tenant/lane/decision/reason).
+    let conn_rate = match sess.conn_limiter.as_mut().map(|lim| lim.acquire_svc(&env.svc)) {
+        Some(Err(wait_ms)) => policy.finish(&metrics, Lane::Ext, PolicyDecision::rate_limited("rate limited", wait_ms)),
+        _ => PolicyDecision::Pass,
+    };
+    let decision = match conn_rate {
+        PolicyDecision::Pass => {
+            let d = match policy.evaluate_text(&metrics, bytes_len, &env.svc, &env.msg_type, io.guest) {
+                PolicyDecision::Pass => match &io.scopes {
+                    Some(scopes) => policy.evaluate_token_scopes(&metrics, scopes, &env.svc, &env.msg_type),
+                    None => PolicyDecision::Pass,
+                },
+                d => d,
+            };
+            let d = match d {
+                PolicyDecision::Pass => match &io.plugin {
+                    Some(p) => p.check_ext(&metrics, &plugin_ctx, &env),
+                    None => PolicyDecision::Pass,
+                },
+                d => d,
+            };
+            // Quotas count only frames that would otherwise be delivered.
+            match d {
+                PolicyDecision::Pass => policy.evaluate_quota(&metrics, &app.quotas(), &io.user_id, &env.svc, &env.msg_type).await,
+                d => d,
+            }
+        }
+        d => d,
+    };
+    match decision {
+        PolicyDecision::Pass => {},
+        PolicyDecision::Drop { .. } => return None,
+        PolicyDecision::Reject { code, msg, reason, retry_after_ms } => {
+            io.log_event(|| SessionEvent::Error(code, msg.into()));
+            let _ = io.high_tx.send(Message::Text(sys_reject_json(code, msg, retry_after_ms, &io.trace_id)));
+            // Read-only and quota rejects are expected traffic, not abuse.
+            let expected = matches!(reason, DecisionReason::ReadOnly | DecisionReason::Quota);
+            if !expected && sess.strike() {
+                policy.record(&metrics, Lane::Ext, &STRIKE_OUT);
+                let _ = io.high_tx.send(Message::Text(sys_strike_out_json(&io.trace_id)));
+                app.audit().record(AuditEvent::PolicyClose(io.audit(STRIKE_MSG)));
+                return Some(strike_out_close());
+            }
+            return None;
+        },
+        PolicyDecision::Close { code, msg, retry_after_ms, .. } => {
+            io.log_event(|| SessionEvent::Error(code, msg.into()));
+            let _ = io.high_tx.send(Message::Text(sys_reject_json(code, msg, retry_after_ms, &io.trace_id)));
+            app.audit().record(AuditEvent::PolicyClose(io.audit(msg)));
+            return Some((code.close_code(), close_reason(msg, retry_after_ms)));
+        }
+    }
+    let mut ctx = io.ctx()
+        .with_room_limiter(policy.room_limiter())
+        .with_fanout_limit(policy.max_fanout_parallelism())
+        .with_replay_on_join(policy.replay_on_join());
+    // Per-room inbound limit, shared by every sender into the room.
+    if let Some(room) = env.room.as_deref() {
+        if let Err(retry_after) = ctx.check_room_rate(room) {
+            let d = PolicyDecision::rate_limited("room rate limited", retry_after.saturating_mul(1000));
+            if !matches!(policy.finish(&metrics, Lane::Ext, d), PolicyDecision::Pass) {
+                let msg = format!("room rate limited: {room}");
+                let _ = io.high_tx.send(Message::Text(sys_rate_limited_json(&msg, retry_after, &io.trace_id)));
+                return None;
+            }
+        }
+    }
+    let (svc, msg_type, room) = (env.svc.clone(), env.msg_type.clone(), env.room.clone());
+    // Built before dispatch consumes `env`; archived only if it succeeds.
+    let archive = app.egress().filter(|_| policy.archives(&svc, &msg_type)).map(|egress| {
+        let mut record = EgressRecord::new(io.tenant.clone(), io.user_id.clone(), &env);
+        record.room = record.room.or_else(|| ctx.active_room().map(str::to_string));
+        (egress, record)
+    });
+    let dispatcher = app.dispatcher();
+    let known = dispatcher.has_text(&svc);
+    let start = Instant::now();
+    let res = dispatcher.dispatch_text(ctx, env).await;
+    if let (Ok(()), Some((egress, record))) = (&res, archive) {
+        egress.offer(record);
+    }
+    // Always measure Ext lane (handlers that ran only: `svc` is client input).
+    let elapsed = start.elapsed();
+    if known {
+        metrics.observe_dispatch(&[("tenant", tenant), ("lane", "ext"), ("svc", &svc)], elapsed);
+    }
+    if slow_handler(app).is_some_and(|t| elapsed >= t) {
+        tracing::warn!(
+            lane = "ext", svc = %svc, msg_type = %msg_type, room = ?room,
+            elapsed_ms = elapsed.as_millis() as u64, ok = res.is_ok(),
+            "slow handler"
+        );
+    }
+    if let Err(e) = res {
+         if known {
+             metrics.service_errors.inc(&[("tenant", tenant), ("lane", "ext"), ("svc", &svc), ("code", e.client_code().as_str())]);
+         } else {
+             metrics.unknown_service_errors.inc(&[("tenant", tenant), ("lane", "ext"), ("svc", &svc)]);
+         }
+         io.log_event(|| SessionEvent::Error(e.client_code(), e.to_string().into()));
+         let _ = io.high_tx.send(Message::Text(e.to_sys_frame_traced(&io.trace_id)));
+    }
+    None
+}
+
+/// `gateway.slow_handler_threshold_ms`, if enabled.
+fn slow_handler(app: &AppState) -> Option<Duration> {
+    let ms = app.cfg().gateway.slow_handler_threshold_ms;
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+async fn run_session(app: AppState, q: WsQuery, identity: Identity, ip: IpAddr, socket: WebSocket) -> Result<()> {
+    let Identity { user_id, claims, guest: is_guest, scopes } = identity;
     let sid = q.sid.unwrap_or_else(gen_sid);
+    let connection_id = ConnectionId::new();
     let trace_id = gen_trace();
-    let core = app.realtime();
     let dispatcher = app.dispatcher();
     let metrics = app.metrics();
-    let user_key = format!("{}::{}", q.tenant, user_id);
-    let session_key = format!("{}::{}::{}", q.tenant, user_id, sid);
-    let span = tracing::info_span!("ws", %trace_id, t=%q.tenant, u=%user_id, s=%sid);
-    let _enter = span.enter();
+    let span = tracing::Span::current();
+    span.record("trace_id", trace_id.as_str());
+    span.record("sid", sid.as_str());
+    span.record("connection_id", tracing::field::display(connection_id));
     let (out_tx, mut out_rx) = mpsc::channel(1024);
+    let (high_tx, mut high_rx) = mpsc::unbounded_channel();
     let (mut ws_tx, mut ws_rx) = socket.split();
+    // Checked before the upgrade; closed the same way if it is gone since.
+    let (Some(policy), Some(t_cfg)) = (app.tenant_policy(&q.tenant), app.cfg().tenants.iter().find(|t| t.id == q.tenant)) else {
+        close_session(&mut ws_tx, ClientCode::BadRequest.close_code(), "unknown tenant").await;
+        return Ok(());
+    };
+    let io = SessionIo {
+        app: app.clone(), tenant: q.tenant.clone(), user_id: user_id.clone(), connection_id, trace_id: trace_id.clone(), guest: is_guest,
+        claims: claims.clone(), scopes, ip, session: SessionHandle::new(t_cfg.limits.clone()), events: new_event_log(&app), plugin: app.tenant_plugin(&q.tenant), out_tx: out_tx.clone(), high_tx: high_tx.clone(),
+    };
 
-    let sp = policy.session_policy();
-    let max_user_sessions = sp.max_sessions_per_user as usize;
-    let current_user_sessions = core.sessions.count_user_sessions(&user_key);
-    if current_user_sessions >= max_user_sessions {
-         metrics.policy_decisions.inc(&[("tenant", &q.tenant), ("lane", "session"), ("decision", "reject"), ("reason", "max_user_sessions")]);
-         match sp.on_exceed {
-             OnExceed::Deny => {
-                 let _ = out_tx.send(Message::Text(sys_error_json("TOO_MANY_SESSIONS", "limit exceeded", &trace_id))).await;
-                 return Ok(());
-             }
-             OnExceed::KickOldest => {
-                 if let Some((victim, victim_conn)) = core.sessions.evict_oldest(&user_key) {
-                     let _ = victim_conn.tx.try_send(Message::Text(sys_kicked_json("max_sessions_exceeded", &trace_id)));
-                     let _ = victim_conn.tx.try_send(Message::Close(Some(CloseFrame { code: 1008, reason: "kicked".into() })));
-                     core.presence.cleanup_session(&q.tenant, &user_key, &victim);
-                     metrics.ws_active_sessions.dec(&[("tenant", &q.tenant)]);
-                 }
-             }
-         }
+    if !admit_user_session(&io, &policy) {
+        let _ = ws_tx.send(Message::Text(sys_error_json("TOO_MANY_SESSIONS", "limit exceeded", &trace_id))).await;
+        close_session(&mut ws_tx, ClientCode::ResourceExhausted.close_code(), "too many sessions").await;
+        return Ok(());
     }
 
-    let t_cfg = app.cfg().tenants.iter().find(|t| t.id == q.tenant).unwrap();
-    core.sessions.try_insert(q.tenant.clone(), user_key.clone(), session_key.clone(), Connection{ tx: out_tx.clone() }, t_cfg.limits.max_sessions_total)?;
-    metrics.ws_active_sessions.inc(&[("tenant", &q.tenant)]);
-    let _cleanup = SessionCleanup { core: core.clone(), tenant_id: q.tenant.clone(), user_key: user_key.clone(), session_key: session_key.clone(), metrics: metrics.clone() };
-    out_tx.send(Message::Text(sys_authed_json(&q.tenant, &user_id, &sid, &trace_id))).await.map_err(|_| WsPrismError::Internal("closed".into()))?;
+    let mut cleanup = open_session(&io, &policy, t_cfg.limits.max_sessions_total)?;
+    greet(&io, &sid, &policy, &t_cfg.auto_join_rooms).await?;
 
     let gw = &app.cfg().gateway;
     let mut ping_tick = tokio::time::interval(Duration::from_millis(gw.ping_interval_ms));
     let mut idle_tick = tokio::time::interval(Duration::from_millis(1000));
     let idle_timeout = Duration::from_millis(gw.idle_timeout_ms);
     let writer_timeout = Duration::from_millis(gw.writer_send_timeout_ms);
-    let mut sess = SessionState { active_room: None, last_activity: Instant::now(), conn_limiter: policy.new_connection_limiter() };
+    let slow_handler = slow_handler(&app);
+    let mut sess = SessionState::new(policy);
+    let plugin_ctx = PluginCtx { tenant: &q.tenant, user: &user_id, guest: is_guest };
     
     // Sampling Counter
     let mut hot_op_counter: u64 = 0;
 
-    loop {
+    // `Some((code, reason))` when the server ends the session; `None` when the
+    // peer is gone or closed first.
+    let close: Option<(u16, String)> = loop {
         tokio::select! {
-            maybe_out = out_rx.recv() => {
+            maybe_out = async { tokio::select! { biased; Some(m) = high_rx.recv() => Some(m), m = out_rx.recv() => m } } => {
                 match maybe_out {
                     Some(m) => {
-                        if timeout(writer_timeout, ws_tx.send(m)).await.is_err() {
-                             metrics.writer_timeouts.inc(&[("tenant", &q.tenant)]);
-                             break;
+                        let frame = data_frame(&m);
+                        if let Some((lane, len)) = frame {
+                            metrics.observe_frame_size(FrameDir::Outbound, &q.tenant, lane.as_str(), len);
+                        }
+                        match timeout(writer_timeout, ws_tx.send(m)).await {
+                            Ok(Ok(())) => {
+                                if let Some((lane, len)) = frame {
+                                    io.log_event(|| SessionEvent::FrameSent(len));
+                                    sess.count_bytes(&metrics, FrameDir::Outbound, &q.tenant, lane, len);
+                                }
+                            }
+                            Ok(Err(_)) => {}
+                            Err(_) => {
+                                metrics.writer_timeouts.inc(&[("tenant", &q.tenant)]);
+                                // The writer is stuck, so a Close frame would not get through either.
+                                break None;
+                            }
                         }
                     }
-                    None => break,
+                    None => break None,
                 }
             }
             incoming = ws_rx.next() => {
-                let Some(Ok(msg)) = incoming else { break; };
+                let msg = match incoming {
+                    Some(Ok(msg)) => msg,
+                    Some(Err(e)) => {
+                        // Invalid UTF-8 in a text frame fails in the socket layer, before `decode`.
+                        if e.to_string().contains("UTF-8") {
+                            metrics.decode_errors.inc(&[("tenant", &q.tenant), ("reason", "utf8")]);
+                        }
+                        break None;
+                    }
+                    None => break None,
+                };
                 sess.last_activity = Instant::now();
-                let decoded = match decode(msg) {
+                match admit_frame(&io, &mut sess, data_frame(&msg)) {
+                    Admit::Pass => {}
+                    Admit::Skip => continue,
+                    Admit::Close(code, reason) => break Some((code, reason)),
+                }
+                let policy = Arc::clone(&sess.policy);
+                let decode_reason = if matches!(msg, Message::Binary(_)) { "hot" } else { "json" };
+                let decoded = match decode(msg, policy.max_frame_bytes()) {
                     Ok(d) => d,
                     Err(e) => {
-                        metrics.decode_errors.inc(&[("tenant", &q.tenant)]);
-                        let _ = out_tx.send(Message::Text(sys_error_json(e.client_code().as_str(), &e.to_string(), &trace_id))).await;
-                        break;
+                        metrics.decode_errors.inc(&[("tenant", &q.tenant), ("reason", decode_reason)]);
+                        io.log_event(|| SessionEvent::Error(e.client_code(), e.to_string().into()));
+                        let _ = high_tx.send(Message::Text(e.to_sys_frame_traced(&trace_id)));
+                        break Some((e.close_code(), e.to_string()));
                     }
                 };
                 match decoded {
                     Inbound::Ping(p) => { let _ = out_tx.send(Message::Pong(p)).await; },
                     Inbound::Pong(_) => {},
-                    Inbound::Close => break,
+                    Inbound::Close => break None,
                     Inbound::Text { env, bytes_len } => {
-                        if let Some(lim) = sess.conn_limiter.as_mut() {
-                            if !lim.allow() { 
-                                metrics.policy_decisions.inc(&[("tenant", &q.tenant), ("lane", "ext"), ("decision", "drop"), ("reason", "conn_rate_limit")]);
-                                continue; 
-                            }
-                        }
-                        match policy.check_text(bytes_len, &env.svc, &env.msg_type) {
-                            PolicyDecision::Pass => {},
-                            PolicyDecision::Drop => { 
-                                metrics.policy_decisions.inc(&[("tenant", &q.tenant), ("lane", "ext"), ("decision", "drop"), ("reason", "policy")]);
-                                continue; 
-                            },
-                            PolicyDecision::Reject { code, msg } => {
-                                // SAFE LABEL: code.as_str()
-                                metrics.policy_decisions.inc(&[("tenant", &q.tenant), ("lane", "ext"), ("decision", "reject"), ("reason", code.as_str())]);
-                                let _ = out_tx.send(Message::Text(sys_error_json(code.as_str(), msg, &trace_id))).await;
-                                continue;
-                            },
-                            PolicyDecision::Close { code, msg } => {
-                                // SAFE LABEL: code.as_str()
-                                metrics.policy_decisions.inc(&[("tenant", &q.tenant), ("lane", "ext"), ("decision", "close"), ("reason", code.as_str())]);
-                                let _ = out_tx.send(Message::Text(sys_error_json(code.as_str(), msg, &trace_id))).await;
-                                break;
-                            }
-                        }
-                        if env.svc == "room" && env.msg_type == "join" {
-                            let room = env.room.clone().unwrap_or_else(|| "default".to_string());
-                            let ctx = RealtimeCtx::new(q.tenant.clone(), user_id.clone(), sid.clone(), trace_id.clone(), sess.active_room.clone(), core.clone());
-                            match ctx.join_room_with_limits(&room, &t_cfg.limits) {
-                                Ok(_) => {
-                                    sess.active_room = Some(room.clone());
-                                    let _ = out_tx.send(Message::Text(sys_joined_json(&room, &trace_id))).await;
-                                },
-                                Err(e) => {
-                                    metrics.service_errors.inc(&[("tenant", &q.tenant), ("svc", "room"), ("type", "join_failed")]);
-                                    let _ = out_tx.send(Message::Text(sys_error_json(e.client_code().as_str(), &e.to_string(), &trace_id))).await;
-                                }
-                            }
-                            continue;
-                        }
-                        if env.svc == "room" && env.msg_type == "leave" {
-                            if let Some(room) = sess.active_room.take() {
-                                let ctx = RealtimeCtx::new(q.tenant.clone(), user_id.clone(), sid.clone(), trace_id.clone(), None, core.clone());
-                                ctx.leave_room(&room);
-                            }
-                            let _ = out_tx.send(Message::Text(sys_left_json(&trace_id))).await;
-                            continue;
-                        }
-                        let ctx = RealtimeCtx::new(q.tenant.clone(), user_id.clone(), sid.clone(), trace_id.clone(), sess.active_room.clone(), core.clone());
-                        let start = Instant::now();
-                        let res = dispatcher.dispatch_text(ctx, env).await;
-                        // Always measure Ext lane
-                        metrics.dispatch_duration.observe(&[("tenant", &q.tenant), ("lane", "ext")], start.elapsed());
-                        if let Err(e) = res {
-                             metrics.service_errors.inc(&[("tenant", &q.tenant), ("lane", "ext")]);
-                             let _ = out_tx.send(Message::Text(sys_error_json(e.client_code().as_str(), &e.to_string(), &trace_id))).await;
+                        if let Some(close) = handle_text(&io, &mut sess, env, bytes_len).await {
+                            break Some(close);
                         }
                     },
                     Inbound::Hot { frame, bytes_len } => {
-                         match policy.check_hot(bytes_len, frame.svc_id, frame.opcode) {
-                            PolicyDecision::Pass => {},
-                            PolicyDecision::Drop => { 
-                                metrics.policy_decisions.inc(&[("tenant", &q.tenant), ("lane", "hot"), ("decision", "drop"), ("reason", "policy")]);
-                                continue; 
+                         let decision = match policy.evaluate_hot(&metrics, bytes_len, frame.svc_id, frame.opcode, is_guest) {
+                            PolicyDecision::Pass => match &io.plugin {
+                                Some(p) => p.check_hot(&metrics, &plugin_ctx, &frame),
+                                None => PolicyDecision::Pass,
                             },
-                            PolicyDecision::Reject { code, msg } => {
-                                metrics.policy_decisions.inc(&[("tenant", &q.tenant), ("lane", "hot"), ("decision", "reject"), ("reason", code.as_str())]);
-                                if let HotErrorMode::SysError = policy.hot_error_mode() {
-                                    let _ = out_tx.send(Message::Text(sys_error_json(code.as_str(), msg, &trace_id))).await;
+                            d => d,
+                         };
+                         match decision {
+                            PolicyDecision::Pass => {},
+                            PolicyDecision::Drop { .. } => continue,
+                            PolicyDecision::Reject { code, msg, reason, .. } => {
+                                let sys_error = matches!(policy.hot_error_mode(), HotErrorMode::SysError);
+                                if sys_error {
+                                    let _ = high_tx.send(Message::Text(sys_error_json(code.as_str(), msg, &trace_id)));
+                                }
+                                if reason != DecisionReason::ReadOnly && sess.strike() {
+                                    policy.record(&metrics, Lane::Hot, &STRIKE_OUT);
+                                    if sys_error {
+                                        let _ = high_tx.send(Message::Text(sys_strike_out_json(&trace_id)));
+                                    }
+                                    app.audit().record(AuditEvent::PolicyClose(io.audit(STRIKE_MSG)));
+                                    break Some(strike_out_close());
                                 }
                                 continue;
                             },
-                            PolicyDecision::Close { code, msg } => {
-                                metrics.policy_decisions.inc(&[("tenant", &q.tenant), ("lane", "hot"), ("decision", "close"), ("reason", code.as_str())]);
+                            PolicyDecision::Close { code, msg, .. } => {
                                 if let HotErrorMode::SysError = policy.hot_error_mode() {
-                                    let _ = out_tx.send(Message::Text(sys_error_json(code.as_str(), msg, &trace_id))).await;
+                                    let _ = high_tx.send(Message::Text(sys_error_json(code.as_str(), msg, &trace_id)));
                                 }
-                                break;
+                                app.audit().record(AuditEvent::PolicyClose(io.audit(msg)));
+                                break Some((code.close_code(), msg.to_string()));
                             }
                          }
-                         if policy.hot_requires_active_room() && sess.active_room.is_none() {
+                         let active_room = io.session.active_room();
+                         if policy.hot_requires_active_room() && active_room.is_none() {
                              if let HotErrorMode::SysError = policy.hot_error_mode() {
-                                 let _ = out_tx.send(Message::Text(sys_error_json("BAD_REQUEST", "no active room", &trace_id))).await;
+                                 let _ = high_tx.send(Message::Text(sys_error_json("BAD_REQUEST", "no active room", &trace_id)));
                              }
                              continue;
                          }
-                         let ctx = RealtimeCtx::new(q.tenant.clone(), user_id.clone(), sid.clone(), trace_id.clone(), sess.active_room.clone(), core.clone());
+                         let mut ctx = io.ctx().with_room_limiter(policy.room_limiter()).with_fanout_limit(policy.max_fanout_parallelism());
+                         if let Some(room) = active_room.as_deref() {
+                             if ctx.check_room_rate(room).is_err()
+                                 && !matches!(policy.finish(&metrics, Lane::Hot, PolicyDecision::Drop { reason: DecisionReason::Rate }), PolicyDecision::Pass)
+                             {
+                                 continue;
+                             }
+                         }
                          
                          // Hot Lane Sampling (1/1024)
                          hot_op_counter = hot_op_counter.wrapping_add(1);
                          let should_sample = (hot_op_counter & 1023) == 0;
-                         let start = if should_sample { Some(Instant::now()) } else { None };
+                         // Timed on every frame only when slow handlers are logged.
+                         let start = (should_sample || slow_handler.is_some()).then(Instant::now);
                          
+                         let (svc_id, opcode) = (frame.svc_id, frame.opcode);
+                         let known = dispatcher.has_hot(svc_id);
                          let res = dispatcher.dispatch_hot(ctx, frame).await;
+                         let svc = svc_id.to_string();
                          if let Some(s) = start {
-                             metrics.dispatch_duration.observe(&[("tenant", &q.tenant), ("lane", "hot")], s.elapsed());
+                             let elapsed = s.elapsed();
+                             if should_sample && known {
+                                 metrics.observe_dispatch(&[("tenant", &q.tenant), ("lane", "hot"), ("svc", &svc)], elapsed);
+                             }
+                             if slow_handler.is_some_and(|t| elapsed >= t) {
+                                 tracing::warn!(
+                                     lane = "hot", svc_id, opcode, room = ?active_room,
+                                     elapsed_ms = elapsed.as_millis() as u64, ok = res.is_ok(),
+                                     "slow handler"
+                                 );
+                             }
                          }
                          if let Err(e) = res {
-                             metrics.service_errors.inc(&[("tenant", &q.tenant), ("lane", "hot")]);
+                             if known {
+                                 metrics.service_errors.inc(&[("tenant", &q.tenant), ("lane", "hot"), ("svc", &svc), ("code", e.client_code().as_str())]);
+                             } else {
+                                 metrics.unknown_service_errors.inc(&[("tenant", &q.tenant), ("lane", "hot"), ("svc", &svc)]);
+                             }
+                             io.log_event(|| SessionEvent::Error(e.client_code(), e.to_string().into()));
                              if let HotErrorMode::SysError = policy.hot_error_mode() {
-                                 let _ = out_tx.send(Message::Text(sys_error_json(e.client_code().as_str(), &e.to_string(), &trace_id))).await;
+                                 let _ = high_tx.send(Message::Text(e.to_sys_frame_traced(&trace_id)));
                              }
                          }
                     }
@@ -305,11 +846,36 @@ async fn run_session(app: AppState, q: WsQuery, socket: WebSocket) -> Result<()>
             _ = ping_tick.tick() => { let _ = out_tx.send(Message::Ping(Vec::new())).await; }
             _ = idle_tick.tick() => {
                 if sess.last_activity.elapsed() >= idle_timeout {
-                    let _ = out_tx.send(Message::Text(sys_error_json("TIMEOUT", "idle", &trace_id))).await;
-                    break;
+                    let _ = high_tx.send(Message::Text(sys_error_json("TIMEOUT", "idle", &trace_id)));
+                    break Some((CLOSE_NORMAL, "idle timeout".to_string()));
                 }
             }
         }
+    };
+
+    cleanup.reason = close.as_ref().map_or("client_closed", |(_, reason)| reason.as_str()).to_string();
+    if let Some((code, reason)) = close {
+        // Flush what was queued before the decision (e.g. the sys.error), then close.
+        while let Ok(m) = high_rx.try_recv().or_else(|_| out_rx.try_recv()) {
+            let frame = data_frame(&m);
+            if !matches!(timeout(writer_timeout, ws_tx.send(m)).await, Ok(Ok(()))) {
+                break;
+            }
+            if let Some((lane, len)) = frame {
+                sess.count_bytes(&metrics, FrameDir::Outbound, &q.tenant, lane, len);
+            }
+        }
+        let _ = timeout(writer_timeout, close_session(&mut ws_tx, code, &reason)).await;
+        // Wait for the peer's Close so the socket is not reset under it.
+        let _ = timeout(writer_timeout, async {
+            while let Some(Ok(m)) = ws_rx.next().await {
+                if matches!(m, Message::Close(_)) {
+                    break;
+                }
+            }
+        })
+        .await;
     }
+    tracing::info!(bytes_in = sess.bytes_in, bytes_out = sess.bytes_out, "session summary");
     Ok(())
 }
@@ -0,0 +1,117 @@
+//! gRPC [`UpstreamClient`] (`upstream-grpc` feature).
+//!
+//! Speaks `wsprism.upstream.v1.Upstream` from `proto/upstream.proto`; the
+//! messages in [`pb`] are written out by hand to match it, so building
+//! needs no `protoc`. Each endpoint gets one lazily connected HTTP/2
+//! channel, shared by every tenant and service that uses it.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::{Channel, Endpoint};
+use wsprism_core::error::{Result, WsPrismError};
+
+use super::{UpstreamClient, UpstreamReply, UpstreamRequest, UpstreamRoute};
+
+/// Messages of `proto/upstream.proto`.
+pub mod pb {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DispatchRequest {
+        #[prost(string, tag = "1")]
+        pub tenant: String,
+        #[prost(string, tag = "2")]
+        pub user: String,
+        #[prost(string, tag = "3")]
+        pub connection_id: String,
+        #[prost(string, tag = "4")]
+        pub envelope: String,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Route {
+        Reply = 0,
+        User = 1,
+        Room = 2,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Outgoing {
+        #[prost(enumeration = "Route", tag = "1")]
+        pub route: i32,
+        #[prost(string, tag = "2")]
+        pub target: String,
+        #[prost(string, tag = "3")]
+        pub payload: String,
+    }
+}
+
+/// Path of the `Dispatch` method.
+pub const DISPATCH_PATH: &str = "/wsprism.upstream.v1.Upstream/Dispatch";
+
+#[derive(Default)]
+pub struct GrpcClient {
+    channels: DashMap<String, Channel>,
+}
+
+impl GrpcClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn channel(&self, endpoint: &str, timeout: Duration) -> Result<Channel> {
+        if let Some(ch) = self.channels.get(endpoint) {
+            return Ok(ch.clone());
+        }
+        let ch = Endpoint::from_shared(endpoint.to_string())
+            .map_err(|e| WsPrismError::Internal(format!("upstream endpoint {endpoint}: {e}")))?
+            .connect_timeout(timeout)
+            .connect_lazy();
+        Ok(self.channels.entry(endpoint.to_string()).or_insert(ch).clone())
+    }
+}
+
+fn reply(msg: pb::Outgoing) -> Result<UpstreamReply> {
+    let route = match pb::Route::try_from(msg.route) {
+        Ok(pb::Route::Reply) => UpstreamRoute::Reply,
+        Ok(pb::Route::User) if !msg.target.is_empty() => UpstreamRoute::User(msg.target),
+        Ok(pb::Route::Room) if !msg.target.is_empty() => UpstreamRoute::Room(msg.target),
+        _ => return Err(WsPrismError::Internal(format!("bad upstream route {} {:?}", msg.route, msg.target))),
+    };
+    Ok(UpstreamReply { route, payload: Bytes::from(msg.payload) })
+}
+
+fn status(s: tonic::Status) -> WsPrismError {
+    WsPrismError::Internal(format!("upstream status {:?}: {}", s.code(), s.message()))
+}
+
+#[async_trait]
+impl UpstreamClient for GrpcClient {
+    async fn dispatch(
+        &self,
+        endpoint: &str,
+        req: UpstreamRequest,
+        timeout: Duration,
+    ) -> Result<BoxStream<'static, Result<UpstreamReply>>> {
+        let mut grpc = tonic::client::Grpc::new(self.channel(endpoint, timeout)?);
+        grpc.ready().await.map_err(|e| WsPrismError::Internal(format!("upstream {endpoint}: {e}")))?;
+        let mut request = tonic::Request::new(pb::DispatchRequest {
+            tenant: req.tenant,
+            user: req.user,
+            connection_id: req.connection_id,
+            envelope: req.envelope,
+        });
+        request.set_timeout(timeout);
+        let codec = tonic_prost::ProstCodec::<pb::DispatchRequest, pb::Outgoing>::default();
+        let resp = grpc
+            .server_streaming(request, PathAndQuery::from_static(DISPATCH_PATH), codec)
+            .await
+            .map_err(status)?;
+        Ok(resp.into_inner().map(|m| m.map_err(status).and_then(reply)).boxed())
+    }
+}
@@ -0,0 +1,224 @@
+//! Ext lane services POSTed to tenant backends (tenant `http_upstreams`).
+//!
+//! A message for a forwarded `svc` is one `POST` of the envelope JSON to
+//! `url`, with the caller in `X-Wsprism-Tenant`, `X-Wsprism-User`,
+//! `X-Wsprism-Connection-Id` and `X-Wsprism-Trace-Id`. A 2xx answer is a
+//! JSON array of [`Instruction`]s (an empty body means none); all of them
+//! are checked before any is delivered. Other answers fail the message with
+//! `sys.error` `INTERNAL`.
+//!
+//! 5xx answers are retried up to `max_attempts` times, but only for
+//! messages flagged [`FLAG_WANT_ACK`]: the client resends those anyway, and
+//! nothing has been delivered for them yet.
+//!
+//! HTTP delivery needs the `upstream-http` feature ([`ReqwestClient`]).
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::Deserialize;
+use serde_json::Value;
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::text::{Envelope, FLAG_WANT_ACK};
+
+#[cfg(feature = "upstream-http")]
+pub use super::http_client::ReqwestClient;
+
+use crate::config::schema::{HttpUpstreamConfig, TenantConfig};
+use crate::dispatch::TextService;
+use crate::realtime::types::REPLY_TIMEOUT_MS;
+use crate::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCtx};
+
+/// Request headers carrying the caller.
+pub const TENANT_HEADER: &str = "x-wsprism-tenant";
+pub const USER_HEADER: &str = "x-wsprism-user";
+pub const CONNECTION_ID_HEADER: &str = "x-wsprism-connection-id";
+pub const TRACE_ID_HEADER: &str = "x-wsprism-trace-id";
+
+/// Transport for the POSTs.
+#[async_trait]
+pub trait HttpUpstreamClient: Send + Sync {
+    /// POST `body` (JSON) to `cfg.url` within `cfg.timeout_ms`. Returns the
+    /// status and body; a body over `cfg.max_response_bytes` is an error.
+    async fn post(&self, cfg: &HttpUpstreamConfig, headers: &[(&'static str, String)], body: Bytes) -> Result<(u16, Bytes)>;
+}
+
+/// Recipient of an [`Instruction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionTarget {
+    /// The calling session.
+    Reply,
+    /// Every member of `room` (same tenant).
+    Room,
+    /// Every session of `user` (same tenant).
+    User,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionQos {
+    /// Dropped for recipients whose queue is full.
+    Lossy,
+    /// Room fan-out waits for slow recipients (`REPLY_TIMEOUT_MS`).
+    #[default]
+    Reliable,
+}
+
+/// One message the backend asks the gateway to send.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Instruction {
+    pub to: InstructionTarget,
+    #[serde(default)]
+    pub room: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub qos: InstructionQos,
+    /// Sent as a JSON text frame.
+    pub payload: Value,
+}
+
+impl Instruction {
+    fn check(&self) -> Result<()> {
+        let ok = match self.to {
+            InstructionTarget::Reply => true,
+            InstructionTarget::Room => self.room.as_deref().is_some_and(|r| !r.is_empty()),
+            InstructionTarget::User => self.user.as_deref().is_some_and(|u| !u.is_empty()),
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(WsPrismError::Internal(format!("upstream instruction to {:?} lacks its target", self.to)))
+        }
+    }
+}
+
+/// Parse a 2xx body into instructions, checking every one.
+pub fn parse_instructions(body: &[u8]) -> Result<Vec<Instruction>> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Vec::new());
+    }
+    let out: Vec<Instruction> = serde_json::from_slice(body)
+        .map_err(|e| WsPrismError::Internal(format!("upstream response invalid: {e}")))?;
+    out.iter().try_for_each(Instruction::check)?;
+    Ok(out)
+}
+
+/// [`TextService`] POSTing one `svc` to each tenant's configured URL.
+/// Tenants without an entry for it get `NOT_ALLOWED`.
+pub struct HttpUpstreamService {
+    svc: &'static str,
+    tenants: HashMap<String, HttpUpstreamConfig>,
+    client: Arc<dyn HttpUpstreamClient>,
+}
+
+impl HttpUpstreamService {
+    pub fn new(svc: &str, tenants: &[TenantConfig], client: Arc<dyn HttpUpstreamClient>) -> Self {
+        let tenants = tenants
+            .iter()
+            .filter_map(|t| Some((t.id.clone(), t.http_upstreams.get(svc)?.clone())))
+            .collect();
+        // Registered once at startup; the dispatcher keys services by
+        // `&'static str`.
+        let svc: &'static str = Box::leak(svc.to_string().into_boxed_str());
+        Self { svc, tenants, client }
+    }
+
+    /// One service per `svc` named in any tenant's `http_upstreams`.
+    pub fn all(tenants: &[TenantConfig], client: Arc<dyn HttpUpstreamClient>) -> Vec<Arc<Self>> {
+        let names: BTreeSet<&str> =
+            tenants.iter().flat_map(|t| t.http_upstreams.keys()).map(String::as_str).collect();
+        names.into_iter().map(|svc| Arc::new(Self::new(svc, tenants, client.clone()))).collect()
+    }
+
+    /// [`all`](Self::all) over the reqwest client; empty if no tenant
+    /// configures `http_upstreams`.
+    pub fn from_config(tenants: &[TenantConfig]) -> Result<Vec<Arc<Self>>> {
+        let Some(t) = tenants.iter().find(|t| !t.http_upstreams.is_empty()) else {
+            return Ok(Vec::new());
+        };
+        #[cfg(feature = "upstream-http")]
+        {
+            let _ = t;
+            Ok(Self::all(tenants, Arc::new(ReqwestClient::new()?)))
+        }
+        #[cfg(not(feature = "upstream-http"))]
+        {
+            Err(WsPrismError::BadRequest(format!(
+                "tenant {} configures http_upstreams but the gateway was built without the upstream-http feature",
+                t.id
+            )))
+        }
+    }
+
+    async fn call(&self, ctx: &RealtimeCtx, cfg: &HttpUpstreamConfig, env: &Envelope) -> Result<()> {
+        let body = Bytes::from(
+            serde_json::to_vec(env).map_err(|e| WsPrismError::Internal(format!("envelope encode failed: {e}")))?,
+        );
+        let headers = [
+            (TENANT_HEADER, ctx.tenant().to_string()),
+            (USER_HEADER, ctx.user().to_string()),
+            (CONNECTION_ID_HEADER, ctx.connection_id().to_string()),
+            (TRACE_ID_HEADER, ctx.trace_id.to_string()),
+        ];
+        let attempts = if env.flags & FLAG_WANT_ACK != 0 { cfg.max_attempts } else { 1 };
+        let mut attempt = 1;
+        let (status, resp) = loop {
+            let (status, resp) = self.client.post(cfg, &headers, body.clone()).await?;
+            if (500..600).contains(&status) && attempt < attempts {
+                attempt += 1;
+                continue;
+            }
+            break (status, resp);
+        };
+        if !(200..300).contains(&status) {
+            return Err(WsPrismError::Internal(format!("upstream answered HTTP {status}")));
+        }
+        for ins in parse_instructions(&resp)? {
+            let qos = match ins.qos {
+                InstructionQos::Lossy => QoS::Lossy,
+                InstructionQos::Reliable => QoS::Reliable { timeout_ms: REPLY_TIMEOUT_MS },
+            };
+            let out = Outgoing { qos, payload: Payload::TextJson(ins.payload), priority: Priority::Normal };
+            // Delivery problems are the recipient's, not the backend's.
+            let delivered = match (ins.to, ins.room.as_deref(), ins.user.as_deref()) {
+                (InstructionTarget::Room, Some(room), _) if ins.qos == InstructionQos::Lossy => {
+                    ctx.publish_room_lossy(room, out)
+                }
+                (InstructionTarget::Room, Some(room), _) => ctx.publish_room_reliable(room, out).await,
+                (InstructionTarget::User, _, Some(user)) => ctx.send_to_tenant_user(user, out),
+                _ => ctx.send_to_session(out),
+            };
+            if let Err(e) = delivered {
+                tracing::debug!(svc = self.svc, to = ?ins.to, error = %e, "upstream instruction not delivered");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TextService for HttpUpstreamService {
+    fn svc(&self) -> &'static str {
+        self.svc
+    }
+
+    async fn handle(&self, ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
+        let cfg = self
+            .tenants
+            .get(ctx.tenant())
+            .ok_or_else(|| WsPrismError::NotAllowed(format!("{} has no upstream for this tenant", self.svc)))?;
+        let start = Instant::now();
+        let result = self.call(&ctx, cfg, &env).await;
+        if let Some(m) = ctx.metrics() {
+            m.upstream_duration.observe(&[("tenant", ctx.tenant()), ("svc", self.svc)], start.elapsed());
+        }
+        result.map_err(|e| WsPrismError::Internal(format!("upstream {} failed: {e}", self.svc)))?;
+        Ok(None)
+    }
+}
@@ -0,0 +1,52 @@
+//! reqwest [`HttpUpstreamClient`] (`upstream-http` feature).
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use wsprism_core::error::{Result, WsPrismError};
+
+use super::http::HttpUpstreamClient;
+use crate::config::schema::HttpUpstreamConfig;
+
+pub struct ReqwestClient {
+    http: reqwest::Client,
+}
+
+impl ReqwestClient {
+    pub fn new() -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .build()
+            .map_err(|e| WsPrismError::Internal(format!("upstream client build failed: {e}")))?;
+        Ok(Self { http })
+    }
+}
+
+#[async_trait]
+impl HttpUpstreamClient for ReqwestClient {
+    async fn post(&self, cfg: &HttpUpstreamConfig, headers: &[(&'static str, String)], body: Bytes) -> Result<(u16, Bytes)> {
+        let mut req = self
+            .http
+            .post(&cfg.url)
+            .timeout(Duration::from_millis(cfg.timeout_ms))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        for (name, value) in headers {
+            req = req.header(*name, value);
+        }
+        let mut resp = req.send().await.map_err(|e| WsPrismError::Internal(format!("upstream request: {e}")))?;
+        let too_large = || WsPrismError::Internal(format!("upstream response over {} bytes", cfg.max_response_bytes));
+        if resp.content_length().is_some_and(|n| n > cfg.max_response_bytes as u64) {
+            return Err(too_large());
+        }
+        let status = resp.status().as_u16();
+        let mut out = BytesMut::new();
+        while let Some(chunk) = resp.chunk().await.map_err(|e| WsPrismError::Internal(format!("upstream response: {e}")))? {
+            if out.len() + chunk.len() > cfg.max_response_bytes {
+                return Err(too_large());
+            }
+            out.extend_from_slice(&chunk);
+        }
+        Ok((status, out.freeze()))
+    }
+}
@@ -0,0 +1,259 @@
+//! Ext lane services forwarded to tenant backends (tenant `upstreams`).
+//!
+//! Each `svc` named in some tenant's `upstreams` is registered as one
+//! [`UpstreamTextService`]. A message for it becomes one `Dispatch` call
+//! carrying `(tenant, user, connection_id, envelope)`; every reply the
+//! backend streams back is delivered as it arrives, routed by its
+//! [`UpstreamRoute`]: to the calling session, to a user of the tenant, or to
+//! a room.
+//!
+//! The whole call, replies included, must finish within `timeout_ms`.
+//! `failure_threshold` consecutive failed calls open the tenant's circuit:
+//! for `cooldown_ms` calls fail at once, without contacting the backend.
+//! Clients see failures as `sys.error` `INTERNAL`.
+//!
+//! The gRPC transport needs the `upstream-grpc` feature ([`GrpcClient`]).
+//! Backends that speak plain HTTP use [`HttpUpstreamService`] instead
+//! (tenant `http_upstreams`, see [`http`]). Both record
+//! `wsprism_upstream_duration_seconds{tenant,svc}`.
+
+#[cfg(feature = "upstream-grpc")]
+mod grpc;
+pub mod http;
+#[cfg(feature = "upstream-http")]
+mod http_client;
+
+#[cfg(feature = "upstream-grpc")]
+pub use grpc::{pb, GrpcClient, DISPATCH_PATH};
+pub use http::{HttpUpstreamClient, HttpUpstreamService};
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use tokio::time::Instant;
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::text::Envelope;
+
+use crate::config::schema::{TenantConfig, UpstreamConfig};
+use crate::dispatch::TextService;
+use crate::realtime::types::REPLY_TIMEOUT_MS;
+use crate::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCtx};
+
+/// One forwarded Ext lane message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamRequest {
+    pub tenant: String,
+    pub user: String,
+    pub connection_id: String,
+    /// The envelope as JSON.
+    pub envelope: String,
+}
+
+/// Where a reply goes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpstreamRoute {
+    /// The calling session.
+    Reply,
+    /// Every session of this user (same tenant).
+    User(String),
+    /// Every member of this room (same tenant), reliable fan-out.
+    Room(String),
+}
+
+/// One streamed reply; `payload` is sent as a text frame unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamReply {
+    pub route: UpstreamRoute,
+    pub payload: Bytes,
+}
+
+/// Transport to the backends.
+#[async_trait]
+pub trait UpstreamClient: Send + Sync {
+    /// Start a call to `endpoint`. An error, from the call or in the stream,
+    /// counts as a failed call.
+    async fn dispatch(
+        &self,
+        endpoint: &str,
+        req: UpstreamRequest,
+        timeout: Duration,
+    ) -> Result<BoxStream<'static, Result<UpstreamReply>>>;
+}
+
+/// Consecutive-failure circuit breaker. Once `cooldown` has passed calls are
+/// let through again; the next failure reopens it, a success closes it.
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self { threshold, cooldown, state: Mutex::new(BreakerState::default()) }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn allows(&self) -> bool {
+        self.state().open_until.is_none_or(|until| Instant::now() >= until)
+    }
+
+    fn succeeded(&self) {
+        *self.state() = BreakerState::default();
+    }
+
+    /// Whether this failure opened the circuit.
+    fn failed(&self) -> bool {
+        let mut s = self.state();
+        s.failures = s.failures.saturating_add(1);
+        if s.failures < self.threshold {
+            return false;
+        }
+        s.open_until = Some(Instant::now() + self.cooldown);
+        true
+    }
+}
+
+struct TenantRoute {
+    endpoint: String,
+    timeout: Duration,
+    breaker: CircuitBreaker,
+}
+
+impl TenantRoute {
+    fn new(cfg: &UpstreamConfig) -> Self {
+        Self {
+            endpoint: cfg.endpoint.clone(),
+            timeout: Duration::from_millis(cfg.timeout_ms),
+            breaker: CircuitBreaker::new(cfg.failure_threshold, Duration::from_millis(cfg.cooldown_ms)),
+        }
+    }
+}
+
+/// [`TextService`] forwarding one `svc` to each tenant's configured backend.
+/// Tenants without an entry for it get `NOT_ALLOWED`.
+pub struct UpstreamTextService {
+    svc: &'static str,
+    tenants: HashMap<String, TenantRoute>,
+    client: Arc<dyn UpstreamClient>,
+}
+
+impl UpstreamTextService {
+    pub fn new(svc: &str, tenants: &[TenantConfig], client: Arc<dyn UpstreamClient>) -> Self {
+        let tenants = tenants
+            .iter()
+            .filter_map(|t| Some((t.id.clone(), TenantRoute::new(t.upstreams.get(svc)?))))
+            .collect();
+        // Registered once at startup; the dispatcher keys services by
+        // `&'static str`.
+        let svc: &'static str = Box::leak(svc.to_string().into_boxed_str());
+        Self { svc, tenants, client }
+    }
+
+    /// One service per `svc` named in any tenant's `upstreams`.
+    pub fn all(tenants: &[TenantConfig], client: Arc<dyn UpstreamClient>) -> Vec<Arc<Self>> {
+        let names: BTreeSet<&str> = tenants.iter().flat_map(|t| t.upstreams.keys()).map(String::as_str).collect();
+        names.into_iter().map(|svc| Arc::new(Self::new(svc, tenants, client.clone()))).collect()
+    }
+
+    /// [`all`](Self::all) over the gRPC client; empty if no tenant
+    /// configures `upstreams`.
+    pub fn from_config(tenants: &[TenantConfig]) -> Result<Vec<Arc<Self>>> {
+        let Some(t) = tenants.iter().find(|t| !t.upstreams.is_empty()) else {
+            return Ok(Vec::new());
+        };
+        #[cfg(feature = "upstream-grpc")]
+        {
+            let _ = t;
+            Ok(Self::all(tenants, Arc::new(GrpcClient::new())))
+        }
+        #[cfg(not(feature = "upstream-grpc"))]
+        {
+            Err(WsPrismError::BadRequest(format!(
+                "tenant {} configures upstreams but the gateway was built without the upstream-grpc feature",
+                t.id
+            )))
+        }
+    }
+
+    async fn call(&self, ctx: &RealtimeCtx, route: &TenantRoute, req: UpstreamRequest) -> Result<()> {
+        let mut replies = self.client.dispatch(&route.endpoint, req, route.timeout).await?;
+        while let Some(reply) = replies.next().await {
+            let reply = reply?;
+            let out = Outgoing {
+                qos: QoS::Reliable { timeout_ms: REPLY_TIMEOUT_MS },
+                payload: Payload::Utf8Bytes(reply.payload),
+                priority: Priority::Normal,
+            };
+            // Delivery problems are the recipient's, not the backend's.
+            let delivered = match &reply.route {
+                UpstreamRoute::Reply => ctx.send_to_session(out),
+                UpstreamRoute::User(user) => ctx.send_to_tenant_user(user, out),
+                UpstreamRoute::Room(room) => ctx.publish_room_reliable(room, out).await,
+            };
+            if let Err(e) = delivered {
+                tracing::debug!(svc = self.svc, route = ?reply.route, error = %e, "upstream reply not delivered");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TextService for UpstreamTextService {
+    fn svc(&self) -> &'static str {
+        self.svc
+    }
+
+    async fn handle(&self, ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
+        let route = self
+            .tenants
+            .get(ctx.tenant())
+            .ok_or_else(|| WsPrismError::NotAllowed(format!("{} has no upstream for this tenant", self.svc)))?;
+        if !route.breaker.allows() {
+            return Err(WsPrismError::Internal(format!("upstream {} unavailable", self.svc)));
+        }
+        let envelope = serde_json::to_string(&env)
+            .map_err(|e| WsPrismError::Internal(format!("envelope encode failed: {e}")))?;
+        let req = UpstreamRequest {
+            tenant: ctx.tenant().to_string(),
+            user: ctx.user().to_string(),
+            connection_id: ctx.connection_id().to_string(),
+            envelope,
+        };
+        let start = std::time::Instant::now();
+        let result = match tokio::time::timeout(route.timeout, self.call(&ctx, route, req)).await {
+            Ok(r) => r,
+            Err(_) => Err(WsPrismError::Internal("deadline exceeded".into())),
+        };
+        if let Some(m) = ctx.metrics() {
+            m.upstream_duration.observe(&[("tenant", ctx.tenant()), ("svc", self.svc)], start.elapsed());
+        }
+        match result {
+            Ok(()) => {
+                route.breaker.succeeded();
+                Ok(None)
+            }
+            Err(e) => {
+                if route.breaker.failed() {
+                    tracing::warn!(svc = self.svc, tenant = ctx.tenant(), error = %e, "upstream circuit open");
+                }
+                Err(WsPrismError::Internal(format!("upstream {} failed: {e}", self.svc)))
+            }
+        }
+    }
+}
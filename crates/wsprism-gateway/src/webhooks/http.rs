@@ -0,0 +1,65 @@
+//! HTTP [`WebhookSender`] (`webhooks` feature).
+//!
+//! Each batch is one `POST` of `application/json` to `webhooks.url`, signed
+//! with `X-Wsprism-Signature: sha256=<hex HMAC-SHA256 of the body>` keyed by
+//! `webhooks.secret`. Any 2xx answer acknowledges the batch.
+
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use ring::hmac;
+use wsprism_core::error::{Result, WsPrismError};
+
+use super::WebhookSender;
+use crate::config::schema::WebhookConfig;
+
+/// Header carrying the body signature.
+pub const SIGNATURE_HEADER: &str = "x-wsprism-signature";
+
+/// Signature header value for `body`: `sha256=<lowercase hex>`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, body);
+    let mut out = String::with_capacity(7 + 64);
+    out.push_str("sha256=");
+    for b in tag.as_ref() {
+        let _ = write!(out, "{b:02x}");
+    }
+    out
+}
+
+pub struct HttpSender {
+    http: reqwest::Client,
+}
+
+impl HttpSender {
+    pub fn new() -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .build()
+            .map_err(|e| WsPrismError::Internal(format!("webhook client build failed: {e}")))?;
+        Ok(Self { http })
+    }
+}
+
+#[async_trait]
+impl WebhookSender for HttpSender {
+    async fn send(&self, cfg: &WebhookConfig, body: Bytes) -> Result<()> {
+        let signature = sign(&cfg.secret, &body);
+        let resp = self
+            .http
+            .post(&cfg.url)
+            .timeout(Duration::from_millis(cfg.timeout_ms))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| WsPrismError::Internal(format!("webhook delivery: {e}")))?;
+        if !resp.status().is_success() {
+            return Err(WsPrismError::Internal(format!("webhook delivery: HTTP {}", resp.status())));
+        }
+        Ok(())
+    }
+}
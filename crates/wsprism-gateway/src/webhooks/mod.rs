@@ -0,0 +1,255 @@
+//! Session lifecycle notifications (tenant `webhooks`).
+//!
+//! Connects, disconnects and room joins/leaves of a tenant with `webhooks`
+//! configured are POSTed to its backend as a JSON array of [`WebhookEvent`]s:
+//!
+//! ```json
+//! [{"event":"connect","ts":1730000000000,"tenant":"acme","user":"u1","connection_id":"…"},
+//!  {"event":"room_join","ts":1730000000003,"tenant":"acme","user":"u1","connection_id":"…","room":"lobby"}]
+//! ```
+//!
+//! Sessions never wait on delivery: events go through a bounded per-tenant
+//! backlog to one worker task per tenant, which batches up to
+//! `batch_max` events or `batch_interval_ms`, then POSTs and retries with
+//! exponential backoff. Lost events are counted in
+//! `wsprism_webhook_dropped_total{tenant,reason}` with `reason`
+//! `backlog_full` or `delivery` (the batch failed `max_attempts` times).
+//!
+//! Delivery over HTTP needs the `webhooks` feature ([`HttpSender`]).
+
+#[cfg(feature = "webhooks")]
+mod http;
+
+#[cfg(feature = "webhooks")]
+pub use http::{sign, HttpSender, SIGNATURE_HEADER};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use wsprism_core::error::Result;
+
+use crate::config::schema::{TenantConfig, WebhookConfig, WebhookEventKind};
+use crate::context::ConnectionId;
+use crate::obs::metrics::GatewayMetrics;
+use crate::policy::quota::unix_now_ms;
+use crate::realtime::MembershipObserver;
+
+/// Longest wait between two attempts of one batch.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// One lifecycle event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WebhookEvent {
+    pub event: WebhookEventKind,
+    /// Event time, unix ms.
+    pub ts: u64,
+    pub tenant: String,
+    /// User id, without the tenant prefix.
+    pub user: String,
+    pub connection_id: ConnectionId,
+    /// `room_join` / `room_leave` only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+    /// `disconnect` only: the close reason, `client_closed` or `error`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// `disconnect` only: session length.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+impl WebhookEvent {
+    fn new(event: WebhookEventKind, tenant: &str, user: &str, connection_id: ConnectionId) -> Self {
+        Self {
+            event,
+            ts: unix_now_ms(),
+            tenant: tenant.to_string(),
+            user: user.to_string(),
+            connection_id,
+            room: None,
+            reason: None,
+            duration_ms: None,
+        }
+    }
+
+    pub fn connect(tenant: &str, user: &str, connection_id: ConnectionId) -> Self {
+        Self::new(WebhookEventKind::Connect, tenant, user, connection_id)
+    }
+
+    pub fn disconnect(tenant: &str, user: &str, connection_id: ConnectionId, reason: &str, duration: Duration) -> Self {
+        Self {
+            reason: Some(reason.to_string()),
+            duration_ms: Some(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)),
+            ..Self::new(WebhookEventKind::Disconnect, tenant, user, connection_id)
+        }
+    }
+
+    pub fn room_join(tenant: &str, user: &str, connection_id: ConnectionId, room: &str) -> Self {
+        Self { room: Some(room.to_string()), ..Self::new(WebhookEventKind::RoomJoin, tenant, user, connection_id) }
+    }
+
+    pub fn room_leave(tenant: &str, user: &str, connection_id: ConnectionId, room: &str) -> Self {
+        Self { room: Some(room.to_string()), ..Self::new(WebhookEventKind::RoomLeave, tenant, user, connection_id) }
+    }
+}
+
+/// Transport for batches. Called from the tenant's worker task, one batch
+/// at a time.
+#[async_trait]
+pub trait WebhookSender: Send + Sync {
+    /// Deliver one batch (`body` is the JSON array). Errors make the worker
+    /// retry the same body.
+    async fn send(&self, cfg: &WebhookConfig, body: Bytes) -> Result<()>;
+}
+
+struct TenantHook {
+    cfg: WebhookConfig,
+    tx: mpsc::Sender<WebhookEvent>,
+    rx: Mutex<Option<mpsc::Receiver<WebhookEvent>>>,
+}
+
+/// Per-tenant event backlogs in front of a [`WebhookSender`].
+pub struct Webhooks {
+    tenants: HashMap<String, TenantHook>,
+    sender: Arc<dyn WebhookSender>,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl Webhooks {
+    /// `tenants` without `webhooks` are ignored.
+    pub fn new(tenants: &[TenantConfig], sender: Arc<dyn WebhookSender>, metrics: Arc<GatewayMetrics>) -> Self {
+        let tenants = tenants
+            .iter()
+            .filter_map(|t| {
+                let cfg = t.webhooks.clone()?;
+                let (tx, rx) = mpsc::channel(cfg.backlog);
+                Some((t.id.clone(), TenantHook { cfg, tx, rx: Mutex::new(Some(rx)) }))
+            })
+            .collect();
+        Self { tenants, sender, metrics }
+    }
+
+    /// Build the HTTP sender when a tenant configures `webhooks`; `None` if
+    /// none does.
+    pub fn from_config(tenants: &[TenantConfig], metrics: Arc<GatewayMetrics>) -> Result<Option<Self>> {
+        let Some(t) = tenants.iter().find(|t| t.webhooks.is_some()) else {
+            return Ok(None);
+        };
+        #[cfg(feature = "webhooks")]
+        {
+            let _ = t;
+            Ok(Some(Self::new(tenants, Arc::new(HttpSender::new()?), metrics)))
+        }
+        #[cfg(not(feature = "webhooks"))]
+        {
+            let _ = metrics;
+            Err(wsprism_core::error::WsPrismError::BadRequest(format!(
+                "tenant {} configures webhooks but the gateway was built without the webhooks feature",
+                t.id
+            )))
+        }
+    }
+
+    /// Queue `event` without waiting, if its tenant subscribed to its kind.
+    /// A full backlog drops it (counted).
+    pub fn emit(&self, event: WebhookEvent) {
+        let Some(hook) = self.tenants.get(&event.tenant) else { return };
+        if !hook.cfg.events.contains(&event.event) {
+            return;
+        }
+        if let Err(e) = hook.tx.try_send(event) {
+            let event = e.into_inner();
+            self.dropped(&event.tenant, "backlog_full", 1);
+        }
+    }
+
+    fn dropped(&self, tenant: &str, reason: &str, n: usize) {
+        self.metrics.webhook_dropped.add(&[("tenant", tenant), ("reason", reason)], n as u64);
+    }
+
+    /// Start one worker per tenant; workers already started are skipped.
+    /// Must be called from within a tokio runtime.
+    pub fn spawn(self: Arc<Self>) -> Vec<tokio::task::JoinHandle<()>> {
+        let mut handles = Vec::new();
+        for (tenant, hook) in &self.tenants {
+            let Some(rx) = hook.rx.lock().ok().and_then(|mut rx| rx.take()) else { continue };
+            let this = self.clone();
+            let tenant = tenant.clone();
+            handles.push(tokio::spawn(async move { this.run(tenant, rx).await }));
+        }
+        handles
+    }
+
+    async fn run(&self, tenant: String, mut rx: mpsc::Receiver<WebhookEvent>) {
+        let Some(cfg) = self.tenants.get(&tenant).map(|h| &h.cfg) else { return };
+        let interval = Duration::from_millis(cfg.batch_interval_ms);
+        while let Some(first) = rx.recv().await {
+            let mut batch = vec![first];
+            let deadline = tokio::time::Instant::now() + interval;
+            while batch.len() < cfg.batch_max {
+                match tokio::time::timeout_at(deadline, rx.recv()).await {
+                    Ok(Some(event)) => batch.push(event),
+                    _ => break,
+                }
+            }
+            self.deliver(&tenant, cfg, &batch).await;
+        }
+    }
+
+    async fn deliver(&self, tenant: &str, cfg: &WebhookConfig, batch: &[WebhookEvent]) {
+        let body = match serde_json::to_vec(batch) {
+            Ok(body) => Bytes::from(body),
+            Err(e) => {
+                tracing::warn!(%tenant, error = %e, "webhook batch not serializable");
+                self.dropped(tenant, "delivery", batch.len());
+                return;
+            }
+        };
+        let mut backoff = Duration::from_millis(cfg.retry_backoff_ms);
+        for attempt in 1..=cfg.max_attempts {
+            match self.sender.send(cfg, body.clone()).await {
+                Ok(()) => return,
+                Err(e) if attempt < cfg.max_attempts => {
+                    tracing::debug!(%tenant, attempt, error = %e, "webhook delivery failed; retrying");
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+                }
+                Err(e) => {
+                    tracing::warn!(%tenant, events = batch.len(), error = %e, "webhook batch dropped");
+                }
+            }
+        }
+        self.dropped(tenant, "delivery", batch.len());
+    }
+}
+
+impl Webhooks {
+    fn membership(&self, kind: WebhookEventKind, tenant_id: &str, room_key: &str, user_key: &str, conn_id: ConnectionId) {
+        // Skip the key formatting for tenants without webhooks.
+        if !self.tenants.contains_key(tenant_id) {
+            return;
+        }
+        let prefix = format!("{tenant_id}::");
+        let room = room_key.strip_prefix(&prefix).unwrap_or(room_key);
+        let user = user_key.strip_prefix(&prefix).unwrap_or(user_key);
+        self.emit(match kind {
+            WebhookEventKind::RoomLeave => WebhookEvent::room_leave(tenant_id, user, conn_id, room),
+            _ => WebhookEvent::room_join(tenant_id, user, conn_id, room),
+        });
+    }
+}
+
+impl MembershipObserver for Webhooks {
+    fn joined(&self, tenant_id: &str, room_key: &str, user_key: &str, conn_id: ConnectionId) {
+        self.membership(WebhookEventKind::RoomJoin, tenant_id, room_key, user_key, conn_id);
+    }
+
+    fn left(&self, tenant_id: &str, room_key: &str, user_key: &str, conn_id: ConnectionId) {
+        self.membership(WebhookEventKind::RoomLeave, tenant_id, room_key, user_key, conn_id);
+    }
+}
@@ -0,0 +1,147 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use axum::body::Body;
+use axum::extract::ws::Message;
+use axum::http::{Request, StatusCode};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::config;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCore};
+use wsprism_gateway::router::build_router;
+
+const CFG: &str = r#"
+version: 1
+gateway:
+  admin_token: "s3cret"
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["chat:*"]
+      allow_server_broadcast: true
+  - id: "quiet"
+    policy:
+      ext_allowlist: ["chat:*"]
+"#;
+
+fn connect(core: &RealtimeCore, tenant: &str, user: &str) -> mpsc::Receiver<Message> {
+    let (tx, rx) = mpsc::channel(8);
+    let conn = Connection::new(tx, SessionClaims::default());
+    core.sessions
+        .try_insert(
+            tenant.into(),
+            format!("{tenant}::{user}"),
+            ConnectionId::new(),
+            conn,
+            0,
+        )
+        .unwrap();
+    rx
+}
+
+fn post(token: Option<&str>) -> Request<Body> {
+    let mut b = Request::post("/admin/v1/broadcast").header("content-type", "application/json");
+    if let Some(t) = token {
+        b = b.header("authorization", format!("Bearer {t}"));
+    }
+    b.body(Body::from(r#"{"svc":"sys","type":"maintenance","data":{"in_secs":300}}"#))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn broadcast_all_reaches_every_session() {
+    let core = RealtimeCore::new();
+    let mut a = connect(&core, "acme", "alice");
+    let mut b = connect(&core, "quiet", "bob");
+
+    let out = Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!({ "hi": 1 })), priority: Priority::Normal };
+    assert_eq!(core.broadcast_all(out).await.unwrap(), 2);
+
+    let out = Outgoing {
+        qos: QoS::Reliable { timeout_ms: 50 },
+        payload: Payload::TextJson(json!({ "hi": 2 })),
+        priority: Priority::Normal,
+    };
+    assert_eq!(core.broadcast_all(out).await.unwrap(), 2);
+
+    for rx in [&mut a, &mut b] {
+        assert!(matches!(rx.recv().await, Some(Message::Text(_))));
+        assert!(matches!(rx.recv().await, Some(Message::Text(_))));
+    }
+}
+
+#[tokio::test]
+async fn admin_broadcast_is_authorized_scoped_and_rate_limited() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let mut acme = connect(&state.realtime(), "acme", "alice");
+    let mut quiet = connect(&state.realtime(), "quiet", "bob");
+    let app = build_router(state);
+
+    let resp = app.clone().oneshot(post(Some("wrong"))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = app.clone().oneshot(post(Some("s3cret"))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), 1024).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["sent"], 1);
+
+    let Some(Message::Text(frame)) = acme.recv().await else { panic!("no frame") };
+    let frame: Value = serde_json::from_str(&frame).unwrap();
+    assert_eq!(frame["type"], "maintenance");
+    assert!(quiet.try_recv().is_err(), "tenant without allow_server_broadcast");
+
+    let resp = app.oneshot(post(Some("s3cret"))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key("retry-after"));
+}
+
+#[tokio::test]
+async fn admin_api_is_disabled_without_token() {
+    let cfg = config::load_from_str(
+        r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["chat:*"]
+"#,
+    )
+    .unwrap();
+    let app = build_router(AppState::new(cfg).unwrap());
+    let resp = app.oneshot(post(Some("anything"))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn admin_sessions_lists_connection_and_user_ids() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let _a1 = connect(&state.realtime(), "acme", "alice");
+    let _a2 = connect(&state.realtime(), "acme", "alice");
+    let app = build_router(state);
+
+    let get = |token: &str| {
+        Request::get("/admin/v1/sessions")
+            .header("authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let resp = app.clone().oneshot(get("wrong")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = app.oneshot(get("s3cret")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), 4096).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let sessions = body["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.iter().all(|s| s["user_id"] == "alice" && s["tenant"] == "acme"));
+    assert!(sessions.iter().all(|s| s["queue_depth"] == 0 && s["connected_at_unix_ms"].as_u64().unwrap() > 0));
+    assert_ne!(sessions[0]["connection_id"], sessions[1]["connection_id"]);
+}
@@ -0,0 +1,139 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use axum::body::Body;
+use axum::extract::ws::Message;
+use axum::http::{Request, StatusCode};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::config::{self, TenantLimits};
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::{Connection, RoomSize};
+use wsprism_gateway::realtime::RealtimeCore;
+use wsprism_gateway::router::build_router;
+
+const CFG: &str = r#"
+version: 1
+gateway:
+  admin_token: "s3cret"
+tenants:
+  - id: "acme"
+  - id: "beta"
+"#;
+
+/// Register a fake session with `queued` messages waiting, in `rooms`.
+fn connect(core: &RealtimeCore, tenant: &str, user: &str, rooms: &[&str], queued: usize) -> mpsc::Receiver<Message> {
+    let (tx, rx) = mpsc::channel(16);
+    for _ in 0..queued {
+        tx.try_send(Message::Text("x".into())).unwrap();
+    }
+    let id = ConnectionId::new();
+    let user_key = format!("{tenant}::{user}");
+    core.sessions
+        .try_insert(tenant.into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    for room in rooms {
+        core.presence
+            .try_join(tenant, &format!("{tenant}::{room}"), &user_key, id, &TenantLimits::default())
+            .unwrap();
+    }
+    rx
+}
+
+async fn stats(state: &AppState, query: &str) -> Value {
+    let req = Request::get(format!("/admin/v1/stats{query}"))
+        .header("authorization", "Bearer s3cret")
+        .body(Body::empty())
+        .unwrap();
+    let resp = build_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    serde_json::from_slice(&axum::body::to_bytes(resp.into_body(), 1 << 16).await.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn stats_snapshot_reports_sessions_rooms_and_queues() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let core = state.realtime();
+    let _rx = [
+        connect(&core, "acme", "alice", &["lobby", "match:1"], 0),
+        connect(&core, "acme", "alice", &["lobby"], 2),
+        connect(&core, "acme", "bob", &["lobby"], 4),
+        connect(&core, "beta", "carol", &["lobby"], 10),
+    ];
+
+    let all = stats(&state, "").await;
+    assert_eq!(all["sessions"], 4);
+    assert_eq!(all["sessions_by_tenant"], json!({ "acme": 3, "beta": 1 }));
+    assert_eq!(all["rooms"], 3);
+    assert_eq!(all["top_rooms"][0], json!({ "tenant": "acme", "room": "lobby", "sessions": 3, "users": 2 }));
+    assert_eq!(all["top_rooms"].as_array().unwrap().len(), 3);
+    assert_eq!(all["queue_depth"], json!({ "p50": 2, "p99": 10, "max": 10 }));
+    assert_eq!(all["draining"], false);
+    assert!(all["uptime_secs"].is_u64());
+
+    let acme = stats(&state, "?tenant=acme").await;
+    assert_eq!(acme["sessions"], 3);
+    assert_eq!(acme["sessions_by_tenant"], json!({ "acme": 3 }));
+    assert_eq!(acme["rooms"], 2);
+    let rooms: Vec<&str> = acme["top_rooms"].as_array().unwrap().iter().map(|r| r["room"].as_str().unwrap()).collect();
+    assert_eq!(rooms, ["lobby", "match:1"]);
+    assert_eq!(acme["queue_depth"]["max"], 4);
+
+    let none = stats(&state, "?tenant=nobody").await;
+    assert_eq!((none["sessions"].as_u64(), none["rooms"].as_u64()), (Some(0), Some(0)));
+    assert_eq!(none["queue_depth"], json!({ "p50": 0, "p99": 0, "max": 0 }));
+}
+
+#[test]
+fn top_rooms_is_bounded_and_ordered() {
+    let core = RealtimeCore::new();
+    let mut rx = Vec::new();
+    // room-i has i sessions.
+    for i in 1..=30 {
+        for u in 0..i {
+            rx.push(connect(&core, "acme", &format!("u{u}"), &[&format!("room-{i:02}")], 0));
+        }
+    }
+    let top = core.presence.top_rooms(None, 5);
+    let expect = |i: usize| RoomSize { room: format!("acme::room-{i:02}"), sessions: i, users: i };
+    assert_eq!(top, (26..=30).rev().map(expect).collect::<Vec<_>>());
+    assert!(core.presence.top_rooms(Some("acme"), 0).is_empty());
+    assert_eq!(core.presence.top_rooms(Some("beta"), 5), []);
+}
+
+#[tokio::test]
+async fn sessions_can_be_listed_per_tenant() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let core = state.realtime();
+    let _rx = [
+        connect(&core, "acme", "alice", &[], 0),
+        connect(&core, "acme", "bob", &[], 0),
+        connect(&core, "beta", "carol", &[], 0),
+    ];
+
+    assert_eq!(core.sessions.connection_ids_for_tenant("acme").len(), 2);
+    assert!(core.sessions.connection_ids_for_tenant("nobody").is_empty());
+    let mut seen = 0;
+    core.sessions.iter_for_tenant("beta", |_, _| seen += 1);
+    assert_eq!(seen, 1);
+
+    let list = |query: &'static str| {
+        let state = state.clone();
+        async move {
+            let req = Request::get(format!("/admin/v1/sessions{query}"))
+                .header("authorization", "Bearer s3cret")
+                .body(Body::empty())
+                .unwrap();
+            let resp = build_router(state).oneshot(req).await.unwrap();
+            serde_json::from_slice::<Value>(&axum::body::to_bytes(resp.into_body(), 1 << 16).await.unwrap()).unwrap()
+        }
+    };
+    assert_eq!(list("").await["sessions"].as_array().unwrap().len(), 3);
+    let acme = list("?tenant=acme").await;
+    let tenants: Vec<&str> = acme["sessions"].as_array().unwrap().iter().map(|s| s["tenant"].as_str().unwrap()).collect();
+    assert_eq!(tenants, ["acme", "acme"]);
+}
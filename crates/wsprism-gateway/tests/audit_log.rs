@@ -0,0 +1,158 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::audit::{AuditEvent, AuditRecord, AuditSink, FileAuditSink};
+use wsprism_gateway::config;
+
+use common::{connect, serve};
+
+#[derive(Default)]
+struct Collect(Mutex<Vec<AuditEvent>>);
+
+impl AuditSink for Collect {
+    fn record(&self, event: AuditEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      strike_limit: 1
+      ext_allowlist: ["room:*"]
+  - id: "closed"
+    suspended: true
+"#;
+
+#[tokio::test]
+async fn auth_failures_suspensions_and_strike_outs_are_audited() {
+    let sink = Arc::new(Collect::default());
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap().with_audit_sink(sink.clone());
+    let addr = serve(&state).await;
+    let url = |q: &str| format!("ws://{addr}/v1/ws?{q}");
+
+    assert!(tokio_tungstenite::connect_async(url("tenant=acme&ticket=forged")).await.is_err());
+    assert!(tokio_tungstenite::connect_async(url("tenant=closed&ticket=dev")).await.is_err());
+
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    for _ in 0..2 {
+        ws.send(Message::Text(r#"{"svc":"chat","type":"send"}"#.into())).await.unwrap();
+    }
+    // Drain until the server closes the session.
+    tokio::time::timeout(Duration::from_secs(5), async { while let Some(Ok(_)) = ws.next().await {} })
+        .await
+        .unwrap();
+
+    let events = sink.0.lock().unwrap().clone();
+    assert_eq!(events.len(), 3, "{events:?}");
+    let localhost = Some("127.0.0.1".parse().unwrap());
+
+    let AuditEvent::AuthFailed(r) = &events[0] else { panic!("{events:?}") };
+    assert_eq!((r.tenant.as_str(), r.user.as_deref(), r.ip), ("acme", None, localhost));
+
+    let AuditEvent::TenantSuspended(r) = &events[1] else { panic!("{events:?}") };
+    assert_eq!((r.tenant.as_str(), r.ip), ("closed", localhost));
+
+    let AuditEvent::PolicyClose(r) = &events[2] else { panic!("{events:?}") };
+    assert_eq!((r.tenant.as_str(), r.user.as_deref(), r.ip), ("acme", Some("user:dev"), localhost));
+    assert_eq!(r.reason, "too many policy violations");
+}
+
+fn log_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("wsprism-audit-{name}-{}.jsonl", std::process::id()));
+    for n in ["", ".1", ".2", ".3"] {
+        let _ = std::fs::remove_file(format!("{}{n}", path.display()));
+    }
+    path
+}
+
+fn read_lines(path: &Path) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        // The writer may be mid-line.
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+/// Poll until the writer thread has caught up.
+fn wait_for(mut done: impl FnMut() -> bool) {
+    for _ in 0..200 {
+        if done() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("audit writer did not catch up");
+}
+
+#[test]
+fn file_sink_writes_jsonl_and_rotates() {
+    let path = log_path("rotate");
+    let sink = FileAuditSink::open(&path, 1024, 2, 1024).unwrap();
+    let event = |i: usize| AuditEvent::Kicked(AuditRecord::new("acme", format!("kick-{i:03}")).user("u1"));
+    // ~130 bytes per line: 40 lines fill the live file and both rotations.
+    for i in 0..40 {
+        sink.record(event(i));
+    }
+    let rotated = |n: usize| PathBuf::from(format!("{}.{n}", path.display()));
+    wait_for(|| read_lines(&path).last().is_some_and(|l| l["reason"] == "kick-039"));
+
+    assert!(rotated(2).exists());
+    assert!(!rotated(3).exists());
+    for p in [&path, &rotated(1), &rotated(2)] {
+        assert!(std::fs::metadata(p).unwrap().len() <= 1024);
+    }
+    let line = &read_lines(&rotated(1))[0];
+    assert_eq!(line["event"], "kicked");
+    assert_eq!(line["tenant"], "acme");
+    assert_eq!(line["user"], "u1");
+    assert!(line["ip"].is_null());
+    assert!(line["ts_unix_ms"].as_u64().unwrap() > 0);
+    assert_eq!(sink.dropped(), 0);
+}
+
+#[test]
+fn full_queue_drops_and_counts() {
+    let path = log_path("overflow");
+    let sink = FileAuditSink::open(&path, 1 << 20, 1, 1).unwrap();
+    let total = 500;
+    for i in 0..total {
+        sink.record(AuditEvent::AuthFailed(AuditRecord::new("acme", format!("{i}"))));
+    }
+    // Every event is either written or counted as dropped, never lost silently.
+    wait_for(|| read_lines(&path).len() as u64 + sink.dropped() == total);
+}
+
+#[test]
+fn audit_config_is_validated() {
+    let cfg = |audit: &str| config::load_from_str(&format!("version: 1\ngateway:\n  audit: {audit}\ntenants:\n  - id: acme\n"));
+    let c = cfg("{ sink: file, path: \"/var/log/wsprism/audit.jsonl\" }").unwrap();
+    assert_eq!(c.gateway.audit.sink, config::AuditSinkKind::File);
+    assert_eq!(c.gateway.audit.max_files, 5);
+
+    for (bad, msg) in [
+        ("{ sink: file }", "path"),
+        ("{ sink: none, max_file_bytes: 10 }", "max_file_bytes"),
+        ("{ sink: none, max_files: 0 }", "max_files"),
+        ("{ sink: none, queue_capacity: 0 }", "queue_capacity"),
+        ("{ sink: syslog }", "sink"),
+    ] {
+        let err = cfg(bad).expect_err(bad);
+        assert!(err.to_string().contains(msg), "{err}");
+    }
+}
@@ -0,0 +1,55 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use wsprism_gateway::config;
+
+use common::{connect, next_json, spawn_gateway};
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    auto_join_rooms: ["announcements", "headlines"]
+    limits:
+      max_rooms_per_session: 2
+"#;
+
+#[tokio::test]
+async fn sessions_join_configured_rooms_on_connect() {
+    let (state, addr) = spawn_gateway(CFG).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+
+    assert_eq!(next_json(&mut ws).await["type"], "authed");
+    for room in ["announcements", "headlines"] {
+        let joined = next_json(&mut ws).await;
+        assert_eq!((joined["svc"].as_str(), joined["type"].as_str()), (Some("sys"), Some("joined")));
+        assert_eq!(joined["room"], room);
+    }
+    let mut rooms = state.realtime().presence.rooms_of("acme::user:dev");
+    rooms.sort();
+    assert_eq!(rooms, ["acme::announcements", "acme::headlines"]);
+}
+
+#[test]
+fn auto_join_rooms_are_validated() {
+    let cfg = |extra: &str| config::load_from_str(&format!("version: 1\ntenants:\n  - id: acme\n{extra}"));
+    assert!(cfg("    auto_join_rooms: [\"news\"]\n").is_ok());
+
+    let bad = [
+        ("    auto_join_rooms: [\"\"]\n", "auto_join_rooms"),
+        ("    auto_join_rooms: [\"two words\"]\n", "auto_join_rooms"),
+        (&format!("    auto_join_rooms: [\"{}\"]\n", "r".repeat(65)), "auto_join_rooms"),
+        ("    auto_join_rooms: [\"a\", \"a\"]\n", "listed twice"),
+        (
+            "    auto_join_rooms: [\"a\", \"b\"]\n    limits:\n      max_rooms_per_session: 1\n",
+            "max_rooms_per_session is 1",
+        ),
+    ];
+    for (extra, msg) in bad {
+        let err = cfg(extra).expect_err(extra);
+        assert!(err.to_string().contains(msg), "{err}");
+    }
+}
@@ -0,0 +1,149 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+use wsprism_gateway::obs::metrics::{FrameDir, GatewayMetrics};
+
+use common::{connect, next_binary, next_text, spawn_gateway};
+
+const EXT: [(&str, &str); 2] = [("tenant", "acme"), ("lane", "ext")];
+
+#[test]
+fn frame_bytes_add_up_per_tenant_and_lane() {
+    let m = GatewayMetrics::default();
+    m.count_frame_bytes(FrameDir::Inbound, "acme", "ext", 100);
+    m.count_frame_bytes(FrameDir::Inbound, "acme", "ext", 28);
+    m.count_frame_bytes(FrameDir::Outbound, "acme", "hot", 7);
+
+    assert_eq!(m.bytes_in.get(&EXT), 128);
+    assert_eq!(m.bytes_out.get(&[("tenant", "acme"), ("lane", "hot")]), 7);
+    assert!(m.render(&[]).contains("wsprism_bytes_in_total{lane=\"ext\",tenant=\"acme\"} 128"));
+}
+
+/// `(bytes_in, bytes_out)` of every "session summary" event.
+#[derive(Clone, Default)]
+struct Summaries(Arc<Mutex<Vec<(u64, u64)>>>);
+
+#[derive(Default)]
+struct SummaryFields {
+    message: String,
+    bytes_in: u64,
+    bytes_out: u64,
+}
+
+impl Visit for SummaryFields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            "bytes_in" => self.bytes_in = value,
+            "bytes_out" => self.bytes_out = value,
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Summaries {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut f = SummaryFields::default();
+        event.record(&mut f);
+        if f.message == "session summary" {
+            self.0.lock().unwrap().push((f.bytes_in, f.bytes_out));
+        }
+    }
+}
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["room:*"]
+"#;
+
+#[tokio::test]
+async fn session_summary_matches_tenant_byte_counters() {
+    // Single-threaded runtime: the server task logs to this thread's subscriber.
+    let summaries = Summaries::default();
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(summaries.clone()));
+
+    let (state, addr) = spawn_gateway(CFG).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+
+    let mut received = 0;
+    let authed = next_text(&mut ws).await;
+    received += authed.len();
+    let join = r#"{"svc":"room","type":"join","room":"lobby"}"#;
+    ws.send(Message::Text(join.into())).await.unwrap();
+    received += next_text(&mut ws).await.len();
+    ws.close(None).await.unwrap();
+
+    let summary = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(s) = summaries.0.lock().unwrap().first().copied() {
+                break s;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    let m = state.metrics();
+    assert_eq!(summary, (join.len() as u64, received as u64));
+    assert_eq!(m.bytes_in.get(&EXT), summary.0);
+    assert_eq!(m.bytes_out.get(&EXT), summary.1);
+}
+
+const HOT_CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["room:*"]
+      hot_allowlist: ["1:*"]
+      hot_requires_active_room: false
+"#;
+
+#[tokio::test]
+async fn hot_frames_count_exact_bytes_each_way() {
+    let (state, addr) = spawn_gateway(HOT_CFG).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    next_text(&mut ws).await;
+
+    // v1, echo service 1, opcode 1, no flags: a 4-byte header and 60 bytes of payload.
+    let mut frame = vec![1, 1, 1, 0];
+    frame.extend([7u8; 60]);
+    let hot = [("tenant", "acme"), ("lane", "hot")];
+    let m = state.metrics();
+    for round in 1..=2u64 {
+        ws.send(Message::Binary(frame.clone())).await.unwrap();
+        let echoed = next_binary(&mut ws).await;
+        assert_eq!(echoed.len(), 60);
+        assert_eq!(m.bytes_in.get(&hot), 64 * round);
+        // Counted once the write completes, which may trail the client's read.
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while m.bytes_out.get(&hot) != 60 * round {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+}
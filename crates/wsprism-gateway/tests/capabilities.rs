@@ -0,0 +1,56 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use wsprism_core::error::Result;
+use wsprism_core::protocol::text::Envelope;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::dispatch::TextService;
+use wsprism_gateway::realtime::{Outgoing, RealtimeCtx};
+use wsprism_gateway::{config, router};
+
+struct Quiz;
+
+#[async_trait]
+impl TextService for Quiz {
+    fn svc(&self) -> &'static str {
+        "quiz"
+    }
+    async fn handle(&self, _ctx: RealtimeCtx, _env: Envelope) -> Result<Option<Outgoing>> {
+        Ok(None)
+    }
+}
+
+async fn capabilities(state: &AppState) -> Value {
+    let req = Request::get("/v1/capabilities").body(Body::empty()).unwrap();
+    let resp = router::build_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    serde_json::from_slice(&axum::body::to_bytes(resp.into_body(), 1 << 16).await.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn lists_every_registered_service() {
+    let state = AppState::new(config::load_from_str("version: 1\ntenants:\n  - id: acme\n").unwrap()).unwrap();
+    state.dispatcher().register_text(Arc::new(Quiz));
+
+    let caps = capabilities(&state).await;
+    assert_eq!(caps["text_services"], json!(["chat", "quiz", "room", "sys"]));
+    assert_eq!(caps["hot_services"], json!([1]));
+    assert_eq!(caps["version"], "1");
+    assert_eq!(caps["features"].as_array().unwrap().contains(&json!("webhooks")), cfg!(feature = "webhooks"));
+}
+
+#[tokio::test]
+async fn stays_open_behind_ops_auth() {
+    let yaml = "version: 1\nops: { auth_token: \"metrics-0123456789\" }\ntenants:\n  - id: acme\n";
+    let state = AppState::new(config::load_from_str(yaml).unwrap()).unwrap();
+    assert_eq!(capabilities(&state).await["hot_services"], json!([1]));
+}
@@ -0,0 +1,360 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+//! Cluster bridge tests. The two-replica scenario runs against an in-memory
+//! transport, and against real servers when their URLs are set:
+//!
+//! ```text
+//! docker compose -f crates/wsprism-gateway/tests/docker-compose.yml up -d
+//! WSPRISM_TEST_REDIS_URL=redis://127.0.0.1:6379 WSPRISM_TEST_NATS_URL=nats://127.0.0.1:4222 \
+//!   cargo test -p wsprism-gateway --features cluster-redis,cluster-nats --test cluster
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::extract::ws::Message;
+use bytes::Bytes;
+use futures_util::stream::{self, StreamExt};
+use serde_json::json;
+use tokio::sync::{broadcast, mpsc, watch};
+
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_gateway::cluster::{
+    decode_frame, decode_presence, encode_frame, encode_presence, encode_snapshots, ClusterRelay, ClusterTransport,
+    FrameStream, PresenceDelta,
+};
+use wsprism_gateway::config::{ClusterBackend, ClusterConfig, TenantLimits};
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, PreparedMsg, Priority, QoS, RealtimeCore, RoomRelay};
+
+/// In-memory pub/sub shared by the replicas of one test.
+struct Hub {
+    frames: broadcast::Sender<(String, Bytes)>,
+    up: watch::Sender<bool>,
+}
+
+impl Hub {
+    fn new() -> Arc<Self> {
+        Arc::new(Self { frames: broadcast::channel(64).0, up: watch::Sender::new(true) })
+    }
+}
+
+struct HubTransport(Arc<Hub>);
+
+#[async_trait]
+impl ClusterTransport for HubTransport {
+    fn backend(&self) -> &'static str { "memory" }
+
+    async fn publish(&self, room_key: &str, frame: Bytes) -> Result<()> {
+        let _ = self.0.frames.send((room_key.to_string(), frame));
+        Ok(())
+    }
+
+    async fn subscribe(&self, tenants: &[String]) -> Result<FrameStream> {
+        if !*self.0.up.borrow() {
+            return Err(WsPrismError::Internal("hub down".into()));
+        }
+        let prefixes: Vec<String> = tenants.iter().map(|t| format!("{t}::")).collect();
+        let frames = stream::unfold(self.0.frames.subscribe(), |mut rx| async move {
+            rx.recv().await.ok().map(|f| (f, rx))
+        })
+        .filter(move |(room_key, _)| std::future::ready(prefixes.iter().any(|p| room_key.starts_with(p.as_str()))));
+        let mut up = self.0.up.subscribe();
+        let lost = async move {
+            let _ = up.wait_for(|up| !*up).await;
+        };
+        Ok(frames.take_until(lost).boxed())
+    }
+}
+
+fn cluster_cfg(node_id: &str) -> ClusterConfig {
+    ClusterConfig {
+        node_id: node_id.into(),
+        backend: ClusterBackend::Redis,
+        redis_url: None,
+        nats_url: None,
+        max_message_bytes: 1024,
+        presence_heartbeat_ms: 100,
+        presence_ttl_ms: 300,
+    }
+}
+
+struct Node {
+    core: Arc<RealtimeCore>,
+    relay: Arc<ClusterRelay>,
+    metrics: Arc<GatewayMetrics>,
+}
+
+fn node(node_id: &str, transport: Arc<dyn ClusterTransport>) -> Node {
+    let metrics = Arc::new(GatewayMetrics::default());
+    let relay = Arc::new(ClusterRelay::new(&cluster_cfg(node_id), transport, metrics.clone()));
+    let core = Arc::new(RealtimeCore::new().with_room_relay(relay.clone()));
+    relay.clone().spawn(core.clone(), vec!["acme".into()]).unwrap();
+    Node { core, relay, metrics }
+}
+
+async fn wait_connected(relay: &ClusterRelay, up: bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while relay.is_connected() != up {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+fn join(core: &RealtimeCore, user: &str) -> mpsc::Receiver<Message> {
+    join_with(core, user, &TenantLimits::default()).unwrap().1
+}
+
+fn join_with(core: &RealtimeCore, user: &str, limits: &TenantLimits) -> Result<(ConnectionId, mpsc::Receiver<Message>)> {
+    let (tx, rx) = mpsc::channel(8);
+    let id = ConnectionId::new();
+    let user_key = format!("acme::{user}");
+    core.sessions
+        .try_insert("acme".into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    core.presence.try_join("acme", "acme::lobby", &user_key, id, limits)?;
+    Ok((id, rx))
+}
+
+async fn wait_global_count(core: &RealtimeCore, n: usize) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while core.presence.global_count("acme::lobby") != n {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("global count stuck at {}", core.presence.global_count("acme::lobby")));
+}
+
+async fn recv(rx: &mut mpsc::Receiver<Message>) -> Message {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap()
+}
+
+/// Shared by every backend: members on two replicas see each other's room
+/// publishes exactly once, and excluded users are skipped remotely too.
+async fn two_replicas_share_rooms(a: Arc<dyn ClusterTransport>, b: Arc<dyn ClusterTransport>) {
+    let (a, b) = (node("gw-a", a), node("gw-b", b));
+    wait_connected(&a.relay, true).await;
+    wait_connected(&b.relay, true).await;
+    let mut alice = join(&a.core, "alice");
+    let mut bob = join(&b.core, "bob");
+    let mut carol = join(&b.core, "carol");
+
+    let out = Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!({ "n": 1 })), priority: Priority::Normal };
+    a.core.publish_room_lossy_excluding("acme::lobby", out, Some("acme::carol")).unwrap();
+    assert!(matches!(recv(&mut bob).await, Message::Text(t) if t == r#"{"n":1}"#));
+    assert!(matches!(recv(&mut alice).await, Message::Text(_)));
+
+    let out = Outgoing { qos: QoS::Reliable { timeout_ms: 100 }, payload: Payload::Binary(Bytes::from_static(b"\x01\x02")), priority: Priority::Normal };
+    b.core.publish_room_reliable("acme::lobby", out).await.unwrap();
+    assert!(matches!(recv(&mut alice).await, Message::Binary(bin) if bin == [1, 2]));
+    assert!(matches!(recv(&mut bob).await, Message::Binary(_)));
+    assert!(matches!(recv(&mut carol).await, Message::Binary(_)), "carol was only excluded from the first");
+
+    // Nothing echoed back to the origin, nothing leaked to carol.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(alice.try_recv().is_err());
+    assert!(bob.try_recv().is_err());
+    assert!(carol.try_recv().is_err());
+    assert!(a.metrics.render(&[]).contains("wsprism_cluster_bridge_lag_seconds_count{tenant=\"acme\"} 1\n"));
+}
+
+#[tokio::test]
+async fn in_memory_replicas_share_rooms() {
+    let hub = Hub::new();
+    two_replicas_share_rooms(Arc::new(HubTransport(hub.clone())), Arc::new(HubTransport(hub))).await;
+}
+
+#[cfg(feature = "cluster-redis")]
+#[tokio::test]
+async fn redis_replicas_share_rooms() {
+    use wsprism_gateway::cluster::RedisBridge;
+    let Ok(url) = std::env::var("WSPRISM_TEST_REDIS_URL") else {
+        eprintln!("skipped: set WSPRISM_TEST_REDIS_URL");
+        return;
+    };
+    two_replicas_share_rooms(Arc::new(RedisBridge::new(&url).unwrap()), Arc::new(RedisBridge::new(&url).unwrap())).await;
+}
+
+#[cfg(feature = "cluster-nats")]
+#[tokio::test]
+async fn nats_replicas_share_rooms() {
+    use wsprism_gateway::cluster::NatsBridge;
+    let Ok(url) = std::env::var("WSPRISM_TEST_NATS_URL") else {
+        eprintln!("skipped: set WSPRISM_TEST_NATS_URL");
+        return;
+    };
+    two_replicas_share_rooms(Arc::new(NatsBridge::new(&url)), Arc::new(NatsBridge::new(&url))).await;
+}
+
+#[tokio::test]
+async fn connection_loss_degrades_to_local_only() {
+    let hub = Hub::new();
+    let a = node("gw-a", Arc::new(HubTransport(hub.clone())));
+    wait_connected(&a.relay, true).await;
+    let connected = |m: &GatewayMetrics| m.render(&[]).contains("wsprism_cluster_connected{backend=\"memory\"} 1\n");
+    assert!(connected(&a.metrics));
+
+    hub.up.send_replace(false);
+    wait_connected(&a.relay, false).await;
+    assert!(!connected(&a.metrics));
+    let mut remote = hub.frames.subscribe();
+    let mut alice = join(&a.core, "alice");
+    a.core.publish_room_lossy("acme::lobby", Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!(1)), priority: Priority::Normal }).unwrap();
+    assert!(matches!(recv(&mut alice).await, Message::Text(_)), "local delivery continues");
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(remote.try_recv().is_err(), "nothing relayed while down");
+
+    hub.up.send_replace(true);
+    wait_connected(&a.relay, true).await;
+    assert!(connected(&a.metrics));
+}
+
+#[tokio::test]
+async fn replicas_share_room_membership() {
+    let hub = Hub::new();
+    let (a, b) = (node("gw-a", Arc::new(HubTransport(hub.clone()))), node("gw-b", Arc::new(HubTransport(hub.clone()))));
+    wait_connected(&a.relay, true).await;
+    wait_connected(&b.relay, true).await;
+    let (alice, _alice_rx) = join_with(&a.core, "alice", &TenantLimits::default()).unwrap();
+    let _bob = join(&b.core, "bob");
+    // One user on both replicas counts once.
+    let _carol_a = join(&a.core, "carol");
+    let _carol_b = join(&b.core, "carol");
+
+    wait_global_count(&a.core, 3).await;
+    wait_global_count(&b.core, 3).await;
+    assert_eq!(b.core.presence.users_in("acme::lobby"), vec!["acme::bob", "acme::carol"]);
+    assert_eq!(b.core.presence.users_in_global("acme::lobby"), vec!["acme::alice", "acme::bob", "acme::carol"]);
+
+    // Room capacity is cluster-wide; members already in may still join.
+    let limits = TenantLimits { max_users_per_room: 3, ..TenantLimits::default() };
+    let e = join_with(&b.core, "dave", &limits).unwrap_err();
+    assert_eq!(e.client_code().as_str(), "RESOURCE_EXHAUSTED");
+    let (alice_b, _) = join_with(&b.core, "alice", &limits).unwrap();
+    b.core.presence.cleanup_session("acme", "acme::alice", alice_b);
+
+    a.core.presence.cleanup_session("acme", "acme::alice", alice);
+    wait_global_count(&b.core, 2).await;
+    join_with(&b.core, "dave", &limits).unwrap();
+}
+
+#[tokio::test]
+async fn silent_replicas_drop_out() {
+    let hub = Hub::new();
+    let (a, b) = (node("gw-a", Arc::new(HubTransport(hub.clone()))), node("gw-b", Arc::new(HubTransport(hub.clone()))));
+    wait_connected(&a.relay, true).await;
+    wait_connected(&b.relay, true).await;
+    let _alice = join(&a.core, "alice");
+    let _bob = join(&b.core, "bob");
+    wait_global_count(&b.core, 2).await;
+
+    // No more heartbeats: after presence_ttl_ms only local members count.
+    hub.up.send_replace(false);
+    wait_global_count(&b.core, 1).await;
+    assert_eq!(b.core.presence.users_in_global("acme::lobby"), vec!["acme::bob"]);
+
+    // Back up: the next heartbeat restores them.
+    hub.up.send_replace(true);
+    wait_global_count(&b.core, 2).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn remote_presence_expires_per_node() {
+    let core = RealtimeCore::new();
+    let remote = core.presence.remote();
+    let soon = tokio::time::Instant::now() + Duration::from_millis(100);
+    let later = tokio::time::Instant::now() + Duration::from_millis(300);
+    remote.refresh("gw-a", "acme::lobby", ["acme::alice", "acme::carol"], soon);
+    remote.refresh("gw-b", "acme::lobby", ["acme::carol"], later);
+    assert_eq!(core.presence.global_count("acme::lobby"), 2);
+
+    remote.remove("gw-a", "acme::lobby", "acme::carol");
+    assert_eq!(core.presence.global_count("acme::lobby"), 2, "still on gw-b");
+
+    tokio::time::advance(Duration::from_millis(200)).await;
+    assert_eq!(core.presence.users_in_global("acme::lobby"), vec!["acme::carol"]);
+    tokio::time::advance(Duration::from_millis(200)).await;
+    assert_eq!(core.presence.global_count("acme::lobby"), 0);
+    assert_eq!(remote.room_count(), 1, "expired entries linger until swept");
+    remote.sweep();
+    assert_eq!(remote.room_count(), 0);
+}
+
+#[test]
+fn presence_frames_round_trip() {
+    for delta in [PresenceDelta::Joined("acme::bob".into()), PresenceDelta::Left("acme::bob".into())] {
+        let f = decode_presence(&encode_presence("gw-2", 7, &delta)).unwrap();
+        assert_eq!((f.origin.as_str(), f.sent_unix_ms, &f.delta), ("gw-2", 7, &delta));
+    }
+
+    let users: Vec<String> = (0..20).map(|i| format!("acme::user-{i:02}")).collect();
+    let frames = encode_snapshots("gw-2", 7, &users, 64);
+    assert!(frames.len() > 1);
+    let mut decoded = Vec::new();
+    for frame in &frames {
+        assert!(frame.len() <= 64);
+        let PresenceDelta::Snapshot(part) = decode_presence(frame).unwrap().delta else { panic!("not a snapshot") };
+        decoded.extend(part);
+    }
+    assert_eq!(decoded, users);
+
+    // Publish frames are not presence frames and vice versa.
+    assert!(decode_presence(&encode_frame("gw-2", 0, None, &PreparedMsg::Text("x".into()))).is_err());
+    assert!(decode_frame(&encode_presence("gw-2", 0, &PresenceDelta::Left("u".into()))).is_err());
+}
+
+#[test]
+fn frames_round_trip() {
+    let text = encode_frame("gw-2", 1234, Some("acme::bob"), &PreparedMsg::Text("{\"n\":1}".into()));
+    let f = decode_frame(&text).unwrap();
+    assert_eq!((f.origin.as_str(), f.sent_unix_ms, f.exclude_user_key.as_deref()), ("gw-2", 1234, Some("acme::bob")));
+    assert!(matches!(f.msg, PreparedMsg::Text(s) if &*s == "{\"n\":1}"));
+
+    let bin = encode_frame("gw-2", 0, None, &PreparedMsg::Binary(Bytes::from_static(&[1, 2, 3])));
+    let f = decode_frame(&bin).unwrap();
+    assert_eq!(f.exclude_user_key, None);
+    assert!(matches!(f.msg, PreparedMsg::Binary(b) if b[..] == [1, 2, 3]));
+
+    for bad in [&[][..], &text[..5], &[9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]] {
+        assert!(decode_frame(bad).is_err());
+    }
+}
+
+#[test]
+fn bad_frames_are_counted_and_dropped() {
+    let metrics = Arc::new(GatewayMetrics::default());
+    let relay = ClusterRelay::new(&cluster_cfg("gw-1"), Arc::new(HubTransport(Hub::new())), metrics.clone());
+    let core = RealtimeCore::new();
+    relay.on_frame(&core, "acme::lobby", b"garbage");
+    relay.on_frame(&core, "acme::lobby", &[0; 2048]);
+    // Not connected yet: publishes stay local and are not counted.
+    relay.relay("acme::lobby", &PreparedMsg::Text("x".repeat(2048).into()), None);
+    let out = metrics.render(&[]);
+    assert!(out.contains("wsprism_cluster_bridge_errors_total{kind=\"decode\"} 1\n"), "{out}");
+    assert!(out.contains("wsprism_cluster_bridge_errors_total{kind=\"too_large\"} 1\n"), "{out}");
+}
+
+#[cfg(feature = "cluster-redis")]
+#[test]
+fn redis_channel_names() {
+    use wsprism_gateway::cluster::RedisBridge;
+    assert_eq!(RedisBridge::channel_for("acme::match:1").as_deref(), Some("wsprism:acme:match:1"));
+    assert_eq!(RedisBridge::channel_for("no-tenant"), None);
+    assert_eq!(RedisBridge::pattern_for("a*b"), "wsprism:a\\*b:*");
+}
+
+#[cfg(feature = "cluster-nats")]
+#[test]
+fn nats_subject_names() {
+    use wsprism_gateway::cluster::NatsBridge;
+    assert_eq!(NatsBridge::subject_for("acme::match.1").as_deref(), Some("wsprism.acme.match.1"));
+    assert_eq!(NatsBridge::subject_for("no-tenant"), None);
+}
@@ -0,0 +1,78 @@
+//! Shared setup for tests that talk to a served gateway over WebSocket.
+//!
+//! Each test binary uses a different subset of these helpers.
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::{config, router};
+
+pub type Ws = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// How long a read waits for the next frame before the test fails.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve `state`'s router on an ephemeral local port.
+pub async fn serve(state: &AppState) -> SocketAddr {
+    let app = router::build_router(state.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+/// Build a gateway from the YAML config `cfg` and serve it.
+pub async fn spawn_gateway(cfg: &str) -> (AppState, SocketAddr) {
+    let state = AppState::new(config::load_from_str(cfg).unwrap()).unwrap();
+    let addr = serve(&state).await;
+    (state, addr)
+}
+
+/// Open `/v1/ws?{query}`, e.g. `connect(addr, "tenant=acme&ticket=dev")`.
+pub async fn connect(addr: SocketAddr, query: &str) -> Ws {
+    let (ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/v1/ws?{query}")).await.unwrap();
+    ws
+}
+
+/// Next data frame, skipping Ping/Pong. The server's first keepalive Ping
+/// goes out right after the upgrade, so any read may meet one.
+pub async fn next_message(ws: &mut Ws) -> Message {
+    loop {
+        match tokio::time::timeout(READ_TIMEOUT, ws.next()).await.unwrap() {
+            Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+            Some(Ok(msg)) => return msg,
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+}
+
+/// Next Text frame.
+pub async fn next_text(ws: &mut Ws) -> String {
+    match next_message(ws).await {
+        Message::Text(t) => t,
+        other => panic!("expected a text frame, got {other:?}"),
+    }
+}
+
+/// Next Text frame, parsed as JSON.
+pub async fn next_json(ws: &mut Ws) -> Value {
+    serde_json::from_str(&next_text(ws).await).unwrap()
+}
+
+/// Next Binary frame.
+pub async fn next_binary(ws: &mut Ws) -> Vec<u8> {
+    match next_message(ws).await {
+        Message::Binary(b) => b,
+        other => panic!("expected a binary frame, got {other:?}"),
+    }
+}
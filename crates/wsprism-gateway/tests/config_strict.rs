@@ -30,3 +30,62 @@ tenants:
     assert_eq!(cfg.version, 1);
     assert_eq!(cfg.tenants[0].id, "acme");
 }
+
+#[test]
+fn guest_scopes_require_allow_guest() {
+    let bad = r#"
+version: 1
+tenants:
+  - id: "acme"
+    guest_scopes: ["room:join"]
+"#;
+    let err = config::load_from_str(bad).expect_err("must fail");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+
+    let ok = r#"
+version: 1
+tenants:
+  - id: "acme"
+    allow_guest: true
+    guest_scopes: ["room:join", "room:leave"]
+"#;
+    let cfg = config::load_from_str(ok).expect("must parse");
+    assert!(cfg.tenants[0].allow_guest);
+}
+
+#[test]
+fn overlay_overrides_matching_tenant_and_appends_new() {
+    let base: config::GatewayConfig = serde_yaml::from_str(
+        r#"
+version: 1
+gateway:
+  idle_timeout_ms: 90000
+tenants:
+  - id: "acme"
+    policy:
+      rate_limit_rps: 100
+      ext_allowlist: ["chat:*"]
+"#,
+    )
+    .unwrap();
+    let overlay: config::GatewayConfig = serde_yaml::from_str(
+        r#"
+version: 1
+gateway:
+  listen: "127.0.0.1:9000"
+tenants:
+  - id: "acme"
+    policy:
+      rate_limit_rps: 500
+  - id: "beta"
+"#,
+    )
+    .unwrap();
+
+    let merged = config::GatewayConfig::merge(base, overlay).expect("merge");
+    assert_eq!(merged.gateway.listen, "127.0.0.1:9000");
+    assert_eq!(merged.gateway.idle_timeout_ms, 90000);
+    assert_eq!(merged.tenants.len(), 2);
+    assert_eq!(merged.tenants[0].policy.rate_limit_rps, 500);
+    assert_eq!(merged.tenants[0].policy.ext_allowlist, vec!["chat:*".to_string()]);
+}
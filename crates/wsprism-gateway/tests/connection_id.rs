@@ -0,0 +1,141 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+
+use axum::extract::ws::Message;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCore, RealtimeCtx};
+
+fn register(core: &RealtimeCore, user: &str) -> (ConnectionId, mpsc::Receiver<Message>) {
+    let (tx, rx) = mpsc::channel(8);
+    let id = ConnectionId::new();
+    core.sessions
+        .try_insert(
+            "acme".into(),
+            format!("acme::{user}"),
+            id,
+            Connection::new(tx, SessionClaims::default()),
+            0,
+        )
+        .unwrap();
+    (id, rx)
+}
+
+fn msg() -> Outgoing {
+    Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!({ "m": 1 })), priority: Priority::Normal }
+}
+
+#[test]
+fn connection_ids_are_unique() {
+    assert_ne!(ConnectionId::new(), ConnectionId::new());
+}
+
+#[test]
+fn same_user_sessions_are_tracked_separately() {
+    let core = Arc::new(RealtimeCore::new());
+    let (a, mut rx_a) = register(&core, "alice");
+    let (b, mut rx_b) = register(&core, "alice");
+    assert_eq!(core.sessions.count_user_sessions("acme::alice"), 2);
+    assert_eq!(core.sessions.count_tenant_sessions("acme"), 2);
+
+    core.send_to_session(b, msg()).unwrap();
+    assert!(rx_a.try_recv().is_err());
+    assert!(matches!(rx_b.try_recv(), Ok(Message::Text(_))));
+
+    assert!(core.sessions.unregister(a).is_some());
+    assert!(core.sessions.unregister(a).is_none(), "unregister is idempotent");
+    assert_eq!(core.sessions.count_user_sessions("acme::alice"), 1);
+    assert_eq!(core.sessions.count_tenant_sessions("acme"), 1);
+    assert_eq!(core.sessions.user_of(b).as_deref(), Some("acme::alice"));
+}
+
+#[test]
+fn room_routing_is_per_connection_and_membership_per_user() {
+    let core = Arc::new(RealtimeCore::new());
+    let (a, mut rx_a) = register(&core, "alice");
+    let (b, mut rx_b) = register(&core, "alice");
+    let limits = TenantLimits::default();
+
+    let ctx_a = RealtimeCtx::new("acme", "alice", a, "t", None, core.clone());
+    let ctx_b = RealtimeCtx::new("acme", "alice", b, "t", None, core.clone());
+    assert_eq!(ctx_a.connection_id(), a);
+    ctx_a.join_room_with_limits("lobby", &limits).unwrap();
+    ctx_b.join_room_with_limits("lobby", &limits).unwrap();
+
+    core.publish_room_lossy("acme::lobby", msg()).unwrap();
+    assert!(rx_a.try_recv().is_ok());
+    assert!(rx_b.try_recv().is_ok());
+
+    core.sessions.unregister(a);
+    core.presence.cleanup_session("acme", "acme::alice", a);
+    assert_eq!(core.presence.sessions_in("acme::lobby"), vec![b]);
+    assert!(ctx_b.is_in_room("lobby"), "user stays in the room via the other connection");
+}
+
+#[test]
+fn collect_sessions_reports_queue_depth_and_connect_time() {
+    let core = RealtimeCore::new();
+    let (a, _rx_a) = register(&core, "alice");
+    let (b, _rx_b) = register(&core, "bob");
+
+    core.send_to_session(a, msg()).unwrap();
+    core.send_to_session(a, msg()).unwrap();
+
+    let seen = std::cell::Cell::new(0);
+    core.sessions.iter_sessions(|_| seen.set(seen.get() + 1));
+    assert_eq!(seen.get(), 2);
+
+    let snaps = core.sessions.collect_sessions();
+    let snap_a = snaps.iter().find(|s| s.connection_id == a).unwrap();
+    let snap_b = snaps.iter().find(|s| s.connection_id == b).unwrap();
+    assert_eq!(snap_a.user_id, "alice");
+    assert_eq!(snap_a.tenant, "acme");
+    assert_eq!(snap_a.queue_depth, 2);
+    assert_eq!(snap_b.queue_depth, 0);
+    assert!(snap_a.connected_at_unix_ms > 0);
+}
+
+#[test]
+fn send_to_self_reaches_every_session_of_the_caller() {
+    let core = Arc::new(RealtimeCore::new());
+    let (a, mut rx_a) = register(&core, "alice");
+    let (_b, mut rx_b) = register(&core, "alice");
+    let (_c, mut rx_c) = register(&core, "bob");
+    let ctx = RealtimeCtx::new("acme", "alice", a, "t", None, core.clone());
+
+    ctx.send_to_self(msg()).unwrap();
+    ctx.send_to_self_lossy(json!({ "type": "ack" })).unwrap();
+    for rx in [&mut rx_a, &mut rx_b] {
+        assert!(matches!(rx.try_recv(), Ok(Message::Text(_))));
+        match rx.try_recv() {
+            Ok(Message::Text(t)) => assert_eq!(t, r#"{"type":"ack"}"#),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+    assert!(rx_c.try_recv().is_err());
+
+    ctx.send_to_self_lossy(bytes::Bytes::from_static(&[1, 2])).unwrap();
+    assert!(matches!(rx_a.try_recv(), Ok(Message::Binary(b)) if b == [1, 2]));
+}
+
+#[tokio::test]
+async fn clone_for_spawn_clears_active_room() {
+    let core = Arc::new(RealtimeCore::new());
+    let (a, mut rx_a) = register(&core, "alice");
+    let ctx = RealtimeCtx::new("acme", "alice", a, "t", Some("lobby".into()), core.clone());
+
+    let spawned = ctx.clone_for_spawn();
+    assert_eq!(ctx.active_room(), Some("lobby"));
+    assert_eq!(spawned.active_room(), None);
+    assert_eq!((spawned.user_key(), spawned.connection_id()), (ctx.user_key(), a));
+
+    tokio::spawn(async move { spawned.send_to_self(msg()) }).await.unwrap().unwrap();
+    assert!(matches!(rx_a.try_recv(), Ok(Message::Text(_))));
+}
@@ -0,0 +1,78 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use axum::body::Body;
+use axum::http::{Request, Response};
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::{config, router};
+
+fn app(cors: &str) -> axum::Router {
+    let yaml = format!("version: 1\ngateway:\n  cors: {cors}\ntenants:\n  - id: acme\n");
+    router::build_router(AppState::new(config::load_from_str(&yaml).unwrap()).unwrap())
+}
+
+async fn send(app: axum::Router, req: Request<Body>) -> Response<Body> {
+    app.oneshot(req).await.unwrap()
+}
+
+fn header<'a>(resp: &'a Response<Body>, name: &str) -> Option<&'a str> {
+    resp.headers().get(name).map(|v| v.to_str().unwrap())
+}
+
+fn upgrade(origin: &str) -> Request<Body> {
+    Request::get("/v1/ws?tenant=acme&ticket=dev")
+        .header("origin", origin)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn listed_origin_is_echoed_on_upgrade_and_preflight() {
+    let app = app(r#"{ allowed_origins: ["https://app.example.com"], allow_credentials: true }"#);
+
+    // Not a real upgrade, so the handler rejects it; CORS headers are still set.
+    let resp = send(app.clone(), upgrade("https://app.example.com")).await;
+    assert_eq!(header(&resp, "access-control-allow-origin"), Some("https://app.example.com"));
+    assert_eq!(header(&resp, "access-control-allow-credentials"), Some("true"));
+
+    let resp = send(app.clone(), upgrade("https://evil.example.com")).await;
+    assert_eq!(header(&resp, "access-control-allow-origin"), None);
+
+    let preflight = Request::options("/v1/ws")
+        .header("origin", "https://app.example.com")
+        .header("access-control-request-method", "GET")
+        .header("access-control-request-headers", "sec-websocket-protocol")
+        .body(Body::empty())
+        .unwrap();
+    let resp = send(app.clone(), preflight).await;
+    assert!(resp.status().is_success(), "{}", resp.status());
+    assert_eq!(header(&resp, "access-control-allow-origin"), Some("https://app.example.com"));
+    assert!(header(&resp, "access-control-allow-methods").unwrap().contains("GET"));
+    assert_eq!(header(&resp, "access-control-allow-headers"), Some("sec-websocket-protocol"));
+
+    // Other routes are not covered.
+    let health = Request::get("/healthz").header("origin", "https://app.example.com").body(Body::empty()).unwrap();
+    assert_eq!(header(&send(app, health).await, "access-control-allow-origin"), None);
+}
+
+#[tokio::test]
+async fn wildcard_allows_any_origin_and_default_sends_nothing() {
+    let resp = send(app(r#"{ allowed_origins: ["*"] }"#), upgrade("https://x.example")).await;
+    assert_eq!(header(&resp, "access-control-allow-origin"), Some("*"));
+    assert_eq!(header(&resp, "access-control-allow-credentials"), None);
+
+    let resp = send(app("{}"), upgrade("https://x.example")).await;
+    assert_eq!(header(&resp, "access-control-allow-origin"), None);
+}
+
+#[test]
+fn wildcard_with_credentials_is_rejected() {
+    let cfg = |cors: &str| config::load_from_str(&format!("version: 1\ngateway:\n  cors: {cors}\ntenants:\n  - id: acme\n"));
+    let err = cfg(r#"{ allowed_origins: ["*"], allow_credentials: true }"#).expect_err("rfc violation");
+    assert!(err.to_string().contains("allow_credentials"), "{err}");
+    assert!(cfg(r#"{ allowed_origins: [""] }"#).is_err());
+    assert!(cfg(r#"{ allowed_origins: ["https://a.example"], allow_credentials: true }"#).is_ok());
+}
@@ -0,0 +1,65 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::config;
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::realtime::{RealtimeCore, RealtimeCtx};
+
+#[test]
+fn custom_metrics_render_after_builtins() {
+    let m = GatewayMetrics::default();
+    let games = m.register_counter("chess_games_total").unwrap();
+    games.inc(&[("tenant", "acme")]);
+    games.add(&[("tenant", "acme")], 2);
+    m.register_gauge("chess_games_active").unwrap().set(&[("tenant", "acme")], 4);
+    m.register_histogram("chess_move_seconds").unwrap().observe(&[], Duration::from_millis(2));
+
+    let out = m.render(&[]);
+    assert!(out.contains("# TYPE chess_games_total counter\nchess_games_total{tenant=\"acme\"} 3\n"), "{out}");
+    assert!(out.contains("# TYPE chess_games_active gauge\nchess_games_active{tenant=\"acme\"} 4\n"), "{out}");
+    assert!(out.contains("# TYPE chess_move_seconds histogram\n"), "{out}");
+    assert!(out.contains("chess_move_seconds_bucket{le=\"0.005\"} 1\n"), "{out}");
+    assert!(out.find("chess_games_active").unwrap() > out.find("wsprism_room_members").unwrap());
+}
+
+#[test]
+fn registering_twice_returns_the_same_metric() {
+    let m = GatewayMetrics::default();
+    let a = m.register_counter("gameplay_inputs_total").unwrap();
+    let b = m.register_counter("gameplay_inputs_total").unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+
+    let err = m.register_gauge("gameplay_inputs_total").err().unwrap();
+    assert!(err.to_string().contains("another kind"), "{err}");
+}
+
+#[test]
+fn invalid_and_reserved_names_are_rejected() {
+    let m = GatewayMetrics::default();
+    for name in ["", "a", "Games", "1games", "_games", "games-active", "games active", "gämes"] {
+        let err = m.register_counter(name).err().unwrap();
+        assert!(err.to_string().contains("must match"), "{name}: {err}");
+    }
+    for name in ["wsprism_ws_upgrades_total", "wsprism_room_members", "wsprism_mine"] {
+        let err = m.register_gauge(name).err().unwrap();
+        assert!(err.to_string().contains("reserved"), "{name}: {err}");
+    }
+    assert!(m.register_counter("a1").is_ok());
+}
+
+#[test]
+fn services_reach_the_registry_through_the_context() {
+    let state = AppState::new(config::load_from_str("version: 1\ntenants:\n  - id: acme\n").unwrap()).unwrap();
+    let ctx = RealtimeCtx::new("acme", "u1", ConnectionId::new(), "t", None, state.realtime());
+    ctx.metrics().unwrap().register_counter("gameplay_inputs_total").unwrap().inc(&[("tenant", ctx.tenant())]);
+    assert!(state.metrics().render(&[]).contains("gameplay_inputs_total{tenant=\"acme\"} 1\n"));
+
+    let bare = RealtimeCtx::new("acme", "u1", ConnectionId::new(), "t", None, Arc::new(RealtimeCore::new()));
+    assert!(bare.metrics().is_none());
+}
@@ -0,0 +1,90 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::extract::ws::Message;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{
+    DeadLetterHandler, Outgoing, Payload, PreparedMsg, Priority, QoS, RealtimeCore,
+};
+
+#[derive(Default)]
+struct Capture(Mutex<Vec<(String, String)>>);
+
+#[async_trait]
+impl DeadLetterHandler for Capture {
+    async fn on_drop(&self, user: &str, room: &str, _msg: &PreparedMsg) {
+        self.0.lock().unwrap().push((user.to_string(), room.to_string()));
+    }
+}
+
+#[tokio::test]
+async fn reliable_timeout_on_full_queue_is_dead_lettered() {
+    let capture = Arc::new(Capture::default());
+    let core = RealtimeCore::new().with_dead_letter_handler(capture.clone());
+
+    // Queue of 1, already full: the reliable send must time out.
+    let (tx, _rx) = mpsc::channel::<Message>(1);
+    tx.try_send(Message::Text("filler".into())).unwrap();
+    let conn = Connection::new(tx, SessionClaims::default());
+    let id = ConnectionId::new();
+    core.sessions
+        .try_insert("acme".into(), "acme::bob".into(), id, conn, 0)
+        .unwrap();
+    core.presence
+        .try_join("acme", "acme::lobby", "acme::bob", id, &TenantLimits::default())
+        .unwrap();
+
+    let out = Outgoing {
+        qos: QoS::Reliable { timeout_ms: 10 },
+        payload: Payload::TextJson(json!({ "hello": "world" })),
+        priority: Priority::Normal,
+    };
+    core.publish_room_reliable("acme::lobby", out).await.unwrap();
+
+    let got = capture.0.lock().unwrap().clone();
+    assert_eq!(got, vec![("acme::bob".to_string(), "acme::lobby".to_string())]);
+}
+
+#[tokio::test]
+async fn reliable_fanout_runs_in_chunks() {
+    let capture = Arc::new(Capture::default());
+    let core = RealtimeCore::new().with_dead_letter_handler(capture.clone()).with_fanout_limit(2);
+
+    // Five stuck recipients; keep the receivers alive so sends time out.
+    let mut receivers = Vec::new();
+    for i in 0..5 {
+        let (tx, rx) = mpsc::channel::<Message>(1);
+        tx.try_send(Message::Text("filler".into())).unwrap();
+        receivers.push(rx);
+        let id = ConnectionId::new();
+        let user = format!("acme::u{i}");
+        core.sessions
+            .try_insert("acme".into(), user.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+            .unwrap();
+        core.presence.try_join("acme", "acme::lobby", &user, id, &TenantLimits::default()).unwrap();
+    }
+
+    let out = || Outgoing {
+        qos: QoS::Reliable { timeout_ms: 50 },
+        payload: Payload::TextJson(json!({ "hello": "world" })),
+        priority: Priority::Normal,
+    };
+    // 5 recipients, 2 at a time: three rounds of the 50 ms timeout.
+    let start = std::time::Instant::now();
+    core.publish_room_reliable("acme::lobby", out()).await.unwrap();
+    assert!(start.elapsed() >= std::time::Duration::from_millis(150), "{:?}", start.elapsed());
+    assert_eq!(capture.0.lock().unwrap().len(), 5);
+
+    // Explicit limit (as used by tenant contexts).
+    core.publish_room_reliable_bounded("acme::lobby", out(), 8).await.unwrap();
+    assert_eq!(capture.0.lock().unwrap().len(), 10);
+}
@@ -0,0 +1,174 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::hot::HotFrame;
+use wsprism_core::protocol::text::Envelope;
+
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::dispatch::{
+    AuthRequiredMiddleware, BinaryService, BinaryServiceChain, Dispatcher, LoggingBinaryMiddleware,
+    LoggingMiddleware, TextService, TextServiceMiddleware,
+};
+use wsprism_gateway::realtime::{Outgoing, RealtimeCore, RealtimeCtx};
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+struct Recorder(Log);
+
+#[async_trait]
+impl TextService for Recorder {
+    fn svc(&self) -> &'static str {
+        "echo"
+    }
+    async fn handle(&self, _ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
+        self.0.lock().unwrap().push(format!("svc:{}", env.msg_type));
+        Ok(None)
+    }
+}
+
+struct Tag(&'static str, Log);
+
+#[async_trait]
+impl TextServiceMiddleware for Tag {
+    async fn call(&self, ctx: RealtimeCtx, env: Envelope, next: &dyn TextService) -> Result<Option<Outgoing>> {
+        self.1.lock().unwrap().push(self.0.to_string());
+        next.handle(ctx, env).await
+    }
+}
+
+fn env() -> Envelope {
+    serde_json::from_str(r#"{"v":1,"svc":"echo","type":"ping"}"#).unwrap()
+}
+
+fn ctx(guest: bool) -> RealtimeCtx {
+    RealtimeCtx::new("acme", "u1", ConnectionId::new(), "t", None, Arc::new(RealtimeCore::new())).with_guest(guest)
+}
+
+#[tokio::test]
+async fn middleware_runs_in_order_before_service() {
+    let log: Log = Arc::default();
+    let d = Dispatcher::new();
+    d.register_text_with_middleware(
+        Arc::new(Recorder(log.clone())),
+        vec![
+            Arc::new(Tag("a", log.clone())),
+            Arc::new(LoggingMiddleware),
+            Arc::new(Tag("b", log.clone())),
+        ],
+    );
+    assert_eq!(d.registered_text_svcs(), vec!["echo"]);
+
+    d.dispatch_text(ctx(false), env()).await.unwrap();
+    assert_eq!(*log.lock().unwrap(), vec!["a", "b", "svc:ping"]);
+}
+
+#[tokio::test]
+async fn auth_required_rejects_guests() {
+    let log: Log = Arc::default();
+    let d = Dispatcher::new();
+    d.register_text_with_middleware(
+        Arc::new(Recorder(log.clone())),
+        vec![Arc::new(AuthRequiredMiddleware)],
+    );
+
+    let err = d.dispatch_text(ctx(true), env()).await.expect_err("guest");
+    assert_eq!(err.client_code().as_str(), "AUTH_FAILED");
+    assert!(log.lock().unwrap().is_empty());
+
+    d.dispatch_text(ctx(false), env()).await.unwrap();
+    assert_eq!(log.lock().unwrap().len(), 1);
+}
+
+struct Stats;
+
+#[async_trait]
+impl TextService for Stats {
+    fn svc(&self) -> &'static str {
+        "echo"
+    }
+    async fn handle(&self, ctx: RealtimeCtx, _env: Envelope) -> Result<Option<Outgoing>> {
+        Ok(Some(Outgoing::reply(serde_json::json!({ "rooms": ctx.rooms().len() }))))
+    }
+}
+
+#[tokio::test]
+async fn returned_outgoing_is_sent_to_caller() {
+    use axum::extract::ws::Message;
+    use wsprism_gateway::context::SessionClaims;
+    use wsprism_gateway::realtime::core::Connection;
+    use wsprism_gateway::realtime::QoS;
+
+    let core = Arc::new(RealtimeCore::new());
+    let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+    let conn = Connection::new(tx, SessionClaims::default());
+    let id = ConnectionId::new();
+    core.sessions
+        .try_insert("acme".into(), "acme::u1".into(), id, conn, 0)
+        .unwrap();
+
+    assert!(matches!(Outgoing::reply(serde_json::Value::Null).qos, QoS::Reliable { timeout_ms: 1500 }));
+
+    let d = Dispatcher::new();
+    d.register_text(Arc::new(Stats));
+    let ctx = RealtimeCtx::new("acme", "u1", id, "t", None, core);
+    d.dispatch_text(ctx, env()).await.unwrap();
+
+    let Some(Message::Text(body)) = rx.recv().await else { panic!("no reply") };
+    assert_eq!(body, r#"{"rooms":0}"#);
+}
+
+/// Hot Lane stage that logs its name and optionally fails.
+struct Stage(&'static str, bool, Log);
+
+#[async_trait]
+impl BinaryService for Stage {
+    fn svc_id(&self) -> u8 {
+        99
+    }
+    async fn handle_binary(&self, _ctx: RealtimeCtx, frame: HotFrame) -> Result<()> {
+        self.2.lock().unwrap().push(format!("{}:{}", self.0, frame.opcode));
+        if self.1 {
+            return Err(WsPrismError::NotAllowed(self.0.into()));
+        }
+        Ok(())
+    }
+}
+
+fn hot_frame(svc_id: u8) -> HotFrame {
+    HotFrame { v: 1, svc_id, opcode: 7, flags: 0, seq: None, payload: Bytes::from_static(b"abc") }
+}
+
+#[tokio::test]
+async fn hot_chain_runs_stages_in_order() {
+    let log: Log = Arc::default();
+    let chain = BinaryServiceChain::new()
+        .push(Arc::new(Stage("auth", false, log.clone())))
+        .push(Arc::new(Stage("game", false, log.clone())));
+    let d = Dispatcher::new();
+    d.register_hot_chain(3, chain);
+    assert_eq!(d.registered_hot_svcs(), vec![3]);
+
+    d.dispatch_hot(ctx(false), hot_frame(3)).await.unwrap();
+    assert_eq!(*log.lock().unwrap(), vec!["auth:7", "game:7"]);
+}
+
+#[tokio::test]
+async fn hot_chain_stops_at_first_error() {
+    let log: Log = Arc::default();
+    let chain = BinaryServiceChain::new()
+        .push(Arc::new(Stage("auth", true, log.clone())))
+        .push(Arc::new(Stage("game", false, log.clone())));
+    let d = Dispatcher::new();
+    d.register_hot(Arc::new(LoggingBinaryMiddleware::new(Arc::new(chain))));
+
+    // Registered directly, the chain keeps the default svc_id 0.
+    let err = d.dispatch_hot(ctx(false), hot_frame(0)).await.expect_err("auth stage fails");
+    assert_eq!(err.client_code().as_str(), "NOT_ALLOWED");
+    assert_eq!(*log.lock().unwrap(), vec!["auth:7"]);
+}
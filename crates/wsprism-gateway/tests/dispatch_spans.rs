@@ -0,0 +1,127 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::hot::HotFrame;
+use wsprism_core::protocol::text::Envelope;
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::dispatch::{BinaryService, Dispatcher, TextService};
+use wsprism_gateway::realtime::{Outgoing, RealtimeCore, RealtimeCtx};
+
+type Fields = HashMap<String, String>;
+
+/// Fields of every `dispatch` span, including ones recorded after creation.
+#[derive(Clone, Default)]
+struct Spans(Arc<Mutex<HashMap<u64, Fields>>>);
+
+impl Spans {
+    fn all(&self) -> Vec<Fields> {
+        self.0.lock().unwrap().values().cloned().collect()
+    }
+}
+
+struct Collect<'a>(&'a mut Fields);
+
+impl Visit for Collect<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Spans {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _: Context<'_, S>) {
+        if attrs.metadata().name() == "dispatch" {
+            let mut fields = Fields::new();
+            attrs.record(&mut Collect(&mut fields));
+            self.0.lock().unwrap().insert(id.into_u64(), fields);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _: Context<'_, S>) {
+        if let Some(fields) = self.0.lock().unwrap().get_mut(&id.into_u64()) {
+            values.record(&mut Collect(fields));
+        }
+    }
+}
+
+struct Echo;
+
+#[async_trait]
+impl TextService for Echo {
+    fn svc(&self) -> &'static str {
+        "echo"
+    }
+    async fn handle(&self, _ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
+        if env.msg_type == "fail" {
+            return Err(WsPrismError::BadRequest("nope".into()));
+        }
+        Ok(None)
+    }
+}
+
+struct Move;
+
+#[async_trait]
+impl BinaryService for Move {
+    fn svc_id(&self) -> u8 {
+        7
+    }
+    async fn handle_binary(&self, _ctx: RealtimeCtx, _frame: HotFrame) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn ctx() -> RealtimeCtx {
+    RealtimeCtx::new("acme", "alice", ConnectionId::new(), "trace-1", None, Arc::new(RealtimeCore::new()))
+}
+
+fn env(msg_type: &str) -> Envelope {
+    serde_json::from_str(&format!(r#"{{"v":1,"svc":"echo","type":"{msg_type}"}}"#)).unwrap()
+}
+
+#[tokio::test]
+async fn dispatch_spans_carry_request_fields_and_error_status() {
+    let spans = Spans::default();
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+
+    let d = Dispatcher::new();
+    d.register_text(Arc::new(Echo));
+    d.register_hot(Arc::new(Move));
+
+    d.dispatch_text(ctx(), env("ping")).await.unwrap();
+    d.dispatch_text(ctx(), env("fail")).await.unwrap_err();
+    let frame = HotFrame { v: 1, svc_id: 7, opcode: 3, flags: 0, seq: None, payload: Bytes::new() };
+    d.dispatch_hot(ctx(), frame).await.unwrap();
+
+    let all = spans.all();
+    assert_eq!(all.len(), 3);
+    let find = |key: &str, value: &str| all.iter().find(|f| f.get(key).map(String::as_str) == Some(value)).unwrap();
+
+    let ok = find("msg_type", "ping");
+    assert_eq!(ok["svc"], "echo");
+    assert_eq!(ok["user"], "alice");
+    assert_eq!(ok["correlation_id"], "trace-1");
+    assert!(!ok.contains_key("otel.status_code"));
+
+    assert_eq!(find("msg_type", "fail")["otel.status_code"], "ERROR");
+
+    let hot = find("svc_id", "7");
+    assert_eq!(hot["opcode"], "3");
+    assert_eq!(hot["user"], "alice");
+    assert_eq!(hot["correlation_id"], "trace-1");
+}
@@ -0,0 +1,85 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::time::Duration;
+
+use axum::extract::ws::Message;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCore};
+
+fn connect(core: &RealtimeCore, user: &str) -> (ConnectionId, mpsc::Receiver<Message>) {
+    let (tx, rx) = mpsc::channel(128);
+    let id = ConnectionId::new();
+    let user_key = format!("acme::{user}");
+    core.sessions
+        .try_insert("acme".into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    core.presence.try_join("acme", "acme::lobby", &user_key, id, &TenantLimits::default()).unwrap();
+    (id, rx)
+}
+
+fn enqueue(core: &RealtimeCore, user: &str, n: usize) {
+    for i in 0..n {
+        let out = Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!({ "i": i })), priority: Priority::Normal };
+        core.send_to_user(&format!("acme::{user}"), out).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn drain_waits_for_queued_messages() {
+    let core = RealtimeCore::new();
+    let (id, mut rx) = connect(&core, "alice");
+    enqueue(&core, "alice", 100);
+
+    // A slow writer: starts late and takes a while per message.
+    let reader = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let mut texts = 0;
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                Message::Text(_) => texts += 1,
+                Message::Close(frame) => return (texts, frame.map(|f| f.code)),
+                _ => {}
+            }
+            tokio::time::sleep(Duration::from_micros(200)).await;
+        }
+        (texts, None)
+    });
+
+    core.drain_user("acme::alice", Duration::from_secs(5)).await.unwrap();
+    assert!(core.sessions.get_session(id).is_none());
+    assert!(core.presence.rooms_of("acme::alice").is_empty());
+    // Every message went out before the Close frame.
+    assert_eq!(reader.await.unwrap(), (100, Some(1000)));
+}
+
+#[tokio::test]
+async fn drain_gives_up_after_timeout() {
+    let core = RealtimeCore::new();
+    let (id, _rx) = connect(&core, "bob");
+    enqueue(&core, "bob", 10);
+
+    let err = core.drain_user("acme::bob", Duration::from_millis(20)).await.unwrap_err();
+    assert!(err.to_string().contains("not flushed"), "{err}");
+    assert!(core.sessions.get_session(id).is_none(), "disconnected anyway");
+
+    assert!(core.drain_user("acme::bob", Duration::from_millis(20)).await.is_err(), "no longer connected");
+}
+
+#[tokio::test]
+async fn flush_all_reports_whether_queues_emptied() {
+    let core = RealtimeCore::new();
+    let (_, mut rx) = connect(&core, "carol");
+    assert!(core.flush_all(Duration::from_millis(10)).await);
+
+    enqueue(&core, "carol", 3);
+    assert!(!core.flush_all(Duration::from_millis(10)).await);
+    while rx.try_recv().is_ok() {}
+    assert!(core.flush_all(Duration::from_millis(10)).await);
+}
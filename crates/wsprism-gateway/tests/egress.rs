@@ -0,0 +1,192 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::text::Envelope;
+
+use wsprism_gateway::config::{self, EgressConfig};
+use wsprism_gateway::egress::{Egress, EgressRecord, EgressSink};
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+
+/// Collects written records; `fail_type` makes writes of that type fail.
+#[derive(Default)]
+struct MemorySink {
+    written: Mutex<Vec<Value>>,
+    flushes: Mutex<u32>,
+    fail_type: Option<&'static str>,
+}
+
+#[async_trait]
+impl EgressSink for MemorySink {
+    async fn write(&self, record: &EgressRecord) -> Result<()> {
+        if self.fail_type == Some(record.msg_type.as_str()) {
+            return Err(WsPrismError::Internal("broker down".into()));
+        }
+        self.written.lock().unwrap().push(serde_json::to_value(record).unwrap());
+        Ok(())
+    }
+
+    async fn flush(&self, _timeout: Duration) -> Result<()> {
+        *self.flushes.lock().unwrap() += 1;
+        Ok(())
+    }
+}
+
+fn record(msg_type: &str, n: u64) -> EgressRecord {
+    let env: Envelope = serde_json::from_value(json!({
+        "svc": "chat", "type": msg_type, "room": "lobby", "data": { "n": n }
+    }))
+    .unwrap();
+    EgressRecord::new("acme", "u1", &env)
+}
+
+fn pipeline(sink: Arc<MemorySink>, queue_capacity: usize) -> (Arc<Egress>, Arc<GatewayMetrics>) {
+    let metrics = Arc::new(GatewayMetrics::default());
+    let cfg = EgressConfig { queue_capacity, ..EgressConfig::default() };
+    (Arc::new(Egress::new(sink, &cfg, metrics.clone())), metrics)
+}
+
+#[test]
+fn record_serializes_to_archive_schema() {
+    let v = serde_json::to_value(record("send", 7)).unwrap();
+    assert!(v["ts"].as_u64().unwrap() > 0);
+    let mut v = v;
+    v.as_object_mut().unwrap().remove("ts");
+    assert_eq!(v, json!({
+        "tenant": "acme", "user": "u1", "room": "lobby", "svc": "chat", "type": "send", "data": { "n": 7 }
+    }));
+
+    let env: Envelope = serde_json::from_value(json!({ "svc": "chat", "type": "ping" })).unwrap();
+    let r = EgressRecord::new("acme", "u1", &env);
+    assert_eq!(r.key(), "acme", "room-less records are keyed by tenant");
+    let v = serde_json::to_value(&r).unwrap();
+    assert_eq!((&v["room"], &v["data"]), (&Value::Null, &Value::Null));
+}
+
+#[tokio::test]
+async fn shutdown_drains_queue_and_flushes() {
+    let sink = Arc::new(MemorySink::default());
+    let (egress, _) = pipeline(sink.clone(), 64);
+    for n in 0..10 {
+        assert!(egress.offer(record("send", n)));
+    }
+    egress.clone().spawn().unwrap();
+    assert!(egress.clone().spawn().is_none(), "one writer only");
+
+    assert!(egress.shutdown().await);
+    let written = sink.written.lock().unwrap().iter().map(|v| v["data"]["n"].as_u64().unwrap()).collect::<Vec<_>>();
+    assert_eq!(written, (0..10).collect::<Vec<_>>());
+    assert_eq!(*sink.flushes.lock().unwrap(), 1);
+    assert!(!egress.offer(record("send", 10)), "closed after shutdown");
+}
+
+#[tokio::test]
+async fn full_queue_and_failed_writes_are_counted() {
+    let sink = Arc::new(MemorySink { fail_type: Some("bad"), ..MemorySink::default() });
+    let (egress, metrics) = pipeline(sink.clone(), 2);
+    assert!(egress.offer(record("send", 0)));
+    assert!(egress.offer(record("bad", 1)));
+    assert!(!egress.offer(record("send", 2)));
+
+    egress.clone().spawn().unwrap();
+    assert!(egress.shutdown().await);
+    assert_eq!(sink.written.lock().unwrap().len(), 1);
+    let text = metrics.render(&[]);
+    assert!(text.contains(r#"wsprism_egress_dropped_total{reason="queue_full",tenant="acme"} 1"#), "{text}");
+    assert!(text.contains(r#"wsprism_egress_dropped_total{reason="delivery",tenant="acme"} 1"#), "{text}");
+}
+
+#[tokio::test]
+async fn shutdown_without_writer_times_out() {
+    let metrics = Arc::new(GatewayMetrics::default());
+    let cfg = EgressConfig { queue_capacity: 1, flush_timeout_ms: 100, ..EgressConfig::default() };
+    let egress = Egress::new(Arc::new(MemorySink::default()), &cfg, metrics);
+    assert!(egress.offer(record("send", 0)));
+    assert!(!egress.shutdown().await);
+}
+
+#[test]
+fn egress_config_is_validated() {
+    let err = |yaml: &str| config::load_from_str(yaml).unwrap_err().to_string();
+    let tenant = "tenants:\n  - id: acme\n    policy:\n      egress: [\"chat:send\"]\n";
+
+    let e = err(&format!("version: 1\n{tenant}"));
+    assert!(e.contains("policy.egress requires gateway.egress.sink"), "{e}");
+    let e = err("version: 1\ngateway:\n  egress:\n    sink: kafka\ntenants:\n  - id: acme\n");
+    assert!(e.contains("gateway.egress.kafka is required"), "{e}");
+    let e = err("version: 1\ngateway:\n  egress:\n    sink: kafka\n    kafka: { brokers: [], topic: t }\ntenants:\n  - id: acme\n");
+    assert!(e.contains("brokers"), "{e}");
+
+    let sink = "gateway:\n  egress:\n    sink: kafka\n    kafka: { brokers: [\"k:9092\"], topic: chat-archive }\n";
+    let cfg = config::load_from_str(&format!("version: 1\n{sink}{tenant}")).unwrap();
+    assert_eq!(cfg.gateway.egress.kafka.unwrap().topic, "chat-archive");
+
+    let bad = "version: 1\ngateway:\n  egress:\n    sink: kafka\n    kafka: { brokers: [\"k:9092\"], topic: t }\ntenants:\n  - id: acme\n    policy:\n      egress: [\"chat:send@10\"]\n";
+    let cfg = config::load_from_str(bad).unwrap();
+    let e = wsprism_gateway::app_state::AppState::new(cfg).err().unwrap().to_string();
+    assert!(e.contains("invalid egress entry"), "{e}");
+}
+
+#[cfg(not(feature = "egress-kafka"))]
+#[test]
+fn kafka_sink_requires_feature() {
+    let yaml = "version: 1\ngateway:\n  egress:\n    sink: kafka\n    kafka: { brokers: [\"k:9092\"], topic: t }\ntenants:\n  - id: acme\n";
+    let e = wsprism_gateway::app_state::AppState::new(config::load_from_str(yaml).unwrap()).err().unwrap().to_string();
+    assert!(e.contains("built without the egress-kafka feature"), "{e}");
+}
+
+/// Matching messages sent over a WebSocket reach the sink; others do not.
+#[cfg(feature = "egress-kafka")]
+#[tokio::test]
+async fn matching_ws_messages_are_archived() {
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+    use wsprism_gateway::app_state::AppState;
+
+    use common::{connect, next_text, serve};
+
+    const CFG: &str = r#"
+version: 1
+gateway:
+  egress:
+    sink: kafka
+    kafka: { brokers: ["127.0.0.1:9"], topic: archive }
+tenants:
+  - id: acme
+    policy:
+      ext_allowlist: ["chat:*", "room:*"]
+      egress: ["chat:send"]
+"#;
+    let sink = Arc::new(MemorySink::default());
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap().with_egress_sink(sink.clone());
+    state.spawn_egress().unwrap();
+    let addr = serve(&state).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+
+    for frame in [
+        json!({ "svc": "room", "type": "join", "room": "lobby" }),
+        json!({ "svc": "chat", "type": "send", "room": "lobby", "data": { "msg": "hi" } }),
+        json!({ "svc": "chat", "type": "send", "data": { "msg": "no room" } }),
+        json!({ "svc": "chat", "type": "send", "room": "lobby", "data": { "msg": "bye" } }),
+        json!({ "svc": "chat", "type": "typing", "room": "lobby" }),
+    ] {
+        ws.send(Message::Text(frame.to_string())).await.unwrap();
+    }
+    // Frames are handled in order: the last one's error means every send
+    // before it was dispatched.
+    while !next_text(&mut ws).await.contains("unknown chat type") {}
+
+    assert!(state.egress().unwrap().shutdown().await);
+    let written = sink.written.lock().unwrap().clone();
+    let msgs = written.iter().map(|v| v["data"]["msg"].as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(msgs, ["hi", "bye"], "failed dispatches are not archived");
+    assert_eq!((&written[0]["user"], &written[0]["room"]), (&json!("user:dev"), &json!("lobby")));
+}
@@ -0,0 +1,73 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::app_state::{AppState, StartupOptions};
+use wsprism_gateway::config;
+use wsprism_gateway::obs::metrics::{FrameDir, GatewayMetrics};
+
+use common::{connect, next_text, serve};
+
+const LANE_EXT: [(&str, &str); 2] = [("tenant", "acme"), ("lane", "ext")];
+
+#[test]
+fn frame_sizes_land_in_byte_buckets() {
+    let m = GatewayMetrics::default();
+    m.observe_frame_size(FrameDir::Inbound, "acme", "ext", 64);
+    m.observe_frame_size(FrameDir::Inbound, "acme", "ext", 65);
+    m.observe_frame_size(FrameDir::Outbound, "acme", "hot", 100_000);
+
+    assert_eq!(m.inbound_frame_bytes.bucket_count(&LANE_EXT, 64), Some(1));
+    assert_eq!(m.inbound_frame_bytes.bucket_count(&LANE_EXT, 256), Some(2));
+    assert_eq!(m.inbound_frame_bytes.bucket_count(&LANE_EXT, 100), None);
+
+    let hot = [("tenant", "acme"), ("lane", "hot")];
+    assert_eq!(m.outbound_frame_bytes.bucket_count(&hot, 65_536), Some(0));
+    assert_eq!(m.outbound_frame_bytes.bucket_count(&hot, 262_144), Some(1));
+
+    let rendered = m.render(&[]);
+    assert!(rendered.contains("# TYPE wsprism_inbound_frame_size_bytes histogram\n# UNIT wsprism_inbound_frame_size_bytes bytes\n"));
+    assert!(rendered.contains("wsprism_outbound_frame_size_bytes_bucket{lane=\"hot\",tenant=\"acme\",le=\"+Inf\"} 1"));
+}
+
+#[tokio::test]
+async fn inbound_2048_byte_frame_is_observed() {
+    let cfg = config::load_from_str(
+        r#"
+version: 1
+tenants:
+  - id: "acme"
+    limits: { max_frame_bytes: 8192 }
+"#,
+    )
+    .unwrap();
+    let opts = StartupOptions { lenient: true, ..Default::default() };
+    let state = AppState::new_with_options(cfg, opts).unwrap();
+    let addr = serve(&state).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert!(next_text(&mut ws).await.contains("authed"));
+
+    // Envelope padded to exactly 2048 bytes.
+    let head = r#"{"v":1,"svc":"room","type":"join","room":"lobby","data":""#;
+    let tail = r#""}"#;
+    let frame = format!("{head}{}{tail}", "x".repeat(2048 - head.len() - tail.len()));
+    assert_eq!(frame.len(), 2048);
+    ws.send(Message::Text(frame)).await.unwrap();
+
+    // sys.joined reply.
+    let reply = next_text(&mut ws).await;
+    assert!(reply.contains("joined"), "{reply}");
+
+    let inbound = &state.metrics().inbound_frame_bytes;
+    assert_eq!(inbound.bucket_count(&LANE_EXT, 1_024), Some(0));
+    assert_eq!(inbound.bucket_count(&LANE_EXT, 4_096), Some(1));
+
+    let outbound = &state.metrics().outbound_frame_bytes;
+    assert!(reply.len() <= 256);
+    assert!(outbound.bucket_count(&LANE_EXT, 256).unwrap() >= 1);
+}
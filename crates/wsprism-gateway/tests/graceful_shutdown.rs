@@ -0,0 +1,80 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::future::{pending, ready};
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use futures_util::FutureExt;
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::ops::shutdown::{bounded, exit_step};
+use wsprism_gateway::{config, router};
+
+const TIMEOUT: Duration = Duration::from_millis(30_000);
+
+#[tokio::test(start_paused = true)]
+async fn stuck_server_times_out_after_the_signal() {
+    let fut = bounded(pending::<()>(), ready(()), TIMEOUT);
+    tokio::pin!(fut);
+    assert!((&mut fut).now_or_never().is_none());
+
+    tokio::time::advance(TIMEOUT - Duration::from_millis(1)).await;
+    assert!((&mut fut).now_or_never().is_none());
+    tokio::time::advance(Duration::from_millis(1)).await;
+    assert_eq!((&mut fut).now_or_never(), Some(None));
+}
+
+#[tokio::test(start_paused = true)]
+async fn timer_starts_only_with_the_signal() {
+    let fut = bounded(pending::<()>(), pending::<()>(), TIMEOUT);
+    tokio::pin!(fut);
+    tokio::time::advance(TIMEOUT * 10).await;
+    assert!((&mut fut).now_or_never().is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn finished_server_wins() {
+    assert_eq!(bounded(ready(7), ready(()), TIMEOUT).await, Some(7));
+}
+
+#[tokio::test(start_paused = true)]
+async fn exit_steps_are_cut_short_only_when_bounded() {
+    let slow = async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        1
+    };
+    assert_eq!(exit_step(slow, Some(Duration::from_secs(2))).await, None);
+    assert_eq!(exit_step(ready(2), Some(Duration::from_secs(2))).await, Some(2));
+    let slow = async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        3
+    };
+    assert_eq!(exit_step(slow, None).await, Some(3));
+}
+
+#[tokio::test]
+async fn draining_fails_readiness() {
+    let state = AppState::new(config::load_from_str("version: 1\ntenants:\n  - id: acme\n").unwrap()).unwrap();
+    let readyz = || async {
+        let req = Request::get("/readyz").body(Body::empty()).unwrap();
+        router::build_router(state.clone()).oneshot(req).await.unwrap().status()
+    };
+    assert_eq!(readyz().await, StatusCode::OK);
+    state.enter_draining();
+    assert_eq!(readyz().await, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[test]
+fn shutdown_timeout_is_validated() {
+    let load = |gw: &str| config::load_from_str(&format!("version: 1\ngateway:\n{gw}tenants:\n  - id: acme\n"));
+    assert_eq!(load("  listen: \"0.0.0.0:8080\"\n").unwrap().gateway.shutdown_timeout_ms, 30_000);
+    assert_eq!(load("  shutdown_timeout_ms: 1000\n").unwrap().gateway.shutdown_timeout_ms, 1000);
+    for bad in [999, 600_001] {
+        let e = load(&format!("  shutdown_timeout_ms: {bad}\n")).unwrap_err().to_string();
+        assert!(e.contains("gateway.shutdown_timeout_ms"), "{e}");
+    }
+}
@@ -0,0 +1,82 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::time::Duration;
+
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use wsprism_gateway::app_state::AppState;
+
+use common::{connect, next_json, spawn_gateway};
+
+const GUEST_CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    allow_guest: true
+    guest_scopes: ["room:*"]
+    policy:
+      ext_allowlist: ["room:*", "chat:*"]
+  - id: "closed"
+    policy:
+      ext_allowlist: ["room:*"]
+"#;
+
+fn active(state: &AppState, kind: &str) -> i64 {
+    state.metrics().ws_active_sessions.get(&[("tenant", "acme"), ("kind", kind)])
+}
+
+/// Sessions are deregistered after the socket closes, so poll for the gauge.
+async fn wait_for_active(state: &AppState, kind: &str, want: i64) {
+    for _ in 0..100 {
+        if active(state, kind) == want {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("ws_active_sessions{{kind={kind}}} = {}, want {want}", active(state, kind));
+}
+
+#[tokio::test]
+async fn guests_get_a_uuid_and_their_own_gauge() {
+    let (state, addr) = spawn_gateway(GUEST_CFG).await;
+
+    let mut guest = connect(addr, "tenant=acme").await;
+    let authed = next_json(&mut guest).await;
+    assert_eq!(authed["type"], "authed");
+    let id = authed["data"]["user"].as_str().unwrap();
+    let uuid = id.strip_prefix("guest-").expect("guest- prefix");
+    assert_eq!(uuid::Uuid::parse_str(uuid).unwrap().get_version_num(), 4, "{id}");
+
+    let mut user = connect(addr, "tenant=acme&ticket=dev").await;
+    assert_eq!(next_json(&mut user).await["type"], "authed");
+    assert_eq!((active(&state, "guest"), active(&state, "user")), (1, 1));
+
+    // Guest scopes still apply on top of the tenant allowlist.
+    guest.send(Message::Text(r#"{"svc":"chat","type":"send","room":"lobby","data":{}}"#.into())).await.unwrap();
+    assert_eq!(next_json(&mut guest).await["data"]["code"], "NOT_ALLOWED");
+
+    guest.close(None).await.unwrap();
+    wait_for_active(&state, "guest", 0).await;
+    assert_eq!(active(&state, "user"), 1);
+}
+
+#[tokio::test]
+async fn missing_ticket_is_auth_failed_without_allow_guest() {
+    let (state, addr) = spawn_gateway(GUEST_CFG).await;
+
+    match tokio_tungstenite::connect_async(format!("ws://{addr}/v1/ws?tenant=closed")).await {
+        Err(tungstenite::Error::Http(resp)) => {
+            assert_eq!(resp.status(), 401);
+            let body = String::from_utf8(resp.body().clone().unwrap_or_default()).unwrap();
+            assert_eq!(body, "AUTH_FAILED");
+        }
+        other => panic!("expected 401, got {other:?}"),
+    }
+    let rejected = [("tenant", "closed"), ("reason", "auth_failed")];
+    assert_eq!(state.metrics().handshake_rejections.get(&rejected), 1);
+}
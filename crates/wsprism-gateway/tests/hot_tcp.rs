@@ -0,0 +1,158 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::transport::hot_tcp;
+use wsprism_gateway::config;
+
+use common::{next_binary, next_text, serve};
+
+const KEY: &str = "backend-key-0123456789";
+
+const CFG: &str = r#"
+version: 1
+hot_tcp:
+  listen: "127.0.0.1:0"
+  auth_timeout_ms: 500
+tenants:
+  - id: acme
+    api_key: "backend-key-0123456789"
+    limits: { max_frame_bytes: 256 }
+    policy:
+      ext_allowlist: ["room:*"]
+      hot_allowlist: ["1:*"]
+      hot_requires_active_room: false
+"#;
+
+async fn start(yaml: &str) -> (AppState, SocketAddr) {
+    let state = AppState::new(config::load_from_str(yaml).unwrap()).unwrap();
+    let (addr, _) = hot_tcp::spawn(&state).await.unwrap().unwrap();
+    (state, addr)
+}
+
+async fn write_frame(s: &mut TcpStream, payload: &[u8]) {
+    s.write_all(&(payload.len() as u32).to_le_bytes()).await.unwrap();
+    s.write_all(payload).await.unwrap();
+}
+
+/// The next non-keepalive frame, or `None` once the server closed the
+/// connection.
+async fn read_frame(s: &mut TcpStream) -> Option<Vec<u8>> {
+    let read = async {
+        loop {
+            let mut len = [0u8; 4];
+            s.read_exact(&mut len).await.ok()?;
+            let mut buf = vec![0; u32::from_le_bytes(len) as usize];
+            s.read_exact(&mut buf).await.ok()?;
+            if !buf.is_empty() {
+                return Some(buf);
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), read).await.unwrap()
+}
+
+async fn connect(addr: SocketAddr, auth: Value) -> (TcpStream, Value) {
+    let mut s = TcpStream::connect(addr).await.unwrap();
+    write_frame(&mut s, auth.to_string().as_bytes()).await;
+    let ack = read_frame(&mut s).await.expect("auth accepted");
+    (s, serde_json::from_slice(&ack).unwrap())
+}
+
+fn hot(payload: &[u8]) -> Vec<u8> {
+    [&[1, 1, 1, 0][..], payload].concat()
+}
+
+#[tokio::test]
+async fn frames_reach_hot_services_and_replies_come_back() {
+    let (state, addr) = start(CFG).await;
+    let (mut s, ack) = connect(addr, json!({ "api_key": KEY })).await;
+    assert_eq!(ack["tenant"], "acme");
+    assert!(ack["connection_id"].is_string());
+
+    write_frame(&mut s, &[]).await; // keepalive: ignored
+    write_frame(&mut s, &hot(b"ping")).await;
+    assert_eq!(read_frame(&mut s).await.unwrap(), b"ping");
+
+    let metrics = state.metrics();
+    assert_eq!(metrics.bytes_in.get(&[("tenant", "acme"), ("lane", "hot")]), 8);
+    assert_eq!(metrics.bytes_out.get(&[("tenant", "acme"), ("lane", "hot")]), 4);
+    let text = metrics.render(&[]);
+    assert!(text.contains(r#"wsprism_ws_sessions_active{kind="backend",tenant="acme"} 1"#), "{text}");
+
+    drop(s);
+    for _ in 0..100 {
+        if state.realtime().sessions.len_sessions() == 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("connection not unregistered");
+}
+
+#[tokio::test]
+async fn backend_publishes_to_its_active_room() {
+    let (state, addr) = start(CFG).await;
+    let mut ws = common::connect(serve(&state).await, "tenant=acme&ticket=dev").await;
+    ws.send(Message::Text(json!({ "svc": "room", "type": "join", "room": "match:1" }).to_string())).await.unwrap();
+    while !next_text(&mut ws).await.contains("joined") {}
+
+    let (mut s, _) = connect(addr, json!({ "api_key": KEY, "room": "match:1" })).await;
+    write_frame(&mut s, &hot(b"tick")).await;
+    assert_eq!(next_binary(&mut ws).await, b"tick");
+}
+
+#[tokio::test]
+async fn bad_auth_and_oversized_frames_close_the_connection() {
+    let (state, addr) = start(CFG).await;
+
+    let mut s = TcpStream::connect(addr).await.unwrap();
+    write_frame(&mut s, json!({ "api_key": "wrong-key-0123456789ab" }).to_string().as_bytes()).await;
+    assert!(read_frame(&mut s).await.is_none());
+
+    let mut s = TcpStream::connect(addr).await.unwrap();
+    write_frame(&mut s, b"not json").await;
+    assert!(read_frame(&mut s).await.is_none());
+
+    // No auth frame within `auth_timeout_ms`.
+    let mut s = TcpStream::connect(addr).await.unwrap();
+    assert!(read_frame(&mut s).await.is_none());
+
+    let text = state.metrics().render(&[]);
+    assert!(text.contains(r#"wsprism_handshake_rejections_total{reason="hot_tcp_auth",tenant="_unknown"} 2"#), "{text}");
+    assert!(text.contains(r#"wsprism_handshake_rejections_total{reason="hot_tcp_auth_timeout",tenant="_unknown"} 1"#), "{text}");
+
+    let (mut s, _) = connect(addr, json!({ "api_key": KEY })).await;
+    write_frame(&mut s, &hot(&[0; 300])).await;
+    assert!(read_frame(&mut s).await.is_none());
+    assert_eq!(state.metrics().decode_errors.get(&[("tenant", "acme"), ("reason", "hot")]), 1);
+}
+
+#[tokio::test]
+async fn listener_is_disabled_without_config() {
+    let state = AppState::new(config::load_from_str("version: 1\ntenants:\n  - id: acme\n").unwrap()).unwrap();
+    assert!(hot_tcp::spawn(&state).await.unwrap().is_none());
+}
+
+#[test]
+fn hot_tcp_config_is_validated() {
+    let err = |yaml: &str| config::load_from_str(yaml).unwrap_err().to_string();
+    let e = err("version: 1\nhot_tcp: { listen: \"nope\" }\ntenants:\n  - id: acme\n    api_key: \"backend-key-0123456789\"\n");
+    assert!(e.contains("hot_tcp.listen"), "{e}");
+    let e = err("version: 1\nhot_tcp: { listen: \"127.0.0.1:7001\" }\ntenants:\n  - id: acme\n");
+    assert!(e.contains("hot_tcp requires a tenant with an api_key"), "{e}");
+    let e = err("version: 1\nhot_tcp: { listen: \"127.0.0.1:7001\", auth_timeout_ms: 10 }\ntenants:\n  - id: acme\n    api_key: \"backend-key-0123456789\"\n");
+    assert!(e.contains("hot_tcp.auth_timeout_ms"), "{e}");
+}
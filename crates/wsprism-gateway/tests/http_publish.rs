@@ -0,0 +1,203 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use axum::body::Body;
+use axum::extract::ws;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::config::{self, TenantLimits};
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::RealtimeCore;
+use wsprism_gateway::router::build_router;
+
+use common::{next_binary, next_json, spawn_gateway};
+
+const KEY: &str = "acme-backend-key-0001";
+
+const CFG: &str = r#"
+version: 1
+gateway:
+  publish:
+    rate_limit_rps: 1
+    rate_limit_burst: 3
+tenants:
+  - id: "acme"
+    api_key: "acme-backend-key-0001"
+    auto_join_rooms: ["orders"]
+    limits:
+      max_frame_bytes: 64
+  - id: "beta"
+"#;
+
+fn publish(key: &str, body: Value) -> Request<Body> {
+    Request::post("/v1/publish")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn send_to_user(key: &str, user: &str, body: Value) -> Request<Body> {
+    Request::post(format!("/v1/users/{user}/send"))
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+async fn call(app: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), 1 << 16).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Register a fake session for `user` in `room`.
+fn connect(core: &RealtimeCore, user: &str, room: &str) -> mpsc::Receiver<ws::Message> {
+    let (tx, rx) = mpsc::channel(8);
+    let id = ConnectionId::new();
+    let user_key = format!("acme::{user}");
+    core.sessions
+        .try_insert("acme".into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    core.presence
+        .try_join("acme", &format!("acme::{room}"), &user_key, id, &TenantLimits::default())
+        .unwrap();
+    rx
+}
+
+#[tokio::test]
+async fn ws_client_receives_http_publish() {
+    let (state, addr) = spawn_gateway(CFG).await;
+    let app = build_router(state);
+    let mut client = common::connect(addr, "tenant=acme&ticket=dev").await;
+    assert_eq!(next_json(&mut client).await["type"], "authed");
+    assert_eq!(next_json(&mut client).await["type"], "joined");
+
+    let body = json!({ "tenant": "acme", "room": "orders", "qos": "reliable",
+        "payload": { "json": { "type": "order_updated", "id": 7 } } });
+    let (status, report) = call(&app, publish(KEY, body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report, json!({ "targets": 1, "dropped": 0 }));
+    assert_eq!(next_json(&mut client).await, json!({ "type": "order_updated", "id": 7 }));
+
+    let body = json!({ "tenant": "acme", "room": "orders", "payload": { "binary_b64": "AQID" } });
+    assert_eq!(call(&app, publish(KEY, body)).await.0, StatusCode::OK);
+    assert_eq!(next_binary(&mut client).await, [1, 2, 3]);
+
+    // Direct send: the key alone selects the tenant.
+    let req = send_to_user(KEY, "user:dev", json!({ "qos": "reliable", "payload": { "json": { "type": "export_ready" } } }));
+    let (status, body) = call(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((body["targets"].as_u64(), body["dropped"].as_u64()), (Some(1), Some(0)));
+    assert_eq!(body["sessions"][0]["delivered"], true);
+    assert!(body["sessions"][0]["connection_id"].is_string());
+    assert_eq!(next_json(&mut client).await, json!({ "type": "export_ready" }));
+}
+
+#[tokio::test]
+async fn publish_skips_excluded_user() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let mut alice = connect(&state.realtime(), "alice", "match:1");
+    let mut bob = connect(&state.realtime(), "bob", "match:1");
+    let app = build_router(state);
+
+    let body = json!({ "tenant": "acme", "room": "match:1",
+        "payload": { "json": { "type": "result" } }, "exclude_user": "alice" });
+    let (status, report) = call(&app, publish(KEY, body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report, json!({ "targets": 1, "dropped": 0 }));
+    assert!(matches!(bob.recv().await, Some(ws::Message::Text(_))));
+    assert!(alice.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn publish_is_authorized_bounded_and_rate_limited() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let app = build_router(state);
+    let body = |tenant: &str, payload: Value| json!({ "tenant": tenant, "room": "orders", "payload": payload });
+    let small = json!({ "json": { "n": 1 } });
+
+    assert_eq!(call(&app, publish("wrong-key-wrong-key", body("acme", small.clone()))).await.0, StatusCode::UNAUTHORIZED);
+    // Tenants without an api_key cannot publish at all.
+    assert_eq!(call(&app, publish(KEY, body("beta", small.clone()))).await.0, StatusCode::UNAUTHORIZED);
+
+    let big = json!({ "json": { "pad": "x".repeat(64) } });
+    let (status, err) = call(&app, publish(KEY, body("acme", big))).await;
+    assert_eq!((status, err["error"].as_str()), (StatusCode::PAYLOAD_TOO_LARGE, Some("payload_too_large")));
+    let (status, err) = call(&app, publish(KEY, body("acme", json!({ "binary_b64": "!!" })))).await;
+    assert_eq!((status, err["error"].as_str()), (StatusCode::BAD_REQUEST, Some("invalid_base64")));
+
+    // Burst of 3, two already spent above.
+    assert_eq!(call(&app, publish(KEY, body("acme", small.clone()))).await.0, StatusCode::OK);
+    let resp = app.clone().oneshot(publish(KEY, body("acme", small))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers()["retry-after"], "1");
+}
+
+#[tokio::test]
+async fn publish_is_charged_to_the_room_rate_limit() {
+    let cfg = r#"
+version: 1
+tenants:
+  - id: "acme"
+    api_key: "acme-backend-key-0001"
+    policy:
+      room_rate_limit_rps: 1
+      room_rate_limit_burst: 2
+"#;
+    let app = build_router(AppState::new(config::load_from_str(cfg).unwrap()).unwrap());
+    let body = |room: &str| json!({ "tenant": "acme", "room": room, "payload": { "json": { "n": 1 } } });
+    for _ in 0..2 {
+        assert_eq!(call(&app, publish(KEY, body("orders"))).await.0, StatusCode::OK);
+    }
+    let resp = app.clone().oneshot(publish(KEY, body("orders"))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers()["retry-after"], "1");
+    assert_eq!(call(&app, publish(KEY, body("other"))).await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn send_to_user_reports_offline_users_and_bad_keys() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let mut alice = connect(&state.realtime(), "alice", "lobby");
+    let app = build_router(state);
+    let body = json!({ "payload": { "json": { "type": "ping" } } });
+
+    let (status, resp) = call(&app, send_to_user(KEY, "alice", body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((resp["targets"].as_u64(), resp["dropped"].as_u64()), (Some(1), Some(0)));
+    assert!(matches!(alice.recv().await, Some(ws::Message::Text(_))));
+
+    let (status, resp) = call(&app, send_to_user(KEY, "carol", body.clone())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(resp, json!({ "error": "user_offline", "queued": false }));
+
+    assert_eq!(call(&app, send_to_user("wrong-key-wrong-key", "alice", body.clone())).await.0, StatusCode::UNAUTHORIZED);
+    let big = json!({ "payload": { "json": { "pad": "x".repeat(64) } } });
+    assert_eq!(call(&app, send_to_user(KEY, "alice", big)).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+    let bad_qos = json!({ "qos": "eventual", "payload": { "json": {} } });
+    assert_eq!(call(&app, send_to_user(KEY, "alice", bad_qos)).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[test]
+fn api_keys_are_unique_across_tenants() {
+    let key = "    api_key: \"shared-key-0123456789\"\n";
+    let err = config::load_from_str(&format!("version: 1\ntenants:\n  - id: a\n{key}  - id: b\n{key}")).unwrap_err();
+    assert!(err.to_string().contains("used by another tenant"), "{err}");
+}
+
+#[test]
+fn api_key_must_be_long_enough() {
+    let err = config::load_from_str("version: 1\ntenants:\n  - id: acme\n    api_key: short\n").unwrap_err();
+    assert!(err.to_string().contains("api_key"), "{err}");
+}
@@ -0,0 +1,269 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::extract::ws::Message;
+use bytes::Bytes;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use wsprism_core::error::Result;
+use wsprism_core::protocol::text::Envelope;
+
+use wsprism_gateway::config::{self, HttpUpstreamConfig, TenantConfig, TenantLimits};
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::dispatch::TextService;
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{RealtimeCore, RealtimeCtx};
+use wsprism_gateway::upstream::http::{parse_instructions, InstructionTarget, TENANT_HEADER, USER_HEADER};
+use wsprism_gateway::upstream::{HttpUpstreamClient, HttpUpstreamService};
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: acme
+    policy:
+      ext_allowlist: ["orders:*"]
+    http_upstreams:
+      orders:
+        url: "http://127.0.0.1:9/orders"
+        max_attempts: 3
+"#;
+
+type Sent = (String, Vec<(&'static str, String)>, Bytes);
+
+/// Answers from `responses` in order, then 200 with no body.
+#[derive(Default)]
+struct FakeClient {
+    sent: Mutex<Vec<Sent>>,
+    responses: Mutex<VecDeque<(u16, &'static str)>>,
+}
+
+impl FakeClient {
+    fn answering(responses: &[(u16, &'static str)]) -> Arc<Self> {
+        Arc::new(Self { responses: Mutex::new(responses.iter().copied().collect()), ..Self::default() })
+    }
+
+    fn calls(&self) -> usize {
+        self.sent.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl HttpUpstreamClient for FakeClient {
+    async fn post(&self, cfg: &HttpUpstreamConfig, headers: &[(&'static str, String)], body: Bytes) -> Result<(u16, Bytes)> {
+        self.sent.lock().unwrap().push((cfg.url.clone(), headers.to_vec(), body));
+        let (status, body) = self.responses.lock().unwrap().pop_front().unwrap_or((200, ""));
+        Ok((status, Bytes::from_static(body.as_bytes())))
+    }
+}
+
+fn tenants() -> Vec<TenantConfig> {
+    config::load_from_str(CFG).unwrap().tenants
+}
+
+fn connect(core: &RealtimeCore, user: &str) -> (ConnectionId, mpsc::Receiver<Message>) {
+    let (tx, rx) = mpsc::channel(16);
+    let id = ConnectionId::new();
+    let user_key = format!("acme::{user}");
+    core.sessions
+        .try_insert("acme".into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    core.presence.try_join("acme", "acme::lobby", &user_key, id, &TenantLimits::default()).unwrap();
+    (id, rx)
+}
+
+fn env(flags: u32) -> Envelope {
+    serde_json::from_value(json!({ "v": 1, "svc": "orders", "type": "place", "flags": flags, "data": { "sku": 7 } })).unwrap()
+}
+
+fn texts(rx: &mut mpsc::Receiver<Message>) -> Vec<Value> {
+    let mut out = Vec::new();
+    while let Ok(m) = rx.try_recv() {
+        if let Message::Text(t) = m {
+            out.push(serde_json::from_str(&t).unwrap());
+        }
+    }
+    out
+}
+
+#[test]
+fn instructions_are_parsed_and_checked() {
+    assert!(parse_instructions(b"").unwrap().is_empty());
+    assert!(parse_instructions(b" \n").unwrap().is_empty());
+    let ins = parse_instructions(br#"[{"to":"room","room":"lobby","qos":"lossy","payload":{"a":1}}]"#).unwrap();
+    assert_eq!(ins[0].to, InstructionTarget::Room);
+    assert!(parse_instructions(br#"[{"to":"user","payload":1}]"#).is_err());
+    assert!(parse_instructions(br#"[{"to":"everyone","payload":1}]"#).is_err());
+    assert!(parse_instructions(br#"{"to":"reply","payload":1}"#).is_err());
+}
+
+#[tokio::test]
+async fn instructions_reach_session_user_and_room() {
+    let client = FakeClient::answering(&[(
+        200,
+        r#"[{"to":"reply","payload":{"r":1}},
+            {"to":"user","user":"bob","payload":{"u":1}},
+            {"to":"room","room":"lobby","qos":"lossy","payload":{"m":1}}]"#,
+    )]);
+    let services = HttpUpstreamService::all(&tenants(), client.clone());
+    assert_eq!(services.len(), 1);
+    let svc = &services[0];
+    assert_eq!(svc.svc(), "orders");
+
+    let metrics = Arc::new(GatewayMetrics::default());
+    let core = Arc::new(RealtimeCore::new().with_metrics(metrics.clone()));
+    let (alice_id, mut alice) = connect(&core, "alice");
+    let (_, mut bob) = connect(&core, "bob");
+    let ctx = RealtimeCtx::new("acme", "alice", alice_id, "trace-1", None, core.clone());
+    assert!(svc.handle(ctx, env(0)).await.unwrap().is_none());
+
+    assert_eq!(texts(&mut alice), vec![json!({ "r": 1 }), json!({ "m": 1 })]);
+    assert_eq!(texts(&mut bob), vec![json!({ "u": 1 }), json!({ "m": 1 })]);
+
+    let sent = client.sent.lock().unwrap();
+    let (url, headers, body) = &sent[0];
+    assert_eq!(url, "http://127.0.0.1:9/orders");
+    assert!(headers.contains(&(TENANT_HEADER, "acme".to_string())));
+    assert!(headers.contains(&(USER_HEADER, "alice".to_string())));
+    let body: Value = serde_json::from_slice(body).unwrap();
+    assert_eq!(body["data"]["sku"], 7);
+
+    let text = metrics.render(&[]);
+    assert!(text.contains(r#"wsprism_upstream_duration_seconds_count{svc="orders",tenant="acme"} 1"#), "{text}");
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_only_for_want_ack_messages() {
+    let ctx = || RealtimeCtx::new("acme", "alice", ConnectionId::new(), "t", None, Arc::new(RealtimeCore::new()));
+
+    let client = FakeClient::answering(&[(503, ""), (502, ""), (200, "[]")]);
+    let svc = HttpUpstreamService::new("orders", &tenants(), client.clone());
+    svc.handle(ctx(), env(1)).await.unwrap();
+    assert_eq!(client.calls(), 3);
+
+    let client = FakeClient::answering(&[(503, ""), (503, ""), (503, ""), (200, "[]")]);
+    let svc = HttpUpstreamService::new("orders", &tenants(), client.clone());
+    let e = svc.handle(ctx(), env(1)).await.unwrap_err();
+    assert_eq!(e.client_code().as_str(), "INTERNAL");
+    assert!(e.to_string().contains("HTTP 503"), "{e}");
+    assert_eq!(client.calls(), 3, "max_attempts");
+
+    let client = FakeClient::answering(&[(503, ""), (200, "[]")]);
+    let svc = HttpUpstreamService::new("orders", &tenants(), client.clone());
+    assert!(svc.handle(ctx(), env(0)).await.is_err());
+    assert_eq!(client.calls(), 1);
+
+    // Client errors are never retried.
+    let client = FakeClient::answering(&[(400, ""), (200, "[]")]);
+    let svc = HttpUpstreamService::new("orders", &tenants(), client.clone());
+    assert!(svc.handle(ctx(), env(1)).await.is_err());
+    assert_eq!(client.calls(), 1);
+}
+
+#[tokio::test]
+async fn bad_responses_deliver_nothing() {
+    let core = Arc::new(RealtimeCore::new());
+    let (id, mut rx) = connect(&core, "alice");
+    let client = FakeClient::answering(&[(200, r#"[{"to":"reply","payload":1},{"to":"room","payload":2}]"#)]);
+    let svc = HttpUpstreamService::new("orders", &tenants(), client);
+    let ctx = RealtimeCtx::new("acme", "alice", id, "t", None, core.clone());
+    assert!(svc.handle(ctx, env(0)).await.is_err());
+    assert!(texts(&mut rx).is_empty());
+}
+
+#[test]
+fn http_upstream_config_is_validated() {
+    let err = |tenant: &str| {
+        let yaml = format!("version: 1\ntenants:\n  - id: acme\n{tenant}");
+        config::load_from_str(&yaml).unwrap_err().to_string()
+    };
+    let e = err("    http_upstreams:\n      orders: { url: \"ftp://x\" }\n");
+    assert!(e.contains("http_upstreams.orders.url"), "{e}");
+    let e = err("    http_upstreams:\n      orders: { url: \"http://x\", max_response_bytes: 0 }\n");
+    assert!(e.contains("http_upstreams.orders.max_response_bytes"), "{e}");
+    let e = err("    http_upstreams:\n      orders: { url: \"http://x\", max_attempts: 0 }\n");
+    assert!(e.contains("http_upstreams.orders.max_attempts"), "{e}");
+    let e = err(
+        "    upstreams:\n      orders: { endpoint: \"http://x:1\" }\n    http_upstreams:\n      orders: { url: \"http://x\" }\n",
+    );
+    assert!(e.contains("both upstreams and http_upstreams"), "{e}");
+}
+
+#[cfg(not(feature = "upstream-http"))]
+#[test]
+fn startup_fails_without_the_feature() {
+    let err = wsprism_gateway::app_state::AppState::new(config::load_from_str(CFG).unwrap()).err().unwrap();
+    assert!(err.to_string().contains("built without the upstream-http feature"), "{err}");
+}
+
+#[cfg(feature = "upstream-http")]
+mod mock_server {
+    use std::net::SocketAddr;
+
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use axum::Router;
+    use futures_util::SinkExt;
+    use serde_json::{json, Value};
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::common::{connect, next_json, spawn_gateway};
+
+    async fn orders(headers: HeaderMap, body: String) -> String {
+        let env: Value = serde_json::from_str(&body).unwrap();
+        if env["type"] == "huge" {
+            return "x".repeat(4096);
+        }
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+        json!([{ "to": "reply", "payload": {
+            "svc": "orders", "type": "placed",
+            "data": { "tenant": header("x-wsprism-tenant"), "user": header("x-wsprism-user"), "sku": env["data"]["sku"] },
+        }}])
+        .to_string()
+    }
+
+    async fn start() -> SocketAddr {
+        let backend = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/orders", backend.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(backend, Router::new().route("/orders", post(orders))).await.unwrap() });
+
+        let yaml = format!(
+            "version: 1\ntenants:\n  - id: acme\n    policy:\n      ext_allowlist: [\"orders:*\"]\n    http_upstreams:\n      orders: {{ url: \"{url}\", max_response_bytes: 1024 }}\n"
+        );
+        spawn_gateway(&yaml).await.1
+    }
+
+    async fn ask(addr: SocketAddr, msg_type: &str) -> Value {
+        let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+        ws.send(Message::Text(json!({ "svc": "orders", "type": msg_type, "data": { "sku": 7 } }).to_string()))
+            .await
+            .unwrap();
+        loop {
+            let v = next_json(&mut ws).await;
+            if v["svc"] != "sys" || v["type"] == "error" {
+                return v;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_round_trip_through_an_http_backend() {
+        let addr = start().await;
+        let v = ask(addr, "place").await;
+        assert_eq!(v["type"], "placed", "{v}");
+        assert_eq!(v["data"]["tenant"], "acme");
+        assert_eq!(v["data"]["user"], "user:dev");
+        assert_eq!(v["data"]["sku"], 7);
+
+        let v = ask(addr, "huge").await;
+        assert_eq!(v["type"], "error", "{v}");
+        assert_eq!(v["data"]["code"], "INTERNAL", "{v}");
+    }
+}
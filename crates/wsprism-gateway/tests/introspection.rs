@@ -0,0 +1,167 @@
+//! Introspection verifier against a local mock IdP.
+
+#![cfg(feature = "oidc-introspection")]
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, routing::post, Form, Json, Router};
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::auth::IntrospectionVerifier;
+use wsprism_gateway::config;
+use wsprism_gateway::config::schema::IntrospectionConfig;
+
+use common::{connect, next_json, serve};
+
+async fn introspect(
+    State(hits): State<Arc<AtomicUsize>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Result<Json<Value>, StatusCode> {
+    hits.fetch_add(1, Ordering::Relaxed);
+    match form.get("token").map(String::as_str) {
+        Some("good") => Ok(Json(json!({ "active": true, "sub": "alice", "scope": "chat room" }))),
+        Some("reader") => Ok(Json(json!({ "active": true, "sub": "bob", "scope": "room:join" }))),
+        Some("broken") => Err(StatusCode::BAD_GATEWAY),
+        Some("misconfigured") => Err(StatusCode::UNAUTHORIZED),
+        _ => Ok(Json(json!({ "active": false }))),
+    }
+}
+
+async fn mock_idp() -> (String, Arc<AtomicUsize>) {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let app = Router::new()
+        .route("/introspect", post(introspect))
+        .with_state(hits.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/introspect"), hits)
+}
+
+fn verifier(url: String) -> IntrospectionVerifier {
+    IntrospectionVerifier::new(IntrospectionConfig {
+        url,
+        client_id: "gw".into(),
+        client_secret: "secret".into(),
+        user_claim: "sub".into(),
+        cache_ttl_ms: 60_000,
+        negative_cache_ttl_ms: 60_000,
+        timeout_ms: 1_000,
+        max_cache_entries: 100,
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn active_token_resolves_and_is_cached() {
+    let (url, hits) = mock_idp().await;
+    let v = verifier(url);
+
+    let (user, claims) = v.verify("good").await.unwrap();
+    assert_eq!(user, "alice");
+    assert_eq!(claims.get("scopes").unwrap(), &json!(["chat", "room"]));
+
+    v.verify("good").await.unwrap();
+    assert_eq!(hits.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn inactive_token_is_auth_failed_and_negatively_cached() {
+    let (url, hits) = mock_idp().await;
+    let v = verifier(url);
+
+    for _ in 0..2 {
+        let err = v.verify("revoked").await.expect_err("inactive");
+        assert_eq!(err.client_code().as_str(), "AUTH_FAILED");
+    }
+    assert_eq!(hits.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn idp_failure_retries_once_then_internal() {
+    let (url, hits) = mock_idp().await;
+    let v = verifier(url);
+
+    let err = v.verify("broken").await.expect_err("idp down");
+    assert_eq!(err.client_code().as_str(), "INTERNAL");
+    assert_eq!(hits.load(Ordering::Relaxed), 2);
+}
+
+#[tokio::test]
+async fn idp_rejection_is_auth_failed_without_retry() {
+    let (url, hits) = mock_idp().await;
+    let v = verifier(url);
+
+    let err = v.verify("misconfigured").await.expect_err("client credentials rejected");
+    assert_eq!(err.client_code().as_str(), "AUTH_FAILED");
+    assert_eq!(hits.load(Ordering::Relaxed), 1);
+}
+
+fn gateway_cfg(url: &str) -> config::GatewayConfig {
+    config::load_from_str(&format!(
+        "version: 1\ntenants:\n  - id: acme\n    introspection: {{ url: \"{url}\", client_id: gw, client_secret: secret, timeout_ms: 500 }}\n"
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn async_startup_probes_the_idp() {
+    let (url, hits) = mock_idp().await;
+    AppState::from_config_async(gateway_cfg(&url)).await.unwrap();
+    assert_eq!(hits.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn async_startup_reports_rejected_credentials_as_config_error() {
+    let (url, _) = mock_idp().await;
+    let err = AppState::from_config_async(gateway_cfg(&url.replace("/introspect", "/nope"))).await.err().expect("404");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+    assert!(err.to_string().contains("tenant acme: introspection endpoint rejected the request"), "{err}");
+}
+
+#[tokio::test]
+async fn async_startup_fails_fast_when_idp_is_down() {
+    // Bind and drop: nothing listens on the port afterwards.
+    let addr = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+    let url = format!("http://{addr}/introspect");
+
+    // The synchronous constructor does not contact the IdP.
+    assert!(AppState::new(gateway_cfg(&url)).is_ok());
+
+    let started = std::time::Instant::now();
+    let err = AppState::from_config_async(gateway_cfg(&url)).await.err().expect("idp is down");
+    assert!(err.to_string().contains("tenant acme: introspection backend unavailable"), "{err}");
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn token_scopes_restrict_ext_frames() {
+    let (url, _) = mock_idp().await;
+    let cfg = format!(
+        "version: 1\ntenants:\n  - id: acme\n    introspection: {{ url: \"{url}\", client_id: gw, client_secret: secret }}\n    policy:\n      strike_limit: 10\n      ext_allowlist: [\"room:*\", \"chat:*\"]\n"
+    );
+    let state = AppState::new(config::load_from_str(&cfg).unwrap()).unwrap();
+    let addr = serve(&state).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=reader").await;
+    assert_eq!(next_json(&mut ws).await["type"], "authed");
+
+    ws.send(Message::Text(r#"{"svc":"room","type":"join","room":"lobby"}"#.into())).await.unwrap();
+    assert_eq!(next_json(&mut ws).await["type"], "joined");
+
+    // Allowed by the tenant policy, but not by the token's `room:join` scope.
+    ws.send(Message::Text(r#"{"svc":"chat","type":"send","room":"lobby","data":{"text":"hi"}}"#.into())).await.unwrap();
+    let reject = next_json(&mut ws).await;
+    assert_eq!(reject["data"]["code"], "AUTH_FAILED", "{reject}");
+    let scope = [("tenant", "acme"), ("lane", "ext"), ("decision", "reject"), ("reason", "scope")];
+    assert_eq!(state.metrics().policy_decisions.get(&scope), 1);
+}
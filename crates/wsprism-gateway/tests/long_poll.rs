@@ -0,0 +1,224 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::config;
+use wsprism_gateway::router::build_router;
+
+use common::{connect, next_json, serve};
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    long_poll: { wait_ms: 1000, session_ttl_ms: 1500 }
+    policy:
+      ext_allowlist: ["room:*", "chat:*"]
+  - id: "beta"
+"#;
+
+fn setup(cfg: &str) -> (AppState, Router) {
+    let state = AppState::new(config::load_from_str(cfg).unwrap()).unwrap();
+    let app = build_router(state.clone());
+    (state, app)
+}
+
+async fn call(app: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), 1 << 16).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn poll(app: &Router, cursor: Option<&str>) -> (StatusCode, Value) {
+    let uri = match cursor {
+        Some(c) => format!("/v1/poll?tenant=acme&ticket=dev&cursor={c}"),
+        None => "/v1/poll?tenant=acme&ticket=dev".to_string(),
+    };
+    call(app, Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+async fn send(app: &Router, cursor: &str, env: &str) -> StatusCode {
+    let req = Request::post(format!("/v1/poll/send?tenant=acme&ticket=dev&cursor={cursor}"))
+        .body(Body::from(env.to_string()))
+        .unwrap();
+    call(app, req).await.0
+}
+
+fn types(batch: &Value) -> Vec<&str> {
+    batch["messages"].as_array().unwrap().iter().map(|m| m["type"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn poll_session_joins_rooms_and_receives_fanout() {
+    let (state, app) = setup(CFG);
+    let (status, first) = poll(&app, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(types(&first), ["authed"]);
+    assert_eq!(first["messages"][0]["data"]["user"], "user:dev");
+    let cursor = first["cursor"].as_str().unwrap().to_string();
+    assert_eq!(state.polls().len(), 1);
+
+    let join = r#"{"v":1,"svc":"room","type":"join","room":"lobby"}"#;
+    assert_eq!(send(&app, &cursor, join).await, StatusCode::ACCEPTED);
+    let (_, batch) = poll(&app, Some(&cursor)).await;
+    assert_eq!(types(&batch), ["joined"]);
+    assert_eq!(state.realtime().presence.users_in("acme::lobby"), ["acme::user:dev"]);
+    let cursor = batch["cursor"].as_str().unwrap().to_string();
+
+    // A WebSocket member of the room sees the poll session's chat message.
+    let addr = serve(&state).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert_eq!(next_json(&mut ws).await["type"], "authed");
+    ws.send(Message::Text(join.into())).await.unwrap();
+    assert_eq!(next_json(&mut ws).await["type"], "joined");
+
+    let chat = r#"{"v":1,"svc":"chat","type":"send","room":"lobby","data":{"msg":"hi"}}"#;
+    assert_eq!(send(&app, &cursor, chat).await, StatusCode::ACCEPTED);
+    assert_eq!(next_json(&mut ws).await["data"]["msg"], "hi");
+
+    // ... and, as a member, receives its own fan-out on the next poll.
+    let (_, batch) = poll(&app, Some(&cursor)).await;
+    assert_eq!(types(&batch), ["msg"]);
+    assert_eq!(batch["messages"][0]["data"]["msg"], "hi");
+}
+
+#[tokio::test]
+async fn unacknowledged_messages_are_returned_again() {
+    let (_, app) = setup(CFG);
+    let (_, first) = poll(&app, None).await;
+    let opened = first["cursor"].as_str().unwrap().to_string();
+    let (session, _) = opened.split_once('.').unwrap();
+
+    // Repeating the opening cursor (seq 0) returns sys.authed again.
+    let (_, again) = poll(&app, Some(&format!("{session}.0"))).await;
+    assert_eq!(types(&again), ["authed"]);
+    assert_eq!(again["cursor"], opened);
+
+    // Acknowledged: the poll waits out wait_ms and returns nothing.
+    let start = std::time::Instant::now();
+    let (status, empty) = poll(&app, Some(&opened)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(start.elapsed() >= Duration::from_millis(900));
+    assert_eq!(empty, json!({ "cursor": opened, "messages": [] }));
+}
+
+#[tokio::test]
+async fn idle_sessions_expire_and_leave_their_rooms() {
+    let (state, app) = setup(CFG);
+    let (_, first) = poll(&app, None).await;
+    let cursor = first["cursor"].as_str().unwrap().to_string();
+    send(&app, &cursor, r#"{"v":1,"svc":"room","type":"join","room":"lobby"}"#).await;
+    assert_eq!(state.realtime().presence.users_in("acme::lobby").len(), 1);
+
+    // A waiting poll keeps the session alive past the TTL.
+    let (_, batch) = poll(&app, Some(&cursor)).await;
+    let cursor = batch["cursor"].as_str().unwrap().to_string();
+    poll(&app, Some(&cursor)).await;
+    assert_eq!(state.realtime().presence.users_in("acme::lobby").len(), 1);
+
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert!(state.realtime().presence.users_in("acme::lobby").is_empty());
+    assert_eq!(state.realtime().sessions.count_tenant_sessions("acme"), 0);
+    let (status, body) = poll(&app, Some(&cursor)).await;
+    assert_eq!(status, StatusCode::GONE);
+    assert_eq!(body, json!({ "error": "session_expired" }));
+    assert!(state.polls().is_empty());
+}
+
+#[tokio::test]
+async fn sends_go_through_tenant_policy() {
+    let (_, app) = setup(CFG);
+    let (_, first) = poll(&app, None).await;
+    let cursor = first["cursor"].as_str().unwrap().to_string();
+
+    // Not in ext_allowlist.
+    let denied = r#"{"v":1,"svc":"orders","type":"create"}"#;
+    assert_eq!(send(&app, &cursor, denied).await, StatusCode::ACCEPTED);
+    let (_, batch) = poll(&app, Some(&cursor)).await;
+    assert_eq!(types(&batch), ["error"]);
+    assert_eq!(batch["messages"][0]["data"]["code"], "BAD_REQUEST");
+    let cursor = batch["cursor"].as_str().unwrap().to_string();
+
+    // Malformed JSON ends the session, as it closes a WebSocket.
+    assert_eq!(send(&app, &cursor, "{nope").await, StatusCode::ACCEPTED);
+    let (_, batch) = poll(&app, Some(&cursor)).await;
+    assert_eq!(types(&batch), ["error"]);
+    assert!(batch["closed"].is_string(), "{batch}");
+    assert_eq!(poll(&app, Some(&cursor)).await.0, StatusCode::GONE);
+    assert_eq!(send(&app, &cursor, denied).await, StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn kicked_poll_session_reports_closed() {
+    let cfg = r#"
+version: 1
+tenants:
+  - id: "acme"
+    long_poll: {}
+    policy:
+      sessions: { max_sessions_per_user: 1, on_exceed: kick_oldest }
+"#;
+    let (state, app) = setup(cfg);
+    let (_, first) = poll(&app, None).await;
+    let cursor = first["cursor"].as_str().unwrap().to_string();
+    let (_, second) = poll(&app, None).await;
+    assert_eq!(types(&second), ["authed"]);
+
+    let (_, batch) = poll(&app, Some(&cursor)).await;
+    assert_eq!(types(&batch), ["kicked"]);
+    assert_eq!(batch["closed"], "kicked");
+    assert_eq!(state.realtime().sessions.count_tenant_sessions("acme"), 1);
+}
+
+#[tokio::test]
+async fn poll_requests_are_checked() {
+    let (_, app) = setup(CFG);
+    let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+    let (status, body) = call(&app, get("/v1/poll?tenant=beta&ticket=dev")).await;
+    assert_eq!((status, body), (StatusCode::NOT_FOUND, json!({ "error": "long_poll_disabled" })));
+    let (status, _) = call(&app, get("/v1/poll?tenant=nope&ticket=dev")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = call(&app, get("/v1/poll?tenant=acme")).await;
+    assert_eq!((status, body), (StatusCode::UNAUTHORIZED, json!({ "error": "auth_failed" })));
+    let (status, body) = call(&app, get("/v1/poll?tenant=acme&ticket=dev&cursor=junk")).await;
+    assert_eq!((status, body), (StatusCode::BAD_REQUEST, json!({ "error": "invalid_cursor" })));
+    let (status, _) = call(&app, get("/v1/poll?tenant=acme&ticket=dev&cursor=gone.1")).await;
+    assert_eq!(status, StatusCode::GONE);
+
+    // A live cursor still needs the session's credentials.
+    let (_, first) = poll(&app, None).await;
+    let cursor = first["cursor"].as_str().unwrap();
+    let (status, _) = call(&app, get(&format!("/v1/poll?tenant=acme&ticket=stolen&cursor={cursor}"))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn long_poll_config_is_validated() {
+    let with = |lp: &str| config::load_from_str(&format!("version: 1\ntenants:\n  - id: acme\n    long_poll: {lp}\n"));
+    let lp = &with("{}").unwrap().tenants[0].long_poll.clone().unwrap();
+    assert_eq!((lp.wait_ms, lp.session_ttl_ms, lp.max_buffered), (25_000, 60_000, 1024));
+    for (bad, msg) in [
+        ("{ wait_ms: 500 }", "long_poll.wait_ms must be between 1000 and 60000"),
+        ("{ wait_ms: 30000, session_ttl_ms: 30000 }", "long_poll.session_ttl_ms must be greater than wait_ms and at most 600000"),
+        ("{ session_ttl_ms: 700000 }", "long_poll.session_ttl_ms must be greater than wait_ms and at most 600000"),
+        ("{ max_buffered: 0 }", "long_poll.max_buffered must be between 1 and 65536"),
+    ] {
+        let err = with(bad).unwrap_err().to_string();
+        assert!(err.contains(msg), "{bad}: {err}");
+    }
+}
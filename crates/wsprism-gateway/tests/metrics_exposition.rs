@@ -0,0 +1,126 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::io::Read;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use flate2::read::GzDecoder;
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::obs::metrics::to_openmetrics;
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::{config, router};
+
+fn state() -> AppState {
+    let state = AppState::new(config::load_from_str("version: 1\ntenants:\n  - id: acme\n").unwrap()).unwrap();
+    state.metrics().ws_upgrades.inc(&[("tenant", "acme")]);
+    state
+}
+
+/// Status, content type, content encoding and (decompressed) body.
+async fn scrape(state: &AppState, headers: &[(header::HeaderName, &str)]) -> (StatusCode, String, Option<String>, String) {
+    let mut req = Request::get("/metrics");
+    for (k, v) in headers {
+        req = req.header(k, *v);
+    }
+    let resp = router::build_router(state.clone()).oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    let status = resp.status();
+    let h = resp.headers().clone();
+    assert_eq!(h[header::VARY], "Accept, Accept-Encoding");
+    let raw = axum::body::to_bytes(resp.into_body(), 1 << 22).await.unwrap();
+    let encoding = h.get(header::CONTENT_ENCODING).map(|v| v.to_str().unwrap().to_string());
+    let body = if encoding.as_deref() == Some("gzip") {
+        let mut s = String::new();
+        GzDecoder::new(&raw[..]).read_to_string(&mut s).unwrap();
+        s
+    } else {
+        String::from_utf8(raw.to_vec()).unwrap()
+    };
+    (status, h[header::CONTENT_TYPE].to_str().unwrap().to_string(), encoding, body)
+}
+
+#[tokio::test]
+async fn plain_scrape_is_prometheus_text() {
+    let s = state();
+    let (status, ctype, encoding, body) = scrape(&s, &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ctype, "text/plain; version=0.0.4; charset=utf-8");
+    assert_eq!(encoding, None);
+    assert!(body.contains("# TYPE wsprism_ws_upgrades_total counter\n"), "{body}");
+    assert!(!body.contains("# EOF"));
+}
+
+#[tokio::test]
+async fn gzip_is_used_when_accepted() {
+    let s = state();
+    let (_, _, plain_enc, plain) = scrape(&s, &[]).await;
+    assert_eq!(plain_enc, None);
+
+    let (status, ctype, encoding, body) = scrape(&s, &[(header::ACCEPT_ENCODING, "deflate, GZIP;q=0.5")]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ctype, "text/plain; version=0.0.4; charset=utf-8");
+    assert_eq!(encoding.as_deref(), Some("gzip"));
+    assert_eq!(body.lines().find(|l| l.starts_with("wsprism_ws_upgrades_total")), plain.lines().find(|l| l.starts_with("wsprism_ws_upgrades_total")));
+
+    // q=0 refuses the coding.
+    let (_, _, encoding, _) = scrape(&s, &[(header::ACCEPT_ENCODING, "gzip;q=0, identity")]).await;
+    assert_eq!(encoding, None);
+}
+
+#[tokio::test]
+async fn openmetrics_is_negotiated() {
+    let s = state();
+    let accept = "application/openmetrics-text; version=1.0.0,text/plain;version=0.0.4;q=0.5";
+    for gzip in [false, true] {
+        let mut headers = vec![(header::ACCEPT, accept)];
+        if gzip {
+            headers.push((header::ACCEPT_ENCODING, "gzip"));
+        }
+        let (status, ctype, encoding, body) = scrape(&s, &headers).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(ctype, "application/openmetrics-text; version=1.0.0; charset=utf-8");
+        assert_eq!(encoding.is_some(), gzip);
+        assert!(body.ends_with("# EOF\n"), "{body}");
+        assert!(body.contains("# TYPE wsprism_ws_upgrades counter\nwsprism_ws_upgrades_total{tenant=\"acme\"} 1\n"), "{body}");
+        assert!(body.contains("# TYPE wsprism_audit_dropped_total unknown\nwsprism_audit_dropped_total 0\n"), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn scrape_reports_tenant_sessions_and_registered_services() {
+    let s = state();
+    let (_, _, _, body) = scrape(&s, &[]).await;
+    assert!(!body.contains("wsprism_tenant_sessions"), "{body}");
+    assert!(body.contains("wsprism_text_services_registered 2\n"), "{body}");
+    assert!(body.contains("wsprism_hot_services_registered 1\n"), "{body}");
+
+    let mut rxs = Vec::new();
+    for user in ["a", "b"] {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        s.realtime()
+            .sessions
+            .try_insert("acme".into(), format!("acme::{user}"), ConnectionId::new(), Connection::new(tx, SessionClaims::default()), 0)
+            .unwrap();
+        rxs.push(rx);
+    }
+    let (_, _, _, body) = scrape(&s, &[]).await;
+    assert!(body.contains("wsprism_tenant_sessions{tenant=\"acme\"} 2\n"), "{body}");
+}
+
+#[test]
+fn openmetrics_conversion() {
+    let text = "# TYPE a_total counter\na_total{x=\"1\"} 2\n# TYPE legacy counter\nlegacy 1\n\
+                # TYPE h histogram\n# UNIT h seconds\nh_bucket{le=\"+Inf\"} 1\nh_sum 0.5\nh_count 1\nextra_total 3\n";
+    let mut out = String::new();
+    to_openmetrics(text, &mut out);
+    assert_eq!(
+        out,
+        "# TYPE a counter\na_total{x=\"1\"} 2\n# TYPE legacy unknown\nlegacy 1\n\
+         # TYPE h histogram\n# UNIT h seconds\nh_bucket{le=\"+Inf\"} 1\nh_sum 0.5\nh_count 1\n\
+         # TYPE extra_total unknown\nextra_total 3\n# EOF\n"
+    );
+}
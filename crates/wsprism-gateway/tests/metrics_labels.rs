@@ -0,0 +1,163 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::config::schema::MetricsConfig;
+use wsprism_gateway::obs::metrics::{
+    FrameDir, GatewayMetrics, BUILD_GIT_SHA, BUILD_RUSTC, BUILD_VERSION, SUMMARY_WINDOW,
+};
+use wsprism_gateway::config;
+
+use common::{connect, spawn_gateway};
+
+#[test]
+fn labeled_render_matches_golden_file() {
+    let m = GatewayMetrics::with_max_label_values_per_metric(2);
+    m.ws_upgrades.inc(&[("tenant", "beta"), ("status", "ok")]);
+    m.ws_upgrades.inc(&[("tenant", "acme"), ("status", "ok")]);
+    m.ws_upgrades.inc(&[("tenant", "acme"), ("status", "ok")]);
+    m.ws_active_sessions.inc(&[("tenant", "acme"), ("kind", "user")]);
+    m.handshake_rejections.inc(&[("tenant", "_unknown"), ("reason", "unknown_tenant")]);
+    m.handshake_rejections.inc(&[("tenant", "acme"), ("reason", "auth_failed")]);
+    m.decode_errors.inc(&[("tenant", "acme"), ("reason", "json")]);
+    m.decode_errors.inc(&[("tenant", "acme"), ("reason", "hot")]);
+    // Third reason value: past the cap of 2, folded into "_other".
+    m.decode_errors.inc(&[("tenant", "acme"), ("reason", "utf8")]);
+    m.service_errors.inc(&[("tenant", "acme"), ("lane", "ext"), ("svc", "chat"), ("code", "BAD_REQUEST")]);
+    m.unknown_service_errors.inc(&[("tenant", "acme"), ("lane", "hot"), ("svc", "9")]);
+    m.writer_timeouts.inc(&[("tenant", "acme")]);
+    m.observe_dispatch(&[("tenant", "acme"), ("lane", "ext")], Duration::from_micros(1_500));
+    m.observe_dispatch(&[("tenant", "acme"), ("lane", "ext")], Duration::from_secs(3));
+
+    let rendered = m.render(&[]);
+    let golden = include_str!("golden/metrics_labeled.prom")
+        .replace("{GIT_SHA}", BUILD_GIT_SHA)
+        .replace("{RUSTC}", BUILD_RUSTC)
+        .replace("{VERSION}", BUILD_VERSION);
+    assert_eq!(rendered, golden, "\n--- rendered ---\n{rendered}");
+}
+
+#[test]
+fn custom_buckets_and_legacy_histogram_names() {
+    let cfg = MetricsConfig {
+        dispatch_buckets_micros: Some(vec![250, 2_000_000]),
+        frame_buckets_bytes: Some(vec![10, 100]),
+        legacy_histogram_names: false,
+        ..Default::default()
+    };
+    let m = GatewayMetrics::new(10, &cfg);
+    let labels = [("tenant", "acme"), ("lane", "hot")];
+    m.dispatch_duration.observe(&labels, Duration::from_micros(300));
+    m.observe_frame_size(FrameDir::Inbound, "acme", "hot", 50);
+    assert_eq!(m.dispatch_duration.bucket_count(&labels, 2_000_000), Some(1));
+    let rendered = m.render(&[]);
+    assert!(rendered.contains(r#"wsprism_dispatch_duration_seconds_bucket{lane="hot",tenant="acme",le="0.00025"} 0"#));
+    assert!(rendered.contains(r#"wsprism_dispatch_duration_seconds_bucket{lane="hot",tenant="acme",le="2"} 1"#));
+    assert!(rendered.contains(r#"wsprism_dispatch_duration_seconds_sum{lane="hot",tenant="acme"} 0.0003"#));
+    assert!(rendered.contains(r#"wsprism_inbound_frame_size_bytes_bucket{lane="hot",tenant="acme",le="100"} 1"#));
+
+    let legacy = GatewayMetrics::new(10, &MetricsConfig { legacy_histogram_names: true, ..cfg });
+    legacy.dispatch_duration.observe(&labels, Duration::from_micros(300));
+    let rendered = legacy.render(&[]);
+    assert!(!rendered.contains("wsprism_dispatch_duration_seconds"));
+    assert!(!rendered.contains("# UNIT wsprism_dispatch_duration_micros"));
+    assert!(rendered.contains(r#"wsprism_dispatch_duration_micros_bucket{lane="hot",tenant="acme",le="250"} 0"#));
+    assert!(rendered.contains(r#"wsprism_dispatch_duration_micros_sum{lane="hot",tenant="acme"} 300"#));
+}
+
+#[test]
+fn summary_quantiles_cover_the_recent_window() {
+    let m = GatewayMetrics::default();
+    let labels = [("tenant", "acme"), ("lane", "ext")];
+    assert_eq!(m.dispatch_latency_summary.quantile(&labels, 0.5), None);
+
+    for us in 1..=1000 {
+        m.observe_dispatch(&labels, Duration::from_micros(us));
+    }
+    let q = |q| m.dispatch_latency_summary.quantile(&labels, q).unwrap();
+    assert_eq!((q(0.5), q(0.9), q(0.99), q(0.999)), (500, 900, 990, 999));
+
+    // Only the last SUMMARY_WINDOW observations count towards quantiles.
+    for _ in 0..SUMMARY_WINDOW {
+        m.observe_dispatch(&labels, Duration::from_millis(2));
+    }
+    assert_eq!(q(0.5), 2_000);
+    let rendered = m.render(&[]);
+    assert!(rendered.contains(r#"wsprism_dispatch_latency_seconds{lane="ext",tenant="acme",quantile="0.999"} 0.002"#));
+    assert!(rendered.contains(r#"wsprism_dispatch_latency_seconds_count{lane="ext",tenant="acme"} 2024"#));
+}
+
+#[test]
+fn histogram_buckets_are_validated() {
+    let cfg = |metrics: &str| {
+        config::load_from_str(&format!("version: 1\ngateway:\n  metrics: {metrics}\ntenants:\n  - id: acme\n"))
+    };
+    assert!(cfg("{ dispatch_buckets_micros: [100, 1000], legacy_histogram_names: true }").is_ok());
+    for bad in ["{ dispatch_buckets_micros: [] }", "{ frame_buckets_bytes: [0, 10] }", "{ dispatch_buckets_micros: [5, 5] }"] {
+        let err = cfg(bad).expect_err(bad);
+        assert!(err.to_string().contains("gateway.metrics."), "{err}");
+    }
+}
+
+#[test]
+fn label_cap_is_per_metric_and_per_label() {
+    let m = GatewayMetrics::with_max_label_values_per_metric(1);
+    m.writer_timeouts.inc(&[("tenant", "acme")]);
+    m.writer_timeouts.inc(&[("tenant", "beta")]);
+    m.writer_timeouts.inc(&[("tenant", "gamma")]);
+    m.writer_timeouts.inc(&[("tenant", "acme")]);
+    assert_eq!(m.writer_timeouts.get(&[("tenant", "acme")]), 2);
+    assert_eq!(m.writer_timeouts.get(&[("tenant", "_other")]), 2);
+
+    // Another metric has its own budget.
+    m.decode_errors.inc(&[("tenant", "beta"), ("reason", "json")]);
+    assert_eq!(m.decode_errors.get(&[("tenant", "beta"), ("reason", "json")]), 1);
+}
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["room:*"]
+      hot_allowlist: ["1:*"]
+"#;
+
+#[tokio::test]
+async fn gateway_labels_decode_errors_and_handshake_rejections() {
+    let (state, addr) = spawn_gateway(CFG).await;
+    let url = |tenant: &str| format!("ws://{addr}/v1/ws?tenant={tenant}&ticket=dev");
+
+    assert!(tokio_tungstenite::connect_async(url("nope")).await.is_err());
+
+    // Malformed JSON, then a short hot frame, then invalid UTF-8: each ends its session.
+    let bad: [Message; 3] = [
+        Message::Text("{not json".into()),
+        Message::Binary(vec![1]),
+        Message::Frame(Frame::message(vec![0xff, 0xfe], OpCode::Data(Data::Text), true)),
+    ];
+    for msg in bad {
+        let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+        ws.send(msg).await.unwrap();
+        let drained = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(Ok(_)) = ws.next().await {}
+        });
+        drained.await.unwrap();
+    }
+
+    let m = state.metrics();
+    assert_eq!(m.handshake_rejections.get(&[("tenant", "_unknown"), ("reason", "unknown_tenant")]), 1);
+    for reason in ["json", "hot", "utf8"] {
+        assert_eq!(m.decode_errors.get(&[("tenant", "acme"), ("reason", reason)]), 1, "{reason}");
+    }
+    assert_eq!(m.ws_upgrades.get(&[("tenant", "acme"), ("status", "ok")]), 3);
+}
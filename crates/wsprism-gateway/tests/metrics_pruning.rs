@@ -0,0 +1,104 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use wsprism_gateway::config;
+use wsprism_gateway::config::schema::MetricsConfig;
+use wsprism_gateway::obs::metrics::{CounterVec, GatewayMetrics, GaugeVec, SeriesStats, OVERFLOW_SERIES_VALUE};
+
+#[test]
+fn idle_counter_series_are_pruned_and_recreated() {
+    let c = CounterVec::default();
+    c.inc(&[("tenant", "old")]);
+    std::thread::sleep(Duration::from_millis(40));
+    c.inc(&[("tenant", "new")]);
+
+    assert_eq!(c.prune(Duration::from_millis(20)), 1);
+    assert_eq!(c.get(&[("tenant", "old")]), 0);
+    assert_eq!(c.get(&[("tenant", "new")]), 1);
+    assert_eq!((c.series_count(), c.pruned_series()), (1, 1));
+
+    c.inc(&[("tenant", "old")]);
+    assert_eq!(c.get(&[("tenant", "old")]), 1);
+}
+
+#[test]
+fn gauges_are_only_pruned_at_zero() {
+    let g = GaugeVec::default();
+    g.set(&[("tenant", "busy")], 3);
+    g.inc(&[("tenant", "idle")]);
+    g.dec(&[("tenant", "idle")]);
+
+    assert_eq!(g.prune(Duration::ZERO), 1);
+    assert_eq!(g.get(&[("tenant", "busy")]), 3);
+    assert_eq!(g.series_count(), 1);
+}
+
+#[test]
+fn label_sets_past_the_series_cap_fold_into_overflow() {
+    let c = CounterVec::default().with_max_series(2);
+    for room in ["a", "b", "c", "d", "a"] {
+        c.inc(&[("tenant", "acme"), ("room", room)]);
+    }
+    assert_eq!(c.get(&[("tenant", "acme"), ("room", "a")]), 2);
+    let overflow = [("tenant", OVERFLOW_SERIES_VALUE), ("room", OVERFLOW_SERIES_VALUE)];
+    assert_eq!(c.get(&overflow), 2);
+    assert_eq!((c.series_count(), c.overflowed_series()), (3, 2));
+}
+
+#[test]
+fn concurrent_increments_survive_pruning() {
+    let c = Arc::new(CounterVec::default());
+    let writers: Vec<_> = (0..4)
+        .map(|_| {
+            let c = c.clone();
+            std::thread::spawn(move || {
+                for _ in 0..10_000 {
+                    c.inc(&[("tenant", "acme")]);
+                }
+            })
+        })
+        .collect();
+    // Nothing is older than an hour, so no live series may be dropped.
+    for _ in 0..1000 {
+        c.prune(Duration::from_secs(3600));
+    }
+    for w in writers {
+        w.join().unwrap();
+    }
+    assert_eq!(c.get(&[("tenant", "acme")]), 40_000);
+    assert_eq!(c.pruned_series(), 0);
+}
+
+#[test]
+fn registry_prunes_with_configured_ttl_and_reports_meta_metrics() {
+    let cfg = MetricsConfig { max_series_per_metric: Some(1), series_ttl_secs: Some(0), ..Default::default() };
+    let m = GatewayMetrics::new(100, &cfg);
+    m.writer_timeouts.inc(&[("tenant", "acme")]);
+    m.writer_timeouts.inc(&[("tenant", "beta")]);
+    assert_eq!(m.prune_stale(), 0, "ttl 0 disables pruning");
+
+    let rendered = m.render(&[]);
+    assert!(rendered.contains(r#"wsprism_writer_timeouts_total{tenant="_overflow"} 1"#), "{rendered}");
+    assert!(rendered.contains(r#"wsprism_metric_series_overflowed_total{metric="wsprism_writer_timeouts_total"} 1"#));
+    assert!(!rendered.contains("wsprism_metric_series_pruned_total{"));
+
+    let m = GatewayMetrics::new(100, &MetricsConfig { series_ttl_secs: Some(1), ..Default::default() });
+    m.decode_errors.inc(&[("tenant", "acme"), ("reason", "json")]);
+    std::thread::sleep(Duration::from_millis(1100));
+    assert_eq!(m.prune_stale(), 1);
+    assert!(m.render(&[]).contains(r#"wsprism_metric_series_pruned_total{metric="wsprism_decode_errors_total"} 1"#));
+}
+
+#[test]
+fn series_cap_is_validated() {
+    let cfg = |metrics: &str| {
+        config::load_from_str(&format!("version: 1\ngateway:\n  metrics: {metrics}\ntenants:\n  - id: acme\n"))
+    };
+    assert!(cfg("{ max_series_per_metric: 500, series_ttl_secs: 0 }").is_ok());
+    let err = cfg("{ max_series_per_metric: 0 }").unwrap_err();
+    assert!(err.to_string().contains("max_series_per_metric"), "{err}");
+}
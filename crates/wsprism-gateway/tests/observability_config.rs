@@ -0,0 +1,34 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use wsprism_gateway::config;
+
+fn cfg(observability: &str) -> wsprism_core::error::Result<config::GatewayConfig> {
+    config::load_from_str(&format!("version: 1\nobservability: {observability}\ntenants:\n  - id: acme\n"))
+}
+
+#[test]
+fn otlp_block_parses_with_defaults() {
+    let c = cfg("{ otlp: { endpoint: \"http://tempo:4318\", headers: { x-scope-orgid: acme } } }").unwrap();
+    let otlp = c.observability.otlp.unwrap();
+    assert_eq!(otlp.endpoint, "http://tempo:4318");
+    assert_eq!(otlp.headers["x-scope-orgid"], "acme");
+    assert_eq!(otlp.sample_ratio, 1.0);
+    assert_eq!(otlp.service_name, "wsprism-gateway");
+
+    let c = config::load_from_str("version: 1\ntenants:\n  - id: acme\n").unwrap();
+    assert!(c.observability.otlp.is_none());
+}
+
+#[test]
+fn otlp_block_is_validated() {
+    for (bad, msg) in [
+        ("{ otlp: { endpoint: \"tempo:4318\" } }", "endpoint"),
+        ("{ otlp: { endpoint: \"http://tempo:4318\", sample_ratio: 1.5 } }", "sample_ratio"),
+        ("{ otlp: { endpoint: \"http://tempo:4318\", service_name: \" \" } }", "service_name"),
+    ] {
+        let err = cfg(bad).expect_err(bad);
+        assert!(err.to_string().contains(msg), "{err}");
+    }
+}
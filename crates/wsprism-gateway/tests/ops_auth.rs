@@ -0,0 +1,102 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::net::SocketAddr;
+
+use axum::body::Body;
+use axum::extract::connect_info::ConnectInfo;
+use axum::http::{header, Request, StatusCode};
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::{config, router};
+
+const TOKEN: &str = "metrics-0123456789";
+
+fn state(ops: &str) -> AppState {
+    let yaml = format!(
+        "version: 1\ngateway:\n  admin_token: \"admin-secret\"\nops: {ops}\ntenants:\n  - id: acme\n"
+    );
+    AppState::new(config::load_from_str(&yaml).unwrap()).unwrap()
+}
+
+async fn status(state: &AppState, path: &str, token: Option<&str>, peer: Option<&str>) -> StatusCode {
+    let mut req = Request::get(path);
+    if let Some(t) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {t}"));
+    }
+    let mut req = req.body(Body::empty()).unwrap();
+    if let Some(p) = peer {
+        req.extensions_mut().insert(ConnectInfo(p.parse::<SocketAddr>().unwrap()));
+    }
+    router::build_router(state.clone()).oneshot(req).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn metrics_and_admin_require_the_token() {
+    let s = state(&format!("{{ auth_token: \"{TOKEN}\" }}"));
+
+    let req = Request::get("/metrics").body(Body::empty()).unwrap();
+    let resp = router::build_router(s.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.headers()[header::WWW_AUTHENTICATE], "Bearer");
+
+    assert_eq!(status(&s, "/metrics", Some("metrics-wrong-token"), None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(&s, "/metrics", Some(TOKEN), None).await, StatusCode::OK);
+    assert_eq!(status(&s, "/admin/v1/stats", None, None).await, StatusCode::UNAUTHORIZED);
+    // The ops token gets past the gate, but admin handlers still want admin_token.
+    assert_eq!(status(&s, "/admin/v1/stats", Some(TOKEN), None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(&s, "/admin/v1/stats", Some("admin-secret"), None).await, StatusCode::OK);
+
+    assert_eq!(status(&s, "/healthz", None, None).await, StatusCode::OK);
+    assert_eq!(status(&s, "/readyz", None, None).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn allowed_networks_skip_the_token() {
+    let s = state(&format!("{{ auth_token: \"{TOKEN}\", allow_cidrs: [\"10.0.0.0/8\", \"fd00::/8\", \"192.0.2.7\"] }}"));
+
+    for peer in ["10.1.2.3:9100", "[fd00::1]:9100", "192.0.2.7:9100"] {
+        assert_eq!(status(&s, "/metrics", None, Some(peer)).await, StatusCode::OK, "{peer}");
+    }
+    for peer in ["192.168.1.1:9100", "192.0.2.8:9100"] {
+        assert_eq!(status(&s, "/metrics", None, Some(peer)).await, StatusCode::UNAUTHORIZED, "{peer}");
+    }
+    // Without a known peer address only the token counts.
+    assert_eq!(status(&s, "/metrics", None, None).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn no_ops_config_leaves_metrics_open() {
+    let s = state("{}");
+    assert_eq!(status(&s, "/metrics", None, None).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn token_can_come_from_a_file() {
+    let path = std::env::temp_dir().join(format!("wsprism-ops-token-{}", std::process::id()));
+    std::fs::write(&path, format!("{TOKEN}\n")).unwrap();
+    let s = state(&format!("{{ auth_token_file: \"{}\" }}", path.display()));
+    assert_eq!(status(&s, "/metrics", Some(TOKEN), None).await, StatusCode::OK);
+
+    std::fs::write(&path, "short").unwrap();
+    let yaml = format!("version: 1\nops: {{ auth_token_file: \"{}\" }}\ntenants:\n  - id: acme\n", path.display());
+    assert!(AppState::new(config::load_from_str(&yaml).unwrap()).is_err());
+    std::fs::remove_file(&path).unwrap();
+    assert!(AppState::new(config::load_from_str(&yaml).unwrap()).is_err());
+}
+
+#[test]
+fn ops_section_is_validated() {
+    let cfg = |ops: &str| config::load_from_str(&format!("version: 1\nops: {ops}\ntenants:\n  - id: acme\n"));
+    for (bad, msg) in [
+        ("{ auth_token: \"short\" }", "at least 16"),
+        (&format!("{{ auth_token: \"{TOKEN}\", auth_token_file: \"/run/token\" }}"), "not both"),
+        ("{ allow_cidrs: [\"10.0.0.0/8\"] }", "no effect"),
+        (&format!("{{ auth_token: \"{TOKEN}\", allow_cidrs: [\"10.0.0.0/33\"] }}"), "invalid network"),
+    ] {
+        let err = cfg(bad).expect_err(bad);
+        assert!(err.to_string().contains(msg), "{err}");
+    }
+}
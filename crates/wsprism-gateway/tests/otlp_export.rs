@@ -0,0 +1,182 @@
+//! OTLP span export against a local mock collector.
+
+#![cfg(feature = "otel")]
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+use serde_json::Value;
+use tracing_subscriber::layer::SubscriberExt;
+
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::text::Envelope;
+use wsprism_gateway::config::OtlpConfig;
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::dispatch::{Dispatcher, TextService};
+use wsprism_gateway::obs::otlp::{envelope_traceparent, OtlpExporter, TraceParent};
+use wsprism_gateway::realtime::{Outgoing, RealtimeCore, RealtimeCtx};
+
+const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+#[derive(Clone, Default)]
+struct Collector(Arc<Mutex<Vec<(HeaderMap, Value)>>>);
+
+impl Collector {
+    fn spans(&self) -> Vec<Value> {
+        let posts = self.0.lock().unwrap();
+        posts
+            .iter()
+            .flat_map(|(_, body)| body["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap().clone())
+            .collect()
+    }
+}
+
+async fn traces(State(c): State<Collector>, headers: HeaderMap, Json(body): Json<Value>) {
+    c.0.lock().unwrap().push((headers, body));
+}
+
+async fn mock_collector() -> (String, Collector) {
+    let collector = Collector::default();
+    let app = Router::new().route("/v1/traces", post(traces)).with_state(collector.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}"), collector)
+}
+
+fn otlp(endpoint: &str, sample_ratio: f64) -> OtlpConfig {
+    OtlpConfig {
+        endpoint: endpoint.to_string(),
+        headers: HashMap::from([("x-api-key".to_string(), "k1".to_string())]),
+        sample_ratio,
+        service_name: "gw-test".into(),
+    }
+}
+
+struct Echo;
+
+#[async_trait]
+impl TextService for Echo {
+    fn svc(&self) -> &'static str {
+        "echo"
+    }
+    async fn handle(&self, _ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
+        if env.msg_type == "fail" {
+            return Err(WsPrismError::BadRequest("nope".into()));
+        }
+        Ok(None)
+    }
+}
+
+fn ctx() -> RealtimeCtx {
+    RealtimeCtx::new("acme", "alice", ConnectionId::new(), "trace-1", None, Arc::new(RealtimeCore::new()))
+}
+
+fn env(msg_type: &str, data: &str) -> Envelope {
+    serde_json::from_str(&format!(r#"{{"v":1,"svc":"echo","type":"{msg_type}","data":{data}}}"#)).unwrap()
+}
+
+fn attr<'a>(span: &'a Value, key: &str) -> &'a Value {
+    let attrs = span["attributes"].as_array().unwrap();
+    &attrs.iter().find(|a| a["key"] == key).unwrap_or_else(|| panic!("no {key} in {span}"))["value"]
+}
+
+#[test]
+fn traceparent_parsing() {
+    let tp = TraceParent::parse(PARENT).unwrap();
+    assert_eq!(tp.span_id, [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]);
+    assert!(tp.sampled);
+    for bad in [
+        "",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+    ] {
+        assert!(TraceParent::parse(bad).is_none(), "{bad}");
+    }
+
+    let data = serde_json::value::RawValue::from_string(format!(r#"{{"traceparent":"{PARENT}","x":1}}"#)).unwrap();
+    assert_eq!(envelope_traceparent(Some(&data)).as_deref(), Some(PARENT));
+    let data = serde_json::value::RawValue::from_string(r#"{"traceparent":"junk"}"#.into()).unwrap();
+    assert_eq!(envelope_traceparent(Some(&data)), None);
+}
+
+#[tokio::test]
+async fn dispatch_spans_are_exported_with_remote_parent() {
+    let (endpoint, collector) = mock_collector().await;
+    let (layer, exporter) = OtlpExporter::start(&otlp(&endpoint, 1.0)).unwrap();
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+    let d = Dispatcher::new();
+    d.register_text(Arc::new(Echo));
+    d.dispatch_text(ctx(), env("ping", &format!(r#"{{"traceparent":"{PARENT}"}}"#))).await.unwrap();
+    d.dispatch_text(ctx(), env("fail", "{}")).await.unwrap_err();
+    exporter.flush().await;
+
+    let posts = collector.0.lock().unwrap().clone();
+    assert_eq!(posts[0].0["x-api-key"], "k1");
+    let service = &posts[0].1["resourceSpans"][0]["resource"]["attributes"][0];
+    assert_eq!(service["value"]["stringValue"], "gw-test");
+
+    let spans = collector.spans();
+    assert_eq!(spans.len(), 2);
+    let ping = spans.iter().find(|s| attr(s, "msg_type")["stringValue"] == "ping").unwrap();
+    assert_eq!(ping["name"], "dispatch");
+    assert_eq!(ping["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(ping["parentSpanId"], "00f067aa0ba902b7");
+    assert_eq!(ping["status"]["code"], 0);
+    for (key, value) in [("tenant", "acme"), ("user", "alice"), ("svc", "echo"), ("correlation_id", "trace-1")] {
+        assert_eq!(attr(ping, key)["stringValue"], value);
+    }
+
+    let fail = spans.iter().find(|s| attr(s, "msg_type")["stringValue"] == "fail").unwrap();
+    assert_eq!(fail["status"]["code"], 2);
+    assert!(fail.get("parentSpanId").is_none());
+    assert_ne!(fail["traceId"], ping["traceId"]);
+}
+
+#[tokio::test]
+async fn child_spans_share_the_trace_and_unsampled_traces_are_dropped() {
+    let (endpoint, collector) = mock_collector().await;
+    let (layer, exporter) = OtlpExporter::start(&otlp(&endpoint, 1.0)).unwrap();
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+    let d = Dispatcher::new();
+    d.register_text(Arc::new(Echo));
+    let session = tracing::info_span!("ws", tenant = "acme");
+    tracing::Instrument::instrument(d.dispatch_text(ctx(), env("ping", "{}")), session.clone()).await.unwrap();
+    drop(session);
+    // Remote parent not sampled: the dispatch span is not exported either.
+    let unsampled = PARENT.replace("-01", "-00");
+    d.dispatch_text(ctx(), env("ping", &format!(r#"{{"traceparent":"{unsampled}"}}"#))).await.unwrap();
+    exporter.flush().await;
+
+    let spans = collector.spans();
+    assert_eq!(spans.len(), 2, "{spans:?}");
+    let ws = spans.iter().find(|s| s["name"] == "ws").unwrap();
+    let dispatch = spans.iter().find(|s| s["name"] == "dispatch").unwrap();
+    assert_eq!(dispatch["traceId"], ws["traceId"]);
+    assert_eq!(dispatch["parentSpanId"], ws["spanId"]);
+}
+
+#[tokio::test]
+async fn zero_sample_ratio_exports_nothing() {
+    let (endpoint, collector) = mock_collector().await;
+    let (layer, exporter) = OtlpExporter::start(&otlp(&endpoint, 0.0)).unwrap();
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+    let d = Dispatcher::new();
+    d.register_text(Arc::new(Echo));
+    for _ in 0..10 {
+        d.dispatch_text(ctx(), env("ping", "{}")).await.unwrap();
+    }
+    exporter.flush().await;
+    assert!(collector.spans().is_empty());
+}
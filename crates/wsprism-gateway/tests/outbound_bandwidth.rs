@@ -0,0 +1,101 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::ws::Message;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCore};
+use wsprism_gateway::transport::bandwidth::BandwidthMeter;
+
+/// A text message of exactly `len` bytes once serialized.
+fn sized(len: usize, qos: QoS) -> Outgoing {
+    Outgoing { qos, payload: Payload::TextJson(json!("x".repeat(len - 2))), priority: Priority::Normal }
+}
+
+fn metered(core: &RealtimeCore, bytes_per_sec: u64, burst: u64) -> (ConnectionId, mpsc::Receiver<Message>) {
+    let (tx, rx) = mpsc::channel(16);
+    let id = ConnectionId::new();
+    let meter = Arc::new(BandwidthMeter::new("acme", bytes_per_sec, burst));
+    let conn = Connection::new(tx, SessionClaims::default()).with_meter(Some(meter));
+    core.sessions.try_insert("acme".into(), "acme::bob".into(), id, conn, 0).unwrap();
+    (id, rx)
+}
+
+#[test]
+fn meter_debits_bytes_and_caps_oversized_frames() {
+    let meter = BandwidthMeter::new("acme", 100, 0);
+    assert!(meter.try_consume(60).is_ok());
+    assert!(meter.try_consume(60).is_err());
+
+    // Larger than the whole bucket: needs a full bucket, not forever.
+    let meter = BandwidthMeter::new("acme", 100, 0);
+    assert!(meter.try_consume(10_000).is_ok());
+    assert!(meter.try_consume(1).is_err());
+}
+
+#[test]
+fn lossy_sends_over_the_cap_are_dropped_and_counted() {
+    let metrics = Arc::new(GatewayMetrics::default());
+    let core = RealtimeCore::new().with_metrics(metrics.clone());
+    let (id, mut rx) = metered(&core, 100, 100);
+
+    for _ in 0..3 {
+        core.send_to_session(id, sized(40, QoS::Lossy)).unwrap();
+    }
+    assert!(rx.try_recv().is_ok());
+    assert!(rx.try_recv().is_ok());
+    assert!(rx.try_recv().is_err(), "third message exceeds 100 bytes");
+
+    let acme = [("tenant", "acme")];
+    assert_eq!(metrics.bytes_sent.get(&acme), 80);
+    assert_eq!(metrics.bandwidth_limited.get(&acme), 1);
+    assert_eq!(core.sessions.sample_outbound()[0].lossy_drops, 1);
+}
+
+#[tokio::test]
+async fn reliable_sends_wait_for_bandwidth_within_timeout() {
+    let metrics = Arc::new(GatewayMetrics::default());
+    let core = RealtimeCore::new().with_metrics(metrics.clone());
+    let (_id, mut rx) = metered(&core, 1000, 1000);
+
+    let start = Instant::now();
+    for _ in 0..2 {
+        let reached = core.broadcast_all(sized(600, QoS::Reliable { timeout_ms: 2000 })).await.unwrap();
+        assert_eq!(reached, 1);
+    }
+    assert!(start.elapsed() >= Duration::from_millis(150), "second send waited for tokens");
+    assert!(rx.try_recv().is_ok() && rx.try_recv().is_ok());
+    assert_eq!(metrics.bandwidth_limited.get(&[("tenant", "acme")]), 1);
+
+    // Not enough time to refill 600 bytes at 1000 B/s.
+    let reached = core.broadcast_all(sized(600, QoS::Reliable { timeout_ms: 50 })).await.unwrap();
+    assert_eq!(reached, 0);
+    assert_eq!(core.sessions.sample_outbound()[0].reliable_drops, 1);
+}
+
+#[tokio::test]
+async fn unmetered_sessions_are_not_limited() {
+    let metrics = Arc::new(GatewayMetrics::default());
+    let core = RealtimeCore::new().with_metrics(metrics.clone());
+    let (tx, mut rx) = mpsc::channel(16);
+    let id = ConnectionId::new();
+    core.sessions
+        .try_insert("acme".into(), "acme::bob".into(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+
+    for _ in 0..10 {
+        core.send_to_session(id, sized(10_000, QoS::Lossy)).unwrap();
+    }
+    for _ in 0..10 {
+        assert!(rx.try_recv().is_ok());
+    }
+    assert_eq!(metrics.bandwidth_limited.get(&[("tenant", "acme")]), 0);
+}
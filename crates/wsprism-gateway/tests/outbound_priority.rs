@@ -0,0 +1,82 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use axum::extract::ws;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCore};
+
+use common::{connect, next_json, spawn_gateway};
+
+fn out(n: u32, priority: Priority) -> Outgoing {
+    Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!({ "n": n })), priority }
+}
+
+fn text(m: ws::Message) -> String {
+    match m {
+        ws::Message::Text(t) => t,
+        other => panic!("unexpected {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn high_priority_skips_a_full_queue() {
+    let core = RealtimeCore::new();
+    let (tx, mut rx) = mpsc::channel(1);
+    let (high_tx, mut high_rx) = mpsc::unbounded_channel();
+    let id = ConnectionId::new();
+    let conn = Connection::new(tx, SessionClaims::default()).with_high_priority(high_tx);
+    core.sessions.try_insert("acme".into(), "acme::alice".into(), id, conn, 0).unwrap();
+
+    core.send_to_session(id, out(1, Priority::Normal)).unwrap();
+    core.send_to_session(id, out(2, Priority::Normal)).unwrap();
+    core.send_to_session(id, out(3, Priority::High)).unwrap();
+    core.send_to_session(id, out(4, Priority::High)).unwrap();
+
+    assert_eq!(text(rx.try_recv().unwrap()), r#"{"n":1}"#);
+    assert!(rx.try_recv().is_err(), "the second normal message was dropped");
+    assert_eq!(text(high_rx.try_recv().unwrap()), r#"{"n":3}"#);
+    assert_eq!(text(high_rx.try_recv().unwrap()), r#"{"n":4}"#);
+}
+
+#[tokio::test]
+async fn sessions_without_a_high_priority_queue_use_the_normal_one() {
+    let core = RealtimeCore::new();
+    let (tx, mut rx) = mpsc::channel(4);
+    let id = ConnectionId::new();
+    core.sessions
+        .try_insert("acme".into(), "acme::alice".into(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+
+    core.send_to_session(id, out(1, Priority::Normal)).unwrap();
+    core.send_to_session(id, out(2, Priority::High)).unwrap();
+    assert_eq!(text(rx.try_recv().unwrap()), r#"{"n":1}"#);
+    assert_eq!(text(rx.try_recv().unwrap()), r#"{"n":2}"#);
+}
+
+#[tokio::test]
+async fn high_priority_frames_overtake_buffered_ones() {
+    let (state, addr) = spawn_gateway("version: 1\ntenants:\n  - id: acme\n").await;
+    let mut client = connect(addr, "tenant=acme&ticket=dev").await;
+    assert_eq!(next_json(&mut client).await["type"], "authed");
+
+    // Single-threaded runtime: the session's writer cannot run until all
+    // five are queued.
+    let core = state.realtime();
+    for n in 0..4 {
+        core.send_to_user("acme::user:dev", out(n, Priority::Normal)).unwrap();
+    }
+    core.send_to_user("acme::user:dev", out(99, Priority::High)).unwrap();
+
+    let mut order = Vec::new();
+    for _ in 0..5 {
+        order.push(next_json(&mut client).await["n"].as_u64().unwrap());
+    }
+    assert_eq!(order, vec![99, 0, 1, 2, 3]);
+}
@@ -0,0 +1,117 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use axum::extract::ws::Message;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::config;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCore};
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+  - id: "globex"
+"#;
+
+/// Register a session whose queue holds `cap` slots, `queued` of them used.
+fn connect(core: &RealtimeCore, tenant: &str, user: &str, cap: usize, queued: usize) -> (ConnectionId, mpsc::Receiver<Message>) {
+    let (tx, rx) = mpsc::channel::<Message>(cap);
+    for _ in 0..queued {
+        tx.try_send(Message::Text("filler".into())).unwrap();
+    }
+    let id = ConnectionId::new();
+    core.sessions
+        .try_insert(tenant.into(), format!("{tenant}::{user}"), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    (id, rx)
+}
+
+fn lossy() -> Outgoing {
+    Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!({ "hello": "world" })), priority: Priority::Normal }
+}
+
+#[test]
+fn sample_reports_depths_and_drops_per_tenant() {
+    let core = RealtimeCore::new();
+    let mut receivers = Vec::new();
+    for (i, queued) in [0, 1, 3, 4].into_iter().enumerate() {
+        receivers.push(connect(&core, "acme", &format!("u{i}"), 4, queued));
+    }
+    receivers.push(connect(&core, "globex", "solo", 4, 2));
+
+    // The full acme session drops both sends.
+    core.send_to_user("acme::u3", lossy()).unwrap();
+    core.send_to_user("acme::u3", lossy()).unwrap();
+
+    let samples = core.sessions.sample_outbound();
+    let tenants: Vec<&str> = samples.iter().map(|s| s.tenant.as_str()).collect();
+    assert_eq!(tenants, ["acme", "globex"]);
+    assert_eq!(samples[0].depths, vec![0, 1, 3, 4]);
+    assert_eq!(samples[0].max_depth(), 4);
+    assert_eq!(samples[0].p99_depth(), 4);
+    assert_eq!((samples[0].lossy_drops, samples[0].reliable_drops), (2, 0));
+    assert_eq!(samples[1].max_depth(), 2);
+    assert_eq!(samples[1].lossy_drops, 0);
+
+    // Drops are reported once.
+    let again = core.sessions.sample_outbound();
+    assert_eq!(again[0].lossy_drops, 0);
+}
+
+#[test]
+fn drops_of_unregistered_sessions_are_kept_for_next_sample() {
+    let core = RealtimeCore::new();
+    let (id, _rx) = connect(&core, "acme", "bob", 1, 1);
+    core.send_to_session(id, lossy()).unwrap();
+    core.sessions.unregister(id).unwrap();
+
+    let samples = core.sessions.sample_outbound();
+    assert_eq!(samples.len(), 1);
+    assert!(samples[0].depths.is_empty());
+    assert_eq!(samples[0].max_depth(), 0);
+    assert_eq!(samples[0].lossy_drops, 1);
+    assert_eq!(core.sessions.sample_outbound()[0].lossy_drops, 0);
+}
+
+#[tokio::test]
+async fn reliable_timeouts_count_as_reliable_drops() {
+    let core = RealtimeCore::new();
+    let (_id, _rx) = connect(&core, "acme", "bob", 1, 1);
+    let out = Outgoing { qos: QoS::Reliable { timeout_ms: 10 }, payload: Payload::TextJson(json!({})), priority: Priority::Normal };
+    assert_eq!(core.broadcast_all(out).await.unwrap(), 0);
+
+    let samples = core.sessions.sample_outbound();
+    assert_eq!((samples[0].lossy_drops, samples[0].reliable_drops), (0, 1));
+}
+
+#[test]
+fn sampler_records_gauges_and_drop_counter() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let core = state.realtime();
+    let (id, rx) = connect(&core, "acme", "bob", 2, 2);
+    core.send_to_session(id, lossy()).unwrap();
+
+    state.sample_outbound();
+    let metrics = state.metrics();
+    let acme = [("tenant", "acme")];
+    assert_eq!(metrics.outbound_queue_depth_max.get(&acme), 2);
+    assert_eq!(metrics.outbound_queue_depth_p99.get(&acme), 2);
+    assert_eq!(metrics.outbound_drops.get(&[("tenant", "acme"), ("qos", "lossy")]), 1);
+
+    // Once the session is gone its depth falls back to zero; drops stay cumulative.
+    drop(rx);
+    core.sessions.unregister(id).unwrap();
+    state.sample_outbound();
+    assert_eq!(metrics.outbound_queue_depth_max.get(&acme), 0);
+    assert_eq!(metrics.outbound_drops.get(&[("tenant", "acme"), ("qos", "lossy")]), 1);
+
+    let text = metrics.render(&[]);
+    assert!(text.contains("wsprism_outbound_queue_depth_max{tenant=\"acme\"} 0"));
+    assert!(text.contains("wsprism_outbound_drops_total{qos=\"lossy\",tenant=\"acme\"} 1"));
+}
@@ -0,0 +1,111 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::hot::HotFrame;
+use wsprism_core::protocol::text::Envelope;
+
+use wsprism_gateway::config::schema::PluginErrorMode;
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::plugin::{PluginCtx, PluginHost, PluginStage, PluginVerdict};
+use wsprism_gateway::policy::{DecisionReason, PolicyDecision};
+
+/// Rejects `chat:shout`, fails on `chat:boom`, panics on `chat:panic`;
+/// drops every hot frame of service 9.
+struct Filter;
+
+impl PluginStage for Filter {
+    fn on_ext(&self, _ctx: &PluginCtx<'_>, env: &Envelope) -> Result<PluginVerdict> {
+        match env.msg_type.as_str() {
+            "shout" => Ok(PluginVerdict::Reject),
+            "boom" => Err(WsPrismError::Internal("boom".into())),
+            "panic" => panic!("plugin bug"),
+            _ => Ok(PluginVerdict::Pass),
+        }
+    }
+
+    fn on_hot(&self, _ctx: &PluginCtx<'_>, frame: &HotFrame) -> Result<PluginVerdict> {
+        Ok(if frame.svc_id == 9 { PluginVerdict::Drop } else { PluginVerdict::Pass })
+    }
+}
+
+const CTX: PluginCtx<'static> = PluginCtx { tenant: "acme", user: "alice", guest: false };
+
+fn env(msg_type: &str) -> Envelope {
+    serde_json::from_str(&format!(r#"{{"v":1,"svc":"chat","type":"{msg_type}"}}"#)).unwrap()
+}
+
+fn hot(svc_id: u8) -> HotFrame {
+    HotFrame { v: 1, svc_id, opcode: 1, flags: 0, seq: None, payload: Bytes::from_static(b"x") }
+}
+
+fn errors(m: &GatewayMetrics, lane: &str) -> u64 {
+    m.plugin_errors.get(&[("tenant", "acme"), ("lane", lane)])
+}
+
+#[test]
+fn verdicts_map_onto_policy_decisions() {
+    let host = PluginHost::new("acme".into(), Arc::new(Filter), PluginErrorMode::Drop);
+    let m = GatewayMetrics::default();
+
+    assert!(matches!(host.check_ext(&m, &CTX, &env("send")), PolicyDecision::Pass));
+    assert!(matches!(
+        host.check_ext(&m, &CTX, &env("shout")),
+        PolicyDecision::Reject { reason: DecisionReason::Plugin, .. }
+    ));
+    assert!(matches!(host.check_hot(&m, &CTX, &hot(9)), PolicyDecision::Drop { .. }));
+    assert!(matches!(host.check_hot(&m, &CTX, &hot(1)), PolicyDecision::Pass));
+
+    let reject = [("tenant", "acme"), ("lane", "ext"), ("decision", "reject"), ("reason", "plugin")];
+    assert_eq!(m.policy_decisions.get(&reject), 1);
+    assert_eq!(errors(&m, "ext"), 0);
+}
+
+#[test]
+fn failures_fail_closed_and_are_counted() {
+    let host = PluginHost::new("acme".into(), Arc::new(Filter), PluginErrorMode::Drop);
+    let m = GatewayMetrics::default();
+
+    assert!(matches!(host.check_ext(&m, &CTX, &env("boom")), PolicyDecision::Drop { .. }));
+    assert!(matches!(host.check_ext(&m, &CTX, &env("panic")), PolicyDecision::Drop { .. }));
+    assert_eq!(errors(&m, "ext"), 2);
+    assert!(m.render(&[]).contains("wsprism_plugin_errors_total"));
+}
+
+#[test]
+fn failures_can_fail_open() {
+    let host = PluginHost::new("acme".into(), Arc::new(Filter), PluginErrorMode::Pass);
+    let m = GatewayMetrics::default();
+
+    assert!(matches!(host.check_ext(&m, &CTX, &env("boom")), PolicyDecision::Pass));
+    assert_eq!(errors(&m, "ext"), 1);
+}
+
+#[test]
+fn plugin_config_is_validated() {
+    let yaml = |extra: &str| {
+        format!(
+            r#"
+version: 1
+tenants:
+  - id: "acme"
+    plugin:
+      path: "filter.wasm"
+      {extra}
+"#
+        )
+    };
+    assert!(wsprism_gateway::config::load_from_str(&yaml("timeout_ms: 0")).is_err());
+    assert!(wsprism_gateway::config::load_from_str(&yaml("fuel: 0")).is_err());
+    let cfg = wsprism_gateway::config::load_from_str(&yaml("on_error: pass")).unwrap();
+    let p = cfg.tenants[0].plugin.as_ref().unwrap();
+    assert_eq!(p.on_error, PluginErrorMode::Pass);
+    assert_eq!(p.timeout_ms, 20);
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    assert!(wsprism_gateway::app_state::AppState::new(cfg).is_err());
+}
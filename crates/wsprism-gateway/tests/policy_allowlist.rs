@@ -0,0 +1,170 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use wsprism_gateway::policy::allowlist::{
+    compile_ext_rules, compile_hot_rules, find_ext_rule, is_ext_allowed, is_hot_allowed, ExtMatchKind,
+    ExtRule, HotMatchKind,
+};
+
+fn rules(raw: &[&str]) -> Vec<wsprism_gateway::policy::allowlist::HotRule> {
+    let raw: Vec<String> = raw.iter().map(|s| s.to_string()).collect();
+    compile_hot_rules(&raw).unwrap()
+}
+
+#[test]
+fn full_range_matches_every_opcode() {
+    let r = rules(&["1:0-255"]);
+    assert_eq!(r[0].opcode, HotMatchKind::Range(0, 255));
+    assert!(is_hot_allowed(&r, 1, 0));
+    assert!(is_hot_allowed(&r, 1, 255));
+    assert!(!is_hot_allowed(&r, 2, 0));
+}
+
+#[test]
+fn adjacent_ranges_cover_boundaries() {
+    let low = rules(&["1:1-10"]);
+    assert!(!is_hot_allowed(&low, 1, 0));
+    assert!(is_hot_allowed(&low, 1, 1));
+    assert!(is_hot_allowed(&low, 1, 10));
+    assert!(!is_hot_allowed(&low, 1, 11));
+
+    let high = rules(&["1:11-20"]);
+    assert!(!is_hot_allowed(&high, 1, 10));
+    assert!(is_hot_allowed(&high, 1, 11));
+    assert!(is_hot_allowed(&high, 1, 20));
+    assert!(!is_hot_allowed(&high, 1, 21));
+
+    let both = rules(&["1:1-10", "1:11-20"]);
+    assert!((1..=20).all(|op| is_hot_allowed(&both, 1, op)));
+}
+
+#[test]
+fn exact_and_wildcard_still_work() {
+    let r = rules(&["1:7", "2:*"]);
+    assert_eq!(r[0].opcode, HotMatchKind::Exact(7));
+    assert_eq!(r[1].opcode, HotMatchKind::Wildcard);
+    assert!(is_hot_allowed(&r, 1, 7));
+    assert!(!is_hot_allowed(&r, 1, 8));
+    assert!(is_hot_allowed(&r, 2, 200));
+}
+
+#[test]
+fn malformed_ranges_are_bad_request() {
+    for bad in ["1:10-1", "1:0-256", "1:-5", "1:3-", "1:a-b"] {
+        let err = compile_hot_rules(&[bad.to_string()]).expect_err(bad);
+        assert_eq!(err.client_code().as_str(), "BAD_REQUEST", "{bad}");
+    }
+}
+
+fn ext(raw: &[&str]) -> Vec<ExtRule> {
+    let raw: Vec<String> = raw.iter().map(|s| s.to_string()).collect();
+    compile_ext_rules(&raw).unwrap()
+}
+
+#[test]
+fn wildcard_svc_matches_any_service() {
+    let r = ext(&["*:ping", "chat:send"]);
+    assert!(is_ext_allowed(&r, "lobby", "ping"));
+    assert!(is_ext_allowed(&r, "chat", "ping"));
+    assert!(!is_ext_allowed(&r, "lobby", "pong"));
+    assert_eq!(r[0].concrete_svc(), Some("chat"));
+    assert_eq!(r[1].concrete_svc(), None);
+}
+
+#[test]
+fn exact_entries_take_precedence_over_wildcards() {
+    // The wildcard is listed first, but the exact entry's clauses apply.
+    let r = ext(&["chat:*@<=10", "*:*@<=20", "chat:send"]);
+    let kinds: Vec<_> = r.iter().map(ExtRule::kind).collect();
+    assert_eq!(kinds, [ExtMatchKind::Exact, ExtMatchKind::Wildcard, ExtMatchKind::Wildcard]);
+
+    let send = find_ext_rule(&r, "chat", "send").unwrap();
+    assert_eq!((send.index, send.max_bytes), (2, None));
+    let typing = find_ext_rule(&r, "chat", "typing").unwrap();
+    assert_eq!((typing.index, typing.max_bytes), (0, Some(10)));
+    assert_eq!(find_ext_rule(&r, "game", "move").unwrap().index, 1);
+}
+
+#[cfg(not(feature = "regex-allowlist"))]
+#[test]
+fn regex_entries_require_the_feature() {
+    let err = compile_ext_rules(&["~game-.*:state".to_string()]).expect_err("feature off");
+    assert!(err.to_string().contains("regex-allowlist"), "{err}");
+    let err = compile_ext_rules(&["/chat:send_(text|image)/".to_string()]).expect_err("feature off");
+    assert!(err.to_string().contains("regex-allowlist"), "{err}");
+}
+
+#[cfg(feature = "regex-allowlist")]
+#[test]
+fn regex_entries_match_whole_svc_after_wildcards() {
+    let r = ext(&["~game-[0-9]+:state@<=64", "game-1:*", "chat:send"]);
+    let kinds: Vec<_> = r.iter().map(ExtRule::kind).collect();
+    assert_eq!(kinds, [ExtMatchKind::Exact, ExtMatchKind::Wildcard, ExtMatchKind::Regex]);
+
+    assert!(is_ext_allowed(&r, "game-42", "state"));
+    assert!(!is_ext_allowed(&r, "game-42", "move"));
+    assert!(!is_ext_allowed(&r, "xgame-42", "state"), "anchored");
+    assert!(!is_ext_allowed(&r, "game-42x", "state"), "anchored");
+    // game-1 hits the wildcard first, so the regex size clause does not apply.
+    assert_eq!(find_ext_rule(&r, "game-1", "state").unwrap().max_bytes, None);
+    assert_eq!(find_ext_rule(&r, "game-2", "state").unwrap().max_bytes, Some(64));
+    assert_eq!(r[2].to_string(), "~game-[0-9]+:state@<=64");
+    assert_eq!(r[2].concrete_svc(), None);
+}
+
+#[cfg(feature = "regex-allowlist")]
+#[test]
+fn regex_entries_round_trip_through_display() {
+    for entry in ["~game-[0-9]+:state", "~(a|b):*@<=64@5", "/chat:send_(text|image)/@<=64"] {
+        let rule = &ext(&[entry])[0];
+        assert_eq!(rule.to_string(), entry);
+        let again = &ext(&[rule.to_string().as_str()])[0];
+        assert_eq!((again.to_string(), again.kind()), (rule.to_string(), rule.kind()));
+        for (svc, ty) in [("game-7", "state"), ("a", "x"), ("chat", "send_image"), ("chat", "send_file")] {
+            assert_eq!(again.matches(svc, ty), rule.matches(svc, ty), "{entry} vs {svc}:{ty}");
+        }
+    }
+}
+
+#[cfg(feature = "regex-allowlist")]
+#[test]
+fn invalid_regex_names_the_entry() {
+    let err = compile_ext_rules(&["~game-(:state".to_string()]).expect_err("bad regex");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+    assert!(err.to_string().contains("~game-(:state"), "{err}");
+}
+
+#[cfg(feature = "regex-allowlist")]
+#[test]
+fn entry_regex_vectors() {
+    // (entry, svc, type, allowed)
+    let vectors = [
+        ("/chat:send_(text|image|file)/", "chat", "send_text", true),
+        ("/chat:send_(text|image|file)/", "chat", "send_video", false),
+        ("/chat:send_(text|image|file)/", "chat", "send_textx", false),
+        ("/(chat|dm):typing/", "dm", "typing", true),
+        ("/game-[0-9]+:.*/", "chat", "game-1:state", false),
+    ];
+    for (entry, svc, ty, allowed) in vectors {
+        assert_eq!(is_ext_allowed(&ext(&[entry]), svc, ty), allowed, "{entry} vs {svc}:{ty}");
+    }
+}
+
+#[cfg(feature = "regex-allowlist")]
+#[test]
+fn entry_regex_is_checked_last_and_keeps_clauses() {
+    let r = ext(&["/chat:send_(text|image)/@<=64", "chat:*", "chat:send_text"]);
+    let kinds: Vec<_> = r.iter().map(ExtRule::kind).collect();
+    assert_eq!(kinds, [ExtMatchKind::Exact, ExtMatchKind::Wildcard, ExtMatchKind::Regex]);
+    assert_eq!(find_ext_rule(&r, "chat", "send_image").unwrap().index, 1);
+    assert_eq!(r[2].to_string(), "/chat:send_(text|image)/@<=64");
+    assert_eq!(r[2].concrete_svc(), None);
+
+    let r = ext(&["/chat:send_(text|image)/@<=64"]);
+    assert_eq!(find_ext_rule(&r, "chat", "send_image").unwrap().max_bytes, Some(64));
+
+    let err = compile_ext_rules(&["/chat:send_(text/".to_string()]).expect_err("bad regex");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+    assert!(err.to_string().contains("/chat:send_(text/"), "{err}");
+}
@@ -0,0 +1,60 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use wsprism_gateway::config::TenantPolicy;
+use wsprism_gateway::policy::{DecisionReason, PolicyDecision, TenantPolicyRuntime};
+
+fn runtime(policy: TenantPolicy) -> TenantPolicyRuntime {
+    TenantPolicyRuntime::new("acme".into(), 4096, &policy).unwrap()
+}
+
+#[test]
+fn deny_overrides_wildcard_allow() {
+    let rt = runtime(TenantPolicy {
+        ext_allowlist: vec!["chat:*".into()],
+        ext_denylist: vec!["chat:send".into()],
+        hot_allowlist: vec!["1:*".into()],
+        hot_denylist: vec!["1:7".into()],
+        ..TenantPolicy::default()
+    });
+
+    match rt.check_text(10, "chat", "send") {
+        PolicyDecision::Reject { code, reason, .. } => {
+            assert_eq!(code.as_str(), "NOT_ALLOWED");
+            assert_eq!(reason, DecisionReason::Denylist);
+        }
+        other => panic!("expected reject, got {other:?}"),
+    }
+    assert!(matches!(rt.check_text(10, "chat", "typing"), PolicyDecision::Pass));
+
+    assert!(matches!(rt.check_hot(10, 1, 7), PolicyDecision::Drop { reason: DecisionReason::Denylist }));
+    assert!(matches!(rt.check_hot(10, 1, 8), PolicyDecision::Pass));
+}
+
+#[test]
+fn rate_suffix_soft_throttles() {
+    let rt = runtime(TenantPolicy {
+        ext_allowlist: vec!["chat:*".into()],
+        ext_denylist: vec!["chat:send@3".into()],
+        ..TenantPolicy::default()
+    });
+
+    let passed = (0..10)
+        .filter(|_| matches!(rt.check_text(10, "chat", "send"), PolicyDecision::Pass))
+        .count();
+    assert_eq!(passed, 3);
+    // Other types do not consume the throttled rule's budget.
+    assert!(matches!(rt.check_text(10, "chat", "typing"), PolicyDecision::Pass));
+}
+
+#[test]
+fn invalid_rate_suffix_fails_compile() {
+    let policy = TenantPolicy {
+        ext_allowlist: vec!["chat:*".into()],
+        ext_denylist: vec!["chat:send@0".into()],
+        ..TenantPolicy::default()
+    };
+    let err = TenantPolicyRuntime::new("acme".into(), 4096, &policy).err().expect("must fail");
+    assert!(err.to_string().contains("ext_denylist"));
+}
@@ -0,0 +1,65 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use wsprism_gateway::config::TenantPolicy;
+use wsprism_gateway::policy::{DecisionReason, PolicyDecision, TenantPolicyRuntime};
+
+fn runtime(allow: &[&str], deny: &[&str]) -> TenantPolicyRuntime {
+    let policy = TenantPolicy {
+        rate_limit_rps: 1,
+        rate_limit_burst: 1,
+        ext_allowlist: allow.iter().map(|s| s.to_string()).collect(),
+        ext_denylist: deny.iter().map(|s| s.to_string()).collect(),
+        ..TenantPolicy::default()
+    };
+    TenantPolicyRuntime::new("acme".into(), 4096, &policy).unwrap()
+}
+
+#[test]
+fn explains_length_allowlist_and_denylist_outcomes() {
+    let rt = runtime(&["room:*", "game:move@<=128", "echo:ping", "chat:*"], &["chat:spam", "chat:typing@5"]);
+
+    let e = rt.explain_text(5000, "chat", "send");
+    assert!(matches!(e.decision, PolicyDecision::Close { reason: DecisionReason::Len, .. }));
+    assert_eq!(e.reason, "frame size 5000 > max 4096");
+    assert_eq!(e.rule_index, None);
+
+    let e = rt.explain_text(10, "chat", "send");
+    assert!(matches!(e.decision, PolicyDecision::Pass));
+    assert_eq!(e.reason, "svc=chat type=send matched rule 3 (chat:*)");
+    assert_eq!(e.rule_index, Some(3));
+
+    let e = rt.explain_text(10, "chat", "spam");
+    assert_eq!(e.decision.reason(), DecisionReason::Denylist);
+    assert_eq!(e.reason, "svc=chat type=spam matched deny rule 0 (chat:spam)");
+    assert_eq!(e.rule_index, Some(0));
+
+    let e = rt.explain_text(10, "chat", "typing");
+    assert!(matches!(e.decision, PolicyDecision::Pass));
+    assert_eq!(e.reason, "svc=chat type=typing matched rule 3 (chat:*); throttled by deny rule 1");
+
+    let e = rt.explain_text(200, "game", "move");
+    assert_eq!(e.decision.reason(), DecisionReason::RuleSize);
+    assert_eq!(e.reason, "frame size 200 > max 128 of rule 1 (game:move@<=128)");
+    assert_eq!(e.rule_index, Some(1));
+
+    let e = rt.explain_text(10, "game", "jump");
+    assert_eq!(e.decision.reason(), DecisionReason::Allowlist);
+    assert_eq!(e.reason, "no matching rule; strict deny");
+    assert_eq!(e.rule_index, None);
+
+    let e = runtime(&[], &[]).explain_text(10, "chat", "send");
+    assert_eq!(e.reason, "ext_allowlist empty; strict deny");
+}
+
+#[test]
+fn explain_spends_no_rate_tokens() {
+    let rt = runtime(&["chat:send@1"], &[]);
+    for _ in 0..10 {
+        assert!(matches!(rt.explain_text(10, "chat", "send").decision, PolicyDecision::Pass));
+    }
+    // Tenant bucket (burst 1) and the @1 clause are both still full.
+    assert!(matches!(rt.check_text(10, "chat", "send"), PolicyDecision::Pass));
+    assert_eq!(rt.explain_text(10, "chat", "send").reason, "svc=chat type=send matched rule 0 (chat:send@1)");
+}
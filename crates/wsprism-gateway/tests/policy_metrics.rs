@@ -0,0 +1,66 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use wsprism_gateway::config::TenantPolicy;
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::policy::{DecisionReason, Lane, PolicyDecision, TenantPolicyRuntime};
+
+fn count(m: &GatewayMetrics, lane: &str, decision: &str, reason: &str) -> u64 {
+    m.policy_decisions.get(&[
+        ("tenant", "acme"),
+        ("lane", lane),
+        ("decision", decision),
+        ("reason", reason),
+    ])
+}
+
+#[test]
+fn scripted_frames_are_counted_by_structured_reason() {
+    let policy = TenantPolicy {
+        ext_allowlist: vec!["chat:*".into()],
+        ext_denylist: vec!["chat:spam".into()],
+        hot_allowlist: vec!["1:1-10".into()],
+        ..TenantPolicy::default()
+    };
+    let rt = TenantPolicyRuntime::new("acme".into(), 64, &policy)
+        .unwrap()
+        .with_guest_scopes(&["chat:read".to_string()])
+        .unwrap();
+    let m = GatewayMetrics::default();
+
+    // Ext lane
+    rt.evaluate_text(&m, 10, "chat", "send", false); // pass
+    rt.evaluate_text(&m, 10, "chat", "send", false); // pass
+    rt.evaluate_text(&m, 10, "chat", "spam", false); // denylist
+    rt.evaluate_text(&m, 10, "room", "kick", false); // allowlist
+    rt.evaluate_text(&m, 10, "chat", "send", true); // guest scope
+    rt.evaluate_text(&m, 10, "chat", "read", true); // guest pass
+    let d = rt.evaluate_text(&m, 65, "chat", "send", false); // too large
+    assert!(matches!(d, PolicyDecision::Close { reason: DecisionReason::Len, .. }));
+
+    // Hot lane
+    rt.evaluate_hot(&m, 10, 1, 5, false); // pass
+    rt.evaluate_hot(&m, 10, 1, 11, false); // allowlist
+    rt.evaluate_hot(&m, 10, 1, 5, true); // guest => scope
+
+    // Transport-side limiter outcomes go through the same path.
+    rt.record(&m, Lane::Ext, &PolicyDecision::Drop { reason: DecisionReason::Rate });
+
+    assert_eq!(count(&m, "ext", "pass", "none"), 3);
+    assert_eq!(count(&m, "ext", "reject", "denylist"), 1);
+    assert_eq!(count(&m, "ext", "reject", "allowlist"), 1);
+    assert_eq!(count(&m, "ext", "reject", "scope"), 1);
+    assert_eq!(count(&m, "ext", "close", "len"), 1);
+    assert_eq!(count(&m, "ext", "drop", "rate"), 1);
+    assert_eq!(count(&m, "hot", "pass", "none"), 1);
+    assert_eq!(count(&m, "hot", "drop", "allowlist"), 1);
+    assert_eq!(count(&m, "hot", "drop", "scope"), 1);
+
+    // Reason labels come from the fixed enum only.
+    let rendered = m.render(&[]);
+    for line in rendered.lines().filter(|l| l.starts_with("wsprism_policy_decisions_total")) {
+        let reason = line.split("reason=\"").nth(1).unwrap().split('"').next().unwrap();
+        assert!(["none", "len", "rate", "allowlist", "denylist", "scope"].contains(&reason), "{line}");
+    }
+}
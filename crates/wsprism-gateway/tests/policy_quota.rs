@@ -0,0 +1,224 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use futures_util::SinkExt;
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+
+use wsprism_gateway::config::schema::{QuotaSpec, QuotaWindow};
+use wsprism_gateway::policy::quota::{
+    compile_quota_rules, QuotaCounter, QuotaKey, QuotaRule, QuotaStore, QuotaTracker,
+};
+use wsprism_gateway::{config, router};
+
+use common::{connect, next_json, spawn_gateway};
+
+const HOUR: u64 = 3_600_000;
+
+fn rules(entries: &[(&str, u64, QuotaWindow)]) -> Vec<QuotaRule> {
+    let map: HashMap<String, QuotaSpec> = entries
+        .iter()
+        .map(|(p, limit, window)| (p.to_string(), QuotaSpec { limit: *limit, window: *window }))
+        .collect();
+    compile_quota_rules(&map).unwrap()
+}
+
+fn key(user: &str, pattern: &str) -> QuotaKey {
+    QuotaKey { tenant: "acme".into(), user: user.into(), pattern: pattern.into() }
+}
+
+#[test]
+fn quota_config_is_validated() {
+    let cfg = |quotas: &str| {
+        config::load_from_str(&format!(
+            "version: 1\ntenants:\n  - id: acme\n    policy:\n      ext_allowlist: [\"chat:*\"]\n      quotas: {quotas}\n"
+        ))
+    };
+    let ok = cfg(r#"{ "chat:send": { limit: 1000, window: "24h" }, "chat:*": { limit: 50, window: "1h" } }"#).unwrap();
+    let quotas = &ok.tenants[0].policy.quotas;
+    assert_eq!(quotas["chat:send"], QuotaSpec { limit: 1000, window: QuotaWindow::Day });
+    assert_eq!(quotas["chat:*"].window, QuotaWindow::Hour);
+
+    let err = cfg(r#"{ "chat:send": { limit: 0, window: "1h" } }"#).expect_err("zero limit");
+    assert!(err.to_string().contains("policy.quotas.chat:send"), "{err}");
+    assert!(cfg(r#"{ "chat:send": { limit: 1, window: "7d" } }"#).is_err());
+
+    let map = HashMap::from([("chat:send@10".to_string(), QuotaSpec { limit: 1, window: QuotaWindow::Hour })]);
+    assert!(compile_quota_rules(&map).is_err());
+}
+
+#[tokio::test]
+async fn quota_rejects_until_the_window_rolls_over() {
+    let rules = rules(&[("chat:send", 2, QuotaWindow::Hour)]);
+    let r: Vec<&QuotaRule> = rules.iter().collect();
+    let tracker = QuotaTracker::new();
+    let t0 = 1_000_000;
+
+    assert!(tracker.charge("acme", "alice", &r, t0).await.is_ok());
+    assert!(tracker.charge("acme", "alice", &r, t0 + 10).await.is_ok());
+    assert_eq!(tracker.charge("acme", "alice", &r, t0 + 20).await, Err(t0 + HOUR));
+    // Per user.
+    assert!(tracker.charge("acme", "bob", &r, t0 + 20).await.is_ok());
+
+    // Lazy reset on the first frame after the window.
+    assert!(tracker.charge("acme", "alice", &r, t0 + HOUR).await.is_ok());
+    let c = tracker.counter(&key("alice", "chat:send")).unwrap();
+    assert_eq!((c.window_start_ms, c.count), (t0 + HOUR, 1));
+}
+
+#[tokio::test]
+async fn matching_quotas_are_charged_all_or_nothing() {
+    let rules = rules(&[("chat:*", 10, QuotaWindow::Day), ("chat:send", 1, QuotaWindow::Hour)]);
+    let r: Vec<&QuotaRule> = rules.iter().filter(|q| q.matches("chat", "send")).collect();
+    assert_eq!(r.len(), 2);
+    let tracker = QuotaTracker::new();
+
+    assert!(tracker.charge("acme", "alice", &r, 0).await.is_ok());
+    assert_eq!(tracker.charge("acme", "alice", &r, 1).await, Err(HOUR));
+    // The rejected frame did not use up the broader quota.
+    assert_eq!(tracker.counter(&key("alice", "chat:*")).unwrap().count, 1);
+}
+
+#[tokio::test]
+async fn expired_counters_are_swept() {
+    let rules = rules(&[("chat:send", 5, QuotaWindow::Hour)]);
+    let r: Vec<&QuotaRule> = rules.iter().collect();
+    let tracker = QuotaTracker::new();
+    tracker.charge("acme", "alice", &r, 0).await.unwrap();
+    tracker.charge("acme", "bob", &r, HOUR / 2).await.unwrap();
+
+    assert_eq!(tracker.sweep_expired(HOUR), 1);
+    assert_eq!(tracker.len(), 1);
+    assert_eq!(tracker.snapshot()[0].user, "bob");
+}
+
+#[derive(Default)]
+struct MemStore {
+    saved: Mutex<HashMap<QuotaKey, QuotaCounter>>,
+}
+
+#[async_trait]
+impl QuotaStore for MemStore {
+    async fn load(&self, key: &QuotaKey) -> Option<QuotaCounter> {
+        self.saved.lock().unwrap().get(key).copied()
+    }
+    async fn save(&self, key: &QuotaKey, counter: &QuotaCounter) {
+        self.saved.lock().unwrap().insert(key.clone(), *counter);
+    }
+}
+
+#[tokio::test]
+async fn store_restores_counters_across_trackers() {
+    let rules = rules(&[("chat:send", 2, QuotaWindow::Day)]);
+    let r: Vec<&QuotaRule> = rules.iter().collect();
+    let store = Arc::new(MemStore::default());
+
+    let first = QuotaTracker::new().with_store(store.clone());
+    first.charge("acme", "alice", &r, 0).await.unwrap();
+    first.charge("acme", "alice", &r, 1).await.unwrap();
+    assert_eq!(store.saved.lock().unwrap()[&key("alice", "chat:send")].count, 2);
+
+    // e.g. after a restart: the persisted count still applies.
+    let second = QuotaTracker::new().with_store(store);
+    assert!(second.charge("acme", "alice", &r, 2).await.is_err());
+}
+
+const CFG: &str = r#"
+version: 1
+gateway:
+  admin_token: "s3cret"
+tenants:
+  - id: "acme"
+    policy:
+      strike_limit: 1
+      ext_allowlist: ["room:*"]
+      quotas:
+        "room:join": { limit: 2, window: "1h" }
+"#;
+
+#[tokio::test]
+async fn quota_exceeded_reaches_client_and_admin_stats() {
+    let (state, addr) = spawn_gateway(CFG).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+
+    assert_eq!(next_json(&mut ws).await["type"], "authed");
+
+    let mut replies = Vec::new();
+    for i in 0..4 {
+        let join = format!(r#"{{"svc":"room","type":"join","room":"r{i}"}}"#);
+        ws.send(Message::Text(join)).await.unwrap();
+        replies.push(next_json(&mut ws).await);
+    }
+    assert_eq!(replies[0]["type"], "joined");
+    assert_eq!(replies[1]["type"], "joined");
+    // Quota rejects do not count as strikes (strike_limit: 1), so the
+    // session stays open and keeps getting the same answer.
+    for r in &replies[2..] {
+        assert_eq!(r["data"]["code"], "QUOTA_EXCEEDED");
+        let retry = r["data"]["retry_after_ms"].as_u64().unwrap();
+        assert!(retry > 0 && retry <= HOUR, "{r}");
+        assert!(r["data"]["reset_at_unix_ms"].as_u64().unwrap() > retry);
+    }
+
+    let req = Request::get("/admin/v1/stats")
+        .header("authorization", "Bearer s3cret")
+        .body(Body::empty())
+        .unwrap();
+    let resp = router::build_router(state).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp: Value = serde_json::from_slice(&axum::body::to_bytes(resp.into_body(), 4096).await.unwrap()).unwrap();
+    assert_eq!(resp["sessions"], 1);
+    let q = &resp["quotas"][0];
+    assert_eq!((q["tenant"].as_str(), q["user"].as_str()), (Some("acme"), Some("user:dev")));
+    assert_eq!(q["pattern"], "room:join");
+    assert_eq!(q["count"], 2);
+}
+
+const DAILY_CFG: &str = r#"
+version: 1
+gateway:
+  admin_token: "s3cret"
+tenants:
+  - id: "acme"
+    limits:
+      max_messages_per_day: 2
+    policy:
+      ext_allowlist: ["room:*"]
+"#;
+
+#[tokio::test]
+async fn daily_message_limit_rejects_with_quota_exceeded() {
+    let (state, addr) = spawn_gateway(DAILY_CFG).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert_eq!(next_json(&mut ws).await["type"], "authed");
+
+    let mut replies = Vec::new();
+    for i in 0..3 {
+        let join = format!(r#"{{"svc":"room","type":"join","room":"r{i}"}}"#);
+        ws.send(Message::Text(join)).await.unwrap();
+        replies.push(next_json(&mut ws).await);
+    }
+    assert_eq!(replies[0]["type"], "joined");
+    assert_eq!(replies[1]["type"], "joined");
+    assert_eq!(replies[2]["data"]["code"], "QUOTA_EXCEEDED");
+
+    let req = Request::get("/admin/v1/stats")
+        .header("authorization", "Bearer s3cret")
+        .body(Body::empty())
+        .unwrap();
+    let resp = router::build_router(state).oneshot(req).await.unwrap();
+    let resp: Value = serde_json::from_slice(&axum::body::to_bytes(resp.into_body(), 4096).await.unwrap()).unwrap();
+    let q = &resp["quotas"][0];
+    assert_eq!(q["pattern"], wsprism_gateway::policy::quota::DAILY_MESSAGES_PATTERN);
+    assert_eq!(q["count"], 2);
+}
@@ -0,0 +1,130 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde_json::Value;
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::{AppState, StartupOptions};
+use wsprism_gateway::config;
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::policy::PolicyDecision;
+use wsprism_gateway::router::build_router;
+
+fn cfg_yaml(allow: &str, max_frame_bytes: usize) -> String {
+    format!(
+        r#"
+version: 1
+gateway:
+  admin_token: "s3cret"
+tenants:
+  - id: "acme"
+    limits:
+      max_frame_bytes: {max_frame_bytes}
+    policy:
+      ext_allowlist: [{allow}]
+  - id: "beta"
+    policy:
+      ext_allowlist: ["chat:*"]
+"#
+    )
+}
+
+fn passes(state: &AppState, msg_type: &str) -> bool {
+    let rt = state.tenant_policy("acme").unwrap();
+    let m = GatewayMetrics::default();
+    matches!(rt.evaluate_text(&m, 10, "chat", msg_type, false), PolicyDecision::Pass)
+}
+
+#[test]
+fn reload_swaps_policy_and_reports_changes() {
+    let state = AppState::new(config::load_from_str(&cfg_yaml(r#""chat:send""#, 4096)).unwrap()).unwrap();
+    let before = state.tenant_policy("acme").unwrap();
+    assert!(!passes(&state, "typing"));
+
+    let next = config::load_from_str(&cfg_yaml(r#""chat:send", "chat:typing""#, 8192)).unwrap();
+    let report = state.reload_policies(&next).unwrap();
+
+    assert!(passes(&state, "typing"));
+    assert!(!Arc::ptr_eq(&before, &state.tenant_policy("acme").unwrap()));
+    let acme = report.tenants.iter().find(|t| t.tenant == "acme").unwrap();
+    assert_eq!(acme.changed, vec!["policy.ext_allowlist"]);
+    assert_eq!(acme.restart_required, vec!["limits"]);
+    let beta = report.tenants.iter().find(|t| t.tenant == "beta").unwrap();
+    assert!(beta.changed.is_empty());
+}
+
+#[test]
+fn failed_reload_keeps_current_policies() {
+    let state = AppState::new(config::load_from_str(&cfg_yaml(r#""chat:send""#, 4096)).unwrap()).unwrap();
+    let before = state.tenant_policy("acme").unwrap();
+
+    // Unregistered service: the binding check rejects it.
+    let bad = config::load_from_str(&cfg_yaml(r#""nope:*""#, 4096)).unwrap();
+    assert!(state.reload_policies(&bad).is_err());
+
+    // Tenant set changes need a restart.
+    let mut fewer = config::load_from_str(&cfg_yaml(r#""chat:*""#, 4096)).unwrap();
+    fewer.tenants.retain(|t| t.id == "acme");
+    assert!(state.reload_policies(&fewer).is_err());
+
+    assert!(Arc::ptr_eq(&before, &state.tenant_policy("acme").unwrap()));
+    assert!(!passes(&state, "typing"));
+}
+
+#[test]
+fn update_tenant_policy_swaps_one_tenant() {
+    let state = AppState::new(config::load_from_str(&cfg_yaml(r#""chat:send""#, 4096)).unwrap()).unwrap();
+    let beta = state.tenant_policy("beta").unwrap();
+    let wider = AppState::new(config::load_from_str(&cfg_yaml(r#""chat:*""#, 4096)).unwrap()).unwrap();
+    let next = wider.tenant_policy("acme").unwrap();
+
+    let prev = state.update_tenant_policy("acme", Arc::clone(&next)).unwrap();
+    assert!(!prev.is_suspended());
+    assert!(passes(&state, "typing"));
+    assert!(Arc::ptr_eq(&next, &state.tenant_policy("acme").unwrap()));
+    assert!(Arc::ptr_eq(&beta, &state.tenant_policy("beta").unwrap()));
+
+    assert!(state.update_tenant_policy("nope", next).is_err());
+}
+
+#[tokio::test]
+async fn admin_reload_rereads_config_file() {
+    let path = std::env::temp_dir().join(format!("wsprism-reload-{}.yaml", std::process::id()));
+    std::fs::write(&path, cfg_yaml(r#""chat:send""#, 4096)).unwrap();
+    let opts = StartupOptions {
+        config_path: Some(path.to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+    let state = AppState::new_with_options(config::load_from_file(path.to_str().unwrap()).unwrap(), opts).unwrap();
+    let app = build_router(state.clone());
+    let reload = || {
+        Request::post("/admin/v1/reload")
+            .header("authorization", "Bearer s3cret")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    std::fs::write(&path, "version: 1\ntenants: [").unwrap();
+    let resp = app.clone().oneshot(reload()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(resp.into_body(), 4096).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"], "reload_failed");
+    assert!(!passes(&state, "typing"));
+
+    std::fs::write(&path, cfg_yaml(r#""chat:*""#, 4096)).unwrap();
+    let resp = app.oneshot(reload()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), 4096).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let acme = body["tenants"].as_array().unwrap().iter().find(|t| t["tenant"] == "acme").unwrap();
+    assert_eq!(acme["changed"][0], "policy.ext_allowlist");
+    assert!(passes(&state, "typing"));
+
+    let _ = std::fs::remove_file(&path);
+}
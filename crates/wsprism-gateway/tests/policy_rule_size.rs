@@ -0,0 +1,83 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use wsprism_gateway::config::{self, TenantPolicy};
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::policy::{DecisionReason, PolicyDecision, TenantPolicyRuntime};
+
+fn runtime(ext: &[&str], hot: &[&str]) -> TenantPolicyRuntime {
+    let policy = TenantPolicy {
+        ext_allowlist: ext.iter().map(|s| s.to_string()).collect(),
+        hot_allowlist: hot.iter().map(|s| s.to_string()).collect(),
+        ..TenantPolicy::default()
+    };
+    TenantPolicyRuntime::new("acme".into(), 65_536, &policy).unwrap()
+}
+
+#[test]
+fn size_clause_caps_matching_entry_only() {
+    let rt = runtime(&["chat:send@<=2048", "room:*"], &["1:1@<=256", "1:2"]);
+
+    assert!(matches!(rt.check_text(2048, "chat", "send"), PolicyDecision::Pass));
+    match rt.check_text(2049, "chat", "send") {
+        PolicyDecision::Reject { code, reason, .. } => {
+            assert_eq!(code.as_str(), "PAYLOAD_TOO_LARGE");
+            assert_eq!(reason, DecisionReason::RuleSize);
+        }
+        other => panic!("expected reject, got {other:?}"),
+    }
+    // Other entries fall back to max_frame_bytes.
+    assert!(matches!(rt.check_text(60_000, "room", "snapshot"), PolicyDecision::Pass));
+
+    assert!(matches!(rt.check_hot(256, 1, 1), PolicyDecision::Pass));
+    assert!(matches!(rt.check_hot(257, 1, 1), PolicyDecision::Drop { reason: DecisionReason::RuleSize }));
+    assert!(matches!(rt.check_hot(4096, 1, 2), PolicyDecision::Pass));
+
+    let m = GatewayMetrics::default();
+    rt.evaluate_text(&m, 4096, "chat", "send", false);
+    let labels = [("tenant", "acme"), ("lane", "ext"), ("decision", "reject"), ("reason", "rule_size")];
+    assert_eq!(m.policy_decisions.get(&labels), 1);
+}
+
+#[test]
+fn size_and_rate_clauses_combine_in_either_order() {
+    for entry in ["chat:send@<=100@3", "chat:send@3@<=100"] {
+        let rt = runtime(&[entry], &[]);
+        assert!(matches!(rt.check_text(101, "chat", "send"), PolicyDecision::Reject { .. }), "{entry}");
+        // Oversized frames do not spend rate tokens.
+        let passed = (0..10).filter(|_| matches!(rt.check_text(10, "chat", "send"), PolicyDecision::Pass)).count();
+        assert_eq!(passed, 3, "{entry}");
+        assert!(matches!(rt.check_text(10, "chat", "send"), PolicyDecision::Reject { reason: DecisionReason::Rate, .. }));
+    }
+
+    let rt = runtime(&[], &["1:1-9@<=64@2"]);
+    assert!(matches!(rt.check_hot(65, 1, 5), PolicyDecision::Drop { reason: DecisionReason::RuleSize }));
+    assert!(matches!(rt.check_hot(64, 1, 5), PolicyDecision::Pass));
+}
+
+#[test]
+fn malformed_clauses_fail_config_validation() {
+    let yaml = |field: &str, entry: &str| {
+        format!("version: 1\ntenants:\n  - id: acme\n    policy:\n      {field}: [\"{entry}\"]\n")
+    };
+    let bad = [
+        ("ext_allowlist", "chat:send@<="),
+        ("ext_allowlist", "chat:send@<=0"),
+        ("ext_allowlist", "chat:send@<=abc"),
+        ("ext_allowlist", "chat:send@<=10@<=20"),
+        ("ext_allowlist", "chat:send@5@6"),
+        ("ext_allowlist", "chat:send@"),
+        ("ext_allowlist", "chat:send@>=10"),
+        ("hot_allowlist", "1:1@<=-1"),
+        ("ext_denylist", "chat:send@<=100"),
+    ];
+    for (field, entry) in bad {
+        let cfg = config::load_from_str(&yaml(field, entry)).unwrap();
+        let err = wsprism_gateway::app_state::AppState::new(cfg).err();
+        assert!(err.is_some(), "{field}: {entry} must be rejected");
+    }
+
+    let ok = config::load_from_str(&yaml("ext_allowlist", "room:join@<=512@10")).unwrap();
+    assert!(wsprism_gateway::app_state::AppState::new(ok).is_ok());
+}
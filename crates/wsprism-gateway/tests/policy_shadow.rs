@@ -0,0 +1,222 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::audit::{AuditEvent, AuditSink};
+use wsprism_gateway::config::schema::PolicyMode;
+use wsprism_gateway::config::{self, TenantPolicy};
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::policy::{DecisionReason, PolicyDecision, TenantPolicyRuntime};
+
+use common::{connect, next_text, spawn_gateway};
+
+fn shadow_count(m: &GatewayMetrics, lane: &str, decision: &str, reason: &str) -> u64 {
+    m.policy_decisions.get(&[
+        ("tenant", "acme"),
+        ("lane", lane),
+        ("decision", decision),
+        ("reason", reason),
+        ("mode", "shadow"),
+    ])
+}
+
+#[test]
+fn shadow_mode_passes_but_counts_would_be_decisions() {
+    let policy = TenantPolicy {
+        mode: Some(PolicyMode::Shadow),
+        ext_allowlist: vec!["chat:send".into()],
+        hot_allowlist: vec!["1:1".into()],
+        ..TenantPolicy::default()
+    };
+    let rt = TenantPolicyRuntime::new("acme".into(), 64, &policy)
+        .unwrap()
+        .with_guest_scopes(&["chat:send".to_string()])
+        .unwrap();
+    let m = GatewayMetrics::default();
+
+    assert!(matches!(rt.evaluate_text(&m, 10, "chat", "typing", false), PolicyDecision::Pass));
+    assert!(matches!(rt.evaluate_hot(&m, 10, 1, 2, false), PolicyDecision::Pass));
+    assert_eq!(shadow_count(&m, "ext", "reject", "allowlist"), 1);
+    assert_eq!(shadow_count(&m, "hot", "drop", "allowlist"), 1);
+
+    // Length and guest scope are enforced regardless.
+    let d = rt.evaluate_text(&m, 65, "chat", "send", false);
+    assert!(matches!(d, PolicyDecision::Close { reason: DecisionReason::Len, .. }));
+    let d = rt.evaluate_text(&m, 10, "chat", "typing", true);
+    assert!(matches!(d, PolicyDecision::Reject { reason: DecisionReason::Scope, .. }));
+    let d = rt.evaluate_hot(&m, 10, 1, 1, true);
+    assert!(matches!(d, PolicyDecision::Drop { reason: DecisionReason::Scope }));
+}
+
+#[test]
+fn shadow_mode_counts_would_be_rejects_as_dryrun_violations() {
+    let violations = |m: &GatewayMetrics, lane: &str, decision: &str| {
+        m.policy_dryrun_violations.get(&[("tenant", "acme"), ("lane", lane), ("decision", decision)])
+    };
+    let policy = |mode| TenantPolicy {
+        mode: Some(mode),
+        ext_allowlist: vec!["chat:send".into()],
+        hot_allowlist: vec!["1:1".into()],
+        ..TenantPolicy::default()
+    };
+
+    let rt = TenantPolicyRuntime::new("acme".into(), 64, &policy(PolicyMode::Shadow)).unwrap();
+    let m = GatewayMetrics::default();
+    for _ in 0..3 {
+        assert!(matches!(rt.evaluate_text(&m, 10, "chat", "typing", false), PolicyDecision::Pass));
+    }
+    assert!(matches!(rt.evaluate_text(&m, 10, "chat", "send", false), PolicyDecision::Pass));
+    assert!(matches!(rt.evaluate_hot(&m, 10, 1, 2, false), PolicyDecision::Pass));
+    assert_eq!(violations(&m, "ext", "reject"), 3);
+    // Would-be drops are not violations the client would have seen.
+    assert_eq!(violations(&m, "hot", "drop"), 0);
+
+    let rt = TenantPolicyRuntime::new("acme".into(), 64, &policy(PolicyMode::Enforce)).unwrap();
+    let m = GatewayMetrics::default();
+    assert!(matches!(rt.evaluate_text(&m, 10, "chat", "typing", false), PolicyDecision::Reject { .. }));
+    assert_eq!(violations(&m, "ext", "reject"), 0);
+}
+
+#[derive(Default)]
+struct Collect(Mutex<Vec<AuditEvent>>);
+
+impl AuditSink for Collect {
+    fn record(&self, event: AuditEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn shadowed_rejects_are_audited() {
+    let policy = TenantPolicy { dry_run: true, ext_allowlist: vec!["chat:send".into()], ..TenantPolicy::default() };
+    let sink = Arc::new(Collect::default());
+    let rt = TenantPolicyRuntime::new("acme".into(), 64, &policy).unwrap().with_audit(sink.clone());
+    let m = GatewayMetrics::default();
+    assert!(matches!(rt.evaluate_text(&m, 10, "chat", "typing", false), PolicyDecision::Pass));
+    assert!(matches!(rt.evaluate_text(&m, 10, "chat", "send", false), PolicyDecision::Pass));
+
+    let events = sink.0.lock().unwrap().clone();
+    let [AuditEvent::PolicyShadowed(r)] = events.as_slice() else { panic!("{events:?}") };
+    assert_eq!((r.tenant.as_str(), r.user.as_deref(), r.reason.as_str()), ("acme", None, "ext reject (allowlist)"));
+}
+
+#[test]
+fn dry_run_is_shorthand_for_shadow_mode() {
+    let load = |policy: &str| config::load_from_str(&format!("version: 1\ntenants:\n  - id: acme\n    policy: {{ {policy} }}\n"));
+    let mode = |policy: &str| load(policy).unwrap().tenants[0].policy.effective_mode();
+    assert_eq!(mode("dry_run: true"), PolicyMode::Shadow);
+    assert_eq!(mode("dry_run: true, mode: shadow"), PolicyMode::Shadow);
+    assert_eq!(mode("dry_run: false"), PolicyMode::Enforce);
+    assert_eq!(mode("mode: shadow"), PolicyMode::Shadow);
+
+    let err = load("dry_run: true, mode: enforce").unwrap_err();
+    assert!(err.to_string().contains("conflicts"), "{err}");
+}
+
+fn cfg_yaml(mode: &str) -> String {
+    format!(
+        r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      mode: {mode}
+      ext_allowlist: ["room:join"]
+"#
+    )
+}
+
+#[tokio::test]
+async fn shadow_rejected_messages_are_dispatched_until_flipped_to_enforce() {
+    let (state, addr) = spawn_gateway(&cfg_yaml("shadow")).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert!(next_text(&mut ws).await.contains("authed"));
+
+    let join = r#"{"v":1,"svc":"room","type":"join","room":"lobby"}"#;
+    ws.send(Message::Text(join.into())).await.unwrap();
+    assert!(next_text(&mut ws).await.contains("joined"));
+
+    // chat:send is not allowlisted, but shadow mode lets it through.
+    let send = r#"{"v":1,"svc":"chat","type":"send","room":"lobby","data":{"msg":"hi"}}"#;
+    ws.send(Message::Text(send.into())).await.unwrap();
+    let got = next_text(&mut ws).await;
+    assert!(got.contains(r#""type":"msg""#), "dispatched to the room: {got}");
+    assert_eq!(shadow_count(&state.metrics(), "ext", "reject", "allowlist"), 1);
+
+    // Flip to enforce without a restart.
+    let report = state.reload_policies(&config::load_from_str(&cfg_yaml("enforce")).unwrap()).unwrap();
+    assert_eq!(report.tenants[0].changed, vec!["policy.mode"]);
+
+    ws.send(Message::Text(send.into())).await.unwrap();
+    let got = next_text(&mut ws).await;
+    assert!(got.contains(r#""type":"error""#), "rejected once enforced: {got}");
+    assert_eq!(shadow_count(&state.metrics(), "ext", "reject", "allowlist"), 1);
+}
+
+#[tokio::test]
+async fn dry_run_lets_connection_rate_limited_frames_through() {
+    let cfg = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      dry_run: true
+      rate_limit_rps: 1
+      rate_limit_burst: 1
+      ext_allowlist: ["room:*", "chat:*"]
+"#;
+    let (state, addr) = spawn_gateway(cfg).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert!(next_text(&mut ws).await.contains("authed"));
+
+    let join = r#"{"v":1,"svc":"room","type":"join","room":"lobby"}"#;
+    ws.send(Message::Text(join.into())).await.unwrap();
+    assert!(next_text(&mut ws).await.contains("joined"));
+
+    // The join took the only token; every send is over the limit.
+    let send = r#"{"v":1,"svc":"chat","type":"send","room":"lobby","data":{"msg":"hi"}}"#;
+    for _ in 0..3 {
+        ws.send(Message::Text(send.into())).await.unwrap();
+        let got = next_text(&mut ws).await;
+        assert!(got.contains(r#""type":"msg""#), "dispatched: {got}");
+    }
+    let m = state.metrics();
+    assert_eq!(shadow_count(&m, "ext", "reject", "rate"), 3);
+    assert_eq!(m.policy_dryrun_violations.get(&[("tenant", "acme"), ("lane", "ext"), ("decision", "reject")]), 3);
+}
+
+#[tokio::test]
+async fn shadow_mode_passes_rate_limited_frames_without_striking() {
+    let limits = [
+        "rate_limit_rps: 1\n      rate_limit_burst: 1",
+        "room_rate_limit_rps: 1",
+        "per_session_bytes_per_sec: 1",
+    ];
+    for limit in limits {
+        let cfg = format!(
+            "version: 1\ntenants:\n  - id: acme\n    limits: {{ max_frame_bytes: 256 }}\n    policy:\n      mode: shadow\n      strike_limit: 1\n      {limit}\n      ext_allowlist: [\"room:*\", \"chat:*\"]\n"
+        );
+        let (state, addr) = spawn_gateway(&cfg).await;
+        let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+        assert!(next_text(&mut ws).await.contains("authed"));
+        let join = r#"{"v":1,"svc":"room","type":"join","room":"lobby"}"#;
+        ws.send(Message::Text(join.into())).await.unwrap();
+        assert!(next_text(&mut ws).await.contains("joined"), "{limit}");
+
+        // Every send is over the limit; none is rejected and no strike closes the session.
+        let send = r#"{"v":1,"svc":"chat","type":"send","room":"lobby","data":{"msg":"hi"}}"#;
+        for _ in 0..5 {
+            ws.send(Message::Text(send.into())).await.unwrap();
+            let got = next_text(&mut ws).await;
+            assert!(got.contains(r#""type":"msg""#), "{limit}: {got}");
+        }
+        assert!(shadow_count(&state.metrics(), "ext", "reject", "rate") >= 1, "{limit}");
+    }
+}
@@ -0,0 +1,185 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::Message as AxumMessage;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::config::{self, TenantLimits};
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{PresenceNotifier, RealtimeCore};
+
+use common::{next_json, spawn_gateway};
+
+struct Member {
+    id: ConnectionId,
+    user_key: String,
+    rx: mpsc::Receiver<AxumMessage>,
+}
+
+fn connect(core: &RealtimeCore, tenant: &str, user: &str) -> Member {
+    let (tx, rx) = mpsc::channel(16);
+    let id = ConnectionId::new();
+    let user_key = format!("{tenant}::{user}");
+    core.sessions.try_insert(tenant.into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0).unwrap();
+    Member { id, user_key, rx }
+}
+
+impl Member {
+    fn join(&self, core: &RealtimeCore, room: &str) {
+        let (tenant, _) = self.user_key.split_once("::").unwrap();
+        core.presence.try_join(tenant, &format!("{tenant}::{room}"), &self.user_key, self.id, &TenantLimits::default()).unwrap();
+    }
+
+    fn leave(&self, core: &RealtimeCore, room: &str) {
+        let (tenant, _) = self.user_key.split_once("::").unwrap();
+        core.presence.leave(tenant, &format!("{tenant}::{room}"), &self.user_key, self.id);
+    }
+
+    /// `(event, user, member_count)` of each queued `sys.presence`.
+    fn events(&mut self) -> Vec<(String, String, u64)> {
+        let mut out = Vec::new();
+        while let Ok(msg) = self.rx.try_recv() {
+            let AxumMessage::Text(t) = msg else { panic!("unexpected {msg:?}") };
+            let v: Value = serde_json::from_str(&t).unwrap();
+            assert_eq!((&v["svc"], &v["type"]), (&json!("sys"), &json!("presence")), "{v}");
+            let d = &v["data"];
+            out.push((d["event"].as_str().unwrap().into(), d["user"].as_str().unwrap().into(), d["member_count"].as_u64().unwrap()));
+        }
+        out
+    }
+}
+
+fn core_with_notifier() -> Arc<RealtimeCore> {
+    let core = Arc::new(RealtimeCore::new());
+    let notifier = PresenceNotifier::new(Arc::downgrade(&core), ["acme".to_string()]).with_coalesce_window(Duration::from_secs(1));
+    core.presence.set_notifier(Some(Arc::new(notifier)));
+    core
+}
+
+fn ev(event: &str, user: &str, n: u64) -> (String, String, u64) {
+    (event.into(), user.into(), n)
+}
+
+#[tokio::test(start_paused = true)]
+async fn joins_and_leaves_reach_the_other_members() {
+    let core = core_with_notifier();
+    let mut alice = connect(&core, "acme", "alice");
+    let mut bob = connect(&core, "acme", "bob");
+    alice.join(&core, "lobby");
+    bob.join(&core, "lobby");
+    assert_eq!(alice.events(), [ev("join", "bob", 2)]);
+    assert!(bob.events().is_empty(), "the subject is not told");
+
+    // Leaves wait for the coalesce window.
+    bob.leave(&core, "lobby");
+    assert!(alice.events().is_empty());
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(alice.events(), [ev("leave", "bob", 1)]);
+
+    // A second session of a member is not a join.
+    let bob2 = connect(&core, "acme", "bob");
+    bob.join(&core, "lobby");
+    bob2.join(&core, "lobby");
+    assert_eq!(alice.events(), [ev("join", "bob", 2)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn flapping_is_coalesced_into_one_pair() {
+    let core = core_with_notifier();
+    let mut alice = connect(&core, "acme", "alice");
+    let bob = connect(&core, "acme", "bob");
+    alice.join(&core, "lobby");
+
+    bob.join(&core, "lobby");
+    for _ in 0..5 {
+        bob.leave(&core, "lobby");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        bob.join(&core, "lobby");
+    }
+    bob.leave(&core, "lobby");
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(alice.events(), [ev("join", "bob", 2), ev("leave", "bob", 1)]);
+
+    // Rejoining within the window cancels the leave outright.
+    bob.join(&core, "lobby");
+    bob.leave(&core, "lobby");
+    bob.join(&core, "lobby");
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(alice.events(), [ev("join", "bob", 2)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn disconnect_leaves_every_room_and_other_tenants_are_quiet() {
+    let core = core_with_notifier();
+    let mut alice = connect(&core, "acme", "alice");
+    let bob = connect(&core, "acme", "bob");
+    for room in ["a", "b"] {
+        alice.join(&core, room);
+        bob.join(&core, room);
+    }
+    alice.events();
+
+    core.sessions.unregister(bob.id);
+    core.presence.cleanup_session("acme", &bob.user_key, bob.id);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(alice.events(), [ev("leave", "bob", 1), ev("leave", "bob", 1)]);
+
+    let mut carol = connect(&core, "globex", "carol");
+    let dave = connect(&core, "globex", "dave");
+    carol.join(&core, "lobby");
+    dave.join(&core, "lobby");
+    dave.leave(&core, "lobby");
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(carol.events().is_empty());
+}
+
+#[tokio::test]
+async fn room_service_joins_and_disconnects_are_announced() {
+    let cfg = r#"
+version: 1
+tenants:
+  - id: "acme"
+    presence_events: true
+    allow_guest: true
+    guest_scopes: ["room:*"]
+    policy:
+      ext_allowlist: ["room:*"]
+"#;
+    let (_, addr) = spawn_gateway(cfg).await;
+    let mut users = Vec::new();
+    for _ in 0..2 {
+        let mut ws = common::connect(addr, "tenant=acme").await;
+        let authed = next_json(&mut ws).await;
+        let user = authed["data"]["user"].as_str().unwrap().to_string();
+        ws.send(Message::Text(r#"{"v":1,"svc":"room","type":"join","room":"lobby"}"#.into())).await.unwrap();
+        assert_eq!(next_json(&mut ws).await["type"], "joined");
+        users.push((ws, user));
+    }
+    let (mut second, second_user) = users.pop().unwrap();
+    let (mut first, _) = users.pop().unwrap();
+
+    let joined = next_json(&mut first).await;
+    assert_eq!(joined["type"], "presence");
+    assert_eq!(joined["room"], "lobby");
+    assert_eq!(joined["data"], json!({ "event": "join", "user": second_user, "member_count": 2 }));
+
+    second.close(None).await.unwrap();
+    let left = next_json(&mut first).await;
+    assert_eq!(left["data"], json!({ "event": "leave", "user": second_user, "member_count": 1 }));
+}
+
+#[test]
+fn presence_events_is_off_by_default() {
+    let cfg = config::load_from_str("version: 1\ntenants:\n  - id: acme\n").unwrap();
+    assert!(!cfg.tenants[0].presence_events);
+}
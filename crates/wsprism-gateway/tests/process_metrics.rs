@@ -0,0 +1,41 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::obs::metrics::{BUILD_GIT_SHA, BUILD_VERSION};
+use wsprism_gateway::{config, router};
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+"#;
+
+#[test]
+fn build_info_is_populated() {
+    assert_eq!(BUILD_VERSION, env!("CARGO_PKG_VERSION"));
+    assert!(!BUILD_GIT_SHA.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn process_lines_are_present_without_traffic() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let req = Request::get("/metrics").body(Body::empty()).unwrap();
+    let resp = router::build_router(state).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), 1 << 20).await.unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+
+    let build_info = text.lines().find(|l| l.starts_with("wsprism_build_info{")).unwrap();
+    assert!(build_info.contains(&format!("version=\"{BUILD_VERSION}\"")), "{build_info}");
+    assert!(build_info.contains(&format!("git_sha=\"{BUILD_GIT_SHA}\"")), "{build_info}");
+    assert!(build_info.ends_with("} 1"));
+    for line in ["wsprism_uptime_seconds 0", "wsprism_sessions_open 0", "wsprism_tokio_worker_threads 2"] {
+        assert!(text.lines().any(|l| l == line), "missing {line}:\n{text}");
+    }
+}
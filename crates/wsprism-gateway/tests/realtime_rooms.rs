@@ -0,0 +1,187 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::realtime::{PresenceEvent, RealtimeCore, RealtimeCtx};
+
+fn ctx(core: &Arc<RealtimeCore>, user: &str) -> RealtimeCtx {
+    RealtimeCtx::new("acme", user, ConnectionId::new(), "trace", None, core.clone())
+}
+
+#[test]
+fn rooms_tracks_user_membership_across_sessions() {
+    let core = Arc::new(RealtimeCore::new());
+    let limits = TenantLimits::default();
+
+    let a1 = ctx(&core, "alice");
+    let a2 = ctx(&core, "alice");
+    a1.join_room_with_limits("lobby", &limits).unwrap();
+    a2.join_room_with_limits("match:1", &limits).unwrap();
+
+    let mut rooms = a1.rooms();
+    rooms.sort();
+    assert_eq!(rooms, vec!["lobby".to_string(), "match:1".to_string()]);
+    assert!(a2.is_in_room("lobby"));
+    assert!(!ctx(&core, "bob").is_in_room("lobby"));
+
+    a1.leave_room("lobby");
+    assert!(!a1.is_in_room("lobby"));
+    assert_eq!(a1.rooms(), vec!["match:1".to_string()]);
+}
+
+#[test]
+fn session_room_limit_allows_exactly_the_limit() {
+    let core = Arc::new(RealtimeCore::new());
+    let limits = TenantLimits { max_rooms_per_session: 2, ..TenantLimits::default() };
+    let a = ctx(&core, "alice");
+
+    a.join_room_with_limits("r1", &limits).unwrap();
+    a.join_room_with_limits("r2", &limits).unwrap();
+    let err = a.join_room_with_limits("r3", &limits).expect_err("over session limit");
+    assert_eq!(err.client_code().as_str(), "NOT_ALLOWED");
+    assert!(err.to_string().contains("max_rooms_per_session=2"));
+
+    // Rejoining does not count twice, and leaving frees a slot.
+    a.join_room_with_limits("r2", &limits).unwrap();
+    assert_eq!(core.presence.session_room_count(a.connection_id()), 2);
+    a.leave_room("r1");
+    a.join_room_with_limits("r3", &limits).unwrap();
+
+    // The limit is per connection, not per user.
+    ctx(&core, "alice").join_room_with_limits("r4", &limits).unwrap();
+}
+
+#[test]
+fn user_room_limit_counts_rooms_across_sessions() {
+    let core = Arc::new(RealtimeCore::new());
+    let limits = TenantLimits { max_rooms_per_user: 2, ..TenantLimits::default() };
+
+    let a1 = ctx(&core, "alice");
+    let a2 = ctx(&core, "alice");
+    a1.join_room_with_limits("lobby", &limits).unwrap();
+    a2.join_room_with_limits("match:1", &limits).unwrap();
+    assert_eq!(core.presence.user_room_count("acme::alice"), 2);
+    assert_eq!(core.presence.user_room_count("acme::bob"), 0);
+
+    assert!(a1.join_room_with_limits("match:2", &limits).is_err());
+    // Joining a room the user is already in (from another session) is not a new room.
+    a1.join_room_with_limits("match:1", &limits).unwrap();
+    assert_eq!(core.presence.user_room_count("acme::alice"), 2);
+}
+
+#[test]
+fn tenant_room_limit_counts_active_rooms() {
+    let core = Arc::new(RealtimeCore::new());
+    let limits = TenantLimits { max_rooms_total: 2, ..TenantLimits::default() };
+    let (a, b) = (ctx(&core, "alice"), ctx(&core, "bob"));
+
+    a.join_room_with_limits("r1", &limits).unwrap();
+    b.join_room_with_limits("r2", &limits).unwrap();
+    assert_eq!(core.presence.tenant_room_count("acme"), 2);
+
+    // Existing rooms stay joinable at the limit; new ones do not.
+    b.join_room_with_limits("r1", &limits).unwrap();
+    a.join_room_with_limits("r1", &limits).unwrap();
+    assert_eq!(core.presence.tenant_room_count("acme"), 2);
+    let err = a.join_room_with_limits("r3", &limits).expect_err("over tenant limit");
+    assert_eq!(err.client_code().as_str(), "NOT_ALLOWED");
+
+    // A room is released once its last session leaves.
+    b.leave_room("r2");
+    assert_eq!(core.presence.tenant_room_count("acme"), 1);
+    a.join_room_with_limits("r3", &limits).unwrap();
+
+    a.leave_room("r1");
+    b.leave_room("r1");
+    a.leave_room("r3");
+    assert_eq!(core.presence.tenant_room_count("acme"), 0);
+}
+
+#[test]
+fn max_rooms_per_tenant_is_accepted_as_config_alias() {
+    let cfg = wsprism_gateway::config::load_from_str(
+        "version: 1\ntenants:\n  - id: acme\n    limits: { max_rooms_per_tenant: 7, max_rooms_per_session: 3 }\n",
+    )
+    .unwrap();
+    assert_eq!(cfg.tenants[0].limits.max_rooms_total, 7);
+    assert_eq!(cfg.tenants[0].limits.max_rooms_per_session, 3);
+}
+
+#[test]
+fn move_room_is_a_single_transition() {
+    let core = Arc::new(RealtimeCore::new());
+    let limits = TenantLimits::default();
+    let a = ctx(&core, "alice");
+
+    let err = a.move_room_with_limits("lobby", "match:1", &limits).expect_err("not in lobby");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+
+    a.join_room_with_limits("lobby", &limits).unwrap();
+    let ev = a.move_room_with_limits("lobby", "match:1", &limits).unwrap();
+    assert_eq!(ev, PresenceEvent::UserMoved {
+        user: "acme::alice".into(),
+        from: "acme::lobby".into(),
+        to: "acme::match:1".into(),
+    });
+    assert_eq!(a.rooms(), vec!["match:1".to_string()]);
+    assert!(core.presence.sessions_in("acme::lobby").is_empty());
+    assert_eq!(core.presence.sessions_in("acme::match:1"), vec![a.connection_id()]);
+    assert_eq!(core.presence.tenant_room_count("acme"), 1);
+
+    // Moving into a room already joined is refused and changes nothing.
+    a.join_room_with_limits("lobby", &limits).unwrap();
+    let err = a.move_room_with_limits("lobby", "match:1", &limits).expect_err("already in target");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+    assert_eq!(core.presence.session_room_count(a.connection_id()), 2);
+}
+
+#[test]
+fn move_room_respects_target_capacity() {
+    let core = Arc::new(RealtimeCore::new());
+    let limits = TenantLimits { max_users_per_room: 1, ..TenantLimits::default() };
+    let a = ctx(&core, "alice");
+    let b = ctx(&core, "bob");
+    a.join_room_with_limits("lobby", &limits).unwrap();
+    b.join_room_with_limits("match:1", &limits).unwrap();
+
+    a.move_room_with_limits("lobby", "match:1", &limits).expect_err("match:1 is full");
+    assert!(a.is_in_room("lobby"));
+    assert!(!a.is_in_room("match:1"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_moves_keep_presence_consistent() {
+    let core = Arc::new(RealtimeCore::new());
+    let limits = TenantLimits::default();
+    let a = Arc::new(ctx(&core, "alice"));
+    a.join_room_with_limits("r0", &limits).unwrap();
+
+    let mut tasks = Vec::new();
+    for i in 0..10 {
+        let (a, limits) = (a.clone(), limits.clone());
+        tasks.push(tokio::spawn(async move {
+            for n in 0..200 {
+                let from = a.rooms().into_iter().next();
+                let to = format!("r{}", (i + n) % 3);
+                if let Some(from) = from {
+                    // Losing a race to another task is expected; corruption is not.
+                    let _ = a.move_room_with_limits(&from, &to, &limits);
+                }
+                tokio::task::yield_now().await;
+            }
+        }));
+    }
+    for t in tasks {
+        t.await.unwrap();
+    }
+
+    assert_eq!(core.presence.session_room_count(a.connection_id()), 1);
+    let rooms = a.rooms();
+    assert_eq!(rooms.len(), 1);
+    assert_eq!(core.presence.tenant_room_count("acme"), 1);
+    assert_eq!(core.presence.sessions_in(&format!("acme::{}", rooms[0])), vec![a.connection_id()]);
+}
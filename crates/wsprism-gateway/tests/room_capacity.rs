@@ -0,0 +1,134 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::sync::{Arc, Barrier};
+
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_core::error::ClientCode;
+use wsprism_gateway::config::{self, TenantLimits};
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::realtime::Presence;
+
+use common::{connect, next_json, spawn_gateway};
+
+fn limits(room_capacity: u64) -> TenantLimits {
+    TenantLimits { room_capacity, ..TenantLimits::default() }
+}
+
+fn join(p: &Presence, user: &str, limits: &TenantLimits, capacity: Option<u64>) -> Result<ConnectionId, ClientCode> {
+    let id = ConnectionId::new();
+    p.try_join_with_capacity("acme", "acme::lobby", &format!("acme::{user}"), id, limits, capacity)
+        .map(|()| id)
+        .map_err(|e| e.client_code())
+}
+
+#[test]
+fn joins_over_capacity_are_refused_without_changing_presence() {
+    let p = Presence::new();
+    let l = limits(2);
+    join(&p, "a", &l, None).unwrap();
+    join(&p, "b", &l, None).unwrap();
+    assert_eq!(join(&p, "c", &l, None), Err(ClientCode::RoomFull));
+    assert_eq!(p.users_in("acme::lobby"), ["acme::a", "acme::b"]);
+    assert!(p.rooms_of("acme::c").is_empty());
+
+    // Another session of a member is not a new user.
+    join(&p, "a", &l, None).unwrap();
+    assert_eq!(p.sessions_in("acme::lobby").len(), 3);
+
+    // Moving into a full room is refused too.
+    let c = ConnectionId::new();
+    p.try_join("acme", "acme::side", "acme::c", c, &l).unwrap();
+    let err = p.move_user("acme", "acme::c", c, "acme::side", "acme::lobby", &l).unwrap_err();
+    assert_eq!(err.client_code(), ClientCode::RoomFull);
+    assert_eq!(p.users_in("acme::side"), ["acme::c"]);
+}
+
+#[test]
+fn first_joiner_sets_the_capacity_until_the_room_empties() {
+    let p = Presence::new();
+    let unlimited = limits(0);
+    let a = join(&p, "a", &unlimited, Some(1)).unwrap();
+    assert_eq!(p.room_capacity_override("acme::lobby"), Some(1));
+    // Later joiners cannot change it.
+    assert_eq!(join(&p, "b", &unlimited, Some(5)), Err(ClientCode::RoomFull));
+
+    p.leave("acme", "acme::lobby", "acme::a", a);
+    assert_eq!(p.room_capacity_override("acme::lobby"), None);
+    join(&p, "b", &unlimited, None).unwrap();
+    join(&p, "c", &unlimited, None).unwrap();
+
+    // The override cannot raise the tenant limit.
+    let p = Presence::new();
+    join(&p, "a", &limits(2), Some(10)).unwrap();
+    join(&p, "b", &limits(2), None).unwrap();
+    assert_eq!(join(&p, "c", &limits(2), None), Err(ClientCode::RoomFull));
+
+    // A refused join that would have created the room leaves no metadata.
+    let p = Presence::new();
+    let l = TenantLimits { max_rooms_total: 1, ..TenantLimits::default() };
+    p.try_join("acme", "acme::other", "acme::a", ConnectionId::new(), &l).unwrap();
+    assert!(join(&p, "b", &l, Some(3)).is_err());
+    assert_eq!(p.room_capacity_override("acme::lobby"), None);
+}
+
+#[test]
+fn concurrent_joins_never_overshoot() {
+    for _ in 0..20 {
+        let p = Arc::new(Presence::new());
+        let barrier = Arc::new(Barrier::new(16));
+        let joined: usize = (0..16)
+            .map(|i| {
+                let (p, barrier) = (Arc::clone(&p), Arc::clone(&barrier));
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    join(&p, &format!("u{i}"), &limits(5), None).is_ok() as usize
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .sum();
+        assert_eq!(joined, 5);
+        assert_eq!(p.users_in("acme::lobby").len(), 5);
+    }
+}
+
+#[tokio::test]
+async fn room_join_data_sets_capacity_and_full_rooms_reply_room_full() {
+    let cfg = "version: 1\ntenants:\n  - id: acme\n    allow_guest: true\n    guest_scopes: [\"room:*\"]\n    policy:\n      ext_allowlist: [\"room:*\"]\n";
+    let (state, addr) = spawn_gateway(cfg).await;
+    let mut guests = Vec::new();
+    for _ in 0..3 {
+        let mut ws = connect(addr, "tenant=acme").await;
+        assert_eq!(next_json(&mut ws).await["type"], "authed");
+        guests.push(ws);
+    }
+
+    let join = r#"{"v":1,"svc":"room","type":"join","room":"lobby","data":{"capacity":2}}"#;
+    let mut replies = Vec::new();
+    for ws in &mut guests {
+        ws.send(Message::Text(join.into())).await.unwrap();
+        replies.push(next_json(ws).await);
+    }
+    assert_eq!((replies[0]["type"].as_str(), replies[1]["type"].as_str()), (Some("joined"), Some("joined")));
+    let full = &replies[2];
+    assert_eq!((full["type"].as_str(), full["data"]["code"].as_str()), (Some("error"), Some("ROOM_FULL")));
+    assert_eq!(state.realtime().presence.users_in("acme::lobby").len(), 2);
+
+    let zero = r#"{"v":1,"svc":"room","type":"join","room":"other","data":{"capacity":0}}"#;
+    guests[2].send(Message::Text(zero.into())).await.unwrap();
+    assert_eq!(next_json(&mut guests[2]).await["data"]["code"], "BAD_REQUEST");
+}
+
+#[test]
+fn room_capacity_is_a_tenant_limit() {
+    let cfg = config::load_from_str("version: 1\ntenants:\n  - id: acme\n    limits: { room_capacity: 10 }\n").unwrap();
+    assert_eq!(cfg.tenants[0].limits.room_capacity, 10);
+    assert_eq!(TenantLimits::default().room_capacity, 0);
+}
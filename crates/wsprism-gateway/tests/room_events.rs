@@ -0,0 +1,71 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+
+use wsprism_gateway::config::{self, TenantLimits};
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::realtime::{Presence, RealtimeCore, RoomEvent};
+
+const ROOM: &str = "acme::lobby";
+
+fn join(p: &Presence, user: &str, id: ConnectionId) {
+    p.try_join("acme", ROOM, &format!("acme::{user}"), id, &TenantLimits::default()).unwrap();
+}
+
+#[test]
+fn joins_and_leaves_are_reported_per_user() {
+    let p = Presence::new();
+    let mut events = p.subscribe_room_events(ROOM);
+    let (a1, a2, b) = (ConnectionId::new(), ConnectionId::new(), ConnectionId::new());
+
+    join(&p, "alice", a1);
+    join(&p, "alice", a2); // second session: already joined
+    join(&p, "bob", b);
+    assert_eq!(events.try_recv().unwrap(), RoomEvent::Joined("acme::alice".into()));
+    assert_eq!(events.try_recv().unwrap(), RoomEvent::Joined("acme::bob".into()));
+    assert_eq!(events.try_recv(), Err(TryRecvError::Empty));
+
+    p.leave("acme", ROOM, "acme::alice", a1);
+    assert_eq!(events.try_recv(), Err(TryRecvError::Empty), "alice still has a session");
+    p.cleanup_session("acme", "acme::alice", a2);
+    assert_eq!(events.try_recv().unwrap(), RoomEvent::Left("acme::alice".into()));
+
+    // The last user leaving closes the channel.
+    p.leave("acme", ROOM, "acme::bob", b);
+    assert_eq!(events.try_recv().unwrap(), RoomEvent::Left("acme::bob".into()));
+    assert_eq!(events.try_recv(), Err(TryRecvError::Closed));
+}
+
+#[test]
+fn moves_are_a_leave_and_a_join() {
+    let p = Presence::new();
+    let id = ConnectionId::new();
+    join(&p, "alice", id);
+    let mut from = p.subscribe_room_events(ROOM);
+    let mut to = p.subscribe_room_events("acme::match:1");
+    p.move_user("acme", "acme::alice", id, ROOM, "acme::match:1", &TenantLimits::default()).unwrap();
+    assert_eq!(to.try_recv().unwrap(), RoomEvent::Joined("acme::alice".into()));
+    assert_eq!(from.try_recv().unwrap(), RoomEvent::Left("acme::alice".into()));
+    assert_eq!(from.try_recv(), Err(TryRecvError::Closed));
+}
+
+#[tokio::test]
+async fn slow_receivers_lag_past_the_capacity() {
+    let core = RealtimeCore::new().with_room_event_capacity(2);
+    let mut events = core.presence.subscribe_room_events(ROOM);
+    for user in ["a", "b", "c"] {
+        join(&core.presence, user, ConnectionId::new());
+    }
+    assert_eq!(events.recv().await, Err(RecvError::Lagged(1)));
+    assert_eq!(events.recv().await.unwrap(), RoomEvent::Joined("acme::b".into()));
+}
+
+#[test]
+fn room_event_capacity_is_validated() {
+    let cfg = |n: usize| format!("version: 1\ngateway:\n  room_event_capacity: {n}\ntenants:\n  - id: acme\n");
+    assert_eq!(config::load_from_str(&cfg(8)).unwrap().gateway.room_event_capacity, 8);
+    let err = config::load_from_str(&cfg(0)).unwrap_err();
+    assert!(err.to_string().contains("room_event_capacity"), "{err}");
+}
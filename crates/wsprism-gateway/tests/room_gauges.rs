@@ -0,0 +1,118 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use axum::extract::ws::Message;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::config::{self, TenantLimits};
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::obs::metrics::GaugeVec;
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::RealtimeCore;
+
+const CFG: &str = r#"
+version: 1
+observability:
+  room_gauges: { top_n: 2 }
+tenants:
+  - id: "acme"
+  - id: "beta"
+"#;
+
+/// Register a session in `room` and return its id and queue.
+fn join(core: &RealtimeCore, tenant: &str, user: &str, room: &str) -> (ConnectionId, mpsc::Receiver<Message>) {
+    let (tx, rx) = mpsc::channel(4);
+    let id = ConnectionId::new();
+    let user_key = format!("{tenant}::{user}");
+    core.sessions
+        .try_insert(tenant.into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    core.presence
+        .try_join(tenant, &format!("{tenant}::{room}"), &user_key, id, &TenantLimits::default())
+        .unwrap();
+    (id, rx)
+}
+
+fn gauge(state: &AppState, tenant: &str, room: &str) -> Option<i64> {
+    let line = format!("wsprism_room_members{{room=\"{room}\",tenant=\"{tenant}\"}} ");
+    let body = state.metrics().render(&[]);
+    body.lines().find_map(|l| l.strip_prefix(line.as_str()).map(|v| v.parse().unwrap()))
+}
+
+#[test]
+fn gauges_follow_the_largest_rooms() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    let core = state.realtime();
+    let mut conns = Vec::new();
+    for (room, size) in [("big", 3), ("mid", 2), ("small", 1)] {
+        for u in 0..size {
+            conns.push(join(&core, "acme", &format!("{room}-{u}"), room));
+        }
+    }
+    let (beta_id, _beta_rx) = join(&core, "beta", "solo", "lobby");
+
+    state.sample_room_sizes();
+    assert_eq!(gauge(&state, "acme", "big"), Some(3));
+    assert_eq!(gauge(&state, "acme", "mid"), Some(2));
+    assert_eq!(gauge(&state, "acme", "small"), None);
+    // Top-N is per tenant.
+    assert_eq!(gauge(&state, "beta", "lobby"), Some(1));
+
+    // "small" overtakes "mid": mid's series goes away rather than going stale.
+    for u in 1..4 {
+        conns.push(join(&core, "acme", &format!("small-{u}"), "small"));
+    }
+    state.sample_room_sizes();
+    assert_eq!(gauge(&state, "acme", "small"), Some(4));
+    assert_eq!(gauge(&state, "acme", "big"), Some(3));
+    assert_eq!(gauge(&state, "acme", "mid"), None);
+
+    // Emptied rooms drop out too.
+    core.presence.cleanup_session("beta", "beta::solo", beta_id);
+    state.sample_room_sizes();
+    assert_eq!(gauge(&state, "beta", "lobby"), None);
+}
+
+#[test]
+fn room_sizes_visits_each_room_of_a_tenant() {
+    let core = RealtimeCore::new();
+    let _conns = [
+        join(&core, "acme", "a", "one"),
+        join(&core, "acme", "b", "one"),
+        join(&core, "acme", "c", "two"),
+        join(&core, "beta", "d", "one"),
+    ];
+    let mut acme = Vec::new();
+    core.presence.room_sizes(Some("acme"), |room, sessions| acme.push((room.to_string(), sessions)));
+    acme.sort();
+    assert_eq!(acme, [("acme::one".to_string(), 2), ("acme::two".to_string(), 1)]);
+    let mut all = 0;
+    core.presence.room_sizes(None, |_, _| all += 1);
+    assert_eq!(all, 3);
+}
+
+#[test]
+fn room_gauges_config_is_validated() {
+    let cfg = |o: &str| config::load_from_str(&format!("version: 1\nobservability: {o}\ntenants:\n  - id: acme\n"));
+    let c = cfg("{}").unwrap();
+    assert_eq!((c.observability.room_gauges.top_n, c.observability.room_gauges.interval_secs), (10, 15));
+    assert!(cfg("{ room_gauges: { top_n: 0 } }").is_ok());
+    for (bad, msg) in [("{ room_gauges: { interval_secs: 0 } }", "interval_secs"), ("{ room_gauges: { top_n: 5000 } }", "top_n")] {
+        let err = cfg(bad).expect_err(bad);
+        assert!(err.to_string().contains(msg), "{err}");
+    }
+}
+
+#[test]
+fn retained_out_rooms_free_their_label_values() {
+    let g = GaugeVec::with_max_label_values(2);
+    g.set(&[("room", "a")], 1);
+    g.set(&[("room", "b")], 1);
+    g.retain(|labels| labels[0].1 == "b");
+    // "a" no longer counts against the cap of 2.
+    g.set(&[("room", "c")], 1);
+    assert_eq!((g.get(&[("room", "a")]), g.get(&[("room", "c")])), (0, 1));
+    assert_eq!(g.get(&[("room", "_other")]), 0);
+}
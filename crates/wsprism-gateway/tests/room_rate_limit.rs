@@ -0,0 +1,114 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+
+use wsprism_gateway::config::TenantPolicy;
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::policy::rate::RoomRateLimiter;
+use wsprism_gateway::policy::TenantPolicyRuntime;
+use wsprism_gateway::realtime::{Outgoing, Payload, Priority, QoS, RealtimeCore, RealtimeCtx};
+
+fn msg() -> Outgoing {
+    Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!({ "m": 1 })), priority: Priority::Normal }
+}
+
+fn limited_core() -> (TenantPolicyRuntime, Arc<GatewayMetrics>, Arc<RealtimeCore>) {
+    let policy = TenantPolicy {
+        room_rate_limit_rps: 1,
+        room_rate_limit_burst: 3,
+        ext_allowlist: vec!["chat:*".into()],
+        ..TenantPolicy::default()
+    };
+    let rt = TenantPolicyRuntime::new("acme".into(), 4096, &policy).unwrap();
+    let metrics = Arc::new(GatewayMetrics::default());
+    let core = Arc::new(RealtimeCore::new().with_metrics(metrics.clone()));
+    (rt, metrics, core)
+}
+
+#[test]
+fn room_limit_is_shared_by_senders_and_isolated_per_room() {
+    let (rt, metrics, core) = limited_core();
+    let ctx = |user: &str| {
+        RealtimeCtx::new("acme", user, ConnectionId::new(), "trace", None, core.clone())
+            .with_room_limiter(rt.room_limiter())
+    };
+
+    let (mut alice, mut bob) = (ctx("alice"), ctx("bob"));
+    alice.check_room_rate("lobby").unwrap();
+    bob.check_room_rate("lobby").unwrap();
+    alice.check_room_rate("lobby").unwrap();
+    let retry_after = bob.check_room_rate("lobby").expect_err("room throttled");
+    assert!(retry_after >= 1);
+
+    // Other rooms keep their own budget.
+    alice.check_room_rate("match:1").unwrap();
+
+    let rendered = metrics.render(&[]);
+    assert!(rendered.contains("wsprism_room_rate_limited_total"));
+    assert!(rendered.contains("room=\"lobby\""));
+}
+
+#[test]
+fn ungated_publishes_are_rate_limited() {
+    let (rt, _, core) = limited_core();
+    let backend = RealtimeCtx::new("acme", "svc", ConnectionId::new(), "trace", None, core.clone())
+        .with_room_limiter(rt.room_limiter());
+    for _ in 0..3 {
+        backend.publish_room_lossy("lobby", msg()).unwrap();
+    }
+    let err = backend.publish_room_lossy("lobby", msg()).expect_err("room throttled");
+    assert_eq!(err.client_code().as_str(), "RATE_LIMITED");
+
+    // Other rooms keep their own budget.
+    backend.publish_room_lossy("match:1", msg()).unwrap();
+}
+
+#[test]
+fn gated_message_is_not_charged_twice_for_its_room() {
+    let (rt, _, core) = limited_core();
+    let mut ctx = RealtimeCtx::new("acme", "alice", ConnectionId::new(), "trace", None, core.clone())
+        .with_room_limiter(rt.room_limiter());
+    ctx.check_room_rate("lobby").unwrap();
+    // The handler's publishes to the gated room ride on the gate's token.
+    for _ in 0..5 {
+        ctx.publish_room_lossy("lobby", msg()).unwrap();
+    }
+    // Another room pays for itself.
+    for _ in 0..3 {
+        ctx.publish_room_lossy("match:1", msg()).unwrap();
+    }
+    assert!(ctx.publish_room_lossy("match:1", msg()).is_err());
+
+    // A spawned task is not the gated message.
+    let task = ctx.clone_for_spawn();
+    task.publish_room_lossy("lobby", msg()).unwrap();
+    task.publish_room_lossy("lobby", msg()).unwrap();
+    assert!(task.publish_room_lossy("lobby", msg()).is_err());
+}
+
+#[test]
+fn room_limit_is_off_by_default() {
+    let rt = TenantPolicyRuntime::new("acme".into(), 4096, &TenantPolicy::default()).unwrap();
+    assert!(rt.room_limiter().is_none());
+}
+
+#[test]
+fn idle_room_buckets_are_swept() {
+    let limiter = RoomRateLimiter::new(10, 10);
+    limiter.check("acme::a").unwrap();
+    limiter.check("acme::b").unwrap();
+    assert_eq!(limiter.len(), 2);
+
+    assert_eq!(limiter.sweep_idle(Duration::from_secs(600)), 0);
+    std::thread::sleep(Duration::from_millis(20));
+    limiter.check("acme::b").unwrap();
+    assert_eq!(limiter.sweep_idle(Duration::from_millis(10)), 1);
+    assert_eq!(limiter.len(), 1);
+    assert_eq!(limiter.retry_after_secs("acme::a"), 0);
+}
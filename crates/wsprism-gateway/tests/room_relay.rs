@@ -0,0 +1,113 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::sync::{Arc, Mutex};
+
+use axum::extract::ws::Message;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::config::{self, TenantLimits};
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{Outgoing, Payload, PreparedMsg, Priority, QoS, RealtimeCore, RoomRelay};
+
+#[derive(Default)]
+struct Recorder(Mutex<Vec<(String, String, Option<String>)>>);
+
+impl RoomRelay for Recorder {
+    fn relay(&self, room_key: &str, msg: &PreparedMsg, exclude_user_key: Option<&str>) {
+        let PreparedMsg::Text(text) = msg else { panic!("expected text") };
+        self.0.lock().unwrap().push((room_key.into(), text.to_string(), exclude_user_key.map(Into::into)));
+    }
+}
+
+fn connect(core: &RealtimeCore, user: &str) -> mpsc::Receiver<Message> {
+    let (tx, rx) = mpsc::channel(8);
+    let id = ConnectionId::new();
+    let user_key = format!("acme::{user}");
+    core.sessions
+        .try_insert("acme".into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    core.presence.try_join("acme", "acme::lobby", &user_key, id, &TenantLimits::default()).unwrap();
+    rx
+}
+
+fn text(n: u32) -> Outgoing {
+    Outgoing { qos: QoS::Lossy, payload: Payload::TextJson(json!({ "n": n })), priority: Priority::Normal }
+}
+
+#[tokio::test]
+async fn room_publishes_are_handed_to_the_relay() {
+    let relay = Arc::new(Recorder::default());
+    let core = RealtimeCore::new().with_room_relay(relay.clone());
+    let mut alice = connect(&core, "alice");
+
+    core.publish_room_lossy("acme::lobby", text(1)).unwrap();
+    let reliable = Outgoing { qos: QoS::Reliable { timeout_ms: 100 }, ..text(2) };
+    core.publish_room_reliable_excluding("acme::lobby", reliable, 4, Some("acme::bob")).await.unwrap();
+    // Nobody here locally: still relayed for the other replicas.
+    core.publish_room_lossy("acme::empty", text(3)).unwrap();
+
+    let relayed = relay.0.lock().unwrap().clone();
+    assert_eq!(
+        relayed,
+        vec![
+            ("acme::lobby".into(), r#"{"n":1}"#.into(), None),
+            ("acme::lobby".into(), r#"{"n":2}"#.into(), Some("acme::bob".into())),
+            ("acme::empty".into(), r#"{"n":3}"#.into(), None),
+        ]
+    );
+    assert!(matches!(alice.recv().await, Some(Message::Text(_))));
+    assert!(matches!(alice.recv().await, Some(Message::Text(_))));
+}
+
+#[tokio::test]
+async fn relayed_messages_are_delivered_locally_only() {
+    let relay = Arc::new(Recorder::default());
+    let core = RealtimeCore::new().with_room_relay(relay.clone());
+    let mut alice = connect(&core, "alice");
+    let mut bob = connect(&core, "bob");
+
+    let report = core.deliver_relayed("acme::lobby", &PreparedMsg::Text("hi".into()), Some("acme::bob"));
+    assert_eq!((report.targets, report.dropped), (1, 0));
+    assert!(matches!(alice.recv().await, Some(Message::Text(t)) if t == "hi"));
+    assert!(bob.try_recv().is_err());
+    assert!(relay.0.lock().unwrap().is_empty());
+}
+
+#[test]
+fn cluster_section_is_validated() {
+    let cfg = |cluster: &str| format!("version: 1\ntenants:\n  - id: acme\ncluster:\n{cluster}");
+    let ok = config::load_from_str(&cfg("  node_id: gw-1\n  redis_url: redis://127.0.0.1:6379\n")).unwrap();
+    let ok = ok.cluster.unwrap();
+    assert_eq!((ok.max_message_bytes, ok.presence_heartbeat_ms, ok.presence_ttl_ms), (64 * 1024, 5000, 15000));
+    assert!(config::load_from_str(&cfg("  node_id: gw-1\n  backend: nats\n  nats_url: nats://127.0.0.1:4222\n")).is_ok());
+
+    for (bad, msg) in [
+        ("  node_id: \"gw 1\"\n  redis_url: redis://r\n", "node_id"),
+        ("  node_id: gw-1\n  redis_url: http://r\n", "redis_url"),
+        ("  node_id: gw-1\n  backend: nats\n  redis_url: redis://r\n", "nats_url"),
+        ("  node_id: gw-1\n  backend: nats\n  nats_url: nats://n\n  redis_url: redis://r\n", "only cluster.nats_url"),
+        ("  node_id: gw-1\n  redis_url: redis://r\n  max_message_bytes: 0\n", "max_message_bytes"),
+        ("  node_id: gw-1\n  redis_url: redis://r\n  presence_heartbeat_ms: 50\n", "presence_heartbeat_ms"),
+        ("  node_id: gw-1\n  redis_url: redis://r\n  presence_ttl_ms: 5000\n", "presence_ttl_ms"),
+    ] {
+        let err = config::load_from_str(&cfg(bad)).unwrap_err();
+        assert!(err.to_string().contains(msg), "{err}");
+    }
+
+    let dotted = "version: 1\ntenants:\n  - id: acme.eu\ncluster:\n  node_id: gw-1\n  backend: nats\n  nats_url: nats://n\n";
+    assert!(config::load_from_str(dotted).unwrap_err().to_string().contains("acme.eu"));
+}
+
+#[cfg(not(feature = "cluster-redis"))]
+#[test]
+fn cluster_backend_requires_its_feature() {
+    use wsprism_gateway::app_state::AppState;
+
+    let cfg = "version: 1\ntenants:\n  - id: acme\ncluster:\n  node_id: gw-1\n  redis_url: redis://127.0.0.1:6379\n";
+    let err = AppState::new(config::load_from_str(cfg).unwrap()).err().unwrap();
+    assert!(err.to_string().contains("cluster-redis"), "{err}");
+}
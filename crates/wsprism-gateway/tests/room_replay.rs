@@ -0,0 +1,112 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::sync::Arc;
+
+use futures_util::SinkExt;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::realtime::core::{Connection, MessageRingBuffer};
+use wsprism_gateway::realtime::{Outgoing, PreparedMsg, RealtimeCore, RealtimeCtx};
+
+use common::{connect, next_json, spawn_gateway};
+
+fn text(m: &PreparedMsg) -> String {
+    match m {
+        PreparedMsg::Text(s) => s.to_string(),
+        other => panic!("unexpected {other:?}"),
+    }
+}
+
+#[test]
+fn ring_buffer_evicts_oldest() {
+    let mut buf = MessageRingBuffer::new(3);
+    for i in 0..5 {
+        buf.push(PreparedMsg::Text(Arc::from(i.to_string())));
+    }
+    assert_eq!(buf.len(), 3);
+    assert_eq!(buf.last(10).iter().map(text).collect::<Vec<_>>(), ["2", "3", "4"]);
+    assert_eq!(buf.last(2).iter().map(text).collect::<Vec<_>>(), ["3", "4"]);
+}
+
+#[test]
+fn replay_sends_recent_room_messages_to_joining_session() {
+    let core = Arc::new(RealtimeCore::new().with_replay_capacity(2));
+    let limits = TenantLimits::default();
+    let join = |user: &str| {
+        let (tx, rx) = mpsc::channel(8);
+        let id = ConnectionId::new();
+        core.sessions
+            .try_insert("acme".into(), format!("acme::{user}"), id, Connection::new(tx, SessionClaims::default()), 0)
+            .unwrap();
+        let ctx = RealtimeCtx::new("acme", user, id, "t", None, core.clone()).with_replay_on_join(5);
+        ctx.join_room_with_limits("lobby", &limits).unwrap();
+        (ctx, rx)
+    };
+
+    // The first joiner starts the room's buffer.
+    let (alice, mut rx_alice) = join("alice");
+    assert_eq!(alice.replay_room("lobby"), 0);
+    for i in 0..3 {
+        alice.publish_room_lossy("lobby", Outgoing::reply(json!({ "n": i }))).unwrap();
+    }
+    while rx_alice.try_recv().is_ok() {}
+
+    let (bob, mut rx_bob) = join("bob");
+    assert_eq!(bob.replay_room("lobby"), 2, "capacity keeps the last two");
+    for n in [1, 2] {
+        match rx_bob.try_recv() {
+            Ok(axum::extract::ws::Message::Text(t)) => assert_eq!(t, format!(r#"{{"n":{n}}}"#)),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    // Buffers of empty rooms are swept.
+    for ctx in [&alice, &bob] {
+        ctx.leave_room("lobby");
+    }
+    core.sweep_replay_buffers();
+    let (carol, _rx) = join("carol");
+    assert_eq!(carol.replay_room("lobby"), 0);
+}
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["room:*", "chat:*"]
+      replay_on_join: 10
+"#;
+
+#[tokio::test]
+async fn joining_user_receives_earlier_chat_messages() {
+    let (_, addr) = spawn_gateway(CFG).await;
+
+    let mut first = connect(addr, "tenant=acme&ticket=dev").await;
+    assert_eq!(next_json(&mut first).await["type"], "authed");
+    first.send(Message::Text(r#"{"svc":"room","type":"join","room":"lobby"}"#.into())).await.unwrap();
+    assert_eq!(next_json(&mut first).await["type"], "joined");
+    for i in 0..3 {
+        let send = json!({ "svc": "chat", "type": "send", "room": "lobby", "data": { "msg": format!("m{i}") } });
+        first.send(Message::Text(send.to_string())).await.unwrap();
+        assert_eq!(next_json(&mut first).await["data"]["msg"], format!("m{i}"));
+    }
+
+    let mut second = connect(addr, "tenant=acme&ticket=dev").await;
+    assert_eq!(next_json(&mut second).await["type"], "authed");
+    second.send(Message::Text(r#"{"svc":"room","type":"join","room":"lobby"}"#.into())).await.unwrap();
+    assert_eq!(next_json(&mut second).await["type"], "joined");
+    for i in 0..3 {
+        let msg = next_json(&mut second).await;
+        assert_eq!((msg["svc"].as_str(), msg["type"].as_str()), (Some("chat"), Some("msg")));
+        assert_eq!(msg["data"]["msg"], format!("m{i}"));
+    }
+}
@@ -0,0 +1,121 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_core::protocol::text::Envelope;
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::dispatch::TextService;
+use wsprism_gateway::realtime::{RealtimeCore, RealtimeCtx};
+use wsprism_gateway::services::RoomService;
+
+use common::{connect, next_message, next_text, spawn_gateway, Ws};
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["room:*"]
+      hot_allowlist: ["1:*"]
+"#;
+
+/// An authenticated `user:dev` session.
+async fn session(addr: SocketAddr) -> Ws {
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert!(next_text(&mut ws).await.contains("authed"));
+    ws
+}
+
+/// Send an Ext lane command and return the reply without its `trace_id`.
+async fn command(ws: &mut Ws, msg_type: &str, room: Option<&str>) -> Value {
+    let mut env = json!({ "v": 1, "svc": "room", "type": msg_type });
+    if let Some(r) = room {
+        env["room"] = r.into();
+    }
+    ws.send(Message::Text(env.to_string())).await.unwrap();
+    let mut reply: Value = serde_json::from_str(&next_text(ws).await).unwrap();
+    assert!(reply.as_object_mut().unwrap().remove("trace_id").is_some_and(|t| t.is_string()), "{reply}");
+    reply
+}
+
+#[tokio::test]
+async fn room_commands_keep_their_wire_shape() {
+    let (state, addr) = spawn_gateway(CFG).await;
+    let mut ws = session(addr).await;
+
+    let joined = command(&mut ws, "join", Some("lobby")).await;
+    assert_eq!(joined, json!({ "v": 1, "svc": "sys", "type": "joined", "room": "lobby" }));
+    assert_eq!(state.realtime().presence.users_in("acme::lobby"), ["acme::user:dev"]);
+
+    let moved = command(&mut ws, "move", Some("arena")).await;
+    assert_eq!(moved, json!({ "v": 1, "svc": "sys", "type": "moved", "from": "lobby", "to": "arena" }));
+    assert!(state.realtime().presence.users_in("acme::lobby").is_empty());
+
+    let left = command(&mut ws, "leave", None).await;
+    assert_eq!(left, json!({ "v": 1, "svc": "sys", "type": "left" }));
+    assert!(state.realtime().presence.users_in("acme::arena").is_empty());
+
+    // Errors come back as sys.error, as before.
+    let err = command(&mut ws, "move", Some("arena")).await;
+    assert_eq!(err["type"], "error");
+    assert_eq!(err["data"]["code"], "BAD_REQUEST");
+}
+
+#[tokio::test]
+async fn list_and_info_report_membership() {
+    let (_, addr) = spawn_gateway(CFG).await;
+    let mut ws = session(addr).await;
+
+    let empty = command(&mut ws, "list", None).await;
+    assert_eq!(empty, json!({ "v": 1, "svc": "sys", "type": "rooms", "data": { "rooms": [] } }));
+    command(&mut ws, "join", Some("b")).await;
+    command(&mut ws, "join", Some("a")).await;
+    let listed = command(&mut ws, "list", None).await;
+    assert_eq!(listed["data"]["rooms"], json!(["a", "b"]));
+
+    // Another session of the same user counts once.
+    let mut other = session(addr).await;
+    command(&mut other, "join", Some("a")).await;
+    let info = command(&mut other, "info", Some("a")).await;
+    assert_eq!(info, json!({ "v": 1, "svc": "sys", "type": "room_info", "room": "a", "data": { "members": 1 } }));
+    assert_eq!(command(&mut other, "list", None).await["data"]["rooms"], json!(["a"]));
+
+    // Without a room, `info` describes the active one.
+    assert_eq!(command(&mut ws, "info", None).await["room"], "a");
+    assert_eq!(command(&mut ws, "info", Some("nowhere")).await["data"]["members"], 0);
+}
+
+#[tokio::test]
+async fn hot_lane_follows_the_joined_room() {
+    let (_, addr) = spawn_gateway(CFG).await;
+    let mut ws = session(addr).await;
+    let mut peer = session(addr).await;
+    command(&mut ws, "join", Some("lobby")).await;
+    command(&mut peer, "join", Some("lobby")).await;
+
+    // Echo service 1 publishes to the sender's active room.
+    ws.send(Message::Binary(vec![1, 1, 1, 0, 42])).await.unwrap();
+    assert_eq!(next_message(&mut peer).await, Message::Binary(vec![42]));
+    assert_eq!(next_message(&mut ws).await, Message::Binary(vec![42]));
+
+    command(&mut ws, "leave", None).await;
+    ws.send(Message::Binary(vec![1, 1, 1, 0, 43])).await.unwrap();
+    let err: Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+    assert_eq!(err["data"]["message"], "no active room");
+}
+
+#[tokio::test]
+async fn room_service_needs_a_client_session() {
+    let ctx = RealtimeCtx::new("acme", "backend", ConnectionId::new(), "t", None, std::sync::Arc::new(RealtimeCore::new()));
+    let env: Envelope = serde_json::from_str(r#"{"svc":"room","type":"join","room":"lobby"}"#).unwrap();
+    let err = RoomService::new().handle(ctx, env).await.unwrap_err();
+    assert_eq!(err.to_string(), "bad request: room commands require a client session");
+}
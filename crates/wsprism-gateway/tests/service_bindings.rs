@@ -0,0 +1,63 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use wsprism_gateway::app_state::{validate_service_bindings, AppState, StartupOptions};
+use wsprism_gateway::config;
+use wsprism_gateway::config::TenantPolicy;
+use wsprism_gateway::dispatch::Dispatcher;
+use wsprism_gateway::policy::TenantPolicyRuntime;
+use wsprism_gateway::services::{ChatService, EchoBinaryService};
+
+const UNBOUND: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["room:join", "chat:send"]
+      hot_allowlist: ["7:*"]
+"#;
+
+#[test]
+fn unregistered_hot_service_fails_startup() {
+    let cfg = config::load_from_str(UNBOUND).unwrap();
+    let err = AppState::new(cfg).err().expect("must fail");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+    assert!(err.to_string().contains("hot service id: 7"));
+}
+
+#[test]
+fn lenient_mode_only_warns() {
+    let cfg = config::load_from_str(UNBOUND).unwrap();
+    assert!(AppState::new_with_options(cfg, StartupOptions { lenient: true, ..Default::default() }).is_ok());
+}
+
+#[tokio::test]
+async fn async_constructor_runs_the_same_startup_checks() {
+    let err = AppState::from_config_async(config::load_from_str(UNBOUND).unwrap()).await.err().expect("must fail");
+    assert!(err.to_string().contains("hot service id: 7"));
+
+    let opts = StartupOptions { lenient: true, ..Default::default() };
+    assert!(AppState::from_config_async_with_options(config::load_from_str(UNBOUND).unwrap(), opts).await.is_ok());
+}
+
+#[test]
+fn duplicate_registration_is_detected() {
+    let d = Dispatcher::new();
+    d.register_text(Arc::new(ChatService::new()));
+    d.register_text(Arc::new(ChatService::new()));
+    d.register_hot(Arc::new(EchoBinaryService::new(1)));
+    assert_eq!(d.duplicate_registrations(), vec!["text:chat".to_string()]);
+
+    let policy = TenantPolicy { ext_allowlist: vec!["chat:*".into()], ..TenantPolicy::default() };
+    let mut tenants = HashMap::new();
+    tenants.insert(
+        "acme".to_string(),
+        Arc::new(TenantPolicyRuntime::new("acme".into(), 4096, &policy).unwrap()),
+    );
+    assert!(validate_service_bindings(&tenants, &d, false).is_err());
+    assert!(validate_service_bindings(&tenants, &d, true).is_ok());
+}
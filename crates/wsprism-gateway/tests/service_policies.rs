@@ -0,0 +1,100 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::collections::HashMap;
+
+use wsprism_gateway::config::schema::RateLimitScope;
+use wsprism_gateway::config::{self, ServicePolicy, TenantPolicy};
+use wsprism_gateway::policy::{DecisionReason, PolicyDecision, TenantPolicyRuntime};
+
+fn runtime(scope: RateLimitScope, services: &[(&str, ServicePolicy)]) -> TenantPolicyRuntime {
+    let policy = TenantPolicy {
+        rate_limit_rps: 1,
+        rate_limit_burst: 5,
+        rate_limit_scope: scope,
+        ext_allowlist: vec!["chat:*".into(), "game:*".into()],
+        ..TenantPolicy::default()
+    };
+    let services: HashMap<_, _> =
+        services.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+    TenantPolicyRuntime::new("acme".into(), 1024, &policy)
+        .unwrap()
+        .with_service_policies(&services)
+        .unwrap()
+}
+
+fn passes(d: PolicyDecision) -> bool {
+    matches!(d, PolicyDecision::Pass)
+}
+
+#[test]
+fn service_rate_override_uses_its_own_tenant_bucket() {
+    let game = ServicePolicy { rate_limit_rps: Some(1), rate_limit_burst: Some(50), max_frame_bytes: None };
+    let rt = runtime(RateLimitScope::Tenant, &[("game", game)]);
+
+    let chat = (0..20).filter(|_| passes(rt.check_text(10, "chat", "send"))).count();
+    let game = (0..100).filter(|_| passes(rt.check_text(10, "game", "move"))).count();
+    assert_eq!(chat, 5);
+    assert_eq!(game, 50);
+}
+
+#[test]
+fn service_rate_override_applies_per_connection() {
+    let game = ServicePolicy { rate_limit_rps: Some(1), rate_limit_burst: Some(50), max_frame_bytes: None };
+    let rt = runtime(RateLimitScope::Connection, &[("game", game)]);
+    let mut lim = rt.new_connection_limiter().unwrap();
+
+    assert_eq!((0..20).filter(|_| lim.allow_svc("chat")).count(), 5);
+    assert_eq!((0..100).filter(|_| lim.allow_svc("game")).count(), 50);
+}
+
+#[test]
+fn service_max_frame_bytes_overrides_tenant_limit() {
+    let game = ServicePolicy { max_frame_bytes: Some(8192), ..Default::default() };
+    let chat = ServicePolicy { max_frame_bytes: Some(64), ..Default::default() };
+    let rt = runtime(RateLimitScope::Connection, &[("game", game), ("chat", chat)]);
+
+    assert!(passes(rt.check_text(4096, "game", "move")));
+    let d = rt.check_text(100, "chat", "send");
+    assert!(matches!(d, PolicyDecision::Close { reason: DecisionReason::Len, .. }));
+    // No override: tenant-level limit (1024).
+    assert!(matches!(rt.check_text(2048, "room", "join"), PolicyDecision::Close { .. }));
+}
+
+#[test]
+fn service_policies_require_schema_v2() {
+    let yaml = |version: u32| {
+        format!(
+            r#"
+version: {version}
+tenants:
+  - id: "acme"
+    service_policies:
+      chat: {{ rate_limit_rps: 50 }}
+"#
+        )
+    };
+    let err = config::load_from_str(&yaml(1)).expect_err("v1 must reject service_policies");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+
+    let cfg = config::load_from_str(&yaml(2)).expect("v2 must parse");
+    assert_eq!(cfg.tenants[0].service_policies["chat"].rate_limit_rps, Some(50));
+
+    let v1 = "version: 1\ntenants:\n  - id: acme\n";
+    assert!(config::load_from_str(v1).is_ok());
+    let v3 = "version: 3\ntenants:\n  - id: acme\n";
+    assert!(config::load_from_str(v3).is_err());
+}
+
+#[test]
+fn zero_service_rate_is_rejected() {
+    let bad = r#"
+version: 2
+tenants:
+  - id: "acme"
+    service_policies:
+      chat: { rate_limit_burst: 0 }
+"#;
+    assert!(config::load_from_str(bad).is_err());
+}
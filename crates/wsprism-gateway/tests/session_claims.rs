@@ -0,0 +1,59 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::ws::Message;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use wsprism_gateway::config::TenantLimits;
+use wsprism_gateway::context::claims::{SessionClaims, MAX_CLAIMS_BYTES};
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::dispatch::TextService;
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{RealtimeCore, RealtimeCtx};
+use wsprism_gateway::services::ChatService;
+
+#[test]
+fn claims_are_size_capped() {
+    let mut ok = HashMap::new();
+    ok.insert("display_name".to_string(), Value::from("Alice"));
+    let claims = SessionClaims::new(ok).unwrap();
+    assert_eq!(claims.display_name(), Some("Alice"));
+
+    let mut big = HashMap::new();
+    big.insert("blob".to_string(), Value::from("x".repeat(MAX_CLAIMS_BYTES)));
+    let err = SessionClaims::new(big).expect_err("must reject oversized claims");
+    assert_eq!(err.client_code().as_str(), "BAD_REQUEST");
+}
+
+fn display_name(name: &str) -> SessionClaims {
+    let mut claims = HashMap::new();
+    claims.insert("display_name".to_string(), Value::from(name));
+    SessionClaims::new(claims).unwrap()
+}
+
+/// `from` of the chat broadcast for a `chat:send` from `u1` with `claims`.
+async fn chat_from(claims: SessionClaims) -> Value {
+    let core = Arc::new(RealtimeCore::new());
+    let (tx, mut rx) = mpsc::channel(4);
+    let listener = ConnectionId::new();
+    core.sessions.try_insert("acme".into(), "acme::u2".into(), listener, Connection::new(tx, SessionClaims::default()), 0).unwrap();
+    core.presence.try_join("acme", "acme::lobby", "acme::u2", listener, &TenantLimits::default()).unwrap();
+
+    let ctx = RealtimeCtx::new("acme", "u1", ConnectionId::new(), "t", None, core).with_claims(claims);
+    let env = serde_json::from_str(r#"{"v":1,"svc":"chat","type":"send","room":"lobby","data":{"msg":"hi"}}"#).unwrap();
+    ChatService::new().handle(ctx, env).await.unwrap();
+
+    let Some(Message::Text(text)) = rx.recv().await else { panic!("no broadcast") };
+    serde_json::from_str::<Value>(&text).unwrap()["data"]["from"].clone()
+}
+
+#[tokio::test]
+async fn chat_uses_display_name_claim() {
+    assert_eq!(chat_from(display_name("Alice")).await, "Alice");
+    assert_eq!(chat_from(SessionClaims::default()).await, "u1");
+}
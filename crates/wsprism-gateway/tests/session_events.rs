@@ -0,0 +1,128 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+
+use wsprism_gateway::app_state::AppState;
+use wsprism_gateway::config;
+use wsprism_gateway::policy::engine::Lane;
+use wsprism_gateway::realtime::{SessionEvent, SessionEventLog};
+use wsprism_gateway::router::build_router;
+
+use common::{connect, next_json, spawn_gateway};
+
+const CFG: &str = r#"
+version: 1
+gateway:
+  admin_token: "s3cret"
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["room:*"]
+"#;
+
+async fn events(state: &AppState, id: &str) -> (StatusCode, Value) {
+    let req = Request::get(format!("/admin/v1/sessions/{id}/events"))
+        .header("authorization", "Bearer s3cret")
+        .body(Body::empty())
+        .unwrap();
+    let resp = build_router(state.clone()).oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), 1 << 20).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// `event` names, with the room or user where the event has one.
+fn names(body: &Value) -> Vec<String> {
+    body["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            let name = e["event"].as_str().unwrap();
+            match e.get("room").or_else(|| e.get("user")) {
+                Some(v) => format!("{name}:{}", v.as_str().unwrap()),
+                None => name.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn log_keeps_the_newest_max_events() {
+    let log = SessionEventLog::new(3);
+    log.record(SessionEvent::Connected);
+    log.record(SessionEvent::Authenticated("alice".into()));
+    log.record(SessionEvent::FrameReceived(Lane::Hot, 12));
+    log.record(SessionEvent::FrameSent(40));
+    let kept: Vec<SessionEvent> = log.snapshot().into_iter().map(|e| e.event).collect();
+    assert_eq!(
+        kept,
+        [SessionEvent::Authenticated("alice".into()), SessionEvent::FrameReceived(Lane::Hot, 12), SessionEvent::FrameSent(40)]
+    );
+    assert_eq!(log.to_json()[1]["lane"], "hot");
+
+    log.clear();
+    assert!(log.is_empty());
+    let off = SessionEventLog::new(0);
+    off.record(SessionEvent::Connected);
+    assert!(off.is_empty());
+}
+
+#[tokio::test]
+async fn admin_endpoint_returns_the_session_lifecycle() {
+    let (state, addr) = spawn_gateway(CFG).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    let authed = next_json(&mut ws).await;
+    let id = authed["data"]["connection_id"].as_str().unwrap().to_string();
+
+    ws.send(Message::Text(r#"{"v":1,"svc":"room","type":"join","room":"lobby"}"#.into())).await.unwrap();
+    next_json(&mut ws).await;
+    ws.send(Message::Text(r#"{"v":1,"svc":"orders","type":"create"}"#.into())).await.unwrap();
+    assert_eq!(next_json(&mut ws).await["type"], "error");
+
+    let (status, body) = events(&state, &id).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((&body["connection_id"], &body["live"]), (&json!(id), &json!(true)));
+    let seen = names(&body);
+    let expected = ["connected", "authenticated:user:dev", "frame_sent", "frame_received", "joined_room:lobby", "frame_sent", "frame_received", "error"];
+    assert_eq!(seen[..expected.len()], expected, "{seen:?}");
+    let error = &body["events"][7];
+    assert_eq!((&error["code"], &error["message"]), (&json!("BAD_REQUEST"), &json!("svc/type not allowed")));
+    assert!(body["events"][0]["at_unix_ms"].as_u64().unwrap() > 0);
+
+    // Kept after the session closes, ending with the close reason.
+    ws.close(None).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while state.realtime().sessions.len_sessions() > 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+    let (_, body) = events(&state, &id).await;
+    assert_eq!(body["live"], false);
+    assert_eq!(names(&body).last().unwrap(), "disconnected");
+    assert_eq!(body["events"].as_array().unwrap().last().unwrap()["reason"], "client_closed");
+}
+
+#[tokio::test]
+async fn unknown_or_unlogged_sessions_are_not_found() {
+    let state = AppState::new(config::load_from_str(CFG).unwrap()).unwrap();
+    assert_eq!(events(&state, "nope").await, (StatusCode::BAD_REQUEST, json!({ "error": "invalid_connection_id" })));
+    let id = wsprism_gateway::context::ConnectionId::new().to_string();
+    assert_eq!(events(&state, &id).await, (StatusCode::NOT_FOUND, json!({ "error": "session_not_found" })));
+
+    let err = config::load_from_str("version: 1\ngateway:\n  max_session_events: 100001\ntenants:\n  - id: acme\n").unwrap_err();
+    assert!(err.to_string().contains("gateway.max_session_events must be <= 100000"), "{err}");
+}
@@ -0,0 +1,110 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::SinkExt;
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+use wsprism_core::error::Result;
+use wsprism_core::protocol::text::Envelope;
+use wsprism_gateway::app_state::{AppState, StartupOptions};
+use wsprism_gateway::dispatch::TextService;
+use wsprism_gateway::realtime::{Outgoing, RealtimeCtx};
+use wsprism_gateway::config;
+
+use common::{connect, next_text, serve};
+
+/// Replies after `delay_ms` from the request data.
+struct SlowService;
+
+#[async_trait]
+impl TextService for SlowService {
+    fn svc(&self) -> &'static str {
+        "slow"
+    }
+    async fn handle(&self, _ctx: RealtimeCtx, env: Envelope) -> Result<Option<Outgoing>> {
+        let delay: u64 = env.data.as_ref().map_or(0, |d| d.get().parse().unwrap());
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        Ok(Some(Outgoing::reply(json!({ "svc": "slow", "type": "done" }))))
+    }
+}
+
+type Fields = HashMap<String, String>;
+
+/// Fields of every WARN event.
+#[derive(Clone, Default)]
+struct Warnings(Arc<Mutex<Vec<Fields>>>);
+
+struct Collect<'a>(&'a mut Fields);
+
+impl Visit for Collect<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Warnings {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if *event.metadata().level() == Level::WARN {
+            let mut fields = Fields::new();
+            event.record(&mut Collect(&mut fields));
+            self.0.lock().unwrap().push(fields);
+        }
+    }
+}
+
+const CFG: &str = r#"
+version: 1
+gateway:
+  slow_handler_threshold_ms: 50
+tenants:
+  - id: "acme"
+    policy:
+      ext_allowlist: ["slow:*"]
+"#;
+
+#[tokio::test]
+async fn slow_dispatch_is_timed_per_service_and_logged() {
+    // Single-threaded runtime: the server task logs to this thread's subscriber.
+    let warnings = Warnings::default();
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(warnings.clone()));
+
+    // Lenient: `slow` is registered after startup.
+    let opts = StartupOptions { lenient: true, ..Default::default() };
+    let state = AppState::new_with_options(config::load_from_str(CFG).unwrap(), opts).unwrap();
+    state.dispatcher().register_text(Arc::new(SlowService));
+    let addr = serve(&state).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert!(next_text(&mut ws).await.contains("authed"));
+
+    for delay in [0, 80] {
+        let req = format!(r#"{{"svc":"slow","type":"work","room":"r1","data":{delay}}}"#);
+        ws.send(Message::Text(req)).await.unwrap();
+        assert!(next_text(&mut ws).await.contains("done"));
+    }
+
+    let labels = [("tenant", "acme"), ("lane", "ext"), ("svc", "slow")];
+    let hist = &state.metrics().dispatch_duration;
+    assert_eq!(hist.bucket_count(&labels, 10_000_000), Some(2), "both dispatches observed");
+    assert_eq!(hist.bucket_count(&labels, 50_000), Some(1), "one under 50 ms");
+
+    let warnings = warnings.0.lock().unwrap();
+    let slow: Vec<&Fields> = warnings.iter().filter(|w| w["message"] == "slow handler").collect();
+    assert_eq!(slow.len(), 1, "{warnings:?}");
+    assert_eq!(slow[0]["svc"], "slow");
+    assert_eq!(slow[0]["msg_type"], "work");
+    assert_eq!(slow[0]["room"], r#"Some("r1")"#);
+    assert!(slow[0]["elapsed_ms"].parse::<u64>().unwrap() >= 80);
+}
@@ -0,0 +1,77 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use std::time::{Duration, Instant};
+
+use wsprism_gateway::config::{self, TenantPolicy};
+use wsprism_gateway::policy::strikes::StrikeCounter;
+use wsprism_gateway::policy::{DecisionReason, TenantPolicyRuntime};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[test]
+fn escalates_once_limit_is_exceeded_within_window() {
+    let t0 = Instant::now();
+    let mut s = StrikeCounter::new(3, WINDOW);
+    for i in 0..3 {
+        assert!(!s.strike(t0 + Duration::from_secs(i)), "strike {i} is within the limit");
+    }
+    assert!(s.strike(t0 + Duration::from_secs(3)));
+}
+
+#[test]
+fn strikes_age_out_while_the_client_behaves() {
+    let t0 = Instant::now();
+    let mut s = StrikeCounter::new(3, WINDOW);
+    for i in 0..3 {
+        assert!(!s.strike(t0 + Duration::from_secs(i)));
+    }
+
+    // Quiet for a full window: everything expires.
+    let later = t0 + Duration::from_secs(2) + WINDOW;
+    assert_eq!(s.live(later), 0);
+    for i in 0..3 {
+        assert!(!s.strike(later + Duration::from_secs(i)));
+    }
+    assert!(s.strike(later + Duration::from_secs(3)));
+}
+
+#[test]
+fn window_slides_instead_of_resetting() {
+    let t0 = Instant::now();
+    let mut s = StrikeCounter::new(2, WINDOW);
+    assert!(!s.strike(t0));
+    assert!(!s.strike(t0 + Duration::from_secs(50)));
+
+    // First strike aged out, second is still live: 2 live, not over.
+    assert!(!s.strike(t0 + Duration::from_secs(60)));
+    assert_eq!(s.live(t0 + Duration::from_secs(60)), 2);
+
+    // Third live strike within 60 s of the second one.
+    assert!(s.strike(t0 + Duration::from_secs(61)));
+}
+
+#[test]
+fn zero_limit_disables_escalation() {
+    let rt = TenantPolicyRuntime::new("acme".into(), 4096, &TenantPolicy::default()).unwrap();
+    assert!(rt.new_strike_counter().is_none());
+
+    let policy = TenantPolicy { strike_limit: 20, ..TenantPolicy::default() };
+    let rt = TenantPolicyRuntime::new("acme".into(), 4096, &policy).unwrap();
+    assert!(rt.new_strike_counter().is_some());
+    assert_eq!(DecisionReason::StrikeLimit.as_str(), "strike_limit");
+}
+
+#[test]
+fn strike_window_must_be_positive() {
+    let yaml = r#"
+version: 1
+tenants:
+  - id: "acme"
+    policy:
+      strike_limit: 20
+      strike_window_ms: 0
+"#;
+    assert!(config::load_from_str(yaml).is_err());
+}
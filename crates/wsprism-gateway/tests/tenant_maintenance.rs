@@ -0,0 +1,104 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use wsprism_gateway::config::{self, TenantPolicy};
+use wsprism_gateway::policy::{DecisionReason, PolicyDecision, TenantPolicyRuntime};
+
+use common::{connect, next_text, spawn_gateway};
+
+fn cfg_yaml(extra: &str) -> String {
+    format!(
+        r#"
+version: 1
+tenants:
+  - id: "acme"
+    {extra}
+    policy:
+      strike_limit: 2
+      ext_allowlist: ["room:*", "chat:*"]
+      hot_allowlist: ["1:*"]
+"#
+    )
+}
+
+#[test]
+fn read_only_rejects_all_but_room_and_sys() {
+    let rt = TenantPolicyRuntime::new("acme".into(), 1024, &TenantPolicy {
+        ext_allowlist: vec!["room:*".into(), "chat:*".into()],
+        hot_allowlist: vec!["1:*".into()],
+        ..TenantPolicy::default()
+    })
+    .unwrap()
+    .with_maintenance(false, true);
+
+    assert!(matches!(rt.check_text(10, "room", "join"), PolicyDecision::Pass));
+    match rt.check_text(10, "chat", "send") {
+        PolicyDecision::Reject { code, reason, .. } => {
+            assert_eq!(code.as_str(), "NOT_ALLOWED");
+            assert_eq!(reason, DecisionReason::ReadOnly);
+        }
+        other => panic!("expected reject, got {other:?}"),
+    }
+    assert!(matches!(rt.check_hot(10, 1, 1), PolicyDecision::Reject { reason: DecisionReason::ReadOnly, .. }));
+}
+
+#[tokio::test]
+async fn suspending_on_reload_sends_goaway_and_blocks_upgrades() {
+    let (state, addr) = spawn_gateway(&cfg_yaml("")).await;
+    let url = format!("ws://{addr}/v1/ws?tenant=acme&ticket=dev");
+
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert!(next_text(&mut ws).await.contains("authed"));
+
+    let report = state
+        .reload_policies(&config::load_from_str(&cfg_yaml("suspended: true")).unwrap())
+        .unwrap();
+    assert_eq!(report.tenants[0].changed, vec!["suspended"]);
+
+    let got = next_text(&mut ws).await;
+    assert!(got.contains(r#""type":"goaway""#) && got.contains("tenant_suspended"), "{got}");
+    let closed = tokio::time::timeout(Duration::from_secs(5), ws.next()).await.unwrap();
+    assert!(matches!(closed, Some(Ok(Message::Close(_))) | None), "{closed:?}");
+
+    match tokio_tungstenite::connect_async(&url).await {
+        Err(tungstenite::Error::Http(resp)) => {
+            assert_eq!(resp.status(), 503);
+            let body = String::from_utf8(resp.body().clone().unwrap_or_default()).unwrap();
+            assert!(body.contains("TENANT_SUSPENDED"), "{body}");
+        }
+        other => panic!("expected 503, got {other:?}"),
+    }
+
+    // Resuming reopens the tenant.
+    state.reload_policies(&config::load_from_str(&cfg_yaml("")).unwrap()).unwrap();
+    assert!(tokio_tungstenite::connect_async(&url).await.is_ok());
+}
+
+#[tokio::test]
+async fn read_only_keeps_spectators_connected() {
+    let (_, addr) = spawn_gateway(&cfg_yaml("read_only: true")).await;
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    assert!(next_text(&mut ws).await.contains("authed"));
+
+    let join = r#"{"v":1,"svc":"room","type":"join","room":"lobby"}"#;
+    ws.send(Message::Text(join.into())).await.unwrap();
+    assert!(next_text(&mut ws).await.contains("joined"));
+
+    // More rejects than strike_limit: still connected.
+    let send = r#"{"v":1,"svc":"chat","type":"send","room":"lobby","data":{"msg":"hi"}}"#;
+    for _ in 0..5 {
+        ws.send(Message::Text(send.into())).await.unwrap();
+        let got = next_text(&mut ws).await;
+        assert!(got.contains("NOT_ALLOWED"), "{got}");
+    }
+    ws.send(Message::Text(join.into())).await.unwrap();
+    assert!(next_text(&mut ws).await.contains("joined"));
+}
@@ -0,0 +1,145 @@
+//! Tenant-level limiter admission. Runs against whichever limiter backend is
+//! compiled in (built-in token bucket or `governor-ratelimit`).
+
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use wsprism_gateway::config::schema::RateLimitScope;
+use wsprism_gateway::config::TenantPolicy;
+use wsprism_gateway::policy::{DecisionReason, PolicyDecision, TenantPolicyRuntime};
+
+#[test]
+fn tenant_limiter_admits_exactly_burst() {
+    let burst = 20;
+    let policy = TenantPolicy {
+        rate_limit_rps: 1,
+        rate_limit_burst: burst,
+        rate_limit_scope: RateLimitScope::Tenant,
+        ext_allowlist: vec!["chat:*".into()],
+        ..TenantPolicy::default()
+    };
+    let rt = TenantPolicyRuntime::new("acme".into(), 4096, &policy).unwrap();
+
+    let admitted = (0..burst * 2)
+        .filter(|_| matches!(rt.check_text(10, "chat", "send"), PolicyDecision::Pass))
+        .count();
+    assert_eq!(admitted, burst as usize);
+}
+
+#[test]
+fn token_bucket_never_over_admits_under_contention() {
+    use std::sync::Arc;
+    use std::time::Instant;
+    use wsprism_gateway::policy::rate::TokenBucket;
+
+    let (rate, capacity) = (100u32, 50u32);
+    let bucket = Arc::new(TokenBucket::new(rate, capacity));
+    let start = Instant::now();
+
+    let handles: Vec<_> = (0..16)
+        .map(|_| {
+            let b = bucket.clone();
+            std::thread::spawn(move || (0..2_000).filter(|_| b.allow()).count())
+        })
+        .collect();
+    let admitted: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    let bound = capacity as u64 + rate as u64 * elapsed_ms / 1000 + 1;
+    assert!(admitted >= capacity as usize, "admitted {admitted} < capacity");
+    assert!(admitted as u64 <= bound, "admitted {admitted} > bound {bound}");
+}
+
+#[test]
+fn token_bucket_denies_costs_above_balance() {
+    use wsprism_gateway::policy::rate::TokenBucket;
+
+    let b = TokenBucket::new(1, 10);
+    assert!(!b.try_take(11));
+    assert!(b.try_take(10));
+    assert!(!b.try_take(1));
+    assert!(b.retry_after_secs(1) >= 1);
+}
+
+#[test]
+fn session_byte_limit_applies_far_below_message_limit() {
+    let policy = TenantPolicy {
+        rate_limit_rps: 1_000,
+        rate_limit_burst: 1_000,
+        rate_limit_scope: RateLimitScope::Connection,
+        per_session_bytes_per_sec: 8_192,
+        ext_allowlist: vec!["chat:*".into()],
+        ..TenantPolicy::default()
+    };
+    let rt = TenantPolicyRuntime::new("acme".into(), 4096, &policy).unwrap();
+    let mut msgs = rt.new_connection_limiter().unwrap();
+    let bytes = rt.new_session_byte_bucket().unwrap();
+
+    // Two 4 KB frames fill the 8 KB burst; the third is byte-limited even
+    // though only 3 of 1000 messages were used.
+    for _ in 0..2 {
+        assert!(msgs.allow());
+        assert!(rt.admit_bytes(4096, Some(&bytes)).is_ok());
+    }
+    assert!(msgs.allow());
+    let retry_after = rt.admit_bytes(4096, Some(&bytes)).expect_err("byte limited");
+    assert!(retry_after >= 1);
+
+    // A fresh session is unaffected (no tenant byte cap configured).
+    let other = rt.new_session_byte_bucket().unwrap();
+    assert!(rt.admit_bytes(4096, Some(&other)).is_ok());
+}
+
+#[test]
+fn tenant_byte_limit_is_shared_across_sessions() {
+    let policy = TenantPolicy {
+        rate_limit_bytes_per_sec: 4_096,
+        ext_allowlist: vec!["chat:*".into()],
+        ..TenantPolicy::default()
+    };
+    let rt = TenantPolicyRuntime::new("acme".into(), 1024, &policy).unwrap();
+    assert!(rt.new_session_byte_bucket().is_none());
+
+    let admitted = (0..8).filter(|_| rt.admit_bytes(1024, None).is_ok()).count();
+    assert_eq!(admitted, 4);
+}
+
+#[test]
+fn empty_bucket_reports_wait_until_next_token() {
+    use wsprism_gateway::policy::rate::TokenBucket;
+
+    let b = TokenBucket::new(4, 1);
+    assert_eq!(b.try_acquire(1), Ok(()));
+    let wait_ms = b.try_acquire(1).expect_err("empty");
+    assert!((200..=250).contains(&wait_ms), "~1/rps, got {wait_ms}");
+}
+
+#[test]
+fn rate_limited_reject_carries_retry_hint() {
+    let policy = TenantPolicy {
+        rate_limit_rps: 10,
+        rate_limit_burst: 1,
+        rate_limit_scope: RateLimitScope::Both,
+        ext_allowlist: vec!["chat:*".into()],
+        ..TenantPolicy::default()
+    };
+    let rt = TenantPolicyRuntime::new("acme".into(), 4096, &policy).unwrap();
+
+    assert!(matches!(rt.check_text(10, "chat", "send"), PolicyDecision::Pass));
+    let d = rt.check_text(10, "chat", "send");
+    assert_eq!(d.reason(), DecisionReason::Rate);
+    let wait_ms = d.retry_after_ms().expect("retry hint");
+    assert!((80..=100).contains(&wait_ms), "~1/rps, got {wait_ms}");
+
+    let mut conn = rt.new_connection_limiter().unwrap();
+    assert_eq!(conn.acquire_svc("chat"), Ok(()));
+    let wait_ms = conn.acquire_svc("chat").expect_err("empty");
+    assert!((80..=100).contains(&wait_ms), "~1/rps, got {wait_ms}");
+
+    // Hot lane stays silent.
+    let hot = TenantPolicy { hot_allowlist: vec!["1:*".into()], ..policy };
+    let rt = TenantPolicyRuntime::new("acme".into(), 4096, &hot).unwrap();
+    assert!(matches!(rt.check_hot(10, 1, 1), PolicyDecision::Pass));
+    assert!(matches!(rt.check_hot(10, 1, 1), PolicyDecision::Drop { reason: DecisionReason::Rate }));
+}
@@ -0,0 +1,318 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::extract::ws::Message;
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use tokio::sync::mpsc;
+use wsprism_core::error::{Result, WsPrismError};
+use wsprism_core::protocol::text::Envelope;
+
+use wsprism_gateway::config::{self, TenantConfig, TenantLimits};
+use wsprism_gateway::context::{ConnectionId, SessionClaims};
+use wsprism_gateway::dispatch::TextService;
+use wsprism_gateway::realtime::core::Connection;
+use wsprism_gateway::realtime::{RealtimeCore, RealtimeCtx};
+use wsprism_gateway::upstream::{UpstreamClient, UpstreamReply, UpstreamRequest, UpstreamRoute, UpstreamTextService};
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: acme
+    policy:
+      ext_allowlist: ["orders:*"]
+    upstreams:
+      orders:
+        endpoint: "http://127.0.0.1:50051"
+        timeout_ms: 100
+        failure_threshold: 2
+        cooldown_ms: 1000
+  - id: beta
+"#;
+
+/// Replies with `replies`, or fails while `fail` is set.
+#[derive(Default)]
+struct FakeClient {
+    requests: Mutex<Vec<(String, UpstreamRequest)>>,
+    replies: Vec<UpstreamReply>,
+    fail: Mutex<bool>,
+    stall: bool,
+}
+
+impl FakeClient {
+    fn calls(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl UpstreamClient for FakeClient {
+    async fn dispatch(
+        &self,
+        endpoint: &str,
+        req: UpstreamRequest,
+        _timeout: Duration,
+    ) -> Result<BoxStream<'static, Result<UpstreamReply>>> {
+        self.requests.lock().unwrap().push((endpoint.to_string(), req));
+        if *self.fail.lock().unwrap() {
+            return Err(WsPrismError::Internal("unavailable".into()));
+        }
+        if self.stall {
+            return Ok(stream::pending().boxed());
+        }
+        Ok(stream::iter(self.replies.clone().into_iter().map(Ok)).boxed())
+    }
+}
+
+fn tenants() -> Vec<TenantConfig> {
+    config::load_from_str(CFG).unwrap().tenants
+}
+
+fn connect(core: &RealtimeCore, user: &str) -> (ConnectionId, mpsc::Receiver<Message>) {
+    let (tx, rx) = mpsc::channel(16);
+    let id = ConnectionId::new();
+    let user_key = format!("acme::{user}");
+    core.sessions
+        .try_insert("acme".into(), user_key.clone(), id, Connection::new(tx, SessionClaims::default()), 0)
+        .unwrap();
+    core.presence.try_join("acme", "acme::lobby", &user_key, id, &TenantLimits::default()).unwrap();
+    (id, rx)
+}
+
+fn env() -> Envelope {
+    serde_json::from_str(r#"{"v":1,"svc":"orders","type":"place","data":{"sku":7}}"#).unwrap()
+}
+
+fn texts(rx: &mut mpsc::Receiver<Message>) -> Vec<String> {
+    let mut out = Vec::new();
+    while let Ok(m) = rx.try_recv() {
+        if let Message::Text(t) = m {
+            out.push(t);
+        }
+    }
+    out
+}
+
+fn reply(route: UpstreamRoute, payload: &'static str) -> UpstreamReply {
+    UpstreamReply { route, payload: Bytes::from_static(payload.as_bytes()) }
+}
+
+#[tokio::test]
+async fn replies_are_routed_to_session_user_and_room() {
+    let client = Arc::new(FakeClient {
+        replies: vec![
+            reply(UpstreamRoute::Reply, r#"{"r":1}"#),
+            reply(UpstreamRoute::User("bob".into()), r#"{"u":1}"#),
+            reply(UpstreamRoute::Room("lobby".into()), r#"{"m":1}"#),
+        ],
+        ..FakeClient::default()
+    });
+    let services = UpstreamTextService::all(&tenants(), client.clone());
+    assert_eq!(services.len(), 1);
+    let svc = &services[0];
+    assert_eq!(svc.svc(), "orders");
+
+    let core = Arc::new(RealtimeCore::new());
+    let (alice_id, mut alice) = connect(&core, "alice");
+    let (_, mut bob) = connect(&core, "bob");
+    let ctx = RealtimeCtx::new("acme", "alice", alice_id, "t", None, core.clone());
+    assert!(svc.handle(ctx, env()).await.unwrap().is_none());
+
+    assert_eq!(texts(&mut alice), vec![r#"{"r":1}"#, r#"{"m":1}"#]);
+    assert_eq!(texts(&mut bob), vec![r#"{"u":1}"#, r#"{"m":1}"#]);
+
+    let requests = client.requests.lock().unwrap();
+    let (endpoint, req) = &requests[0];
+    assert_eq!(endpoint, "http://127.0.0.1:50051");
+    assert_eq!((req.tenant.as_str(), req.user.as_str()), ("acme", "alice"));
+    assert_eq!(req.connection_id, alice_id.to_string());
+    let sent: serde_json::Value = serde_json::from_str(&req.envelope).unwrap();
+    assert_eq!(sent["type"], "place");
+    assert_eq!(sent["data"]["sku"], 7);
+}
+
+#[tokio::test(start_paused = true)]
+async fn circuit_opens_after_consecutive_failures() {
+    let client = Arc::new(FakeClient { fail: Mutex::new(true), ..FakeClient::default() });
+    let svc = UpstreamTextService::new("orders", &tenants(), client.clone());
+    let core = Arc::new(RealtimeCore::new());
+    let ctx = || RealtimeCtx::new("acme", "alice", ConnectionId::new(), "t", None, core.clone());
+
+    for _ in 0..2 {
+        let e = svc.handle(ctx(), env()).await.unwrap_err();
+        assert_eq!(e.client_code().as_str(), "INTERNAL");
+    }
+    // Open: fails without calling the backend.
+    let e = svc.handle(ctx(), env()).await.unwrap_err();
+    assert!(e.to_string().contains("unavailable"), "{e}");
+    assert_eq!(client.calls(), 2);
+
+    tokio::time::advance(Duration::from_millis(1001)).await;
+    *client.fail.lock().unwrap() = false;
+    svc.handle(ctx(), env()).await.unwrap();
+    assert_eq!(client.calls(), 3);
+
+    // Closed again: a single failure no longer opens it.
+    *client.fail.lock().unwrap() = true;
+    assert!(svc.handle(ctx(), env()).await.is_err());
+    assert!(svc.handle(ctx(), env()).await.is_err());
+    assert_eq!(client.calls(), 5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn calls_past_the_deadline_fail() {
+    let client = Arc::new(FakeClient { stall: true, ..FakeClient::default() });
+    let svc = UpstreamTextService::new("orders", &tenants(), client);
+    let ctx = RealtimeCtx::new("acme", "alice", ConnectionId::new(), "t", None, Arc::new(RealtimeCore::new()));
+    let e = svc.handle(ctx, env()).await.unwrap_err();
+    assert!(e.to_string().contains("deadline exceeded"), "{e}");
+}
+
+#[tokio::test]
+async fn tenants_without_the_upstream_are_rejected() {
+    let client = Arc::new(FakeClient::default());
+    let svc = UpstreamTextService::new("orders", &tenants(), client.clone());
+    let ctx = RealtimeCtx::new("beta", "u1", ConnectionId::new(), "t", None, Arc::new(RealtimeCore::new()));
+    let e = svc.handle(ctx, env()).await.unwrap_err();
+    assert_eq!(e.client_code().as_str(), "NOT_ALLOWED");
+    assert_eq!(client.calls(), 0);
+}
+
+#[test]
+fn upstream_config_is_validated() {
+    let err = |upstream: &str| {
+        let yaml = format!("version: 1\ntenants:\n  - id: acme\n    upstreams:\n      {upstream}\n");
+        config::load_from_str(&yaml).unwrap_err().to_string()
+    };
+    let e = err("orders: { endpoint: \"https://orders:443\" }");
+    assert!(e.contains("upstreams.orders.endpoint"), "{e}");
+    let e = err("orders: { endpoint: \"http://orders:50051\", timeout_ms: 0 }");
+    assert!(e.contains("upstreams.orders.timeout_ms"), "{e}");
+    let e = err("orders: { endpoint: \"http://orders:50051\", failure_threshold: 0 }");
+    assert!(e.contains("upstreams.orders.failure_threshold"), "{e}");
+    let e = err("room: { endpoint: \"http://orders:50051\" }");
+    assert!(e.contains("not a valid service name"), "{e}");
+}
+
+#[cfg(not(feature = "upstream-grpc"))]
+#[test]
+fn startup_fails_without_the_feature() {
+    let err = wsprism_gateway::app_state::AppState::new(config::load_from_str(CFG).unwrap()).err().unwrap();
+    assert!(err.to_string().contains("built without the upstream-grpc feature"), "{err}");
+}
+
+#[cfg(feature = "upstream-grpc")]
+mod grpc {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::BoxFuture;
+    use futures_util::stream::{self, BoxStream, StreamExt};
+    use futures_util::SinkExt;
+    use serde_json::{json, Value};
+    use tokio_tungstenite::tungstenite::Message;
+    use tonic::codegen::http;
+
+    use wsprism_gateway::upstream::pb;
+
+    use super::common::{connect, next_json, spawn_gateway};
+
+    /// `wsprism.upstream.v1.Upstream` echoing the request back as a reply.
+    #[derive(Clone)]
+    struct Backend;
+
+    impl tonic::server::NamedService for Backend {
+        const NAME: &'static str = "wsprism.upstream.v1.Upstream";
+    }
+
+    struct Dispatch;
+
+    impl tonic::server::ServerStreamingService<pb::DispatchRequest> for Dispatch {
+        type Response = pb::Outgoing;
+        type ResponseStream = BoxStream<'static, Result<pb::Outgoing, tonic::Status>>;
+        type Future = BoxFuture<'static, Result<tonic::Response<Self::ResponseStream>, tonic::Status>>;
+
+        fn call(&mut self, req: tonic::Request<pb::DispatchRequest>) -> Self::Future {
+            let req = req.into_inner();
+            let payload = json!({ "svc": "orders", "type": "placed", "data": {
+                "tenant": req.tenant, "user": req.user, "envelope": req.envelope,
+            }});
+            let out = pb::Outgoing { route: pb::Route::Reply as i32, target: String::new(), payload: payload.to_string() };
+            Box::pin(async move { Ok(tonic::Response::new(stream::iter([Ok(out)]).boxed())) })
+        }
+    }
+
+    impl tower::Service<http::Request<tonic::body::Body>> for Backend {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<'static, Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<tonic::body::Body>) -> Self::Future {
+            Box::pin(async move {
+                let codec = tonic_prost::ProstCodec::<pb::Outgoing, pb::DispatchRequest>::default();
+                Ok(tonic::server::Grpc::new(codec).server_streaming(Dispatch, req).await)
+            })
+        }
+    }
+
+    async fn gateway(endpoint: &str) -> SocketAddr {
+        let yaml = format!(
+            "version: 1\ntenants:\n  - id: acme\n    policy:\n      ext_allowlist: [\"orders:*\"]\n    upstreams:\n      orders: {{ endpoint: \"{endpoint}\", timeout_ms: 2000 }}\n"
+        );
+        spawn_gateway(&yaml).await.1
+    }
+
+    async fn ask(addr: SocketAddr) -> Value {
+        let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+        ws.send(Message::Text(json!({ "svc": "orders", "type": "place", "data": { "sku": 7 } }).to_string()))
+            .await
+            .unwrap();
+        loop {
+            let v = next_json(&mut ws).await;
+            if v["svc"] != "sys" || v["type"] == "error" {
+                return v;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_round_trip_through_a_grpc_backend() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(Backend)
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener))
+                .await
+                .unwrap();
+        });
+
+        let v = ask(gateway(&format!("http://{backend}")).await).await;
+        assert_eq!(v["type"], "placed", "{v}");
+        assert_eq!(v["data"]["tenant"], "acme");
+        let env: Value = serde_json::from_str(v["data"]["envelope"].as_str().unwrap()).unwrap();
+        assert_eq!(env["data"]["sku"], 7);
+    }
+
+    #[tokio::test]
+    async fn unreachable_backend_is_a_sys_error() {
+        let unused = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", unused.local_addr().unwrap());
+        drop(unused);
+        let v = ask(gateway(&endpoint).await).await;
+        assert_eq!(v["type"], "error", "{v}");
+        assert_eq!(v["data"]["code"], "INTERNAL", "{v}");
+    }
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "wasm-plugins")]
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+use bytes::Bytes;
+use wsprism_core::protocol::hot::HotFrame;
+use wsprism_core::protocol::text::Envelope;
+
+use wsprism_gateway::config::schema::{PluginConfig, PluginErrorMode};
+use wsprism_gateway::plugin::{PluginCtx, PluginStage, PluginVerdict, WasmPlugin};
+
+/// Rejects ext frames longer than 80 bytes; drops hot frames of service 9.
+const FILTER: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) i32.const 16)
+  (func (export "check_ext") (param $p i32) (param $l i32) (result i32)
+    local.get $l
+    i32.const 80
+    i32.gt_u
+    (if (result i32) (then i32.const 2) (else i32.const 0)))
+  (func (export "check_hot") (param $p i32) (param $l i32) (result i32)
+    local.get $p
+    i32.load8_u
+    i32.const 9
+    i32.eq
+    (if (result i32) (then i32.const 1) (else i32.const 0))))
+"#;
+
+const SPIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) i32.const 16)
+  (func (export "check_ext") (param i32 i32) (result i32)
+    (loop $l (br $l))
+    i32.const 0))
+"#;
+
+const CTX: PluginCtx<'static> = PluginCtx { tenant: "acme", user: "alice", guest: false };
+
+fn cfg(fuel: u64, timeout_ms: u64) -> PluginConfig {
+    PluginConfig { path: "test.wasm".into(), fuel, timeout_ms, on_error: PluginErrorMode::Drop }
+}
+
+fn load(wat: &str, cfg: &PluginConfig) -> wsprism_core::error::Result<WasmPlugin> {
+    WasmPlugin::from_bytes(&wat::parse_str(wat).unwrap(), cfg)
+}
+
+fn env(data: &str) -> Envelope {
+    serde_json::from_str(&format!(r#"{{"v":1,"svc":"chat","type":"send","data":"{data}"}}"#)).unwrap()
+}
+
+fn hot(svc_id: u8) -> HotFrame {
+    HotFrame { v: 1, svc_id, opcode: 1, flags: 0, seq: None, payload: Bytes::from_static(b"xy") }
+}
+
+#[test]
+fn module_verdicts_follow_the_abi() {
+    let p = load(FILTER, &cfg(100_000, 50)).unwrap();
+    assert_eq!(p.on_ext(&CTX, &env("hi")).unwrap(), PluginVerdict::Pass);
+    assert_eq!(p.on_ext(&CTX, &env(&"x".repeat(100))).unwrap(), PluginVerdict::Reject);
+    assert_eq!(p.on_hot(&CTX, &hot(1)).unwrap(), PluginVerdict::Pass);
+    assert_eq!(p.on_hot(&CTX, &hot(9)).unwrap(), PluginVerdict::Drop);
+}
+
+#[test]
+fn runaway_plugins_are_stopped() {
+    // Out of fuel.
+    let p = load(SPIN, &cfg(10_000, 1000)).unwrap();
+    assert!(p.on_ext(&CTX, &env("hi")).is_err());
+
+    // Out of time.
+    let p = load(SPIN, &cfg(u64::MAX, 5)).unwrap();
+    assert!(p.on_ext(&CTX, &env("hi")).is_err());
+
+    // No check_hot export: the hot lane passes.
+    assert_eq!(p.on_hot(&CTX, &hot(9)).unwrap(), PluginVerdict::Pass);
+}
+
+#[test]
+fn modules_must_match_the_abi() {
+    let c = cfg(1000, 10);
+    assert!(load("(module)", &c).is_err());
+    let imports = r#"
+(module
+  (import "env" "log" (func))
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) i32.const 0)
+  (func (export "check_ext") (param i32 i32) (result i32) i32.const 0))
+"#;
+    assert!(load(imports, &c).is_err());
+}
@@ -0,0 +1,267 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde_json::{json, Value};
+use wsprism_core::error::{Result, WsPrismError};
+
+use wsprism_gateway::config::{self, TenantConfig, TenantLimits, WebhookConfig};
+use wsprism_gateway::context::ConnectionId;
+use wsprism_gateway::obs::metrics::GatewayMetrics;
+use wsprism_gateway::realtime::Presence;
+use wsprism_gateway::webhooks::{WebhookEvent, WebhookSender, Webhooks};
+
+/// Records every attempted batch; the first `fail` attempts error.
+#[derive(Default)]
+struct FakeSender {
+    batches: Mutex<Vec<Vec<Value>>>,
+    attempts: Mutex<u32>,
+    fail: u32,
+}
+
+impl FakeSender {
+    fn events(&self) -> Vec<Value> {
+        self.batches.lock().unwrap().concat()
+    }
+}
+
+#[async_trait]
+impl WebhookSender for FakeSender {
+    async fn send(&self, _cfg: &WebhookConfig, body: Bytes) -> Result<()> {
+        let mut attempts = self.attempts.lock().unwrap();
+        *attempts += 1;
+        if *attempts <= self.fail {
+            return Err(WsPrismError::Internal("backend down".into()));
+        }
+        self.batches.lock().unwrap().push(serde_json::from_slice(&body).unwrap());
+        Ok(())
+    }
+}
+
+const SECRET: &str = "0123456789abcdef";
+
+fn tenants(extra: &str) -> Vec<TenantConfig> {
+    let yaml = format!(
+        "version: 1\ntenants:\n  - id: acme\n    webhooks:\n      url: http://hooks.test/\n      secret: {SECRET}\n      batch_interval_ms: 20\n      retry_backoff_ms: 10\n{extra}  - id: quiet\n"
+    );
+    config::load_from_str(&yaml).unwrap().tenants
+}
+
+fn dispatcher(extra: &str, sender: Arc<FakeSender>) -> (Arc<Webhooks>, Arc<GatewayMetrics>) {
+    let metrics = Arc::new(GatewayMetrics::default());
+    (Arc::new(Webhooks::new(&tenants(extra), sender, metrics.clone())), metrics)
+}
+
+async fn wait_for(what: &str, mut f: impl FnMut() -> bool) {
+    for _ in 0..200 {
+        if f() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("timed out waiting for {what}");
+}
+
+#[test]
+fn events_serialize_without_unset_fields() {
+    let id = ConnectionId::new();
+    let mut v = serde_json::to_value(WebhookEvent::connect("acme", "u1", id)).unwrap();
+    assert!(v.as_object_mut().unwrap().remove("ts").unwrap().as_u64().unwrap() > 0);
+    assert_eq!(v, json!({ "event": "connect", "tenant": "acme", "user": "u1", "connection_id": id.to_string() }));
+
+    let v = serde_json::to_value(WebhookEvent::disconnect("acme", "u1", id, "idle timeout", Duration::from_millis(1500))).unwrap();
+    assert_eq!((&v["event"], &v["reason"], &v["duration_ms"]), (&json!("disconnect"), &json!("idle timeout"), &json!(1500)));
+    assert!(v.get("room").is_none());
+
+    let v = serde_json::to_value(WebhookEvent::room_leave("acme", "u1", id, "lobby")).unwrap();
+    assert_eq!((&v["event"], &v["room"]), (&json!("room_leave"), &json!("lobby")));
+}
+
+#[tokio::test]
+async fn events_are_batched_up_to_batch_max() {
+    let sender = Arc::new(FakeSender::default());
+    let (hooks, _) = dispatcher("      batch_max: 2\n", sender.clone());
+    for n in 0..5 {
+        hooks.emit(WebhookEvent::connect("acme", &format!("u{n}"), ConnectionId::new()));
+    }
+    // Tenants without webhooks are ignored.
+    hooks.emit(WebhookEvent::connect("quiet", "u9", ConnectionId::new()));
+    assert_eq!(hooks.clone().spawn().len(), 1);
+    assert!(hooks.clone().spawn().is_empty(), "one worker per tenant");
+
+    wait_for("5 events", || sender.events().len() == 5).await;
+    let sizes = sender.batches.lock().unwrap().iter().map(Vec::len).collect::<Vec<_>>();
+    assert_eq!(sizes, [2, 2, 1]);
+    let users = sender.events().iter().map(|e| e["user"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+    assert_eq!(users, ["u0", "u1", "u2", "u3", "u4"]);
+}
+
+#[tokio::test]
+async fn unsubscribed_kinds_are_not_sent() {
+    let sender = Arc::new(FakeSender::default());
+    let (hooks, _) = dispatcher("      events: [disconnect]\n", sender.clone());
+    let id = ConnectionId::new();
+    hooks.emit(WebhookEvent::connect("acme", "u1", id));
+    hooks.emit(WebhookEvent::room_join("acme", "u1", id, "lobby"));
+    hooks.emit(WebhookEvent::disconnect("acme", "u1", id, "client_closed", Duration::ZERO));
+    hooks.clone().spawn();
+
+    wait_for("the disconnect", || !sender.events().is_empty()).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let events = sender.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "disconnect");
+}
+
+#[tokio::test]
+async fn failed_batches_are_retried_then_dropped() {
+    // Two failures then success: delivered on the third attempt.
+    let sender = Arc::new(FakeSender { fail: 2, ..FakeSender::default() });
+    let (hooks, metrics) = dispatcher("      max_attempts: 3\n", sender.clone());
+    hooks.emit(WebhookEvent::connect("acme", "u1", ConnectionId::new()));
+    hooks.clone().spawn();
+    wait_for("the retried batch", || sender.events().len() == 1).await;
+    assert_eq!(*sender.attempts.lock().unwrap(), 3);
+
+    // Always failing: dropped after `max_attempts`, counted per event.
+    let sender = Arc::new(FakeSender { fail: u32::MAX, ..FakeSender::default() });
+    let (hooks, metrics2) = dispatcher("      max_attempts: 3\n", sender.clone());
+    hooks.emit(WebhookEvent::connect("acme", "u1", ConnectionId::new()));
+    hooks.emit(WebhookEvent::connect("acme", "u2", ConnectionId::new()));
+    hooks.clone().spawn();
+    let dropped = r#"wsprism_webhook_dropped_total{reason="delivery",tenant="acme"} 2"#;
+    wait_for("the drop", || metrics2.render(&[]).contains(dropped)).await;
+    assert_eq!(*sender.attempts.lock().unwrap(), 3);
+    assert!(!metrics.render(&[]).contains("wsprism_webhook_dropped_total{"));
+}
+
+#[tokio::test]
+async fn full_backlog_drops_events() {
+    let sender = Arc::new(FakeSender::default());
+    let (hooks, metrics) = dispatcher("      backlog: 2\n", sender.clone());
+    for n in 0..3 {
+        hooks.emit(WebhookEvent::connect("acme", &format!("u{n}"), ConnectionId::new()));
+    }
+    let text = metrics.render(&[]);
+    assert!(text.contains(r#"wsprism_webhook_dropped_total{reason="backlog_full",tenant="acme"} 1"#), "{text}");
+
+    hooks.clone().spawn();
+    wait_for("the queued events", || sender.events().len() == 2).await;
+}
+
+#[tokio::test]
+async fn presence_reports_every_session_joining_and_leaving() {
+    let sender = Arc::new(FakeSender::default());
+    let (hooks, _) = dispatcher("", sender.clone());
+    let p = Presence::new();
+    p.set_observer(Some(hooks.clone()));
+    let (a1, a2) = (ConnectionId::new(), ConnectionId::new());
+    let limits = TenantLimits::default();
+
+    p.try_join("acme", "acme::lobby", "acme::alice", a1, &limits).unwrap();
+    p.try_join("acme", "acme::lobby", "acme::alice", a2, &limits).unwrap();
+    p.try_join("acme", "acme::lobby", "acme::alice", a2, &limits).unwrap(); // no-op rejoin
+    p.move_user("acme", "acme::alice", a1, "acme::lobby", "acme::match:1", &limits).unwrap();
+    p.leave("acme", "acme::nowhere", "acme::alice", a1); // not a member
+    p.cleanup_session("acme", "acme::alice", a2);
+    p.try_join("quiet", "quiet::lobby", "quiet::bob", ConnectionId::new(), &limits).unwrap();
+    hooks.clone().spawn();
+
+    wait_for("5 events", || sender.events().len() == 5).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let seen = sender
+        .events()
+        .iter()
+        .map(|e| format!("{} {} {}", e["event"].as_str().unwrap(), e["room"].as_str().unwrap(), e["connection_id"].as_str().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(seen, [
+        format!("room_join lobby {a1}"),
+        format!("room_join lobby {a2}"),
+        format!("room_join match:1 {a1}"),
+        format!("room_leave lobby {a1}"),
+        format!("room_leave lobby {a2}"),
+    ]);
+    assert!(sender.events().iter().all(|e| e["user"] == "alice"));
+}
+
+#[test]
+fn webhook_config_is_validated() {
+    let err = |hook: &str| {
+        let yaml = format!("version: 1\ntenants:\n  - id: acme\n    webhooks:\n      url: https://hooks.test/\n{hook}");
+        config::load_from_str(&yaml).unwrap_err().to_string()
+    };
+    assert!(err("      secret: short\n").contains("webhooks.secret"));
+    assert!(err(&format!("      secret: {SECRET}\n      events: []\n")).contains("webhooks.events"));
+    assert!(err(&format!("      secret: {SECRET}\n      batch_max: 0\n")).contains("webhooks.batch_max"));
+    assert!(err(&format!("      secret: {SECRET}\n      max_attempts: 0\n")).contains("webhooks.max_attempts"));
+    assert!(err(&format!("      secret: {SECRET}\n      backlog: 0\n")).contains("webhooks.backlog"));
+    assert!(err(&format!("      secret: {SECRET}\n      events: [kick]\n")).contains("unknown variant"));
+    let yaml = format!("version: 1\ntenants:\n  - id: acme\n    webhooks:\n      url: ftp://x/\n      secret: {SECRET}\n");
+    assert!(config::load_from_str(&yaml).unwrap_err().to_string().contains("webhooks.url"));
+
+    let cfg = tenants("").remove(0).webhooks.unwrap();
+    assert_eq!((cfg.events.len(), cfg.batch_max, cfg.max_attempts, cfg.backlog), (4, 100, 5, 10_000));
+}
+
+#[cfg(not(feature = "webhooks"))]
+#[test]
+fn webhooks_require_feature() {
+    let yaml = format!("version: 1\ntenants:\n  - id: acme\n    webhooks:\n      url: http://hooks.test/\n      secret: {SECRET}\n");
+    let e = wsprism_gateway::app_state::AppState::new(config::load_from_str(&yaml).unwrap()).err().unwrap().to_string();
+    assert!(e.contains("built without the webhooks feature"), "{e}");
+}
+
+/// A session's lifecycle reaches an HTTP backend as signed batches.
+#[cfg(feature = "webhooks")]
+#[tokio::test]
+async fn ws_sessions_are_posted_with_a_signature() {
+    use axum::http::HeaderMap;
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+    use wsprism_gateway::app_state::AppState;
+    use wsprism_gateway::webhooks::{sign, SIGNATURE_HEADER};
+
+    use common::{connect, next_text, serve};
+
+    let received = Arc::new(Mutex::new(Vec::<Value>::new()));
+    let sink = received.clone();
+    let backend = axum::Router::new().route(
+        "/hooks",
+        axum::routing::post(move |headers: HeaderMap, body: Bytes| async move {
+            let sig = headers.get(SIGNATURE_HEADER).unwrap().to_str().unwrap();
+            assert_eq!(sig, sign(SECRET, &body));
+            let batch: Vec<Value> = serde_json::from_slice(&body).unwrap();
+            sink.lock().unwrap().extend(batch);
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let hook_addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, backend).await.unwrap() });
+
+    let yaml = format!(
+        "version: 1\ntenants:\n  - id: acme\n    policy:\n      ext_allowlist: [\"room:*\"]\n    webhooks:\n      url: http://{hook_addr}/hooks\n      secret: {SECRET}\n      batch_interval_ms: 20\n"
+    );
+    let state = AppState::new(config::load_from_str(&yaml).unwrap()).unwrap();
+    assert_eq!(state.spawn_webhooks().len(), 1);
+    let addr = serve(&state).await;
+
+    let mut ws = connect(addr, "tenant=acme&ticket=dev").await;
+    ws.send(Message::Text(json!({ "svc": "room", "type": "join", "room": "lobby" }).to_string())).await.unwrap();
+    while !next_text(&mut ws).await.contains("joined") {}
+    ws.close(None).await.unwrap();
+
+    wait_for("4 events", || received.lock().unwrap().len() == 4).await;
+    let events = received.lock().unwrap().clone();
+    let kinds = events.iter().map(|e| e["event"].as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(kinds, ["connect", "room_join", "room_leave", "disconnect"]);
+    assert!(events.iter().all(|e| e["user"] == "user:dev" && e["connection_id"] == events[0]["connection_id"]));
+    assert_eq!((&events[1]["room"], &events[3]["reason"]), (&json!("lobby"), &json!("client_closed")));
+    assert!(events[3]["duration_ms"].is_u64());
+}
@@ -0,0 +1,104 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod common;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+
+use wsprism_gateway::app_state::AppState;
+
+use common::{connect, spawn_gateway, Ws, READ_TIMEOUT};
+
+const CFG: &str = r#"
+version: 1
+tenants:
+  - id: "acme"
+    limits: { max_frame_bytes: 256 }
+    policy:
+      strike_limit: 1
+      ext_allowlist: ["room:*"]
+"#;
+
+async fn start() -> (AppState, Ws) {
+    let (state, addr) = spawn_gateway(CFG).await;
+    (state, connect(addr, "tenant=acme&ticket=dev").await)
+}
+
+/// Text frames up to the Close frame, plus the Close frame itself.
+async fn read_until_close(ws: &mut Ws) -> (Vec<String>, CloseFrame<'static>) {
+    let mut texts = Vec::new();
+    tokio::time::timeout(READ_TIMEOUT, async {
+        loop {
+            match ws.next().await {
+                Some(Ok(Message::Text(t))) => texts.push(t),
+                Some(Ok(Message::Close(Some(f)))) => break (texts, f),
+                Some(Ok(_)) => continue,
+                other => panic!("expected a close frame, got {other:?}"),
+            }
+        }
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn oversized_frame_closes_with_bad_request_code() {
+    let (_state, mut ws) = start().await;
+    let frame = format!(r#"{{"v":1,"svc":"room","type":"join","data":"{}"}}"#, "x".repeat(300));
+    ws.send(Message::Text(frame)).await.unwrap();
+
+    let (texts, close) = read_until_close(&mut ws).await;
+    assert_eq!(close.code, CloseCode::from(4000));
+    assert_eq!(close.reason, "frame too large");
+    // The sys.error queued before the decision still reaches the client.
+    assert!(texts.iter().any(|t| t.contains(r#""type":"error""#)), "{texts:?}");
+}
+
+#[tokio::test]
+async fn oversized_hot_payload_fails_decoding() {
+    let (state, mut ws) = start().await;
+    let mut frame = vec![1, 1, 1, 0];
+    frame.extend(std::iter::repeat_n(0u8, 300));
+    ws.send(Message::Binary(frame)).await.unwrap();
+
+    let (texts, close) = read_until_close(&mut ws).await;
+    assert_eq!(close.code, CloseCode::from(1009));
+    assert!(texts.iter().any(|t| t.contains("PAYLOAD_TOO_LARGE")), "{texts:?}");
+    let m = state.metrics();
+    assert_eq!(m.decode_errors.get(&[("tenant", "acme"), ("reason", "hot")]), 1);
+}
+
+#[tokio::test]
+async fn malformed_envelope_closes_with_bad_request_code() {
+    let (_state, mut ws) = start().await;
+    ws.send(Message::Text("{not json".into())).await.unwrap();
+
+    let (_, close) = read_until_close(&mut ws).await;
+    assert_eq!(close.code, CloseCode::from(4000));
+}
+
+#[tokio::test]
+async fn strike_out_closes_with_not_allowed_code() {
+    let (_state, mut ws) = start().await;
+    let denied = r#"{"v":1,"svc":"chat","type":"send"}"#;
+    for _ in 0..2 {
+        ws.send(Message::Text(denied.into())).await.unwrap();
+    }
+
+    let (_, close) = read_until_close(&mut ws).await;
+    assert_eq!(close.code, CloseCode::from(4003));
+    assert_eq!(close.reason, "too many policy violations");
+}
+
+#[tokio::test]
+async fn drain_closes_with_going_away() {
+    let (state, mut ws) = start().await;
+    state.realtime().best_effort_shutdown_all("draining");
+
+    let (_, close) = read_until_close(&mut ws).await;
+    assert_eq!(close.code, CloseCode::Away);
+}